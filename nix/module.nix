@@ -12,13 +12,37 @@ let
   tomlFormat = pkgs.formats.toml { };
 
   configFile = tomlFormat.generate "grabby-config.toml" {
-    logging.level = cfg.logLevel;
+    logging = {
+      level = cfg.logLevel;
+      format = cfg.logFormat;
+    }
+    // lib.optionalAttrs (cfg.logDirectory != null) { directory = cfg.logDirectory; };
     servers = map (server: {
       server_id = server.serverId;
       auto_embed_channels = server.autoEmbedChannels;
+      auto_embed_thread_parents = server.autoEmbedThreadParents;
       embed_enabled = server.embedEnabled;
       disabled_domains = server.disabledDomains;
-    }) cfg.servers;
+      auto_delete_channels = server.autoDeleteChannels;
+      mirror_channels = server.mirrorChannels;
+      webhook_repost = server.webhookRepost;
+      failure_notice = server.failureNotice;
+      config_role_ids = server.configRoleIds;
+      embed = server.embed;
+      channels = server.channels;
+      branding = server.branding;
+      quota = server.quota;
+      blocklist = server.blocklist;
+    }
+    // lib.optionalAttrs (server.locale != null) { locale = server.locale; }
+    // lib.optionalAttrs (server.logChannel != null) { log_channel = server.logChannel; }
+    // lib.optionalAttrs (server.jobWebhook != null) { job_webhook = server.jobWebhook; }
+    // lib.optionalAttrs (server.maxWatches != null) { max_watches = server.maxWatches; }
+    // lib.optionalAttrs (server.dedupWindowSecs != null) { dedup_window_secs = server.dedupWindowSecs; }
+    // lib.optionalAttrs (server.durationCapSecs != null) { duration_cap_secs = server.durationCapSecs; }
+    // lib.optionalAttrs (server.voteDeleteThreshold != null) { vote_delete_threshold = server.voteDeleteThreshold; }
+    // lib.optionalAttrs (server.voteDeleteWindowSecs != null) { vote_delete_window_secs = server.voteDeleteWindowSecs; }
+    // lib.optionalAttrs (server.retentionSecs != null) { retention_secs = server.retentionSecs; }) cfg.servers;
   };
 in
 {
@@ -50,6 +74,22 @@ in
       description = "Log level for the grabby bot";
     };
 
+    logFormat = lib.mkOption {
+      type = lib.types.enum [
+        "json"
+        "pretty"
+      ];
+      default = "json";
+      description = "Format of log lines, both on stdout and in log files";
+    };
+
+    logDirectory = lib.mkOption {
+      type = lib.types.nullOr lib.types.str;
+      default = null;
+      description = "Directory daily rotated log files are written to besides the journal";
+      example = "/var/log/grabby";
+    };
+
     servers = lib.mkOption {
       type = lib.types.listOf (
         lib.types.submodule {
@@ -70,6 +110,13 @@ in
               ];
             };
 
+            autoEmbedThreadParents = lib.mkOption {
+              type = lib.types.listOf lib.types.str;
+              default = [ ];
+              description = "List of channel IDs (text or forum) whose threads and posts are all auto-embedded";
+              example = [ "forum1" ];
+            };
+
             embedEnabled = lib.mkOption {
               type = lib.types.bool;
               default = true;
@@ -85,6 +132,161 @@ in
                 "another-site.org"
               ];
             };
+
+            autoDeleteChannels = lib.mkOption {
+              type = lib.types.attrsOf lib.types.ints.unsigned;
+              default = { };
+              description = "Channel IDs mapped to the number of seconds after which bot uploads are deleted";
+              example = {
+                channel1 = 86400;
+              };
+            };
+
+            mirrorChannels = lib.mkOption {
+              type = lib.types.attrsOf lib.types.str;
+              default = { };
+              description = "Channel IDs mapped to the channel their auto-embeds are also posted to, e.g. a media archive";
+              example = {
+                channel1 = "archive_channel_id";
+              };
+            };
+
+            retentionSecs = lib.mkOption {
+              type = lib.types.nullOr lib.types.ints.positive;
+              default = null;
+              description = "Seconds after which bot uploads in channels not listed in autoDeleteChannels are deleted";
+              example = 604800;
+            };
+
+            webhookRepost = lib.mkOption {
+              type = lib.types.bool;
+              default = false;
+              description = "Repost auto-embeds through a webhook under the original author's name and avatar (requires Manage Webhooks)";
+            };
+
+            failureNotice = lib.mkOption {
+              type = lib.types.enum [
+                "message"
+                "brief"
+                "react"
+                "silent"
+              ];
+              default = "message";
+              description = "How auto-embed failures are reported: full error with a retry button, short self-deleting error, ⚠️ reaction or nothing";
+            };
+
+            configRoleIds = lib.mkOption {
+              type = lib.types.listOf lib.types.str;
+              default = [ ];
+              description = "Role IDs allowed to use config commands, besides Administrator and Manage Server";
+            };
+
+            locale = lib.mkOption {
+              type = lib.types.nullOr (lib.types.enum [ "en" "sl" ]);
+              default = null;
+              description = "Language of bot messages for this server (defaults to English)";
+              example = "sl";
+            };
+
+            logChannel = lib.mkOption {
+              type = lib.types.nullOr lib.types.str;
+              default = null;
+              description = "Channel ID receiving notices about failed downloads and uploads";
+              example = "123456789";
+            };
+
+            jobWebhook = lib.mkOption {
+              type = lib.types.nullOr lib.types.str;
+              default = null;
+              description = "URL a JSON payload is posted to when a download finishes or fails (stored world-readable in the Nix store)";
+              example = "https://automation.example.com/grabby";
+            };
+
+            maxWatches = lib.mkOption {
+              type = lib.types.nullOr lib.types.ints.unsigned;
+              default = null;
+              description = "Feeds the server may watch with /watch, overriding watch.max_per_server";
+              example = 25;
+            };
+
+            durationCapSecs = lib.mkOption {
+              type = lib.types.nullOr lib.types.ints.unsigned;
+              default = null;
+              description = "Seconds videos may last before they are refused, overriding media.duration_cap_secs (0 lifts the cap)";
+              example = 900;
+            };
+
+            dedupWindowSecs = lib.mkOption {
+              type = lib.types.nullOr lib.types.ints.positive;
+              default = null;
+              description = "Seconds during which a link already embedded in a channel is answered with a link to the earlier embed";
+              example = 3600;
+            };
+
+            voteDeleteThreshold = lib.mkOption {
+              type = lib.types.nullOr lib.types.ints.positive;
+              default = null;
+              description = "Distinct members whose ❌ reactions delete a bot upload";
+              example = 3;
+            };
+
+            voteDeleteWindowSecs = lib.mkOption {
+              type = lib.types.nullOr lib.types.ints.positive;
+              default = null;
+              description = "Seconds from the first ❌ reaction within which the votes have to come in (default: 600)";
+              example = 600;
+            };
+
+            embed = lib.mkOption {
+              type = tomlFormat.type;
+              default = { };
+              description = "Embed settings of all channels in this server (max_upload_mb, template, allowed_domains, audio_only, nsfw, skip_domains, fix_domains, max_pending, container)";
+              example = {
+                nsfw = "spoiler";
+              };
+            };
+
+            branding = lib.mkOption {
+              type = tomlFormat.type;
+              default = { };
+              description = "Accent color, footer text and credit of rich embeds (color, footer, credit)";
+              example = {
+                color = "#5865F2";
+                footer = "My Server";
+              };
+            };
+
+            quota = lib.mkOption {
+              type = tomlFormat.type;
+              default = { };
+              description = "Monthly download limits of this server, overriding the global ones (monthly_downloads, monthly_mb)";
+              example = {
+                monthly_downloads = 5000;
+                monthly_mb = 51200;
+              };
+            };
+
+            blocklist = lib.mkOption {
+              type = tomlFormat.type;
+              default = { };
+              description = "Members, roles and link patterns nothing is downloaded for in this server (user_ids, role_ids, url_patterns)";
+              example = {
+                user_ids = [ "123456789" ];
+                url_patterns = [ "/private/" ];
+              };
+            };
+
+            channels = lib.mkOption {
+              type = lib.types.attrsOf tomlFormat.type;
+              default = { };
+              description = "Embed settings of single channels keyed by channel ID, overriding the server's";
+              example = {
+                "123456789" = {
+                  nsfw = "block";
+                  max_upload_mb = 8;
+                };
+              };
+            };
           };
         }
       );
@@ -142,7 +344,7 @@ in
         RestartSec = "5s";
         ExecStart = "${cfg.package}/bin/grabby --config ${configFile}";
         EnvironmentFile = lib.mkIf (cfg.environmentFile != null) cfg.environmentFile;
-        ReadWritePaths = [ "/var/lib/grabby" ];
+        ReadWritePaths = [ "/var/lib/grabby" ] ++ lib.optional (cfg.logDirectory != null) cfg.logDirectory;
         WorkingDirectory = "/var/lib/grabby";
       };
     };
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use twilight_http::api_error::ApiError;
+use twilight_http::error::ErrorType;
+
+/// How long Discord accepts edits and follow-ups with an interaction's token.
+pub const TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// API error codes of edits to a response nobody can see anymore: Unknown Message, Unknown
+/// Webhook and Invalid Webhook Token.
+const GONE_CODES: [u64; 3] = [10008, 10015, 50027];
+
+/// Whether a failed edit of an interaction's response means it was dismissed or its token
+/// expired.
+pub fn is_gone(error: &twilight_http::Error) -> bool {
+    match error.kind() {
+        ErrorType::Response {
+            error: ApiError::General(error),
+            ..
+        } => GONE_CODES.contains(&error.code),
+        _ => false,
+    }
+}
+
+/// Jobs started by interactions, with whether their response went away before they were done.
+#[derive(Default)]
+pub struct InteractionJobs {
+    /// Expiry of the token and whether the job was abandoned, by interaction id
+    jobs: Mutex<HashMap<u64, (Instant, bool)>>,
+    abandoned: Notify,
+}
+
+impl InteractionJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks the job of an interaction received at `received_at` until the guard is dropped.
+    pub fn track(&self, interaction_id: u64, received_at: Instant) -> TrackedJob<'_> {
+        self.lock()
+            .insert(interaction_id, (received_at + TOKEN_LIFETIME, false));
+        TrackedJob {
+            jobs: self,
+            interaction_id,
+        }
+    }
+
+    /// Marks the job as abandoned, e.g. once its response was dismissed.
+    pub fn abandon(&self, interaction_id: u64) {
+        if let Some((_, abandoned)) = self.lock().get_mut(&interaction_id) {
+            *abandoned = true;
+        }
+        self.abandoned.notify_waiters();
+    }
+
+    /// Whether nobody can see the job's outcome anymore. Untracked jobs never are.
+    pub fn is_abandoned(&self, interaction_id: u64, now: Instant) -> bool {
+        self.lock()
+            .get(&interaction_id)
+            .is_some_and(|(expires_at, abandoned)| *abandoned || now >= *expires_at)
+    }
+
+    /// Resolves once the job is abandoned or its token expired, never for untracked jobs.
+    pub async fn abandoned(&self, interaction_id: u64) {
+        let Some((expires_at, _)) = self.lock().get(&interaction_id).copied() else {
+            return std::future::pending().await;
+        };
+        let expired = tokio::time::sleep_until(expires_at.into());
+        tokio::pin!(expired);
+        loop {
+            let notified = self.abandoned.notified();
+            tokio::pin!(notified);
+            // Registered before checking, so an abandonment in between isn't missed
+            notified.as_mut().enable();
+            if self.is_abandoned(interaction_id, Instant::now()) {
+                return;
+            }
+            tokio::select! {
+                () = &mut expired => return,
+                () = notified => {}
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, (Instant, bool)>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A job being tracked, forgotten once dropped.
+pub struct TrackedJob<'a> {
+    jobs: &'a InteractionJobs,
+    interaction_id: u64,
+}
+
+impl Drop for TrackedJob<'_> {
+    fn drop(&mut self) {
+        self.jobs.lock().remove(&self.interaction_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_expiry_abandons_job() {
+        let jobs = InteractionJobs::new();
+        let now = Instant::now();
+
+        let job = jobs.track(1, now);
+        assert!(!jobs.is_abandoned(1, now));
+        assert!(jobs.is_abandoned(1, now + TOKEN_LIFETIME));
+        // Jobs that aren't tracked, or not anymore, are left alone
+        assert!(!jobs.is_abandoned(2, now + TOKEN_LIFETIME));
+        drop(job);
+        assert!(!jobs.is_abandoned(1, now + TOKEN_LIFETIME));
+    }
+
+    #[tokio::test]
+    async fn test_abandon_wakes_waiting_job() {
+        let jobs = InteractionJobs::new();
+        let _job = jobs.track(1, Instant::now());
+        let _other = jobs.track(2, Instant::now());
+
+        let waiting = jobs.abandoned(1);
+        tokio::pin!(waiting);
+        jobs.abandon(2);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), &mut waiting)
+                .await
+                .is_err()
+        );
+
+        jobs.abandon(1);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("abandoned job kept waiting");
+        assert!(jobs.is_abandoned(1, Instant::now()));
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_ends_wait() {
+        let jobs = InteractionJobs::new();
+        let _job = jobs.track(1, Instant::now() - TOKEN_LIFETIME);
+
+        tokio::time::timeout(Duration::from_secs(1), jobs.abandoned(1))
+            .await
+            .expect("job with an expired token kept waiting");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::storage::{JsonStore, Storage};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Changes kept per server, older ones are dropped as new ones come in.
+const MAX_ENTRIES_PER_GUILD: usize = 100;
+
+/// A server setting changed with `/admin`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub guild_id: u64,
+    /// Member who made the change
+    pub user_id: Option<u64>,
+    /// Unix seconds
+    pub changed_at: u64,
+    /// The change as the `/admin` command that made it, e.g. "auto-embed channel:<#1> enabled:true"
+    pub change: String,
+}
+
+impl AuditEntry {
+    /// One line with a relative timestamp and a mention of the member.
+    pub fn render(&self) -> String {
+        let user = self
+            .user_id
+            .map_or_else(|| "?".to_string(), |id| format!("<@{id}>"));
+        format!("<t:{}:R> {user}: /admin {}", self.changed_at, self.change)
+    }
+}
+
+/// Persisted log of who changed which server setting and when.
+pub struct AuditLog {
+    store: JsonStore<Vec<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            store: storage.open("audit_log").await?,
+        })
+    }
+
+    pub async fn record(&self, entry: AuditEntry) -> Result<()> {
+        self.store
+            .update(|entries| {
+                let guild_id = entry.guild_id;
+                entries.push(entry);
+                let count = entries.iter().filter(|e| e.guild_id == guild_id).count();
+                let mut excess = count.saturating_sub(MAX_ENTRIES_PER_GUILD);
+                entries.retain(|e| {
+                    let drop = excess > 0 && e.guild_id == guild_id;
+                    if drop {
+                        excess -= 1;
+                    }
+                    !drop
+                });
+            })
+            .await
+    }
+
+    /// The `limit` latest changes in a server, newest first.
+    pub async fn recent(&self, guild_id: u64, limit: usize) -> Vec<AuditEntry> {
+        self.store
+            .read(|entries| {
+                entries
+                    .iter()
+                    .rev()
+                    .filter(|e| e.guild_id == guild_id)
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .await
+    }
+
+    /// Removes and returns every entry matching `predicate`.
+    pub async fn take_where(
+        &self,
+        predicate: impl Fn(&AuditEntry) -> bool,
+    ) -> Result<Vec<AuditEntry>> {
+        self.store
+            .update(|entries| {
+                let (taken, remaining) = entries.drain(..).partition(predicate);
+                *entries = remaining;
+                taken
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(guild_id: u64, changed_at: u64) -> AuditEntry {
+        AuditEntry {
+            guild_id,
+            user_id: Some(5),
+            changed_at,
+            change: "embed-command enabled:false".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recent_changes_per_guild() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let log = AuditLog::open(&storage).await.unwrap();
+
+        for changed_at in 0..MAX_ENTRIES_PER_GUILD as u64 + 5 {
+            log.record(entry(1, changed_at)).await.unwrap();
+        }
+        log.record(entry(2, 1000)).await.unwrap();
+        drop(log);
+
+        let log = AuditLog::open(&storage).await.unwrap();
+        let recent = log.recent(1, 3).await;
+        let times: Vec<u64> = recent.iter().map(|e| e.changed_at).collect();
+        assert_eq!(times, vec![104, 103, 102]);
+        // Only the latest changes of a server are kept, others' are left alone
+        assert_eq!(log.recent(1, usize::MAX).await.len(), MAX_ENTRIES_PER_GUILD);
+        assert_eq!(
+            log.recent(1, usize::MAX).await.last().unwrap().changed_at,
+            5
+        );
+        assert_eq!(log.recent(2, 10).await.len(), 1);
+
+        assert_eq!(
+            recent[0].render(),
+            "<t:104:R> <@5>: /admin embed-command enabled:false"
+        );
+    }
+}
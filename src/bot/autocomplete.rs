@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Most choices Discord shows for an option.
+const MAX_CHOICES: usize = 25;
+/// Longest value Discord accepts for a choice.
+const MAX_CHOICE_LEN: usize = 100;
+/// Offered when the formats of a link aren't known (yet).
+const COMMON_HEIGHTS: [u32; 5] = [1080, 720, 480, 360, 240];
+
+/// Suggestions for the `url` option from the links the user requested before, newest first. Only
+/// the link being typed is completed, the ones before it in the option are kept. Links whose host
+/// starts with what is typed come before ones merely containing it.
+pub fn url_choices(typed: &str, recent: &[String]) -> Vec<String> {
+    let start = typed
+        .rfind(|c: char| c.is_whitespace() || c == ',')
+        .map_or(0, |index| index + 1);
+    let (before, token) = typed.split_at(start);
+    let token = token.to_lowercase();
+
+    let host_matches = |url: &String| {
+        url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .is_some_and(|host| host.trim_start_matches("www.").starts_with(&token))
+    };
+    let (by_host, by_text): (Vec<&String>, Vec<&String>) = recent
+        .iter()
+        .filter(|url| url.to_lowercase().contains(&token))
+        .partition(|url| host_matches(url));
+
+    by_host
+        .into_iter()
+        .chain(by_text)
+        .map(|url| format!("{before}{url}"))
+        .filter(|choice| choice.len() <= MAX_CHOICE_LEN)
+        .take(MAX_CHOICES)
+        .collect()
+}
+
+/// Suggestions for the `quality` option from formats like `1080p mp4`, tallest first, narrowed
+/// down to the ones starting with what is typed.
+pub fn quality_choices(typed: &str, formats: &[String]) -> Vec<String> {
+    let mut heights: Vec<u32> = formats
+        .iter()
+        .filter_map(|format| format.split_once("p ")?.0.parse().ok())
+        .collect();
+    if heights.is_empty() {
+        heights = COMMON_HEIGHTS.to_vec();
+    }
+    heights.sort_unstable_by(|a, b| b.cmp(a));
+    heights.dedup();
+
+    let typed = typed.trim().trim_end_matches(['p', 'P']);
+    heights
+        .into_iter()
+        .map(|height| height.to_string())
+        .filter(|height| height.starts_with(typed))
+        .map(|height| format!("{height}p"))
+        .take(MAX_CHOICES)
+        .collect()
+}
+
+/// Height of a quality like `720p` or `720`.
+pub fn parse_quality(quality: &str) -> Option<u32> {
+    quality
+        .trim()
+        .trim_end_matches(['p', 'P'])
+        .parse()
+        .ok()
+        .filter(|height| *height > 0)
+}
+
+/// Links whose formats are being probed for autocomplete, so every keystroke while a slow probe
+/// runs doesn't start another one.
+#[derive(Clone, Default)]
+pub struct FormatProbes {
+    running: Arc<Mutex<HashSet<String>>>,
+}
+
+impl FormatProbes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the link as being probed, unless it already is.
+    pub fn try_begin(&self, url: &str) -> Option<FormatProbe> {
+        let mut running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        running.insert(url.to_string()).then(|| FormatProbe {
+            probes: self.clone(),
+            url: url.to_string(),
+        })
+    }
+}
+
+/// A running probe, done once dropped.
+pub struct FormatProbe {
+    probes: FormatProbes,
+    url: String,
+}
+
+impl Drop for FormatProbe {
+    fn drop(&mut self) {
+        self.probes
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(urls: &[&str]) -> Vec<String> {
+        urls.iter().map(|url| url.to_string()).collect()
+    }
+
+    #[test]
+    fn test_url_choices() {
+        let recent = urls(&[
+            "https://example.com/youtube-clips",
+            "https://www.youtube.com/watch?v=abc",
+            "https://vimeo.com/1",
+        ]);
+
+        assert_eq!(url_choices("", &recent), recent);
+        // Hosts starting with the text come first
+        assert_eq!(
+            url_choices("you", &recent),
+            urls(&[
+                "https://www.youtube.com/watch?v=abc",
+                "https://example.com/youtube-clips",
+            ])
+        );
+        // Links typed before the last one are kept
+        assert_eq!(
+            url_choices("https://x.com/a, VIM", &recent),
+            urls(&["https://x.com/a, https://vimeo.com/1"])
+        );
+        assert!(url_choices("nothing", &recent).is_empty());
+
+        let long = urls(&[&format!(
+            "https://example.com/{}",
+            "a".repeat(MAX_CHOICE_LEN)
+        )]);
+        assert!(url_choices("", &long).is_empty());
+    }
+
+    #[test]
+    fn test_quality_choices() {
+        let formats = urls(&[
+            "1080p mp4",
+            "720p webm",
+            "720p mp4",
+            "144p mp4",
+            "audio m4a",
+        ]);
+
+        assert_eq!(
+            quality_choices("", &formats),
+            urls(&["1080p", "720p", "144p"])
+        );
+        assert_eq!(quality_choices("1", &formats), urls(&["1080p", "144p"]));
+        assert_eq!(quality_choices("720p", &formats), urls(&["720p"]));
+        // Common resolutions are offered for links without known video formats
+        assert_eq!(
+            quality_choices("", &urls(&["audio m4a"])),
+            urls(&["1080p", "720p", "480p", "360p", "240p"])
+        );
+    }
+
+    #[test]
+    fn test_parse_quality() {
+        assert_eq!(parse_quality("720p"), Some(720));
+        assert_eq!(parse_quality(" 1080 "), Some(1080));
+        assert_eq!(parse_quality("0p"), None);
+        assert_eq!(parse_quality("best"), None);
+    }
+
+    #[test]
+    fn test_probe_runs_once_per_url() {
+        let probes = FormatProbes::new();
+
+        let probe = probes.try_begin("https://example.com/a").unwrap();
+        assert!(probes.try_begin("https://example.com/a").is_none());
+        assert!(probes.try_begin("https://example.com/b").is_some());
+
+        drop(probe);
+        assert!(probes.try_begin("https://example.com/a").is_some());
+    }
+}
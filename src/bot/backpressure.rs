@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+/// Auto-embeds being downloaded or uploaded per channel, so a flood of links in one channel
+/// can't pile up downloads without bound.
+pub struct ChannelLoad {
+    pending: Mutex<HashMap<u64, usize>>,
+}
+
+impl ChannelLoad {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a place for another auto-embed in the channel, unless `max` are already pending.
+    /// The place is given back when the returned guard is dropped.
+    pub fn try_begin(&self, channel_id: u64, max: usize) -> Option<PendingEmbed<'_>> {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = pending.entry(channel_id).or_default();
+        if *count >= max {
+            return None;
+        }
+        *count += 1;
+        Some(PendingEmbed {
+            load: self,
+            channel_id,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn pending(&self, channel_id: u64) -> usize {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&channel_id)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// A place taken by an auto-embed of a channel.
+pub struct PendingEmbed<'a> {
+    load: &'a ChannelLoad,
+    channel_id: u64,
+}
+
+impl Drop for PendingEmbed<'_> {
+    fn drop(&mut self) {
+        let mut pending = self
+            .load
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = pending.get_mut(&self.channel_id) {
+            *count -= 1;
+            if *count == 0 {
+                pending.remove(&self.channel_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caps_pending_embeds_per_channel() {
+        let load = ChannelLoad::new();
+
+        let first = load.try_begin(1, 2).unwrap();
+        let second = load.try_begin(1, 2).unwrap();
+        assert!(load.try_begin(1, 2).is_none());
+        // Other channels have their own places
+        let other = load.try_begin(2, 2).unwrap();
+        assert_eq!(load.pending(1), 2);
+
+        drop(first);
+        assert!(load.try_begin(1, 2).is_some());
+        drop((second, other));
+        assert_eq!(load.pending(1), 0);
+        assert_eq!(load.pending(2), 0);
+    }
+}
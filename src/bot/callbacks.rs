@@ -0,0 +1,409 @@
+use crate::i18n::{t, Locale};
+use crate::media::{DownloadRequest, MediaInfo};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How long a job webhook may take to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a download job ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Completed,
+    Failed,
+}
+
+/// What the source says about the downloaded media.
+#[derive(Debug, Serialize)]
+pub struct JobMetadata {
+    pub title: String,
+    pub author: Option<String>,
+    pub site: Option<String>,
+    pub duration: Option<u64>,
+    pub thumbnail: Option<String>,
+    pub uploaded_at: Option<u64>,
+    pub view_count: Option<u64>,
+    pub likes: Option<u64>,
+    pub nsfw: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JobFile {
+    pub filename: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobError {
+    /// Stable key of the kind of failure, e.g. "error.timeout"
+    pub class: &'static str,
+    pub message: String,
+}
+
+/// Payload posted to a server's job webhook once a download finished or failed.
+#[derive(Debug, Serialize)]
+pub struct JobEvent {
+    pub status: JobStatus,
+    pub request_id: String,
+    /// Server the download was requested in, as a string like Discord's own ids
+    pub guild_id: String,
+    pub url: String,
+    /// Unix seconds the job ended at
+    pub finished_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JobMetadata>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<JobFile>,
+    /// Link to the message carrying the upload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_url: Option<String>,
+    /// Links to files too large to attach, uploaded to the offload backend
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub offloaded_urls: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JobError>,
+}
+
+impl JobEvent {
+    pub fn completed(
+        guild_id: u64,
+        request: &DownloadRequest,
+        media_info: &MediaInfo,
+        message_url: Option<String>,
+        offloaded_urls: Vec<String>,
+    ) -> Self {
+        let metadata = &media_info.metadata;
+        Self {
+            status: JobStatus::Completed,
+            request_id: request.id.clone(),
+            guild_id: guild_id.to_string(),
+            url: request.url.clone(),
+            finished_at: super::expiry::unix_now(),
+            metadata: Some(JobMetadata {
+                title: metadata.title.clone(),
+                author: metadata.author.clone(),
+                site: metadata.site.clone(),
+                duration: metadata.duration,
+                thumbnail: metadata.thumbnail.clone(),
+                uploaded_at: metadata.uploaded_at,
+                view_count: metadata.view_count,
+                likes: metadata.likes,
+                nsfw: metadata.nsfw,
+            }),
+            files: media_info
+                .files
+                .iter()
+                .map(|file| JobFile {
+                    filename: file.filename.clone(),
+                    size: file.data.len() as u64,
+                })
+                .collect(),
+            message_url,
+            offloaded_urls,
+            error: None,
+        }
+    }
+
+    /// Event for a failed download, with the error in English for the receiving end.
+    pub fn failed(guild_id: u64, request: &DownloadRequest, class: &'static str) -> Self {
+        Self {
+            status: JobStatus::Failed,
+            request_id: request.id.clone(),
+            guild_id: guild_id.to_string(),
+            url: request.url.clone(),
+            finished_at: super::expiry::unix_now(),
+            metadata: None,
+            files: Vec::new(),
+            message_url: None,
+            offloaded_urls: Vec::new(),
+            error: Some(JobError {
+                class,
+                message: t(Locale::En, class).to_string(),
+            }),
+        }
+    }
+}
+
+/// Host of a job webhook URL, or `None` if it isn't an HTTP(S) URL.
+pub fn webhook_host(url: &str) -> Option<String> {
+    url::Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .and_then(|url| url.host_str().map(str::to_string))
+}
+
+/// Why a job webhook URL is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookRefusal {
+    /// Not an HTTP(S) URL, or plain HTTP where HTTPS is required
+    InvalidUrl,
+    /// The host couldn't be resolved
+    Unresolvable,
+    /// The host resolves to an address on the bot's own host or network
+    PrivateAddress(IpAddr),
+}
+
+impl std::fmt::Display for WebhookRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUrl => write!(f, "not a valid webhook URL"),
+            Self::Unresolvable => write!(f, "host could not be resolved"),
+            Self::PrivateAddress(ip) => write!(f, "host resolves to private address {ip}"),
+        }
+    }
+}
+
+impl std::error::Error for WebhookRefusal {}
+
+/// Host of a job webhook URL set with `/admin`, which must be HTTPS, or `None` if it isn't one.
+pub fn admin_webhook_host(url: &str) -> Option<String> {
+    url::Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+        .and_then(|url| url.host_str().map(str::to_string))
+}
+
+/// Resolves the host of a job webhook URL, refusing it if any of its addresses is loopback,
+/// private, link-local or unspecified, so servers can't make the bot post to its own network.
+pub async fn resolve_webhook(url: &str) -> Result<Vec<SocketAddr>, WebhookRefusal> {
+    let parsed = url::Url::parse(url).map_err(|_| WebhookRefusal::InvalidUrl)?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(WebhookRefusal::InvalidUrl);
+    }
+    let host = match parsed.host().ok_or(WebhookRefusal::InvalidUrl)? {
+        url::Host::Domain(domain) => domain.to_string(),
+        url::Host::Ipv4(ip) => ip.to_string(),
+        url::Host::Ipv6(ip) => ip.to_string(),
+    };
+    let port = parsed
+        .port_or_known_default()
+        .ok_or(WebhookRefusal::InvalidUrl)?;
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|_| WebhookRefusal::Unresolvable)?
+        .collect();
+    if addrs.is_empty() {
+        return Err(WebhookRefusal::Unresolvable);
+    }
+    if let Some(addr) = addrs.iter().find(|addr| is_private(addr.ip())) {
+        return Err(WebhookRefusal::PrivateAddress(addr.ip()));
+    }
+    Ok(addrs)
+}
+
+/// Whether `ip` is on the bot's own host or network rather than the public internet.
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0xC0 == 64)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_private(IpAddr::V4(mapped)),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local, fc00::/7
+                    || ip.segments()[0] & 0xFE00 == 0xFC00
+                    // Link-local, fe80::/10
+                    || ip.segments()[0] & 0xFFC0 == 0xFE80
+            }
+        },
+    }
+}
+
+/// Posts job events to the webhooks servers registered for them.
+pub struct JobCallbacks {
+    /// Lets webhooks on private addresses through, for tests posting to a local server
+    allow_private: bool,
+}
+
+impl JobCallbacks {
+    pub fn new() -> Self {
+        Self {
+            allow_private: false,
+        }
+    }
+
+    /// Callbacks posting to webhooks on any address.
+    #[cfg(test)]
+    pub fn allowing_private() -> Self {
+        Self {
+            allow_private: true,
+        }
+    }
+
+    /// Posts the event in the background, so a slow receiver doesn't hold up the upload.
+    pub fn send(&self, url: String, event: JobEvent) {
+        let allow_private = self.allow_private;
+        tokio::spawn(async move {
+            match post(&url, &event, allow_private).await {
+                Ok(()) => debug!(
+                    "Posted {:?} job {} to webhook",
+                    event.status, event.request_id
+                ),
+                Err(e) => warn!(
+                    "Failed to post job {} to webhook: {:#}",
+                    event.request_id, e
+                ),
+            }
+        });
+    }
+}
+
+/// Posts `event` to the addresses the webhook's host resolved to when it was checked, so a
+/// host can't resolve to a public address for the check and a private one for the request.
+async fn post(url: &str, event: &JobEvent, allow_private: bool) -> Result<()> {
+    let mut client = reqwest::Client::builder()
+        .user_agent(concat!("grabby/", env!("CARGO_PKG_VERSION")))
+        .timeout(TIMEOUT)
+        // A redirect could point anywhere, including the bot's own network
+        .redirect(reqwest::redirect::Policy::none());
+    if !allow_private {
+        let addrs = resolve_webhook(url).await?;
+        if let Some(host) = url::Url::parse(url)?.host_str() {
+            client = client.resolve_to_addrs(host, &addrs);
+        }
+    }
+    let client = client
+        .build()
+        .context("Failed to build job webhook client")?;
+    client
+        .post(url)
+        .json(event)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::MediaMetadata;
+
+    fn media_info() -> MediaInfo {
+        MediaInfo {
+            url: "https://example.com/v".to_string(),
+            files: Vec::new(),
+            metadata: MediaMetadata {
+                title: "Clip".to_string(),
+                id: "clip".to_string(),
+                thumbnail: None,
+                duration: Some(42),
+                author: Some("someone".to_string()),
+                likes: None,
+                description: None,
+                view_count: Some(7),
+                uploaded_at: None,
+                site: Some("example".to_string()),
+                format_ext: "mp4".to_string(),
+                chapters: Vec::new(),
+                nsfw: false,
+                live: false,
+                formats: Vec::new(),
+            },
+            truncated_to: None,
+            timeouts: Default::default(),
+            memory: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_completed_payload() {
+        let request = DownloadRequest {
+            id: "abc123".to_string(),
+            ..DownloadRequest::new("https://example.com/v")
+        };
+        let event = JobEvent::completed(
+            100,
+            &request,
+            &media_info(),
+            Some("https://discord.com/channels/100/200/300".to_string()),
+            Vec::new(),
+        );
+
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(payload["status"], "completed");
+        assert_eq!(payload["request_id"], "abc123");
+        assert_eq!(payload["guild_id"], "100");
+        assert_eq!(payload["metadata"]["title"], "Clip");
+        assert_eq!(payload["metadata"]["duration"], 42);
+        assert_eq!(
+            payload["message_url"],
+            "https://discord.com/channels/100/200/300"
+        );
+        assert!(payload.get("offloaded_urls").is_none());
+        assert!(payload.get("error").is_none());
+    }
+
+    #[test]
+    fn test_failed_payload() {
+        let request = DownloadRequest::new("https://example.com/v");
+        let event = JobEvent::failed(100, &request, "error.timeout");
+
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(payload["status"], "failed");
+        assert_eq!(payload["error"]["class"], "error.timeout");
+        assert!(!payload["error"]["message"].as_str().unwrap().is_empty());
+        assert!(payload.get("metadata").is_none());
+    }
+
+    #[test]
+    fn test_webhook_host() {
+        assert_eq!(
+            webhook_host("https://hooks.example.com/x?token=1").as_deref(),
+            Some("hooks.example.com")
+        );
+        assert_eq!(webhook_host("ftp://example.com/x"), None);
+        assert_eq!(webhook_host("not a url"), None);
+    }
+
+    #[test]
+    fn test_admin_webhook_requires_https() {
+        assert_eq!(
+            admin_webhook_host("https://hooks.example.com/x").as_deref(),
+            Some("hooks.example.com")
+        );
+        assert_eq!(admin_webhook_host("http://hooks.example.com/x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_private_webhooks() {
+        for url in [
+            "https://127.0.0.1/x",
+            "http://169.254.169.254/latest/meta-data",
+            "https://10.0.0.5:8443/x",
+            "https://192.168.1.1/x",
+            "https://172.16.0.1/x",
+            "https://0.0.0.0/x",
+            "https://[::1]/x",
+            "https://[fe80::1]/x",
+            "https://[fd00::1]/x",
+            "https://[::ffff:127.0.0.1]/x",
+            "https://localhost/x",
+        ] {
+            assert!(
+                matches!(
+                    resolve_webhook(url).await,
+                    Err(WebhookRefusal::PrivateAddress(_))
+                ),
+                "{url} was not refused"
+            );
+        }
+        assert_eq!(
+            resolve_webhook("ftp://example.com/x").await,
+            Err(WebhookRefusal::InvalidUrl)
+        );
+        assert!(resolve_webhook("https://93.184.215.14/x").await.is_ok());
+    }
+}
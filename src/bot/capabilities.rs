@@ -0,0 +1,156 @@
+use twilight_model::guild::PremiumTier;
+
+/// Limits and features of the destination media is posted to.
+///
+/// The upload pipeline queries these instead of hardcoding a single
+/// platform's constraints, so resize targets, gallery pagination and
+/// caption length follow whatever the destination supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrontendCapabilities {
+    /// Maximum size of a single uploaded file in bytes
+    pub max_upload_bytes: u64,
+    /// Maximum number of attachments in a single message
+    pub max_attachments: usize,
+    /// Maximum length of the message text accompanying the upload
+    pub max_content_chars: usize,
+}
+
+impl FrontendCapabilities {
+    /// Capabilities of a Discord channel without any boost perks.
+    pub const fn discord() -> Self {
+        Self {
+            max_upload_bytes: 10_000_000,
+            max_attachments: 10,
+            max_content_chars: 2000,
+        }
+    }
+
+    /// Capabilities of a Discord guild channel, taking the guild's boost level into account.
+    pub fn discord_for_premium_tier(tier: PremiumTier) -> Self {
+        let max_upload_bytes = match tier {
+            PremiumTier::Tier2 => 50_000_000,
+            PremiumTier::Tier3 => 100_000_000,
+            _ => 10_000_000,
+        };
+
+        Self {
+            max_upload_bytes,
+            ..Self::discord()
+        }
+    }
+
+    /// Lowers the upload size limit to `max_upload_bytes`, if given.
+    pub fn with_upload_limit(self, max_upload_bytes: Option<u64>) -> Self {
+        Self {
+            max_upload_bytes: max_upload_bytes
+                .map_or(self.max_upload_bytes, |max| max.min(self.max_upload_bytes)),
+            ..self
+        }
+    }
+
+    /// Truncates `content` to fit the destination's message length limit.
+    pub fn truncate_content(&self, content: &str) -> String {
+        if content.chars().count() <= self.max_content_chars {
+            return content.to_string();
+        }
+
+        let mut truncated: String = content
+            .chars()
+            .take(self.max_content_chars.saturating_sub(1))
+            .collect();
+        truncated.push('…');
+        truncated
+    }
+
+    /// Puts `label` on a line below `content`, truncating the content so both fit the
+    /// destination's message length limit.
+    pub fn label_content(&self, content: &str, label: &str) -> String {
+        let room = self
+            .max_content_chars
+            .saturating_sub(label.chars().count() + 1);
+        let content = Self {
+            max_content_chars: room,
+            ..*self
+        }
+        .truncate_content(content);
+        if content.is_empty() {
+            return label.to_string();
+        }
+        format!("{content}\n{label}")
+    }
+}
+
+impl Default for FrontendCapabilities {
+    fn default() -> Self {
+        Self::discord()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discord_defaults() {
+        let caps = FrontendCapabilities::discord();
+        assert_eq!(caps.max_upload_bytes, 10_000_000);
+        assert_eq!(caps.max_attachments, 10);
+    }
+
+    #[test]
+    fn test_discord_premium_tiers() {
+        assert_eq!(
+            FrontendCapabilities::discord_for_premium_tier(PremiumTier::None).max_upload_bytes,
+            10_000_000
+        );
+        assert_eq!(
+            FrontendCapabilities::discord_for_premium_tier(PremiumTier::Tier1).max_upload_bytes,
+            10_000_000
+        );
+        assert_eq!(
+            FrontendCapabilities::discord_for_premium_tier(PremiumTier::Tier2).max_upload_bytes,
+            50_000_000
+        );
+        assert_eq!(
+            FrontendCapabilities::discord_for_premium_tier(PremiumTier::Tier3).max_upload_bytes,
+            100_000_000
+        );
+    }
+
+    #[test]
+    fn test_with_upload_limit() {
+        let caps = FrontendCapabilities::discord();
+        assert_eq!(caps.with_upload_limit(None), caps);
+        assert_eq!(
+            caps.with_upload_limit(Some(5_000_000)).max_upload_bytes,
+            5_000_000
+        );
+        // Limits can only be lowered
+        assert_eq!(
+            caps.with_upload_limit(Some(500_000_000)).max_upload_bytes,
+            10_000_000
+        );
+    }
+
+    #[test]
+    fn test_truncate_content() {
+        let caps = FrontendCapabilities {
+            max_content_chars: 5,
+            ..FrontendCapabilities::discord()
+        };
+        assert_eq!(caps.truncate_content("abc"), "abc");
+        assert_eq!(caps.truncate_content("abcde"), "abcde");
+        assert_eq!(caps.truncate_content("abcdef"), "abcd…");
+    }
+
+    #[test]
+    fn test_label_content() {
+        let caps = FrontendCapabilities {
+            max_content_chars: 10,
+            ..FrontendCapabilities::discord()
+        };
+        assert_eq!(caps.label_content("abc", "1/2"), "abc\n1/2");
+        assert_eq!(caps.label_content("abcdefgh", "1/2"), "abcde…\n1/2");
+        assert_eq!(caps.label_content("", "1/2"), "1/2");
+    }
+}
@@ -0,0 +1,459 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use twilight_model::{
+    application::{
+        command::{Command, CommandType},
+        interaction::InteractionContextType,
+    },
+    channel::ChannelType,
+    guild::Permissions,
+};
+use twilight_util::builder::command::{
+    BooleanBuilder, ChannelBuilder, CommandBuilder, IntegerBuilder, RoleBuilder, StringBuilder,
+    SubCommandBuilder, SubCommandGroupBuilder, UserBuilder,
+};
+
+/// `/admin block add` or `remove`, taking one of a user, a role or a link pattern.
+fn block_subcommand(name: &str, description: &str) -> SubCommandBuilder {
+    SubCommandBuilder::new(name, description)
+        .option(UserBuilder::new("user", "Member").required(false))
+        .option(RoleBuilder::new("role", "Role").required(false))
+        .option(
+            StringBuilder::new("pattern", "Regular expression matched against links")
+                .required(false)
+                .max_length(200),
+        )
+}
+
+/// Full desired state of the bot's global slash commands.
+pub fn desired_commands() -> Vec<Command> {
+    // Build the /embed command
+    let embed_command = CommandBuilder::new(
+        "embed".to_string(),
+        "Download and embed media from a URL".to_string(),
+        CommandType::ChatInput,
+    )
+    .option(
+        StringBuilder::new(
+            "url",
+            "URL to download and embed, or up to 5 separated by spaces or commas",
+        )
+        .required(true)
+        .autocomplete(true),
+    )
+    .option(StringBuilder::new("message", "Message to send with the embed").required(false))
+    .option(BooleanBuilder::new("spoiler", "Mark the embed as a spoiler").required(false))
+    .option(
+        StringBuilder::new("size", "Output size profile")
+            .required(false)
+            .choices([("Standard", "standard"), ("Tiny (≤512 KB)", "tiny")]),
+    )
+    .option(
+        StringBuilder::new("codec", "Video codec for re-encoded videos")
+            .required(false)
+            .choices([
+                ("H.264 (MP4)", "h264"),
+                ("VP9 (WebM)", "vp9"),
+                ("AV1 (WebM)", "av1"),
+            ]),
+    )
+    .option(
+        StringBuilder::new("quality", "Tallest video resolution to download, e.g. 720p")
+            .required(false)
+            .autocomplete(true)
+            .max_length(16),
+    )
+    .option(StringBuilder::new("chapter", "Embed only the chapter with this title").required(false))
+    .option(BooleanBuilder::new("audio", "Send only the audio track").required(false))
+    .option(BooleanBuilder::new("zip", "Send a gallery as a single zip archive").required(false))
+    .option(
+        StringBuilder::new("pick", "Only send these gallery files, e.g. 1-3,7")
+            .required(false)
+            .max_length(100),
+    )
+    .option(
+        StringBuilder::new(
+            "subtitles",
+            "Burn in subtitles of this language, e.g. \"en\"",
+        )
+        .required(false)
+        .max_length(16),
+    )
+    .option(
+        StringBuilder::new(
+            "simulate",
+            "Show what would be embedded without uploading it",
+        )
+        .required(false)
+        .choices([
+            ("Everything but the upload", "full"),
+            ("Metadata only", "metadata"),
+        ]),
+    )
+    .option(
+        ChannelBuilder::new(
+            "channel",
+            "Channel to post the media in (default: this one)",
+        )
+        .required(false)
+        .channel_types([
+            ChannelType::GuildText,
+            ChannelType::GuildAnnouncement,
+            ChannelType::PublicThread,
+            ChannelType::PrivateThread,
+            ChannelType::AnnouncementThread,
+        ]),
+    )
+    .build();
+
+    // Build the /info command
+    let info_command = CommandBuilder::new(
+        "info".to_string(),
+        "Show what a link contains without downloading it".to_string(),
+        CommandType::ChatInput,
+    )
+    .option(StringBuilder::new("url", "URL to look up").required(true))
+    .build();
+
+    // Build the /admin command, hidden from members without Manage Server by default
+    let admin_command = CommandBuilder::new(
+        "admin".to_string(),
+        "Server administration".to_string(),
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .contexts([InteractionContextType::Guild])
+    .option(
+        SubCommandBuilder::new("auto-embed", "Turn auto-embedding in a channel on or off")
+            .option(
+                ChannelBuilder::new("channel", "Channel to change")
+                    .required(true)
+                    .channel_types([
+                        ChannelType::GuildText,
+                        ChannelType::GuildAnnouncement,
+                        ChannelType::PublicThread,
+                        ChannelType::PrivateThread,
+                        ChannelType::AnnouncementThread,
+                    ]),
+            )
+            .option(BooleanBuilder::new("enabled", "Embed links posted there").required(true)),
+    )
+    .option(
+        SubCommandBuilder::new("embed-command", "Allow or disallow /embed in this server")
+            .option(BooleanBuilder::new("enabled", "Allow /embed").required(true)),
+    )
+    .option(
+        SubCommandBuilder::new("channel", "Change embed settings of a channel")
+            .option(
+                ChannelBuilder::new("channel", "Channel to change")
+                    .required(true)
+                    .channel_types([
+                        ChannelType::GuildText,
+                        ChannelType::GuildAnnouncement,
+                        ChannelType::PublicThread,
+                        ChannelType::PrivateThread,
+                        ChannelType::AnnouncementThread,
+                    ]),
+            )
+            .option(
+                IntegerBuilder::new("max-size", "Upload size limit in MB")
+                    .required(false)
+                    .min_value(1),
+            )
+            .option(
+                StringBuilder::new(
+                    "template",
+                    "Caption with {user}, {url}, {author}, {likes}, {title} and {uploaded} placeholders",
+                )
+                .required(false)
+                .max_length(500),
+            )
+            .option(
+                StringBuilder::new(
+                    "allowed-domains",
+                    "Only embed links to these domains, separated by commas",
+                )
+                .required(false)
+                .max_length(500),
+            )
+            .option(
+                BooleanBuilder::new("audio-only", "Send only the audio track by default")
+                    .required(false),
+            )
+            .option(
+                StringBuilder::new("nsfw", "What to do with media marked as NSFW")
+                    .required(false)
+                    .choices([
+                        ("Allow", "allow"),
+                        ("Spoiler", "spoiler"),
+                        ("Block", "block"),
+                    ]),
+            )
+            .option(
+                StringBuilder::new(
+                    "skip-domains",
+                    "Domains not to auto-embed, separated by commas, or \"none\"",
+                )
+                .required(false)
+                .max_length(500),
+            )
+            .option(
+                StringBuilder::new(
+                    "fix-domains",
+                    "Domains to post as a mirror link instead of downloading, or \"none\"",
+                )
+                .required(false)
+                .max_length(500),
+            )
+            .option(
+                IntegerBuilder::new(
+                    "max-pending",
+                    "Auto-embeds in progress at once, further links are skipped",
+                )
+                .required(false)
+                .min_value(1),
+            )
+            .option(
+                StringBuilder::new("container", "Container videos are delivered in")
+                    .required(false)
+                    .choices([("MP4", "mp4"), ("WebM", "webm"), ("Original", "original")]),
+            )
+            .option(
+                BooleanBuilder::new("reset", "Drop the channel's previous settings first")
+                    .required(false),
+            ),
+    )
+    .option(
+        SubCommandBuilder::new(
+            "job-webhook",
+            "Post the results of downloads to a URL, for automation",
+        )
+        .option(
+            StringBuilder::new("url", "HTTP(S) URL to post to, left out to stop posting")
+                .required(false)
+                .max_length(500),
+        ),
+    )
+    .option(
+        SubCommandBuilder::new(
+            "mirror",
+            "Also post a channel's auto-embeds to an archive channel",
+        )
+        .option(
+            ChannelBuilder::new("channel", "Auto-embed channel to mirror")
+                .required(true)
+                .channel_types([
+                    ChannelType::GuildText,
+                    ChannelType::GuildAnnouncement,
+                    ChannelType::PublicThread,
+                    ChannelType::PrivateThread,
+                    ChannelType::AnnouncementThread,
+                ]),
+        )
+        .option(
+            ChannelBuilder::new("archive", "Channel to post to, left out to stop mirroring")
+                .required(false)
+                .channel_types([ChannelType::GuildText, ChannelType::GuildAnnouncement]),
+        ),
+    )
+    .option(SubCommandBuilder::new(
+        "history",
+        "Show recent changes to the server's settings",
+    ))
+    .option(
+        SubCommandGroupBuilder::new("block", "Keep members or links from being downloaded")
+            .subcommands([
+                block_subcommand("add", "Block a member, a role or links matching a pattern"),
+                block_subcommand("remove", "Unblock a member, a role or a link pattern"),
+                SubCommandBuilder::new("list", "Show the server's blocklist"),
+            ]),
+    )
+    .option(
+        SubCommandGroupBuilder::new("forget", "Remove stored data").subcommands([
+            SubCommandBuilder::new("guild", "Remove everything stored about this server"),
+            SubCommandBuilder::new("user", "Remove everything stored about a user")
+                .option(UserBuilder::new("user", "User to forget").required(true)),
+        ]),
+    )
+    // Bot-wide commands, only run for configured owners
+    .option(SubCommandBuilder::new(
+        "reload-config",
+        "Reload the config file (bot owners only)",
+    ))
+    .option(SubCommandBuilder::new(
+        "tool-versions",
+        "Show versions of yt-dlp, gallery-dl and ffmpeg (bot owners only)",
+    ))
+    .option(SubCommandBuilder::new(
+        "stats",
+        "Show runtime statistics (bot owners only)",
+    ))
+    .option(SubCommandBuilder::new(
+        "purge-cache",
+        "Clear in-memory caches (bot owners only)",
+    ))
+    .option(SubCommandBuilder::new(
+        "diagnostics",
+        "Check tools, encoders, disk space and connectivity (bot owners only)",
+    ))
+    .build();
+
+    // Build the /watch command, with the same permissions as /admin
+    let watch_command = CommandBuilder::new(
+        "watch".to_string(),
+        "Embed new posts of creators into a channel".to_string(),
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .contexts([InteractionContextType::Guild])
+    .option(
+        SubCommandBuilder::new("add", "Watch a channel, playlist, RSS feed or Reddit user")
+            .option(
+                StringBuilder::new("url", "Link to the creator or feed")
+                    .required(true)
+                    .max_length(500),
+            )
+            .option(
+                ChannelBuilder::new(
+                    "channel",
+                    "Channel to embed new posts in (default: this one)",
+                )
+                .required(false)
+                .channel_types([
+                    ChannelType::GuildText,
+                    ChannelType::GuildAnnouncement,
+                    ChannelType::PublicThread,
+                    ChannelType::PrivateThread,
+                    ChannelType::AnnouncementThread,
+                ]),
+            ),
+    )
+    .option(
+        SubCommandBuilder::new("remove", "Stop watching a feed").option(
+            IntegerBuilder::new("id", "Number of the feed, as shown by /watch list")
+                .required(true)
+                .min_value(1),
+        ),
+    )
+    .option(SubCommandBuilder::new(
+        "list",
+        "Show the feeds this server watches",
+    ))
+    .build();
+
+    vec![embed_command, info_command, admin_command, watch_command]
+}
+
+/// Returns true if the registered commands differ from the desired ones.
+///
+/// Fields Discord fills in on its own (ids, versions, defaults) are ignored.
+pub fn needs_update(desired: &[Command], registered: &[Command]) -> bool {
+    if desired.len() != registered.len() {
+        return true;
+    }
+
+    let registered: HashMap<&str, &Command> = registered
+        .iter()
+        .map(|command| (command.name.as_str(), command))
+        .collect();
+
+    desired.iter().any(|command| {
+        registered
+            .get(command.name.as_str())
+            .is_none_or(|existing| signature(command, command) != signature(existing, command))
+    })
+}
+
+/// The parts of a command that are set by us, normalized for comparison against `desired`.
+fn signature(command: &Command, desired: &Command) -> Value {
+    let mut signature = serde_json::json!({
+        "name": command.name,
+        "description": command.description,
+        "type": command.kind,
+        "default_member_permissions": command.default_member_permissions,
+        "options": command.options,
+    });
+    // Discord reports its own default when no contexts were requested
+    if desired.contexts.is_some() {
+        signature["contexts"] = serde_json::json!(command.contexts);
+    }
+
+    normalize(&mut signature);
+    signature
+}
+
+/// Drops nulls, `false` flags and empty collections, which Discord omits from responses.
+fn normalize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.values_mut().for_each(normalize);
+            map.retain(|_, v| !is_empty(v));
+        }
+        Value::Array(items) => items.iter_mut().for_each(normalize),
+        _ => {}
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Simulates what Discord returns for a registered command.
+    fn as_registered(command: &Command) -> Command {
+        let mut value = serde_json::to_value(command).unwrap();
+        value["id"] = "1".into();
+        value["application_id"] = "2".into();
+        value["version"] = "3".into();
+        value["integration_types"] = serde_json::json!([0]);
+        if value.get("contexts").is_none_or(Value::is_null) {
+            value["contexts"] = serde_json::json!([0, 1, 2]);
+        }
+        normalize(&mut value["options"]);
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_unchanged_commands_are_not_pushed() {
+        let desired = desired_commands();
+        let registered: Vec<_> = desired.iter().rev().map(as_registered).collect();
+
+        assert!(!needs_update(&desired, &registered));
+    }
+
+    #[test]
+    fn test_changed_description_is_pushed() {
+        let desired = desired_commands();
+        let mut registered: Vec<_> = desired.iter().map(as_registered).collect();
+        registered[0].description = "Old description".to_string();
+
+        assert!(needs_update(&desired, &registered));
+    }
+
+    #[test]
+    fn test_added_option_is_pushed() {
+        let desired = desired_commands();
+        let mut registered: Vec<_> = desired.iter().map(as_registered).collect();
+        registered[0].options.pop();
+
+        assert!(needs_update(&desired, &registered));
+    }
+
+    #[test]
+    fn test_missing_or_extra_commands_are_pushed() {
+        let desired = desired_commands();
+        let registered: Vec<_> = desired.iter().map(as_registered).collect();
+
+        assert!(needs_update(&desired, &registered[..1]));
+        assert!(needs_update(&desired[..1], &registered));
+        assert!(needs_update(&desired, &[]));
+    }
+}
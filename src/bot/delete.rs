@@ -0,0 +1,72 @@
+use twilight_model::{
+    guild::Permissions,
+    id::{marker::UserMarker, Id},
+};
+
+const CUSTOM_ID_PREFIX: &str = "delete:";
+
+/// Custom id of the delete button of an upload made for `requester`.
+///
+/// The requester is part of the id so buttons keep working across restarts.
+pub fn custom_id(requester: Option<Id<UserMarker>>) -> String {
+    match requester {
+        Some(id) => format!("{CUSTOM_ID_PREFIX}{id}"),
+        None => CUSTOM_ID_PREFIX.to_string(),
+    }
+}
+
+/// The requester a delete button was issued for, `Some(None)` if the upload had none.
+pub fn requester_from_custom_id(custom_id: &str) -> Option<Option<Id<UserMarker>>> {
+    let requester = custom_id.strip_prefix(CUSTOM_ID_PREFIX)?;
+    if requester.is_empty() {
+        return Some(None);
+    }
+    requester.parse().ok().and_then(Id::new_checked).map(Some)
+}
+
+/// Checks whether `user_id` may delete an upload: its requester and members who can manage
+/// messages can.
+pub fn may_delete(
+    user_id: Id<UserMarker>,
+    permissions: Permissions,
+    requester: Option<Id<UserMarker>>,
+) -> bool {
+    requester == Some(user_id)
+        || permissions.intersects(Permissions::ADMINISTRATOR | Permissions::MANAGE_MESSAGES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_id_round_trip() {
+        let requester = Some(Id::new(42));
+        assert_eq!(
+            requester_from_custom_id(&custom_id(requester)),
+            Some(requester)
+        );
+        assert_eq!(requester_from_custom_id(&custom_id(None)), Some(None));
+        assert_eq!(requester_from_custom_id("delete:abc"), None);
+        assert_eq!(requester_from_custom_id("retry:42"), None);
+    }
+
+    #[test]
+    fn test_requester_and_moderators_may_delete() {
+        let (requester, other) = (Id::new(1), Id::new(2));
+
+        assert!(may_delete(requester, Permissions::empty(), Some(requester)));
+        assert!(!may_delete(
+            other,
+            Permissions::SEND_MESSAGES,
+            Some(requester)
+        ));
+        assert!(may_delete(
+            other,
+            Permissions::MANAGE_MESSAGES,
+            Some(requester)
+        ));
+        assert!(may_delete(other, Permissions::ADMINISTRATOR, None));
+        assert!(!may_delete(other, Permissions::empty(), None));
+    }
+}
@@ -1,49 +1,373 @@
-use crate::{config::ConfigManager, media::MediaDownloader};
+use super::abandon::{self, InteractionJobs};
+use super::audit::{AuditEntry, AuditLog};
+use super::autocomplete::{self, FormatProbes};
+use super::backpressure::ChannelLoad;
+use super::callbacks::{admin_webhook_host, resolve_webhook, JobCallbacks, JobEvent};
+use super::capabilities::FrontendCapabilities;
+use super::commands;
+use super::delete;
+use super::entitlements::{self, Entitlements};
+use super::expiry::{self, ExpiryScheduler};
+use super::forget::{ForgetSummary, ForgetTarget};
+use super::history::{self, normalize_url, EmbedHistory, RequestHistory};
+use super::links;
+use super::owner::{self, BotStats, OwnerCommand};
+use super::permissions;
+use super::quota::{self, QuotaTracker};
+use super::ratelimit::{self, UploadLanes};
+use super::retry::{self, RetryDenied, RetryQueue};
+use super::settings::{ServerSettings, SettingsCommand};
+use super::simulate::{MetadataReport, SimulationReport};
+use super::votes::DeleteVotes;
+use super::watch::{Added, Subscription, WatchCommand, WatchList};
+use super::webhook::{RepostAs, WebhookReposter};
+use crate::{
+    cluster::{self, Coordinator},
+    config::{
+        BlockEntry, BrandingConfig, ChannelConfig, ConfigManager, FailureNotice, NsfwPolicy,
+        Requester, ServerConfig, SimulateMode, TierConfig,
+    },
+    health::{self, Heartbeat},
+    i18n::{t, tf, Locale},
+    media::{
+        check_free_space, error_class, AudioFormat, Diagnostics, DownloadRequest, Feed, FeedEntry,
+        ImageTooLarge, JobClass, MediaDownloader, MediaMetadata, OutputContainer, Progress,
+        ProgressReporter, ResizeProfile, Stage, StageTimeout, StageTimeouts, TooLong, VideoCodec,
+    },
+    metrics::RuntimeSnapshot,
+    storage::{
+        media::{MediaStore, StoredMedia},
+        Storage,
+    },
+};
 use anyhow::{Context, Result};
+use futures_util::future::join_all;
+use std::collections::HashMap;
 use std::env;
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
 use tokio::join;
-use tracing::{debug, error, info, warn};
+use tokio::sync::watch;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use twilight_cache_inmemory::InMemoryCache;
 use twilight_gateway::{Event, Intents, Shard, ShardId, StreamExt};
 use twilight_http::request::channel::reaction::RequestReactionType;
 use twilight_http::Client as HttpClient;
 use twilight_model::{
     application::{
-        command::CommandType,
+        command::{CommandOptionChoice, CommandOptionChoiceValue},
         interaction::{
-            application_command::CommandData, Interaction, InteractionData, InteractionType,
+            application_command::{CommandData, CommandDataOption, CommandOptionValue},
+            Interaction, InteractionData, InteractionType,
         },
+        monetization::Entitlement,
     },
-    channel::message::{EmojiReactionType, MessageFlags},
-    gateway::payload::incoming::{MessageCreate, ReactionAdd},
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle, Component},
+        AllowedMentions, Embed, EmojiReactionType, MessageFlags,
+    },
+    gateway::payload::incoming::{MessageCreate, ReactionAdd, ReactionRemove},
+    guild::Permissions,
     http::{
         attachment::Attachment,
         interaction::{InteractionResponse, InteractionResponseType},
     },
     id::{
-        marker::{ApplicationMarker, ChannelMarker, UserMarker},
+        marker::{ApplicationMarker, ChannelMarker, GuildMarker, RoleMarker, UserMarker},
         Id,
     },
 };
-use twilight_util::builder::command::{BooleanBuilder, CommandBuilder, StringBuilder};
+use twilight_util::builder::{
+    embed::{EmbedBuilder, EmbedFieldBuilder, EmbedFooterBuilder, ImageSource},
+    InteractionResponseDataBuilder,
+};
+
+/// Most links embedded from a single message or `/embed` invocation.
+const MAX_URLS: usize = 5;
+
+/// Settings changes listed by `/admin history`.
+const HISTORY_ENTRIES: usize = 10;
+
+/// Most formats listed by `/info`.
+const MAX_INFO_FORMATS: usize = 15;
+
+/// Characters of the description `/info` shows before cutting it off.
+const MAX_INFO_DESCRIPTION: usize = 300;
+
+/// Shortest time between two progress updates of an interaction response.
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a brief auto-embed failure notice stays up.
+const BRIEF_NOTICE_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Most new posts of a watched feed embedded per poll. Older ones are skipped, so a feed that
+/// was unreachable for a while doesn't flood the channel once it's back.
+const MAX_WATCH_POSTS: usize = 5;
+
+/// How long suggesting qualities waits for the formats of a link, leaving time to answer before
+/// Discord gives up on the suggestions. A probe taking longer keeps running so its formats are
+/// cached for the next keystroke.
+const FORMAT_PROBE_WAIT: Duration = Duration::from_millis(2000);
+
+/// Fills the placeholders of a caption template set with `/admin channel`.
+fn render_template(
+    template: &str,
+    user_id: Option<Id<UserMarker>>,
+    media_info: &crate::media::MediaInfo,
+) -> String {
+    let metadata = &media_info.metadata;
+    template
+        .replace(
+            "{user}",
+            &user_id.map(|id| format!("<@{id}>")).unwrap_or_default(),
+        )
+        .replace("{url}", &media_info.url)
+        .replace("{author}", metadata.author.as_deref().unwrap_or_default())
+        .replace(
+            "{likes}",
+            &metadata
+                .likes
+                .map(crate::utils::abbreviate_count)
+                .unwrap_or_default(),
+        )
+        .replace(
+            "{uploaded}",
+            &metadata
+                .uploaded_at
+                .map(|uploaded_at| crate::utils::format_relative(uploaded_at, expiry::unix_now()))
+                .unwrap_or_default(),
+        )
+        .replace("{title}", &metadata.title)
+}
+
+fn clean_error_message(error: &anyhow::Error, locale: Locale) -> String {
+    if let Some(too_long) = TooLong::find(error) {
+        return tf(
+            locale,
+            "error.too_long_limit",
+            &[
+                (
+                    "duration",
+                    &crate::utils::format_duration(too_long.duration_secs),
+                ),
+                ("limit", &crate::utils::format_duration(too_long.max_secs)),
+            ],
+        );
+    }
+    if let Some(too_large) = ImageTooLarge::find(error) {
+        return tf(
+            locale,
+            "error.image_too_large_size",
+            &[
+                ("width", &too_large.width.to_string()),
+                ("height", &too_large.height.to_string()),
+            ],
+        );
+    }
+    match StageTimeout::find(error) {
+        Some(timeout) => tf(
+            locale,
+            timeout.stage.timeout_key(),
+            &[("secs", &timeout.after.as_secs().to_string())],
+        ),
+        None => t(locale, error_class(error)).to_string(),
+    }
+}
+
+/// Runs blocking transcoding work on its own thread, giving up once the transcode timeout is up
+/// and killing the ffmpeg it runs.
+async fn transcode<T: Send + 'static>(
+    timeouts: &StageTimeouts,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T> {
+    let task = crate::media::run_blocking(work);
+    Ok(timeouts.run(Stage::Transcode, task).await??)
+}
+
+/// Embed of `/info` showing what the source reports about a link.
+fn info_embed(
+    url: &str,
+    metadata: &MediaMetadata,
+    branding: &BrandingConfig,
+    locale: Locale,
+) -> Embed {
+    // Embed titles are limited to 256 characters
+    let title: String = metadata.title.chars().take(256).collect();
+    let mut embed = EmbedBuilder::new().title(title).url(url);
+
+    if let Some(description) = &metadata.description {
+        let mut text: String = description.chars().take(MAX_INFO_DESCRIPTION).collect();
+        if text.len() < description.len() {
+            text.push('…');
+        }
+        embed = embed.description(text);
+    }
+    if let Some(site) = &metadata.site {
+        embed = embed.field(EmbedFieldBuilder::new(t(locale, "info.site"), site).inline());
+    }
+    if let Some(author) = &metadata.author {
+        embed = embed.field(EmbedFieldBuilder::new(t(locale, "info.author"), author).inline());
+    }
+    if metadata.live {
+        embed = embed.field(
+            EmbedFieldBuilder::new(t(locale, "info.duration"), t(locale, "info.live")).inline(),
+        );
+    } else if let Some(duration) = metadata.duration {
+        embed = embed.field(
+            EmbedFieldBuilder::new(
+                t(locale, "info.duration"),
+                crate::utils::format_duration(duration),
+            )
+            .inline(),
+        );
+    }
+    if let Some(likes) = metadata.likes {
+        embed = embed.field(
+            EmbedFieldBuilder::new(
+                t(locale, "info.likes"),
+                crate::utils::abbreviate_count(likes),
+            )
+            .inline(),
+        );
+    }
+    if let Some(views) = metadata.view_count {
+        embed = embed.field(
+            EmbedFieldBuilder::new(
+                t(locale, "info.views"),
+                crate::utils::abbreviate_count(views),
+            )
+            .inline(),
+        );
+    }
+    if let Some(uploaded_at) = metadata.uploaded_at {
+        // Discord shows the date in each member's own time zone
+        embed = embed.field(
+            EmbedFieldBuilder::new(t(locale, "info.uploaded"), format!("<t:{uploaded_at}:D>"))
+                .inline(),
+        );
+    }
+
+    let formats = if metadata.formats.is_empty() {
+        metadata.format_ext.clone()
+    } else {
+        metadata
+            .formats
+            .iter()
+            .take(MAX_INFO_FORMATS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if !formats.is_empty() {
+        embed = embed.field(EmbedFieldBuilder::new(t(locale, "info.formats"), formats));
+    }
+
+    if let Some(thumbnail) = metadata
+        .thumbnail
+        .as_deref()
+        .and_then(|thumbnail| ImageSource::url(thumbnail).ok())
+    {
+        embed = embed.thumbnail(thumbnail);
+    }
 
-fn clean_error_message(error: &anyhow::Error) -> String {
-    let error_str = error.to_string().to_lowercase();
+    branded(embed, branding, locale).build()
+}
 
-    if error_str.contains("unsupported url") || error_str.contains("no extractor found") {
-        return "Unsupported URL".to_string();
+/// Applies a server's accent color and footer to one of the bot's embeds.
+fn branded(mut embed: EmbedBuilder, branding: &BrandingConfig, locale: Locale) -> EmbedBuilder {
+    if let Some(color) = branding.color() {
+        embed = embed.color(color);
     }
 
-    if error_str.contains("network error") || error_str.contains("connection") {
-        return "Network error - please try again".to_string();
+    let footer = [
+        branding
+            .footer
+            .as_deref()
+            .filter(|footer| !footer.is_empty()),
+        branding.show_credit().then(|| t(locale, "embed.credit")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" • ");
+    if !footer.is_empty() {
+        // Embed footers are limited to 2048 characters
+        let footer: String = footer.chars().take(2048).collect();
+        embed = embed.footer(EmbedFooterBuilder::new(footer));
     }
 
-    if error_str.contains("timeout") {
-        return "Request timed out - please try again".to_string();
+    embed
+}
+
+/// Appends the request id users can quote when reporting a failure.
+fn with_reference(locale: Locale, message: &str, request_id: &str) -> String {
+    format!(
+        "{message}\n-# {}",
+        tf(locale, "error.reference", &[("id", request_id)])
+    )
+}
+
+/// Loads the application's current entitlements, which gateway events keep up to date from then on.
+async fn load_entitlements(
+    http: &HttpClient,
+    application_id: Id<ApplicationMarker>,
+    entitlements: &Entitlements,
+) -> Result<()> {
+    let mut after = None;
+    loop {
+        let mut request = http
+            .entitlements(application_id)
+            .exclude_ended(true)
+            .limit(100)?;
+        if let Some(after) = after {
+            request = request.after(after);
+        }
+        let page = request.await?.models().await?;
+        after = page.last().map(|entitlement| entitlement.id);
+        let full = page.len() == 100;
+        for entitlement in page {
+            entitlements.update(entitlement);
+        }
+        if !full {
+            return Ok(());
+        }
     }
+}
+
+/// Where an upload ended up.
+#[derive(Debug, Default)]
+struct Delivery {
+    /// Link to the first message of the upload
+    message_url: Option<String>,
+    /// Links to the files uploaded to the offload backend
+    offloaded_urls: Vec<String>,
+}
 
-    "Download failed".to_string()
+/// Per-upload settings resolved from the destination and the command options.
+struct UploadOptions {
+    message: Option<String>,
+    spoiler: bool,
+    profile: ResizeProfile,
+    codec: VideoCodec,
+    /// Container videos are kept in when resized, overriding `codec` where they differ
+    container: Option<OutputContainer>,
+    audio_only: bool,
+    /// Bundle the files into a zip when there are more of them than this
+    zip_over: Option<usize>,
+    capabilities: FrontendCapabilities,
+    locale: Locale,
+    expires_after: Option<Duration>,
+    /// Remember the embed for deduplicating the link for this long
+    dedup_window: Option<Duration>,
+    repost_as: Option<RepostAs>,
+    /// Caption replacing the default one, see [`render_template`]
+    template: Option<String>,
+    /// Request id of an embed that is only simulated, which reports what it would upload instead
+    simulated_request: Option<String>,
+    /// Where to tell the user the upload is held up by a rate limit
+    progress: Option<ProgressReporter>,
+    /// Channel the upload is posted to again once done, such as a media archive
+    mirror_to: Option<Id<ChannelMarker>>,
 }
 
 #[derive(Clone)]
@@ -51,9 +375,43 @@ pub struct DiscordBot {
     http: Arc<HttpClient>,
     cache: Arc<InMemoryCache>,
     media_downloader: Arc<MediaDownloader>,
-    config: Arc<ConfigManager>,
+    /// Replaced as a whole by `/admin reload-config`
+    config: Arc<RwLock<Arc<ConfigManager>>>,
     application_id: Id<ApplicationMarker>,
     user_id: Id<UserMarker>,
+    heartbeat: Arc<Heartbeat>,
+    expiry: Arc<ExpiryScheduler>,
+    reposter: Arc<WebhookReposter>,
+    history: Arc<EmbedHistory>,
+    settings: Arc<ServerSettings>,
+    /// Who changed which server setting and when
+    audit: Arc<AuditLog>,
+    /// Failed downloads that can be retried with the button on their error message
+    retries: Arc<RetryQueue<EmbedCommandOptions>>,
+    /// ❌ reactions counting towards deleting bot uploads
+    delete_votes: Arc<DeleteVotes>,
+    /// Downloads of each server this month, limited by its quota
+    quota: Arc<QuotaTracker>,
+    /// Auto-embeds in progress per channel, capped by the channel's `max_pending`
+    channel_load: Arc<ChannelLoad>,
+    /// Uploads taking turns per channel, so they don't race each other into its rate limit
+    upload_lanes: Arc<UploadLanes>,
+    /// SKU entitlements granting tiers, tracked only if a tier uses them
+    entitlements: Arc<Entitlements>,
+    /// Other instances running side by side, which share links and settings with this one
+    cluster: Option<Arc<dyn Coordinator>>,
+    offload: Option<Arc<dyn MediaStore>>,
+    /// Posts the results of downloads to the webhooks servers registered
+    callbacks: Arc<JobCallbacks>,
+    /// Creator feeds servers watch with `/watch`
+    watches: Arc<WatchList>,
+    /// Links users embedded with `/embed`, suggested back to them as they type
+    requests: Arc<RequestHistory>,
+    /// Links whose formats are being probed to suggest qualities for `/embed`
+    format_probes: FormatProbes,
+    /// Commands still working, cancelled once nobody can see their outcome anymore
+    interaction_jobs: Arc<InteractionJobs>,
+    started_at: Instant,
 }
 
 impl DiscordBot {
@@ -62,18 +420,64 @@ impl DiscordBot {
     }
 
     pub async fn new_with_config(token: String, config: ConfigManager) -> Result<(Self, Shard)> {
-        let http = Arc::new(HttpClient::new(token.clone()));
+        let timeouts = config.global().get_timeouts();
+        // Uploads are limited per stage, so requests must not give up before that
+        let http = Arc::new(
+            HttpClient::builder()
+                .token(token.clone())
+                .timeout(timeouts.longest_upload())
+                .build(),
+        );
         let cache = Arc::new(InMemoryCache::new());
 
-        let intents =
-            Intents::GUILD_MESSAGES | Intents::MESSAGE_CONTENT | Intents::GUILD_MESSAGE_REACTIONS;
-        let shard = Shard::new(ShardId::ONE, token, intents);
+        let intents = Intents::GUILDS
+            | Intents::GUILD_MESSAGES
+            | Intents::MESSAGE_CONTENT
+            | Intents::GUILD_MESSAGE_REACTIONS;
+        let shard_id = cluster::shard_from_env()?;
+        if shard_id != ShardId::ONE {
+            info!(
+                "Running shard {} of {}",
+                shard_id.number(),
+                shard_id.total()
+            );
+        }
+        let shard = Shard::new(shard_id, token, intents);
 
-        let media_downloader =
-            Arc::new(MediaDownloader::new().context("Failed to initialize media downloader")?);
+        crate::media::configure_http_client(&config.global().get_http_settings())?;
+        let orphans = crate::media::kill_orphans();
+        if orphans > 0 {
+            info!(
+                "Cleaned up {} processes left over from a previous run",
+                orphans
+            );
+        }
+        let media_downloader = Arc::new(
+            MediaDownloader::new(
+                config.global().get_duration_limit(),
+                config.global().get_live_capture_secs(),
+                config.global().get_gallery_dl_sites(),
+                config.global().get_audio_format(),
+            )
+            .context("Failed to initialize media downloader")?
+            .with_url_rewrites(config.global().get_url_rewrites())
+            .with_timeouts(timeouts)
+            .with_queue(
+                config.global().get_max_concurrent_downloads(),
+                config.global().get_queue_policy(),
+            )
+            .with_memory_budget(config.global().get_memory_budget_bytes())
+            .with_image_limits(config.global().get_image_limits())
+            .with_scanner(config.global().get_file_scanner()),
+        );
 
-        if let Err(e) = media_downloader.test_setup().await {
-            warn!("Media downloader test failed: {}", e);
+        if let Some(tools_dir) = config.global().get_bootstrap_dir() {
+            crate::media::ensure_tools(&tools_dir, &config.global().get_tool_pins()).await;
+        }
+        media_downloader.warm_up().await;
+        media_downloader.load_supported_sites().await;
+        if let Some(idle_timeout) = config.global().get_idle_timeout() {
+            media_downloader.spawn_idle_monitor(idle_timeout);
         }
 
         let application_id = {
@@ -86,54 +490,182 @@ impl DiscordBot {
             response.model().await?.id
         };
 
+        let heartbeat = Arc::new(Heartbeat::new());
+        health::spawn_watchdog(heartbeat.clone(), config.global().get_stall_threshold());
+        if let Some(bind) = config.global().get_health_bind() {
+            health::spawn_server(bind, heartbeat.clone()).await?;
+        }
+
+        let storage = Storage::from_config(config.global())
+            .await
+            .context("Failed to set up storage")?;
+        let expiry = Arc::new(
+            ExpiryScheduler::open(&storage)
+                .await
+                .context("Failed to load scheduled deletions")?,
+        );
+        info!("Loaded {} scheduled deletions", expiry.pending().await);
+        let reposter = Arc::new(
+            WebhookReposter::open(http.clone(), user_id, &storage)
+                .await
+                .context("Failed to load webhook reposts")?,
+        );
+        let history = Arc::new(
+            EmbedHistory::open(&storage)
+                .await
+                .context("Failed to load embed history")?,
+        );
+        let settings = Arc::new(
+            ServerSettings::open(&storage)
+                .await
+                .context("Failed to load server settings")?,
+        );
+        let audit = Arc::new(
+            AuditLog::open(&storage)
+                .await
+                .context("Failed to load audit log")?,
+        );
+        let quota = Arc::new(
+            QuotaTracker::open(&storage)
+                .await
+                .context("Failed to load quota usage")?,
+        );
+        let watches = Arc::new(
+            WatchList::open(&storage)
+                .await
+                .context("Failed to load watched feeds")?,
+        );
+        let requests = Arc::new(
+            RequestHistory::open(&storage)
+                .await
+                .context("Failed to load request history")?,
+        );
+        let entitlements = Arc::new(Entitlements::new());
+        if config.global().has_sku_tiers() {
+            load_entitlements(&http, application_id, &entitlements)
+                .await
+                .context("Failed to load entitlements")?;
+            info!("Loaded {} entitlements", entitlements.count());
+        }
+        let saved = settings.all().await;
+        info!("Loaded saved settings of {} servers", saved.len());
+        config.apply_server_configs(saved);
+
+        let cluster = cluster::connect(config.global())
+            .await
+            .context("Failed to set up coordination with other instances")?;
+        if let Some(cluster) = &cluster {
+            info!(
+                "Coordinating with other instances through {}",
+                cluster.name()
+            );
+            let shared = cluster
+                .server_settings()
+                .await
+                .context("Failed to load shared server settings")?;
+            info!("Loaded shared settings of {} servers", shared.len());
+            config.apply_server_configs(shared);
+        }
+
+        let offload = config
+            .global()
+            .offload
+            .as_ref()
+            .map(crate::storage::media::open)
+            .transpose()
+            .context("Failed to set up media offload")?;
+        if let Some(store) = &offload {
+            info!("Offloading oversized media to {}", store.name());
+        }
+
         let bot = Self {
             http: http.clone(),
             cache,
             media_downloader: media_downloader.clone(),
-            config: Arc::new(config),
+            config: Arc::new(RwLock::new(Arc::new(config))),
             application_id,
             user_id,
+            heartbeat,
+            expiry,
+            reposter,
+            history,
+            settings,
+            audit,
+            retries: Arc::new(RetryQueue::new()),
+            delete_votes: Arc::new(DeleteVotes::new()),
+            quota,
+            channel_load: Arc::new(ChannelLoad::new()),
+            upload_lanes: Arc::new(UploadLanes::new()),
+            entitlements,
+            cluster,
+            offload,
+            callbacks: Arc::new(JobCallbacks::new()),
+            watches,
+            requests,
+            format_probes: FormatProbes::new(),
+            interaction_jobs: Arc::new(InteractionJobs::new()),
+            started_at: Instant::now(),
         };
 
+        #[cfg(feature = "grpc")]
+        if let Some(bind) = bot.config().global().get_grpc_bind() {
+            let token = bot.config().global().get_grpc_token().map(str::to_string);
+            let api = crate::grpc::MediaApi::new(media_downloader.clone(), bot.config.clone());
+            crate::grpc::spawn_server(bind, token, api).await?;
+        }
+
         bot.register_commands().await?;
 
         Ok((bot, shard))
     }
 
+    fn config(&self) -> Arc<ConfigManager> {
+        self.config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
     async fn register_commands(&self) -> Result<()> {
-        info!("Registering Discord slash commands...");
+        let desired = commands::desired_commands();
+        let client = self.http.interaction(self.application_id);
 
-        // Build the /embed command
-        let embed_command = CommandBuilder::new(
-            "embed".to_string(),
-            "Download and embed media from a URL".to_string(),
-            CommandType::ChatInput,
-        )
-        .option(StringBuilder::new("url", "URL to download and embed").required(true))
-        .option(StringBuilder::new("message", "Message to send with the embed").required(false))
-        .option(BooleanBuilder::new("spoiler", "Mark the embed as a spoiler").required(false))
-        .build();
+        let registered = client.global_commands().await?.models().await?;
+        if !commands::needs_update(&desired, &registered) {
+            info!("Slash commands are up to date, skipping registration");
+            return Ok(());
+        }
 
-        // Create the global command using the interaction client
-        self.http
-            .interaction(self.application_id)
-            .create_global_command()
-            .chat_input(&embed_command.name, &embed_command.description)
-            .command_options(&embed_command.options)
-            .await?;
+        info!("Registering Discord slash commands...");
 
-        info!("Successfully registered /embed slash command");
+        // Replace the global commands using the interaction client
+        client.set_global_commands(&desired).await?;
+
+        info!("Successfully registered {} slash commands", desired.len());
         Ok(())
     }
 
     pub async fn run(self, mut shard: Shard) -> Result<()> {
         info!("Discord bot starting...");
 
+        self.spawn_expiry_worker();
+        self.spawn_settings_sync();
+        self.spawn_watch_worker();
+
+        // Beat even when no events arrive, so only a blocked loop looks stalled
+        let mut heartbeat_tick = tokio::time::interval(Duration::from_secs(1));
+
         loop {
-            let event = match shard
-                .next_event(twilight_gateway::EventTypeFlags::all())
-                .await
-            {
+            let next_event = tokio::select! {
+                event = shard.next_event(twilight_gateway::EventTypeFlags::all()) => event,
+                _ = heartbeat_tick.tick() => {
+                    self.heartbeat.beat();
+                    continue;
+                }
+            };
+            self.heartbeat.beat();
+
+            let event = match next_event {
                 Some(Ok(event)) => event,
                 Some(Err(source)) => {
                     error!(?source, "Error receiving event");
@@ -175,6 +707,10 @@ impl DiscordBot {
                         }
                     });
                 }
+                Event::ReactionRemove(reaction) => self.handle_reaction_remove(&reaction),
+                Event::EntitlementCreate(entitlement) => self.entitlements.update(entitlement.0),
+                Event::EntitlementUpdate(entitlement) => self.entitlements.update(entitlement.0),
+                Event::EntitlementDelete(entitlement) => self.entitlements.remove(entitlement.id),
                 Event::Ready(_) => {
                     info!("Discord bot is ready!");
                 }
@@ -191,79 +727,360 @@ impl DiscordBot {
 
         // Check if this is an auto-embed channel
         if let Some(guild_id) = msg.guild_id {
-            let server_config = self.config.get_server_config(&guild_id.to_string());
-            if server_config.is_auto_embed_channel(&msg.channel_id.to_string()) {
-                for url in self.extract_urls(&msg.content) {
-                    // Skip disabled domains silently
-                    if server_config.is_domain_disabled(&url) {
-                        info!("Skipping disabled domain in auto-embed channel: {}", url);
-                        continue;
-                    }
+            let roles = msg.member.as_ref().map(|member| member.roles.as_slice());
+            if self
+                .config()
+                .blocks_user(Some(guild_id), msg.author.id, roles.unwrap_or_default())
+            {
+                debug!("Ignoring message of blocked user {}", msg.author.id);
+                return Ok(());
+            }
+
+            let server_config = self.config().get_server_config(guild_id);
+            let thread_parent_id = if server_config.auto_embed_thread_parents.is_empty()
+                && !server_config.webhook_repost
+            {
+                None
+            } else {
+                self.thread_parent_id(msg.channel_id).await
+            };
+
+            // Replies go to msg.channel_id, so threads get their embeds in place
+            if server_config.is_auto_embed_target(msg.channel_id, thread_parent_id) {
+                let channel_config = self
+                    .config()
+                    .get_channel_config(Some(guild_id), msg.channel_id);
+                let urls: Vec<String> = links::message_urls(msg)
+                    .into_iter()
+                    .filter(|url| {
+                        // Skip disabled domains silently
+                        if server_config.is_domain_disabled(url)
+                            || !channel_config.is_domain_allowed(url)
+                            || channel_config.skips_auto_embed(url)
+                        {
+                            info!("Skipping disabled domain in auto-embed channel: {}", url);
+                            return false;
+                        }
+                        if self.config().blocks_url(Some(guild_id), url) {
+                            info!("Skipping blocked link in auto-embed channel: {}", url);
+                            return false;
+                        }
+                        self.media_downloader.is_supported_url(url)
+                    })
+                    .take(MAX_URLS)
+                    .collect();
 
-                    if self.media_downloader.is_supported_url(&url) {
-                        match self.media_downloader.download(&url).await {
-                            Ok(media_info) => {
-                                info!("Downloaded media: {}", media_info.metadata.title);
-                                if let Err(e) = self
-                                    .send_media_to_channel(
-                                        &msg.channel_id,
-                                        Some(msg.author.id),
+                let results = join_all(urls.iter().enumerate().map(|(index, url)| {
+                    // The original message can only be reposted once
+                    let repost = server_config.webhook_repost && index == 0;
+                    self.auto_embed_url(
+                        msg,
+                        url,
+                        &server_config,
+                        &channel_config,
+                        thread_parent_id,
+                        repost,
+                    )
+                }))
+                .await;
+
+                // The original message stays around as long as any of its links failed
+                if !results.is_empty() && results.into_iter().all(|embedded| embedded) {
+                    let _ = self.http.delete_message(msg.channel_id, msg.id).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Embeds one link of a message in an auto-embed channel, returning whether it was handled
+    /// so the original message can be removed.
+    async fn auto_embed_url(
+        &self,
+        msg: &MessageCreate,
+        url: &str,
+        server_config: &ServerConfig,
+        channel_config: &ChannelConfig,
+        thread_parent_id: Option<Id<ChannelMarker>>,
+        repost: bool,
+    ) -> bool {
+        let locale = server_config.locale();
+        let guild_id = msg.guild_id;
+        let simulate = self.config().global().get_simulate();
+
+        // Another instance receiving the same message, e.g. during a rolling deploy, embeds it
+        if let Some(cluster) = &self.cluster {
+            let job = format!("embed:{}:{}", msg.id, normalize_url(url));
+            match cluster.try_claim(&job).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!("Skipping {}, another instance is embedding it", url);
+                    return false;
+                }
+                Err(e) => warn!("Failed to claim {}, embedding it anyway: {}", url, e),
+            }
+        }
+
+        let dedup_window = server_config.dedup_window();
+        if dedup_window.is_some() {
+            if let Some(previous) = self.history.find(msg.channel_id.get(), url).await {
+                info!("Skipping repeated link {}, already embedded", url);
+                let _ = self
+                    .http
+                    .create_message(msg.channel_id)
+                    .content(&tf(
+                        locale,
+                        "dedup.already_embedded",
+                        &[("link", &previous.jump_link())],
+                    ))
+                    .reply(msg.id)
+                    .await;
+                return false;
+            }
+        }
+
+        if let Some(mirror) = self.fixed_url(url, channel_config) {
+            info!("Posting {} instead of downloading {}", mirror, url);
+            if simulate.is_some() {
+                let report = tf(
+                    locale,
+                    "simulate.mirror",
+                    &[("mirror", &mirror), ("url", url)],
+                );
+                let _ = self.post_simulation(msg.channel_id, &report).await;
+                return false;
+            }
+            let _ = self
+                .http
+                .create_message(msg.channel_id)
+                .content(&format!("<@{}> {}", msg.author.id, mirror))
+                .await;
+            return true;
+        }
+
+        let roles = msg.member.as_ref().map(|member| member.roles.as_slice());
+        let tier = self.tier_for(
+            guild_id,
+            Some(msg.author.id),
+            roles.unwrap_or_default(),
+            &[],
+        );
+        if let Some(reason) = self.quota_exceeded(guild_id, tier.as_ref(), locale).await {
+            if server_config.failure_notice == FailureNotice::Message {
+                let _ = self
+                    .http
+                    .create_message(msg.channel_id)
+                    .content(&reason)
+                    .reply(msg.id)
+                    .await;
+            } else {
+                self.notify_failure_briefly(msg, server_config, &reason)
+                    .await;
+            }
+            return false;
+        }
+
+        // Held until the upload is done, so the links of a raid are dropped instead of queued
+        let _pending = match channel_config.max_pending {
+            Some(max) => match self.channel_load.try_begin(msg.channel_id.get(), max) {
+                Some(pending) => Some(pending),
+                None => {
+                    info!(
+                        "Skipping {}, {} auto-embeds already in progress in the channel",
+                        url, max
+                    );
+                    return false;
+                }
+            },
+            None => None,
+        };
+
+        let capabilities = self
+            .capabilities_for(guild_id)
+            .with_upload_limit(channel_config.max_upload_bytes())
+            .with_upload_limit(tier.as_ref().and_then(TierConfig::max_upload_bytes));
+        let request = DownloadRequest {
+            audio_only: channel_config.audio_only(),
+            container: channel_config.container(),
+            max_filesize: Some(capabilities.max_upload_bytes),
+            downloader_order: self
+                .config()
+                .get_downloader_order(Some(server_config.server_id)),
+            duration_limit: tier.as_ref().and_then(TierConfig::duration_limit),
+            max_duration_secs: self
+                .config()
+                .get_duration_cap(Some(server_config.server_id)),
+            priority: tier
+                .as_ref()
+                .and_then(|tier| tier.priority)
+                .unwrap_or_default(),
+            ..DownloadRequest::new(url)
+        };
+        let span = info_span!("download", request_id = %request.id, url = %request.url);
+        async {
+            // Simulations leave the original message in place, as nothing replaces it
+            if simulate == Some(SimulateMode::Metadata) {
+                if let Err(failure) = self
+                    .simulate_metadata(
+                        Some(msg.channel_id),
+                        &request,
+                        capabilities.max_upload_bytes,
+                        channel_config,
+                        locale,
+                    )
+                    .await
+                {
+                    let _ = self.post_simulation(msg.channel_id, &failure.reply).await;
+                }
+                return false;
+            }
+
+            match self.download(&request).await {
+                Ok(media_info) => {
+                    info!("Downloaded media: {}", media_info.metadata.title);
+                    if simulate.is_none() {
+                        self.record_quota_usage(guild_id, &media_info).await;
+                    }
+                    let nsfw = media_info
+                        .metadata
+                        .nsfw
+                        .then(|| channel_config.nsfw_policy());
+                    if nsfw == Some(NsfwPolicy::Block) {
+                        info!("Not embedding NSFW media from {}", url);
+                        let _ = self
+                            .http
+                            .create_message(msg.channel_id)
+                            .content(t(locale, "media.nsfw_blocked"))
+                            .reply(msg.id)
+                            .await;
+                        return false;
+                    }
+                    // Falls back to a regular upload if no webhook is available
+                    let repost_as = if repost && simulate.is_none() {
+                        self.reposter.prepare(msg, thread_parent_id).await
+                    } else {
+                        None
+                    };
+                    match self
+                        .send_media_to_channel(
+                            &msg.channel_id,
+                            Some(msg.author.id),
+                            &media_info,
+                            UploadOptions {
+                                message: None,
+                                spoiler: nsfw == Some(NsfwPolicy::Spoiler),
+                                profile: ResizeProfile::Standard,
+                                codec: self.config().global().get_video_codec(),
+                                container: channel_config.container,
+                                audio_only: channel_config.audio_only(),
+                                zip_over: self.config().global().get_gallery_zip_threshold(),
+                                capabilities,
+                                locale,
+                                expires_after: server_config.auto_delete_after(msg.channel_id),
+                                dedup_window,
+                                repost_as,
+                                template: channel_config.template.clone(),
+                                simulated_request: simulate.map(|_| request.id.clone()),
+                                progress: None,
+                                mirror_to: server_config.mirror_channel(msg.channel_id),
+                            },
+                        )
+                        .await
+                    {
+                        Ok(delivery) => {
+                            if simulate.is_none() {
+                                self.send_job_event(guild_id, |guild_id| {
+                                    JobEvent::completed(
+                                        guild_id,
+                                        &request,
                                         &media_info,
-                                        None,
-                                        false,
+                                        delivery.message_url,
+                                        delivery.offloaded_urls,
                                     )
-                                    .await
-                                {
-                                    let error_msg = format!("❌ Failed to send media: {}", e);
-                                    let _ = self
-                                        .http
-                                        .create_message(msg.channel_id)
-                                        .content(&error_msg)
-                                        .await;
-                                    error!("Failed to send media to channel: {}", e);
-                                } else {
-                                    let _ = self.http.delete_message(msg.channel_id, msg.id).await;
-                                }
+                                });
                             }
-                            Err(e) => {
-                                // Check if URL can be transformed (e.g., Instagram -> kkinstagram)
-                                if let Some(transformed_url) =
-                                    self.media_downloader.get_transformed_url(&url)
-                                {
-                                    info!(
-                                        "Download failed, sending transformed URL: {}",
-                                        transformed_url
-                                    );
-                                    let _ = self
-                                        .http
-                                        .create_message(msg.channel_id)
-                                        .content(&format!(
-                                            "<@{}> {}",
-                                            msg.author.id, transformed_url
-                                        ))
-                                        .await;
-                                    let _ = self.http.delete_message(msg.channel_id, msg.id).await;
-                                } else {
-                                    let cleaned_error = clean_error_message(&e);
-                                    let error_msg =
-                                        format!("Failed to download media: `{}`", cleaned_error);
-                                    let _ = self
-                                        .http
-                                        .create_message(msg.channel_id)
-                                        .content(&error_msg)
-                                        .reply(msg.id)
-                                        .await;
-                                    error!("Failed to download media from {}: {}", url, e);
-                                }
+                            simulate.is_none()
+                        }
+                        Err(e) => {
+                            if server_config.failure_notice == FailureNotice::Message {
+                                let error_msg = with_reference(
+                                    locale,
+                                    &tf(locale, "auto.send_failed", &[("error", &e.to_string())]),
+                                    &request.id,
+                                );
+                                let _ = self
+                                    .http
+                                    .create_message(msg.channel_id)
+                                    .content(&error_msg)
+                                    .await;
+                            } else {
+                                self.notify_failure_briefly(
+                                    msg,
+                                    server_config,
+                                    t(locale, "embed.send_failed"),
+                                )
+                                .await;
                             }
+                            error!("Failed to send media to channel: {}", e);
+                            self.report_failure(guild_id, &request, "embed.send_failed")
+                                .await;
+                            false
                         }
-                        break; // Only process the first supported URL
+                    }
+                }
+                Err(e) => {
+                    self.report_failure(guild_id, &request, error_class(&e))
+                        .await;
+                    // Check if URL can be transformed (e.g., Instagram -> kkinstagram)
+                    if let Some(transformed_url) = self.media_downloader.get_transformed_url(url) {
+                        info!(
+                            "Download failed, sending transformed URL: {}",
+                            transformed_url
+                        );
+                        let _ = self
+                            .http
+                            .create_message(msg.channel_id)
+                            .content(&format!("<@{}> {}", msg.author.id, transformed_url))
+                            .await;
+                        true
+                    } else if server_config.failure_notice != FailureNotice::Message {
+                        self.notify_failure_briefly(msg, server_config, t(locale, error_class(&e)))
+                            .await;
+                        error!("Failed to download media from {}: {}", url, e);
+                        false
+                    } else {
+                        let cleaned_error = clean_error_message(&e, locale);
+                        let error_msg = with_reference(
+                            locale,
+                            &tf(locale, "auto.download_failed", &[("error", &cleaned_error)]),
+                            &request.id,
+                        );
+                        let retry = self.retry_components(
+                            Some(&request.id),
+                            EmbedCommandOptions {
+                                url: url.to_string(),
+                                ..Default::default()
+                            },
+                            Some(msg.author.id),
+                            0,
+                            locale,
+                        );
+                        let _ = self
+                            .http
+                            .create_message(msg.channel_id)
+                            .content(&error_msg)
+                            .components(&retry)
+                            .reply(msg.id)
+                            .await;
+                        error!("Failed to download media from {}: {}", url, e);
+                        false
                     }
                 }
             }
         }
-
-        Ok(())
+        .instrument(span)
+        .await
     }
 
     async fn handle_reaction_add(&self, reaction: &ReactionAdd) -> Result<()> {
@@ -275,8 +1092,23 @@ impl DiscordBot {
                     return Ok(());
                 }
 
-                // Skip if the message was not posted by the bot user
+                // Webhook reposts don't mention the author, so they are looked up instead
                 if reaction.message_author_id != Some(self.user_id) {
+                    let Some(author) = self.reposter.author_of(reaction.message_id.get()).await
+                    else {
+                        return Ok(());
+                    };
+                    if author == reaction.user_id.get() {
+                        if let Err(e) = self
+                            .http
+                            .delete_message(reaction.channel_id, reaction.message_id)
+                            .await
+                        {
+                            error!("Failed to delete repost: {}", e);
+                        }
+                    } else {
+                        self.vote_to_delete(reaction).await;
+                    }
                     return Ok(());
                 }
 
@@ -300,6 +1132,8 @@ impl DiscordBot {
                             {
                                 error!("Failed to delete message: {}", e);
                             }
+                        } else {
+                            self.vote_to_delete(reaction).await;
                         }
                     }
                 }
@@ -309,6 +1143,107 @@ impl DiscordBot {
         Ok(())
     }
 
+    /// Counts a ❌ reaction from someone other than the requester towards deleting a bot upload,
+    /// in servers with vote deletion.
+    async fn vote_to_delete(&self, reaction: &ReactionAdd) {
+        if reaction
+            .member
+            .as_ref()
+            .is_some_and(|member| member.user.bot)
+        {
+            return;
+        }
+        let Some(guild_id) = reaction.guild_id else {
+            return;
+        };
+        let Some((threshold, window)) = self.config().get_server_config(guild_id).vote_delete()
+        else {
+            return;
+        };
+
+        if self.delete_votes.vote(
+            reaction.message_id.get(),
+            reaction.user_id.get(),
+            threshold,
+            window,
+            Instant::now(),
+        ) {
+            info!(
+                "{} members voted to delete {}, deleting it",
+                threshold, reaction.message_id
+            );
+            if let Err(e) = self
+                .http
+                .delete_message(reaction.channel_id, reaction.message_id)
+                .await
+            {
+                error!("Failed to delete voted out message: {}", e);
+            }
+        }
+    }
+
+    /// Reports an auto-embed failure without the full error, for servers that chose a quieter
+    /// failure notice.
+    async fn notify_failure_briefly(
+        &self,
+        msg: &MessageCreate,
+        server_config: &ServerConfig,
+        reason: &str,
+    ) {
+        match server_config.failure_notice {
+            FailureNotice::Message | FailureNotice::Silent => {}
+            FailureNotice::React => {
+                let _ = self
+                    .http
+                    .create_reaction(
+                        msg.channel_id,
+                        msg.id,
+                        &RequestReactionType::Unicode { name: "⚠️" },
+                    )
+                    .await;
+            }
+            FailureNotice::Brief => {
+                let content = tf(
+                    server_config.locale(),
+                    "auto.failed_brief",
+                    &[("error", reason)],
+                );
+                let Ok(response) = self
+                    .http
+                    .create_message(msg.channel_id)
+                    .content(&content)
+                    .reply(msg.id)
+                    .await
+                else {
+                    return;
+                };
+                let Ok(notice) = response.model().await else {
+                    return;
+                };
+                if let Err(e) = self
+                    .expiry
+                    .schedule(
+                        msg.guild_id.map(|id| id.get()),
+                        Some(msg.author.id.get()),
+                        notice.channel_id.get(),
+                        notice.id.get(),
+                        BRIEF_NOTICE_LIFETIME,
+                    )
+                    .await
+                {
+                    warn!("Failed to schedule deletion of notice {}: {}", notice.id, e);
+                }
+            }
+        }
+    }
+
+    fn handle_reaction_remove(&self, reaction: &ReactionRemove) {
+        if matches!(&reaction.emoji, EmojiReactionType::Unicode { name } if name == "❌") {
+            self.delete_votes
+                .retract(reaction.message_id.get(), reaction.user_id.get());
+        }
+    }
+
     #[allow(clippy::single_match)]
     async fn handle_interaction(&self, interaction: &Interaction) -> Result<()> {
         match interaction.kind {
@@ -318,107 +1253,2156 @@ impl DiscordBot {
                         "embed" => {
                             self.handle_embed_command(interaction, data).await?;
                         }
+                        "info" => {
+                            self.handle_info_command(interaction, data).await?;
+                        }
+                        "admin" => {
+                            self.handle_admin_command(interaction, data).await?;
+                        }
+                        "watch" => {
+                            self.handle_watch_command(interaction, data).await?;
+                        }
                         _ => {
                             info!("Unknown command: {}", data.name);
                         }
                     }
                 }
             }
+            InteractionType::ApplicationCommandAutocomplete => {
+                if let Some(InteractionData::ApplicationCommand(data)) = &interaction.data {
+                    if data.name == "embed" {
+                        self.handle_embed_autocomplete(interaction, data).await?;
+                    }
+                }
+            }
+            InteractionType::MessageComponent => {
+                if let Some(InteractionData::MessageComponent(data)) = &interaction.data {
+                    if data.custom_id.starts_with("forget:") {
+                        self.handle_forget_confirmation(interaction, &data.custom_id)
+                            .await?;
+                    } else if let Some(id) = retry::id_from_custom_id(&data.custom_id) {
+                        self.handle_retry(interaction, id).await?;
+                    } else if let Some(requester) =
+                        delete::requester_from_custom_id(&data.custom_id)
+                    {
+                        self.handle_delete_button(interaction, requester).await?;
+                    }
+                }
+            }
             _ => {}
         }
 
         Ok(())
     }
 
-    async fn handle_embed_command(
+    /// Suggests the links the user requested before for the `url` option of `/embed`, and the
+    /// resolutions of the link for the `quality` option.
+    async fn handle_embed_autocomplete(
         &self,
         interaction: &Interaction,
         data: &CommandData,
     ) -> Result<()> {
-        let options = EmbedCommandOptions::from_command_data(data);
-
-        if options.url.is_empty() {
-            self.respond_to_interaction(interaction, "Please provide a valid URL.")
-                .await?;
+        let Some((name, typed)) = data.options.iter().find_map(|opt| match &opt.value {
+            CommandOptionValue::Focused(typed, _) => Some((opt.name.as_str(), typed.as_str())),
+            _ => None,
+        }) else {
             return Ok(());
-        }
-
-        if !self.media_downloader.is_supported_url(&options.url) {
-            self.respond_to_interaction(interaction, "This URL is not supported.")
-                .await?;
-            return Ok(());
-        }
-
-        // Acknowledge the interaction and download media concurrently
-        let (ack_result, download_result) = join!(
-            self.respond_to_interaction(interaction, "Downloading media..."),
-            self.media_downloader.download(&options.url)
-        );
+        };
 
-        // Check if acknowledgment failed
+        let choices = match name {
+            "url" => match interaction.author_id() {
+                Some(user_id) => {
+                    let recent = self.requests.recent(user_id.get()).await;
+                    autocomplete::url_choices(typed, &recent)
+                }
+                None => Vec::new(),
+            },
+            "quality" => {
+                let url = EmbedCommandOptions::from_command_data(data)
+                    .urls()
+                    .into_iter()
+                    .next();
+                let formats = match url {
+                    Some(url) => self.probe_formats(interaction, &url).await,
+                    None => Vec::new(),
+                };
+                autocomplete::quality_choices(typed, &formats)
+            }
+            _ => Vec::new(),
+        };
+
+        let response = InteractionResponse {
+            kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .choices(choices.into_iter().map(|choice| CommandOptionChoice {
+                        name: choice.clone(),
+                        name_localizations: None,
+                        value: CommandOptionChoiceValue::String(choice),
+                    }))
+                    .build(),
+            ),
+        };
+        self.http
+            .interaction(self.application_id)
+            .create_response(interaction.id, &interaction.token, &response)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Formats of a link, from the metadata cache if it was looked at recently. Empty if they
+    /// can't be known in time, leaving the suggestions to common resolutions.
+    async fn probe_formats(&self, interaction: &Interaction, url: &str) -> Vec<String> {
+        if !self.media_downloader.is_supported_url(url) {
+            return Vec::new();
+        }
+        let Some(probe) = self.format_probes.try_begin(url) else {
+            return Vec::new();
+        };
+
+        let request = DownloadRequest {
+            downloader_order: self.config().get_downloader_order(interaction.guild_id),
+            ..DownloadRequest::new(url)
+        };
+        let downloader = self.media_downloader.clone();
+        let span = info_span!("probe_formats", request_id = %request.id, url = %request.url);
+        let metadata = tokio::spawn(
+            async move {
+                let _probe = probe;
+                downloader.metadata(&request).await
+            }
+            .instrument(span),
+        );
+        match tokio::time::timeout(FORMAT_PROBE_WAIT, metadata).await {
+            Ok(Ok(Ok(metadata))) => metadata.formats,
+            Ok(Ok(Err(e))) => {
+                debug!("Failed to probe formats of {}: {}", url, e);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    async fn handle_admin_command(
+        &self,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> Result<()> {
+        let locale = self.locale_for(interaction);
+
+        if let Some(command) = data
+            .options
+            .first()
+            .and_then(|opt| OwnerCommand::from_name(&opt.name))
+        {
+            return self
+                .handle_owner_command(interaction, command, locale)
+                .await;
+        }
+
+        let Some(guild_id) = interaction.guild_id else {
+            self.respond_to_interaction(interaction, t(locale, "admin.guild_only"))
+                .await?;
+            return Ok(());
+        };
+
+        if !self
+            .ensure_can_configure(interaction, guild_id, locale)
+            .await?
+        {
+            return Ok(());
+        }
+
+        if let Some(command) = SettingsCommand::from_command_data(data) {
+            return self
+                .handle_settings_command(interaction, guild_id, command, locale)
+                .await;
+        }
+
+        if data
+            .options
+            .first()
+            .is_some_and(|opt| opt.name == "history")
+        {
+            let entries = self.audit.recent(guild_id.get(), HISTORY_ENTRIES).await;
+            let content = if entries.is_empty() {
+                t(locale, "admin.history_empty").to_string()
+            } else {
+                let mut lines = vec![t(locale, "admin.history").to_string()];
+                lines.extend(entries.iter().map(AuditEntry::render));
+                lines.join("\n")
+            };
+            return self.respond_to_interaction(interaction, &content).await;
+        }
+
+        let Some(target) = ForgetTarget::from_command_data(data, guild_id.get()) else {
+            info!("Unknown admin subcommand");
+            return Ok(());
+        };
+
+        let prompt = match target {
+            ForgetTarget::Guild { .. } => t(locale, "admin.forget_confirm_guild").to_string(),
+            ForgetTarget::User { user_id, .. } => tf(
+                locale,
+                "admin.forget_confirm_user",
+                &[("user", &format!("<@{user_id}>"))],
+            ),
+        };
+
+        let buttons = Component::ActionRow(ActionRow {
+            id: None,
+            components: vec![
+                confirmation_button(
+                    target.custom_id(),
+                    t(locale, "admin.confirm"),
+                    ButtonStyle::Danger,
+                ),
+                confirmation_button(
+                    "forget:cancel".to_string(),
+                    t(locale, "admin.cancel"),
+                    ButtonStyle::Secondary,
+                ),
+            ],
+        });
+
+        let response = InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .content(prompt)
+                    .components([buttons])
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            ),
+        };
+
+        self.http
+            .interaction(self.application_id)
+            .create_response(interaction.id, &interaction.token, &response)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adds, removes or lists the creator feeds a server watches.
+    async fn handle_watch_command(
+        &self,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> Result<()> {
+        let locale = self.locale_for(interaction);
+        let Some(guild_id) = interaction.guild_id else {
+            self.respond_to_interaction(interaction, t(locale, "admin.guild_only"))
+                .await?;
+            return Ok(());
+        };
+
+        if !self
+            .ensure_can_configure(interaction, guild_id, locale)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let Some(command) = WatchCommand::from_command_data(data) else {
+            info!("Unknown watch subcommand");
+            return Ok(());
+        };
+
+        match command {
+            WatchCommand::Add { url, channel_id } => {
+                let Some(channel_id) = channel_id
+                    .or_else(|| interaction.channel.as_ref().map(|channel| channel.id.get()))
+                else {
+                    return self
+                        .respond_to_interaction(interaction, t(locale, "embed.no_channel"))
+                        .await;
+                };
+                let Ok(feed) = Feed::detect(&url) else {
+                    return self
+                        .respond_to_interaction(interaction, t(locale, "watch.invalid_url"))
+                        .await;
+                };
+
+                // Reading the feed can take longer than an interaction may go unanswered
+                let timeouts = self.config().global().get_timeouts();
+                let (ack_result, entries) = join!(
+                    self.respond_to_interaction(interaction, t(locale, "watch.checking")),
+                    feed.entries(&timeouts)
+                );
+                ack_result?;
+
+                let content = match entries {
+                    Ok(entries) => {
+                        let max = self.config().get_max_watches(guild_id);
+                        let channel = format!("<#{channel_id}>");
+                        // Only posts published from now on are embedded
+                        let seen = entries.into_iter().map(|entry| entry.id).collect();
+                        match self
+                            .watches
+                            .add(
+                                guild_id.get(),
+                                channel_id,
+                                &url,
+                                interaction.author_id().map(Id::get),
+                                seen,
+                                max,
+                            )
+                            .await?
+                        {
+                            Added::New(subscription) => {
+                                info!(%guild_id, id = subscription.id, %url, "Watching feed");
+                                tf(
+                                    locale,
+                                    "watch.added",
+                                    &[
+                                        ("url", &url),
+                                        ("id", &subscription.id.to_string()),
+                                        ("channel", &channel),
+                                    ],
+                                )
+                            }
+                            Added::Duplicate(id) => tf(
+                                locale,
+                                "watch.duplicate",
+                                &[("channel", &channel), ("id", &id.to_string())],
+                            ),
+                            Added::LimitReached => {
+                                tf(locale, "watch.limit", &[("max", &max.to_string())])
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to read feed {}: {:#}", url, e);
+                        tf(locale, "watch.feed_failed", &[("url", &url)])
+                    }
+                };
+
+                self.http
+                    .interaction(self.application_id)
+                    .update_response(&interaction.token)
+                    .content(Some(&content))
+                    .await?;
+                Ok(())
+            }
+            WatchCommand::Remove { id } => {
+                let content = match self.watches.remove(guild_id.get(), id).await? {
+                    Some(subscription) => {
+                        info!(%guild_id, id, url = %subscription.url, "Stopped watching feed");
+                        tf(locale, "watch.removed", &[("url", &subscription.url)])
+                    }
+                    None => tf(locale, "watch.not_found", &[("id", &id.to_string())]),
+                };
+                self.respond_to_interaction(interaction, &content).await
+            }
+            WatchCommand::List => {
+                let subscriptions = self.watches.for_guild(guild_id.get()).await;
+                let content = if subscriptions.is_empty() {
+                    t(locale, "watch.list_empty").to_string()
+                } else {
+                    let mut lines = vec![t(locale, "watch.list").to_string()];
+                    lines.extend(subscriptions.iter().map(Subscription::render));
+                    lines.join("\n")
+                };
+                self.respond_to_interaction(interaction, &content).await
+            }
+        }
+    }
+
+    /// Changes a server setting and saves it, so it survives restarts and config reloads. Listing
+    /// the blocklist changes nothing.
+    async fn handle_settings_command(
+        &self,
+        interaction: &Interaction,
+        guild_id: Id<GuildMarker>,
+        command: SettingsCommand,
+        locale: Locale,
+    ) -> Result<()> {
+        let change = command.describe();
+        info!(%guild_id, %change, "Changed server settings");
+        let (config, mut content) = match command {
+            SettingsCommand::AutoEmbed {
+                channel_id,
+                enabled,
+            } => {
+                let config = self
+                    .config()
+                    .set_auto_embed_channel(guild_id, channel_id, enabled);
+                let key = if enabled {
+                    "admin.auto_embed_on"
+                } else {
+                    "admin.auto_embed_off"
+                };
+                let channel = format!("<#{channel_id}>");
+                (config, tf(locale, key, &[("channel", &channel)]))
+            }
+            SettingsCommand::EmbedCommand { enabled } => {
+                let config = self.config().set_enabled(guild_id, enabled);
+                let key = if enabled {
+                    "admin.embed_command_on"
+                } else {
+                    "admin.embed_command_off"
+                };
+                (config, t(locale, key).to_string())
+            }
+            SettingsCommand::Channel {
+                channel_id,
+                overrides,
+                reset,
+            } => {
+                let config = self
+                    .config()
+                    .set_channel_config(guild_id, channel_id, *overrides, reset);
+                let channel = format!("<#{channel_id}>");
+                let content = match config.channels.get(&channel_id) {
+                    Some(settings) => tf(
+                        locale,
+                        "admin.channel_settings",
+                        &[("channel", &channel), ("settings", &settings.summary())],
+                    ),
+                    None => tf(locale, "admin.channel_reset", &[("channel", &channel)]),
+                };
+                (config, content)
+            }
+            SettingsCommand::Block { entry, blocked } => {
+                if let BlockEntry::Pattern(pattern) = &entry {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        info!(%guild_id, "Rejected invalid blocked URL pattern: {}", e);
+                        let content = tf(locale, "admin.invalid_pattern", &[("pattern", pattern)]);
+                        return self.respond_to_interaction(interaction, &content).await;
+                    }
+                }
+                let config = self.config().set_blocked(guild_id, &entry, blocked);
+                let key = if blocked {
+                    "admin.blocked"
+                } else {
+                    "admin.unblocked"
+                };
+                (config, tf(locale, key, &[("entry", &entry.to_string())]))
+            }
+            SettingsCommand::BlockList => {
+                // Saving would copy the server's section of the config file into its settings
+                let blocklist = self.config().get_server_config(guild_id).blocklist;
+                let content = if blocklist.is_empty() {
+                    t(locale, "admin.blocklist_empty").to_string()
+                } else {
+                    tf(
+                        locale,
+                        "admin.blocklist",
+                        &[("entries", &blocklist.summary())],
+                    )
+                };
+                return self.respond_to_interaction(interaction, &content).await;
+            }
+            SettingsCommand::JobWebhook { url } => {
+                let host = match url.as_deref().map(admin_webhook_host) {
+                    Some(None) => {
+                        info!(%guild_id, "Rejected invalid job webhook URL");
+                        let content = t(locale, "admin.invalid_webhook");
+                        return self.respond_to_interaction(interaction, content).await;
+                    }
+                    host => host.flatten(),
+                };
+                if let Some(url) = &url {
+                    if let Err(refusal) = resolve_webhook(url).await {
+                        info!(%guild_id, "Rejected job webhook URL: {}", refusal);
+                        let content = t(locale, "admin.unreachable_webhook");
+                        return self.respond_to_interaction(interaction, content).await;
+                    }
+                }
+                let config = self.config().set_job_webhook(guild_id, url);
+                let content = match host {
+                    Some(host) => tf(locale, "admin.job_webhook_set", &[("host", &host)]),
+                    None => t(locale, "admin.job_webhook_cleared").to_string(),
+                };
+                (config, content)
+            }
+            SettingsCommand::Mirror {
+                channel_id,
+                archive,
+            } => {
+                if archive == Some(channel_id) {
+                    let content = t(locale, "admin.mirror_same_channel");
+                    return self.respond_to_interaction(interaction, content).await;
+                }
+                let config = self
+                    .config()
+                    .set_mirror_channel(guild_id, channel_id, archive);
+                let channel = format!("<#{channel_id}>");
+                let content = match archive {
+                    Some(archive) => tf(
+                        locale,
+                        "admin.mirror_set",
+                        &[("channel", &channel), ("archive", &format!("<#{archive}>"))],
+                    ),
+                    None => tf(locale, "admin.mirror_cleared", &[("channel", &channel)]),
+                };
+                (config, content)
+            }
+        };
+
+        let shared = match &self.cluster {
+            Some(cluster) => cluster.save_server_settings(&config).await,
+            None => Ok(()),
+        };
+        if let Err(e) = self.settings.save(config).await.and(shared) {
+            error!("Failed to save settings of server {}: {}", guild_id, e);
+            content.push('\n');
+            content.push_str(t(locale, "admin.settings_not_saved"));
+        }
+        let entry = AuditEntry {
+            guild_id: guild_id.get(),
+            user_id: interaction.author_id().map(Id::get),
+            changed_at: expiry::unix_now(),
+            change,
+        };
+        if let Err(e) = self.audit.record(entry).await {
+            error!(
+                "Failed to record settings change of server {}: {}",
+                guild_id, e
+            );
+        }
+
+        self.respond_to_interaction(interaction, &content).await
+    }
+
+    async fn handle_owner_command(
+        &self,
+        interaction: &Interaction,
+        command: OwnerCommand,
+        locale: Locale,
+    ) -> Result<()> {
+        let user_id = interaction.author_id();
+        if !user_id.is_some_and(|id| self.config().global().is_owner(id)) {
+            info!(
+                ?user_id,
+                ?command,
+                "Denied owner command to user without ownership"
+            );
+            self.respond_to_interaction(interaction, t(locale, "admin.owner_only"))
+                .await?;
+            return Ok(());
+        }
+
+        info!(?user_id, ?command, "Running owner command");
+        let content = match command {
+            OwnerCommand::ReloadConfig => match self.config().reload() {
+                Ok(config) => {
+                    // Settings changed with commands outlive the file they came from
+                    config.apply_server_configs(self.settings.all().await);
+                    self.apply_shared_settings(&config).await;
+                    let servers = config.server_count();
+                    *self.config.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+                    info!(servers, "Reloaded config");
+                    tf(
+                        locale,
+                        "admin.reload_done",
+                        &[("servers", &servers.to_string())],
+                    )
+                }
+                Err(e) => {
+                    warn!("Failed to reload config: {:#}", e);
+                    tf(
+                        locale,
+                        "admin.reload_failed",
+                        &[("error", &format!("{e:#}"))],
+                    )
+                }
+            },
+            OwnerCommand::ToolVersions => {
+                owner::render_tool_versions(&crate::media::tool_versions().await, locale)
+            }
+            OwnerCommand::Stats => BotStats {
+                uptime: self.started_at.elapsed(),
+                servers: self.cache.stats().guilds(),
+                configured_servers: self.config().server_count(),
+                active_downloads: self.media_downloader.active_downloads(),
+                queued_downloads: self.media_downloader.queued_downloads(),
+                scheduled_deletions: self.expiry.pending().await,
+                alive_tasks: RuntimeSnapshot::capture().alive_tasks,
+            }
+            .render(locale),
+            OwnerCommand::Diagnostics => {
+                // Checking connectivity can take longer than an interaction may go unanswered
+                let (ack_result, report) = join!(
+                    self.respond_to_interaction(interaction, t(locale, "diag.running")),
+                    Diagnostics::collect()
+                );
+                ack_result?;
+                self.http
+                    .interaction(self.application_id)
+                    .update_response(&interaction.token)
+                    .content(Some(&owner::render_diagnostics(&report, locale)))
+                    .await?;
+                return Ok(());
+            }
+            OwnerCommand::PurgeCache => {
+                self.cache.clear();
+                let webhooks = self.reposter.clear_webhooks().await;
+                self.media_downloader.release_idle_resources().await;
+                let breakers = self.media_downloader.reset_circuit_breakers();
+                tf(
+                    locale,
+                    "admin.cache_purged",
+                    &[
+                        ("webhooks", &webhooks.to_string()),
+                        ("breakers", &breakers.to_string()),
+                    ],
+                )
+            }
+        };
+
+        self.respond_to_interaction(interaction, &content).await
+    }
+
+    async fn handle_forget_confirmation(
+        &self,
+        interaction: &Interaction,
+        custom_id: &str,
+    ) -> Result<()> {
+        let locale = self.locale_for(interaction);
+
+        if let Some(guild_id) = interaction.guild_id {
+            if !self
+                .ensure_can_configure(interaction, guild_id, locale)
+                .await?
+            {
+                return Ok(());
+            }
+        }
+
+        // Only act on buttons issued for the guild the interaction comes from
+        let content = match ForgetTarget::from_custom_id(custom_id) {
+            Some(target) if interaction.guild_id.map(|id| id.get()) == Some(target.guild_id()) => {
+                match self.forget(target).await {
+                    Ok(summary) => {
+                        info!(
+                            ?target,
+                            removed = summary.total(),
+                            "Removed stored data on request"
+                        );
+                        summary.render(locale)
+                    }
+                    Err(e) => {
+                        error!("Failed to remove stored data for {:?}: {}", target, e);
+                        t(locale, "admin.forget_failed").to_string()
+                    }
+                }
+            }
+            _ => t(locale, "admin.cancelled").to_string(),
+        };
+
+        let response = InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .components([])
+                    .build(),
+            ),
+        };
+
+        self.http
+            .interaction(self.application_id)
+            .create_response(interaction.id, &interaction.token, &response)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Button retrying a failed download, unless there is nothing to retry or it ran out of
+    /// retries.
+    fn retry_components(
+        &self,
+        request_id: Option<&str>,
+        options: EmbedCommandOptions,
+        user_id: Option<Id<UserMarker>>,
+        retries: u32,
+        locale: Locale,
+    ) -> Vec<Component> {
+        let (Some(request_id), Some(user_id)) = (request_id, user_id) else {
+            return Vec::new();
+        };
+        if !self
+            .retries
+            .register(request_id, options, user_id, retries, Instant::now())
+        {
+            return Vec::new();
+        }
+
+        vec![Component::ActionRow(ActionRow {
+            id: None,
+            components: vec![confirmation_button(
+                retry::custom_id(request_id),
+                t(locale, "retry.button"),
+                ButtonStyle::Secondary,
+            )],
+        })]
+    }
+
+    /// Deletes an upload from its delete button, if the member pressing it may.
+    async fn handle_delete_button(
+        &self,
+        interaction: &Interaction,
+        requester: Option<Id<UserMarker>>,
+    ) -> Result<()> {
+        let locale = self.locale_for(interaction);
+        let (Some(user_id), Some(message)) = (interaction.author_id(), &interaction.message) else {
+            return Ok(());
+        };
+
+        let permissions = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .unwrap_or_else(Permissions::empty);
+        if !delete::may_delete(user_id, permissions, requester) {
+            return self
+                .respond_to_interaction(interaction, t(locale, "delete.not_allowed"))
+                .await;
+        }
+
+        let response = InteractionResponse {
+            kind: InteractionResponseType::DeferredUpdateMessage,
+            data: None,
+        };
+        self.http
+            .interaction(self.application_id)
+            .create_response(interaction.id, &interaction.token, &response)
+            .await?;
+        self.http
+            .delete_message(message.channel_id, message.id)
+            .await?;
+        info!("Deleted upload {} on request of {}", message.id, user_id);
+
+        Ok(())
+    }
+
+    /// Runs a failed download again from the retry button on its error message.
+    async fn handle_retry(&self, interaction: &Interaction, id: &str) -> Result<()> {
+        let locale = self.locale_for(interaction);
+        let Some(user_id) = interaction.author_id() else {
+            return Ok(());
+        };
+
+        let job = match self.retries.take(id, user_id, Instant::now()) {
+            Ok(job) => job,
+            Err(denied) => {
+                let content = match denied {
+                    RetryDenied::Unknown => t(locale, "retry.expired").to_string(),
+                    RetryDenied::NotRequester => t(locale, "retry.not_requester").to_string(),
+                    RetryDenied::CoolingDown(wait) => tf(
+                        locale,
+                        "retry.cooldown",
+                        &[("secs", &wait.as_secs().max(1).to_string())],
+                    ),
+                };
+                return self.respond_to_interaction(interaction, &content).await;
+            }
+        };
+        info!(%id, %user_id, retries = job.retries, "Retrying failed download");
+        let _job = self
+            .interaction_jobs
+            .track(interaction.id.get(), Instant::now());
+
+        // The button goes away right away, so the download is not started twice
+        let response = InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(
+                InteractionResponseDataBuilder::new()
+                    .content(t(locale, "retry.retrying"))
+                    .components([])
+                    .build(),
+            ),
+        };
+        self.http
+            .interaction(self.application_id)
+            .create_response(interaction.id, &interaction.token, &response)
+            .await?;
+
+        let dedup_window = interaction
+            .guild_id
+            .and_then(|guild_id| self.config().get_server_config(guild_id).dedup_window());
+        let channel_config = match job.payload.channel_id(interaction) {
+            Some(channel_id) => self
+                .config()
+                .get_channel_config(interaction.guild_id, channel_id),
+            None => ChannelConfig::default(),
+        };
+
+        let url = job.payload.url.clone();
+        let (reporter, progress) = ProgressReporter::channel();
+        let (embed_result, ()) = join!(
+            self.embed_url(
+                interaction,
+                &job.payload,
+                &url,
+                dedup_window,
+                &channel_config,
+                Some(reporter),
+                locale,
+            ),
+            self.show_progress(interaction, progress, locale)
+        );
+        if self.is_abandoned(interaction) {
+            return Ok(());
+        }
+        match embed_result {
+            Ok(()) => {
+                if let Some(message) = &interaction.message {
+                    let _ = self
+                        .http
+                        .delete_message(message.channel_id, message.id)
+                        .await;
+                }
+            }
+            Err(failure) => {
+                let retry = self.retry_components(
+                    failure.retry_id.as_deref(),
+                    job.payload,
+                    Some(user_id),
+                    job.retries + 1,
+                    locale,
+                );
+                let _ = self
+                    .http
+                    .interaction(self.application_id)
+                    .update_response(&interaction.token)
+                    .content(Some(&failure.reply))
+                    .components(Some(&retry))
+                    .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the invoker may change server settings, replying with an ephemeral denial if not.
+    async fn ensure_can_configure(
+        &self,
+        interaction: &Interaction,
+        guild_id: Id<GuildMarker>,
+        locale: Locale,
+    ) -> Result<bool> {
+        let server_config = self.config().get_server_config(guild_id);
+        if permissions::interaction_can_configure(interaction, &server_config) {
+            return Ok(true);
+        }
+
+        info!(
+            user_id = ?interaction.author_id(),
+            %guild_id,
+            "Denied config command to member without permission"
+        );
+        self.respond_to_interaction(interaction, t(locale, "admin.permission_denied"))
+            .await?;
+        Ok(false)
+    }
+
+    /// Purges everything stored about `target` and reports what was removed.
+    async fn forget(&self, target: ForgetTarget) -> Result<ForgetSummary> {
+        let mut summary = ForgetSummary::default();
+
+        // Uploads waiting for expiry are deleted right away instead of being left untracked
+        let deletions = self
+            .expiry
+            .take_where(|d| target.matches(d.guild_id, d.user_id))
+            .await?;
+        for deletion in &deletions {
+            let _ = self
+                .http
+                .delete_message(Id::new(deletion.channel_id), Id::new(deletion.message_id))
+                .await;
+        }
+        summary.add("forget.scheduled_deletions", deletions.len());
+
+        let reposts = self
+            .reposter
+            .take_where(|r| target.matches(r.guild_id, Some(r.user_id)))
+            .await?;
+        summary.add("forget.reposts", reposts.len());
+
+        let embeds = self
+            .history
+            .take_where(|r| target.matches(r.guild_id, r.user_id))
+            .await?;
+        summary.add("forget.embed_history", embeds.len());
+
+        let changes = self
+            .audit
+            .take_where(|e| target.matches(Some(e.guild_id), e.user_id))
+            .await?;
+        summary.add("forget.audit_log", changes.len());
+
+        let watches = self
+            .watches
+            .take_where(|s| target.matches(Some(s.guild_id), s.added_by))
+            .await?;
+        summary.add("forget.watches", watches.len());
+
+        let requests = self
+            .requests
+            .take_where(|r| target.matches(r.guild_id, Some(r.user_id)))
+            .await?;
+        summary.add("forget.requests", requests.len());
+
+        // Cached metadata is keyed by link alone, so all of it goes
+        let metadata = self.media_downloader.clear_metadata_cache();
+        summary.add("forget.metadata_cache", metadata);
+
+        if let ForgetTarget::Guild { guild_id } = target {
+            let guild_id = Id::new(guild_id);
+            let mut removed = self.settings.remove(guild_id).await?;
+            if let Some(cluster) = &self.cluster {
+                removed |= cluster.remove_server_settings(guild_id).await?;
+            }
+            self.config().reset_server_config(guild_id);
+            summary.add("forget.settings", usize::from(removed));
+
+            let usage = self.quota.forget(guild_id.get()).await?;
+            summary.add("forget.quota", usage);
+        }
+
+        Ok(summary)
+    }
+
+    async fn handle_embed_command(
+        &self,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> Result<()> {
+        let options = EmbedCommandOptions::from_command_data(data);
+        let locale = self.locale_for(interaction);
+        let urls = options.urls();
+        let _job = self
+            .interaction_jobs
+            .track(interaction.id.get(), Instant::now());
+
+        let server_config = interaction
+            .guild_id
+            .map(|guild_id| self.config().get_server_config(guild_id));
+        if server_config
+            .as_ref()
+            .is_some_and(|config| !config.embed_enabled)
+        {
+            self.respond_to_interaction(interaction, t(locale, "embed.disabled"))
+                .await?;
+            return Ok(());
+        }
+        if let Some(target) = options.channel {
+            let resolved = data
+                .resolved
+                .as_ref()
+                .and_then(|resolved| resolved.channels.get(&target));
+            let allowed =
+                server_config
+                    .as_ref()
+                    .zip(resolved)
+                    .is_some_and(|(server_config, target)| {
+                        permissions::interaction_can_forward_to(interaction, server_config, target)
+                    });
+            if !allowed {
+                info!(%target, "Refusing to post /embed to another channel");
+                self.respond_to_interaction(
+                    interaction,
+                    &tf(
+                        locale,
+                        "embed.channel_denied",
+                        &[("channel", &format!("<#{target}>"))],
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+        let dedup_window = server_config.and_then(|config| config.dedup_window());
+        let channel_config = match options.channel_id(interaction) {
+            Some(channel_id) => self
+                .config()
+                .get_channel_config(interaction.guild_id, channel_id),
+            None => ChannelConfig::default(),
+        };
+
+        match urls.as_slice() {
+            [] => {
+                self.respond_to_interaction(interaction, t(locale, "embed.invalid_url"))
+                    .await?;
+            }
+            [url] => {
+                if let Err(message) = self
+                    .check_embeddable(
+                        interaction,
+                        url,
+                        options.channel_id(interaction),
+                        dedup_window,
+                        &channel_config,
+                        locale,
+                    )
+                    .await
+                {
+                    self.respond_to_interaction(interaction, &message).await?;
+                    return Ok(());
+                }
+
+                // Acknowledge the interaction and download media concurrently, updating the
+                // acknowledgement with the download's progress
+                let (reporter, progress) = ProgressReporter::channel();
+                let (ack_result, embed_result, ()) = join!(
+                    self.respond_to_interaction(interaction, t(locale, "embed.downloading")),
+                    self.embed_url(
+                        interaction,
+                        &options,
+                        url,
+                        dedup_window,
+                        &channel_config,
+                        Some(reporter),
+                        locale
+                    ),
+                    self.show_progress(interaction, progress, locale)
+                );
+
+                // Check if acknowledgment failed
+                ack_result?;
+
+                if self.is_abandoned(interaction) {
+                    return Ok(());
+                }
+                if let Err(failure) = embed_result {
+                    let retry = self.retry_components(
+                        failure.retry_id.as_deref(),
+                        options.for_url(url),
+                        interaction.author_id(),
+                        0,
+                        locale,
+                    );
+                    let _ = self
+                        .http
+                        .interaction(self.application_id)
+                        .create_followup(&interaction.token)
+                        .content(&failure.reply)
+                        .components(&retry)
+                        .await;
+                }
+            }
+            urls => {
+                self.respond_to_interaction(
+                    interaction,
+                    &tf(
+                        locale,
+                        "embed.downloading_many",
+                        &[("count", &urls.len().to_string())],
+                    ),
+                )
+                .await?;
+
+                let results = join_all(urls.iter().map(|url| async {
+                    self.check_embeddable(
+                        interaction,
+                        url,
+                        options.channel_id(interaction),
+                        dedup_window,
+                        &channel_config,
+                        locale,
+                    )
+                    .await
+                    .map_err(EmbedFailure::new)?;
+                    self.embed_url(
+                        interaction,
+                        &options,
+                        url,
+                        dedup_window,
+                        &channel_config,
+                        None,
+                        locale,
+                    )
+                    .await
+                }))
+                .await;
+
+                let summary = urls
+                    .iter()
+                    .zip(results)
+                    .map(|(url, result)| match result {
+                        Ok(()) => tf(locale, "embed.summary_ok", &[("url", url)]),
+                        Err(failure) => tf(
+                            locale,
+                            "embed.summary_failed",
+                            &[("url", url), ("reason", &failure.reason)],
+                        ),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if !self.is_abandoned(interaction) {
+                    let _ = self.followup_message(interaction, &summary).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replies with the metadata of a link, without downloading its media.
+    async fn handle_info_command(
+        &self,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> Result<()> {
+        let locale = self.locale_for(interaction);
+        let Some(url) = EmbedCommandOptions::from_command_data(data)
+            .urls()
+            .into_iter()
+            .next()
+        else {
+            self.respond_to_interaction(interaction, t(locale, "embed.invalid_url"))
+                .await?;
+            return Ok(());
+        };
+
+        let request = DownloadRequest {
+            downloader_order: self.config().get_downloader_order(interaction.guild_id),
+            ..DownloadRequest::new(&url)
+        };
+        let span = info_span!("info", request_id = %request.id, url = %request.url);
+        let (ack_result, metadata) = join!(
+            self.respond_to_interaction(interaction, t(locale, "info.fetching")),
+            self.media_downloader.metadata(&request).instrument(span)
+        );
         ack_result?;
 
-        // Process the download result
-        match download_result {
-            Ok(media_info) => {
-                info!("Successfully downloaded: {}", media_info.metadata.title);
-
-                if !media_info.files.is_empty() {
-                    // Use the working channel upload method instead of interaction followup
-                    let channel_id = match interaction.channel.as_ref() {
-                        Some(channel) => channel.id,
-                        None => {
-                            error!("No channel information in interaction");
-                            let _ = self
-                                .followup_message(
-                                    interaction,
-                                    "Cannot determine channel for upload",
-                                )
-                                .await;
-                            return Ok(());
-                        }
-                    };
+        let client = self.http.interaction(self.application_id);
+        let response = client.update_response(&interaction.token);
+        match metadata {
+            Ok(metadata) => {
+                response
+                    .content(None)
+                    .embeds(Some(&[info_embed(
+                        &url,
+                        &metadata,
+                        &self.config().get_server_branding(interaction.guild_id),
+                        locale,
+                    )]))
+                    .await?;
+            }
+            Err(e) => {
+                error!("Failed to extract metadata from {}: {}", url, e);
+                let content = with_reference(locale, &clean_error_message(&e, locale), &request.id);
+                response.content(Some(&content)).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a link before downloading it, returning the reply when it should be skipped.
+    async fn check_embeddable(
+        &self,
+        interaction: &Interaction,
+        url: &str,
+        channel_id: Option<Id<ChannelMarker>>,
+        dedup_window: Option<Duration>,
+        channel_config: &ChannelConfig,
+        locale: Locale,
+    ) -> Result<(), String> {
+        if !self.media_downloader.is_supported_url(url) {
+            return Err(t(locale, "embed.unsupported_url").to_string());
+        }
+
+        if !channel_config.is_domain_allowed(url) {
+            return Err(t(locale, "embed.domain_not_allowed").to_string());
+        }
+
+        let config = self.config();
+        let roles = interaction
+            .member
+            .as_ref()
+            .map(|member| member.roles.as_slice());
+        if interaction.author_id().is_some_and(|user_id| {
+            config.blocks_user(interaction.guild_id, user_id, roles.unwrap_or_default())
+        }) {
+            return Err(t(locale, "embed.user_blocked").to_string());
+        }
+        if config.blocks_url(interaction.guild_id, url) {
+            return Err(t(locale, "embed.url_blocked").to_string());
+        }
+
+        if let (Some(_), Some(channel_id)) = (dedup_window, channel_id) {
+            if let Some(previous) = self.history.find(channel_id.get(), url).await {
+                return Err(tf(
+                    locale,
+                    "dedup.already_embedded",
+                    &[("link", &previous.jump_link())],
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Downloads one link of `/embed` and uploads it to the interaction's channel, or the one
+    /// picked with the command.
+    #[allow(clippy::too_many_arguments)]
+    async fn embed_url(
+        &self,
+        interaction: &Interaction,
+        options: &EmbedCommandOptions,
+        url: &str,
+        dedup_window: Option<Duration>,
+        channel_config: &ChannelConfig,
+        progress: Option<ProgressReporter>,
+        locale: Locale,
+    ) -> Result<(), EmbedFailure> {
+        let simulate = options
+            .simulate
+            .or_else(|| self.config().global().get_simulate());
+
+        if let Some(mirror) = self.fixed_url(url, channel_config) {
+            info!("Posting {} instead of downloading {}", mirror, url);
+            let Some(channel_id) = options.channel_id(interaction) else {
+                return Err(EmbedFailure::new(t(locale, "embed.no_channel").to_string()));
+            };
+            let mut content = match interaction.author_id() {
+                _ if simulate.is_some() => tf(
+                    locale,
+                    "simulate.mirror",
+                    &[("mirror", &mirror), ("url", url)],
+                ),
+                Some(user_id) => format!("<@{user_id}> {mirror}"),
+                None => mirror,
+            };
+            if let Some(message) = &options.message {
+                content.push_str(&format!("\n{message}"));
+            }
+            return match self.http.create_message(channel_id).content(&content).await {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    error!("Failed to send mirror link: {}", e);
+                    Err(EmbedFailure::new(
+                        t(locale, "embed.send_failed").to_string(),
+                    ))
+                }
+            };
+        }
+
+        let roles = interaction
+            .member
+            .as_ref()
+            .map(|member| member.roles.as_slice());
+        let tier = self.tier_for(
+            interaction.guild_id,
+            interaction.author_id(),
+            roles.unwrap_or_default(),
+            &interaction.entitlements,
+        );
+        if let Some(reason) = self
+            .quota_exceeded(interaction.guild_id, tier.as_ref(), locale)
+            .await
+        {
+            return Err(EmbedFailure::new(reason));
+        }
+
+        let audio_only = options
+            .audio_only
+            .unwrap_or_else(|| channel_config.audio_only());
+        let capabilities = self
+            .capabilities_for(interaction.guild_id)
+            .with_upload_limit(channel_config.max_upload_bytes());
+        let request = DownloadRequest {
+            chapter: options.chapter.clone(),
+            audio_only,
+            container: channel_config.container(),
+            max_filesize: Some(capabilities.max_upload_bytes),
+            max_height: options.quality,
+            downloader_order: self.config().get_downloader_order(interaction.guild_id),
+            progress,
+            duration_limit: tier.as_ref().and_then(TierConfig::duration_limit),
+            max_duration_secs: self.config().get_duration_cap(interaction.guild_id),
+            class: JobClass::Command,
+            priority: tier
+                .as_ref()
+                .and_then(|tier| tier.priority)
+                .unwrap_or_default(),
+            ..DownloadRequest::new(url)
+        };
+
+        let span = info_span!("download", request_id = %request.id, url = %request.url);
+        async {
+            if simulate == Some(SimulateMode::Metadata) {
+                return self
+                    .simulate_metadata(
+                        options.channel_id(interaction),
+                        &request,
+                        capabilities.max_upload_bytes,
+                        channel_config,
+                        locale,
+                    )
+                    .await;
+            }
+
+            // Dropping the download kills the tools running it and removes their work dirs
+            let Some(download) = self
+                .unless_abandoned(interaction, self.download(&request))
+                .await
+            else {
+                info!("Cancelled download of {}, nobody is waiting for it", url);
+                return Err(EmbedFailure::new(t(locale, "embed.abandoned").to_string()));
+            };
+            let mut media_info = match download {
+                Ok(media_info) => media_info,
+                Err(e) => {
+                    error!("Failed to download media from {}: {}", url, e);
+                    self.report_failure(interaction.guild_id, &request, error_class(&e))
+                        .await;
+                    let reason = format!("{} (`{}`)", clean_error_message(&e, locale), request.id);
+                    return Err(match self.media_downloader.get_transformed_url(url) {
+                        Some(transformed_url) => EmbedFailure {
+                            reason: format!("{reason} → {transformed_url}"),
+                            reply: transformed_url,
+                            retry_id: Some(request.id.clone()),
+                        },
+                        None => EmbedFailure {
+                            reply: url.to_string(),
+                            reason,
+                            retry_id: Some(request.id.clone()),
+                        },
+                    });
+                }
+            };
+            info!("Successfully downloaded: {}", media_info.metadata.title);
+            if simulate.is_none() {
+                self.record_quota_usage(interaction.guild_id, &media_info)
+                    .await;
+                if let Some(user_id) = interaction.author_id() {
+                    let guild_id = interaction.guild_id.map(Id::get);
+                    if let Err(e) = self.requests.record(guild_id, user_id.get(), url).await {
+                        warn!("Failed to record request of {}: {}", url, e);
+                    }
+                }
+            }
+
+            let nsfw = media_info
+                .metadata
+                .nsfw
+                .then(|| channel_config.nsfw_policy());
+            if nsfw == Some(NsfwPolicy::Block) {
+                info!("Not embedding NSFW media from {}", url);
+                return Err(EmbedFailure::new(
+                    t(locale, "media.nsfw_blocked").to_string(),
+                ));
+            }
+
+            if let Some(pick) = &options.pick {
+                let count = media_info.files.len();
+                let indices = crate::media::parse_selection(pick, count).map_err(|_| {
+                    EmbedFailure::new(tf(
+                        locale,
+                        "embed.invalid_pick",
+                        &[("count", &count.to_string())],
+                    ))
+                })?;
+                let mut index = 0;
+                media_info.files.retain(|_| {
+                    index += 1;
+                    indices.contains(&(index - 1))
+                });
+            }
+
+            if let Some(lang) = &options.subtitles {
+                self.burn_in_subtitles(&mut media_info, lang).await;
+            }
+
+            if media_info.files.is_empty() {
+                return Err(EmbedFailure::new(t(locale, "embed.no_files").to_string()));
+            }
+            // Processing in between can't be interrupted, but an upload nobody waits for anymore
+            // isn't started. One already started is finished.
+            if self.is_abandoned(interaction) {
+                info!("Not uploading {}, nobody is waiting for it", url);
+                return Err(EmbedFailure::new(t(locale, "embed.abandoned").to_string()));
+            }
+
+            // Use the working channel upload method instead of interaction followup
+            let Some(channel_id) = options.channel_id(interaction) else {
+                error!("No channel information in interaction");
+                return Err(EmbedFailure::new(t(locale, "embed.no_channel").to_string()));
+            };
+
+            let user_id = interaction
+                .author_id()
+                .or_else(|| interaction.user.as_ref().map(|u| u.id));
+            let delivery = match self
+                .send_media_to_channel(
+                    &channel_id,
+                    user_id,
+                    &media_info,
+                    UploadOptions {
+                        message: options.message.clone(),
+                        spoiler: options.spoiler || nsfw == Some(NsfwPolicy::Spoiler),
+                        profile: options.profile,
+                        codec: options
+                            .codec
+                            .unwrap_or_else(|| self.config().global().get_video_codec()),
+                        // A codec picked for the command wins over the channel's container
+                        container: channel_config.container.filter(|_| options.codec.is_none()),
+                        audio_only,
+                        zip_over: if options.zip {
+                            Some(1)
+                        } else {
+                            self.config().global().get_gallery_zip_threshold()
+                        },
+                        capabilities,
+                        locale,
+                        expires_after: self.expiry_for(interaction.guild_id, channel_id),
+                        dedup_window,
+                        repost_as: None,
+                        template: channel_config.template.clone(),
+                        simulated_request: simulate.map(|_| request.id.clone()),
+                        progress: request.progress.clone(),
+                        mirror_to: None,
+                    },
+                )
+                .await
+            {
+                Ok(delivery) => delivery,
+                Err(e) => {
+                    error!("Failed to send media to channel: {}", e);
+                    self.report_failure(interaction.guild_id, &request, "embed.send_failed")
+                        .await;
+                    return Err(EmbedFailure::new(with_reference(
+                        locale,
+                        t(locale, "embed.send_failed"),
+                        &request.id,
+                    )));
+                }
+            };
+            if simulate.is_none() {
+                self.send_job_event(interaction.guild_id, |guild_id| {
+                    JobEvent::completed(
+                        guild_id,
+                        &request,
+                        &media_info,
+                        delivery.message_url,
+                        delivery.offloaded_urls,
+                    )
+                });
+            }
+
+            Ok(())
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Mirror to post instead of downloading the link, if the channel has its domain fixed.
+    fn fixed_url(&self, url: &str, channel_config: &ChannelConfig) -> Option<String> {
+        if !channel_config.fixes(url) {
+            return None;
+        }
+        self.media_downloader.get_transformed_url(url)
+    }
+
+    /// Shows the download progress in the interaction's response until the download is done, and
+    /// any rate limit holding up its upload after.
+    async fn show_progress(
+        &self,
+        interaction: &Interaction,
+        mut progress: watch::Receiver<Option<Progress>>,
+        locale: Locale,
+    ) {
+        let mut last_update: Option<Instant> = None;
+        while progress.changed().await.is_ok() {
+            let Some(current) = *progress.borrow_and_update() else {
+                continue;
+            };
+            // Response edits are rate limited, updates in between are skipped, unless they tell
+            // why the upload is held up
+            if current.rate_limited.is_none()
+                && last_update.is_some_and(|at| at.elapsed() < PROGRESS_UPDATE_INTERVAL)
+            {
+                continue;
+            }
+            last_update = Some(Instant::now());
 
-                    let user_id = interaction
-                        .author_id()
-                        .or_else(|| interaction.user.as_ref().map(|u| u.id));
-                    if let Err(e) = self
-                        .send_media_to_channel(
-                            &channel_id,
-                            user_id,
-                            &media_info,
-                            options.message,
-                            options.spoiler,
-                        )
-                        .await
-                    {
-                        error!("Failed to send media to channel: {}", e);
-                        let _ = self
-                            .followup_message(interaction, "Failed to send media file")
-                            .await;
+            let content = match current.rate_limited {
+                Some(wait) => tf(
+                    locale,
+                    "embed.rate_limited",
+                    &[("secs", &wait.as_secs().max(1).to_string())],
+                ),
+                None => {
+                    let mut content = tf(
+                        locale,
+                        "embed.progress",
+                        &[("percent", &format!("{:.0}", current.percent))],
+                    );
+                    if let Some(eta) = current.eta {
+                        let eta = crate::utils::format_duration(eta.as_secs());
+                        content.push(' ');
+                        content.push_str(&tf(locale, "embed.progress_eta", &[("eta", &eta)]));
                     }
-                } else {
-                    let _ = self
-                        .followup_message(interaction, "Media processed but no files to send")
-                        .await;
+                    content
+                }
+            };
+            let update = self
+                .http
+                .interaction(self.application_id)
+                .update_response(&interaction.token)
+                .content(Some(&content))
+                .await;
+            if let Err(e) = update {
+                if abandon::is_gone(&e) {
+                    info!("Response to interaction {} is gone", interaction.id);
+                    self.interaction_jobs.abandon(interaction.id.get());
+                    return;
                 }
             }
+        }
+    }
+
+    /// Whether the outcome of the interaction's job can't be shown anymore.
+    fn is_abandoned(&self, interaction: &Interaction) -> bool {
+        self.interaction_jobs
+            .is_abandoned(interaction.id.get(), Instant::now())
+    }
+
+    /// Runs `work` for an interaction, dropping it once nobody can see its outcome anymore.
+    async fn unless_abandoned<T>(
+        &self,
+        interaction: &Interaction,
+        work: impl std::future::Future<Output = T>,
+    ) -> Option<T> {
+        tokio::select! {
+            output = work => Some(output),
+            () = self.interaction_jobs.abandoned(interaction.id.get()) => None,
+        }
+    }
+
+    /// Burns subtitles into every video, leaving files as they are if none can be added.
+    async fn burn_in_subtitles(&self, media_info: &mut crate::media::MediaInfo, lang: &str) {
+        if !media_info.files.iter().any(|file| file.is_video()) {
+            return;
+        }
+
+        let subtitles = match crate::media::fetch_subtitles(&media_info.url, lang).await {
+            Ok(Some(subtitles)) => subtitles,
+            Ok(None) => {
+                info!("No {} subtitles available for {}", lang, media_info.url);
+                return;
+            }
             Err(e) => {
-                error!("Failed to download media from {}: {}", options.url, e);
-                let message = if let Some(transformed_url) =
-                    self.media_downloader.get_transformed_url(&options.url)
+                warn!("Failed to fetch subtitles for {}: {}", media_info.url, e);
+                return;
+            }
+        };
+
+        for file in media_info.files.iter_mut().filter(|file| file.is_video()) {
+            let burn_result = transcode(&media_info.timeouts, {
+                let file_data = file.data.clone();
+                let file_name = file.filename.clone();
+                let subtitles = subtitles.clone();
+                move || crate::media::burn_subtitles(&file_data, &file_name, &subtitles)
+            })
+            .await;
+
+            match burn_result {
+                Ok(Ok(data)) => {
+                    file.filename =
+                        crate::media::transcoded_filename(&file.filename, VideoCodec::H264);
+                    file.data = data;
+                }
+                Ok(Err(e)) => warn!("Failed to burn subtitles into {}: {}", file.filename, e),
+                Err(e) => warn!("Subtitle task failed for {}: {}", file.filename, e),
+            }
+        }
+    }
+
+    /// Returns the parent channel if `channel_id` is a thread or forum post.
+    async fn thread_parent_id(&self, channel_id: Id<ChannelMarker>) -> Option<Id<ChannelMarker>> {
+        if let Some(channel) = self.cache.channel(channel_id) {
+            return channel
+                .kind
+                .is_thread()
+                .then_some(channel.parent_id)
+                .flatten();
+        }
+
+        let channel = self
+            .http
+            .channel(channel_id)
+            .await
+            .ok()?
+            .model()
+            .await
+            .ok()?;
+        channel
+            .kind
+            .is_thread()
+            .then_some(channel.parent_id)
+            .flatten()
+    }
+
+    /// Resolves the upload capabilities of the destination, based on the guild's boost level.
+    fn capabilities_for(&self, guild_id: Option<Id<GuildMarker>>) -> FrontendCapabilities {
+        guild_id
+            .and_then(|id| self.cache.guild(id))
+            .map(|guild| FrontendCapabilities::discord_for_premium_tier(guild.premium_tier()))
+            .unwrap_or_default()
+    }
+
+    /// Resolves how long uploads to `channel_id` are kept, if the guild enabled auto-delete.
+    fn expiry_for(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+    ) -> Option<Duration> {
+        let guild_id = guild_id?;
+        self.config()
+            .get_server_config(guild_id)
+            .auto_delete_after(channel_id)
+    }
+
+    /// Applies server settings other instances changed, keeping the current ones if they can't
+    /// be loaded.
+    async fn apply_shared_settings(&self, config: &ConfigManager) {
+        let Some(cluster) = &self.cluster else {
+            return;
+        };
+        match cluster.server_settings().await {
+            Ok(shared) => config.apply_server_configs(shared),
+            Err(e) => warn!("Failed to load shared server settings: {}", e),
+        }
+    }
+
+    /// Picks up settings changed on other instances, if the bot runs alongside any.
+    fn spawn_settings_sync(&self) {
+        if self.cluster.is_none() {
+            return;
+        }
+        let bot = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(bot.config().global().get_settings_refresh());
+            // The first tick is immediate, and settings were just loaded on startup
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                bot.apply_shared_settings(&bot.config()).await;
+            }
+        });
+    }
+
+    /// Polls the feeds servers watch in the background, embedding what was posted since.
+    fn spawn_watch_worker(&self) {
+        let bot = self.clone();
+        // Feeds of servers on other shards are polled by the instances running those
+        let shard = cluster::shard_from_env().unwrap_or(ShardId::ONE);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(bot.config().global().get_watch_interval());
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                for subscription in bot.watches.all().await {
+                    if (subscription.guild_id >> 22) % u64::from(shard.total())
+                        == u64::from(shard.number())
+                    {
+                        bot.poll_subscription(subscription).await;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn poll_subscription(&self, subscription: Subscription) {
+        let entries = match Feed::detect(&subscription.url) {
+            Ok(feed) => feed.entries(&self.config().global().get_timeouts()).await,
+            Err(e) => Err(e),
+        };
+        match entries {
+            Ok(entries) => self.embed_new_entries(&subscription, entries).await,
+            Err(e) => warn!(
+                "Failed to poll watched feed #{} ({}): {:#}",
+                subscription.id, subscription.url, e
+            ),
+        }
+    }
+
+    /// Embeds the entries of a watched feed it hadn't seen yet, oldest first.
+    async fn embed_new_entries(&self, subscription: &Subscription, entries: Vec<FeedEntry>) {
+        let new: Vec<FeedEntry> = entries
+            .into_iter()
+            .filter(|entry| !subscription.has_seen(&entry.id))
+            .collect();
+        if new.is_empty() {
+            return;
+        }
+
+        // Marked before embedding, so posts that fail aren't retried on every poll
+        let ids = new.iter().map(|entry| entry.id.clone()).collect();
+        if let Err(e) = self.watches.mark_seen(subscription.id, ids).await {
+            error!(
+                "Failed to save seen posts of watched feed #{}: {}",
+                subscription.id, e
+            );
+            return;
+        }
+        info!(
+            "Watched feed #{} has {} new posts",
+            subscription.id,
+            new.len()
+        );
+
+        let guild_id = Id::new(subscription.guild_id);
+        let server_config = self.config().get_server_config(guild_id);
+        // Feeds list the newest post first
+        for entry in new.into_iter().take(MAX_WATCH_POSTS).rev() {
+            if server_config.is_domain_disabled(&entry.url)
+                || self.config().blocks_url(Some(guild_id), &entry.url)
+            {
+                info!("Skipping blocked link of watched feed: {}", entry.url);
+                continue;
+            }
+            self.embed_watched(subscription, &server_config, &entry.url)
+                .await;
+        }
+    }
+
+    /// Embeds a new post of a watched feed into the subscribed channel. Nobody is waiting on it, so
+    /// failures only go to the log channel and the job webhook.
+    async fn embed_watched(
+        &self,
+        subscription: &Subscription,
+        server_config: &ServerConfig,
+        url: &str,
+    ) {
+        let guild_id = Some(server_config.server_id);
+        let channel_id = Id::new(subscription.channel_id);
+        let channel_config = self.config().get_channel_config(guild_id, channel_id);
+        let locale = server_config.locale();
+        let simulate = self.config().global().get_simulate();
+
+        if let Some(cluster) = &self.cluster {
+            let job = format!("watch:{}:{}", subscription.id, normalize_url(url));
+            match cluster.try_claim(&job).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!("Skipping {}, another instance is embedding it", url);
+                    return;
+                }
+                Err(e) => warn!("Failed to claim {}, embedding it anyway: {}", url, e),
+            }
+        }
+
+        let tier = self.tier_for(guild_id, None, &[], &[]);
+        if self
+            .quota_exceeded(guild_id, tier.as_ref(), locale)
+            .await
+            .is_some()
+        {
+            info!(
+                "Skipping {} of watched feed #{}, the server's quota is used up",
+                url, subscription.id
+            );
+            return;
+        }
+
+        let capabilities = self
+            .capabilities_for(guild_id)
+            .with_upload_limit(channel_config.max_upload_bytes())
+            .with_upload_limit(tier.as_ref().and_then(TierConfig::max_upload_bytes));
+        let request = DownloadRequest {
+            audio_only: channel_config.audio_only(),
+            container: channel_config.container(),
+            max_filesize: Some(capabilities.max_upload_bytes),
+            downloader_order: self.config().get_downloader_order(guild_id),
+            duration_limit: tier.as_ref().and_then(TierConfig::duration_limit),
+            max_duration_secs: self.config().get_duration_cap(guild_id),
+            priority: tier
+                .as_ref()
+                .and_then(|tier| tier.priority)
+                .unwrap_or_default(),
+            ..DownloadRequest::new(url)
+        };
+        let span = info_span!("watch", request_id = %request.id, url = %request.url);
+        async {
+            if simulate == Some(SimulateMode::Metadata) {
+                if let Err(failure) = self
+                    .simulate_metadata(
+                        Some(channel_id),
+                        &request,
+                        capabilities.max_upload_bytes,
+                        &channel_config,
+                        locale,
+                    )
+                    .await
                 {
-                    transformed_url
-                } else {
-                    options.url.clone()
-                };
-                let _ = self.followup_message(interaction, &message).await;
+                    let _ = self.post_simulation(channel_id, &failure.reply).await;
+                }
+                return;
+            }
+
+            let media_info = match self.download(&request).await {
+                Ok(media_info) => media_info,
+                Err(e) => {
+                    warn!(
+                        "Failed to download {} of watched feed #{}: {:#}",
+                        url, subscription.id, e
+                    );
+                    self.report_failure(guild_id, &request, error_class(&e))
+                        .await;
+                    return;
+                }
+            };
+            info!("Downloaded media: {}", media_info.metadata.title);
+            if simulate.is_none() {
+                self.record_quota_usage(guild_id, &media_info).await;
+            }
+            let nsfw = media_info
+                .metadata
+                .nsfw
+                .then(|| channel_config.nsfw_policy());
+            if nsfw == Some(NsfwPolicy::Block) {
+                info!("Not embedding NSFW media from {}", url);
+                return;
+            }
+
+            match self
+                .send_media_to_channel(
+                    &channel_id,
+                    None,
+                    &media_info,
+                    UploadOptions {
+                        message: None,
+                        spoiler: nsfw == Some(NsfwPolicy::Spoiler),
+                        profile: ResizeProfile::Standard,
+                        codec: self.config().global().get_video_codec(),
+                        container: channel_config.container,
+                        audio_only: channel_config.audio_only(),
+                        zip_over: self.config().global().get_gallery_zip_threshold(),
+                        capabilities,
+                        locale,
+                        expires_after: server_config.auto_delete_after(channel_id),
+                        dedup_window: server_config.dedup_window(),
+                        repost_as: None,
+                        template: channel_config.template.clone(),
+                        simulated_request: simulate.map(|_| request.id.clone()),
+                        progress: None,
+                        mirror_to: None,
+                    },
+                )
+                .await
+            {
+                Ok(delivery) => {
+                    if simulate.is_none() {
+                        self.send_job_event(guild_id, |guild_id| {
+                            JobEvent::completed(
+                                guild_id,
+                                &request,
+                                &media_info,
+                                delivery.message_url,
+                                delivery.offloaded_urls,
+                            )
+                        });
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to send media to channel: {}", e);
+                    self.report_failure(guild_id, &request, "embed.send_failed")
+                        .await;
+                }
+            }
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Deletes expired uploads in the background, picking up deletions scheduled before a restart.
+    fn spawn_expiry_worker(&self) {
+        let http = self.http.clone();
+        let expiry = self.expiry.clone();
+        let offload = self.offload.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(30));
+            loop {
+                interval.tick().await;
+
+                for deletion in expiry.due(expiry::unix_now()).await {
+                    debug!(
+                        "Deleting expired upload {} in channel {}",
+                        deletion.message_id, deletion.channel_id
+                    );
+                    let result = match http
+                        .delete_message(Id::new(deletion.channel_id), Id::new(deletion.message_id))
+                        .await
+                    {
+                        Ok(_) => expiry.complete(&deletion).await,
+                        // Messages may already be gone (e.g. removed via reaction), which is fine
+                        Err(e) if expiry::is_already_deleted(&e) => {
+                            expiry.complete(&deletion).await
+                        }
+                        Err(e) => {
+                            warn!(
+                                "Failed to delete expired upload {} in channel {}, retrying later: {}",
+                                deletion.message_id, deletion.channel_id, e
+                            );
+                            expiry.retry(&deletion, expiry::unix_now()).await.map(drop)
+                        }
+                    };
+                    if let Err(e) = result {
+                        error!("Failed to update scheduled deletions: {}", e);
+                    }
+                }
+
+                if let Some(store) = &offload {
+                    match store.purge_expired(expiry::unix_now()).await {
+                        Ok(0) => {}
+                        Ok(removed) => info!("Removed {} expired offloaded files", removed),
+                        Err(e) => warn!("Failed to remove expired offloaded files: {}", e),
+                    }
+                }
+            }
+        });
+    }
+
+    /// Posts a short failure notice to the server's and the global log channel, if configured.
+    /// Tier the download is for, through its server, its requester, one of their roles or their
+    /// entitlements. `entitlements` are the interaction's, in addition to the tracked ones.
+    fn tier_for(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        user_id: Option<Id<UserMarker>>,
+        role_ids: &[Id<RoleMarker>],
+        entitlements: &[Entitlement],
+    ) -> Option<TierConfig> {
+        let now = expiry::unix_now();
+        let mut sku_ids = self.entitlements.sku_ids(guild_id, user_id, now);
+        sku_ids.extend(
+            entitlements
+                .iter()
+                .filter(|entitlement| entitlements::is_active(entitlement, now))
+                .map(|entitlement| entitlement.sku_id),
+        );
+        let requester = Requester {
+            guild_id,
+            user_id,
+            role_ids: role_ids.to_vec(),
+            sku_ids,
+        };
+
+        let config = self.config();
+        let tier = config.global().get_tier(&requester)?;
+        debug!("Download is in tier {}", tier.name);
+        Some(tier.clone())
+    }
+
+    /// Notice refusing a download, once the server used up its monthly quota.
+    async fn quota_exceeded(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        tier: Option<&TierConfig>,
+        locale: Locale,
+    ) -> Option<String> {
+        let guild_id = guild_id?;
+        let limits = self.config().get_quota(guild_id, tier);
+        if !limits.is_limited() {
+            return None;
+        }
+
+        let now = expiry::unix_now();
+        let usage = self.quota.usage(guild_id.get(), now).await;
+        if !usage.exhausted(limits.monthly_downloads, limits.monthly_bytes()) {
+            return None;
+        }
+        info!(
+            "Server {} used up its monthly quota ({} downloads, {} bytes)",
+            guild_id, usage.downloads, usage.bytes
+        );
+        Some(tf(
+            locale,
+            "quota.exceeded",
+            &[("reset", &quota::next_month_start(now).to_string())],
+        ))
+    }
+
+    /// Counts a download towards the monthly quota of its server.
+    async fn record_quota_usage(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        media_info: &crate::media::MediaInfo,
+    ) {
+        let Some(guild_id) = guild_id else {
+            return;
+        };
+        if let Err(e) = self
+            .quota
+            .record(guild_id.get(), media_info.total_bytes(), expiry::unix_now())
+            .await
+        {
+            warn!("Failed to record quota usage of server {}: {}", guild_id, e);
+        }
+    }
+
+    /// Fails with `LowDiskSpace` unless the temp directory's disk can take `needed` more bytes,
+    /// removing expired offloaded files first in case they share it.
+    async fn ensure_disk_space(&self, needed: u64) -> Result<()> {
+        let Some(min_free) = self.config().global().get_min_free_disk_bytes() else {
+            return Ok(());
+        };
+        if check_free_space(min_free, needed).await.is_ok() {
+            return Ok(());
+        }
+
+        if let Some(store) = &self.offload {
+            match store.purge_expired(expiry::unix_now()).await {
+                Ok(0) => {}
+                Ok(removed) => info!(
+                    "Removed {} expired offloaded files to free disk space",
+                    removed
+                ),
+                Err(e) => warn!("Failed to remove expired offloaded files: {}", e),
+            }
+        }
+        Ok(check_free_space(min_free, needed).await?)
+    }
+
+    /// Downloads the request, unless the disk is too full to spool it.
+    async fn download(&self, request: &DownloadRequest) -> Result<crate::media::MediaInfo> {
+        self.ensure_disk_space(0).await?;
+        self.media_downloader.download(request).await
+    }
+
+    /// Posts the outcome of a download to the job webhook of its server, if it registered one.
+    fn send_job_event(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        event: impl FnOnce(u64) -> JobEvent,
+    ) {
+        let Some(guild_id) = guild_id else {
+            return;
+        };
+        if let Some(url) = self.config().get_server_config(guild_id).job_webhook {
+            self.callbacks.send(url, event(guild_id.get()));
+        }
+    }
+
+    async fn report_failure(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        request: &DownloadRequest,
+        error_key: &'static str,
+    ) {
+        self.send_job_event(guild_id, |guild_id| {
+            JobEvent::failed(guild_id, request, error_key)
+        });
+
+        let mut targets = Vec::new();
+        if let Some(guild_id) = guild_id {
+            let server_config = self.config().get_server_config(guild_id);
+            if let Some(channel) = server_config.log_channel {
+                targets.push((channel, server_config.locale()));
+            }
+        }
+        if let Some(channel) = self.config().global().get_log_channel() {
+            if !targets.iter().any(|(target, _)| *target == channel) {
+                targets.push((channel, Locale::default()));
             }
         }
 
+        let domain = url::Url::parse(&request.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "?".to_string());
+
+        for (channel_id, locale) in targets {
+            let notice = tf(
+                locale,
+                "log.failure",
+                &[
+                    ("error", t(locale, error_key)),
+                    ("domain", &domain),
+                    ("id", &request.id),
+                ],
+            );
+            if let Err(e) = self.http.create_message(channel_id).content(&notice).await {
+                warn!("Failed to post to log channel {}: {}", channel_id, e);
+            }
+        }
+    }
+
+    /// Reports what the metadata of a link means for embedding it, without downloading it.
+    async fn simulate_metadata(
+        &self,
+        channel_id: Option<Id<ChannelMarker>>,
+        request: &DownloadRequest,
+        upload_limit: u64,
+        channel_config: &ChannelConfig,
+        locale: Locale,
+    ) -> Result<(), EmbedFailure> {
+        let Some(channel_id) = channel_id else {
+            return Err(EmbedFailure::new(t(locale, "embed.no_channel").to_string()));
+        };
+        let metadata = self.media_downloader.metadata(request).await.map_err(|e| {
+            error!("Failed to extract metadata from {}: {}", request.url, e);
+            EmbedFailure::new(with_reference(
+                locale,
+                &clean_error_message(&e, locale),
+                &request.id,
+            ))
+        })?;
+
+        let report = MetadataReport {
+            url: &request.url,
+            request_id: &request.id,
+            metadata: &metadata,
+            downloader_order: &request.downloader_order,
+            upload_limit,
+            max_duration_secs: request.max_duration_secs,
+            nsfw_policy: channel_config.nsfw_policy(),
+        };
+        self.post_simulation(channel_id, &report.render(locale))
+            .await
+            .map_err(|e| {
+                error!("Failed to post simulation report: {}", e);
+                EmbedFailure::new(t(locale, "embed.send_failed").to_string())
+            })
+    }
+
+    /// Posts the report of a simulated embed in place of its upload.
+    async fn post_simulation(&self, channel_id: Id<ChannelMarker>, report: &str) -> Result<()> {
+        self.http
+            .create_message(channel_id)
+            .content(&FrontendCapabilities::discord().truncate_content(report))
+            .flags(MessageFlags::SUPPRESS_EMBEDS)
+            .await?;
         Ok(())
     }
 
+    /// Uploads a file that can't be sent to Discord to the offload backend, if one is configured.
+    async fn offload_file(&self, filename: &str, data: Vec<u8>) -> Option<StoredMedia> {
+        let store = self.offload.as_ref()?;
+        let ttl = self.config().global().get_offload_ttl();
+
+        match store.upload(filename, data, ttl).await {
+            Ok(stored) => {
+                info!("Offloaded {} to {}", filename, store.name());
+                Some(stored)
+            }
+            Err(e) => {
+                warn!("Failed to offload {} to {}: {}", filename, store.name(), e);
+                None
+            }
+        }
+    }
+
+    /// Resolves the language configured for the guild an interaction came from.
+    fn locale_for(&self, interaction: &Interaction) -> Locale {
+        interaction
+            .guild_id
+            .map(|guild_id| self.config().get_server_config(guild_id).locale())
+            .unwrap_or_default()
+    }
+
     async fn respond_to_interaction(&self, interaction: &Interaction, content: &str) -> Result<()> {
         let response = InteractionResponse {
             kind: InteractionResponseType::ChannelMessageWithSource,
@@ -454,14 +3438,74 @@ impl DiscordBot {
         Ok(())
     }
 
+    /// Caption listing the link and whatever metadata the source provided.
+    fn default_caption(
+        &self,
+        user_id: Option<Id<UserMarker>>,
+        media_info: &crate::media::MediaInfo,
+        locale: Locale,
+    ) -> String {
+        let mut content = if let Some(user_id) = user_id {
+            format!("<@{}>", user_id)
+        } else {
+            "".to_string()
+        };
+
+        content.push_str(&format!("\n{}", media_info.url));
+
+        // Add author if available
+        if let Some(author) = &media_info.metadata.author {
+            content.push('\n');
+            content.push_str(&tf(locale, "media.author", &[("author", author)]));
+        }
+
+        // Add likes if available
+        if let Some(likes) = media_info.metadata.likes {
+            content.push('\n');
+            content.push_str(&tf(
+                locale,
+                "media.likes",
+                &[("likes", &crate::utils::abbreviate_count(likes))],
+            ));
+        }
+
+        // Add title if available
+        if !media_info.metadata.title.is_empty()
+            && media_info.metadata.title != "Unknown Title"
+            && media_info.metadata.title != "Unknown Media"
+        {
+            content.push_str(&format!("\n> {}", media_info.metadata.title,));
+        }
+
+        content
+    }
+
     async fn send_media_to_channel(
         &self,
         channel_id: &Id<ChannelMarker>,
         user_id: Option<twilight_model::id::Id<twilight_model::id::marker::UserMarker>>,
         media_info: &crate::media::MediaInfo,
-        message: Option<String>,
-        spoiler: bool,
-    ) -> Result<()> {
+        options: UploadOptions,
+    ) -> Result<Delivery> {
+        let UploadOptions {
+            message,
+            spoiler,
+            profile,
+            codec,
+            container,
+            audio_only,
+            zip_over,
+            capabilities,
+            locale,
+            expires_after,
+            dedup_window,
+            repost_as,
+            template,
+            simulated_request,
+            progress,
+            mirror_to,
+        } = options;
+
         if media_info.files.is_empty() {
             return Err(anyhow::anyhow!("No files to send"));
         }
@@ -469,7 +3513,10 @@ impl DiscordBot {
         // Create attachments from in-memory files
         let mut attachments = Vec::new();
         let mut oversized_files = Vec::new();
-        let mut attachment_id = 1u64;
+        // Files that still exceed the upload limit after processing
+        let mut unsent = Vec::new();
+        // Sizes of resized files before resizing, by the name they are attached as
+        let mut resized_from = HashMap::new();
 
         for file in &media_info.files {
             let file_size = file.data.len() as u64;
@@ -485,111 +3532,261 @@ impl DiscordBot {
                 continue;
             }
 
-            // Discord has a 10MB file size limit for most servers
+            let is_video = file.is_video();
+            let is_audio = file.is_audio();
+            let codec = container.map_or(codec, |container| container.codec(&file.filename, codec));
+
+            // Audio-only downloads may already be audio, which still gets converted and normalized
+            if audio_only && (is_video || is_audio) {
+                let normalize = self.config().global().get_normalize_audio();
+                let span = info_span!("transcode", file = %file.filename);
+                let audio_result = transcode(&media_info.timeouts, {
+                    let file_data = file.data.clone();
+                    let file_name = file.filename.clone();
+                    move || {
+                        span.in_scope(|| {
+                            crate::media::extract_audio_file(&file_data, &file_name, normalize)
+                        })
+                    }
+                })
+                .await;
+
+                match audio_result {
+                    Ok(Ok(audio_data))
+                        if audio_data.len() as u64 <= capabilities.max_upload_bytes =>
+                    {
+                        let file_name = crate::media::audio_filename(&file.filename);
+                        let file_name = if spoiler {
+                            format!("SPOILER_{}", file_name)
+                        } else {
+                            file_name
+                        };
+                        attachments.push(Attachment::from_bytes(
+                            file_name,
+                            audio_data,
+                            (attachments.len() % capabilities.max_attachments) as u64 + 1,
+                        ));
+                    }
+                    Ok(Ok(audio_data)) => {
+                        warn!(
+                            "Audio of {} is too large, marking as oversized",
+                            file.filename
+                        );
+                        unsent.push((crate::media::audio_filename(&file.filename), audio_data));
+                    }
+                    Ok(Err(e)) => {
+                        warn!("Failed to extract audio from {}: {}", file.filename, e);
+                        oversized_files.push((file.filename.clone(), file_size));
+                    }
+                    Err(e) => {
+                        warn!("Audio task failed for {}: {}", file.filename, e);
+                        oversized_files.push((file.filename.clone(), file_size));
+                    }
+                }
+                continue;
+            }
+
+            // Resize anything above the destination's upload limit, or everything but audio for
+            // tiny output
+            let too_large = file_size > capabilities.max_upload_bytes;
             #[allow(unused_variables)]
-            let (file_data, file_size) = if file_size > 10_000_000 {
-                info!(
-                    "File {} is too large ({} MB), attempting to resize",
-                    file.filename,
-                    file_size as f64 / 1_000_000.0
-                );
-
-                let is_video = file.filename.ends_with(".mp4")
-                    || file.filename.ends_with(".webm")
-                    || file.filename.ends_with(".mov");
+            let (file_data, file_size, base_name) = if too_large
+                || (profile.always_process() && !is_audio)
+            {
+                if too_large {
+                    info!(
+                        "File {} is too large ({} MB), attempting to resize",
+                        file.filename,
+                        file_size as f64 / 1_000_000.0
+                    );
+                } else {
+                    info!(
+                        "Compressing {} ({} MB) for the {:?} profile",
+                        file.filename,
+                        file_size as f64 / 1_000_000.0,
+                        profile
+                    );
+                }
+                // ffmpeg reads the file from and writes its output to the temp directory
+                if let Err(e) = self.ensure_disk_space(file_size.saturating_mul(2)).await {
+                    warn!("Not resizing {}: {}", file.filename, e);
+                    unsent.push((file.filename.clone(), file.data.clone()));
+                    continue;
+                }
 
-                let resize_result = tokio::task::spawn_blocking({
+                let max_size_bytes = capabilities.max_upload_bytes;
+                // Profiles like tiny promise smaller files than the upload limit
+                let size_limit = if is_audio {
+                    max_size_bytes
+                } else {
+                    profile.size_limit(max_size_bytes)
+                };
+                let span = info_span!("transcode", file = %file.filename);
+                let started = Instant::now();
+                let resize_result = transcode(&media_info.timeouts, {
                     let file_data = file.data.clone();
                     let file_name = file.filename.clone();
                     move || {
+                        let _entered = span.enter();
                         if is_video {
-                            crate::media::resize_media_file(&file_data, &file_name, 10)
+                            crate::media::resize_media_file_with_profile(
+                                &file_data,
+                                &file_name,
+                                max_size_bytes,
+                                profile,
+                                codec,
+                            )
+                        } else if is_audio {
+                            crate::media::transcode_audio(
+                                &file_data,
+                                AudioFormat::for_filename(&file_name),
+                                Some(max_size_bytes),
+                            )
                         } else {
-                            crate::media::resize_image_file(&file_data, &file_name, 10)
+                            crate::media::resize_image_file_with_profile(
+                                &file_data,
+                                &file_name,
+                                max_size_bytes,
+                                profile,
+                            )
                         }
                     }
                 })
                 .await;
 
                 match resize_result {
+                    Ok(Ok(resized_data)) if resized_data.len() as u64 > size_limit => {
+                        warn!(
+                            "Resized {} is still too large ({} bytes, limit {})",
+                            file.filename,
+                            resized_data.len(),
+                            size_limit
+                        );
+                        unsent.push((file.filename.clone(), file.data.clone()));
+                        continue;
+                    }
                     Ok(Ok(resized_data)) => {
                         info!(
+                            histogram.transcode_duration_seconds = started.elapsed().as_secs_f64(),
                             "Successfully resized {} from {} to {} bytes",
                             file.filename,
                             file_size,
                             resized_data.len()
                         );
-                        (resized_data.clone(), resized_data.len() as u64)
+                        let base_name = if is_video {
+                            crate::media::transcoded_filename(&file.filename, codec)
+                        } else if is_audio {
+                            AudioFormat::for_filename(&file.filename).filename(&file.filename)
+                        } else {
+                            file.filename.clone()
+                        };
+                        (resized_data.clone(), resized_data.len() as u64, base_name)
                     }
                     Ok(Err(e)) => {
                         warn!(
                             "Failed to resize {}: {}, marking as oversized",
                             file.filename, e
                         );
-                        oversized_files.push((file.filename.clone(), file_size));
+                        unsent.push((file.filename.clone(), file.data.clone()));
                         continue;
                     }
                     Err(e) => {
                         warn!("Resize task failed for {}: {}", file.filename, e);
-                        oversized_files.push((file.filename.clone(), file_size));
+                        unsent.push((file.filename.clone(), file.data.clone()));
                         continue;
                     }
                 }
             } else {
-                (file.data.clone(), file_size)
+                (file.data.clone(), file_size, file.filename.clone())
             };
 
             let file_name = if spoiler {
-                format!("SPOILER_{}", file.filename)
+                format!("SPOILER_{}", base_name)
             } else {
-                file.filename.clone()
+                base_name
             };
+            if file_size != file.data.len() as u64 {
+                resized_from.insert(file_name.clone(), file.data.len() as u64);
+            }
 
-            let attachment = Attachment::from_bytes(file_name, file_data, attachment_id);
-            attachment_id += 1;
+            // Attachment ids only need to be unique within a single message
+            let attachment = Attachment::from_bytes(
+                file_name,
+                file_data,
+                (attachments.len() % capabilities.max_attachments) as u64 + 1,
+            );
 
             attachments.push(attachment);
         }
 
-        // If all files are oversized, send transformed URL or original URL
-        if attachments.is_empty() && !oversized_files.is_empty() {
-            let url = self
-                .media_downloader
-                .get_transformed_url(&media_info.url)
-                .unwrap_or_else(|| media_info.url.clone());
-            self.http.create_message(*channel_id).content(&url).await?;
-            return Ok(());
+        // Link to what can't be uploaded instead of dropping it, when offloading is set up
+        let mut offloaded = Vec::new();
+        let mut would_offload = Vec::new();
+        for (filename, data) in unsent {
+            let size = data.len() as u64;
+            if simulated_request.is_some() {
+                match self.offload {
+                    Some(_) => would_offload.push((filename, size)),
+                    None => oversized_files.push((filename, size)),
+                }
+                continue;
+            }
+            let span = info_span!("offload", file = %filename);
+            match self.offload_file(&filename, data).instrument(span).await {
+                Some(stored) => offloaded.push((filename, stored)),
+                None => oversized_files.push((filename, size)),
+            }
         }
 
-        // Build message content with metadata
-        let mut content = if let Some(user_id) = user_id {
-            format!("<@{}>", user_id)
-        } else {
-            "".to_string()
-        };
-
-        content.push_str(&format!("\n{}", media_info.url));
-
-        // Add author if available
-        if let Some(author) = &media_info.metadata.author {
-            content.push_str(&format!("\n👤 Author: {author}"));
-        }
+        // Bundle large galleries into a single archive instead of a long run of messages
+        if zip_over.is_some_and(|limit| attachments.len() > limit) {
+            let files = std::mem::take(&mut attachments);
+            let max_bytes = capabilities.max_upload_bytes;
+            let zip_result = tokio::task::spawn_blocking(move || {
+                let archive = crate::media::zip_files(
+                    files
+                        .iter()
+                        .map(|a| (a.filename.as_str(), a.file.as_slice())),
+                    max_bytes,
+                );
+                (files, archive)
+            })
+            .await;
 
-        // Add likes if available
-        if let Some(likes) = media_info.metadata.likes {
-            content.push_str(&format!(
-                "\n❤️ Likes: {}",
-                crate::utils::format_number(likes)
-            ));
+            match zip_result {
+                Ok((files, Ok(archive))) => {
+                    info!(
+                        "Bundled {} files into a {} byte archive",
+                        files.len() - archive.skipped.len(),
+                        archive.data.len()
+                    );
+                    for name in archive.skipped {
+                        let size = files
+                            .iter()
+                            .find(|a| a.filename == name)
+                            .map_or(0, |a| a.file.len() as u64);
+                        oversized_files.push((name, size));
+                    }
+                    let archive_name = if spoiler {
+                        format!("SPOILER_{}.zip", media_info.metadata.id)
+                    } else {
+                        format!("{}.zip", media_info.metadata.id)
+                    };
+                    attachments.push(Attachment::from_bytes(archive_name, archive.data, 1));
+                }
+                Ok((files, Err(e))) => {
+                    warn!("Failed to bundle gallery, sending files instead: {}", e);
+                    attachments = files;
+                }
+                Err(e) => return Err(anyhow::anyhow!("Zip task failed: {}", e)),
+            }
         }
 
-        // Add title if available
-        if !media_info.metadata.title.is_empty()
-            && media_info.metadata.title != "Unknown Title"
-            && media_info.metadata.title != "Unknown Media"
-        {
-            content.push_str(&format!("\n> {}", media_info.metadata.title,));
-        }
+        // Build message content with metadata, unless the channel has its own template
+        let mut content = match &template {
+            Some(template) => render_template(template, user_id, media_info),
+            None => self.default_caption(user_id, media_info, locale),
+        };
 
         // Add user message if provided
         if let Some(message_content) = message {
@@ -598,6 +3795,36 @@ impl DiscordBot {
             }
         }
 
+        if let Some(request_id) = simulated_request {
+            let report = SimulationReport {
+                url: media_info.url.clone(),
+                request_id,
+                attachments: attachments
+                    .iter()
+                    .map(|a| {
+                        let original = resized_from.get(&a.filename).copied();
+                        (a.filename.clone(), a.file.len() as u64, original)
+                    })
+                    .collect(),
+                offloaded: would_offload,
+                skipped: oversized_files,
+                caption: content,
+            };
+            self.post_simulation(*channel_id, &report.render(locale))
+                .await?;
+            return Ok(Delivery::default());
+        }
+
+        // If all files are oversized, send transformed URL or original URL
+        if attachments.is_empty() && offloaded.is_empty() && !oversized_files.is_empty() {
+            let url = self
+                .media_downloader
+                .get_transformed_url(&media_info.url)
+                .unwrap_or_else(|| media_info.url.clone());
+            self.http.create_message(*channel_id).content(&url).await?;
+            return Ok(Delivery::default());
+        }
+
         // Add warning about oversized files if some were skipped
         if !oversized_files.is_empty() {
             let oversized_names = oversized_files
@@ -605,37 +3832,287 @@ impl DiscordBot {
                 .map(|(name, _)| name.as_str())
                 .collect::<Vec<_>>()
                 .join(", ");
-            content.push_str(&format!("\nSkipped oversized files: {oversized_names}"));
+            content.push('\n');
+            content.push_str(&tf(
+                locale,
+                "media.skipped_oversized",
+                &[("files", &oversized_names)],
+            ));
         }
 
-        // Send message with multiple attachments
-        debug!("Sending message with {} attachments", attachments.len());
-        debug!(
-            "Attachment filenames: {:?}",
-            attachments.iter().map(|a| &a.filename).collect::<Vec<_>>()
-        );
+        for (filename, stored) in &offloaded {
+            content.push('\n');
+            content.push_str(&match stored.expires_at {
+                Some(expires_at) => tf(
+                    locale,
+                    "media.offloaded",
+                    &[
+                        ("file", filename),
+                        ("url", &stored.url),
+                        ("expires", &format!("<t:{}:R>", expires_at)),
+                    ],
+                ),
+                None => tf(
+                    locale,
+                    "media.offloaded_permanent",
+                    &[("file", filename), ("url", &stored.url)],
+                ),
+            });
+        }
 
-        let message = self
-            .http
-            .create_message(*channel_id)
-            .content(&content)
-            .attachments(&attachments)
-            .flags(MessageFlags::SUPPRESS_EMBEDS)
-            .await?;
+        // Point out cut-down videos, offering their chapters as an alternative
+        if let Some(secs) = media_info.truncated_to {
+            let secs = secs.to_string();
+            let chapters = media_info
+                .metadata
+                .chapters
+                .iter()
+                .take(10)
+                .map(|chapter| format!("`{}`", chapter.title))
+                .collect::<Vec<_>>()
+                .join(", ");
+            content.push('\n');
+            if chapters.is_empty() {
+                content.push_str(&tf(locale, "media.truncated", &[("secs", &secs)]));
+            } else {
+                content.push_str(&tf(
+                    locale,
+                    "media.truncated_chapters",
+                    &[("secs", &secs), ("chapters", &chapters)],
+                ));
+            }
+        }
 
-        // Add X reaction for easy deletion
-        if let Ok(msg) = message.model().await {
-            let _ = self
-                .http
-                .create_reaction(
-                    msg.channel_id,
-                    msg.id,
-                    &RequestReactionType::Unicode { name: "❌" },
+        // Reposts look like the original message, so they carry its content instead
+        let content = match &repost_as {
+            Some(repost) => repost.content.as_str(),
+            None => content.as_str(),
+        };
+        // Follow-up parts only carry the mention, so reaction deletion keeps working
+        let mention = match repost_as {
+            Some(_) => String::new(),
+            None => user_id.map(|id| format!("<@{id}>")).unwrap_or_default(),
+        };
+
+        let delete_button = [Component::ActionRow(ActionRow {
+            id: None,
+            components: vec![confirmation_button(
+                delete::custom_id(user_id),
+                t(locale, "delete.button"),
+                ButtonStyle::Secondary,
+            )],
+        })];
+
+        let mut delivery = Delivery {
+            message_url: None,
+            offloaded_urls: offloaded
+                .iter()
+                .map(|(_, stored)| stored.url.clone())
+                .collect(),
+        };
+
+        // Split galleries into as many messages as the destination requires,
+        // keeping the caption on the first one only
+        // Offloaded files alone still need a message carrying their links
+        let chunks: Vec<&[Attachment]> = if attachments.is_empty() {
+            vec![&[]]
+        } else {
+            attachments.chunks(capabilities.max_attachments).collect()
+        };
+        let parts = chunks.len();
+        // Each part replies to the one before, so the gallery stays together
+        let mut previous = None;
+        let mut posted = Vec::new();
+        // Parts of other uploads to the channel go before or after all of these
+        let _lane = self.upload_lanes.enter(channel_id.get()).await;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            debug!("Sending message with {} attachments", chunk.len());
+            debug!(
+                "Attachment filenames: {:?}",
+                chunk.iter().map(|a| &a.filename).collect::<Vec<_>>()
+            );
+
+            let chunk_content = if index == 0 { content } else { &mention };
+            let chunk_content = if parts > 1 {
+                let label = tf(
+                    locale,
+                    "media.part",
+                    &[
+                        ("part", &(index + 1).to_string()),
+                        ("parts", &parts.to_string()),
+                    ],
+                );
+                capabilities.label_content(chunk_content, &label)
+            } else {
+                capabilities.truncate_content(chunk_content)
+            };
+            if mirror_to.is_some() {
+                posted.push((chunk_content.clone(), chunk));
+            }
+            let chunk_content = chunk_content.as_str();
+
+            let upload = async {
+                Ok::<_, anyhow::Error>(match &repost_as {
+                    Some(repost) => {
+                        let msg = self.reposter.execute(repost, chunk_content, chunk).await?;
+                        if let Err(e) = self.reposter.record(repost, &msg).await {
+                            warn!("Failed to record webhook repost {}: {}", msg.id, e);
+                        }
+                        Some(msg)
+                    }
+                    None => {
+                        let mut request = self
+                            .http
+                            .create_message(*channel_id)
+                            .content(chunk_content)
+                            .attachments(chunk)
+                            .components(&delete_button)
+                            .flags(MessageFlags::SUPPRESS_EMBEDS);
+                        // Webhooks can't reply, so only the bot's own parts are chained
+                        if let Some(previous) = previous {
+                            request = request.reply(previous).fail_if_not_exists(false);
+                        }
+                        request.await?.model().await.ok()
+                    }
+                })
+            };
+            // Waiting out the rate limit doesn't count towards the upload's timeout
+            let endpoint = match &repost_as {
+                Some(repost) => repost.endpoint(),
+                None => ratelimit::channel_upload(channel_id.get()),
+            };
+            if let Some(wait) = ratelimit::wait_for(self.http.ratelimiter(), endpoint).await {
+                warn!(
+                    "Upload of part {} is rate limited, sending it in {:?}",
+                    index + 1,
+                    wait
+                );
+                if let Some(progress) = &progress {
+                    progress.rate_limited(wait);
+                }
+                tokio::time::sleep(wait).await;
+            }
+            let started = Instant::now();
+            let message = media_info
+                .timeouts
+                .run(
+                    Stage::Upload,
+                    upload.instrument(info_span!(
+                        "upload",
+                        part = index + 1,
+                        attachments = chunk.len()
+                    )),
                 )
-                .await;
+                .await??;
+            info!(
+                histogram.upload_duration_seconds = started.elapsed().as_secs_f64(),
+                "Uploaded {} attachments",
+                chunk.len()
+            );
+
+            previous = message.as_ref().map(|msg| msg.id);
+
+            // Add X reaction for easy deletion
+            if let Some(msg) = message {
+                let guild_id = msg.guild_id.or(repost_as.as_ref().and_then(|r| r.guild_id));
+                if index == 0 {
+                    delivery.message_url = Some(history::jump_link(
+                        guild_id.map(|id| id.get()),
+                        msg.channel_id.get(),
+                        msg.id.get(),
+                    ));
+                }
+                if let Some(window) = dedup_window.filter(|_| index == 0) {
+                    if let Err(e) = self
+                        .history
+                        .record(
+                            guild_id.map(|id| id.get()),
+                            user_id.map(|id| id.get()),
+                            msg.channel_id.get(),
+                            msg.id.get(),
+                            &media_info.url,
+                            window,
+                        )
+                        .await
+                    {
+                        warn!("Failed to record embed {}: {}", msg.id, e);
+                    }
+                }
+
+                let _ = self
+                    .http
+                    .create_reaction(
+                        msg.channel_id,
+                        msg.id,
+                        &RequestReactionType::Unicode { name: "❌" },
+                    )
+                    .await;
+
+                if let Some(after) = expires_after {
+                    if let Err(e) = self
+                        .expiry
+                        .schedule(
+                            guild_id.map(|id| id.get()),
+                            user_id.map(|id| id.get()),
+                            msg.channel_id.get(),
+                            msg.id.get(),
+                            after,
+                        )
+                        .await
+                    {
+                        warn!("Failed to schedule deletion of upload {}: {}", msg.id, e);
+                    }
+                }
+            }
         }
 
-        Ok(())
+        if let Some(archive) = mirror_to {
+            self.mirror_upload(
+                archive,
+                &posted,
+                delivery.message_url.as_deref(),
+                capabilities,
+                locale,
+            )
+            .await;
+        }
+
+        Ok(delivery)
+    }
+
+    /// Posts the parts of a finished upload again in `archive`, the first one linking back to
+    /// the original. Failing to do so leaves the upload itself alone.
+    async fn mirror_upload(
+        &self,
+        archive: Id<ChannelMarker>,
+        parts: &[(String, &[Attachment])],
+        original: Option<&str>,
+        capabilities: FrontendCapabilities,
+        locale: Locale,
+    ) {
+        for (index, (content, attachments)) in parts.iter().enumerate() {
+            let content = match original.filter(|_| index == 0) {
+                Some(link) => capabilities.label_content(
+                    content,
+                    &tf(locale, "media.mirrored_from", &[("link", link)]),
+                ),
+                None => content.clone(),
+            };
+            // Mentions in the caption already pinged in the original channel
+            let result = self
+                .http
+                .create_message(archive)
+                .content(&content)
+                .attachments(attachments)
+                .allowed_mentions(Some(&AllowedMentions::default()))
+                .flags(MessageFlags::SUPPRESS_EMBEDS)
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to mirror upload to channel {}: {}", archive, e);
+                return;
+            }
+        }
+        info!("Mirrored {} parts to channel {}", parts.len(), archive);
     }
 
     fn extract_original_user_from_content(
@@ -653,19 +4130,6 @@ impl DiscordBot {
         }
         None
     }
-
-    fn extract_urls(&self, content: &str) -> Vec<String> {
-        content
-            .split_whitespace()
-            .filter_map(|word| {
-                if word.starts_with("http://") || word.starts_with("https://") {
-                    Some(word.to_string())
-                } else {
-                    None
-                }
-            })
-            .collect()
-    }
 }
 
 pub async fn run() -> Result<()> {
@@ -682,10 +4146,236 @@ pub async fn run_with_config(config: ConfigManager) -> Result<()> {
     bot.run(shard).await
 }
 
+fn confirmation_button(custom_id: String, label: &str, style: ButtonStyle) -> Component {
+    Component::Button(Button {
+        id: None,
+        custom_id: Some(custom_id),
+        disabled: false,
+        emoji: None,
+        label: Some(label.to_string()),
+        style,
+        url: None,
+        sku_id: None,
+    })
+}
+
+impl ForgetTarget {
+    /// Parses `/admin forget guild|user` into the target it applies to.
+    fn from_command_data(data: &CommandData, guild_id: u64) -> Option<Self> {
+        let group = data.options.iter().find(|opt| opt.name == "forget")?;
+        let CommandOptionValue::SubCommandGroup(subcommands) = &group.value else {
+            return None;
+        };
+        let subcommand = subcommands.first()?;
+
+        match (subcommand.name.as_str(), &subcommand.value) {
+            ("guild", _) => Some(Self::Guild { guild_id }),
+            ("user", CommandOptionValue::SubCommand(options)) => {
+                options.iter().find_map(|opt| match opt.value {
+                    CommandOptionValue::User(user_id) if opt.name == "user" => Some(Self::User {
+                        guild_id,
+                        user_id: user_id.get(),
+                    }),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl WatchCommand {
+    /// Parses `/watch add`, `remove` and `list`.
+    fn from_command_data(data: &CommandData) -> Option<Self> {
+        let subcommand = data.options.first()?;
+        let CommandOptionValue::SubCommand(options) = &subcommand.value else {
+            return None;
+        };
+
+        match subcommand.name.as_str() {
+            "add" => Some(Self::Add {
+                url: options.iter().find_map(|opt| match &opt.value {
+                    CommandOptionValue::String(url) if opt.name == "url" => {
+                        Some(url.trim().to_string())
+                    }
+                    _ => None,
+                })?,
+                channel_id: options.iter().find_map(|opt| match opt.value {
+                    CommandOptionValue::Channel(channel_id) if opt.name == "channel" => {
+                        Some(channel_id.get())
+                    }
+                    _ => None,
+                }),
+            }),
+            "remove" => Some(Self::Remove {
+                id: options.iter().find_map(|opt| match opt.value {
+                    CommandOptionValue::Integer(id) if opt.name == "id" => u64::try_from(id).ok(),
+                    _ => None,
+                })?,
+            }),
+            "list" => Some(Self::List),
+            _ => None,
+        }
+    }
+}
+
+impl SettingsCommand {
+    /// Parses `/admin auto-embed`, `/admin embed-command`, `/admin channel`, `/admin block`,
+    /// `/admin job-webhook` and `/admin mirror`.
+    fn from_command_data(data: &CommandData) -> Option<Self> {
+        let subcommand = data.options.first()?;
+        if let CommandOptionValue::SubCommandGroup(subcommands) = &subcommand.value {
+            return match subcommand.name.as_str() {
+                "block" => Self::from_block_subcommand(subcommands.first()?),
+                _ => None,
+            };
+        }
+        let CommandOptionValue::SubCommand(options) = &subcommand.value else {
+            return None;
+        };
+        let boolean = |name: &str| {
+            options.iter().find_map(|opt| match opt.value {
+                CommandOptionValue::Boolean(value) if opt.name == name => Some(value),
+                _ => None,
+            })
+        };
+        let string = |name: &str| {
+            options.iter().find_map(|opt| match &opt.value {
+                CommandOptionValue::String(value) if opt.name == name => Some(value.clone()),
+                _ => None,
+            })
+        };
+        let domains = |name: &str| {
+            string(name).map(|domains| {
+                domains
+                    .split([',', ' '])
+                    .map(|domain| domain.trim().to_lowercase())
+                    .filter(|domain| !domain.is_empty() && domain != "none")
+                    .collect()
+            })
+        };
+        let channel = |name: &str| {
+            options.iter().find_map(|opt| match opt.value {
+                CommandOptionValue::Channel(channel_id) if opt.name == name => Some(channel_id),
+                _ => None,
+            })
+        };
+        let channel_id = channel("channel");
+
+        match subcommand.name.as_str() {
+            "auto-embed" => Some(Self::AutoEmbed {
+                channel_id: channel_id?,
+                enabled: boolean("enabled")?,
+            }),
+            "embed-command" => Some(Self::EmbedCommand {
+                enabled: boolean("enabled")?,
+            }),
+            "channel" => Some(Self::Channel {
+                channel_id: channel_id?,
+                overrides: Box::new(ChannelConfig {
+                    max_upload_mb: options.iter().find_map(|opt| match opt.value {
+                        CommandOptionValue::Integer(mb) if opt.name == "max-size" => {
+                            u64::try_from(mb).ok()
+                        }
+                        _ => None,
+                    }),
+                    template: string("template"),
+                    allowed_domains: domains("allowed-domains"),
+                    audio_only: boolean("audio-only"),
+                    nsfw: string("nsfw").and_then(|name| NsfwPolicy::from_name(&name)),
+                    skip_domains: domains("skip-domains"),
+                    fix_domains: domains("fix-domains"),
+                    max_pending: options.iter().find_map(|opt| match opt.value {
+                        CommandOptionValue::Integer(max) if opt.name == "max-pending" => {
+                            usize::try_from(max).ok()
+                        }
+                        _ => None,
+                    }),
+                    container: string("container")
+                        .and_then(|name| OutputContainer::from_name(&name)),
+                }),
+                reset: boolean("reset").unwrap_or(false),
+            }),
+            "job-webhook" => Some(Self::JobWebhook {
+                url: string("url")
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty() && url != "none"),
+            }),
+            "mirror" => Some(Self::Mirror {
+                channel_id: channel_id?,
+                archive: channel("archive"),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parses `/admin block add`, `remove` and `list`, taking the first of user, role and pattern.
+    fn from_block_subcommand(subcommand: &CommandDataOption) -> Option<Self> {
+        let CommandOptionValue::SubCommand(options) = &subcommand.value else {
+            return None;
+        };
+        let blocked = match subcommand.name.as_str() {
+            "add" => true,
+            "remove" => false,
+            "list" => return Some(Self::BlockList),
+            _ => return None,
+        };
+        let entry = options.iter().find_map(|opt| match &opt.value {
+            CommandOptionValue::User(user_id) if opt.name == "user" => {
+                Some(BlockEntry::User(*user_id))
+            }
+            CommandOptionValue::Role(role_id) if opt.name == "role" => {
+                Some(BlockEntry::Role(*role_id))
+            }
+            CommandOptionValue::String(pattern) if opt.name == "pattern" => {
+                Some(BlockEntry::Pattern(pattern.trim().to_string()))
+            }
+            _ => None,
+        })?;
+        Some(Self::Block { entry, blocked })
+    }
+}
+
+/// Why a link of `/embed` was not embedded.
+struct EmbedFailure {
+    /// Follow-up sent when it was the only link
+    reply: String,
+    /// Reason listed in the summary of several links
+    reason: String,
+    /// Request id of a failed download, which can be retried
+    retry_id: Option<String>,
+}
+
+impl EmbedFailure {
+    fn new(message: String) -> Self {
+        Self {
+            reply: message.clone(),
+            reason: message,
+            retry_id: None,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 struct EmbedCommandOptions {
+    /// One or more links, separated by whitespace or commas
     url: String,
     message: Option<String>,
     spoiler: bool,
+    profile: ResizeProfile,
+    codec: Option<VideoCodec>,
+    /// Unset leaves it to the channel's default
+    audio_only: Option<bool>,
+    subtitles: Option<String>,
+    chapter: Option<String>,
+    zip: bool,
+    pick: Option<String>,
+    /// Tallest video resolution wanted
+    quality: Option<u32>,
+    /// Report what would be embedded instead of uploading it, overriding the global setting
+    simulate: Option<SimulateMode>,
+    /// Channel to post to instead of the interaction's
+    channel: Option<Id<ChannelMarker>>,
 }
 
 impl EmbedCommandOptions {
@@ -693,6 +4383,16 @@ impl EmbedCommandOptions {
         let mut url = String::new();
         let mut message = None;
         let mut spoiler = false;
+        let mut profile = ResizeProfile::Standard;
+        let mut codec = None;
+        let mut audio_only = None;
+        let mut subtitles = None;
+        let mut chapter = None;
+        let mut zip = false;
+        let mut pick = None;
+        let mut quality = None;
+        let mut simulate = None;
+        let mut channel = None;
 
         for opt in &data.options {
             match opt.name.as_str() {
@@ -711,6 +4411,56 @@ impl EmbedCommandOptions {
                         spoiler = *b;
                     }
                 }
+                "size" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        profile = ResizeProfile::from_name(s).unwrap_or_default();
+                    }
+                }
+                "codec" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        codec = VideoCodec::from_name(s);
+                    }
+                }
+                "subtitles" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        subtitles = Some(s.clone());
+                    }
+                }
+                "chapter" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        chapter = Some(s.clone());
+                    }
+                }
+                "zip" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::Boolean(b) = &opt.value {
+                        zip = *b;
+                    }
+                }
+                "pick" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        pick = Some(s.clone());
+                    }
+                }
+                "quality" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        quality = autocomplete::parse_quality(s);
+                    }
+                }
+                "audio" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::Boolean(b) = &opt.value {
+                        audio_only = Some(*b);
+                    }
+                }
+                "simulate" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::String(s) = &opt.value {
+                        simulate = SimulateMode::from_name(s);
+                    }
+                }
+                "channel" => {
+                    if let twilight_model::application::interaction::application_command::CommandOptionValue::Channel(id) = &opt.value {
+                        channel = Some(*id);
+                    }
+                }
                 _ => {}
             }
         }
@@ -719,6 +4469,41 @@ impl EmbedCommandOptions {
             url,
             message,
             spoiler,
+            profile,
+            codec,
+            audio_only,
+            subtitles,
+            chapter,
+            zip,
+            pick,
+            quality,
+            simulate,
+            channel,
+        }
+    }
+
+    /// Channel the media is posted to.
+    fn channel_id(&self, interaction: &Interaction) -> Option<Id<ChannelMarker>> {
+        self.channel
+            .or_else(|| interaction.channel.as_ref().map(|channel| channel.id))
+    }
+
+    /// The same options for just one of the links.
+    fn for_url(&self, url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            ..self.clone()
         }
     }
+
+    /// Links given in the url option, without duplicates and capped at [`MAX_URLS`].
+    fn urls(&self) -> Vec<String> {
+        // Commas inside a link are kept, only the ones starting another link separate them
+        let mut urls = links::extract_urls(&self.url.replace(",http", " http"));
+        urls.truncate(MAX_URLS);
+        urls
+    }
 }
+
+#[cfg(test)]
+mod tests;
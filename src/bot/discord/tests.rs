@@ -0,0 +1,1360 @@
+//! End-to-end tests driving the bot with synthetic gateway events, against a mock downloader and
+//! a local server standing in for Discord's HTTP API.
+
+use super::*;
+use crate::config::QuotaConfig;
+use crate::media::MockDownloader;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tempfile::TempDir;
+use twilight_model::channel::Message;
+
+const GUILD_ID: u64 = 100;
+const CHANNEL_ID: u64 = 200;
+const OTHER_CHANNEL_ID: u64 = 201;
+const AUTHOR_ID: u64 = 300;
+const BOT_USER_ID: u64 = 400;
+const APPLICATION_ID: u64 = 500;
+const MESSAGE_ID: u64 = 600;
+
+const VIDEO_URL: &str = "https://example.com/watch/1";
+const BROKEN_URL: &str = "https://example.com/watch/broken";
+
+/// A request the bot sent to the Discord API.
+#[derive(Debug, Clone)]
+struct ApiRequest {
+    method: Method,
+    path: String,
+    body: String,
+}
+
+impl ApiRequest {
+    /// The JSON body, or the JSON part of a multipart upload.
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or_else(|_| {
+            self.body
+                .split("\r\n\r\n")
+                .find_map(|part| {
+                    let part = part.split("\r\n--").next()?;
+                    serde_json::from_str(part).ok()
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    fn content(&self) -> String {
+        let json = self.json();
+        json["content"]
+            .as_str()
+            .or(json["data"]["content"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+type Recorded = Arc<Mutex<Vec<ApiRequest>>>;
+
+/// Local stand-in for Discord's HTTP API, recording every request and answering with a message
+/// wherever one is expected.
+async fn mock_discord() -> (String, Recorded) {
+    async fn handle(
+        State(recorded): State<Recorded>,
+        method: Method,
+        uri: Uri,
+        body: Bytes,
+    ) -> Response {
+        let path = uri.path().trim_start_matches("/api/v10").to_string();
+        recorded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(ApiRequest {
+                method: method.clone(),
+                path: path.clone(),
+                body: String::from_utf8_lossy(&body).into_owned(),
+            });
+
+        let returns_message = ((method == Method::POST || method == Method::PATCH)
+            && (path.ends_with("/messages")
+                || path.contains("/messages/")
+                || path.starts_with("/webhooks/")))
+            || (method == Method::GET && path.contains("/messages/"));
+        if returns_message && !path.ends_with("/callback") {
+            Json(message_json(
+                MESSAGE_ID + 1,
+                CHANNEL_ID,
+                BOT_USER_ID,
+                true,
+                "",
+            ))
+            .into_response()
+        } else {
+            StatusCode::NO_CONTENT.into_response()
+        }
+    }
+
+    let recorded = Recorded::default();
+    let app = Router::new()
+        .fallback(handle)
+        .with_state(Arc::clone(&recorded));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (addr, recorded)
+}
+
+fn message_json(id: u64, channel_id: u64, author_id: u64, bot: bool, content: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "guild_id": GUILD_ID.to_string(),
+        "author": {
+            "id": author_id.to_string(),
+            "username": "someone",
+            "discriminator": "0000",
+            "avatar": null,
+            "bot": bot
+        },
+        "content": content,
+        "timestamp": "2024-01-01T00:00:00.000000+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "components": [],
+        "pinned": false,
+        "type": 0
+    })
+}
+
+fn message(channel_id: u64, content: &str) -> MessageCreate {
+    MessageCreate(
+        serde_json::from_value::<Message>(message_json(
+            MESSAGE_ID, channel_id, AUTHOR_ID, false, content,
+        ))
+        .unwrap(),
+    )
+}
+
+fn embed_command(url: &str) -> Interaction {
+    embed_command_with(url, &[])
+}
+
+/// `/embed` for `url` with further options, given as `(name, value)` of string options.
+fn embed_command_with(url: &str, extra: &[(&str, &str)]) -> Interaction {
+    let mut options = vec![json!({"name": "url", "type": 3, "value": url})];
+    options.extend(
+        extra
+            .iter()
+            .map(|(name, value)| json!({"name": name, "type": 3, "value": value})),
+    );
+    serde_json::from_value(json!({
+        "id": "700",
+        "application_id": APPLICATION_ID.to_string(),
+        "type": 2,
+        "token": "interaction-token",
+        "guild_id": GUILD_ID.to_string(),
+        "channel": {"id": CHANNEL_ID.to_string(), "type": 0},
+        "user": {
+            "id": AUTHOR_ID.to_string(),
+            "username": "someone",
+            "discriminator": "0000",
+            "avatar": null
+        },
+        "entitlements": [],
+        "authorizing_integration_owners": {},
+        "data": {
+            "id": "800",
+            "name": "embed",
+            "type": 1,
+            "options": options
+        }
+    }))
+    .unwrap()
+}
+
+/// The bot wired to a mock downloader and the mock Discord API.
+struct Harness {
+    bot: DiscordBot,
+    /// Address of the mock Discord API, which records requests to any other path as well
+    addr: String,
+    recorded: Recorded,
+    downloads: Arc<Mutex<Vec<DownloadRequest>>>,
+    _data_dir: TempDir,
+}
+
+impl Harness {
+    async fn new(config: ConfigManager, downloader: MockDownloader) -> Self {
+        let (addr, recorded) = mock_discord().await;
+        let http = Arc::new(
+            HttpClient::builder()
+                .token("test-token".to_string())
+                .proxy(addr.clone(), true)
+                .ratelimiter(None)
+                .build(),
+        );
+
+        let data_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(data_dir.path());
+        let user_id = Id::new(BOT_USER_ID);
+        let downloads = downloader.requests();
+
+        let bot = DiscordBot {
+            http: http.clone(),
+            cache: Arc::new(InMemoryCache::new()),
+            media_downloader: Arc::new(MediaDownloader::with_downloaders(vec![Box::new(
+                downloader,
+            )])),
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            application_id: Id::new(APPLICATION_ID),
+            user_id,
+            heartbeat: Arc::new(Heartbeat::new()),
+            expiry: Arc::new(ExpiryScheduler::open(&storage).await.unwrap()),
+            reposter: Arc::new(
+                WebhookReposter::open(http, user_id, &storage)
+                    .await
+                    .unwrap(),
+            ),
+            history: Arc::new(EmbedHistory::open(&storage).await.unwrap()),
+            settings: Arc::new(ServerSettings::open(&storage).await.unwrap()),
+            audit: Arc::new(AuditLog::open(&storage).await.unwrap()),
+            retries: Arc::new(RetryQueue::new()),
+            delete_votes: Arc::new(DeleteVotes::new()),
+            quota: Arc::new(QuotaTracker::open(&storage).await.unwrap()),
+            channel_load: Arc::new(ChannelLoad::new()),
+            upload_lanes: Arc::new(UploadLanes::new()),
+            entitlements: Arc::new(Entitlements::new()),
+            cluster: None,
+            offload: None,
+            callbacks: Arc::new(JobCallbacks::allowing_private()),
+            watches: Arc::new(WatchList::open(&storage).await.unwrap()),
+            requests: Arc::new(RequestHistory::open(&storage).await.unwrap()),
+            format_probes: FormatProbes::new(),
+            interaction_jobs: Arc::new(InteractionJobs::new()),
+            started_at: Instant::now(),
+        };
+
+        Self {
+            bot,
+            addr,
+            recorded,
+            downloads,
+            _data_dir: data_dir,
+        }
+    }
+
+    fn requests(&self) -> Vec<ApiRequest> {
+        self.recorded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn downloaded_urls(&self) -> Vec<String> {
+        self.downloads
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|req| req.url.clone())
+            .collect()
+    }
+}
+
+/// Backend shared by instances in memory, standing in for Redis.
+#[derive(Default)]
+struct SharedBackend {
+    claims: Mutex<HashSet<String>>,
+    settings: Mutex<HashMap<Id<GuildMarker>, ServerConfig>>,
+}
+
+#[async_trait::async_trait]
+impl Coordinator for SharedBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn try_claim(&self, job: &str) -> Result<bool> {
+        Ok(self.claims.lock().unwrap().insert(job.to_string()))
+    }
+
+    async fn server_settings(&self) -> Result<Vec<ServerConfig>> {
+        Ok(self.settings.lock().unwrap().values().cloned().collect())
+    }
+
+    async fn save_server_settings(&self, config: &ServerConfig) -> Result<()> {
+        self.settings
+            .lock()
+            .unwrap()
+            .insert(config.server_id, config.clone());
+        Ok(())
+    }
+
+    async fn remove_server_settings(&self, guild_id: Id<GuildMarker>) -> Result<bool> {
+        Ok(self.settings.lock().unwrap().remove(&guild_id).is_some())
+    }
+}
+
+fn auto_embed_config() -> ConfigManager {
+    let config = ConfigManager::new();
+    config.set_auto_embed_channel(Id::new(GUILD_ID), Id::new(CHANNEL_ID), true);
+    config
+}
+
+fn video_downloader() -> MockDownloader {
+    MockDownloader::new()
+        .with_media(VIDEO_URL, &[("clip.mp4", b"video data")])
+        .with_error(BROKEN_URL, "Unsupported URL")
+}
+
+#[tokio::test]
+async fn test_auto_embed_uploads_media_and_removes_original() {
+    let harness = Harness::new(auto_embed_config(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, &format!("look {VIDEO_URL}")))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let requests = harness.requests();
+    let upload = requests
+        .iter()
+        .find(|r| r.method == Method::POST && r.path == format!("/channels/{CHANNEL_ID}/messages"))
+        .expect("media was not uploaded");
+    assert!(upload.body.contains("filename=\"clip.mp4\""));
+    assert!(upload.body.contains("video data"));
+    assert!(upload.content().contains(&format!("<@{AUTHOR_ID}>")));
+
+    assert!(requests.iter().any(|r| r.method == Method::PUT
+        && r.path.starts_with(&format!(
+            "/channels/{CHANNEL_ID}/messages/{}/reactions/",
+            MESSAGE_ID + 1
+        ))));
+    assert!(requests.iter().any(|r| r.method == Method::DELETE
+        && r.path == format!("/channels/{CHANNEL_ID}/messages/{MESSAGE_ID}")));
+}
+
+#[tokio::test]
+async fn test_auto_embed_is_mirrored_to_archive() {
+    let config = auto_embed_config();
+    config.set_mirror_channel(
+        Id::new(GUILD_ID),
+        Id::new(CHANNEL_ID),
+        Some(Id::new(OTHER_CHANNEL_ID)),
+    );
+    let harness = Harness::new(config, video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+
+    let requests = harness.requests();
+    let mirrored = requests
+        .iter()
+        .find(|r| {
+            r.method == Method::POST && r.path == format!("/channels/{OTHER_CHANNEL_ID}/messages")
+        })
+        .expect("upload was not mirrored");
+    assert!(mirrored.body.contains("video data"));
+    let original = history::jump_link(Some(GUILD_ID), CHANNEL_ID, MESSAGE_ID + 1);
+    assert!(mirrored.content().ends_with(&tf(
+        Locale::default(),
+        "media.mirrored_from",
+        &[("link", &original)]
+    )));
+    assert_eq!(mirrored.json()["allowed_mentions"]["parse"], json!([]));
+}
+
+#[tokio::test]
+async fn test_auto_embed_failure_offers_retry_and_keeps_original() {
+    let harness = Harness::new(auto_embed_config(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, BROKEN_URL))
+        .await
+        .unwrap();
+
+    let requests = harness.requests();
+    let reply = requests
+        .iter()
+        .find(|r| r.method == Method::POST)
+        .expect("failure was not reported");
+    assert!(reply.json()["components"].to_string().contains("retry"));
+    assert_eq!(
+        reply.json()["message_reference"]["message_id"],
+        MESSAGE_ID.to_string()
+    );
+    assert!(!requests.iter().any(|r| r.method == Method::DELETE));
+}
+
+#[tokio::test]
+async fn test_auto_embed_failure_notice_policies() {
+    let config = auto_embed_config();
+    config.update_server_config(Id::new(GUILD_ID), |server| {
+        server.failure_notice = FailureNotice::React
+    });
+    let harness = Harness::new(config, video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, BROKEN_URL))
+        .await
+        .unwrap();
+
+    let requests = harness.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::PUT);
+    assert!(requests[0].path.starts_with(&format!(
+        "/channels/{CHANNEL_ID}/messages/{MESSAGE_ID}/reactions/"
+    )));
+
+    let config = auto_embed_config();
+    config.update_server_config(Id::new(GUILD_ID), |server| {
+        server.failure_notice = FailureNotice::Brief
+    });
+    let harness = Harness::new(config, video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, BROKEN_URL))
+        .await
+        .unwrap();
+
+    let requests = harness.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].json()["components"].is_null());
+    assert_eq!(
+        requests[0].content(),
+        tf(
+            Locale::default(),
+            "auto.failed_brief",
+            &[("error", t(Locale::default(), "error.unsupported_url"))]
+        )
+    );
+    assert_eq!(harness.bot.expiry.pending().await, 1);
+}
+
+#[tokio::test]
+async fn test_auto_embed_stops_at_monthly_quota() {
+    let config = auto_embed_config();
+    config.update_server_config(Id::new(GUILD_ID), |server| {
+        server.quota = Some(QuotaConfig {
+            monthly_downloads: Some(1),
+            monthly_mb: None,
+        })
+    });
+    let harness = Harness::new(config, video_downloader()).await;
+
+    for _ in 0..2 {
+        harness
+            .bot
+            .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let notice = harness.requests().pop().unwrap();
+    assert!(notice
+        .content()
+        .starts_with("📉 This server used up its monthly download quota"));
+    assert_eq!(
+        harness
+            .bot
+            .quota
+            .usage(GUILD_ID, expiry::unix_now())
+            .await
+            .downloads,
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_auto_embed_skips_links_over_pending_cap() {
+    let other_url = "https://example.com/watch/2";
+    let config = auto_embed_config();
+    config.set_channel_config(
+        Id::new(GUILD_ID),
+        Id::new(CHANNEL_ID),
+        ChannelConfig {
+            max_pending: Some(1),
+            ..Default::default()
+        },
+        false,
+    );
+    let downloader = video_downloader().with_media(other_url, &[("other.mp4", b"video data")]);
+    let harness = Harness::new(config, downloader).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, &format!("{VIDEO_URL} {other_url}")))
+        .await
+        .unwrap();
+
+    // The second link arrived while the first was still uploading
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    assert_eq!(harness.bot.channel_load.pending(CHANNEL_ID), 0);
+    // The original message stays, as not all of its links were embedded
+    assert!(!harness
+        .requests()
+        .iter()
+        .any(|request| request.method == Method::DELETE));
+}
+
+#[tokio::test]
+async fn test_auto_embed_downloads_for_channel_settings() {
+    let config = auto_embed_config();
+    config.set_channel_config(
+        Id::new(GUILD_ID),
+        Id::new(CHANNEL_ID),
+        ChannelConfig {
+            container: Some(OutputContainer::Webm),
+            max_upload_mb: Some(5),
+            ..Default::default()
+        },
+        false,
+    );
+    let harness = Harness::new(config, video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+
+    let downloads: Vec<(OutputContainer, Option<u64>)> = harness
+        .downloads
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|req| (req.container, req.max_filesize))
+        .collect();
+    assert_eq!(downloads, vec![(OutputContainer::Webm, Some(5_000_000))]);
+}
+
+#[tokio::test]
+async fn test_auto_embed_skips_blocked_users_and_links() {
+    let other_url = "https://example.com/watch/2";
+    let config = auto_embed_config();
+    config.set_blocked(
+        Id::new(GUILD_ID),
+        &BlockEntry::Pattern("/watch/2$".to_string()),
+        true,
+    );
+    let downloader = video_downloader().with_media(other_url, &[("other.mp4", b"video data")]);
+    let harness = Harness::new(config, downloader).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, &format!("{VIDEO_URL} {other_url}")))
+        .await
+        .unwrap();
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+
+    harness.bot.config().set_blocked(
+        Id::new(GUILD_ID),
+        &BlockEntry::User(Id::new(AUTHOR_ID)),
+        true,
+    );
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+}
+
+#[tokio::test]
+async fn test_instances_embed_a_shared_message_once() {
+    let backend: Arc<dyn Coordinator> = Arc::new(SharedBackend::default());
+    let mut first = Harness::new(auto_embed_config(), video_downloader()).await;
+    let mut second = Harness::new(auto_embed_config(), video_downloader()).await;
+    first.bot.cluster = Some(Arc::clone(&backend));
+    second.bot.cluster = Some(Arc::clone(&backend));
+
+    let msg = message(CHANNEL_ID, VIDEO_URL);
+    first.bot.handle_message(&msg).await.unwrap();
+    second.bot.handle_message(&msg).await.unwrap();
+
+    assert_eq!(first.downloaded_urls(), vec![VIDEO_URL]);
+    assert!(second.downloaded_urls().is_empty());
+    assert!(second.requests().is_empty());
+
+    // Settings changed on one instance reach the other
+    let config =
+        first
+            .bot
+            .config()
+            .set_auto_embed_channel(Id::new(GUILD_ID), Id::new(CHANNEL_ID), false);
+    backend.save_server_settings(&config).await.unwrap();
+    second.bot.apply_shared_settings(&second.bot.config()).await;
+    assert!(!second
+        .bot
+        .config()
+        .get_server_config(Id::new(GUILD_ID))
+        .is_auto_embed_channel(Id::new(CHANNEL_ID)));
+}
+
+#[tokio::test]
+async fn test_auto_embed_blocks_nsfw_media() {
+    let config = auto_embed_config();
+    config.set_channel_config(
+        Id::new(GUILD_ID),
+        Id::new(CHANNEL_ID),
+        ChannelConfig {
+            nsfw: Some(NsfwPolicy::Block),
+            ..Default::default()
+        },
+        false,
+    );
+    let harness = Harness::new(config, video_downloader().nsfw()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+
+    let requests = harness.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].content(),
+        t(Locale::default(), "media.nsfw_blocked").to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_messages_outside_auto_embed_channels_are_ignored() {
+    let harness = Harness::new(auto_embed_config(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(OTHER_CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+
+    assert!(harness.downloaded_urls().is_empty());
+    assert!(harness.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_embed_command_acknowledges_and_uploads() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command(VIDEO_URL))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let requests = harness.requests();
+    let ack = requests
+        .iter()
+        .find(|r| r.path == "/interactions/700/interaction-token/callback")
+        .expect("interaction was not acknowledged");
+    assert_eq!(
+        ack.content(),
+        t(Locale::default(), "embed.downloading").to_string()
+    );
+    assert!(requests.iter().any(|r| r.method == Method::POST
+        && r.path == format!("/channels/{CHANNEL_ID}/messages")
+        && r.body.contains("video data")));
+}
+
+#[tokio::test]
+async fn test_gallery_parts_are_numbered_and_chained() {
+    const GALLERY_URL: &str = "https://example.com/gallery/1";
+    let names = (1..=12).map(|i| format!("{i}.jpg")).collect::<Vec<_>>();
+    let files = names
+        .iter()
+        .map(|name| (name.as_str(), b"image data".as_slice()))
+        .collect::<Vec<_>>();
+    let harness = Harness::new(
+        ConfigManager::new(),
+        MockDownloader::new().with_media(GALLERY_URL, &files),
+    )
+    .await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command(GALLERY_URL))
+        .await
+        .unwrap();
+
+    let uploads = harness
+        .requests()
+        .into_iter()
+        .filter(|r| {
+            r.method == Method::POST && r.path == format!("/channels/{CHANNEL_ID}/messages")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(uploads.len(), 2);
+    let label = |part: &str| {
+        tf(
+            Locale::default(),
+            "media.part",
+            &[("part", part), ("parts", "2")],
+        )
+    };
+    assert!(uploads[0].content().ends_with(&label("1")));
+    assert!(uploads[0].json()["message_reference"].is_null());
+    assert_eq!(
+        uploads[1].content(),
+        format!("<@{AUTHOR_ID}>\n{}", label("2"))
+    );
+    assert_eq!(
+        uploads[1].json()["message_reference"]["message_id"],
+        (MESSAGE_ID + 1).to_string()
+    );
+}
+
+/// `/embed` being typed, with `focused` the option being completed.
+fn embed_autocomplete(options: Value) -> Interaction {
+    let mut interaction = serde_json::to_value(embed_command("")).unwrap();
+    interaction["type"] = json!(4);
+    interaction["data"]["options"] = options;
+    serde_json::from_value(interaction).unwrap()
+}
+
+#[tokio::test]
+async fn test_embed_autocomplete_suggests_requested_links_and_qualities() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command_with(VIDEO_URL, &[("quality", "720p")]))
+        .await
+        .unwrap();
+    let downloads = harness.downloads.lock().unwrap().clone();
+    assert_eq!(downloads[0].max_height, Some(720));
+
+    harness
+        .bot
+        .handle_interaction(&embed_autocomplete(json!([
+            {"name": "url", "type": 3, "value": "example", "focused": true}
+        ])))
+        .await
+        .unwrap();
+    harness
+        .bot
+        .handle_interaction(&embed_autocomplete(json!([
+            {"name": "url", "type": 3, "value": VIDEO_URL},
+            {"name": "quality", "type": 3, "value": "7", "focused": true}
+        ])))
+        .await
+        .unwrap();
+
+    let choices: Vec<Value> = harness
+        .requests()
+        .iter()
+        .filter(|r| r.path == "/interactions/700/interaction-token/callback")
+        .filter_map(|r| {
+            let body = r.json();
+            (body["type"] == 8).then(|| body["data"]["choices"].clone())
+        })
+        .collect();
+    assert_eq!(
+        choices,
+        vec![
+            json!([{"name": VIDEO_URL, "value": VIDEO_URL}]),
+            json!([{"name": "720p", "value": "720p"}]),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_embed_command_simulation_reports_instead_of_uploading() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command_with(VIDEO_URL, &[("simulate", "full")]))
+        .await
+        .unwrap();
+
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let requests = harness.requests();
+    let report = requests
+        .iter()
+        .find(|r| r.method == Method::POST && r.path == format!("/channels/{CHANNEL_ID}/messages"))
+        .expect("simulation was not reported");
+    assert!(!report.body.contains("video data"));
+    assert!(report.content().contains("nothing was uploaded"));
+    assert!(report.content().contains("`clip.mp4` (10 B)"));
+}
+
+#[tokio::test]
+async fn test_embed_command_metadata_simulation_skips_download() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command_with(VIDEO_URL, &[("simulate", "metadata")]))
+        .await
+        .unwrap();
+
+    // The mock counts the metadata lookup as a request as well
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let requests = harness.requests();
+    let report = requests
+        .iter()
+        .find(|r| r.method == Method::POST && r.path == format!("/channels/{CHANNEL_ID}/messages"))
+        .expect("simulation was not reported");
+    assert!(!report.body.contains("video data"));
+    assert!(report.content().contains("metadata only"));
+    assert!(report.content().contains("Upload limit: "));
+}
+
+#[tokio::test]
+async fn test_embed_command_failure_follows_up_with_retry() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command(BROKEN_URL))
+        .await
+        .unwrap();
+
+    let requests = harness.requests();
+    let followup = requests
+        .iter()
+        .find(|r| r.method == Method::POST && r.path.starts_with("/webhooks/"))
+        .expect("failure was not followed up");
+    assert_eq!(followup.content(), BROKEN_URL);
+    assert!(followup.json()["components"].to_string().contains("retry"));
+}
+
+#[tokio::test]
+async fn test_job_webhook_receives_results() {
+    let harness = Harness::new(auto_embed_config(), video_downloader()).await;
+    let hook = format!("http://{}/job-hook", harness.addr);
+    harness
+        .bot
+        .config()
+        .set_job_webhook(Id::new(GUILD_ID), Some(hook));
+
+    for url in [VIDEO_URL, BROKEN_URL] {
+        harness
+            .bot
+            .handle_message(&message(CHANNEL_ID, url))
+            .await
+            .unwrap();
+    }
+
+    // Events are posted in the background
+    let mut events = Vec::new();
+    for _ in 0..50 {
+        events = harness
+            .requests()
+            .into_iter()
+            .filter(|r| r.path == "/job-hook")
+            .map(|r| r.json())
+            .collect::<Vec<_>>();
+        if events.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(events.len(), 2);
+
+    let completed = events
+        .iter()
+        .find(|event| event["status"] == "completed")
+        .expect("completion was not posted");
+    assert_eq!(completed["url"], VIDEO_URL);
+    assert_eq!(completed["guild_id"], GUILD_ID.to_string());
+    assert_eq!(completed["files"][0]["filename"], "clip.mp4");
+    assert_eq!(
+        completed["message_url"],
+        format!(
+            "https://discord.com/channels/{GUILD_ID}/{CHANNEL_ID}/{}",
+            MESSAGE_ID + 1
+        )
+    );
+
+    let failed = events
+        .iter()
+        .find(|event| event["status"] == "failed")
+        .expect("failure was not posted");
+    assert_eq!(failed["url"], BROKEN_URL);
+    assert!(failed["error"]["class"].is_string());
+}
+
+#[tokio::test]
+async fn test_forget_guild_purges_saved_settings() {
+    let harness = Harness::new(auto_embed_config(), video_downloader()).await;
+    let guild_id = Id::new(GUILD_ID);
+    let config = harness
+        .bot
+        .config()
+        .set_job_webhook(guild_id, Some("https://hooks.example.com/x".to_string()));
+    harness.bot.settings.save(config).await.unwrap();
+    let now = expiry::unix_now();
+    harness.bot.quota.record(GUILD_ID, 100, now).await.unwrap();
+
+    let summary = harness
+        .bot
+        .forget(ForgetTarget::Guild { guild_id: GUILD_ID })
+        .await
+        .unwrap();
+    assert!(summary
+        .render(Locale::En)
+        .contains("- Saved server settings: 1"));
+    assert!(summary.render(Locale::En).contains("- Quota usage: 1"));
+    assert_eq!(harness.bot.quota.usage(GUILD_ID, now).await.downloads, 0);
+
+    assert!(harness.bot.settings.all().await.is_empty());
+    let config = harness.bot.config().get_server_config(guild_id);
+    assert_eq!(config.job_webhook, None);
+    assert!(!config.is_auto_embed_channel(Id::new(CHANNEL_ID)));
+}
+
+#[tokio::test]
+async fn test_watched_feed_embeds_only_new_posts() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+    let Added::New(subscription) = harness
+        .bot
+        .watches
+        .add(
+            GUILD_ID,
+            CHANNEL_ID,
+            "https://example.com/feed.xml",
+            Some(AUTHOR_ID),
+            vec!["old".to_string()],
+            10,
+        )
+        .await
+        .unwrap()
+    else {
+        panic!("subscription was not added");
+    };
+
+    let entry = |id: &str, url: &str| FeedEntry {
+        id: id.to_string(),
+        url: url.to_string(),
+    };
+    let entries = vec![
+        entry("new", VIDEO_URL),
+        entry("old", "https://example.com/watch/old"),
+    ];
+    harness
+        .bot
+        .embed_new_entries(&subscription, entries.clone())
+        .await;
+
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let upload = harness
+        .requests()
+        .into_iter()
+        .find(|r| r.method == Method::POST && r.path == format!("/channels/{CHANNEL_ID}/messages"))
+        .expect("new post was not uploaded");
+    assert!(upload.body.contains("filename=\"clip.mp4\""));
+
+    // The next poll finds nothing new
+    let subscription = harness.bot.watches.all().await.remove(0);
+    assert!(subscription.has_seen("new"));
+    harness.bot.embed_new_entries(&subscription, entries).await;
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+}
+
+#[tokio::test]
+async fn test_embed_command_disabled_in_server() {
+    let config = ConfigManager::new();
+    config.set_enabled(Id::new(GUILD_ID), false);
+    let harness = Harness::new(config, video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_command(VIDEO_URL))
+        .await
+        .unwrap();
+
+    assert!(harness.downloaded_urls().is_empty());
+    let requests = harness.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].content(),
+        t(Locale::default(), "embed.disabled").to_string()
+    );
+}
+
+/// `/embed` posting to the other channel, by a member with `permissions` who can post media there.
+fn embed_to_other_channel(permissions: Permissions) -> Interaction {
+    let mut interaction = serde_json::to_value(embed_command(VIDEO_URL)).unwrap();
+    interaction["member"] = json!({
+        "user": interaction["user"].take(),
+        "roles": [],
+        "joined_at": "2024-01-01T00:00:00.000000+00:00",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+        "permissions": permissions.bits().to_string()
+    });
+    interaction["data"]["options"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({"name": "channel", "type": 7, "value": OTHER_CHANNEL_ID.to_string()}));
+    let postable =
+        Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::ATTACH_FILES;
+    interaction["data"]["resolved"] = json!({"channels": {
+        OTHER_CHANNEL_ID.to_string(): {
+            "id": OTHER_CHANNEL_ID.to_string(),
+            "type": 0,
+            "name": "media",
+            "permissions": postable.bits().to_string()
+        }
+    }});
+    serde_json::from_value(interaction).unwrap()
+}
+
+#[tokio::test]
+async fn test_embed_command_posts_to_picked_channel() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_to_other_channel(Permissions::MANAGE_GUILD))
+        .await
+        .unwrap();
+
+    let uploads = harness
+        .requests()
+        .into_iter()
+        .filter(|r| r.method == Method::POST && r.path.ends_with("/messages"))
+        .map(|r| r.path)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        uploads,
+        vec![format!("/channels/{OTHER_CHANNEL_ID}/messages")]
+    );
+}
+
+#[tokio::test]
+async fn test_embed_command_refuses_channel_for_members() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_interaction(&embed_to_other_channel(Permissions::SEND_MESSAGES))
+        .await
+        .unwrap();
+
+    assert!(harness.downloaded_urls().is_empty());
+    let requests = harness.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].content(),
+        tf(
+            Locale::default(),
+            "embed.channel_denied",
+            &[("channel", &format!("<#{OTHER_CHANNEL_ID}>"))]
+        )
+    );
+}
+
+#[test]
+fn test_timeout_errors_name_the_stage() {
+    let timeout = StageTimeout {
+        stage: Stage::Download,
+        after: Duration::from_secs(120),
+    };
+    let error = anyhow::Error::new(timeout).context("Media download failed");
+
+    assert_eq!(error_class(&error), "error.timeout");
+    assert_eq!(
+        clean_error_message(&error, Locale::default()),
+        tf(
+            Locale::default(),
+            "error.timeout_download",
+            &[("secs", "120")]
+        )
+    );
+    assert_eq!(
+        clean_error_message(&anyhow::anyhow!("Unsupported URL"), Locale::default()),
+        t(Locale::default(), "error.unsupported_url")
+    );
+}
+
+#[tokio::test]
+async fn test_auto_embed_refuses_videos_over_duration_cap() {
+    let config = auto_embed_config();
+    let mut server = config.get_server_config(Id::new(GUILD_ID));
+    server.duration_cap_secs = Some(900);
+    config.apply_server_configs([server]);
+    let harness = Harness::new(config, video_downloader().with_duration(1380)).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+
+    // Only the metadata was looked up
+    assert_eq!(harness.downloaded_urls(), vec![VIDEO_URL]);
+    let requests = harness.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0]
+        .content()
+        .contains("Video is too long (23:00 > 15:00 limit)"));
+}
+
+#[test]
+fn test_live_stream_errors_are_explained() {
+    let error = anyhow::anyhow!(
+        "Media download failed: Unsupported URL. Live streams can't be downloaded until they end"
+    );
+    assert_eq!(error_class(&error), "error.live_stream");
+}
+
+#[test]
+fn test_missing_session_errors_are_explained() {
+    let error = anyhow::anyhow!(
+        "Media download failed: Instagram stories need a logged-in session, configure cookies \
+         for gallery_dl.instagram"
+    );
+    assert_eq!(error_class(&error), "error.session_required");
+}
+
+#[test]
+fn test_info_embed_branding() {
+    let metadata = MediaMetadata {
+        title: "Clip".to_string(),
+        id: "clip".to_string(),
+        thumbnail: None,
+        duration: None,
+        author: None,
+        likes: None,
+        description: None,
+        view_count: None,
+        uploaded_at: None,
+        site: None,
+        format_ext: "mp4".to_string(),
+        chapters: Vec::new(),
+        nsfw: false,
+        live: false,
+        formats: Vec::new(),
+    };
+
+    let embed = info_embed(VIDEO_URL, &metadata, &BrandingConfig::default(), Locale::En);
+    assert_eq!(embed.color, None);
+    assert_eq!(embed.footer.unwrap().text, "via Grabby");
+
+    let branding = BrandingConfig {
+        color: Some("#5865F2".to_string()),
+        footer: Some("My Server".to_string()),
+        credit: Some(false),
+    };
+    let embed = info_embed(VIDEO_URL, &metadata, &branding, Locale::En);
+    assert_eq!(embed.color, Some(0x5865F2));
+    assert_eq!(embed.footer.unwrap().text, "My Server");
+
+    let branding = BrandingConfig {
+        credit: Some(false),
+        ..Default::default()
+    };
+    let embed = info_embed(VIDEO_URL, &metadata, &branding, Locale::En);
+    assert!(embed.footer.is_none());
+}
+
+#[test]
+fn test_info_embed_details() {
+    let metadata = MediaMetadata {
+        title: "Clip".to_string(),
+        id: "clip".to_string(),
+        thumbnail: None,
+        duration: None,
+        author: None,
+        likes: None,
+        description: Some("a".repeat(MAX_INFO_DESCRIPTION + 1)),
+        view_count: Some(1234),
+        uploaded_at: Some(1_709_164_800),
+        site: Some("Youtube".to_string()),
+        format_ext: "mp4".to_string(),
+        chapters: Vec::new(),
+        nsfw: false,
+        live: false,
+        formats: Vec::new(),
+    };
+
+    let embed = info_embed(VIDEO_URL, &metadata, &BrandingConfig::default(), Locale::En);
+    let description = embed.description.unwrap();
+    assert_eq!(description.chars().count(), MAX_INFO_DESCRIPTION + 1);
+    assert!(description.ends_with('…'));
+    let fields: Vec<(&str, &str)> = embed
+        .fields
+        .iter()
+        .map(|field| (field.name.as_str(), field.value.as_str()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("Site", "Youtube"),
+            ("Views", "1.2K"),
+            ("Uploaded", "<t:1709164800:D>"),
+            ("Formats", "mp4"),
+        ]
+    );
+}
+
+fn delete_button_press(user_id: u64, permissions: Permissions) -> Interaction {
+    serde_json::from_value(json!({
+        "id": "701",
+        "application_id": APPLICATION_ID.to_string(),
+        "type": 3,
+        "token": "button-token",
+        "guild_id": GUILD_ID.to_string(),
+        "channel": {"id": CHANNEL_ID.to_string(), "type": 0},
+        "member": {
+            "user": {
+                "id": user_id.to_string(),
+                "username": "someone",
+                "discriminator": "0000",
+                "avatar": null
+            },
+            "roles": [],
+            "joined_at": "2024-01-01T00:00:00.000000+00:00",
+            "deaf": false,
+            "mute": false,
+            "flags": 0,
+            "permissions": permissions.bits().to_string()
+        },
+        "message": message_json(900, CHANNEL_ID, BOT_USER_ID, true, "<@300>"),
+        "entitlements": [],
+        "authorizing_integration_owners": {},
+        "data": {
+            "custom_id": delete::custom_id(Some(Id::new(AUTHOR_ID))),
+            "component_type": 2
+        }
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_uploads_carry_a_delete_button() {
+    let harness = Harness::new(auto_embed_config(), video_downloader()).await;
+
+    harness
+        .bot
+        .handle_message(&message(CHANNEL_ID, VIDEO_URL))
+        .await
+        .unwrap();
+
+    let upload = harness
+        .requests()
+        .into_iter()
+        .find(|r| r.method == Method::POST && r.body.contains("video data"))
+        .expect("media was not uploaded");
+    assert!(upload
+        .body
+        .contains(&delete::custom_id(Some(Id::new(AUTHOR_ID)))));
+}
+
+fn cross_reaction(user_id: u64) -> ReactionAdd {
+    ReactionAdd(
+        serde_json::from_value(json!({
+            "channel_id": CHANNEL_ID.to_string(),
+            "guild_id": GUILD_ID.to_string(),
+            "message_id": "900",
+            "message_author_id": BOT_USER_ID.to_string(),
+            "user_id": user_id.to_string(),
+            "emoji": {"id": null, "name": "❌"},
+            "burst": false,
+            "burst_colors": [],
+            "type": 0
+        }))
+        .unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_enough_cross_reactions_delete_upload() {
+    let config = ConfigManager::new();
+    config.update_server_config(Id::new(GUILD_ID), |server| {
+        server.vote_delete_threshold = Some(2)
+    });
+    let harness = Harness::new(config, video_downloader()).await;
+    let deletes = |harness: &Harness| {
+        harness
+            .requests()
+            .iter()
+            .filter(|r| r.method == Method::DELETE)
+            .count()
+    };
+
+    harness
+        .bot
+        .handle_reaction_add(&cross_reaction(301))
+        .await
+        .unwrap();
+    harness
+        .bot
+        .handle_reaction_add(&cross_reaction(301))
+        .await
+        .unwrap();
+    assert_eq!(deletes(&harness), 0);
+
+    harness
+        .bot
+        .handle_reaction_add(&cross_reaction(302))
+        .await
+        .unwrap();
+    assert_eq!(deletes(&harness), 1);
+}
+
+#[tokio::test]
+async fn test_delete_button_is_limited_to_requester_and_moderators() {
+    let harness = Harness::new(ConfigManager::new(), video_downloader()).await;
+    let deleted = |requests: &[ApiRequest]| {
+        requests.iter().any(|r| {
+            r.method == Method::DELETE && r.path == format!("/channels/{CHANNEL_ID}/messages/900")
+        })
+    };
+
+    harness
+        .bot
+        .handle_interaction(&delete_button_press(301, Permissions::SEND_MESSAGES))
+        .await
+        .unwrap();
+    let requests = harness.requests();
+    assert!(!deleted(&requests));
+    assert_eq!(
+        requests[0].content(),
+        t(Locale::default(), "delete.not_allowed").to_string()
+    );
+
+    harness
+        .bot
+        .handle_interaction(&delete_button_press(301, Permissions::MANAGE_MESSAGES))
+        .await
+        .unwrap();
+    assert!(deleted(&harness.requests()));
+
+    harness
+        .bot
+        .handle_interaction(&delete_button_press(AUTHOR_ID, Permissions::empty()))
+        .await
+        .unwrap();
+    let requests = harness.requests();
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|r| r.method == Method::DELETE)
+            .count(),
+        2
+    );
+}
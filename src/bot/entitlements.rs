@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use twilight_model::application::monetization::Entitlement;
+use twilight_model::id::{
+    marker::{EntitlementMarker, GuildMarker, SkuMarker, UserMarker},
+    Id,
+};
+
+/// Whether `entitlement` grants its SKU at `now` (unix seconds). Test entitlements have no dates
+/// and never run out.
+pub fn is_active(entitlement: &Entitlement, now: u64) -> bool {
+    let now = now as i64;
+    !entitlement.deleted
+        && entitlement.consumed != Some(true)
+        && entitlement
+            .starts_at
+            .is_none_or(|start| start.as_secs() <= now)
+        && entitlement.ends_at.is_none_or(|end| end.as_secs() > now)
+}
+
+/// Discord SKU entitlements of the application, kept up to date from gateway events so tiers
+/// also apply to auto-embeds, which don't carry entitlements like interactions do.
+///
+/// Kept in memory only and loaded from Discord on startup.
+pub struct Entitlements {
+    known: Mutex<HashMap<Id<EntitlementMarker>, Entitlement>>,
+}
+
+impl Entitlements {
+    pub fn new() -> Self {
+        Self {
+            known: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds or replaces an entitlement, dropping it once deleted.
+    pub fn update(&self, entitlement: Entitlement) {
+        let mut known = self.known.lock().unwrap_or_else(PoisonError::into_inner);
+        if entitlement.deleted {
+            known.remove(&entitlement.id);
+        } else {
+            known.insert(entitlement.id, entitlement);
+        }
+    }
+
+    pub fn remove(&self, id: Id<EntitlementMarker>) {
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id);
+    }
+
+    pub fn count(&self) -> usize {
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// SKUs the server or the user is entitled to at `now` (unix seconds).
+    pub fn sku_ids(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        user_id: Option<Id<UserMarker>>,
+        now: u64,
+    ) -> Vec<Id<SkuMarker>> {
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|entitlement| {
+                (entitlement.guild_id.is_some() && entitlement.guild_id == guild_id)
+                    || (entitlement.user_id.is_some() && entitlement.user_id == user_id)
+            })
+            .filter(|entitlement| is_active(entitlement, now))
+            .map(|entitlement| entitlement.sku_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twilight_model::application::monetization::EntitlementType;
+    use twilight_model::util::Timestamp;
+
+    const NOW: u64 = 1_792_152_000;
+
+    fn entitlement(id: u64, guild_id: Option<u64>, user_id: Option<u64>) -> Entitlement {
+        Entitlement {
+            application_id: Id::new(1),
+            consumed: None,
+            deleted: false,
+            ends_at: Some(Timestamp::from_secs(NOW as i64 + 3600).unwrap()),
+            guild_id: guild_id.map(Id::new),
+            id: Id::new(id),
+            kind: EntitlementType::ApplicationSubscription,
+            sku_id: Id::new(id * 10),
+            starts_at: Some(Timestamp::from_secs(NOW as i64 - 3600).unwrap()),
+            user_id: user_id.map(Id::new),
+        }
+    }
+
+    #[test]
+    fn test_sku_ids_of_guild_and_user() {
+        let entitlements = Entitlements::new();
+        entitlements.update(entitlement(1, Some(100), None));
+        entitlements.update(entitlement(2, None, Some(300)));
+        entitlements.update(entitlement(3, Some(101), None));
+
+        let mut sku_ids = entitlements.sku_ids(Some(Id::new(100)), Some(Id::new(300)), NOW);
+        sku_ids.sort();
+        assert_eq!(sku_ids, vec![Id::new(10), Id::new(20)]);
+        assert!(entitlements.sku_ids(None, None, NOW).is_empty());
+
+        // Ended entitlements don't count, deleted ones are dropped
+        assert!(entitlements
+            .sku_ids(Some(Id::new(100)), None, NOW + 7200)
+            .is_empty());
+        entitlements.update(Entitlement {
+            deleted: true,
+            ..entitlement(2, None, Some(300))
+        });
+        entitlements.remove(Id::new(3));
+        assert_eq!(entitlements.count(), 1);
+    }
+
+    #[test]
+    fn test_test_entitlements_never_end() {
+        let test = Entitlement {
+            starts_at: None,
+            ends_at: None,
+            ..entitlement(1, Some(100), None)
+        };
+        assert!(is_active(&test, NOW));
+        assert!(!is_active(
+            &Entitlement {
+                consumed: Some(true),
+                ..test
+            },
+            NOW
+        ));
+    }
+}
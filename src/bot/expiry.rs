@@ -0,0 +1,254 @@
+use crate::storage::{JsonStore, Storage};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+use twilight_http::error::ErrorType;
+
+/// Wait before retrying a failed deletion, doubled on every further failure.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Longest wait between retries of a failed deletion.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(6 * 3600);
+
+/// Attempts at deleting an upload before it is given up on, about a day's worth of retries.
+const MAX_ATTEMPTS: u32 = 12;
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// A bot upload that should be deleted once `delete_at` (unix seconds) has passed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledDeletion {
+    pub guild_id: Option<u64>,
+    /// User the upload was made for
+    #[serde(default)]
+    pub user_id: Option<u64>,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub delete_at: u64,
+    /// Failed attempts at deleting the upload so far
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+impl ScheduledDeletion {
+    fn is(&self, other: &Self) -> bool {
+        self.channel_id == other.channel_id && self.message_id == other.message_id
+    }
+}
+
+/// Whether a failed deletion means the message is already gone, e.g. removed via reaction.
+pub fn is_already_deleted(error: &twilight_http::Error) -> bool {
+    matches!(error.kind(), ErrorType::Response { status, .. } if status.get() == 404)
+}
+
+/// Persisted queue of pending message deletions, so expiry survives restarts.
+pub struct ExpiryScheduler {
+    store: JsonStore<Vec<ScheduledDeletion>>,
+}
+
+impl ExpiryScheduler {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            store: storage.open("scheduled_deletions").await?,
+        })
+    }
+
+    pub async fn schedule(
+        &self,
+        guild_id: Option<u64>,
+        user_id: Option<u64>,
+        channel_id: u64,
+        message_id: u64,
+        after: Duration,
+    ) -> Result<()> {
+        let deletion = ScheduledDeletion {
+            guild_id,
+            user_id,
+            channel_id,
+            message_id,
+            delete_at: unix_now() + after.as_secs(),
+            attempts: 0,
+        };
+
+        self.store.update(|pending| pending.push(deletion)).await
+    }
+
+    /// Every deletion that is due at `now`. They stay pending until they are
+    /// [completed](Self::complete), so a failed or interrupted deletion isn't lost.
+    pub async fn due(&self, now: u64) -> Vec<ScheduledDeletion> {
+        self.store
+            .read(|pending| {
+                pending
+                    .iter()
+                    .filter(|d| d.delete_at <= now)
+                    .cloned()
+                    .collect()
+            })
+            .await
+    }
+
+    /// Stops tracking a deletion once the upload is gone.
+    pub async fn complete(&self, deletion: &ScheduledDeletion) -> Result<()> {
+        self.store
+            .update(|pending| pending.retain(|d| !d.is(deletion)))
+            .await
+    }
+
+    /// Reschedules a failed deletion with backoff, returning when it is retried, or `None` if
+    /// it was given up on after too many attempts or is no longer pending.
+    pub async fn retry(&self, deletion: &ScheduledDeletion, now: u64) -> Result<Option<u64>> {
+        self.store
+            .update(|pending| {
+                let index = pending.iter().position(|d| d.is(deletion))?;
+                let entry = &mut pending[index];
+                entry.attempts += 1;
+                if entry.attempts >= MAX_ATTEMPTS {
+                    warn!(
+                        "Giving up on deleting upload {} in channel {} after {} attempts",
+                        entry.message_id, entry.channel_id, entry.attempts
+                    );
+                    pending.remove(index);
+                    return None;
+                }
+                let delay = RETRY_DELAY
+                    .saturating_mul(1 << (entry.attempts - 1).min(16))
+                    .min(MAX_RETRY_DELAY);
+                entry.delete_at = now + delay.as_secs();
+                Some(entry.delete_at)
+            })
+            .await
+    }
+
+    /// Removes and returns every deletion matching `predicate`.
+    pub async fn take_where(
+        &self,
+        predicate: impl Fn(&ScheduledDeletion) -> bool,
+    ) -> Result<Vec<ScheduledDeletion>> {
+        self.store
+            .update(|pending| {
+                let (taken, remaining) = pending.drain(..).partition(predicate);
+                *pending = remaining;
+                taken
+            })
+            .await
+    }
+
+    pub async fn pending(&self) -> usize {
+        self.store.read(|pending| pending.len()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_due_only_returns_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = ExpiryScheduler::open(&Storage::new(dir.path()))
+            .await
+            .unwrap();
+
+        scheduler
+            .schedule(Some(1), None, 10, 100, Duration::ZERO)
+            .await
+            .unwrap();
+        scheduler
+            .schedule(Some(1), None, 10, 101, Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        let due = scheduler.due(unix_now()).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].message_id, 100);
+        // Due deletions stay pending until they are completed
+        assert_eq!(scheduler.pending().await, 2);
+        scheduler.complete(&due[0]).await.unwrap();
+        assert_eq!(scheduler.pending().await, 1);
+        assert!(scheduler.due(unix_now()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_deletions_are_retried_with_backoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = ExpiryScheduler::open(&Storage::new(dir.path()))
+            .await
+            .unwrap();
+        scheduler
+            .schedule(Some(1), None, 10, 100, Duration::ZERO)
+            .await
+            .unwrap();
+        let now = unix_now();
+        let deletion = scheduler.due(now).await.remove(0);
+
+        assert_eq!(
+            scheduler.retry(&deletion, now).await.unwrap(),
+            Some(now + 60)
+        );
+        assert!(scheduler.due(now).await.is_empty());
+        assert_eq!(scheduler.due(now + 60).await[0].attempts, 1);
+        assert_eq!(
+            scheduler.retry(&deletion, now).await.unwrap(),
+            Some(now + 120)
+        );
+
+        // Retries back off up to a limit and eventually give up
+        for _ in 2..MAX_ATTEMPTS - 1 {
+            let retry_at = scheduler.retry(&deletion, now).await.unwrap().unwrap();
+            assert!(retry_at <= now + MAX_RETRY_DELAY.as_secs());
+        }
+        assert_eq!(scheduler.retry(&deletion, now).await.unwrap(), None);
+        assert_eq!(scheduler.pending().await, 0);
+        assert_eq!(scheduler.retry(&deletion, now).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        let scheduler = ExpiryScheduler::open(&storage).await.unwrap();
+        scheduler
+            .schedule(None, None, 10, 100, Duration::from_secs(60))
+            .await
+            .unwrap();
+        drop(scheduler);
+
+        let scheduler = ExpiryScheduler::open(&storage).await.unwrap();
+        assert_eq!(scheduler.pending().await, 1);
+        let due = scheduler.due(unix_now() + 60).await;
+        assert_eq!(due[0].channel_id, 10);
+    }
+
+    #[tokio::test]
+    async fn test_take_where_removes_matching() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = ExpiryScheduler::open(&Storage::new(dir.path()))
+            .await
+            .unwrap();
+
+        let hour = Duration::from_secs(3600);
+        scheduler
+            .schedule(Some(1), Some(5), 10, 100, hour)
+            .await
+            .unwrap();
+        scheduler
+            .schedule(Some(1), Some(6), 10, 101, hour)
+            .await
+            .unwrap();
+
+        let taken = scheduler
+            .take_where(|d| d.user_id == Some(5))
+            .await
+            .unwrap();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].message_id, 100);
+        assert_eq!(scheduler.pending().await, 1);
+    }
+}
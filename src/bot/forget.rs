@@ -0,0 +1,152 @@
+use crate::i18n::{t, tf, Locale};
+
+/// Whose data a `/admin forget` request purges. User requests are scoped to the guild they came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForgetTarget {
+    Guild { guild_id: u64 },
+    User { guild_id: u64, user_id: u64 },
+}
+
+impl ForgetTarget {
+    /// Encodes the target into the custom id of the confirmation button.
+    pub fn custom_id(self) -> String {
+        match self {
+            Self::Guild { guild_id } => format!("forget:guild:{guild_id}"),
+            Self::User { guild_id, user_id } => format!("forget:user:{guild_id}:{user_id}"),
+        }
+    }
+
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.strip_prefix("forget:")?.split(':');
+        let target = match parts.next()? {
+            "guild" => Self::Guild {
+                guild_id: parts.next()?.parse().ok()?,
+            },
+            "user" => Self::User {
+                guild_id: parts.next()?.parse().ok()?,
+                user_id: parts.next()?.parse().ok()?,
+            },
+            _ => return None,
+        };
+
+        parts.next().is_none().then_some(target)
+    }
+
+    pub fn guild_id(self) -> u64 {
+        match self {
+            Self::Guild { guild_id } | Self::User { guild_id, .. } => guild_id,
+        }
+    }
+
+    /// Returns true if a record owned by `guild_id` and `user_id` belongs to this target.
+    pub fn matches(self, guild_id: Option<u64>, user_id: Option<u64>) -> bool {
+        match self {
+            Self::Guild { guild_id: target } => guild_id == Some(target),
+            Self::User {
+                guild_id: target_guild,
+                user_id: target_user,
+            } => guild_id == Some(target_guild) && user_id == Some(target_user),
+        }
+    }
+}
+
+/// Number of removed records per kind of stored data, keyed by message key.
+#[derive(Debug, Default)]
+pub struct ForgetSummary {
+    removed: Vec<(&'static str, usize)>,
+}
+
+impl ForgetSummary {
+    pub fn add(&mut self, key: &'static str, count: usize) {
+        if count > 0 {
+            self.removed.push((key, count));
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.removed.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn render(&self, locale: Locale) -> String {
+        if self.removed.is_empty() {
+            return t(locale, "admin.forget_nothing").to_string();
+        }
+
+        let mut lines = vec![tf(
+            locale,
+            "admin.forget_done",
+            &[("total", &self.total().to_string())],
+        )];
+        lines.extend(
+            self.removed
+                .iter()
+                .map(|(key, count)| format!("- {}: {}", t(locale, key), count)),
+        );
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_id_round_trip() {
+        let targets = [
+            ForgetTarget::Guild { guild_id: 1 },
+            ForgetTarget::User {
+                guild_id: 1,
+                user_id: 2,
+            },
+        ];
+
+        for target in targets {
+            assert_eq!(
+                ForgetTarget::from_custom_id(&target.custom_id()),
+                Some(target)
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_custom_id_rejects_garbage() {
+        assert_eq!(ForgetTarget::from_custom_id("forget:guild"), None);
+        assert_eq!(ForgetTarget::from_custom_id("forget:guild:abc"), None);
+        assert_eq!(ForgetTarget::from_custom_id("forget:guild:1:2"), None);
+        assert_eq!(ForgetTarget::from_custom_id("forget:channel:1"), None);
+        assert_eq!(ForgetTarget::from_custom_id("other:guild:1"), None);
+    }
+
+    #[test]
+    fn test_matches() {
+        let guild = ForgetTarget::Guild { guild_id: 1 };
+        assert!(guild.matches(Some(1), Some(5)));
+        assert!(guild.matches(Some(1), None));
+        assert!(!guild.matches(Some(2), Some(5)));
+        assert!(!guild.matches(None, Some(5)));
+
+        let user = ForgetTarget::User {
+            guild_id: 1,
+            user_id: 5,
+        };
+        assert!(user.matches(Some(1), Some(5)));
+        assert!(!user.matches(Some(1), Some(6)));
+        assert!(!user.matches(Some(2), Some(5)));
+    }
+
+    #[test]
+    fn test_summary_render() {
+        let mut summary = ForgetSummary::default();
+        assert_eq!(
+            summary.render(Locale::En),
+            t(Locale::En, "admin.forget_nothing")
+        );
+
+        summary.add("forget.scheduled_deletions", 3);
+        summary.add("forget.scheduled_deletions", 0);
+        assert_eq!(summary.total(), 3);
+        assert!(summary
+            .render(Locale::En)
+            .ends_with("\n- Scheduled upload deletions: 3"));
+    }
+}
@@ -0,0 +1,589 @@
+use super::expiry::unix_now;
+#[cfg(feature = "redis")]
+use crate::storage::redis::RedisStore;
+use crate::storage::{JsonStore, Storage};
+#[cfg(feature = "redis")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "redis")]
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+#[cfg(feature = "redis")]
+use tracing::warn;
+
+/// A URL embedded in a channel, remembered until `expires_at` (unix seconds) to skip reposts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbedRecord {
+    pub guild_id: Option<u64>,
+    /// User the embed was made for
+    pub user_id: Option<u64>,
+    pub channel_id: u64,
+    pub message_id: u64,
+    /// Normalized with [`normalize_url`]
+    pub url: String,
+    pub expires_at: u64,
+}
+
+impl EmbedRecord {
+    /// Link that jumps to the embed in the Discord client.
+    pub fn jump_link(&self) -> String {
+        jump_link(self.guild_id, self.channel_id, self.message_id)
+    }
+}
+
+/// Link that jumps to a message in the Discord client.
+pub fn jump_link(guild_id: Option<u64>, channel_id: u64, message_id: u64) -> String {
+    let guild = guild_id.map_or_else(|| "@me".to_string(), |id| id.to_string());
+    format!("https://discord.com/channels/{guild}/{channel_id}/{message_id}")
+}
+
+enum Backend {
+    Local(JsonStore<Vec<EmbedRecord>>),
+    /// One key per channel and link, expiring along with its record
+    #[cfg(feature = "redis")]
+    Redis(RedisStore),
+}
+
+/// Persisted record of recent embeds per channel, used to deduplicate reposted links.
+pub struct EmbedHistory {
+    backend: Backend,
+}
+
+impl EmbedHistory {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        #[cfg(feature = "redis")]
+        if let Some(shared) = storage.shared() {
+            return Ok(Self {
+                backend: Backend::Redis(shared.clone()),
+            });
+        }
+
+        Ok(Self {
+            backend: Backend::Local(storage.open("embed_history").await?),
+        })
+    }
+
+    /// Returns the unexpired embed of `url` in `channel_id`, if there is one.
+    pub async fn find(&self, channel_id: u64, url: &str) -> Option<EmbedRecord> {
+        let url = normalize_url(url);
+        let now = unix_now();
+        match &self.backend {
+            Backend::Local(store) => {
+                store
+                    .read(|records| {
+                        records
+                            .iter()
+                            .rev()
+                            .find(|r| {
+                                r.channel_id == channel_id && r.url == url && r.expires_at > now
+                            })
+                            .cloned()
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(shared) => {
+                let key = shared.key(&format!("embed_history:{channel_id}:{url}"));
+                let json: Option<String> = match shared.connection().get(&key).await {
+                    Ok(json) => json,
+                    Err(e) => {
+                        // Reposts slip through while Redis is unavailable rather than failing
+                        warn!("Failed to read embed history from Redis: {}", e);
+                        None
+                    }
+                };
+                json.and_then(|json| serde_json::from_str(&json).ok())
+            }
+        }
+    }
+
+    /// Remembers an embed for `window`, dropping records that have expired.
+    pub async fn record(
+        &self,
+        guild_id: Option<u64>,
+        user_id: Option<u64>,
+        channel_id: u64,
+        message_id: u64,
+        url: &str,
+        window: Duration,
+    ) -> Result<()> {
+        let now = unix_now();
+        let record = EmbedRecord {
+            guild_id,
+            user_id,
+            channel_id,
+            message_id,
+            url: normalize_url(url),
+            expires_at: now + window.as_secs(),
+        };
+
+        match &self.backend {
+            Backend::Local(store) => {
+                store
+                    .update(|records| {
+                        records.retain(|r| r.expires_at > now);
+                        records.push(record);
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(shared) => {
+                let key = shared.key(&format!(
+                    "embed_history:{}:{}",
+                    record.channel_id, record.url
+                ));
+                let json = serde_json::to_string(&record)?;
+                shared
+                    .connection()
+                    .set_ex(key, json, window.as_secs().max(1))
+                    .await
+                    .context("Failed to save embed history to Redis")
+            }
+        }
+    }
+
+    /// Removes and returns every record matching `predicate`.
+    pub async fn take_where(
+        &self,
+        predicate: impl Fn(&EmbedRecord) -> bool,
+    ) -> Result<Vec<EmbedRecord>> {
+        match &self.backend {
+            Backend::Local(store) => {
+                store
+                    .update(|records| {
+                        let (taken, remaining) = records.drain(..).partition(predicate);
+                        *records = remaining;
+                        taken
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(shared) => {
+                let mut connection = shared.connection();
+                let mut taken = Vec::new();
+                for key in shared.keys_with_prefix("embed_history:").await? {
+                    let json: Option<String> = connection.get(&key).await?;
+                    let Some(record) =
+                        json.and_then(|json| serde_json::from_str::<EmbedRecord>(&json).ok())
+                    else {
+                        continue;
+                    };
+                    if predicate(&record) {
+                        let _: () = connection.del(&key).await?;
+                        taken.push(record);
+                    }
+                }
+                Ok(taken)
+            }
+        }
+    }
+}
+
+/// Links a user asked for recently, suggested back to them when they type `/embed`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestedUrl {
+    pub guild_id: Option<u64>,
+    pub user_id: u64,
+    /// Normalized with [`normalize_url`]
+    pub url: String,
+    pub requested_at: u64,
+}
+
+/// Links remembered per user, more than autocomplete shows so typing can narrow them down.
+const REQUESTS_PER_USER: usize = 25;
+/// Requests older than this are forgotten.
+const REQUEST_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+enum RequestBackend {
+    Local(JsonStore<Vec<RequestedUrl>>),
+    /// One key per user holding their requests, expiring after the last one is as old as the
+    /// retention
+    #[cfg(feature = "redis")]
+    Redis(RedisStore),
+}
+
+/// Persisted record of the links each user had embedded with `/embed`.
+pub struct RequestHistory {
+    backend: RequestBackend,
+}
+
+impl RequestHistory {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        #[cfg(feature = "redis")]
+        if let Some(shared) = storage.shared() {
+            return Ok(Self {
+                backend: RequestBackend::Redis(shared.clone()),
+            });
+        }
+
+        Ok(Self {
+            backend: RequestBackend::Local(storage.open("request_history").await?),
+        })
+    }
+
+    /// Remembers that the user requested `url`, moving it to the front if they had before.
+    pub async fn record(&self, guild_id: Option<u64>, user_id: u64, url: &str) -> Result<()> {
+        let request = RequestedUrl {
+            guild_id,
+            user_id,
+            url: normalize_url(url),
+            requested_at: unix_now(),
+        };
+        match &self.backend {
+            RequestBackend::Local(store) => {
+                store
+                    .update(|requests| push_request(requests, request, unix_now()))
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            RequestBackend::Redis(shared) => {
+                let key = shared.key(&format!("request_history:{user_id}"));
+                let mut connection = shared.connection();
+                let json: Option<String> = connection
+                    .get(&key)
+                    .await
+                    .context("Failed to read request history from Redis")?;
+                let mut requests: Vec<RequestedUrl> = json
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default();
+                push_request(&mut requests, request, unix_now());
+                connection
+                    .set_ex(
+                        key,
+                        serde_json::to_string(&requests)?,
+                        REQUEST_RETENTION.as_secs(),
+                    )
+                    .await
+                    .context("Failed to save request history to Redis")
+            }
+        }
+    }
+
+    /// Links the user requested, newest first.
+    pub async fn recent(&self, user_id: u64) -> Vec<String> {
+        let cutoff = unix_now().saturating_sub(REQUEST_RETENTION.as_secs());
+        let requests: Vec<RequestedUrl> = match &self.backend {
+            RequestBackend::Local(store) => {
+                store
+                    .read(|requests| {
+                        requests
+                            .iter()
+                            .filter(|r| r.user_id == user_id)
+                            .cloned()
+                            .collect()
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            RequestBackend::Redis(shared) => {
+                let key = shared.key(&format!("request_history:{user_id}"));
+                let json: Option<String> = match shared.connection().get(&key).await {
+                    Ok(json) => json,
+                    Err(e) => {
+                        // Suggestions are left out while Redis is unavailable
+                        warn!("Failed to read request history from Redis: {}", e);
+                        None
+                    }
+                };
+                json.and_then(|json| serde_json::from_str::<Vec<RequestedUrl>>(&json).ok())
+                    .unwrap_or_default()
+            }
+        };
+        requests
+            .into_iter()
+            .rev()
+            .filter(|r| r.requested_at > cutoff)
+            .map(|r| r.url)
+            .collect()
+    }
+
+    /// Removes and returns every request matching `predicate`.
+    pub async fn take_where(
+        &self,
+        predicate: impl Fn(&RequestedUrl) -> bool,
+    ) -> Result<Vec<RequestedUrl>> {
+        match &self.backend {
+            RequestBackend::Local(store) => {
+                store
+                    .update(|requests| {
+                        let (taken, remaining) = requests.drain(..).partition(predicate);
+                        *requests = remaining;
+                        taken
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            RequestBackend::Redis(shared) => {
+                let mut connection = shared.connection();
+                let mut taken = Vec::new();
+                for key in shared.keys_with_prefix("request_history:").await? {
+                    let json: Option<String> = connection.get(&key).await?;
+                    let Some(requests) =
+                        json.and_then(|json| serde_json::from_str::<Vec<RequestedUrl>>(&json).ok())
+                    else {
+                        continue;
+                    };
+                    let (matching, remaining): (Vec<_>, Vec<_>) =
+                        requests.into_iter().partition(&predicate);
+                    if matching.is_empty() {
+                        continue;
+                    }
+                    if remaining.is_empty() {
+                        let _: () = connection.del(&key).await?;
+                    } else {
+                        let _: () = connection
+                            .set_ex(
+                                &key,
+                                serde_json::to_string(&remaining)?,
+                                REQUEST_RETENTION.as_secs(),
+                            )
+                            .await?;
+                    }
+                    taken.extend(matching);
+                }
+                Ok(taken)
+            }
+        }
+    }
+}
+
+/// Appends a request, dropping the user's earlier request of the same link, their requests past
+/// [`REQUESTS_PER_USER`] and everyone's requests older than the retention.
+fn push_request(requests: &mut Vec<RequestedUrl>, request: RequestedUrl, now: u64) {
+    let cutoff = now.saturating_sub(REQUEST_RETENTION.as_secs());
+    requests.retain(|r| {
+        r.requested_at > cutoff && !(r.user_id == request.user_id && r.url == request.url)
+    });
+    let user_id = request.user_id;
+    requests.push(request);
+
+    let excess = requests
+        .iter()
+        .filter(|r| r.user_id == user_id)
+        .count()
+        .saturating_sub(REQUESTS_PER_USER);
+    let mut skipped = 0;
+    requests.retain(|r| {
+        if r.user_id != user_id || skipped >= excess {
+            return true;
+        }
+        skipped += 1;
+        false
+    });
+}
+
+/// Canonical form of a link, so trivially different copies of it are recognized as the same.
+pub fn normalize_url(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    parsed.set_fragment(None);
+
+    let query: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !key.starts_with("utm_"))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if query.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(query);
+    }
+
+    let normalized = parsed.to_string();
+    match normalized.strip_suffix('/') {
+        Some(trimmed) if parsed.query().is_none() => trimmed.to_string(),
+        _ => normalized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("https://Example.com/video/1/"),
+            "https://example.com/video/1"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/watch?v=abc&utm_source=share#t=10"),
+            "https://example.com/watch?v=abc"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/a?utm_medium=x"),
+            "https://example.com/a"
+        );
+        assert_eq!(normalize_url(" not a url "), "not a url");
+    }
+
+    #[test]
+    fn test_jump_link() {
+        let mut record = EmbedRecord {
+            guild_id: Some(1),
+            user_id: None,
+            channel_id: 2,
+            message_id: 3,
+            url: String::new(),
+            expires_at: 0,
+        };
+        assert_eq!(record.jump_link(), "https://discord.com/channels/1/2/3");
+
+        record.guild_id = None;
+        assert_eq!(record.jump_link(), "https://discord.com/channels/@me/2/3");
+    }
+
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    #[ignore = "Requires a Redis server at REDIS_URL"]
+    async fn test_history_in_redis() {
+        let storage = crate::storage::redis::test_storage().await;
+        let history = EmbedHistory::open(&storage).await.unwrap();
+        let window = Duration::from_secs(60);
+
+        history
+            .record(Some(1), Some(5), 10, 100, "https://example.com/a/", window)
+            .await
+            .unwrap();
+        history
+            .record(Some(2), Some(5), 20, 200, "https://example.com/a", window)
+            .await
+            .unwrap();
+
+        // Instances sharing the Redis see each other's embeds
+        let other = EmbedHistory::open(&storage).await.unwrap();
+        let found = other.find(10, "https://example.com/a#top").await.unwrap();
+        assert_eq!(found.message_id, 100);
+        assert!(other.find(11, "https://example.com/a").await.is_none());
+
+        let taken = other.take_where(|r| r.guild_id == Some(1)).await.unwrap();
+        assert_eq!(taken.len(), 1);
+        assert!(history.find(10, "https://example.com/a").await.is_none());
+        assert!(history.find(20, "https://example.com/a").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_find_matches_channel_and_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = EmbedHistory::open(&Storage::new(dir.path())).await.unwrap();
+
+        history
+            .record(
+                Some(1),
+                Some(5),
+                10,
+                100,
+                "https://example.com/a/",
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        history
+            .record(
+                Some(1),
+                Some(5),
+                10,
+                101,
+                "https://example.com/b",
+                Duration::ZERO,
+            )
+            .await
+            .unwrap();
+
+        let found = history.find(10, "https://example.com/a#top").await.unwrap();
+        assert_eq!(found.message_id, 100);
+        assert!(history.find(11, "https://example.com/a").await.is_none());
+        assert!(history.find(10, "https://example.com/b").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_history_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        let history = EmbedHistory::open(&storage).await.unwrap();
+        history
+            .record(
+                None,
+                None,
+                10,
+                100,
+                "https://example.com/a",
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        drop(history);
+
+        let history = EmbedHistory::open(&storage).await.unwrap();
+        assert!(history.find(10, "https://example.com/a").await.is_some());
+        assert_eq!(
+            history
+                .take_where(|r| r.guild_id.is_none())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_requests_per_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let requests = RequestHistory::open(&Storage::new(dir.path()))
+            .await
+            .unwrap();
+
+        requests
+            .record(Some(1), 5, "https://example.com/a/")
+            .await
+            .unwrap();
+        requests
+            .record(Some(1), 5, "https://example.com/b")
+            .await
+            .unwrap();
+        requests
+            .record(Some(1), 6, "https://example.com/c")
+            .await
+            .unwrap();
+        // Requesting a link again moves it to the front instead of listing it twice
+        requests
+            .record(Some(1), 5, "https://example.com/a")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            requests.recent(5).await,
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+        assert_eq!(requests.recent(6).await, vec!["https://example.com/c"]);
+        assert!(requests.recent(7).await.is_empty());
+
+        let taken = requests.take_where(|r| r.user_id == 5).await.unwrap();
+        assert_eq!(taken.len(), 2);
+        assert!(requests.recent(5).await.is_empty());
+    }
+
+    #[test]
+    fn test_push_request_is_bounded() {
+        let request = |user_id, index: usize, requested_at| RequestedUrl {
+            guild_id: None,
+            user_id,
+            url: format!("https://example.com/{index}"),
+            requested_at,
+        };
+        let now = REQUEST_RETENTION.as_secs() * 2;
+        let mut requests = vec![request(6, 0, 1), request(6, 1, now)];
+        for index in 0..REQUESTS_PER_USER + 3 {
+            push_request(&mut requests, request(5, index, now), now);
+        }
+
+        let of_user: Vec<_> = requests.iter().filter(|r| r.user_id == 5).collect();
+        assert_eq!(of_user.len(), REQUESTS_PER_USER);
+        assert_eq!(of_user[0].url, "https://example.com/3");
+        // Requests past the retention are dropped for everyone
+        assert_eq!(requests.iter().filter(|r| r.user_id == 6).count(), 1);
+    }
+}
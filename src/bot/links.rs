@@ -0,0 +1,118 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use twilight_model::channel::{message::Embed, Message};
+
+/// Candidate links, cut at whitespace and the characters Discord markdown wraps them in.
+static URL_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)https?://[^\s<>"'`|\[\]]+"#).unwrap());
+
+/// Extracts links from message text, including markdown `[text](url)` and `<url>` forms.
+pub fn extract_urls(content: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    push_urls(&mut urls, content);
+    urls
+}
+
+/// Links of a message, from its text, its embeds and the messages it forwards.
+pub fn message_urls(message: &Message) -> Vec<String> {
+    let mut urls = Vec::new();
+    push_urls(&mut urls, &message.content);
+    for snapshot in &message.message_snapshots {
+        push_urls(&mut urls, &snapshot.message.content);
+        push_embed_urls(&mut urls, &snapshot.message.embeds);
+    }
+    push_embed_urls(&mut urls, &message.embeds);
+    urls
+}
+
+fn push_embed_urls(urls: &mut Vec<String>, embeds: &[Embed]) {
+    for embed in embeds {
+        if let Some(url) = &embed.url {
+            push_urls(urls, url);
+        }
+        if let Some(description) = &embed.description {
+            push_urls(urls, description);
+        }
+        for field in &embed.fields {
+            push_urls(urls, &field.value);
+        }
+    }
+}
+
+/// Appends the links found in `text` that aren't collected yet.
+fn push_urls(urls: &mut Vec<String>, text: &str) {
+    for candidate in URL_PATTERN.find_iter(text) {
+        let url = trim_url(candidate.as_str());
+        let is_valid = url::Url::parse(url).is_ok_and(|parsed| parsed.host_str().is_some());
+        if is_valid && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+}
+
+/// Drops punctuation ending the sentence around a link, and a closing parenthesis it doesn't
+/// open itself.
+fn trim_url(mut url: &str) -> &str {
+    while let Some(last) = url.chars().last() {
+        let trailing = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' | '*' | '_' | '~' => true,
+            ')' => url.matches('(').count() < url.matches(')').count(),
+            _ => false,
+        };
+        if !trailing {
+            break;
+        }
+        url = &url[..url.len() - last.len_utf8()];
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_urls() {
+        assert_eq!(
+            extract_urls("look https://example.com/a and http://example.org/b?x=1"),
+            vec!["https://example.com/a", "http://example.org/b?x=1"]
+        );
+        assert!(extract_urls("no links, just https:// and example.com").is_empty());
+    }
+
+    #[test]
+    fn test_extract_urls_from_markdown() {
+        assert_eq!(
+            extract_urls("[clip](https://example.com/v/1) <https://example.com/v/2>"),
+            vec!["https://example.com/v/1", "https://example.com/v/2"]
+        );
+        assert_eq!(
+            extract_urls("||https://example.com/spoiler|| **https://example.com/bold**"),
+            vec!["https://example.com/spoiler", "https://example.com/bold"]
+        );
+        assert_eq!(
+            extract_urls("[https://example.com/shown](https://example.com/real)"),
+            vec!["https://example.com/shown", "https://example.com/real"]
+        );
+    }
+
+    #[test]
+    fn test_extract_urls_trims_punctuation() {
+        assert_eq!(
+            extract_urls("Watch this: https://example.com/v/1. Or (https://example.com/v/2)!"),
+            vec!["https://example.com/v/1", "https://example.com/v/2"]
+        );
+        assert_eq!(
+            extract_urls("https://en.wikipedia.org/wiki/Rust_(programming_language),"),
+            vec!["https://en.wikipedia.org/wiki/Rust_(programming_language)"]
+        );
+    }
+
+    #[test]
+    fn test_extract_urls_skips_duplicates() {
+        assert_eq!(
+            extract_urls("https://example.com/a https://example.com/a"),
+            vec!["https://example.com/a"]
+        );
+    }
+}
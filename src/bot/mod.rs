@@ -1,4 +1,27 @@
+pub mod abandon;
+pub mod audit;
+pub mod autocomplete;
+pub mod backpressure;
+pub mod callbacks;
+pub mod capabilities;
+pub mod commands;
+pub mod delete;
 pub mod discord;
+pub mod entitlements;
+pub mod expiry;
+pub mod forget;
+pub mod history;
+pub mod links;
+pub mod owner;
+pub mod permissions;
+pub mod quota;
+pub mod ratelimit;
+pub mod retry;
+pub mod settings;
+pub mod simulate;
+pub mod votes;
+pub mod watch;
+pub mod webhook;
 
 use crate::config::ConfigManager;
 use anyhow::Result;
@@ -0,0 +1,199 @@
+use crate::i18n::{t, tf, Locale};
+use crate::media::Diagnostics;
+use crate::utils::{format_size, format_uptime};
+use std::time::Duration;
+
+/// Bot-wide `/admin` subcommands, restricted to the owners listed in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnerCommand {
+    ReloadConfig,
+    ToolVersions,
+    Stats,
+    PurgeCache,
+    Diagnostics,
+}
+
+impl OwnerCommand {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "reload-config" => Some(Self::ReloadConfig),
+            "tool-versions" => Some(Self::ToolVersions),
+            "stats" => Some(Self::Stats),
+            "purge-cache" => Some(Self::PurgeCache),
+            "diagnostics" => Some(Self::Diagnostics),
+            _ => None,
+        }
+    }
+}
+
+/// Runtime figures reported by `/admin stats`.
+#[derive(Debug, Default)]
+pub struct BotStats {
+    pub uptime: Duration,
+    /// Servers the bot is in, as far as the gateway cache knows
+    pub servers: usize,
+    pub configured_servers: usize,
+    pub active_downloads: usize,
+    pub queued_downloads: usize,
+    pub scheduled_deletions: usize,
+    pub alive_tasks: usize,
+}
+
+impl BotStats {
+    pub fn render(&self, locale: Locale) -> String {
+        [
+            ("stats.uptime", format_uptime(self.uptime)),
+            ("stats.servers", self.servers.to_string()),
+            (
+                "stats.configured_servers",
+                self.configured_servers.to_string(),
+            ),
+            ("stats.active_downloads", self.active_downloads.to_string()),
+            ("stats.queued_downloads", self.queued_downloads.to_string()),
+            (
+                "stats.scheduled_deletions",
+                self.scheduled_deletions.to_string(),
+            ),
+            ("stats.alive_tasks", self.alive_tasks.to_string()),
+        ]
+        .iter()
+        .map(|(key, value)| format!("- {}: {}", t(locale, key), value))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+/// Lists each tool with its version, or a note that it could not be run.
+pub fn render_tool_versions(versions: &[(&str, Option<String>)], locale: Locale) -> String {
+    versions
+        .iter()
+        .map(|(name, version)| match version {
+            Some(version) => format!("- {name}: `{version}`"),
+            None => tf(locale, "admin.tool_missing", &[("tool", name)]),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lists each check of a diagnostics report with its outcome, below the overall one.
+pub fn render_diagnostics(report: &Diagnostics, locale: Locale) -> String {
+    let tools = report
+        .tools
+        .iter()
+        .map(|(name, version)| match version {
+            Some(version) => format!("{name} {version}"),
+            None => format!("{name} {}", t(locale, "diag.not_found")),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let encoders = match &report.encoders {
+        Some(encoders) => {
+            let list = |available: bool| {
+                encoders
+                    .iter()
+                    .filter(|(_, has)| *has == available)
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let missing = list(false);
+            if missing.is_empty() {
+                list(true)
+            } else {
+                format!(
+                    "{} ({})",
+                    list(true),
+                    tf(locale, "diag.missing", &[("list", &missing)])
+                )
+            }
+        }
+        None => t(locale, "diag.no_ffmpeg").to_string(),
+    };
+    let temp_dir = match &report.temp_dir_error {
+        Some(error) => format!("{}: {error}", report.temp_dir.display()),
+        None => report.temp_dir.display().to_string(),
+    };
+    let disk = match report.free_space {
+        Some(free) => format_size(free),
+        None => t(locale, "diag.unknown").to_string(),
+    };
+    let network = match &report.connectivity {
+        Ok(status) => tf(locale, "diag.reachable", &[("status", &status.to_string())]),
+        Err(error) => error.clone(),
+    };
+
+    let mut lines = vec![format!(
+        "{} **{}**",
+        report.status().icon(),
+        t(locale, "diag.title")
+    )];
+    for (status, key, detail) in [
+        (report.tools_status(), "diag.tools", tools),
+        (report.encoders_status(), "diag.encoders", encoders),
+        (report.temp_dir_status(), "diag.temp_dir", temp_dir),
+        (report.disk_status(), "diag.disk", disk),
+        (report.connectivity_status(), "diag.network", network),
+    ] {
+        lines.push(format!("{} {}: {}", status.icon(), t(locale, key), detail));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            OwnerCommand::from_name("reload-config"),
+            Some(OwnerCommand::ReloadConfig)
+        );
+        assert_eq!(
+            OwnerCommand::from_name("purge-cache"),
+            Some(OwnerCommand::PurgeCache)
+        );
+        assert_eq!(OwnerCommand::from_name("forget"), None);
+    }
+
+    #[test]
+    fn test_render_tool_versions() {
+        let versions = [
+            ("yt-dlp", Some("2025.06.30".to_string())),
+            ("gallery-dl", None),
+        ];
+        assert_eq!(
+            render_tool_versions(&versions, Locale::En),
+            "- yt-dlp: `2025.06.30`\n- gallery-dl: not found"
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostics() {
+        let report = Diagnostics {
+            tools: vec![
+                ("yt-dlp", Some("2025.06.30".to_string())),
+                ("gallery-dl", None),
+            ],
+            encoders: Some(vec![
+                ("libx264", true),
+                ("aac", true),
+                ("libaom-av1", false),
+            ]),
+            temp_dir: "/tmp".into(),
+            temp_dir_error: None,
+            free_space: Some(12_300_000_000),
+            connectivity: Err("dns error".to_string()),
+        };
+
+        assert_eq!(
+            render_diagnostics(&report, Locale::En),
+            "❌ **Diagnostics**\n\
+             ⚠️ Tools: yt-dlp 2025.06.30, gallery-dl not found\n\
+             ⚠️ ffmpeg encoders: libx264, aac (missing: libaom-av1)\n\
+             ✅ Temp directory: /tmp\n\
+             ✅ Free disk space: 12.3 GB\n\
+             ❌ Discord API: dns error"
+        );
+    }
+}
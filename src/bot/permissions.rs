@@ -0,0 +1,144 @@
+use crate::config::ServerConfig;
+use twilight_model::{
+    application::interaction::{Interaction, InteractionChannel},
+    guild::Permissions,
+    id::{marker::RoleMarker, Id},
+};
+
+/// Checks whether a member may run config-mutating commands.
+///
+/// Members with Administrator or Manage Server always can; other members need one
+/// of the roles listed in `config_role_ids`.
+pub fn can_configure(
+    permissions: Permissions,
+    roles: &[Id<RoleMarker>],
+    server_config: &ServerConfig,
+) -> bool {
+    if permissions.intersects(Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD) {
+        return true;
+    }
+
+    roles
+        .iter()
+        .any(|role_id| server_config.config_role_ids.contains(role_id))
+}
+
+/// Checks the invoker of a guild interaction, denying interactions without member data.
+pub fn interaction_can_configure(interaction: &Interaction, server_config: &ServerConfig) -> bool {
+    interaction.member.as_ref().is_some_and(|member| {
+        can_configure(
+            member.permissions.unwrap_or_else(Permissions::empty),
+            &member.roles,
+            server_config,
+        )
+    })
+}
+
+/// Checks whether a member may have `/embed` post to another channel: only those who can
+/// configure the server, and only where they could post the media themselves.
+pub fn can_forward_to(
+    permissions: Permissions,
+    roles: &[Id<RoleMarker>],
+    server_config: &ServerConfig,
+    target: &InteractionChannel,
+) -> bool {
+    let send = if target.kind.is_thread() {
+        Permissions::SEND_MESSAGES_IN_THREADS
+    } else {
+        Permissions::SEND_MESSAGES
+    };
+    let needed = Permissions::VIEW_CHANNEL | Permissions::ATTACH_FILES | send;
+    target.permissions.contains(needed) && can_configure(permissions, roles, server_config)
+}
+
+/// Checks the invoker of a guild interaction, denying interactions without member data.
+pub fn interaction_can_forward_to(
+    interaction: &Interaction,
+    server_config: &ServerConfig,
+    target: &InteractionChannel,
+) -> bool {
+    interaction.member.as_ref().is_some_and(|member| {
+        can_forward_to(
+            member.permissions.unwrap_or_else(Permissions::empty),
+            &member.roles,
+            server_config,
+            target,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twilight_model::channel::ChannelType;
+
+    fn channel(kind: ChannelType, permissions: Permissions) -> InteractionChannel {
+        InteractionChannel {
+            id: Id::new(5),
+            kind,
+            name: "media".to_string(),
+            parent_id: None,
+            permissions,
+            thread_metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_admins_and_managers_can_configure() {
+        let config = ServerConfig::new(Id::new(3));
+
+        assert!(can_configure(Permissions::ADMINISTRATOR, &[], &config));
+        assert!(can_configure(
+            Permissions::MANAGE_GUILD | Permissions::SEND_MESSAGES,
+            &[],
+            &config
+        ));
+        assert!(!can_configure(Permissions::SEND_MESSAGES, &[], &config));
+    }
+
+    #[test]
+    fn test_configured_roles_can_configure() {
+        let mut config = ServerConfig::new(Id::new(3));
+        config.config_role_ids.insert(Id::new(10));
+
+        assert!(can_configure(Permissions::empty(), &[Id::new(10)], &config));
+        assert!(!can_configure(
+            Permissions::empty(),
+            &[Id::new(11)],
+            &config
+        ));
+    }
+
+    #[test]
+    fn test_forwarding_needs_posting_in_target() {
+        let config = ServerConfig::new(Id::new(3));
+        let postable =
+            Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::ATTACH_FILES;
+
+        let target = channel(ChannelType::GuildText, postable);
+        assert!(can_forward_to(
+            Permissions::MANAGE_GUILD,
+            &[],
+            &config,
+            &target
+        ));
+        // Members who can't configure the server can't forward at all
+        assert!(!can_forward_to(Permissions::empty(), &[], &config, &target));
+
+        let read_only = channel(ChannelType::GuildText, Permissions::VIEW_CHANNEL);
+        assert!(!can_forward_to(
+            Permissions::MANAGE_GUILD,
+            &[],
+            &config,
+            &read_only
+        ));
+        // Threads need their own permission to post in
+        let thread = channel(ChannelType::PublicThread, postable);
+        assert!(!can_forward_to(
+            Permissions::MANAGE_GUILD,
+            &[],
+            &config,
+            &thread
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+#[cfg(feature = "redis")]
+use crate::storage::redis::RedisStore;
+use crate::storage::{JsonStore, Storage};
+#[cfg(feature = "redis")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "redis")]
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "redis")]
+use std::collections::HashMap;
+#[cfg(feature = "redis")]
+use tracing::warn;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Downloads a server made in one calendar month (UTC).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub guild_id: u64,
+    /// Month the usage counts towards, e.g. "2026-10"
+    pub month: String,
+    pub downloads: u64,
+    /// Size of the downloaded media, before it was fitted to the upload limit
+    pub bytes: u64,
+}
+
+impl QuotaUsage {
+    /// Whether another download would go over one of the limits.
+    pub fn exhausted(&self, max_downloads: Option<u64>, max_bytes: Option<u64>) -> bool {
+        max_downloads.is_some_and(|max| self.downloads >= max)
+            || max_bytes.is_some_and(|max| self.bytes >= max)
+    }
+}
+
+enum Backend {
+    Local(JsonStore<Vec<QuotaUsage>>),
+    /// A hash of counters per server and month, expiring when the month ends
+    #[cfg(feature = "redis")]
+    Redis(RedisStore),
+}
+
+/// Persisted monthly download counts and sizes per server, so a shared instance can limit them.
+pub struct QuotaTracker {
+    backend: Backend,
+}
+
+impl QuotaTracker {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        #[cfg(feature = "redis")]
+        if let Some(shared) = storage.shared() {
+            return Ok(Self {
+                backend: Backend::Redis(shared.clone()),
+            });
+        }
+
+        Ok(Self {
+            backend: Backend::Local(storage.open("quota_usage").await?),
+        })
+    }
+
+    /// Usage of a server in the month of `now` (unix seconds).
+    pub async fn usage(&self, guild_id: u64, now: u64) -> QuotaUsage {
+        let month = month_of(now);
+        let usage = match &self.backend {
+            Backend::Local(store) => {
+                store
+                    .read(|usages| {
+                        usages
+                            .iter()
+                            .find(|u| u.guild_id == guild_id && u.month == month)
+                            .cloned()
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(shared) => {
+                let key = shared.key(&format!("quota_usage:{month}:{guild_id}"));
+                match shared
+                    .connection()
+                    .hgetall::<_, HashMap<String, u64>>(key)
+                    .await
+                {
+                    Ok(counters) => Some(QuotaUsage {
+                        guild_id,
+                        month: month.clone(),
+                        downloads: counters.get("downloads").copied().unwrap_or(0),
+                        bytes: counters.get("bytes").copied().unwrap_or(0),
+                    }),
+                    Err(e) => {
+                        // Downloads go on while Redis is unavailable rather than all failing
+                        warn!("Failed to read quota usage from Redis: {}", e);
+                        None
+                    }
+                }
+            }
+        };
+
+        usage.unwrap_or(QuotaUsage {
+            guild_id,
+            month,
+            ..Default::default()
+        })
+    }
+
+    /// Counts a download of `bytes` towards the month of `now`, forgetting earlier months.
+    pub async fn record(&self, guild_id: u64, bytes: u64, now: u64) -> Result<()> {
+        let month = month_of(now);
+        match &self.backend {
+            Backend::Local(store) => {
+                store
+                    .update(|usages| {
+                        usages.retain(|u| u.month == month);
+                        match usages.iter_mut().find(|u| u.guild_id == guild_id) {
+                            Some(usage) => {
+                                usage.downloads += 1;
+                                usage.bytes += bytes;
+                            }
+                            None => usages.push(QuotaUsage {
+                                guild_id,
+                                month,
+                                downloads: 1,
+                                bytes,
+                            }),
+                        }
+                    })
+                    .await
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(shared) => {
+                let key = shared.key(&format!("quota_usage:{month}:{guild_id}"));
+                redis::pipe()
+                    .atomic()
+                    .hincr(&key, "downloads", 1)
+                    .ignore()
+                    .hincr(&key, "bytes", bytes)
+                    .ignore()
+                    .expire_at(&key, next_month_start(now) as i64)
+                    .ignore()
+                    .query_async::<()>(&mut shared.connection())
+                    .await
+                    .context("Failed to save quota usage to Redis")
+            }
+        }
+    }
+
+    /// Forgets a server's usage, returning how many months of it were kept.
+    pub async fn forget(&self, guild_id: u64) -> Result<usize> {
+        match &self.backend {
+            Backend::Local(store) => {
+                store
+                    .update(|usages| {
+                        let before = usages.len();
+                        usages.retain(|u| u.guild_id != guild_id);
+                        before - usages.len()
+                    })
+                    .await
+            }
+            // Counters of earlier months expired when they ended
+            #[cfg(feature = "redis")]
+            Backend::Redis(shared) => {
+                let month = month_of(crate::bot::expiry::unix_now());
+                let key = shared.key(&format!("quota_usage:{month}:{guild_id}"));
+                shared
+                    .connection()
+                    .del::<_, usize>(key)
+                    .await
+                    .context("Failed to delete quota usage from Redis")
+            }
+        }
+    }
+}
+
+/// Year and month of a day counted from the unix epoch.
+fn civil_from_days(days: u64) -> (u64, u64) {
+    // Howard Hinnant's algorithm, with eras starting on March 1st
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month)
+}
+
+/// Days from the unix epoch to the first of a month.
+fn days_from_civil(year: u64, month: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year % 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Month of `now` (unix seconds), e.g. "2026-10".
+pub fn month_of(now: u64) -> String {
+    let (year, month) = civil_from_days(now / SECS_PER_DAY);
+    format!("{year}-{month:02}")
+}
+
+/// Unix seconds at which the month after the one of `now` starts, when quotas reset.
+pub fn next_month_start(now: u64) -> u64 {
+    let (year, month) = civil_from_days(now / SECS_PER_DAY);
+    let (year, month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    days_from_civil(year, month) * SECS_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-10-16T12:00:00Z
+    const NOW: u64 = 1_792_152_000;
+
+    #[test]
+    fn test_months() {
+        assert_eq!(month_of(0), "1970-01");
+        assert_eq!(month_of(NOW), "2026-10");
+        // 2026-11-01T00:00:00Z
+        assert_eq!(next_month_start(NOW), 1_793_491_200);
+        assert_eq!(month_of(next_month_start(NOW)), "2026-11");
+        assert_eq!(month_of(next_month_start(NOW) - 1), "2026-10");
+        // December rolls over to January
+        assert_eq!(month_of(next_month_start(1_796_083_200)), "2027-01");
+    }
+
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    #[ignore = "Requires a Redis server at REDIS_URL"]
+    async fn test_usage_in_redis() {
+        let storage = crate::storage::redis::test_storage().await;
+        let tracker = QuotaTracker::open(&storage).await.unwrap();
+        let now = crate::bot::expiry::unix_now();
+
+        tracker.record(1, 100, now).await.unwrap();
+        // Instances sharing the Redis count towards the same quota
+        let other = QuotaTracker::open(&storage).await.unwrap();
+        other.record(1, 50, now).await.unwrap();
+
+        let usage = tracker.usage(1, now).await;
+        assert_eq!((usage.downloads, usage.bytes), (2, 150));
+        assert_eq!(tracker.usage(2, now).await.downloads, 0);
+        assert_eq!(tracker.usage(1, next_month_start(now)).await.downloads, 0);
+
+        assert_eq!(tracker.forget(1).await.unwrap(), 1);
+        assert_eq!(other.usage(1, now).await.downloads, 0);
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_tracked_per_guild_and_month() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker = QuotaTracker::open(&Storage::new(dir.path())).await.unwrap();
+
+        tracker.record(1, 100, NOW).await.unwrap();
+        tracker.record(1, 50, NOW).await.unwrap();
+        tracker.record(2, 10, NOW).await.unwrap();
+
+        let usage = tracker.usage(1, NOW).await;
+        assert_eq!((usage.downloads, usage.bytes), (2, 150));
+        assert!(usage.exhausted(Some(2), None));
+        assert!(usage.exhausted(None, Some(150)));
+        assert!(!usage.exhausted(Some(3), Some(151)));
+        assert!(!usage.exhausted(None, None));
+
+        // A new month starts from zero and drops the previous one
+        let next = next_month_start(NOW);
+        assert_eq!(tracker.usage(1, next).await.downloads, 0);
+        tracker.record(2, 10, next).await.unwrap();
+        assert_eq!(tracker.usage(1, NOW).await.downloads, 0);
+        assert_eq!(tracker.usage(2, next).await.downloads, 1);
+    }
+
+    #[tokio::test]
+    async fn test_forget_guild_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let tracker = QuotaTracker::open(&storage).await.unwrap();
+
+        tracker.record(1, 100, NOW).await.unwrap();
+        tracker.record(2, 10, NOW).await.unwrap();
+
+        assert_eq!(tracker.forget(1).await.unwrap(), 1);
+        assert_eq!(tracker.forget(1).await.unwrap(), 0);
+        assert_eq!(tracker.usage(1, NOW).await.downloads, 0);
+        assert_eq!(tracker.usage(2, NOW).await.downloads, 1);
+
+        // Forgetting persists
+        let reopened = QuotaTracker::open(&storage).await.unwrap();
+        assert_eq!(reopened.usage(1, NOW).await.downloads, 0);
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use twilight_http_ratelimiting::{Endpoint, Method, RateLimiter};
+
+/// Longest wait for a rate limit, beyond which the upload is tried anyway.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Endpoint an upload of a message to a channel counts towards.
+pub fn channel_upload(channel_id: u64) -> Endpoint {
+    Endpoint {
+        method: Method::Post,
+        path: format!("channels/{channel_id}/messages"),
+    }
+}
+
+/// Endpoint a repost through a channel's webhook counts towards.
+pub fn webhook_upload(webhook_id: u64, token: &str) -> Endpoint {
+    Endpoint {
+        method: Method::Post,
+        path: format!("webhooks/{webhook_id}/{token}"),
+    }
+}
+
+/// How long until a request to `endpoint` can be sent, if the requests before it used up its
+/// rate limit. The client would hold the request back for that long anyway, within the upload's
+/// timeout.
+pub async fn wait_for(ratelimiter: Option<&RateLimiter>, endpoint: Endpoint) -> Option<Duration> {
+    let bucket = ratelimiter?.bucket(endpoint).await?;
+    if bucket.remaining > 0 {
+        return None;
+    }
+    Some(bucket.reset_at.saturating_duration_since(Instant::now()))
+        .filter(|wait| !wait.is_zero())
+        .map(|wait| wait.min(MAX_WAIT))
+}
+
+/// Uploads per channel, one at a time, so galleries going to the same channel don't race each
+/// other into its rate limit and their parts stay together.
+#[derive(Default)]
+pub struct UploadLanes {
+    lanes: Mutex<HashMap<u64, Arc<AsyncMutex<()>>>>,
+}
+
+impl UploadLanes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for the channel's uploads before this one to finish. The channel is free again
+    /// once the returned guard is dropped.
+    pub async fn enter(&self, channel_id: u64) -> Lane<'_> {
+        let lane = self.lock().entry(channel_id).or_default().clone();
+        Lane {
+            lanes: self,
+            channel_id,
+            guard: Some(lane.lock_owned().await),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn busy(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Arc<AsyncMutex<()>>>> {
+        self.lanes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Turn of an upload in its channel.
+pub struct Lane<'a> {
+    lanes: &'a UploadLanes,
+    channel_id: u64,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for Lane<'_> {
+    fn drop(&mut self) {
+        drop(self.guard.take());
+        let mut lanes = self.lanes.lock();
+        // Forget the lane once nobody uses or waits for it anymore
+        if lanes
+            .get(&self.channel_id)
+            .is_some_and(|lane| Arc::strong_count(lane) == 1)
+        {
+            lanes.remove(&self.channel_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twilight_http_ratelimiting::RateLimitHeaders;
+
+    #[tokio::test]
+    async fn test_serializes_uploads_per_channel() {
+        let lanes = UploadLanes::new();
+
+        let first = lanes.enter(1).await;
+        // Other channels upload at the same time
+        let other = lanes.enter(2).await;
+
+        let second = lanes.enter(1);
+        tokio::pin!(second);
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut second)
+            .await
+            .is_err());
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(1), second)
+            .await
+            .expect("upload kept waiting for a finished one");
+        drop(second);
+        drop(other);
+        assert_eq!(lanes.busy(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_exhausted_bucket() {
+        let ratelimiter = RateLimiter::default();
+        assert_eq!(wait_for(None, channel_upload(1)).await, None);
+        assert_eq!(wait_for(Some(&ratelimiter), channel_upload(1)).await, None);
+
+        let permit = ratelimiter.acquire(channel_upload(1)).await;
+        permit.complete(Some(RateLimitHeaders {
+            bucket: b"messages".to_vec(),
+            limit: 5,
+            remaining: 0,
+            reset_at: Instant::now() + Duration::from_secs(2),
+        }));
+
+        let wait = wait_for(Some(&ratelimiter), channel_upload(1))
+            .await
+            .expect("exhausted bucket was not waited for");
+        assert!(wait > Duration::from_secs(1) && wait < Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use twilight_model::id::{marker::UserMarker, Id};
+
+/// Most times a failed download can be retried with its button.
+pub const MAX_RETRIES: u32 = 3;
+/// Time a failed download has to wait before it can be retried.
+pub const RETRY_COOLDOWN: Duration = Duration::from_secs(10);
+/// How long the button of a failed download stays usable.
+const RETRY_TTL: Duration = Duration::from_secs(3600);
+
+const CUSTOM_ID_PREFIX: &str = "retry:";
+
+/// Custom id of the retry button of the failed request `id`.
+pub fn custom_id(id: &str) -> String {
+    format!("{CUSTOM_ID_PREFIX}{id}")
+}
+
+pub fn id_from_custom_id(custom_id: &str) -> Option<&str> {
+    custom_id
+        .strip_prefix(CUSTOM_ID_PREFIX)
+        .filter(|id| !id.is_empty())
+}
+
+/// A failed download that can be run again, with what is needed to repeat it.
+#[derive(Debug, Clone)]
+pub struct RetryJob<T> {
+    pub payload: T,
+    /// User the download was made for, the only one who may retry it
+    pub user_id: Id<UserMarker>,
+    /// Retries already made
+    pub retries: u32,
+    failed_at: Instant,
+}
+
+/// Why a retry button did nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDenied {
+    /// Expired, already retried, or issued before a restart
+    Unknown,
+    NotRequester,
+    CoolingDown(Duration),
+}
+
+/// Failed downloads awaiting a retry, keyed by the id of their failed request.
+///
+/// Kept in memory only, buttons from before a restart are answered as expired.
+pub struct RetryQueue<T> {
+    jobs: Mutex<HashMap<String, RetryJob<T>>>,
+}
+
+impl<T> RetryQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remembers a failed download under its request id. Returns false once it has been retried
+    /// [`MAX_RETRIES`] times, in which case it should not get a button.
+    pub fn register(
+        &self,
+        id: &str,
+        payload: T,
+        user_id: Id<UserMarker>,
+        retries: u32,
+        now: Instant,
+    ) -> bool {
+        if retries >= MAX_RETRIES {
+            return false;
+        }
+
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.retain(|_, job| now.duration_since(job.failed_at) < RETRY_TTL);
+        jobs.insert(
+            id.to_string(),
+            RetryJob {
+                payload,
+                user_id,
+                retries,
+                failed_at: now,
+            },
+        );
+        true
+    }
+
+    /// Removes and returns the job if `user_id` may retry it at `now`.
+    pub fn take(
+        &self,
+        id: &str,
+        user_id: Id<UserMarker>,
+        now: Instant,
+    ) -> Result<RetryJob<T>, RetryDenied> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = jobs
+            .get(id)
+            .filter(|job| now.duration_since(job.failed_at) < RETRY_TTL)
+            .ok_or(RetryDenied::Unknown)?;
+
+        if job.user_id != user_id {
+            return Err(RetryDenied::NotRequester);
+        }
+        let waited = now.duration_since(job.failed_at);
+        if waited < RETRY_COOLDOWN {
+            return Err(RetryDenied::CoolingDown(RETRY_COOLDOWN - waited));
+        }
+
+        jobs.remove(id).ok_or(RetryDenied::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_id_round_trip() {
+        assert_eq!(id_from_custom_id(&custom_id("a1b2c3d4")), Some("a1b2c3d4"));
+        assert_eq!(id_from_custom_id("retry:"), None);
+        assert_eq!(id_from_custom_id("forget:guild:1"), None);
+    }
+
+    #[test]
+    fn test_take_checks_user_and_cooldown() {
+        let queue = RetryQueue::new();
+        let (user, other) = (Id::new(1), Id::new(2));
+        let now = Instant::now();
+
+        assert!(queue.register("abc", "https://example.com", user, 0, now));
+
+        assert_eq!(
+            queue.take("abc", user, now + Duration::from_secs(4)).err(),
+            Some(RetryDenied::CoolingDown(Duration::from_secs(6)))
+        );
+        let later = now + RETRY_COOLDOWN;
+        assert_eq!(
+            queue.take("abc", other, later).err(),
+            Some(RetryDenied::NotRequester)
+        );
+
+        let job = queue.take("abc", user, later).unwrap();
+        assert_eq!(job.payload, "https://example.com");
+        // Each button works once
+        assert_eq!(
+            queue.take("abc", user, later).err(),
+            Some(RetryDenied::Unknown)
+        );
+    }
+
+    #[test]
+    fn test_retries_are_limited_and_expire() {
+        let queue = RetryQueue::new();
+        let user = Id::new(1);
+        let now = Instant::now();
+
+        assert!(!queue.register("abc", (), user, MAX_RETRIES, now));
+        assert_eq!(
+            queue.take("abc", user, now + RETRY_COOLDOWN).err(),
+            Some(RetryDenied::Unknown)
+        );
+
+        assert!(queue.register("def", (), user, MAX_RETRIES - 1, now));
+        assert_eq!(
+            queue.take("def", user, now + RETRY_TTL).err(),
+            Some(RetryDenied::Unknown)
+        );
+    }
+}
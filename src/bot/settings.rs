@@ -0,0 +1,254 @@
+use super::callbacks::webhook_host;
+use crate::config::secret::SecretKey;
+use crate::config::{BlockEntry, ChannelConfig, ServerConfig};
+use crate::storage::{JsonStore, Storage};
+use anyhow::Result;
+use std::collections::HashMap;
+use tracing::warn;
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker},
+    Id,
+};
+
+/// Server settings `/admin` subcommands.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsCommand {
+    AutoEmbed {
+        channel_id: Id<ChannelMarker>,
+        enabled: bool,
+    },
+    EmbedCommand {
+        enabled: bool,
+    },
+    /// Embed settings of a channel, applied on top of its current ones unless `reset`
+    Channel {
+        channel_id: Id<ChannelMarker>,
+        overrides: Box<ChannelConfig>,
+        reset: bool,
+    },
+    /// Adds an entry to the server's blocklist, or removes it unless `blocked`
+    Block {
+        entry: BlockEntry,
+        blocked: bool,
+    },
+    BlockList,
+    /// URL download results are posted to, or `None` to stop posting them
+    JobWebhook {
+        url: Option<String>,
+    },
+    /// Channel auto-embeds of a channel are also posted to, or `None` to stop posting them
+    Mirror {
+        channel_id: Id<ChannelMarker>,
+        archive: Option<Id<ChannelMarker>>,
+    },
+}
+
+impl SettingsCommand {
+    /// The change in the form of the command that makes it, for the audit log.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::AutoEmbed {
+                channel_id,
+                enabled,
+            } => format!("auto-embed channel:<#{channel_id}> enabled:{enabled}"),
+            Self::EmbedCommand { enabled } => format!("embed-command enabled:{enabled}"),
+            Self::Channel {
+                channel_id,
+                overrides,
+                reset,
+            } => {
+                let mut change = format!("channel channel:<#{channel_id}>");
+                let settings = overrides.summary();
+                if !settings.is_empty() {
+                    change.push_str(&format!(" {settings}"));
+                }
+                if *reset {
+                    change.push_str(" reset:true");
+                }
+                change
+            }
+            Self::Block { entry, blocked } => {
+                let action = if *blocked { "add" } else { "remove" };
+                format!("block {action} {entry}")
+            }
+            Self::BlockList => "block list".to_string(),
+            // Webhook URLs carry their credentials, so only the host is recorded
+            Self::JobWebhook { url: Some(url) } => {
+                let host = webhook_host(url).unwrap_or_default();
+                format!("job-webhook url:{host}")
+            }
+            Self::JobWebhook { url: None } => "job-webhook clear".to_string(),
+            Self::Mirror {
+                channel_id,
+                archive: Some(archive),
+            } => format!("mirror channel:<#{channel_id}> archive:<#{archive}>"),
+            Self::Mirror {
+                channel_id,
+                archive: None,
+            } => format!("mirror channel:<#{channel_id}> clear"),
+        }
+    }
+}
+
+/// Server settings changed with commands, persisted so they survive restarts and reloads.
+///
+/// Saved settings replace the server's section of the config file as a whole.
+pub struct ServerSettings {
+    store: JsonStore<HashMap<Id<GuildMarker>, ServerConfig>>,
+    /// Encrypts secret values like job webhooks at rest, if `GRABBY_SECRET_KEY` is set
+    key: Option<SecretKey>,
+}
+
+impl ServerSettings {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        Self::open_with_key(storage, SecretKey::from_env()?).await
+    }
+
+    pub async fn open_with_key(storage: &Storage, key: Option<SecretKey>) -> Result<Self> {
+        Ok(Self {
+            store: storage.open("server_settings").await?,
+            key,
+        })
+    }
+
+    /// Saved settings of every server, leaving out those whose secrets can't be decrypted.
+    pub async fn all(&self) -> Vec<ServerConfig> {
+        let saved: Vec<ServerConfig> = self
+            .store
+            .read(|settings| settings.values().cloned().collect())
+            .await;
+        saved
+            .into_iter()
+            .filter_map(
+                |mut config| match config.reveal_secrets(self.key.as_ref()) {
+                    Ok(()) => Some(config),
+                    Err(e) => {
+                        warn!("Skipping saved settings: {:#}", e);
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    pub async fn save(&self, config: ServerConfig) -> Result<()> {
+        let config = config.sealed(self.key.as_ref())?;
+        self.store
+            .update(|settings| {
+                settings.insert(config.server_id, config);
+            })
+            .await
+    }
+
+    /// Forgets the saved settings of a server, returning whether it had any.
+    pub async fn remove(&self, guild_id: Id<GuildMarker>) -> Result<bool> {
+        self.store
+            .update(|settings| settings.remove(&guild_id).is_some())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let command = SettingsCommand::AutoEmbed {
+            channel_id: Id::new(10),
+            enabled: true,
+        };
+        assert_eq!(command.describe(), "auto-embed channel:<#10> enabled:true");
+
+        let command = SettingsCommand::Channel {
+            channel_id: Id::new(10),
+            overrides: Box::new(ChannelConfig {
+                audio_only: Some(true),
+                ..Default::default()
+            }),
+            reset: true,
+        };
+        assert_eq!(
+            command.describe(),
+            "channel channel:<#10> `audio-only`: true reset:true"
+        );
+
+        let command = SettingsCommand::Block {
+            entry: BlockEntry::Role(Id::new(20)),
+            blocked: false,
+        };
+        assert_eq!(command.describe(), "block remove <@&20>");
+
+        let command = SettingsCommand::JobWebhook {
+            url: Some("https://hooks.example.com/grabby?token=secret".to_string()),
+        };
+        assert_eq!(command.describe(), "job-webhook url:hooks.example.com");
+
+        let command = SettingsCommand::Mirror {
+            channel_id: Id::new(10),
+            archive: Some(Id::new(11)),
+        };
+        assert_eq!(command.describe(), "mirror channel:<#10> archive:<#11>");
+    }
+
+    #[tokio::test]
+    async fn test_settings_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        let settings = ServerSettings::open(&storage).await.unwrap();
+        let mut config = ServerConfig::new(Id::new(1));
+        config.auto_embed_channels.insert(Id::new(10));
+        settings.save(config.clone()).await.unwrap();
+        config.embed_enabled = false;
+        settings.save(config).await.unwrap();
+        drop(settings);
+
+        let settings = ServerSettings::open(&storage).await.unwrap();
+        let saved = settings.all().await;
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].is_auto_embed_channel(Id::new(10)));
+        assert!(!saved[0].embed_enabled);
+
+        assert!(settings.remove(Id::new(1)).await.unwrap());
+        assert!(!settings.remove(Id::new(1)).await.unwrap());
+        drop(settings);
+        let saved = ServerSettings::open(&storage).await.unwrap().all().await;
+        assert!(saved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_job_webhooks_are_encrypted_at_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let encoded = SecretKey::generate().unwrap();
+        let key = || Some(SecretKey::from_base64(&encoded).unwrap());
+        let url = "https://hooks.example.com/grabby?token=secret";
+
+        let settings = ServerSettings::open_with_key(&storage, key())
+            .await
+            .unwrap();
+        let mut config = ServerConfig::new(Id::new(1));
+        config.job_webhook = Some(url.to_string());
+        settings.save(config).await.unwrap();
+        drop(settings);
+
+        let stored = std::fs::read_to_string(dir.path().join("server_settings.json")).unwrap();
+        assert!(!stored.contains("token=secret"));
+
+        let saved = ServerSettings::open_with_key(&storage, key())
+            .await
+            .unwrap()
+            .all()
+            .await;
+        assert_eq!(saved[0].job_webhook.as_deref(), Some(url));
+
+        // Without the key the server's settings can't be used
+        let saved = ServerSettings::open_with_key(&storage, None)
+            .await
+            .unwrap()
+            .all()
+            .await;
+        assert!(saved.is_empty());
+    }
+}
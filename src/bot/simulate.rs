@@ -0,0 +1,242 @@
+use crate::config::NsfwPolicy;
+use crate::i18n::{t, tf, Locale};
+use crate::media::MediaMetadata;
+use crate::utils::{format_duration, format_size};
+
+/// What a simulated embed would have uploaded, posted in place of the upload.
+#[derive(Debug, Default)]
+pub struct SimulationReport {
+    pub url: String,
+    pub request_id: String,
+    /// Files that would be attached with their size, and their size before resizing
+    pub attachments: Vec<(String, u64, Option<u64>)>,
+    /// Files too large to attach that would be uploaded to the offload backend
+    pub offloaded: Vec<(String, u64)>,
+    /// Files too large to attach that would be left out
+    pub skipped: Vec<(String, u64)>,
+    pub caption: String,
+}
+
+impl SimulationReport {
+    pub fn render(&self, locale: Locale) -> String {
+        let mut lines = vec![tf(
+            locale,
+            "simulate.header",
+            &[("url", &self.url), ("id", &self.request_id)],
+        )];
+
+        for (file, size, original) in &self.attachments {
+            let size = format_size(*size);
+            lines.push(match original {
+                Some(original) => tf(
+                    locale,
+                    "simulate.resized",
+                    &[
+                        ("file", file),
+                        ("size", &size),
+                        ("original", &format_size(*original)),
+                    ],
+                ),
+                None => tf(
+                    locale,
+                    "simulate.attachment",
+                    &[("file", file), ("size", &size)],
+                ),
+            });
+        }
+        for (file, size) in &self.offloaded {
+            lines.push(tf(
+                locale,
+                "simulate.offloaded",
+                &[("file", file), ("size", &format_size(*size))],
+            ));
+        }
+        for (file, size) in &self.skipped {
+            lines.push(tf(
+                locale,
+                "simulate.skipped",
+                &[("file", file), ("size", &format_size(*size))],
+            ));
+        }
+
+        if self.attachments.is_empty() && self.offloaded.is_empty() {
+            lines.push(t(locale, "simulate.link_only").to_string());
+        } else if !self.caption.trim().is_empty() {
+            lines.push(t(locale, "simulate.caption").to_string());
+            lines.extend(
+                self.caption
+                    .trim()
+                    .lines()
+                    .map(|line| format!("> {}", line.trim_start_matches("> "))),
+            );
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// What a metadata-only simulation found out about a link.
+#[derive(Debug)]
+pub struct MetadataReport<'a> {
+    pub url: &'a str,
+    pub request_id: &'a str,
+    pub metadata: &'a MediaMetadata,
+    pub downloader_order: &'a [String],
+    pub upload_limit: u64,
+    /// Longest video embedded, if capped
+    pub max_duration_secs: Option<u64>,
+    pub nsfw_policy: NsfwPolicy,
+}
+
+impl MetadataReport<'_> {
+    pub fn render(&self, locale: Locale) -> String {
+        let metadata = self.metadata;
+        let mut lines = vec![tf(
+            locale,
+            "simulate.metadata_header",
+            &[("url", self.url), ("id", self.request_id)],
+        )];
+
+        if !metadata.title.is_empty() {
+            lines.push(format!("> {}", metadata.title));
+        }
+        if let Some(author) = &metadata.author {
+            lines.push(format!("{}: {}", t(locale, "info.author"), author));
+        }
+        if let Some(site) = &metadata.site {
+            lines.push(format!("{}: {}", t(locale, "info.site"), site));
+        }
+        if metadata.live {
+            lines.push(t(locale, "info.live").to_string());
+        } else if let Some(duration) = metadata.duration {
+            lines.push(format!(
+                "{}: {}",
+                t(locale, "info.duration"),
+                format_duration(duration)
+            ));
+        }
+
+        if metadata.live {
+            lines.push(format!("❌ {}", t(locale, "error.live_stream")));
+        }
+        if let (Some(duration), Some(max_secs)) = (metadata.duration, self.max_duration_secs) {
+            if duration > max_secs {
+                lines.push(format!(
+                    "❌ {}",
+                    tf(
+                        locale,
+                        "error.too_long_limit",
+                        &[
+                            ("duration", &format_duration(duration)),
+                            ("limit", &format_duration(max_secs)),
+                        ],
+                    )
+                ));
+            }
+        }
+        if metadata.nsfw {
+            match self.nsfw_policy {
+                NsfwPolicy::Allow => {}
+                NsfwPolicy::Spoiler => lines.push(t(locale, "simulate.spoiler").to_string()),
+                NsfwPolicy::Block => {
+                    lines.push(format!("❌ {}", t(locale, "media.nsfw_blocked")));
+                }
+            }
+        }
+
+        if !self.downloader_order.is_empty() {
+            lines.push(tf(
+                locale,
+                "simulate.downloaders",
+                &[("order", &self.downloader_order.join(" → "))],
+            ));
+        }
+        lines.push(tf(
+            locale,
+            "simulate.upload_limit",
+            &[("size", &format_size(self.upload_limit))],
+        ));
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_files_and_caption() {
+        let report = SimulationReport {
+            url: "https://example.com/v".to_string(),
+            request_id: "abc123".to_string(),
+            attachments: vec![
+                ("clip.mp4".to_string(), 8_000_000, Some(30_000_000)),
+                ("cover.jpg".to_string(), 1_500, None),
+            ],
+            offloaded: vec![("long.mp4".to_string(), 90_000_000)],
+            skipped: vec![],
+            caption: "<@1>\nhttps://example.com/v\n> Title".to_string(),
+        };
+
+        let rendered = report.render(Locale::En);
+        assert!(rendered.contains("`abc123`"));
+        assert!(rendered.contains("`clip.mp4` (8.0 MB, resized from 30.0 MB)"));
+        assert!(rendered.contains("`cover.jpg` (1.5 KB)"));
+        assert!(rendered.contains("`long.mp4` (90.0 MB) would be offloaded"));
+        assert!(rendered.ends_with("> <@1>\n> https://example.com/v\n> Title"));
+    }
+
+    #[test]
+    fn test_report_without_files_falls_back_to_link() {
+        let report = SimulationReport {
+            skipped: vec![("huge.mp4".to_string(), 2_000_000_000)],
+            caption: "caption".to_string(),
+            ..Default::default()
+        };
+
+        let rendered = report.render(Locale::En);
+        assert!(rendered.contains("`huge.mp4` (2.0 GB) would be skipped"));
+        assert!(rendered.contains("the link would be posted instead"));
+        assert!(!rendered.contains("caption"));
+    }
+
+    #[test]
+    fn test_metadata_report_flags_refusals() {
+        let metadata = MediaMetadata {
+            title: "Long video".to_string(),
+            id: "long".to_string(),
+            thumbnail: None,
+            duration: Some(7200),
+            author: None,
+            likes: None,
+            description: None,
+            view_count: None,
+            uploaded_at: None,
+            site: None,
+            format_ext: "mp4".to_string(),
+            chapters: Vec::new(),
+            nsfw: true,
+            live: false,
+            formats: Vec::new(),
+        };
+        let order = vec!["gallery-dl".to_string(), "yt-dlp".to_string()];
+        let report = MetadataReport {
+            url: "https://example.com/v",
+            request_id: "abc123",
+            metadata: &metadata,
+            downloader_order: &order,
+            upload_limit: 25_000_000,
+            max_duration_secs: Some(600),
+            nsfw_policy: NsfwPolicy::Spoiler,
+        };
+
+        let rendered = report.render(Locale::En);
+        assert!(rendered.contains("> Long video"));
+        assert!(rendered.contains("Duration: 2:00:00"));
+        assert!(rendered.contains("❌ Video is too long (2:00:00 > 10:00 limit)"));
+        assert!(rendered.contains("behind a spoiler"));
+        assert!(rendered.contains("Downloaders: gallery-dl → yt-dlp"));
+        assert!(rendered.contains("Upload limit: 25.0 MB"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Votes to delete a bot upload.
+struct Ballot {
+    opened_at: Instant,
+    voters: HashSet<u64>,
+}
+
+/// ❌ reactions on bot uploads from members other than the requester, counted towards deleting
+/// the upload once enough distinct members reacted within a window.
+///
+/// Kept in memory only, votes from before a restart are forgotten.
+pub struct DeleteVotes {
+    ballots: Mutex<HashMap<u64, Ballot>>,
+}
+
+impl DeleteVotes {
+    pub fn new() -> Self {
+        Self {
+            ballots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts the vote of `user_id` on `message_id`, returning true once `threshold` distinct
+    /// members voted within `window` of the first vote.
+    pub fn vote(
+        &self,
+        message_id: u64,
+        user_id: u64,
+        threshold: usize,
+        window: Duration,
+        now: Instant,
+    ) -> bool {
+        let mut ballots = self.ballots.lock().unwrap_or_else(PoisonError::into_inner);
+        ballots.retain(|_, ballot| now.duration_since(ballot.opened_at) < window);
+
+        let ballot = ballots.entry(message_id).or_insert_with(|| Ballot {
+            opened_at: now,
+            voters: HashSet::new(),
+        });
+        ballot.voters.insert(user_id);
+
+        let passed = ballot.voters.len() >= threshold;
+        if passed {
+            ballots.remove(&message_id);
+        }
+        passed
+    }
+
+    /// Takes back a vote whose reaction was removed.
+    pub fn retract(&self, message_id: u64, user_id: u64) {
+        let mut ballots = self.ballots.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ballot) = ballots.get_mut(&message_id) {
+            ballot.voters.remove(&user_id);
+            if ballot.voters.is_empty() {
+                ballots.remove(&message_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(600);
+
+    #[test]
+    fn test_distinct_votes_reach_threshold() {
+        let votes = DeleteVotes::new();
+        let now = Instant::now();
+
+        assert!(!votes.vote(1, 10, 3, WINDOW, now));
+        // Reacting again doesn't count twice
+        assert!(!votes.vote(1, 10, 3, WINDOW, now));
+        assert!(!votes.vote(1, 11, 3, WINDOW, now));
+        assert!(!votes.vote(2, 12, 3, WINDOW, now));
+        assert!(votes.vote(1, 12, 3, WINDOW, now));
+    }
+
+    #[test]
+    fn test_votes_expire_and_can_be_retracted() {
+        let votes = DeleteVotes::new();
+        let now = Instant::now();
+
+        assert!(!votes.vote(1, 10, 2, WINDOW, now));
+        assert!(!votes.vote(1, 11, 2, WINDOW, now + WINDOW));
+
+        votes.retract(1, 11);
+        assert!(!votes.vote(1, 12, 2, WINDOW, now + WINDOW));
+        assert!(votes.vote(1, 13, 2, WINDOW, now + WINDOW));
+    }
+}
@@ -0,0 +1,291 @@
+use super::expiry::unix_now;
+use crate::storage::{JsonStore, Storage};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Entry ids remembered per feed, a few polls' worth, so posts dropping out of a feed and coming
+/// back aren't embedded twice.
+const SEEN_LIMIT: usize = 200;
+
+/// A creator feed whose new posts are embedded into a channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Number shown by `/watch list` and taken by `/watch remove`
+    pub id: u64,
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub url: String,
+    /// Member who added the subscription
+    pub added_by: Option<u64>,
+    pub added_at: u64,
+    /// Ids of the entries already embedded or in the feed when it was added, oldest first
+    pub seen: VecDeque<String>,
+}
+
+impl Subscription {
+    pub fn has_seen(&self, entry_id: &str) -> bool {
+        self.seen.iter().any(|seen| seen == entry_id)
+    }
+
+    /// One line of `/watch list`.
+    pub fn render(&self) -> String {
+        format!("`#{}` <{}> → <#{}>", self.id, self.url, self.channel_id)
+    }
+}
+
+/// `/watch` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchCommand {
+    Add {
+        url: String,
+        channel_id: Option<u64>,
+    },
+    Remove {
+        id: u64,
+    },
+    List,
+}
+
+/// Outcome of adding a subscription.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Added {
+    New(Subscription),
+    /// The channel already watches the feed, under this subscription id
+    Duplicate(u64),
+    /// The server watches as many feeds as it may
+    LimitReached,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Subscriptions {
+    next_id: u64,
+    subscriptions: Vec<Subscription>,
+}
+
+/// Persisted feed subscriptions of every server, with the posts each has seen.
+pub struct WatchList {
+    store: JsonStore<Subscriptions>,
+}
+
+impl WatchList {
+    pub async fn open(storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            store: storage.open("watch_subscriptions").await?,
+        })
+    }
+
+    /// Subscribes the channel to the feed at `url`, with the feed's current entries marked as seen
+    /// so only posts published from now on are embedded.
+    pub async fn add(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        url: &str,
+        added_by: Option<u64>,
+        seen: Vec<String>,
+        max: usize,
+    ) -> Result<Added> {
+        self.store
+            .update(|data| {
+                let in_guild = data.subscriptions.iter().filter(|s| s.guild_id == guild_id);
+                if let Some(existing) = in_guild
+                    .clone()
+                    .find(|s| s.channel_id == channel_id && s.url == url)
+                {
+                    return Added::Duplicate(existing.id);
+                }
+                if in_guild.count() >= max {
+                    return Added::LimitReached;
+                }
+
+                data.next_id += 1;
+                let mut subscription = Subscription {
+                    id: data.next_id,
+                    guild_id,
+                    channel_id,
+                    url: url.to_string(),
+                    added_by,
+                    added_at: unix_now(),
+                    seen: VecDeque::new(),
+                };
+                remember(&mut subscription, seen);
+                data.subscriptions.push(subscription.clone());
+                Added::New(subscription)
+            })
+            .await
+    }
+
+    /// Removes a subscription of the server, returning it if it existed.
+    pub async fn remove(&self, guild_id: u64, id: u64) -> Result<Option<Subscription>> {
+        self.store
+            .update(|data| {
+                let index = data
+                    .subscriptions
+                    .iter()
+                    .position(|s| s.guild_id == guild_id && s.id == id)?;
+                Some(data.subscriptions.remove(index))
+            })
+            .await
+    }
+
+    pub async fn for_guild(&self, guild_id: u64) -> Vec<Subscription> {
+        self.store
+            .read(|data| {
+                data.subscriptions
+                    .iter()
+                    .filter(|s| s.guild_id == guild_id)
+                    .cloned()
+                    .collect()
+            })
+            .await
+    }
+
+    pub async fn all(&self) -> Vec<Subscription> {
+        self.store.read(|data| data.subscriptions.clone()).await
+    }
+
+    /// Marks entries of a subscription as seen. Does nothing if it was removed meanwhile.
+    pub async fn mark_seen(&self, id: u64, entry_ids: Vec<String>) -> Result<()> {
+        self.store
+            .update(|data| {
+                if let Some(subscription) = data.subscriptions.iter_mut().find(|s| s.id == id) {
+                    remember(subscription, entry_ids);
+                }
+            })
+            .await
+    }
+
+    /// Removes and returns every subscription matching `predicate`.
+    pub async fn take_where(
+        &self,
+        predicate: impl Fn(&Subscription) -> bool,
+    ) -> Result<Vec<Subscription>> {
+        self.store
+            .update(|data| {
+                let (taken, remaining) = data.subscriptions.drain(..).partition(predicate);
+                data.subscriptions = remaining;
+                taken
+            })
+            .await
+    }
+}
+
+fn remember(subscription: &mut Subscription, entry_ids: Vec<String>) {
+    for entry_id in entry_ids {
+        if !subscription.has_seen(&entry_id) {
+            subscription.seen.push_back(entry_id);
+        }
+    }
+    while subscription.seen.len() > SEEN_LIMIT {
+        subscription.seen.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_add_enforces_limit_and_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let watches = WatchList::open(&Storage::new(dir.path())).await.unwrap();
+
+        let Added::New(first) = watches
+            .add(1, 10, "https://example.com/a", Some(5), ids(&["x"]), 2)
+            .await
+            .unwrap()
+        else {
+            panic!("first subscription was not added");
+        };
+        assert!(first.has_seen("x"));
+        assert_eq!(
+            watches
+                .add(1, 10, "https://example.com/a", Some(5), Vec::new(), 2)
+                .await
+                .unwrap(),
+            Added::Duplicate(first.id)
+        );
+        assert!(matches!(
+            watches
+                .add(1, 11, "https://example.com/a", Some(5), Vec::new(), 2)
+                .await
+                .unwrap(),
+            Added::New(_)
+        ));
+        assert_eq!(
+            watches
+                .add(1, 10, "https://example.com/b", Some(5), Vec::new(), 2)
+                .await
+                .unwrap(),
+            Added::LimitReached
+        );
+        // Other servers have limits of their own
+        assert!(matches!(
+            watches
+                .add(2, 20, "https://example.com/b", Some(5), Vec::new(), 2)
+                .await
+                .unwrap(),
+            Added::New(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_seen_entries_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        let watches = WatchList::open(&storage).await.unwrap();
+        watches
+            .add(1, 10, "https://example.com/a", Some(5), Vec::new(), 10)
+            .await
+            .unwrap();
+        watches.mark_seen(1, ids(&["a", "b"])).await.unwrap();
+        drop(watches);
+
+        let watches = WatchList::open(&storage).await.unwrap();
+        let subscriptions = watches.all().await;
+        assert_eq!(subscriptions.len(), 1);
+        assert!(subscriptions[0].has_seen("b"));
+        assert!(!subscriptions[0].has_seen("c"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_is_scoped_to_guild() {
+        let dir = tempfile::tempdir().unwrap();
+        let watches = WatchList::open(&Storage::new(dir.path())).await.unwrap();
+        watches
+            .add(1, 10, "https://example.com/a", Some(5), Vec::new(), 10)
+            .await
+            .unwrap();
+
+        assert_eq!(watches.remove(2, 1).await.unwrap(), None);
+        assert!(watches.remove(1, 1).await.unwrap().is_some());
+        assert!(watches.for_guild(1).await.is_empty());
+    }
+
+    #[test]
+    fn test_seen_is_bounded() {
+        let mut subscription = Subscription {
+            id: 1,
+            guild_id: 1,
+            channel_id: 10,
+            url: "https://example.com/a".to_string(),
+            added_by: Some(5),
+            added_at: 0,
+            seen: VecDeque::new(),
+        };
+        remember(
+            &mut subscription,
+            (0..SEEN_LIMIT + 5).map(|i| i.to_string()).collect(),
+        );
+
+        assert_eq!(subscription.seen.len(), SEEN_LIMIT);
+        assert!(!subscription.has_seen("0"));
+        assert!(subscription.has_seen(&(SEEN_LIMIT + 4).to_string()));
+    }
+}
@@ -0,0 +1,298 @@
+use super::expiry::unix_now;
+use super::ratelimit;
+use crate::storage::{JsonStore, Storage};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use twilight_http::Client as HttpClient;
+use twilight_http_ratelimiting::Endpoint;
+use twilight_model::{
+    channel::message::{Message, MessageFlags},
+    gateway::payload::incoming::MessageCreate,
+    http::attachment::Attachment,
+    id::{
+        marker::{ChannelMarker, GuildMarker, UserMarker, WebhookMarker},
+        Id,
+    },
+    user::User,
+    util::ImageHash,
+};
+
+const WEBHOOK_NAME: &str = "Grabby";
+
+/// Reposts older than this are no longer deletable via reaction, so their records are dropped.
+const REPOST_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// A message reposted through a webhook, kept so the original author can still delete it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repost {
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub user_id: u64,
+    pub posted_at: u64,
+}
+
+#[derive(Clone)]
+struct ChannelWebhook {
+    id: Id<WebhookMarker>,
+    token: String,
+}
+
+/// Everything needed to repost a message under its author's name and avatar.
+pub struct RepostAs {
+    webhook: ChannelWebhook,
+    thread_id: Option<Id<ChannelMarker>>,
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub user_id: Id<UserMarker>,
+    username: String,
+    avatar_url: String,
+    pub content: String,
+}
+
+impl RepostAs {
+    /// Rate limited endpoint the repost is uploaded to.
+    pub fn endpoint(&self) -> Endpoint {
+        ratelimit::webhook_upload(self.webhook.id.get(), &self.webhook.token)
+    }
+}
+
+pub struct WebhookReposter {
+    http: Arc<HttpClient>,
+    bot_user_id: Id<UserMarker>,
+    webhooks: Mutex<HashMap<Id<ChannelMarker>, ChannelWebhook>>,
+    reposts: JsonStore<Vec<Repost>>,
+}
+
+impl WebhookReposter {
+    pub async fn open(
+        http: Arc<HttpClient>,
+        bot_user_id: Id<UserMarker>,
+        storage: &Storage,
+    ) -> Result<Self> {
+        Ok(Self {
+            http,
+            bot_user_id,
+            webhooks: Mutex::new(HashMap::new()),
+            reposts: storage.open("webhook_reposts").await?,
+        })
+    }
+
+    /// Resolves the webhook to repost `msg` with, or `None` if the bot can't manage webhooks there.
+    pub async fn prepare(
+        &self,
+        msg: &MessageCreate,
+        thread_parent_id: Option<Id<ChannelMarker>>,
+    ) -> Option<RepostAs> {
+        // Webhooks belong to the parent channel and post into threads by id
+        let webhook_channel = thread_parent_id.unwrap_or(msg.channel_id);
+        let webhook = match self.webhook_for(webhook_channel).await {
+            Ok(webhook) => webhook,
+            Err(e) => {
+                warn!(
+                    "Cannot repost via webhook in channel {} (missing Manage Webhooks?): {}",
+                    webhook_channel, e
+                );
+                return None;
+            }
+        };
+
+        let member = msg.member.as_ref();
+        let username = member
+            .and_then(|m| m.nick.clone())
+            .or_else(|| msg.author.global_name.clone())
+            .unwrap_or_else(|| msg.author.name.clone());
+        let member_avatar = msg
+            .guild_id
+            .zip(member.and_then(|m| m.avatar))
+            .map(|(guild_id, hash)| (guild_id.get(), hash));
+
+        Some(RepostAs {
+            webhook,
+            thread_id: thread_parent_id.map(|_| msg.channel_id),
+            guild_id: msg.guild_id,
+            user_id: msg.author.id,
+            username: webhook_username(&username),
+            avatar_url: avatar_url(&msg.author, member_avatar),
+            content: msg.content.clone(),
+        })
+    }
+
+    pub async fn execute(
+        &self,
+        repost: &RepostAs,
+        content: &str,
+        attachments: &[Attachment],
+    ) -> Result<Message> {
+        let mut request = self
+            .http
+            .execute_webhook(repost.webhook.id, &repost.webhook.token)
+            .username(&repost.username)
+            .avatar_url(&repost.avatar_url)
+            .content(content)
+            .attachments(attachments)
+            .flags(MessageFlags::SUPPRESS_EMBEDS);
+        if let Some(thread_id) = repost.thread_id {
+            request = request.thread_id(thread_id);
+        }
+
+        Ok(request.wait().await?.model().await?)
+    }
+
+    pub async fn record(&self, repost: &RepostAs, message: &Message) -> Result<()> {
+        let now = unix_now();
+        let repost = Repost {
+            guild_id: repost.guild_id.map(|id| id.get()),
+            channel_id: message.channel_id.get(),
+            message_id: message.id.get(),
+            user_id: repost.user_id.get(),
+            posted_at: now,
+        };
+
+        self.reposts
+            .update(|reposts| {
+                reposts.retain(|r| r.posted_at + REPOST_RETENTION_SECS > now);
+                reposts.push(repost);
+            })
+            .await
+    }
+
+    /// Returns the user a webhook message was reposted for.
+    pub async fn author_of(&self, message_id: u64) -> Option<u64> {
+        self.reposts
+            .read(|reposts| {
+                reposts
+                    .iter()
+                    .find(|r| r.message_id == message_id)
+                    .map(|r| r.user_id)
+            })
+            .await
+    }
+
+    /// Forgets the cached channel webhooks, returning how many there were.
+    pub async fn clear_webhooks(&self) -> usize {
+        let mut webhooks = self.webhooks.lock().await;
+        let count = webhooks.len();
+        webhooks.clear();
+        count
+    }
+
+    /// Removes and returns every repost record matching `predicate`.
+    pub async fn take_where(&self, predicate: impl Fn(&Repost) -> bool) -> Result<Vec<Repost>> {
+        self.reposts
+            .update(|reposts| {
+                let (taken, remaining) = reposts.drain(..).partition(predicate);
+                *reposts = remaining;
+                taken
+            })
+            .await
+    }
+
+    async fn webhook_for(&self, channel_id: Id<ChannelMarker>) -> Result<ChannelWebhook> {
+        let mut webhooks = self.webhooks.lock().await;
+        if let Some(webhook) = webhooks.get(&channel_id) {
+            return Ok(webhook.clone());
+        }
+
+        let existing = self
+            .http
+            .channel_webhooks(channel_id)
+            .await
+            .context("Failed to list channel webhooks")?
+            .models()
+            .await?
+            .into_iter()
+            .find(|w| w.user.as_ref().map(|u| u.id) == Some(self.bot_user_id) && w.token.is_some());
+
+        let webhook = match existing {
+            Some(webhook) => webhook,
+            None => {
+                info!("Creating repost webhook in channel {}", channel_id);
+                self.http
+                    .create_webhook(channel_id, WEBHOOK_NAME)
+                    .await
+                    .context("Failed to create webhook")?
+                    .model()
+                    .await?
+            }
+        };
+
+        let webhook = ChannelWebhook {
+            id: webhook.id,
+            token: webhook.token.context("Webhook has no token")?,
+        };
+        webhooks.insert(channel_id, webhook.clone());
+        Ok(webhook)
+    }
+}
+
+/// Builds the CDN URL of the avatar shown for `user`, preferring their per-guild avatar.
+pub fn avatar_url(user: &User, member_avatar: Option<(u64, ImageHash)>) -> String {
+    if let Some((guild_id, hash)) = member_avatar {
+        return format!(
+            "https://cdn.discordapp.com/guilds/{}/users/{}/avatars/{}.png",
+            guild_id, user.id, hash
+        );
+    }
+
+    match user.avatar {
+        Some(hash) => format!(
+            "https://cdn.discordapp.com/avatars/{}/{}.png",
+            user.id, hash
+        ),
+        None => format!(
+            "https://cdn.discordapp.com/embed/avatars/{}.png",
+            (user.id.get() >> 22) % 6
+        ),
+    }
+}
+
+/// Webhook usernames are limited to 80 characters.
+fn webhook_username(name: &str) -> String {
+    name.chars().take(80).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(avatar: Option<ImageHash>) -> User {
+        serde_json::from_value(serde_json::json!({
+            "id": "80351110224678912",
+            "username": "nelly",
+            "discriminator": "0",
+            "avatar": avatar.map(|hash| hash.to_string()),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_avatar_url() {
+        let hash = ImageHash::parse(b"8342729096ea3675442027381ff50dfe").unwrap();
+
+        assert_eq!(
+            avatar_url(&user(Some(hash)), None),
+            "https://cdn.discordapp.com/avatars/80351110224678912/8342729096ea3675442027381ff50dfe.png"
+        );
+        assert_eq!(
+            avatar_url(&user(Some(hash)), Some((1, hash))),
+            "https://cdn.discordapp.com/guilds/1/users/80351110224678912/avatars/8342729096ea3675442027381ff50dfe.png"
+        );
+        assert_eq!(
+            avatar_url(&user(None), None),
+            format!(
+                "https://cdn.discordapp.com/embed/avatars/{}.png",
+                (80351110224678912u64 >> 22) % 6
+            )
+        );
+    }
+
+    #[test]
+    fn test_webhook_username_is_truncated() {
+        assert_eq!(webhook_username("nelly"), "nelly");
+        assert_eq!(webhook_username(&"a".repeat(100)).len(), 80);
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "redis")]
+mod redis;
+
+#[cfg(feature = "redis")]
+pub use self::redis::RedisCoordinator;
+
+use crate::config::{Config, ServerConfig};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+use twilight_gateway::ShardId;
+use twilight_model::id::{marker::GuildMarker, Id};
+
+/// Zero-based shard of this instance, taken from Discord's shard assignment.
+pub const SHARD_ID_ENV: &str = "GRABBY_SHARD_ID";
+/// Number of shards the bot's servers are split between.
+pub const SHARD_COUNT_ENV: &str = "GRABBY_SHARD_COUNT";
+
+/// Coordination with other instances of the bot sharing the same backend, so several of them
+/// can run side by side without embedding a link twice.
+#[async_trait]
+pub trait Coordinator: Send + Sync {
+    /// Human-readable name of the backend
+    fn name(&self) -> &'static str;
+
+    /// Claims a job for this instance, returning false if another instance already has it.
+    /// Claims expire on their own after the configured claim TTL.
+    async fn try_claim(&self, job: &str) -> Result<bool>;
+
+    /// Server settings changed with commands on any instance.
+    async fn server_settings(&self) -> Result<Vec<ServerConfig>>;
+
+    async fn save_server_settings(&self, config: &ServerConfig) -> Result<()>;
+
+    /// Forgets the shared settings of a server, returning whether it had any.
+    async fn remove_server_settings(&self, guild_id: Id<GuildMarker>) -> Result<bool>;
+}
+
+/// Connects to the backend of the `[cluster]` section, `None` if the instance runs on its own.
+pub async fn connect(config: &Config) -> Result<Option<Arc<dyn Coordinator>>> {
+    let Some(url) = config.get_redis_url() else {
+        return Ok(None);
+    };
+
+    #[cfg(feature = "redis")]
+    {
+        let store =
+            crate::storage::redis::RedisStore::connect(url, config.get_cluster_key_prefix())
+                .await?;
+        Ok(Some(Arc::new(RedisCoordinator::new(
+            store,
+            config.get_claim_ttl(),
+            crate::config::secret::SecretKey::from_env()?,
+        ))))
+    }
+
+    #[cfg(not(feature = "redis"))]
+    {
+        let _ = url;
+        Err(anyhow!(
+            "cluster.redis_url is set, but coordinating instances needs the redis feature"
+        ))
+    }
+}
+
+/// Shard of this instance from `GRABBY_SHARD_ID` and `GRABBY_SHARD_COUNT`, the only shard if
+/// neither is set.
+pub fn shard_from_env() -> Result<ShardId> {
+    parse_shard(
+        std::env::var(SHARD_ID_ENV).ok().as_deref(),
+        std::env::var(SHARD_COUNT_ENV).ok().as_deref(),
+    )
+}
+
+fn parse_shard(id: Option<&str>, count: Option<&str>) -> Result<ShardId> {
+    let (id, count) = match (id, count) {
+        (None, None) => return Ok(ShardId::ONE),
+        (Some(id), Some(count)) => (id, count),
+        _ => {
+            return Err(anyhow!(
+                "{SHARD_ID_ENV} and {SHARD_COUNT_ENV} have to be set together"
+            ))
+        }
+    };
+    let id: u32 = id
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid {SHARD_ID_ENV}: {id}"))?;
+    let count: u32 = count
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid {SHARD_COUNT_ENV}: {count}"))?;
+    ShardId::new_checked(id, count)
+        .with_context(|| format!("Shard {id} is out of range for {count} shards"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!(parse_shard(None, None).unwrap(), ShardId::ONE);
+        assert_eq!(
+            parse_shard(Some("2"), Some(" 4")).unwrap(),
+            ShardId::new(2, 4)
+        );
+        assert!(parse_shard(Some("4"), Some("4")).is_err());
+        assert!(parse_shard(Some("0"), Some("0")).is_err());
+        assert!(parse_shard(Some("1"), None).is_err());
+        assert!(parse_shard(Some("one"), Some("2")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_instances_run_alone_without_redis() {
+        assert!(connect(&Config::default()).await.unwrap().is_none());
+    }
+}
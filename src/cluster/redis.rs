@@ -0,0 +1,94 @@
+use super::Coordinator;
+use crate::config::{secret::SecretKey, ServerConfig};
+use crate::storage::redis::RedisStore;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::time::Duration;
+use twilight_model::id::{marker::GuildMarker, Id};
+
+/// Coordinates instances through a Redis they all connect to.
+///
+/// Claims are keys set only if missing, which expire after the claim TTL. Server settings are
+/// kept as JSON in a hash keyed by server id.
+pub struct RedisCoordinator {
+    store: RedisStore,
+    claim_ttl: Duration,
+    /// Encrypts secret values of server settings, if `GRABBY_SECRET_KEY` is set
+    key: Option<SecretKey>,
+}
+
+impl RedisCoordinator {
+    pub fn new(store: RedisStore, claim_ttl: Duration, key: Option<SecretKey>) -> Self {
+        Self {
+            store,
+            claim_ttl,
+            key,
+        }
+    }
+}
+
+#[async_trait]
+impl Coordinator for RedisCoordinator {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn try_claim(&self, job: &str) -> Result<bool> {
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(self.store.key(&format!("claim:{job}")))
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(self.claim_ttl.as_millis() as u64)
+            .query_async(&mut self.store.connection())
+            .await
+            .context("Failed to claim job in Redis")?;
+        Ok(claimed.is_some())
+    }
+
+    async fn server_settings(&self) -> Result<Vec<ServerConfig>> {
+        let settings: HashMap<String, String> = self
+            .store
+            .connection()
+            .hgetall(self.store.key("server_settings"))
+            .await
+            .context("Failed to read server settings from Redis")?;
+
+        settings
+            .into_iter()
+            .map(|(server_id, json)| {
+                let mut config: ServerConfig = serde_json::from_str(&json)
+                    .with_context(|| format!("Invalid settings of server {server_id} in Redis"))?;
+                config.reveal_secrets(self.key.as_ref())?;
+                Ok(config)
+            })
+            .collect()
+    }
+
+    async fn save_server_settings(&self, config: &ServerConfig) -> Result<()> {
+        let json = serde_json::to_string(&config.sealed(self.key.as_ref())?)?;
+        let _: () = self
+            .store
+            .connection()
+            .hset(
+                self.store.key("server_settings"),
+                config.server_id.to_string(),
+                json,
+            )
+            .await
+            .context("Failed to save server settings to Redis")?;
+        Ok(())
+    }
+
+    async fn remove_server_settings(&self, guild_id: Id<GuildMarker>) -> Result<bool> {
+        let removed: u64 = self
+            .store
+            .connection()
+            .hdel(self.store.key("server_settings"), guild_id.to_string())
+            .await
+            .context("Failed to remove server settings from Redis")?;
+        Ok(removed > 0)
+    }
+}
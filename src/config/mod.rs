@@ -1,314 +1,2551 @@
+pub mod secret;
+
+use crate::i18n::Locale;
+use crate::media::{
+    AudioFormat, DurationLimit, FileScanner, GalleryDlSite, HttpSettings, ImageLimits,
+    OutputContainer, QueuePolicy, Scanner, StageTimeouts, Timeouts, Tool, ToolPin, VideoCodec,
+};
+use crate::storage::StorageBackend;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker, RoleMarker, SkuMarker, UserMarker},
+    Id,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
-    pub server_id: String,
-    pub auto_embed_channels: HashSet<String>,
+    pub server_id: Id<GuildMarker>,
+    pub auto_embed_channels: HashSet<Id<ChannelMarker>>,
+    /// Channels whose threads and forum posts are all auto-embedded
+    #[serde(default)]
+    pub auto_embed_thread_parents: HashSet<Id<ChannelMarker>>,
     pub embed_enabled: bool,
     #[serde(default)]
     pub disabled_domains: HashSet<String>,
+    /// Language of user-facing messages, e.g. "en" or "sl" (default: "en")
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Channels whose bot uploads are deleted after the given number of seconds
+    #[serde(default)]
+    pub auto_delete_channels: HashMap<Id<ChannelMarker>, u64>,
+    /// Seconds bot uploads in the server's other channels are kept before being deleted
+    #[serde(default)]
+    pub retention_secs: Option<u64>,
+    /// Repost auto-embeds through a webhook under the original author's name and avatar
+    #[serde(default)]
+    pub webhook_repost: bool,
+    /// How failures in auto-embed channels are reported to members
+    #[serde(default)]
+    pub failure_notice: FailureNotice,
+    /// Roles allowed to run config-mutating commands, in addition to Administrator/Manage Server
+    #[serde(default)]
+    pub config_role_ids: HashSet<Id<RoleMarker>>,
+    /// Order general-purpose downloaders are tried in, overriding `media.downloader_order`
+    #[serde(default)]
+    pub downloader_order: Option<Vec<String>>,
+    /// Videos longer than this many seconds are refused, overriding `media.duration_cap_secs`
+    /// (0 lifts the cap)
+    #[serde(default)]
+    pub duration_cap_secs: Option<u64>,
+    /// Channel receiving short notices about failed downloads and uploads
+    #[serde(default)]
+    pub log_channel: Option<Id<ChannelMarker>>,
+    /// Seconds a link embedded in a channel is answered with a link to that embed instead of
+    /// being embedded again
+    #[serde(default)]
+    pub dedup_window_secs: Option<u64>,
+    /// Distinct members whose ❌ reactions delete a bot upload (disabled when unset)
+    #[serde(default)]
+    pub vote_delete_threshold: Option<usize>,
+    /// Seconds from the first ❌ reaction within which the votes have to come in (default: 600)
+    #[serde(default)]
+    pub vote_delete_window_secs: Option<u64>,
+    /// Embed settings of the server's channels, overriding the global `[embed]` section
+    #[serde(default)]
+    pub embed: ChannelConfig,
+    /// Embed settings of single channels, overriding the server's `embed` section
+    #[serde(default)]
+    pub channels: HashMap<Id<ChannelMarker>, ChannelConfig>,
+    /// Look of the rich embeds the bot builds, such as `/info`'s
+    #[serde(default)]
+    pub branding: BrandingConfig,
+    /// Monthly download limits, overriding the global ones
+    #[serde(default)]
+    pub quota: Option<QuotaConfig>,
+    /// Members, roles and links nothing is downloaded for, on top of the global blocklist
+    #[serde(default)]
+    pub blocklist: BlocklistConfig,
+    /// URL a JSON payload is posted to when a download of the server finishes or fails
+    #[serde(default)]
+    pub job_webhook: Option<String>,
+    /// Feeds the server may watch with `/watch`, overriding `watch.max_per_server`
+    #[serde(default)]
+    pub max_watches: Option<usize>,
+    /// Channels whose auto-embeds are also posted to another channel, e.g. a media archive
+    #[serde(default)]
+    pub mirror_channels: HashMap<Id<ChannelMarker>, Id<ChannelMarker>>,
 }
 
-impl Default for ServerConfig {
-    fn default() -> Self {
-        Self {
-            server_id: String::new(),
-            auto_embed_channels: HashSet::new(),
-            embed_enabled: true,
-            disabled_domains: HashSet::new(),
+/// Users, roles and links the bot downloads nothing for.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct BlocklistConfig {
+    #[serde(default)]
+    pub user_ids: HashSet<Id<UserMarker>>,
+    /// Members with one of these roles are blocked
+    #[serde(default)]
+    pub role_ids: HashSet<Id<RoleMarker>>,
+    /// Regular expressions, links matching any of them are blocked
+    #[serde(default)]
+    pub url_patterns: Vec<String>,
+}
+
+impl BlocklistConfig {
+    pub fn blocks_user(&self, user_id: Id<UserMarker>, role_ids: &[Id<RoleMarker>]) -> bool {
+        self.user_ids.contains(&user_id) || role_ids.iter().any(|id| self.role_ids.contains(id))
+    }
+
+    /// Patterns were checked when they were added, invalid ones block nothing.
+    pub fn blocks_url(&self, url: &str) -> bool {
+        self.url_patterns
+            .iter()
+            .filter_map(|pattern| regex::Regex::new(pattern).ok())
+            .any(|pattern| pattern.is_match(url))
+    }
+
+    /// Adds or removes an entry, returning whether that changed anything.
+    pub fn set(&mut self, entry: &BlockEntry, blocked: bool) -> bool {
+        match (entry, blocked) {
+            (BlockEntry::User(id), true) => self.user_ids.insert(*id),
+            (BlockEntry::User(id), false) => self.user_ids.remove(id),
+            (BlockEntry::Role(id), true) => self.role_ids.insert(*id),
+            (BlockEntry::Role(id), false) => self.role_ids.remove(id),
+            (BlockEntry::Pattern(pattern), true) => {
+                let added = !self.url_patterns.contains(pattern);
+                if added {
+                    self.url_patterns.push(pattern.clone());
+                }
+                added
+            }
+            (BlockEntry::Pattern(pattern), false) => {
+                let count = self.url_patterns.len();
+                self.url_patterns.retain(|p| p != pattern);
+                self.url_patterns.len() != count
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fails on the first pattern that is not a valid regular expression.
+    fn validate(&self) -> Result<()> {
+        for pattern in &self.url_patterns {
+            regex::Regex::new(pattern)
+                .with_context(|| format!("Invalid blocked URL pattern {pattern}"))?;
+        }
+        Ok(())
+    }
+
+    /// Lists the entries as mentions and code spans.
+    pub fn summary(&self) -> String {
+        let mut entries: Vec<String> = self
+            .user_ids
+            .iter()
+            .map(|id| BlockEntry::User(*id).to_string())
+            .collect();
+        entries.sort_unstable();
+        let mut roles: Vec<String> = self
+            .role_ids
+            .iter()
+            .map(|id| BlockEntry::Role(*id).to_string())
+            .collect();
+        roles.sort_unstable();
+        entries.extend(roles);
+        entries.extend(
+            self.url_patterns
+                .iter()
+                .map(|pattern| BlockEntry::Pattern(pattern.clone()).to_string()),
+        );
+        entries.join(", ")
+    }
+}
+
+/// A single entry of a blocklist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockEntry {
+    User(Id<UserMarker>),
+    Role(Id<RoleMarker>),
+    Pattern(String),
+}
+
+impl std::fmt::Display for BlockEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User(id) => write!(f, "<@{id}>"),
+            Self::Role(id) => write!(f, "<@&{id}>"),
+            Self::Pattern(pattern) => write!(f, "`{pattern}`"),
+        }
+    }
+}
+
+/// Accent color and footer of the bot's rich embeds.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct BrandingConfig {
+    /// Accent color as a hex code, e.g. "#5865F2" (default: none)
+    pub color: Option<String>,
+    /// Footer text, e.g. the server's name
+    pub footer: Option<String>,
+    /// Show the "via Grabby" credit in the footer (default: true)
+    pub credit: Option<bool>,
+}
+
+impl BrandingConfig {
+    /// The accent color as an RGB value, if it is a valid hex code.
+    pub fn color(&self) -> Option<u32> {
+        let hex = self.color.as_deref()?.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 {
+            return None;
         }
+        u32::from_str_radix(hex, 16).ok()
+    }
+
+    pub fn show_credit(&self) -> bool {
+        self.credit.unwrap_or(true)
     }
 }
 
 impl ServerConfig {
-    pub fn new(server_id: &str) -> Self {
+    /// Decrypts the server's secret values that are stored encrypted at rest.
+    pub fn reveal_secrets(&mut self, key: Option<&secret::SecretKey>) -> Result<()> {
+        if let Some(url) = self.job_webhook.as_mut() {
+            *url = secret::reveal(url, key).with_context(|| {
+                format!("Failed to decrypt job_webhook of server {}", self.server_id)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Returns a copy to store, with the secret values encrypted if a key is given.
+    pub fn sealed(&self, key: Option<&secret::SecretKey>) -> Result<Self> {
+        let mut config = self.clone();
+        if let (Some(url), Some(key)) = (config.job_webhook.as_mut(), key) {
+            if !secret::is_encrypted(url) {
+                *url = key.encrypt(url)?;
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn new(server_id: Id<GuildMarker>) -> Self {
         Self {
-            server_id: server_id.to_string(),
+            server_id,
             auto_embed_channels: HashSet::new(),
+            auto_embed_thread_parents: HashSet::new(),
             embed_enabled: true,
             disabled_domains: HashSet::new(),
+            locale: None,
+            auto_delete_channels: HashMap::new(),
+            retention_secs: None,
+            webhook_repost: false,
+            failure_notice: FailureNotice::default(),
+            config_role_ids: HashSet::new(),
+            downloader_order: None,
+            duration_cap_secs: None,
+            log_channel: None,
+            dedup_window_secs: None,
+            vote_delete_threshold: None,
+            vote_delete_window_secs: None,
+            embed: ChannelConfig::default(),
+            channels: HashMap::new(),
+            branding: BrandingConfig::default(),
+            quota: None,
+            blocklist: BlocklistConfig::default(),
+            job_webhook: None,
+            max_watches: None,
+            mirror_channels: HashMap::new(),
         }
     }
 
-    pub fn is_auto_embed_channel(&self, channel_id: &str) -> bool {
-        self.auto_embed_channels.iter().any(|id| id == channel_id)
+    pub fn locale(&self) -> Locale {
+        self.locale
+            .as_deref()
+            .and_then(Locale::from_code)
+            .unwrap_or_default()
     }
 
-    pub fn is_domain_disabled(&self, url: &str) -> bool {
-        if self.disabled_domains.is_empty() {
-            return false;
+    pub fn is_auto_embed_channel(&self, channel_id: Id<ChannelMarker>) -> bool {
+        self.auto_embed_channels.contains(&channel_id)
+    }
+
+    /// Checks a channel, or a thread given its parent channel, for auto-embedding.
+    pub fn is_auto_embed_target(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        thread_parent_id: Option<Id<ChannelMarker>>,
+    ) -> bool {
+        self.is_auto_embed_channel(channel_id)
+            || thread_parent_id
+                .is_some_and(|parent_id| self.auto_embed_thread_parents.contains(&parent_id))
+    }
+
+    /// Returns how long bot uploads in `channel_id` are kept before being deleted, falling back
+    /// to the server's retention. A channel set to 0 keeps its uploads.
+    pub fn auto_delete_after(&self, channel_id: Id<ChannelMarker>) -> Option<Duration> {
+        self.auto_delete_channels
+            .get(&channel_id)
+            .copied()
+            .or(self.retention_secs)
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Returns the channel auto-embeds of `channel_id` are also posted to, if any.
+    pub fn mirror_channel(&self, channel_id: Id<ChannelMarker>) -> Option<Id<ChannelMarker>> {
+        self.mirror_channels
+            .get(&channel_id)
+            .copied()
+            .filter(|archive| *archive != channel_id)
+    }
+
+    /// Returns how long repeated links are deduplicated for, if enabled.
+    pub fn dedup_window(&self) -> Option<Duration> {
+        self.dedup_window_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Returns how many votes delete a bot upload and the window they have to come in within,
+    /// if vote deletion is enabled.
+    pub fn vote_delete(&self) -> Option<(usize, Duration)> {
+        let threshold = self.vote_delete_threshold.filter(|votes| *votes > 0)?;
+        let window = self.vote_delete_window_secs.unwrap_or(600).max(1);
+        Some((threshold, Duration::from_secs(window)))
+    }
+
+    /// Embed settings of a channel, falling back to the server's for anything it leaves unset.
+    pub fn channel_config(&self, channel_id: Id<ChannelMarker>) -> ChannelConfig {
+        match self.channels.get(&channel_id) {
+            Some(channel) => channel.clone().or(&self.embed),
+            None => self.embed.clone(),
         }
+    }
 
-        if let Some(host) = Self::extract_host(url) {
-            let host_lower = host.to_lowercase();
+    pub fn is_domain_disabled(&self, url: &str) -> bool {
+        matches_domain(url, &self.disabled_domains)
+    }
+}
 
-            for disabled in &self.disabled_domains {
-                let disabled_lower = disabled.to_lowercase();
+/// Whether the host of `url` is one of `domains` or a subdomain of one.
+fn matches_domain(url: &str, domains: &HashSet<String>) -> bool {
+    if domains.is_empty() {
+        return false;
+    }
 
-                // Exact match
-                if host_lower == disabled_lower {
-                    return true;
-                }
+    if let Some(host) = extract_host(url) {
+        let host_lower = host.to_lowercase();
 
-                // Subdomain match (e.g., if "example.com" is disabled, "sub.example.com" is also disabled)
-                if host_lower.ends_with(&format!(".{}", disabled_lower)) {
-                    return true;
-                }
+        for domain in domains {
+            let domain_lower = domain.to_lowercase();
+
+            // Exact match
+            if host_lower == domain_lower {
+                return true;
+            }
+
+            // Subdomain match (e.g., "example.com" also covers "sub.example.com")
+            if host_lower.ends_with(&format!(".{}", domain_lower)) {
+                return true;
             }
         }
+    }
+
+    false
+}
+
+fn extract_host(url: &str) -> Option<String> {
+    let without_protocol = if let Some(pos) = url.find("://") {
+        &url[pos + 3..]
+    } else {
+        url
+    };
+
+    let host = without_protocol.split('/').next()?.split(':').next()?;
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_string())
+    }
+}
+
+/// What happens to media its source marks as adult or sensitive.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NsfwPolicy {
+    #[default]
+    Allow,
+    /// Upload it behind a spoiler
+    Spoiler,
+    /// Refuse to embed it
+    Block,
+}
+
+impl NsfwPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "allow" => Some(Self::Allow),
+            "spoiler" => Some(Self::Spoiler),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
 
-        false
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Spoiler => "spoiler",
+            Self::Block => "block",
+        }
     }
+}
 
-    fn extract_host(url: &str) -> Option<String> {
-        let without_protocol = if let Some(pos) = url.find("://") {
-            &url[pos + 3..]
-        } else {
-            url
-        };
+/// How auto-embed lets members know a link in their message couldn't be embedded.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureNotice {
+    /// Reply with the error and a retry button
+    #[default]
+    Message,
+    /// Reply with a short error that deletes itself after a minute
+    Brief,
+    /// React with ⚠️ on the original message
+    React,
+    /// Only log the failure
+    Silent,
+}
+
+/// How much of an embed runs when it is only simulated. Nothing is uploaded either way, a report
+/// of what would have been is posted instead.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SimulateMode {
+    /// Download and process the media, stopping before the upload
+    Full,
+    /// Only extract the metadata, without downloading anything
+    Metadata,
+}
+
+impl SimulateMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "metadata" => Some(Self::Metadata),
+            _ => None,
+        }
+    }
+}
+
+/// Domains auto-embed leaves to Discord's own link previews unless configured otherwise.
+const DEFAULT_SKIP_DOMAINS: &[&str] = &["youtube.com", "youtu.be", "spotify.com"];
+
+/// Embed settings set globally, per server or per channel. Unset values are taken from the next
+/// broader level, resolving channel → server → global.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ChannelConfig {
+    /// Upload size limit in MB, below what the destination allows
+    pub max_upload_mb: Option<u64>,
+    /// Caption replacing the default one, with `{user}`, `{url}`, `{author}`, `{likes}`,
+    /// `{title}` and `{uploaded}` placeholders
+    pub template: Option<String>,
+    /// Only links to these domains and their subdomains are embedded (all when unset)
+    pub allowed_domains: Option<HashSet<String>>,
+    /// Send only the audio track unless `/embed` asks otherwise (default: false)
+    pub audio_only: Option<bool>,
+    /// Media marked as NSFW: "allow", "spoiler" or "block" (default: "allow")
+    pub nsfw: Option<NsfwPolicy>,
+    /// Links to these domains and their subdomains are not auto-embedded, `/embed` still takes
+    /// them (default: YouTube and Spotify)
+    pub skip_domains: Option<HashSet<String>>,
+    /// Links to these domains and their subdomains are posted as a link to an embed-friendly
+    /// mirror instead of being downloaded, if one is known (default: none)
+    pub fix_domains: Option<HashSet<String>>,
+    /// Auto-embeds downloading or uploading in the channel at once, further links are skipped
+    /// until one finishes (default: no limit)
+    pub max_pending: Option<usize>,
+    /// Container videos are delivered in: "mp4", "webm" or "original" (default: "mp4")
+    pub container: Option<OutputContainer>,
+}
+
+impl ChannelConfig {
+    /// Fills the values left unset from `fallback`.
+    pub fn or(self, fallback: &ChannelConfig) -> Self {
+        Self {
+            max_upload_mb: self.max_upload_mb.or(fallback.max_upload_mb),
+            template: self.template.or_else(|| fallback.template.clone()),
+            allowed_domains: self
+                .allowed_domains
+                .or_else(|| fallback.allowed_domains.clone()),
+            audio_only: self.audio_only.or(fallback.audio_only),
+            nsfw: self.nsfw.or(fallback.nsfw),
+            skip_domains: self.skip_domains.or_else(|| fallback.skip_domains.clone()),
+            fix_domains: self.fix_domains.or_else(|| fallback.fix_domains.clone()),
+            max_pending: self.max_pending.or(fallback.max_pending),
+            container: self.container.or(fallback.container),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn max_upload_bytes(&self) -> Option<u64> {
+        self.max_upload_mb.map(|mb| mb * 1_000_000)
+    }
+
+    pub fn is_domain_allowed(&self, url: &str) -> bool {
+        self.allowed_domains
+            .as_ref()
+            .is_none_or(|domains| domains.is_empty() || matches_domain(url, domains))
+    }
+
+    pub fn audio_only(&self) -> bool {
+        self.audio_only.unwrap_or(false)
+    }
+
+    pub fn fixes(&self, url: &str) -> bool {
+        self.fix_domains
+            .as_ref()
+            .is_some_and(|domains| matches_domain(url, domains))
+    }
+
+    pub fn skips_auto_embed(&self, url: &str) -> bool {
+        match &self.skip_domains {
+            Some(domains) => matches_domain(url, domains),
+            None => matches_domain(
+                url,
+                &DEFAULT_SKIP_DOMAINS.iter().map(|d| d.to_string()).collect(),
+            ),
+        }
+    }
+
+    pub fn nsfw_policy(&self) -> NsfwPolicy {
+        self.nsfw.unwrap_or_default()
+    }
 
-        let host = without_protocol.split('/').next()?.split(':').next()?;
+    pub fn container(&self) -> OutputContainer {
+        self.container.unwrap_or_default()
+    }
 
-        if host.is_empty() {
-            None
-        } else {
-            Some(host.to_string())
+    /// Lists the values that are set, by their `/admin channel` option names.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(mb) = self.max_upload_mb {
+            parts.push(format!("`max-size`: {mb} MB"));
+        }
+        if let Some(template) = &self.template {
+            parts.push(format!("`template`: {template}"));
+        }
+        if let Some(domains) = &self.allowed_domains {
+            let mut domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+            domains.sort_unstable();
+            parts.push(format!("`allowed-domains`: {}", domains.join(", ")));
+        }
+        if let Some(audio_only) = self.audio_only {
+            parts.push(format!("`audio-only`: {audio_only}"));
+        }
+        if let Some(nsfw) = self.nsfw {
+            parts.push(format!("`nsfw`: {}", nsfw.name()));
+        }
+        if let Some(domains) = &self.skip_domains {
+            let mut domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+            domains.sort_unstable();
+            let domains = if domains.is_empty() {
+                "none".to_string()
+            } else {
+                domains.join(", ")
+            };
+            parts.push(format!("`skip-domains`: {domains}"));
+        }
+        if let Some(domains) = &self.fix_domains {
+            let mut domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+            domains.sort_unstable();
+            parts.push(format!("`fix-domains`: {}", domains.join(", ")));
         }
+        if let Some(max) = self.max_pending {
+            parts.push(format!("`max-pending`: {max}"));
+        }
+        if let Some(container) = self.container {
+            parts.push(format!("`container`: {}", container.name()));
+        }
+        parts.join(", ")
     }
 }
 
+/// How often the log file starts over in a new one.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    /// Keep writing to a single file
+    Never,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct LoggingConfig {
     pub format: Option<String>,
     pub level: Option<String>,
+    /// Directory logs are written to besides stdout (stdout only when unset)
+    pub directory: Option<PathBuf>,
+    /// Name the log files start with (default: "grabby")
+    pub file_prefix: Option<String>,
+    /// How often a new log file is started (default: "daily")
+    pub rotation: Option<LogRotation>,
+    /// Rotated files kept before the oldest are removed, 0 keeps all (default: 7)
+    pub max_files: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct DiscordConfig {
     pub token: Option<String>,
+    /// Channel receiving failure notices from every server
+    pub log_channel: Option<Id<ChannelMarker>>,
+    /// Users allowed to run bot-wide admin commands such as `/admin reload-config`
+    #[serde(default)]
+    pub owner_ids: Vec<Id<UserMarker>>,
+    /// Simulate every embed instead of uploading it: "full" or "metadata" (default: off)
+    pub simulate: Option<SimulateMode>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
-pub struct Config {
-    pub discord: Option<DiscordConfig>,
-    pub servers: Vec<ServerConfig>,
-    pub logging: Option<LoggingConfig>,
+pub struct MediaConfig {
+    /// Seconds without any download after which cached link metadata is dropped (0 disables)
+    pub idle_timeout_secs: Option<u64>,
+    /// Normalize loudness (EBU R128) of audio extracted with `/embed audio` (default: true)
+    pub normalize_audio: Option<bool>,
+    /// Codec for re-encoded videos: "h264", "vp9" or "av1" (default: "h264")
+    pub video_codec: Option<String>,
+    /// Videos longer than this many seconds are cut down to `clip_secs` (no limit when unset)
+    pub max_duration_secs: Option<u64>,
+    /// Length in seconds that over-long videos are cut down to (default: 60)
+    pub clip_secs: Option<u64>,
+    /// Videos longer than this many seconds are refused before downloading instead of being cut
+    /// down (no cap when unset or 0)
+    pub duration_cap_secs: Option<u64>,
+    /// Seconds captured from the end of live streams, which are refused when unset or 0
+    pub live_capture_secs: Option<u64>,
+    /// Galleries with more files than this are sent as a single zip, 0 disables (default: 20)
+    pub gallery_zip_threshold: Option<usize>,
+    /// Format of tracks from audio platforms: "opus" or "mp3" (default: "opus")
+    pub audio_format: Option<String>,
+    /// Order general-purpose downloaders are tried in (default: ["gallery-dl", "yt-dlp"])
+    pub downloader_order: Option<Vec<String>>,
+    /// Mirrors links are rewritten to instead of being downloaded in `fix_domains`, or when a
+    /// download fails, keyed by the domain whose links they take, e.g.
+    /// `{ "tiktok.com" = "vxtiktok.com" }` (adds to and overrides the built-in ones)
+    pub url_rewrites: Option<HashMap<String, String>>,
+    pub timeouts: Option<TimeoutsConfig>,
+    pub http: Option<HttpConfig>,
+    /// Downloads running at once, further ones wait in a queue (no limit when unset or 0)
+    pub max_concurrent_downloads: Option<usize>,
+    /// Order queued downloads start in: "commands_first" (/embed before auto-embeds) or "fifo"
+    /// (default: "commands_first"). Tiers with a higher priority go first either way
+    pub queue_policy: Option<String>,
+    /// Megabytes that must stay free in the temp directory, below which downloads and
+    /// transcodes are refused (default: 500, 0 disables)
+    pub min_free_disk_mb: Option<u64>,
+    /// Megabytes downloaded media may take up in memory across all downloads, further downloads
+    /// wait until enough of it is uploaded (no limit when unset or 0)
+    pub memory_budget_mb: Option<u64>,
+    /// Downloaded images with more megapixels than this are refused before they are processed,
+    /// guarding against decompression bombs (default: 100, 0 disables)
+    pub max_image_megapixels: Option<u64>,
+    /// Downloaded images wider or taller than this many pixels are refused (no limit when unset
+    /// or 0)
+    pub max_image_edge: Option<u32>,
 }
 
-impl Config {
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+/// Seconds each stage of handling a link may take.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StageTimeoutsConfig {
+    /// Looking up the media and its metadata (default: 30)
+    pub extraction_secs: Option<u64>,
+    /// Fetching the media files (default: 120)
+    pub download_secs: Option<u64>,
+    /// Re-encoding files to fit the upload limit (default: 600)
+    pub transcode_secs: Option<u64>,
+    /// Sending the files to Discord (default: 120)
+    pub upload_secs: Option<u64>,
+}
 
-        let config: Config = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+impl StageTimeoutsConfig {
+    /// `base` with the stages set here overridden.
+    fn apply(&self, base: StageTimeouts) -> StageTimeouts {
+        let secs = |secs: Option<u64>, default: Duration| {
+            secs.map(|secs| Duration::from_secs(secs.max(1)))
+                .unwrap_or(default)
+        };
+        StageTimeouts {
+            extraction: secs(self.extraction_secs, base.extraction),
+            download: secs(self.download_secs, base.download),
+            transcode: secs(self.transcode_secs, base.transcode),
+            upload: secs(self.upload_secs, base.upload),
+        }
+    }
+}
 
-        Ok(config)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimeoutsConfig {
+    #[serde(flatten)]
+    pub defaults: StageTimeoutsConfig,
+    /// Overrides for single downloaders, keyed by name, e.g. "yt-dlp" or "gallery-dl (site)"
+    #[serde(default)]
+    pub downloaders: HashMap<String, StageTimeoutsConfig>,
+}
+
+/// The client the bot fetches media files, API responses and tools with.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HttpConfig {
+    /// User-Agent header sent with requests, also passed on to yt-dlp and gallery-dl
+    /// (default: "grabby/<version>", tools keep their own)
+    pub user_agent: Option<String>,
+    /// Headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Headers sent to a domain and its subdomains, overriding the ones above, e.g.
+    /// `{ "i.imgur.com" = { Referer = "https://imgur.com/" } }`
+    #[serde(default)]
+    pub sites: HashMap<String, HashMap<String, String>>,
+    /// HTTP(S) proxy all requests go through, e.g. "http://127.0.0.1:3128"
+    pub proxy: Option<String>,
+    /// Seconds to wait for a connection (default: 10)
+    pub connect_timeout_secs: Option<u64>,
+    /// Seconds to wait for the next bytes of a response (default: 30)
+    pub read_timeout_secs: Option<u64>,
+    /// Seconds a whole request may take (default: unlimited, downloads end with their stage)
+    pub timeout_secs: Option<u64>,
+    /// Redirects followed before a request fails (default: 10)
+    pub max_redirects: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HealthConfig {
+    /// Address to serve the health endpoint on, e.g. "127.0.0.1:8080" (disabled when unset)
+    pub bind: Option<String>,
+    /// Seconds without gateway activity after which the event loop is considered stalled
+    pub stall_threshold_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GrpcConfig {
+    /// Address to serve the gRPC API on, e.g. "127.0.0.1:50051" (disabled when unset, needs a
+    /// build with the `grpc` feature)
+    pub bind: Option<String>,
+    /// Token callers must send as "authorization: Bearer <token>" (no authentication when unset)
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WatchConfig {
+    /// Seconds between polls of the feeds servers watch (default: 900)
+    pub poll_interval_secs: Option<u64>,
+    /// Feeds each server may watch (default: 10)
+    pub max_per_server: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL, e.g. "http://localhost:4318" (falls back to the standard
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT` variable, export is disabled when neither is set)
+    pub otlp_endpoint: Option<String>,
+    /// Service name reported with traces and metrics (default: "grabby")
+    pub service_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ErrorReportingConfig {
+    /// Sentry DSN events are sent to (falls back to the standard `SENTRY_DSN` variable,
+    /// reporting is disabled when neither is set)
+    pub sentry_dsn: Option<String>,
+    /// Environment events are tagged with, e.g. "production"
+    pub environment: Option<String>,
+    /// Failures of the same class, downloader and domain within an hour after which they are
+    /// reported (default: 3)
+    pub repeat_threshold: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StorageConfig {
+    /// Directory for persisted bot state, relative to the working directory (default: "data")
+    pub data_dir: Option<String>,
+    /// Where state instances can share is kept: "local" files in the data_dir or "redis", the
+    /// Redis of the `[cluster]` section (default: "local")
+    pub backend: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ClusterConfig {
+    /// Redis shared by all instances, e.g. "redis://localhost:6379" (coordination is disabled
+    /// when unset, needs a build with the `redis` feature)
+    pub redis_url: Option<String>,
+    /// Prefix of the keys, so deployments can share a Redis (default: "grabby")
+    pub key_prefix: Option<String>,
+    /// How long a link of a message stays claimed by the instance embedding it (default: 600)
+    pub claim_ttl_secs: Option<u64>,
+    /// Seconds between loading server settings changed by other instances (default: 30)
+    pub settings_refresh_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BootstrapConfig {
+    /// Download pinned yt-dlp/gallery-dl builds when they are missing from PATH (default: false)
+    pub enabled: Option<bool>,
+    /// Directory for downloaded tools (default: "<data_dir>/tools")
+    pub dir: Option<String>,
+    pub yt_dlp_version: Option<String>,
+    /// Expected SHA-256 of the yt-dlp build for this host's OS/arch, required to bootstrap it
+    pub yt_dlp_sha256: Option<String>,
+    pub gallery_dl_version: Option<String>,
+    /// Expected SHA-256 of the gallery-dl build for this host's OS/arch, required to bootstrap it
+    pub gallery_dl_sha256: Option<String>,
+}
+
+/// gallery-dl settings for one site, keyed by its gallery-dl extractor name (e.g. "pixiv").
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GalleryDlSiteConfig {
+    /// Domains sent straight to gallery-dl (default: the extractor's known domains)
+    pub domains: Option<Vec<String>>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub api_key: Option<String>,
+    pub user_id: Option<String>,
+    /// OAuth refresh token, as used by Pixiv
+    pub refresh_token: Option<String>,
+    /// Netscape cookies.txt file exported from a logged-in browser, needed for Instagram and
+    /// Snapchat stories. The file itself may hold the whole content encrypted
+    pub cookies: Option<String>,
+    /// Convert Pixiv ugoira animations to MP4 (default: true)
+    pub ugoira_to_video: Option<bool>,
+}
+
+impl GalleryDlSiteConfig {
+    /// Values that may be stored encrypted, with their field names.
+    fn secrets_mut(&mut self) -> [(&'static str, &mut Option<String>); 3] {
+        [
+            ("password", &mut self.password),
+            ("api_key", &mut self.api_key),
+            ("refresh_token", &mut self.refresh_token),
+        ]
     }
+}
 
-    pub fn get_discord_token(&self) -> Option<String> {
-        self.discord.as_ref().and_then(|d| d.token.clone())
+/// How much a server may download per calendar month (UTC).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct QuotaConfig {
+    /// Links downloaded (unlimited when unset)
+    pub monthly_downloads: Option<u64>,
+    /// Megabytes of media downloaded (unlimited when unset)
+    pub monthly_mb: Option<u64>,
+}
+
+impl QuotaConfig {
+    /// This quota with the limits it leaves unset taken from `fallback`.
+    pub fn or(&self, fallback: &QuotaConfig) -> QuotaConfig {
+        QuotaConfig {
+            monthly_downloads: self.monthly_downloads.or(fallback.monthly_downloads),
+            monthly_mb: self.monthly_mb.or(fallback.monthly_mb),
+        }
     }
 
-    pub fn get_logging_format(&self) -> &str {
-        self.logging
-            .as_ref()
-            .and_then(|l| l.format.as_deref())
-            .unwrap_or("json")
+    pub fn monthly_bytes(&self) -> Option<u64> {
+        self.monthly_mb.map(|mb| mb.saturating_mul(1024 * 1024))
     }
 
-    pub fn get_log_level(&self) -> &str {
-        self.logging
-            .as_ref()
-            .and_then(|l| l.level.as_deref())
-            .unwrap_or("info")
+    pub fn is_limited(&self) -> bool {
+        self.monthly_downloads.is_some() || self.monthly_mb.is_some()
     }
 }
 
-pub struct ConfigManager {
-    configs: HashMap<String, ServerConfig>,
+/// Who a download is for, matched against the tiers.
+#[derive(Debug, Clone, Default)]
+pub struct Requester {
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub user_id: Option<Id<UserMarker>>,
+    pub role_ids: Vec<Id<RoleMarker>>,
+    /// SKUs the server or user is entitled to
+    pub sku_ids: Vec<Id<SkuMarker>>,
 }
 
-impl ConfigManager {
-    pub fn new() -> Self {
-        Self {
-            configs: HashMap::new(),
-        }
-    }
+/// Limits granted to servers and members, e.g. to run a freemium instance. Limits left unset
+/// fall back to the global ones.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct TierConfig {
+    /// Name shown in logs
+    pub name: String,
+    /// Servers in the tier
+    #[serde(default)]
+    pub guild_ids: Vec<Id<GuildMarker>>,
+    /// Users in the tier, in every server
+    #[serde(default)]
+    pub user_ids: Vec<Id<UserMarker>>,
+    /// Members with one of these roles are in the tier
+    #[serde(default)]
+    pub role_ids: Vec<Id<RoleMarker>>,
+    /// Servers and users entitled to one of these Discord SKUs are in the tier
+    #[serde(default)]
+    pub sku_ids: Vec<Id<SkuMarker>>,
+    /// Upload size limit in MB, lowering the server's Discord limit
+    pub max_upload_mb: Option<u64>,
+    /// Videos longer than this are cut down to clip_secs, in place of media.max_duration_secs
+    pub max_duration_secs: Option<u64>,
+    /// Seconds long videos are cut down to (default: 60)
+    pub clip_secs: Option<u64>,
+    /// Monthly download limits, in place of the [quota] section
+    pub quota: Option<QuotaConfig>,
+    /// Downloads of tiers with a higher priority leave the download queue first (default: 0)
+    pub priority: Option<u8>,
+}
 
-    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let config = Config::from_file(path)?;
-        let configs: HashMap<String, ServerConfig> = config
-            .servers
-            .into_iter()
-            .map(|s| (s.server_id.clone(), s))
-            .collect();
+impl TierConfig {
+    pub fn matches(&self, requester: &Requester) -> bool {
+        requester
+            .guild_id
+            .is_some_and(|id| self.guild_ids.contains(&id))
+            || requester
+                .user_id
+                .is_some_and(|id| self.user_ids.contains(&id))
+            || requester
+                .role_ids
+                .iter()
+                .any(|id| self.role_ids.contains(id))
+            || requester.sku_ids.iter().any(|id| self.sku_ids.contains(id))
+    }
 
-        Ok(Self { configs })
+    pub fn max_upload_bytes(&self) -> Option<u64> {
+        self.max_upload_mb.map(|mb| mb * 1_000_000)
     }
 
-    pub fn get_server_config(&self, server_id: &str) -> ServerConfig {
-        self.configs
-            .get(server_id)
-            .cloned()
-            .unwrap_or_else(|| ServerConfig::new(server_id))
+    pub fn duration_limit(&self) -> Option<DurationLimit> {
+        Some(DurationLimit {
+            max_secs: self.max_duration_secs?,
+            clip_secs: self.clip_secs.unwrap_or(60).max(1),
+        })
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OffloadConfig {
+    /// Where media too large for Discord is uploaded: "filesystem", "s3", "catbox", "litterbox"
+    /// or "0x0"
+    pub backend: String,
+    /// Seconds offloaded links stay valid (default: 86400), rounded up to what file hosts offer
+    pub ttl_secs: Option<u64>,
+    /// Directory uploads are written to (filesystem)
+    pub dir: Option<String>,
+    /// Public URL the directory or bucket is served at (required for filesystem, optional for s3)
+    pub base_url: Option<String>,
+    /// Endpoint of the S3-compatible service, e.g. "https://s3.eu-central-1.amazonaws.com"
+    pub endpoint: Option<String>,
+    pub bucket: Option<String>,
+    /// Region used for request signing (default: "us-east-1")
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
 
-    #[test]
-    fn test_server_config_new() {
-        let config = ServerConfig::new("test_server");
-        assert_eq!(config.server_id, "test_server");
-        assert!(config.auto_embed_channels.is_empty());
-        assert!(config.embed_enabled);
-    }
+/// Scanner downloaded files pass through before they are uploaded, set either `clamd_socket`
+/// or `command`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ScanConfig {
+    /// Unix socket of a clamd daemon files are streamed to
+    pub clamd_socket: Option<String>,
+    /// Command files are piped to on stdin, e.g. `["clamscan", "--no-summary", "-"]`, which
+    /// exits with 0 for clean files, 1 for flagged ones and anything else when it fails
+    pub command: Option<Vec<String>>,
+    /// Seconds scanning a file may take, after which the download fails (default: 60)
+    pub timeout_secs: Option<u64>,
+}
 
-    #[test]
-    fn test_server_config_default() {
-        let config = ServerConfig::default();
-        assert_eq!(config.server_id, "");
-        assert!(config.auto_embed_channels.is_empty());
-        assert!(config.embed_enabled);
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Config {
+    pub discord: Option<DiscordConfig>,
+    pub servers: Vec<ServerConfig>,
+    pub logging: Option<LoggingConfig>,
+    pub media: Option<MediaConfig>,
+    pub health: Option<HealthConfig>,
+    /// API other services download media through
+    pub grpc: Option<GrpcConfig>,
+    pub telemetry: Option<TelemetryConfig>,
+    pub error_reporting: Option<ErrorReportingConfig>,
+    pub storage: Option<StorageConfig>,
+    /// Coordination of several instances running side by side
+    pub cluster: Option<ClusterConfig>,
+    pub bootstrap: Option<BootstrapConfig>,
+    pub offload: Option<OffloadConfig>,
+    /// Threat scanning of downloaded files, which drops flagged ones
+    pub scan: Option<ScanConfig>,
+    pub gallery_dl: Option<HashMap<String, GalleryDlSiteConfig>>,
+    /// Monthly download limits of every server, unless overridden by the server
+    pub quota: Option<QuotaConfig>,
+    /// Creator feeds servers subscribe to with `/watch`
+    pub watch: Option<WatchConfig>,
+    /// Limits of premium servers and members, the first matching tier applies
+    pub tiers: Option<Vec<TierConfig>>,
+    /// Users and links blocked in every server
+    pub blocklist: Option<BlocklistConfig>,
+    /// Embed settings of every channel, unless overridden by its server or itself
+    pub embed: Option<ChannelConfig>,
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        let mut config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+        config.reveal_secrets(secret::SecretKey::from_env()?.as_ref())?;
+        config.validate_blocklists()?;
+        config.validate_media()?;
+
+        Ok(config)
+    }
+
+    fn validate_blocklists(&self) -> Result<()> {
+        if let Some(blocklist) = &self.blocklist {
+            blocklist.validate()?;
+        }
+        for server in &self.servers {
+            server
+                .blocklist
+                .validate()
+                .with_context(|| format!("Invalid blocklist of server {}", server.server_id))?;
+        }
+        Ok(())
+    }
+
+    fn validate_media(&self) -> Result<()> {
+        let codec = self.media.as_ref().and_then(|m| m.video_codec.as_deref());
+        if let Some(codec) = codec.filter(|codec| VideoCodec::from_name(codec).is_none()) {
+            anyhow::bail!(
+                "Invalid media.video_codec \"{}\", expected \"h264\", \"vp9\" or \"av1\"",
+                codec
+            );
+        }
+        Ok(())
+    }
+
+    /// Decrypts every secret value that is stored encrypted at rest.
+    fn reveal_secrets(&mut self, key: Option<&secret::SecretKey>) -> Result<()> {
+        if let Some(token) = self.discord.as_mut().and_then(|d| d.token.as_mut()) {
+            *token = secret::reveal(token, key).context("Failed to decrypt discord.token")?;
+        }
+        if let Some(secret) = self
+            .offload
+            .as_mut()
+            .and_then(|o| o.secret_access_key.as_mut())
+        {
+            *secret = secret::reveal(secret, key)
+                .context("Failed to decrypt offload.secret_access_key")?;
+        }
+        if let Some(url) = self.cluster.as_mut().and_then(|c| c.redis_url.as_mut()) {
+            *url = secret::reveal(url, key).context("Failed to decrypt cluster.redis_url")?;
+        }
+        if let Some(token) = self.grpc.as_mut().and_then(|g| g.token.as_mut()) {
+            *token = secret::reveal(token, key).context("Failed to decrypt grpc.token")?;
+        }
+        if let Some(dsn) = self
+            .error_reporting
+            .as_mut()
+            .and_then(|r| r.sentry_dsn.as_mut())
+        {
+            *dsn =
+                secret::reveal(dsn, key).context("Failed to decrypt error_reporting.sentry_dsn")?;
+        }
+        for server in &mut self.servers {
+            server.reveal_secrets(key)?;
+        }
+        for (extractor, site) in self.gallery_dl.iter_mut().flatten() {
+            for (field, value) in site.secrets_mut() {
+                if let Some(value) = value {
+                    *value = secret::reveal(value, key).with_context(|| {
+                        format!("Failed to decrypt gallery_dl.{extractor}.{field}")
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy that is safe to print or export, with all secret values replaced.
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if let Some(token) = config.discord.as_mut().and_then(|d| d.token.as_mut()) {
+            *token = secret::REDACTED.to_string();
+        }
+        if let Some(secret) = config
+            .offload
+            .as_mut()
+            .and_then(|o| o.secret_access_key.as_mut())
+        {
+            *secret = secret::REDACTED.to_string();
+        }
+        if let Some(url) = config.cluster.as_mut().and_then(|c| c.redis_url.as_mut()) {
+            *url = secret::REDACTED.to_string();
+        }
+        if let Some(token) = config.grpc.as_mut().and_then(|g| g.token.as_mut()) {
+            *token = secret::REDACTED.to_string();
+        }
+        if let Some(dsn) = config
+            .error_reporting
+            .as_mut()
+            .and_then(|r| r.sentry_dsn.as_mut())
+        {
+            *dsn = secret::REDACTED.to_string();
+        }
+        // Webhook URLs carry their credentials
+        for url in config
+            .servers
+            .iter_mut()
+            .filter_map(|server| server.job_webhook.as_mut())
+        {
+            *url = secret::REDACTED.to_string();
+        }
+        for site in config
+            .gallery_dl
+            .iter_mut()
+            .flat_map(|sites| sites.values_mut())
+        {
+            for (_, value) in site.secrets_mut() {
+                if let Some(value) = value {
+                    *value = secret::REDACTED.to_string();
+                }
+            }
+            // Cookies files hold a logged-in session, so their paths aren't printed either
+            if let Some(path) = site.cookies.as_mut() {
+                *path = secret::REDACTED.to_string();
+            }
+        }
+        config
+    }
+
+    pub fn get_discord_token(&self) -> Option<String> {
+        self.discord.as_ref().and_then(|d| d.token.clone())
+    }
+
+    pub fn get_log_channel(&self) -> Option<Id<ChannelMarker>> {
+        self.discord.as_ref().and_then(|d| d.log_channel)
+    }
+
+    pub fn get_simulate(&self) -> Option<SimulateMode> {
+        self.discord.as_ref().and_then(|d| d.simulate)
+    }
+
+    pub fn is_owner(&self, user_id: Id<UserMarker>) -> bool {
+        self.discord
+            .as_ref()
+            .is_some_and(|d| d.owner_ids.contains(&user_id))
+    }
+
+    pub fn get_logging_format(&self) -> &str {
+        self.logging
+            .as_ref()
+            .and_then(|l| l.format.as_deref())
+            .unwrap_or("json")
+    }
+
+    pub fn get_log_level(&self) -> &str {
+        self.logging
+            .as_ref()
+            .and_then(|l| l.level.as_deref())
+            .unwrap_or("info")
+    }
+
+    pub fn get_log_directory(&self) -> Option<&Path> {
+        self.logging.as_ref().and_then(|l| l.directory.as_deref())
+    }
+
+    pub fn get_log_file_prefix(&self) -> &str {
+        self.logging
+            .as_ref()
+            .and_then(|l| l.file_prefix.as_deref())
+            .unwrap_or("grabby")
+    }
+
+    pub fn get_log_rotation(&self) -> LogRotation {
+        self.logging
+            .as_ref()
+            .and_then(|l| l.rotation)
+            .unwrap_or_default()
+    }
+
+    /// None keeps every rotated file
+    pub fn get_log_max_files(&self) -> Option<usize> {
+        match self.logging.as_ref().and_then(|l| l.max_files) {
+            Some(0) => None,
+            Some(n) => Some(n),
+            None => Some(7),
+        }
+    }
+
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        let secs = self
+            .media
+            .as_ref()
+            .and_then(|m| m.idle_timeout_secs)
+            .unwrap_or(900);
+
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    pub fn get_normalize_audio(&self) -> bool {
+        self.media
+            .as_ref()
+            .and_then(|m| m.normalize_audio)
+            .unwrap_or(true)
+    }
+
+    pub fn get_video_codec(&self) -> VideoCodec {
+        self.media
+            .as_ref()
+            .and_then(|m| m.video_codec.as_deref())
+            .and_then(VideoCodec::from_name)
+            .unwrap_or_default()
+    }
+
+    pub fn get_audio_format(&self) -> AudioFormat {
+        self.media
+            .as_ref()
+            .and_then(|m| m.audio_format.as_deref())
+            .and_then(AudioFormat::from_name)
+            .unwrap_or_default()
+    }
+
+    pub fn get_max_concurrent_downloads(&self) -> Option<usize> {
+        self.media
+            .as_ref()
+            .and_then(|m| m.max_concurrent_downloads)
+            .filter(|max| *max > 0)
+    }
+
+    pub fn get_queue_policy(&self) -> QueuePolicy {
+        self.media
+            .as_ref()
+            .and_then(|m| m.queue_policy.as_deref())
+            .and_then(QueuePolicy::from_name)
+            .unwrap_or_default()
+    }
+
+    pub fn get_min_free_disk_bytes(&self) -> Option<u64> {
+        let mb = self
+            .media
+            .as_ref()
+            .and_then(|m| m.min_free_disk_mb)
+            .unwrap_or(500);
+
+        (mb > 0).then(|| mb * 1_000_000)
+    }
+
+    pub fn get_memory_budget_bytes(&self) -> Option<u64> {
+        self.media
+            .as_ref()
+            .and_then(|m| m.memory_budget_mb)
+            .filter(|mb| *mb > 0)
+            .map(|mb| mb * 1_000_000)
+    }
+
+    pub fn get_image_limits(&self) -> ImageLimits {
+        let media = self.media.as_ref();
+        ImageLimits {
+            max_pixels: Some(media.and_then(|m| m.max_image_megapixels).unwrap_or(100))
+                .filter(|mp| *mp > 0)
+                .map(|mp| mp * 1_000_000),
+            max_edge: media.and_then(|m| m.max_image_edge).filter(|px| *px > 0),
+        }
+    }
+
+    /// Scanner downloaded files pass through, if one is configured. A clamd socket takes
+    /// precedence over a command.
+    pub fn get_file_scanner(&self) -> Option<FileScanner> {
+        let scan = self.scan.as_ref()?;
+        let scanner = match (&scan.clamd_socket, &scan.command) {
+            (Some(socket), _) => Scanner::Clamd(socket.into()),
+            (None, Some(command)) if !command.is_empty() => Scanner::Command(command.clone()),
+            _ => return None,
+        };
+        let timeout = Duration::from_secs(scan.timeout_secs.unwrap_or(60).max(1));
+        Some(FileScanner::new(scanner, timeout))
+    }
+
+    pub fn get_url_rewrites(&self) -> HashMap<String, String> {
+        self.media
+            .as_ref()
+            .and_then(|m| m.url_rewrites.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_downloader_order(&self) -> Vec<String> {
+        self.media
+            .as_ref()
+            .and_then(|m| m.downloader_order.clone())
+            .unwrap_or_else(|| vec!["gallery-dl".to_string(), "yt-dlp".to_string()])
+    }
+
+    pub fn get_duration_limit(&self) -> Option<DurationLimit> {
+        let media = self.media.as_ref()?;
+        Some(DurationLimit {
+            max_secs: media.max_duration_secs?,
+            clip_secs: media.clip_secs.unwrap_or(60).max(1),
+        })
+    }
+
+    pub fn get_duration_cap(&self) -> Option<u64> {
+        self.media
+            .as_ref()?
+            .duration_cap_secs
+            .filter(|secs| *secs > 0)
+    }
+
+    /// First tier the requester is in, if any.
+    pub fn get_tier(&self, requester: &Requester) -> Option<&TierConfig> {
+        self.tiers
+            .as_ref()?
+            .iter()
+            .find(|tier| tier.matches(requester))
+    }
+
+    /// Whether any tier is granted through Discord entitlements, which then have to be tracked.
+    pub fn has_sku_tiers(&self) -> bool {
+        self.tiers
+            .iter()
+            .flatten()
+            .any(|tier| !tier.sku_ids.is_empty())
+    }
+
+    pub fn get_http_settings(&self) -> HttpSettings {
+        let defaults = HttpSettings::default();
+        let Some(config) = self.media.as_ref().and_then(|m| m.http.as_ref()) else {
+            return defaults;
+        };
+
+        HttpSettings {
+            user_agent: config.user_agent.clone(),
+            headers: config.headers.clone(),
+            site_headers: config.sites.clone(),
+            proxy: config.proxy.clone(),
+            connect_timeout: config
+                .connect_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.connect_timeout),
+            read_timeout: config
+                .read_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.read_timeout),
+            timeout: config.timeout_secs.map(Duration::from_secs),
+            max_redirects: config.max_redirects.unwrap_or(defaults.max_redirects),
+        }
+    }
+
+    pub fn get_timeouts(&self) -> Timeouts {
+        let Some(config) = self.media.as_ref().and_then(|m| m.timeouts.as_ref()) else {
+            return Timeouts::default();
+        };
+
+        let defaults = config.defaults.apply(StageTimeouts::default());
+        Timeouts {
+            defaults,
+            downloaders: config
+                .downloaders
+                .iter()
+                .map(|(name, overrides)| (name.clone(), overrides.apply(defaults)))
+                .collect(),
+        }
+    }
+
+    pub fn get_live_capture_secs(&self) -> Option<u64> {
+        self.media
+            .as_ref()
+            .and_then(|m| m.live_capture_secs)
+            .filter(|secs| *secs > 0)
+    }
+
+    pub fn get_gallery_zip_threshold(&self) -> Option<usize> {
+        let threshold = self
+            .media
+            .as_ref()
+            .and_then(|m| m.gallery_zip_threshold)
+            .unwrap_or(20);
+
+        (threshold > 0).then_some(threshold)
+    }
+
+    pub fn get_offload_ttl(&self) -> Duration {
+        let secs = self
+            .offload
+            .as_ref()
+            .and_then(|o| o.ttl_secs)
+            .unwrap_or(86400);
+
+        Duration::from_secs(secs.max(60))
+    }
+
+    pub fn get_health_bind(&self) -> Option<&str> {
+        self.health.as_ref().and_then(|h| h.bind.as_deref())
+    }
+
+    pub fn get_grpc_bind(&self) -> Option<&str> {
+        self.grpc.as_ref().and_then(|g| g.bind.as_deref())
+    }
+
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub fn get_grpc_token(&self) -> Option<&str> {
+        self.grpc.as_ref().and_then(|g| g.token.as_deref())
+    }
+
+    pub fn get_otlp_endpoint(&self) -> Option<&str> {
+        self.telemetry
+            .as_ref()
+            .and_then(|t| t.otlp_endpoint.as_deref())
+    }
+
+    #[cfg_attr(not(feature = "otel"), allow(dead_code))]
+    pub fn get_service_name(&self) -> &str {
+        self.telemetry
+            .as_ref()
+            .and_then(|t| t.service_name.as_deref())
+            .unwrap_or("grabby")
+    }
+
+    pub fn get_sentry_dsn(&self) -> Option<&str> {
+        self.error_reporting
+            .as_ref()
+            .and_then(|r| r.sentry_dsn.as_deref())
+    }
+
+    #[cfg_attr(not(feature = "sentry"), allow(dead_code))]
+    pub fn get_sentry_environment(&self) -> Option<&str> {
+        self.error_reporting
+            .as_ref()
+            .and_then(|r| r.environment.as_deref())
+    }
+
+    #[cfg_attr(not(feature = "sentry"), allow(dead_code))]
+    pub fn get_error_repeat_threshold(&self) -> u32 {
+        self.error_reporting
+            .as_ref()
+            .and_then(|r| r.repeat_threshold)
+            .unwrap_or(3)
+            .max(1)
+    }
+
+    pub fn get_stall_threshold(&self) -> Duration {
+        let secs = self
+            .health
+            .as_ref()
+            .and_then(|h| h.stall_threshold_secs)
+            .unwrap_or(30);
+
+        Duration::from_secs(secs.max(1))
+    }
+
+    pub fn get_redis_url(&self) -> Option<&str> {
+        self.cluster.as_ref().and_then(|c| c.redis_url.as_deref())
+    }
+
+    #[cfg_attr(not(feature = "redis"), allow(dead_code))]
+    pub fn get_cluster_key_prefix(&self) -> &str {
+        self.cluster
+            .as_ref()
+            .and_then(|c| c.key_prefix.as_deref())
+            .unwrap_or("grabby")
+    }
+
+    #[cfg_attr(not(feature = "redis"), allow(dead_code))]
+    pub fn get_claim_ttl(&self) -> Duration {
+        let secs = self
+            .cluster
+            .as_ref()
+            .and_then(|c| c.claim_ttl_secs)
+            .unwrap_or(600);
+        Duration::from_secs(secs.max(1))
+    }
+
+    pub fn get_watch_interval(&self) -> Duration {
+        let secs = self
+            .watch
+            .as_ref()
+            .and_then(|w| w.poll_interval_secs)
+            .unwrap_or(900);
+        Duration::from_secs(secs.max(60))
+    }
+
+    pub fn get_max_watches(&self) -> usize {
+        self.watch
+            .as_ref()
+            .and_then(|w| w.max_per_server)
+            .unwrap_or(10)
+    }
+
+    pub fn get_settings_refresh(&self) -> Duration {
+        let secs = self
+            .cluster
+            .as_ref()
+            .and_then(|c| c.settings_refresh_secs)
+            .unwrap_or(30);
+        Duration::from_secs(secs.max(5))
+    }
+
+    pub fn get_storage_backend(&self) -> StorageBackend {
+        self.storage
+            .as_ref()
+            .and_then(|s| s.backend.as_deref())
+            .and_then(StorageBackend::from_name)
+            .unwrap_or_default()
+    }
+
+    pub fn get_data_dir(&self) -> PathBuf {
+        self.storage
+            .as_ref()
+            .and_then(|s| s.data_dir.as_deref())
+            .unwrap_or("data")
+            .into()
+    }
+
+    /// Directory to bootstrap missing tools into, or `None` if bootstrapping is disabled.
+    pub fn get_bootstrap_dir(&self) -> Option<PathBuf> {
+        let bootstrap = self.bootstrap.as_ref()?;
+        if !bootstrap.enabled.unwrap_or(false) {
+            return None;
+        }
+
+        Some(
+            bootstrap
+                .dir
+                .as_deref()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| self.get_data_dir().join("tools")),
+        )
+    }
+
+    /// gallery-dl sites with their settings, sorted by extractor name.
+    pub fn get_gallery_dl_sites(&self) -> Vec<GalleryDlSite> {
+        let mut sites: Vec<GalleryDlSite> = self
+            .gallery_dl
+            .iter()
+            .flatten()
+            .map(|(extractor, site)| GalleryDlSite {
+                extractor: extractor.clone(),
+                domains: site.domains.clone().unwrap_or_else(|| {
+                    GalleryDlSite::default_domains(extractor)
+                        .iter()
+                        .map(|d| d.to_string())
+                        .collect()
+                }),
+                username: site.username.clone(),
+                password: site.password.clone(),
+                api_key: site.api_key.clone(),
+                user_id: site.user_id.clone(),
+                refresh_token: site.refresh_token.clone(),
+                cookies: site.cookies.clone(),
+                ugoira_to_video: site.ugoira_to_video.unwrap_or(true),
+            })
+            .collect();
+        sites.sort_by(|a, b| a.extractor.cmp(&b.extractor));
+        sites
+    }
+
+    pub fn get_tool_pins(&self) -> Vec<ToolPin> {
+        let bootstrap = self.bootstrap.clone().unwrap_or_default();
+        let pin = |tool: Tool, version: Option<String>, sha256: Option<String>| ToolPin {
+            tool,
+            version: version.unwrap_or_else(|| tool.default_version().to_string()),
+            sha256,
+        };
+
+        vec![
+            pin(
+                Tool::YtDlp,
+                bootstrap.yt_dlp_version,
+                bootstrap.yt_dlp_sha256,
+            ),
+            pin(
+                Tool::GalleryDl,
+                bootstrap.gallery_dl_version,
+                bootstrap.gallery_dl_sha256,
+            ),
+        ]
+    }
+}
+
+pub struct ConfigManager {
+    /// Server settings, changed at runtime by config commands
+    configs: RwLock<HashMap<Id<GuildMarker>, ServerConfig>>,
+    global: Config,
+    /// File the config was loaded from, if any
+    path: Option<PathBuf>,
+}
+
+impl ConfigManager {
+    pub fn new() -> Self {
+        Self {
+            configs: RwLock::new(HashMap::new()),
+            global: Config::default(),
+            path: None,
+        }
+    }
+
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config = Config::from_file(&path)?;
+        let configs: HashMap<Id<GuildMarker>, ServerConfig> = config
+            .servers
+            .iter()
+            .map(|s| (s.server_id, s.clone()))
+            .collect();
+
+        Ok(Self {
+            configs: RwLock::new(configs),
+            global: config,
+            path: Some(path.as_ref().to_path_buf()),
+        })
+    }
+
+    /// Loads the config file this manager was created from again.
+    pub fn reload(&self) -> Result<Self> {
+        let path = self
+            .path
+            .as_ref()
+            .context("Running without a config file, nothing to reload")?;
+        Self::from_config_file(path)
+    }
+
+    pub fn server_count(&self) -> usize {
+        self.read_configs().len()
+    }
+
+    /// Returns the settings that apply to all servers.
+    pub fn global(&self) -> &Config {
+        &self.global
+    }
+
+    pub fn get_server_config(&self, server_id: Id<GuildMarker>) -> ServerConfig {
+        self.read_configs()
+            .get(&server_id)
+            .cloned()
+            .unwrap_or_else(|| ServerConfig::new(server_id))
+    }
+
+    /// Downloader order for a server, falling back to the global order outside of servers.
+    pub fn get_downloader_order(&self, server_id: Option<Id<GuildMarker>>) -> Vec<String> {
+        server_id
+            .and_then(|id| self.read_configs().get(&id)?.downloader_order.clone())
+            .unwrap_or_else(|| self.global.get_downloader_order())
+    }
+
+    /// Seconds videos may last before they are refused, if capped.
+    pub fn get_duration_cap(&self, server_id: Option<Id<GuildMarker>>) -> Option<u64> {
+        match server_id.and_then(|id| self.read_configs().get(&id)?.duration_cap_secs) {
+            Some(secs) => Some(secs).filter(|secs| *secs > 0),
+            None => self.global.get_duration_cap(),
+        }
+    }
+
+    /// Feeds a server may watch at once.
+    pub fn get_max_watches(&self, server_id: Id<GuildMarker>) -> usize {
+        self.read_configs()
+            .get(&server_id)
+            .and_then(|config| config.max_watches)
+            .unwrap_or_else(|| self.global.get_max_watches())
+    }
+
+    pub fn get_server_branding(&self, server_id: Option<Id<GuildMarker>>) -> BrandingConfig {
+        server_id
+            .and_then(|id| Some(self.read_configs().get(&id)?.branding.clone()))
+            .unwrap_or_default()
+    }
+
+    /// Monthly download limits of a server, resolved from its settings, the requester's tier and
+    /// the global config.
+    pub fn get_quota(&self, server_id: Id<GuildMarker>, tier: Option<&TierConfig>) -> QuotaConfig {
+        let mut global = self.global.quota.clone().unwrap_or_default();
+        if let Some(quota) = tier.and_then(|tier| tier.quota.as_ref()) {
+            global = quota.or(&global);
+        }
+        match self
+            .read_configs()
+            .get(&server_id)
+            .and_then(|config| config.quota.as_ref())
+        {
+            Some(quota) => quota.or(&global),
+            None => global,
+        }
+    }
+
+    /// Embed settings of a channel, resolved from the channel, its server and the global config.
+    pub fn get_channel_config(
+        &self,
+        server_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+    ) -> ChannelConfig {
+        let global = self.global.embed.clone().unwrap_or_default();
+        match server_id.and_then(|id| self.read_configs().get(&id).cloned()) {
+            Some(server) => server.channel_config(channel_id).or(&global),
+            None => global,
+        }
+    }
+
+    /// Whether the user is blocked globally or in the server, also through one of their roles.
+    pub fn blocks_user(
+        &self,
+        server_id: Option<Id<GuildMarker>>,
+        user_id: Id<UserMarker>,
+        role_ids: &[Id<RoleMarker>],
+    ) -> bool {
+        if self
+            .global
+            .blocklist
+            .as_ref()
+            .is_some_and(|blocklist| blocklist.blocks_user(user_id, role_ids))
+        {
+            return true;
+        }
+        server_id.is_some_and(|server_id| {
+            self.read_configs()
+                .get(&server_id)
+                .is_some_and(|config| config.blocklist.blocks_user(user_id, role_ids))
+        })
+    }
+
+    /// Whether the link is blocked globally or in the server.
+    pub fn blocks_url(&self, server_id: Option<Id<GuildMarker>>, url: &str) -> bool {
+        if self
+            .global
+            .blocklist
+            .as_ref()
+            .is_some_and(|blocklist| blocklist.blocks_url(url))
+        {
+            return true;
+        }
+        server_id.is_some_and(|server_id| {
+            self.read_configs()
+                .get(&server_id)
+                .is_some_and(|config| config.blocklist.blocks_url(url))
+        })
+    }
+
+    /// Adds an entry to a server's blocklist or removes it.
+    pub fn set_blocked(
+        &self,
+        server_id: Id<GuildMarker>,
+        entry: &BlockEntry,
+        blocked: bool,
+    ) -> ServerConfig {
+        self.update_server_config(server_id, |config| {
+            config.blocklist.set(entry, blocked);
+        })
+    }
+
+    /// Applies `f` to a server's settings, creating them if the server has none yet, and returns
+    /// the result.
+    pub fn update_server_config(
+        &self,
+        server_id: Id<GuildMarker>,
+        f: impl FnOnce(&mut ServerConfig),
+    ) -> ServerConfig {
+        let mut configs = self.configs.write().unwrap_or_else(PoisonError::into_inner);
+        let config = configs
+            .entry(server_id)
+            .or_insert_with(|| ServerConfig::new(server_id));
+        f(config);
+        config.clone()
+    }
+
+    /// Turns auto-embedding in a channel on or off.
+    pub fn set_auto_embed_channel(
+        &self,
+        server_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        enabled: bool,
+    ) -> ServerConfig {
+        self.update_server_config(server_id, |config| {
+            if enabled {
+                config.auto_embed_channels.insert(channel_id);
+            } else {
+                config.auto_embed_channels.remove(&channel_id);
+            }
+        })
+    }
+
+    /// Allows or disallows the `/embed` command in a server.
+    pub fn set_enabled(&self, server_id: Id<GuildMarker>, enabled: bool) -> ServerConfig {
+        self.update_server_config(server_id, |config| config.embed_enabled = enabled)
+    }
+
+    /// Posts auto-embeds of a channel to `archive` as well, or stops doing so.
+    pub fn set_mirror_channel(
+        &self,
+        server_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        archive: Option<Id<ChannelMarker>>,
+    ) -> ServerConfig {
+        self.update_server_config(server_id, |config| match archive {
+            Some(archive) => {
+                config.mirror_channels.insert(channel_id, archive);
+            }
+            None => {
+                config.mirror_channels.remove(&channel_id);
+            }
+        })
+    }
+
+    pub fn set_job_webhook(&self, server_id: Id<GuildMarker>, url: Option<String>) -> ServerConfig {
+        self.update_server_config(server_id, |config| config.job_webhook = url)
+    }
+
+    /// Sets the given embed settings of a channel, keeping the ones left unset, or drops all of
+    /// them with `reset` so the server's apply again.
+    pub fn set_channel_config(
+        &self,
+        server_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        overrides: ChannelConfig,
+        reset: bool,
+    ) -> ServerConfig {
+        self.update_server_config(server_id, |config| {
+            let current = config.channels.remove(&channel_id).unwrap_or_default();
+            let updated = if reset {
+                overrides
+            } else {
+                overrides.or(&current)
+            };
+            if !updated.is_empty() {
+                config.channels.insert(channel_id, updated);
+            }
+        })
+    }
+
+    /// Drops the settings of a server changed at runtime, going back to its entry in the config
+    /// file, if it has one.
+    pub fn reset_server_config(&self, server_id: Id<GuildMarker>) {
+        let mut configs = self.configs.write().unwrap_or_else(PoisonError::into_inner);
+        match self
+            .global
+            .servers
+            .iter()
+            .find(|s| s.server_id == server_id)
+        {
+            Some(config) => configs.insert(server_id, config.clone()),
+            None => configs.remove(&server_id),
+        };
+    }
+
+    /// Replaces the settings of the given servers, e.g. with ones saved at runtime.
+    pub fn apply_server_configs(&self, configs: impl IntoIterator<Item = ServerConfig>) {
+        let mut current = self.configs.write().unwrap_or_else(PoisonError::into_inner);
+        for config in configs {
+            current.insert(config.server_id, config);
+        }
+    }
+
+    fn read_configs(
+        &self,
+    ) -> std::sync::RwLockReadGuard<'_, HashMap<Id<GuildMarker>, ServerConfig>> {
+        self.configs.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config_new() {
+        let config = ServerConfig::new(Id::new(1));
+        assert_eq!(config.server_id, Id::new(1));
+        assert!(config.auto_embed_channels.is_empty());
+        assert!(config.embed_enabled);
+    }
+
+    #[test]
+    fn test_server_config_locale() {
+        let mut config = ServerConfig::new(Id::new(1));
+        assert_eq!(config.locale(), Locale::En);
+
+        config.locale = Some("sl".to_string());
+        assert_eq!(config.locale(), Locale::Sl);
+
+        config.locale = Some("unknown".to_string());
+        assert_eq!(config.locale(), Locale::En);
+    }
+
+    #[test]
+    fn test_branding_color() {
+        let branding = |color: &str| BrandingConfig {
+            color: Some(color.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(branding("#5865F2").color(), Some(0x5865F2));
+        assert_eq!(branding("ff0000").color(), Some(0xFF0000));
+        assert_eq!(branding("#fff").color(), None);
+        assert_eq!(branding("red").color(), None);
+        assert_eq!(BrandingConfig::default().color(), None);
+        assert!(BrandingConfig::default().show_credit());
+    }
+
+    #[test]
+    fn test_server_config_is_auto_embed_channel_found() {
+        let mut config = ServerConfig::new(Id::new(1));
+        config.auto_embed_channels = HashSet::from([Id::new(10), Id::new(11)]);
+
+        assert!(config.is_auto_embed_channel(Id::new(10)));
+        assert!(config.is_auto_embed_channel(Id::new(11)));
+    }
+
+    #[test]
+    fn test_server_config_is_auto_embed_channel_not_found() {
+        let mut config = ServerConfig::new(Id::new(1));
+        config.auto_embed_channels = HashSet::from([Id::new(10)]);
+
+        assert!(!config.is_auto_embed_channel(Id::new(11)));
+        assert!(!config.is_auto_embed_channel(Id::new(99)));
+    }
+
+    #[test]
+    fn test_server_config_is_auto_embed_target_thread() {
+        let mut config = ServerConfig::new(Id::new(1));
+        let (channel, forum, thread) = (Id::new(10), Id::new(20), Id::new(30));
+        config.auto_embed_channels = HashSet::from([channel]);
+        config.auto_embed_thread_parents = HashSet::from([forum]);
+
+        assert!(config.is_auto_embed_target(channel, None));
+        assert!(config.is_auto_embed_target(thread, Some(forum)));
+        assert!(!config.is_auto_embed_target(thread, Some(channel)));
+        assert!(!config.is_auto_embed_target(thread, None));
+        assert!(!config.is_auto_embed_target(forum, None));
+    }
+
+    #[test]
+    fn test_config_get_discord_token_some() {
+        let config = Config {
+            discord: Some(DiscordConfig {
+                token: Some("test_token".to_string()),
+                ..Default::default()
+            }),
+            servers: vec![],
+            logging: None,
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_discord_token(), Some("test_token".to_string()));
+    }
+
+    #[test]
+    fn test_config_get_discord_token_none() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: None,
+            ..Default::default()
+        };
+
+        assert!(config.get_discord_token().is_none());
+    }
+
+    #[test]
+    fn test_config_get_discord_token_none_inner() {
+        let config = Config {
+            discord: Some(DiscordConfig {
+                token: None,
+                ..Default::default()
+            }),
+            servers: vec![],
+            logging: None,
+            ..Default::default()
+        };
+
+        assert!(config.get_discord_token().is_none());
+    }
+
+    #[test]
+    fn test_config_get_logging_format_custom() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: Some(LoggingConfig {
+                format: Some("pretty".to_string()),
+                level: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_logging_format(), "pretty");
+    }
+
+    #[test]
+    fn test_config_get_logging_format_none() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: None,
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_logging_format(), "json");
+    }
+
+    #[test]
+    fn test_config_get_logging_format_none_inner() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: Some(LoggingConfig {
+                format: None,
+                level: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_logging_format(), "json");
+    }
+
+    #[test]
+    fn test_config_get_log_level_custom() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: Some(LoggingConfig {
+                format: None,
+                level: Some("debug".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_log_level(), "debug");
+    }
+
+    #[test]
+    fn test_config_get_log_level_none() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: None,
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_log_level(), "info");
+    }
+
+    #[test]
+    fn test_config_get_log_level_none_inner() {
+        let config = Config {
+            discord: None,
+            servers: vec![],
+            logging: Some(LoggingConfig {
+                format: None,
+                level: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_log_level(), "info");
+    }
+
+    #[test]
+    fn test_config_log_file_defaults() {
+        let config = Config::default();
+
+        assert!(config.get_log_directory().is_none());
+        assert_eq!(config.get_log_file_prefix(), "grabby");
+        assert_eq!(config.get_log_rotation(), LogRotation::Daily);
+        assert_eq!(config.get_log_max_files(), Some(7));
+    }
+
+    #[test]
+    fn test_config_log_file_from_toml() {
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [logging]
+            directory = "/var/log/grabby"
+            file_prefix = "bot"
+            rotation = "hourly"
+            max_files = 0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_log_directory(),
+            Some(Path::new("/var/log/grabby"))
+        );
+        assert_eq!(config.get_log_file_prefix(), "bot");
+        assert_eq!(config.get_log_rotation(), LogRotation::Hourly);
+        assert_eq!(config.get_log_max_files(), None);
+    }
+
+    #[test]
+    fn test_config_get_idle_timeout_default() {
+        let config = Config::default();
+        assert_eq!(config.get_idle_timeout(), Some(Duration::from_secs(900)));
+    }
+
+    #[test]
+    fn test_config_get_idle_timeout_custom() {
+        let config = Config {
+            media: Some(MediaConfig {
+                idle_timeout_secs: Some(60),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(config.get_idle_timeout(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_config_get_idle_timeout_disabled() {
+        let config = Config {
+            media: Some(MediaConfig {
+                idle_timeout_secs: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(config.get_idle_timeout().is_none());
+    }
+
+    #[test]
+    fn test_config_get_min_free_disk_bytes() {
+        assert_eq!(
+            Config::default().get_min_free_disk_bytes(),
+            Some(500_000_000)
+        );
+
+        let config = Config {
+            media: Some(MediaConfig {
+                min_free_disk_mb: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(config.get_min_free_disk_bytes().is_none());
+    }
+
+    #[test]
+    fn test_config_get_memory_budget_bytes() {
+        assert!(Config::default().get_memory_budget_bytes().is_none());
+
+        let config = Config {
+            media: Some(MediaConfig {
+                memory_budget_mb: Some(512),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(config.get_memory_budget_bytes(), Some(512_000_000));
+    }
+
+    #[test]
+    fn test_config_get_normalize_audio() {
+        assert!(Config::default().get_normalize_audio());
+
+        let config = Config {
+            media: Some(MediaConfig {
+                normalize_audio: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(!config.get_normalize_audio());
+    }
+
+    #[test]
+    fn test_config_get_video_codec() {
+        assert_eq!(Config::default().get_video_codec(), VideoCodec::H264);
+
+        let config = Config {
+            media: Some(MediaConfig {
+                video_codec: Some("vp9".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(config.get_video_codec(), VideoCodec::Vp9);
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            temp_file.path(),
+            "servers = []\n[media]\nvideo_codec = \"hevc\"\n",
+        )
+        .unwrap();
+        let error = Config::from_file(temp_file.path()).unwrap_err();
+        assert!(error.to_string().contains("media.video_codec"));
+    }
+
+    #[test]
+    fn test_downloader_order() {
+        let config: Config = toml::from_str(
+            r#"
+            [media]
+            downloader_order = ["yt-dlp", "gallery-dl"]
+
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            downloader_order = ["gallery-dl"]
+
+            [[servers]]
+            server_id = "2"
+            auto_embed_channels = []
+            embed_enabled = true
+        "#,
+        )
+        .unwrap();
+        let manager = ConfigManager {
+            configs: RwLock::new(
+                config
+                    .servers
+                    .iter()
+                    .map(|s| (s.server_id, s.clone()))
+                    .collect(),
+            ),
+            global: config,
+            path: None,
+        };
+
+        assert_eq!(
+            manager.get_downloader_order(Some(Id::new(1))),
+            vec!["gallery-dl"]
+        );
+        assert_eq!(
+            manager.get_downloader_order(Some(Id::new(2))),
+            vec!["yt-dlp", "gallery-dl"]
+        );
+        assert_eq!(
+            manager.get_downloader_order(None),
+            vec!["yt-dlp", "gallery-dl"]
+        );
+        assert_eq!(
+            Config::default().get_downloader_order(),
+            vec!["gallery-dl", "yt-dlp"]
+        );
     }
 
     #[test]
-    fn test_server_config_is_auto_embed_channel_found() {
-        let mut config = ServerConfig::new("test_server");
-        config.auto_embed_channels =
-            HashSet::from(["channel1".to_string(), "channel2".to_string()]);
+    fn test_log_channels() {
+        let config: Config = toml::from_str(
+            r#"
+            [discord]
+            log_channel = "100"
 
-        assert!(config.is_auto_embed_channel("channel1"));
-        assert!(config.is_auto_embed_channel("channel2"));
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            log_channel = "200"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.get_log_channel(), Some(Id::new(100)));
+        assert_eq!(config.servers[0].log_channel, Some(Id::new(200)));
+        assert_eq!(Config::default().get_log_channel(), None);
     }
 
     #[test]
-    fn test_server_config_is_auto_embed_channel_not_found() {
-        let mut config = ServerConfig::new("test_server");
-        config.auto_embed_channels = HashSet::from(["channel1".to_string()]);
+    fn test_is_owner() {
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
 
-        assert!(!config.is_auto_embed_channel("channel2"));
-        assert!(!config.is_auto_embed_channel("nonexistent"));
+            [discord]
+            owner_ids = ["42"]
+        "#,
+        )
+        .unwrap();
+
+        assert!(config.is_owner(Id::new(42)));
+        assert!(!config.is_owner(Id::new(43)));
+        assert!(!Config::default().is_owner(Id::new(42)));
     }
 
     #[test]
-    fn test_config_get_discord_token_some() {
-        let config = Config {
-            discord: Some(DiscordConfig {
-                token: Some("test_token".to_string()),
-            }),
-            servers: vec![],
-            logging: None,
+    fn test_config_manager_reload() {
+        let server = |id: &str| {
+            format!("[[servers]]\nserver_id = \"{id}\"\nauto_embed_channels = []\nembed_enabled = true\n")
         };
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), server("1")).unwrap();
 
-        assert_eq!(config.get_discord_token(), Some("test_token".to_string()));
-    }
+        let manager = ConfigManager::from_config_file(file.path()).unwrap();
+        assert_eq!(manager.server_count(), 1);
 
-    #[test]
-    fn test_config_get_discord_token_none() {
-        let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: None,
-        };
+        std::fs::write(file.path(), server("1") + &server("2")).unwrap();
+        let reloaded = manager.reload().unwrap();
+        assert_eq!(reloaded.server_count(), 2);
+        assert_eq!(reloaded.get_server_config(Id::new(2)).server_id, Id::new(2));
 
-        assert!(config.get_discord_token().is_none());
+        assert!(ConfigManager::new().reload().is_err());
     }
 
     #[test]
-    fn test_config_get_discord_token_none_inner() {
-        let config = Config {
-            discord: Some(DiscordConfig { token: None }),
-            servers: vec![],
-            logging: None,
-        };
+    fn test_config_manager_runtime_changes() {
+        let manager = ConfigManager::new();
 
-        assert!(config.get_discord_token().is_none());
+        let (guild, channel) = (Id::new(1), Id::new(10));
+
+        let config = manager.set_auto_embed_channel(guild, channel, true);
+        assert!(config.is_auto_embed_channel(channel));
+        assert!(manager
+            .get_server_config(guild)
+            .is_auto_embed_channel(channel));
+        assert_eq!(manager.server_count(), 1);
+
+        manager.set_auto_embed_channel(guild, channel, false);
+        assert!(!manager
+            .get_server_config(guild)
+            .is_auto_embed_channel(channel));
+
+        manager.set_enabled(guild, false);
+        assert!(!manager.get_server_config(guild).embed_enabled);
+
+        let mut saved = ServerConfig::new(Id::new(2));
+        saved.embed_enabled = false;
+        manager.apply_server_configs([saved]);
+        assert!(!manager.get_server_config(Id::new(2)).embed_enabled);
+        assert_eq!(manager.server_count(), 2);
     }
 
     #[test]
-    fn test_config_get_logging_format_custom() {
+    fn test_config_get_audio_format() {
+        assert_eq!(Config::default().get_audio_format(), AudioFormat::Opus);
+
         let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: Some(LoggingConfig {
-                format: Some("pretty".to_string()),
-                level: None,
+            media: Some(MediaConfig {
+                audio_format: Some("mp3".to_string()),
+                ..Default::default()
             }),
+            ..Default::default()
         };
+        assert_eq!(config.get_audio_format(), AudioFormat::Mp3);
+    }
 
-        assert_eq!(config.get_logging_format(), "pretty");
+    #[test]
+    fn test_duration_cap_per_server() {
+        let toml_content = r#"
+            [media]
+            duration_cap_secs = 900
+
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            duration_cap_secs = 300
+
+            [[servers]]
+            server_id = "2"
+            auto_embed_channels = []
+            embed_enabled = true
+            duration_cap_secs = 0
+        "#;
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), toml_content).unwrap();
+        let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
+
+        assert_eq!(manager.get_duration_cap(Some(Id::new(1))), Some(300));
+        assert_eq!(manager.get_duration_cap(Some(Id::new(2))), None);
+        assert_eq!(manager.get_duration_cap(Some(Id::new(3))), Some(900));
+        assert_eq!(manager.get_duration_cap(None), Some(900));
     }
 
     #[test]
-    fn test_config_get_logging_format_none() {
-        let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: None,
-        };
+    fn test_max_watches_per_server() {
+        let toml_content = r#"
+            [watch]
+            max_per_server = 5
 
-        assert_eq!(config.get_logging_format(), "json");
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            max_watches = 20
+        "#;
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), toml_content).unwrap();
+        let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
+
+        assert_eq!(manager.get_max_watches(Id::new(1)), 20);
+        assert_eq!(manager.get_max_watches(Id::new(2)), 5);
+        assert_eq!(Config::default().get_max_watches(), 10);
+        assert_eq!(
+            Config::default().get_watch_interval(),
+            Duration::from_secs(900)
+        );
+        assert_eq!(ConfigManager::new().get_duration_cap(None), None);
     }
 
     #[test]
-    fn test_config_get_logging_format_none_inner() {
+    fn test_config_get_duration_limit() {
+        assert_eq!(Config::default().get_duration_limit(), None);
+
         let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: Some(LoggingConfig {
-                format: None,
-                level: None,
+            media: Some(MediaConfig {
+                max_duration_secs: Some(600),
+                ..Default::default()
             }),
+            ..Default::default()
         };
-
-        assert_eq!(config.get_logging_format(), "json");
+        assert_eq!(
+            config.get_duration_limit(),
+            Some(DurationLimit {
+                max_secs: 600,
+                clip_secs: 60
+            })
+        );
     }
 
     #[test]
-    fn test_config_get_log_level_custom() {
-        let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: Some(LoggingConfig {
-                format: None,
-                level: Some("debug".to_string()),
+    fn test_config_get_live_capture_secs() {
+        assert_eq!(Config::default().get_live_capture_secs(), None);
+
+        let config = |secs| Config {
+            media: Some(MediaConfig {
+                live_capture_secs: Some(secs),
+                ..Default::default()
             }),
+            ..Default::default()
         };
+        assert_eq!(config(0).get_live_capture_secs(), None);
+        assert_eq!(config(30).get_live_capture_secs(), Some(30));
+    }
 
-        assert_eq!(config.get_log_level(), "debug");
+    #[test]
+    fn test_config_get_timeouts() {
+        assert_eq!(Config::default().get_timeouts(), Timeouts::default());
+
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [media.timeouts]
+            download_secs = 300
+
+            [media.timeouts.downloaders."yt-dlp"]
+            download_secs = 900
+            upload_secs = 0
+            "#,
+        )
+        .unwrap();
+        let timeouts = config.get_timeouts();
+        assert_eq!(timeouts.defaults.download, Duration::from_secs(300));
+        assert_eq!(timeouts.defaults.extraction, Duration::from_secs(30));
+
+        let ytdlp = timeouts.for_downloader("yt-dlp");
+        assert_eq!(ytdlp.download, Duration::from_secs(900));
+        assert_eq!(ytdlp.upload, Duration::from_secs(1));
+        assert_eq!(ytdlp.transcode, Duration::from_secs(600));
+        assert_eq!(
+            timeouts.for_downloader("gallery-dl").download,
+            Duration::from_secs(300)
+        );
     }
 
     #[test]
-    fn test_config_get_log_level_none() {
+    fn test_config_get_gallery_zip_threshold() {
+        assert_eq!(Config::default().get_gallery_zip_threshold(), Some(20));
+
         let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: None,
+            media: Some(MediaConfig {
+                gallery_zip_threshold: Some(0),
+                ..Default::default()
+            }),
+            ..Default::default()
         };
+        assert_eq!(config.get_gallery_zip_threshold(), None);
+    }
 
-        assert_eq!(config.get_log_level(), "info");
+    #[test]
+    fn test_config_get_health_defaults() {
+        let config = Config::default();
+        assert!(config.get_health_bind().is_none());
+        assert_eq!(config.get_stall_threshold(), Duration::from_secs(30));
     }
 
     #[test]
-    fn test_config_get_log_level_none_inner() {
+    fn test_config_get_health_custom() {
         let config = Config {
-            discord: None,
-            servers: vec![],
-            logging: Some(LoggingConfig {
-                format: None,
-                level: None,
+            health: Some(HealthConfig {
+                bind: Some("127.0.0.1:8080".to_string()),
+                stall_threshold_secs: Some(10),
             }),
+            ..Default::default()
         };
+        assert_eq!(config.get_health_bind(), Some("127.0.0.1:8080"));
+        assert_eq!(config.get_stall_threshold(), Duration::from_secs(10));
+    }
 
-        assert_eq!(config.get_log_level(), "info");
+    #[test]
+    fn test_config_telemetry() {
+        let config = Config::default();
+        assert_eq!(config.get_otlp_endpoint(), None);
+        assert_eq!(config.get_service_name(), "grabby");
+
+        let config: Config = toml::from_str(
+            r#"
+servers = []
+
+[telemetry]
+otlp_endpoint = "http://localhost:4318"
+service_name = "grabby-eu"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.get_otlp_endpoint(), Some("http://localhost:4318"));
+        assert_eq!(config.get_service_name(), "grabby-eu");
+    }
+
+    #[test]
+    fn test_config_cluster() {
+        let config = Config::default();
+        assert_eq!(config.get_redis_url(), None);
+        assert_eq!(config.get_cluster_key_prefix(), "grabby");
+        assert_eq!(config.get_claim_ttl(), Duration::from_secs(600));
+        assert_eq!(config.get_settings_refresh(), Duration::from_secs(30));
+
+        let config: Config = toml::from_str(
+            r#"
+servers = []
+
+[cluster]
+redis_url = "redis://localhost:6379"
+key_prefix = "grabby-eu"
+claim_ttl_secs = 60
+settings_refresh_secs = 1
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.get_redis_url(), Some("redis://localhost:6379"));
+        assert_eq!(config.get_cluster_key_prefix(), "grabby-eu");
+        assert_eq!(config.get_claim_ttl(), Duration::from_secs(60));
+        assert_eq!(config.get_settings_refresh(), Duration::from_secs(5));
     }
 
     #[test]
@@ -322,12 +2559,12 @@ mod tests {
             level = "debug"
 
             [[servers]]
-            server_id = "server1"
-            auto_embed_channels = ["channel1", "channel2"]
+            server_id = "1"
+            auto_embed_channels = ["10", "11"]
             embed_enabled = true
 
             [[servers]]
-            server_id = "server2"
+            server_id = "2"
             auto_embed_channels = []
             embed_enabled = false
         "#;
@@ -341,15 +2578,49 @@ mod tests {
         assert_eq!(config.get_logging_format(), "pretty");
         assert_eq!(config.get_log_level(), "debug");
         assert_eq!(config.servers.len(), 2);
-        assert_eq!(config.servers[0].server_id, "server1");
-        assert_eq!(config.servers[1].server_id, "server2");
+        assert_eq!(config.servers[0].server_id, Id::new(1));
+        assert_eq!(config.servers[1].server_id, Id::new(2));
+    }
+
+    #[test]
+    fn test_config_ids_as_strings_or_numbers() {
+        let config: Config = toml::from_str(
+            r#"
+            [[servers]]
+            server_id = 1
+            auto_embed_channels = ["10", 11]
+            embed_enabled = true
+            config_role_ids = [20]
+            auto_delete_channels = { 10 = 60 }
+            mirror_channels = { 10 = "30", 11 = 11 }
+        "#,
+        )
+        .unwrap();
+
+        let server = &config.servers[0];
+        assert_eq!(server.server_id, Id::new(1));
+        assert!(server.is_auto_embed_channel(Id::new(10)));
+        assert!(server.is_auto_embed_channel(Id::new(11)));
+        assert!(server.config_role_ids.contains(&Id::new(20)));
+        assert_eq!(
+            server.auto_delete_after(Id::new(10)),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(server.mirror_channel(Id::new(10)), Some(Id::new(30)));
+        // A channel isn't mirrored into itself
+        assert_eq!(server.mirror_channel(Id::new(11)), None);
+
+        assert!(toml::from_str::<Config>(
+            "[[servers]]\nserver_id = \"guild\"\nauto_embed_channels = []\nembed_enabled = true"
+        )
+        .is_err());
     }
 
     #[test]
     fn test_config_from_file_minimal() {
         let toml_content = r#"
             [[servers]]
-            server_id = "server1"
+            server_id = "1"
             auto_embed_channels = []
             embed_enabled = true
         "#;
@@ -396,19 +2667,19 @@ mod tests {
     #[test]
     fn test_config_manager_new() {
         let manager = ConfigManager::new();
-        assert!(manager.configs.is_empty());
+        assert_eq!(manager.server_count(), 0);
     }
 
     #[test]
     fn test_config_manager_from_config_file() {
         let toml_content = r#"
             [[servers]]
-            server_id = "server1"
-            auto_embed_channels = ["channel1"]
+            server_id = "1"
+            auto_embed_channels = ["10"]
             embed_enabled = true
 
             [[servers]]
-            server_id = "server2"
+            server_id = "2"
             auto_embed_channels = []
             embed_enabled = false
         "#;
@@ -418,17 +2689,17 @@ mod tests {
 
         let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
 
-        assert_eq!(manager.configs.len(), 2);
-        assert!(manager.configs.contains_key("server1"));
-        assert!(manager.configs.contains_key("server2"));
+        assert_eq!(manager.server_count(), 2);
+        assert_eq!(manager.get_server_config(Id::new(1)).server_id, Id::new(1));
+        assert!(!manager.get_server_config(Id::new(2)).embed_enabled);
     }
 
     #[test]
     fn test_config_manager_get_server_config_existing() {
         let toml_content = r#"
             [[servers]]
-            server_id = "server1"
-            auto_embed_channels = ["channel1"]
+            server_id = "1"
+            auto_embed_channels = ["10"]
             embed_enabled = false
         "#;
 
@@ -436,32 +2707,32 @@ mod tests {
         std::fs::write(temp_file.path(), toml_content).unwrap();
 
         let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
-        let config = manager.get_server_config("server1");
+        let config = manager.get_server_config(Id::new(1));
 
-        assert_eq!(config.server_id, "server1");
+        assert_eq!(config.server_id, Id::new(1));
         assert!(!config.embed_enabled);
-        assert!(config.is_auto_embed_channel("channel1"));
+        assert!(config.is_auto_embed_channel(Id::new(10)));
     }
 
     #[test]
     fn test_config_manager_get_server_config_default() {
         let manager = ConfigManager::new();
-        let config = manager.get_server_config("new_server");
+        let config = manager.get_server_config(Id::new(5));
 
-        assert_eq!(config.server_id, "new_server");
+        assert_eq!(config.server_id, Id::new(5));
         assert!(config.auto_embed_channels.is_empty());
         assert!(config.embed_enabled);
     }
 
     #[test]
     fn test_is_domain_disabled_empty() {
-        let config = ServerConfig::new("test_server");
+        let config = ServerConfig::new(Id::new(1));
         assert!(!config.is_domain_disabled("https://example.com"));
     }
 
     #[test]
     fn test_is_domain_disabled_exact_match() {
-        let mut config = ServerConfig::new("test_server");
+        let mut config = ServerConfig::new(Id::new(1));
         config.disabled_domains.insert("example.com".to_string());
 
         assert!(config.is_domain_disabled("https://example.com"));
@@ -471,7 +2742,7 @@ mod tests {
 
     #[test]
     fn test_is_domain_disabled_subdomain() {
-        let mut config = ServerConfig::new("test_server");
+        let mut config = ServerConfig::new(Id::new(1));
         config.disabled_domains.insert("example.com".to_string());
 
         assert!(config.is_domain_disabled("https://sub.example.com"));
@@ -481,7 +2752,7 @@ mod tests {
 
     #[test]
     fn test_is_domain_disabled_case_insensitive() {
-        let mut config = ServerConfig::new("test_server");
+        let mut config = ServerConfig::new(Id::new(1));
         config.disabled_domains.insert("EXAMPLE.COM".to_string());
 
         assert!(config.is_domain_disabled("https://example.com"));
@@ -491,7 +2762,7 @@ mod tests {
 
     #[test]
     fn test_is_domain_disabled_not_disabled() {
-        let mut config = ServerConfig::new("test_server");
+        let mut config = ServerConfig::new(Id::new(1));
         config.disabled_domains.insert("example.com".to_string());
 
         assert!(!config.is_domain_disabled("https://other.com"));
@@ -500,7 +2771,7 @@ mod tests {
 
     #[test]
     fn test_is_domain_disabled_with_port() {
-        let mut config = ServerConfig::new("test_server");
+        let mut config = ServerConfig::new(Id::new(1));
         config.disabled_domains.insert("example.com".to_string());
 
         assert!(config.is_domain_disabled("https://example.com:8080"));
@@ -511,8 +2782,8 @@ mod tests {
     fn test_config_from_file_with_disabled_domains() {
         let toml_content = r#"
             [[servers]]
-            server_id = "server1"
-            auto_embed_channels = ["channel1"]
+            server_id = "1"
+            auto_embed_channels = ["10"]
             embed_enabled = true
             disabled_domains = ["example.com", "test.org"]
         "#;
@@ -525,4 +2796,580 @@ mod tests {
         assert!(config.servers[0].disabled_domains.contains("example.com"));
         assert!(config.servers[0].disabled_domains.contains("test.org"));
     }
+
+    #[test]
+    fn test_auto_delete_after() {
+        let mut config = ServerConfig::new(Id::new(1));
+        let (memes, off, general) = (Id::new(10), Id::new(11), Id::new(12));
+        config.auto_delete_channels.insert(memes, 86400);
+        config.auto_delete_channels.insert(off, 0);
+
+        assert_eq!(
+            config.auto_delete_after(memes),
+            Some(Duration::from_secs(86400))
+        );
+        assert_eq!(config.auto_delete_after(off), None);
+        assert_eq!(config.auto_delete_after(general), None);
+
+        config.retention_secs = Some(7 * 86400);
+        assert_eq!(
+            config.auto_delete_after(general),
+            Some(Duration::from_secs(7 * 86400))
+        );
+        assert_eq!(
+            config.auto_delete_after(memes),
+            Some(Duration::from_secs(86400))
+        );
+        assert_eq!(config.auto_delete_after(off), None);
+    }
+
+    #[test]
+    fn test_quota_falls_back_to_global() {
+        let toml_content = r#"
+            [quota]
+            monthly_downloads = 500
+            monthly_mb = 2048
+
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            quota = { monthly_mb = 10240 }
+
+            [[servers]]
+            server_id = "2"
+            auto_embed_channels = []
+            embed_enabled = true
+        "#;
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), toml_content).unwrap();
+        let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
+
+        let quota = manager.get_quota(Id::new(1), None);
+        assert_eq!(quota.monthly_downloads, Some(500));
+        assert_eq!(quota.monthly_bytes(), Some(10240 * 1024 * 1024));
+        assert_eq!(manager.get_quota(Id::new(2), None).monthly_mb, Some(2048));
+        assert!(!ConfigManager::new()
+            .get_quota(Id::new(1), None)
+            .is_limited());
+    }
+
+    #[test]
+    fn test_blocklists() {
+        let toml_content = r#"
+            [blocklist]
+            user_ids = ["7"]
+            url_patterns = ["^https?://(www\\.)?spam\\.example/"]
+
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            blocklist = { role_ids = ["20"], url_patterns = ["/private/"] }
+        "#;
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), toml_content).unwrap();
+        let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
+        let (server, other) = (Some(Id::new(1)), Some(Id::new(2)));
+
+        assert!(manager.blocks_user(other, Id::new(7), &[]));
+        assert!(manager.blocks_user(server, Id::new(8), &[Id::new(20)]));
+        assert!(!manager.blocks_user(other, Id::new(8), &[Id::new(20)]));
+
+        assert!(manager.blocks_url(None, "https://www.spam.example/a"));
+        assert!(manager.blocks_url(server, "https://example.com/private/1"));
+        assert!(!manager.blocks_url(other, "https://example.com/private/1"));
+
+        let entry = BlockEntry::User(Id::new(8));
+        let config = manager.set_blocked(Id::new(2), &entry, true);
+        assert_eq!(config.blocklist.summary(), "<@8>");
+        assert!(manager.blocks_user(other, Id::new(8), &[]));
+        manager.set_blocked(Id::new(2), &entry, false);
+        assert!(!manager.blocks_user(other, Id::new(8), &[]));
+
+        std::fs::write(
+            temp_file.path(),
+            "servers = []\n[blocklist]\nurl_patterns = [\"(unclosed\"]\n",
+        )
+        .unwrap();
+        assert!(Config::from_file(temp_file.path()).is_err());
+    }
+
+    #[test]
+    fn test_download_queue_settings() {
+        assert_eq!(Config::default().get_max_concurrent_downloads(), None);
+        assert_eq!(
+            Config::default().get_queue_policy(),
+            QueuePolicy::CommandsFirst
+        );
+
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [media]
+            max_concurrent_downloads = 4
+            queue_policy = "fifo"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.get_max_concurrent_downloads(), Some(4));
+        assert_eq!(config.get_queue_policy(), QueuePolicy::Fifo);
+
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [media]
+            max_concurrent_downloads = 0
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.get_max_concurrent_downloads(), None);
+    }
+
+    #[test]
+    fn test_tiers() {
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [quota]
+            monthly_downloads = 100
+
+            [[tiers]]
+            name = "premium"
+            guild_ids = ["1"]
+            sku_ids = ["50"]
+            max_upload_mb = 100
+            max_duration_secs = 3600
+            quota = { monthly_mb = 10240 }
+
+            [[tiers]]
+            name = "supporter"
+            role_ids = ["20"]
+            user_ids = ["30"]
+            max_upload_mb = 50
+        "#,
+        )
+        .unwrap();
+        assert!(config.has_sku_tiers());
+
+        let tier = |requester: Requester| config.get_tier(&requester).map(|t| t.name.as_str());
+        assert_eq!(
+            tier(Requester {
+                guild_id: Some(Id::new(1)),
+                role_ids: vec![Id::new(20)],
+                ..Default::default()
+            }),
+            Some("premium")
+        );
+        assert_eq!(
+            tier(Requester {
+                guild_id: Some(Id::new(2)),
+                sku_ids: vec![Id::new(50)],
+                ..Default::default()
+            }),
+            Some("premium")
+        );
+        assert_eq!(
+            tier(Requester {
+                user_id: Some(Id::new(30)),
+                ..Default::default()
+            }),
+            Some("supporter")
+        );
+        assert_eq!(tier(Requester::default()), None);
+
+        let premium = &config.tiers.as_ref().unwrap()[0];
+        assert_eq!(premium.max_upload_bytes(), Some(100_000_000));
+        assert_eq!(
+            premium.duration_limit(),
+            Some(DurationLimit {
+                max_secs: 3600,
+                clip_secs: 60
+            })
+        );
+        assert_eq!(config.tiers.as_ref().unwrap()[1].duration_limit(), None);
+
+        let manager = ConfigManager {
+            global: config.clone(),
+            ..ConfigManager::new()
+        };
+        let quota = manager.get_quota(Id::new(1), Some(premium));
+        assert_eq!(quota.monthly_downloads, Some(100));
+        assert_eq!(quota.monthly_mb, Some(10240));
+    }
+
+    #[test]
+    fn test_http_settings() {
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [media.http]
+            proxy = "http://127.0.0.1:3128"
+            max_redirects = 3
+            timeout_secs = 300
+
+            [media.http.sites."i.imgur.com"]
+            Referer = "https://imgur.com/"
+        "#,
+        )
+        .unwrap();
+
+        let settings = config.get_http_settings();
+        assert_eq!(settings.proxy.as_deref(), Some("http://127.0.0.1:3128"));
+        assert_eq!(settings.max_redirects, 3);
+        assert_eq!(settings.user_agent, None);
+        assert_eq!(
+            settings.site_headers["i.imgur.com"]["Referer"],
+            "https://imgur.com/"
+        );
+        assert_eq!(settings.connect_timeout, Duration::from_secs(10));
+        assert_eq!(settings.read_timeout, Duration::from_secs(30));
+        assert_eq!(settings.timeout, Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_failure_notice() {
+        let config: Config = toml::from_str(
+            r#"
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            failure_notice = "react"
+
+            [[servers]]
+            server_id = "2"
+            auto_embed_channels = []
+            embed_enabled = true
+        "#,
+        )
+        .unwrap();
+
+        let servers = config.servers;
+        assert_eq!(servers[0].failure_notice, FailureNotice::React);
+        assert_eq!(servers[1].failure_notice, FailureNotice::Message);
+    }
+
+    #[test]
+    fn test_vote_delete() {
+        let mut config = ServerConfig::new(Id::new(1));
+        assert_eq!(config.vote_delete(), None);
+
+        config.vote_delete_threshold = Some(3);
+        assert_eq!(config.vote_delete(), Some((3, Duration::from_secs(600))));
+
+        config.vote_delete_window_secs = Some(60);
+        assert_eq!(config.vote_delete(), Some((3, Duration::from_secs(60))));
+
+        config.vote_delete_threshold = Some(0);
+        assert_eq!(config.vote_delete(), None);
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let mut config = ServerConfig::new(Id::new(1));
+        assert_eq!(config.dedup_window(), None);
+
+        config.dedup_window_secs = Some(0);
+        assert_eq!(config.dedup_window(), None);
+
+        config.dedup_window_secs = Some(3600);
+        assert_eq!(config.dedup_window(), Some(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_channel_config_resolution() {
+        let toml_content = r#"
+            [embed]
+            max_upload_mb = 25
+            nsfw = "spoiler"
+
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = []
+            embed_enabled = true
+            embed = { audio_only = true, max_upload_mb = 8, fix_domains = ["tiktok.com"], container = "webm" }
+
+            [servers.channels.10]
+            nsfw = "block"
+            allowed_domains = ["example.com"]
+            skip_domains = []
+            container = "original"
+        "#;
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), toml_content).unwrap();
+        let manager = ConfigManager::from_config_file(temp_file.path()).unwrap();
+
+        let channel = manager.get_channel_config(Some(Id::new(1)), Id::new(10));
+        assert_eq!(channel.nsfw_policy(), NsfwPolicy::Block);
+        assert_eq!(channel.max_upload_bytes(), Some(8_000_000));
+        assert!(channel.audio_only());
+        assert_eq!(channel.container(), OutputContainer::Original);
+        assert!(channel.is_domain_allowed("https://sub.example.com/a"));
+        assert!(!channel.is_domain_allowed("https://other.com/a"));
+
+        let other = manager.get_channel_config(Some(Id::new(1)), Id::new(11));
+        assert_eq!(other.nsfw_policy(), NsfwPolicy::Spoiler);
+        assert_eq!(other.container(), OutputContainer::Webm);
+        assert!(other.is_domain_allowed("https://other.com/a"));
+        assert!(other.skips_auto_embed("https://www.youtube.com/watch?v=1"));
+        assert!(other.skips_auto_embed("https://open.spotify.com/track/1"));
+        assert!(!other.skips_auto_embed("https://x.com/a/status/1"));
+        assert!(other.fixes("https://vm.tiktok.com/abc/"));
+        assert!(!other.fixes("https://x.com/a/status/1"));
+        // An empty list overrides the default one
+        assert!(!channel.skips_auto_embed("https://www.youtube.com/watch?v=1"));
+
+        let dm = manager.get_channel_config(None, Id::new(10));
+        assert!(!dm.fixes("https://vm.tiktok.com/abc/"));
+        assert_eq!(dm.max_upload_bytes(), Some(25_000_000));
+        assert!(!dm.audio_only());
+        assert_eq!(dm.container(), OutputContainer::Mp4);
+    }
+
+    #[test]
+    fn test_set_channel_config() {
+        let manager = ConfigManager::new();
+        let (guild, channel) = (Id::new(1), Id::new(10));
+
+        manager.set_channel_config(
+            guild,
+            channel,
+            ChannelConfig {
+                nsfw: Some(NsfwPolicy::Block),
+                ..Default::default()
+            },
+            false,
+        );
+        let config = manager.set_channel_config(
+            guild,
+            channel,
+            ChannelConfig {
+                max_upload_mb: Some(5),
+                ..Default::default()
+            },
+            false,
+        );
+        assert_eq!(
+            config.channel_config(channel).summary(),
+            "`max-size`: 5 MB, `nsfw`: block"
+        );
+
+        let config = manager.set_channel_config(guild, channel, ChannelConfig::default(), true);
+        assert!(config.channels.is_empty());
+    }
+
+    #[test]
+    fn test_config_from_file_with_auto_delete_and_storage() {
+        let toml_content = r#"
+            [storage]
+            data_dir = "/var/lib/grabby/state"
+            backend = "redis"
+
+            [[servers]]
+            server_id = "1"
+            auto_embed_channels = ["10"]
+            embed_enabled = true
+            auto_delete_channels = { 10 = 3600 }
+        "#;
+
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), toml_content).unwrap();
+
+        let config = Config::from_file(temp_file.path()).unwrap();
+        assert_eq!(
+            config.get_data_dir(),
+            PathBuf::from("/var/lib/grabby/state")
+        );
+        assert_eq!(
+            config.servers[0].auto_delete_after(Id::new(10)),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(Config::default().get_data_dir(), PathBuf::from("data"));
+        assert_eq!(config.get_storage_backend(), StorageBackend::Redis);
+        assert_eq!(
+            Config::default().get_storage_backend(),
+            StorageBackend::Local
+        );
+    }
+
+    #[test]
+    fn test_reveal_secrets_decrypts_token() {
+        let key = secret::SecretKey::from_base64(&secret::SecretKey::generate().unwrap()).unwrap();
+        let mut config = Config {
+            discord: Some(DiscordConfig {
+                token: Some(key.encrypt("my-bot-token").unwrap()),
+                ..Default::default()
+            }),
+            servers: vec![ServerConfig {
+                job_webhook: Some(key.encrypt("https://hooks.example.com/x").unwrap()),
+                ..ServerConfig::new(Id::new(1))
+            }],
+            ..Default::default()
+        };
+
+        assert!(config.clone().reveal_secrets(None).is_err());
+        config.reveal_secrets(Some(&key)).unwrap();
+        assert_eq!(config.get_discord_token().as_deref(), Some("my-bot-token"));
+        assert_eq!(
+            config.servers[0].job_webhook.as_deref(),
+            Some("https://hooks.example.com/x")
+        );
+    }
+
+    #[test]
+    fn test_redacted_hides_secrets() {
+        let config = Config {
+            discord: Some(DiscordConfig {
+                token: Some("my-bot-token".to_string()),
+                ..Default::default()
+            }),
+            offload: Some(OffloadConfig {
+                backend: "s3".to_string(),
+                secret_access_key: Some("my-s3-secret".to_string()),
+                ..Default::default()
+            }),
+            gallery_dl: Some(HashMap::from([(
+                "pixiv".to_string(),
+                GalleryDlSiteConfig {
+                    refresh_token: Some("my-pixiv-token".to_string()),
+                    cookies: Some("/run/secrets/my-pixiv-cookies.txt".to_string()),
+                    ..Default::default()
+                },
+            )])),
+            cluster: Some(ClusterConfig {
+                redis_url: Some("redis://:my-redis-password@localhost".to_string()),
+                ..Default::default()
+            }),
+            error_reporting: Some(ErrorReportingConfig {
+                sentry_dsn: Some("https://my-sentry-key@sentry.io/1".to_string()),
+                ..Default::default()
+            }),
+            grpc: Some(GrpcConfig {
+                bind: Some("127.0.0.1:50051".to_string()),
+                token: Some("my-grpc-token".to_string()),
+            }),
+            servers: vec![ServerConfig {
+                job_webhook: Some("https://example.com/hook?token=my-hook-token".to_string()),
+                ..ServerConfig::new(Id::new(1))
+            }],
+            ..Default::default()
+        };
+
+        let exported = toml::to_string(&config.redacted()).unwrap();
+        assert!(!exported.contains("my-bot-token"));
+        assert!(!exported.contains("my-s3-secret"));
+        assert!(!exported.contains("my-pixiv-token"));
+        assert!(!exported.contains("my-pixiv-cookies"));
+        assert!(!exported.contains("my-redis-password"));
+        assert!(!exported.contains("my-sentry-key"));
+        assert!(!exported.contains("my-hook-token"));
+        assert!(!exported.contains("my-grpc-token"));
+        assert!(exported.contains(secret::REDACTED));
+        assert_eq!(config.get_discord_token().as_deref(), Some("my-bot-token"));
+    }
+
+    #[test]
+    fn test_gallery_dl_sites() {
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [gallery_dl.pixiv]
+            refresh_token = "token"
+
+            [gallery_dl.danbooru]
+            domains = ["safebooru.donmai.us"]
+            username = "user"
+            api_key = "key"
+            ugoira_to_video = false
+        "#,
+        )
+        .unwrap();
+
+        let sites = config.get_gallery_dl_sites();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].extractor, "danbooru");
+        assert_eq!(sites[0].domains, vec!["safebooru.donmai.us".to_string()]);
+        assert!(!sites[0].ugoira_to_video);
+        assert_eq!(sites[1].domains, vec!["pixiv.net".to_string()]);
+        assert_eq!(sites[1].refresh_token.as_deref(), Some("token"));
+        assert!(sites[1].ugoira_to_video);
+    }
+
+    #[test]
+    fn test_simulate_config() {
+        assert_eq!(Config::default().get_simulate(), None);
+
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [discord]
+            simulate = "metadata"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.get_simulate(), Some(SimulateMode::Metadata));
+        assert_eq!(SimulateMode::from_name("Full"), Some(SimulateMode::Full));
+        assert_eq!(SimulateMode::from_name("upload"), None);
+    }
+
+    #[test]
+    fn test_error_reporting_config() {
+        let config = Config::default();
+        assert_eq!(config.get_sentry_dsn(), None);
+        assert_eq!(config.get_error_repeat_threshold(), 3);
+
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [error_reporting]
+            sentry_dsn = "https://key@sentry.io/1"
+            environment = "production"
+            repeat_threshold = 0
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.get_sentry_dsn(), Some("https://key@sentry.io/1"));
+        assert_eq!(config.get_sentry_environment(), Some("production"));
+        assert_eq!(config.get_error_repeat_threshold(), 1);
+    }
+
+    #[test]
+    fn test_bootstrap_config() {
+        assert_eq!(Config::default().get_bootstrap_dir(), None);
+
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [bootstrap]
+            enabled = true
+            gallery_dl_version = "1.29.0"
+            gallery_dl_sha256 = "abcd"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_bootstrap_dir(),
+            Some(PathBuf::from("data").join("tools"))
+        );
+
+        let pins = config.get_tool_pins();
+        assert_eq!(pins[0].tool, Tool::YtDlp);
+        assert_eq!(pins[0].version, Tool::YtDlp.default_version());
+        assert_eq!(pins[0].sha256, None);
+        assert_eq!(pins[1].version, "1.29.0");
+        assert_eq!(pins[1].sha256.as_deref(), Some("abcd"));
+    }
 }
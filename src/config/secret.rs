@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Environment variable holding the base64-encoded 32 byte key for encrypted config values.
+pub const SECRET_KEY_ENV: &str = "GRABBY_SECRET_KEY";
+
+/// Prefix marking a config value as AES-256-GCM encrypted, followed by base64(nonce || ciphertext).
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+pub const REDACTED: &str = "<redacted>";
+
+pub struct SecretKey(LessSafeKey);
+
+impl SecretKey {
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .context("Secret key is not valid base64")?;
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| anyhow!("Secret key must be exactly 32 bytes"))?;
+
+        Ok(Self(LessSafeKey::new(key)))
+    }
+
+    /// Loads the key from `GRABBY_SECRET_KEY`, if set.
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(SECRET_KEY_ENV) {
+            Ok(encoded) => Self::from_base64(&encoded)
+                .with_context(|| format!("Invalid {}", SECRET_KEY_ENV))
+                .map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Generates a new random key, base64-encoded.
+    pub fn generate() -> Result<String> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| anyhow!("Failed to generate secret key"))?;
+        Ok(STANDARD.encode(bytes))
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate nonce"))?;
+
+        let mut ciphertext = plaintext.as_bytes().to_vec();
+        self.0
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut ciphertext,
+            )
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        Ok(format!("{ENCRYPTED_PREFIX}{}", STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let encoded = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .context("Value is not an encrypted secret")?;
+        let mut payload = STANDARD
+            .decode(encoded)
+            .context("Encrypted secret is not valid base64")?;
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow!("Encrypted secret is too short"));
+        }
+
+        let mut ciphertext = payload.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&payload)
+            .map_err(|_| anyhow!("Invalid nonce in encrypted secret"))?;
+        let plaintext = self
+            .0
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt secret (wrong key?)"))?;
+
+        String::from_utf8(plaintext.to_vec()).context("Decrypted secret is not valid UTF-8")
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Decrypts `value` if it is encrypted, passing plain values through unchanged.
+pub fn reveal(value: &str, key: Option<&SecretKey>) -> Result<String> {
+    if !is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+
+    key.with_context(|| {
+        format!(
+            "Config contains encrypted secrets but {} is not set",
+            SECRET_KEY_ENV
+        )
+    })?
+    .decrypt(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> SecretKey {
+        SecretKey::from_base64(&SecretKey::generate().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let key = key();
+        let encrypted = key.encrypt("my-bot-token").unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("my-bot-token"));
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "my-bot-token");
+    }
+
+    #[test]
+    fn test_encryption_uses_fresh_nonces() {
+        let key = key();
+        assert_ne!(key.encrypt("token").unwrap(), key.encrypt("token").unwrap());
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key_fails() {
+        let encrypted = key().encrypt("my-bot-token").unwrap();
+        assert!(key().decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_invalid_keys_are_rejected() {
+        assert!(SecretKey::from_base64("not base64!").is_err());
+        assert!(SecretKey::from_base64(&STANDARD.encode([0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_reveal() {
+        let key = key();
+        let encrypted = key.encrypt("my-bot-token").unwrap();
+
+        assert_eq!(reveal("plain", None).unwrap(), "plain");
+        assert_eq!(reveal(&encrypted, Some(&key)).unwrap(), "my-bot-token");
+        assert!(reveal(&encrypted, None).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+mod proto;
+mod server;
+
+use crate::config::ConfigManager;
+use crate::i18n::{t, Locale};
+use crate::media::{check_free_space, error_class, JobClass, MediaDownloader, ProgressReporter};
+use anyhow::{Context, Result};
+use proto::download_event::Event;
+use proto::{DownloadEvent, DownloadReply, Metadata, MetadataRequest};
+use ring::digest::{digest, SHA256};
+use server::MediaServer;
+use std::sync::{Arc, PoisonError, RwLock};
+use tokio::sync::mpsc;
+use tonic::codegen::BoxStream;
+use tonic::metadata::MetadataValue;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Code, Status};
+use tracing::{info, info_span, warn, Instrument};
+
+/// Progress updates a slow caller may fall behind on before the download waits for it.
+const EVENT_BUFFER: usize = 16;
+
+/// Downloads for other services through the same pipeline, limits and queue as the bot's.
+#[derive(Clone)]
+pub struct MediaApi {
+    downloader: Arc<MediaDownloader>,
+    config: Arc<RwLock<Arc<ConfigManager>>>,
+}
+
+impl MediaApi {
+    pub fn new(downloader: Arc<MediaDownloader>, config: Arc<RwLock<Arc<ConfigManager>>>) -> Self {
+        Self { downloader, config }
+    }
+
+    fn config(&self) -> Arc<ConfigManager> {
+        self.config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Request for a link with the global settings, as no server is involved.
+    fn request(&self, url: &str) -> Result<crate::media::DownloadRequest, Status> {
+        let url = url.trim();
+        if url::Url::parse(url).is_err() {
+            return Err(Status::invalid_argument("url must be an absolute URL"));
+        }
+        let config = self.config();
+        Ok(crate::media::DownloadRequest {
+            downloader_order: config.get_downloader_order(None),
+            max_duration_secs: config.get_duration_cap(None),
+            class: JobClass::Command,
+            ..crate::media::DownloadRequest::new(url)
+        })
+    }
+
+    fn download_request(
+        &self,
+        message: proto::DownloadRequest,
+    ) -> Result<crate::media::DownloadRequest, Status> {
+        Ok(crate::media::DownloadRequest {
+            audio_only: message.audio_only,
+            chapter: message.chapter.filter(|chapter| !chapter.is_empty()),
+            max_filesize: Some(message.max_filesize).filter(|bytes| *bytes > 0),
+            ..self.request(&message.url)?
+        })
+    }
+
+    async fn fetch(
+        &self,
+        request: &crate::media::DownloadRequest,
+    ) -> Result<DownloadReply, Status> {
+        let free = match self.config().global().get_min_free_disk_bytes() {
+            Some(min_free) => check_free_space(min_free, 0).await,
+            None => Ok(()),
+        };
+        let result = match free {
+            Ok(()) => self.downloader.download(request).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(media_info) => {
+                info!("Downloaded media: {}", media_info.metadata.title);
+                Ok(DownloadReply::new(request.id.clone(), media_info))
+            }
+            Err(e) => {
+                warn!("Failed to download {}: {:#}", request.url, e);
+                Err(status(&e, &request.id))
+            }
+        }
+    }
+
+    pub async fn download(&self, message: proto::DownloadRequest) -> Result<DownloadReply, Status> {
+        let request = self.download_request(message)?;
+        let span = info_span!("grpc_download", request_id = %request.id, url = %request.url);
+        self.fetch(&request).instrument(span).await
+    }
+
+    pub async fn metadata(&self, message: MetadataRequest) -> Result<Metadata, Status> {
+        let request = self.request(&message.url)?;
+        let span = info_span!("grpc_metadata", request_id = %request.id, url = %request.url);
+        match self.downloader.metadata(&request).instrument(span).await {
+            Ok(metadata) => Ok(Metadata::from(&metadata)),
+            Err(e) => {
+                warn!("Failed to fetch metadata of {}: {:#}", request.url, e);
+                Err(status(&e, &request.id))
+            }
+        }
+    }
+
+    /// Starts the download in the background, streaming its progress and then its files. The
+    /// download is cancelled once the caller goes away.
+    pub fn download_with_progress(
+        &self,
+        message: proto::DownloadRequest,
+    ) -> Result<BoxStream<DownloadEvent>, Status> {
+        let (reporter, mut progress) = ProgressReporter::channel();
+        let request = crate::media::DownloadRequest {
+            progress: Some(reporter),
+            ..self.download_request(message)?
+        };
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+        let api = self.clone();
+        let span = info_span!("grpc_download", request_id = %request.id, url = %request.url);
+
+        tokio::spawn(
+            async move {
+                let fetch = api.fetch(&request);
+                tokio::pin!(fetch);
+                loop {
+                    tokio::select! {
+                        result = &mut fetch => {
+                            let event = result.map(|reply| DownloadEvent {
+                                event: Some(Event::Done(reply)),
+                            });
+                            let _ = sender.send(event).await;
+                            break;
+                        }
+                        Ok(()) = progress.changed() => {
+                            let Some(update) = *progress.borrow_and_update() else {
+                                continue;
+                            };
+                            let event = DownloadEvent {
+                                event: Some(Event::Progress(update.into())),
+                            };
+                            if sender.send(Ok(event)).await.is_err() {
+                                info!("Caller went away, cancelling download");
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            .instrument(span),
+        );
+
+        Ok(Box::pin(futures_util::stream::unfold(
+            receiver,
+            |mut receiver| async move { receiver.recv().await.map(|event| (event, receiver)) },
+        )))
+    }
+}
+
+/// Status for a failed download, with the error class and request id in its metadata.
+fn status(error: &anyhow::Error, request_id: &str) -> Status {
+    let class = error_class(error);
+    let code = match class {
+        "error.timeout" => Code::DeadlineExceeded,
+        "error.unsupported_url" => Code::InvalidArgument,
+        "error.too_long"
+        | "error.live_stream"
+        | "error.session_required"
+        | "error.image_too_large" => Code::FailedPrecondition,
+        "error.low_disk_space" => Code::ResourceExhausted,
+        "error.flagged" => Code::PermissionDenied,
+        "error.network" => Code::Unavailable,
+        _ => Code::Internal,
+    };
+    let mut status = Status::new(code, t(Locale::En, class));
+    let metadata = status.metadata_mut();
+    metadata.insert("grabby-error-class", MetadataValue::from_static(class));
+    if let Ok(id) = request_id.parse() {
+        metadata.insert("grabby-request-id", id);
+    }
+    status
+}
+
+/// Lets requests through if no token is configured or they carry it as a bearer token.
+fn authorize(token: Option<&str>, request: &tonic::Request<()>) -> Result<(), Status> {
+    let Some(token) = token else {
+        return Ok(());
+    };
+    let given = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Compared as digests, so how long the comparison takes says nothing about the token
+    if digest(&SHA256, given.as_bytes()).as_ref() == digest(&SHA256, token.as_bytes()).as_ref() {
+        Ok(())
+    } else {
+        Err(Status::unauthenticated("Missing or wrong token"))
+    }
+}
+
+/// Serves the gRPC API on `bind` in a background task.
+pub async fn spawn_server(bind: &str, token: Option<String>, api: MediaApi) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to bind gRPC API to {}", bind))?;
+
+    if token.is_none() {
+        warn!("gRPC API on {} accepts requests without a token", bind);
+    }
+    let service = InterceptedService::new(MediaServer::new(api), move |request| {
+        authorize(token.as_deref(), &request).map(|()| request)
+    });
+
+    info!("gRPC API listening on {}", bind);
+
+    tokio::spawn(async move {
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+        {
+            warn!("gRPC API stopped: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::MockDownloader;
+    use futures_util::StreamExt;
+    use prost::Message;
+    use tonic::codegen::{http, Service};
+
+    const VIDEO_URL: &str = "https://example.com/watch/1";
+
+    fn api() -> MediaApi {
+        let downloader = MockDownloader::new()
+            .with_media(VIDEO_URL, &[("clip.mp4", b"video data")])
+            .with_error("https://example.com/watch/broken", "Unsupported URL");
+        MediaApi::new(
+            Arc::new(MediaDownloader::with_downloaders(vec![Box::new(
+                downloader,
+            )])),
+            Arc::new(RwLock::new(Arc::new(ConfigManager::new()))),
+        )
+    }
+
+    fn download_request(url: &str) -> proto::DownloadRequest {
+        proto::DownloadRequest {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_returns_files() {
+        let reply = api().download(download_request(VIDEO_URL)).await.unwrap();
+
+        assert_eq!(reply.request_id.len(), 8);
+        assert_eq!(reply.files.len(), 1);
+        assert_eq!(reply.files[0].filename, "clip.mp4");
+        assert_eq!(reply.files[0].data, b"video data");
+        assert!(reply.metadata.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_download_failure_carries_class() {
+        let status = api()
+            .download(download_request("https://example.com/watch/broken"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            status.metadata().get("grabby-error-class").unwrap(),
+            "error.unsupported_url"
+        );
+        assert!(status.metadata().get("grabby-request-id").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_url_is_rejected() {
+        let status = api()
+            .download(download_request("not a url"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_progress_stream_ends_with_files() {
+        let events: Vec<_> = api()
+            .download_with_progress(download_request(VIDEO_URL))
+            .unwrap()
+            .collect()
+            .await;
+
+        let Some(Ok(DownloadEvent {
+            event: Some(Event::Done(reply)),
+        })) = events.last()
+        else {
+            panic!("stream did not end with the download: {events:?}");
+        };
+        assert_eq!(reply.files[0].filename, "clip.mp4");
+    }
+
+    #[tokio::test]
+    async fn test_server_routes_framed_requests() {
+        let message = download_request(VIDEO_URL).encode_to_vec();
+        let mut frame = vec![0];
+        frame.extend((message.len() as u32).to_be_bytes());
+        frame.extend(message);
+        let request = http::Request::builder()
+            .method("POST")
+            .uri("/grabby.v1.Media/Download")
+            .header("content-type", "application/grpc")
+            .body(axum::body::Body::from(frame))
+            .unwrap();
+
+        let response = MediaServer::new(api()).call(request).await.unwrap();
+        let body = axum::body::to_bytes(axum::body::Body::new(response.into_body()), usize::MAX)
+            .await
+            .unwrap();
+        let reply = DownloadReply::decode(&body[5..]).unwrap();
+        assert_eq!(reply.files[0].data, b"video data");
+    }
+
+    #[test]
+    fn test_authorize() {
+        let request = |value: Option<&str>| {
+            let mut request = tonic::Request::new(());
+            if let Some(value) = value {
+                request
+                    .metadata_mut()
+                    .insert("authorization", value.parse().unwrap());
+            }
+            request
+        };
+
+        assert!(authorize(None, &request(None)).is_ok());
+        assert!(authorize(Some("secret"), &request(Some("Bearer secret"))).is_ok());
+        assert!(authorize(Some("secret"), &request(Some("Bearer wrong"))).is_err());
+        assert!(authorize(Some("secret"), &request(None)).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Messages of `proto/grabby/v1/media.proto`, written out the way prost generates them.
+
+use crate::media::{MediaInfo, MediaMetadata};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DownloadRequest {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(bool, tag = "2")]
+    pub audio_only: bool,
+    #[prost(string, optional, tag = "3")]
+    pub chapter: Option<String>,
+    #[prost(uint64, tag = "4")]
+    pub max_filesize: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MetadataRequest {
+    #[prost(string, tag = "1")]
+    pub url: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Metadata {
+    #[prost(string, tag = "1")]
+    pub title: String,
+    #[prost(string, tag = "2")]
+    pub id: String,
+    #[prost(string, optional, tag = "3")]
+    pub author: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub site: Option<String>,
+    #[prost(uint64, optional, tag = "5")]
+    pub duration: Option<u64>,
+    #[prost(string, optional, tag = "6")]
+    pub thumbnail: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub description: Option<String>,
+    #[prost(uint64, optional, tag = "8")]
+    pub uploaded_at: Option<u64>,
+    #[prost(uint64, optional, tag = "9")]
+    pub view_count: Option<u64>,
+    #[prost(uint64, optional, tag = "10")]
+    pub likes: Option<u64>,
+    #[prost(bool, tag = "11")]
+    pub nsfw: bool,
+    #[prost(bool, tag = "12")]
+    pub live: bool,
+    #[prost(string, repeated, tag = "13")]
+    pub formats: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct File {
+    #[prost(string, tag = "1")]
+    pub filename: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DownloadReply {
+    #[prost(string, tag = "1")]
+    pub request_id: String,
+    #[prost(message, optional, tag = "2")]
+    pub metadata: Option<Metadata>,
+    #[prost(message, repeated, tag = "3")]
+    pub files: Vec<File>,
+    #[prost(uint64, optional, tag = "4")]
+    pub truncated_to: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Progress {
+    #[prost(double, tag = "1")]
+    pub percent: f64,
+    #[prost(uint64, optional, tag = "2")]
+    pub eta_secs: Option<u64>,
+    #[prost(double, optional, tag = "3")]
+    pub speed: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DownloadEvent {
+    #[prost(oneof = "download_event::Event", tags = "1, 2")]
+    pub event: Option<download_event::Event>,
+}
+
+pub mod download_event {
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Progress(super::Progress),
+        #[prost(message, tag = "2")]
+        Done(super::DownloadReply),
+    }
+}
+
+impl From<&MediaMetadata> for Metadata {
+    fn from(metadata: &MediaMetadata) -> Self {
+        Self {
+            title: metadata.title.clone(),
+            id: metadata.id.clone(),
+            author: metadata.author.clone(),
+            site: metadata.site.clone(),
+            duration: metadata.duration,
+            thumbnail: metadata.thumbnail.clone(),
+            description: metadata.description.clone(),
+            uploaded_at: metadata.uploaded_at,
+            view_count: metadata.view_count,
+            likes: metadata.likes,
+            nsfw: metadata.nsfw,
+            live: metadata.live,
+            formats: metadata.formats.clone(),
+        }
+    }
+}
+
+impl DownloadReply {
+    pub fn new(request_id: String, media_info: MediaInfo) -> Self {
+        Self {
+            request_id,
+            metadata: Some(Metadata::from(&media_info.metadata)),
+            truncated_to: media_info.truncated_to,
+            files: media_info
+                .files
+                .into_iter()
+                .map(|file| File {
+                    filename: file.filename,
+                    data: file.data,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::media::Progress> for Progress {
+    fn from(progress: crate::media::Progress) -> Self {
+        Self {
+            percent: progress.percent,
+            eta_secs: progress.eta.map(|eta| eta.as_secs()),
+            speed: progress.speed,
+        }
+    }
+}
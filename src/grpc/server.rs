@@ -0,0 +1,112 @@
+//! Routing of the `grabby.v1.Media` service to [`MediaApi`], written out the way tonic generates
+//! it.
+
+use super::proto::{DownloadEvent, DownloadReply, DownloadRequest, Metadata, MetadataRequest};
+use super::MediaApi;
+use std::convert::Infallible;
+use std::task::{Context, Poll};
+use tonic::body::Body;
+use tonic::codegen::{http, BoxFuture, BoxStream, Service, StdError};
+use tonic::server::{Grpc, NamedService, ServerStreamingService, UnaryService};
+use tonic_prost::ProstCodec;
+
+/// The `grabby.v1.Media` service, to be added to a tonic server.
+#[derive(Clone)]
+pub struct MediaServer {
+    api: MediaApi,
+}
+
+impl MediaServer {
+    pub fn new(api: MediaApi) -> Self {
+        Self { api }
+    }
+}
+
+impl NamedService for MediaServer {
+    const NAME: &'static str = "grabby.v1.Media";
+}
+
+struct Download(MediaApi);
+
+impl UnaryService<DownloadRequest> for Download {
+    type Response = DownloadReply;
+    type Future = BoxFuture<tonic::Response<DownloadReply>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<DownloadRequest>) -> Self::Future {
+        let api = self.0.clone();
+        Box::pin(async move {
+            api.download(request.into_inner())
+                .await
+                .map(tonic::Response::new)
+        })
+    }
+}
+
+struct GetMetadata(MediaApi);
+
+impl UnaryService<MetadataRequest> for GetMetadata {
+    type Response = Metadata;
+    type Future = BoxFuture<tonic::Response<Metadata>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<MetadataRequest>) -> Self::Future {
+        let api = self.0.clone();
+        Box::pin(async move {
+            api.metadata(request.into_inner())
+                .await
+                .map(tonic::Response::new)
+        })
+    }
+}
+
+struct DownloadWithProgress(MediaApi);
+
+impl ServerStreamingService<DownloadRequest> for DownloadWithProgress {
+    type Response = DownloadEvent;
+    type ResponseStream = BoxStream<DownloadEvent>;
+    type Future = BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<DownloadRequest>) -> Self::Future {
+        let api = self.0.clone();
+        Box::pin(async move {
+            api.download_with_progress(request.into_inner())
+                .map(tonic::Response::new)
+        })
+    }
+}
+
+impl<B> Service<http::Request<B>> for MediaServer
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let api = self.api.clone();
+        match request.uri().path() {
+            "/grabby.v1.Media/Download" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(Download(api), request).await)
+            }),
+            "/grabby.v1.Media/GetMetadata" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(GetMetadata(api), request).await)
+            }),
+            "/grabby.v1.Media/DownloadWithProgress" => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc
+                    .server_streaming(DownloadWithProgress(api), request)
+                    .await)
+            }),
+            _ => Box::pin(
+                async move { Ok(tonic::Status::unimplemented("Unknown method").into_http()) },
+            ),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::metrics::RuntimeSnapshot;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Liveness signal of the gateway event loop, beaten on every event and tick.
+pub struct Heartbeat {
+    last_beat_ms: AtomicU64,
+    stalled: AtomicBool,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self {
+            last_beat_ms: AtomicU64::new(now_millis()),
+            stalled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn beat(&self) {
+        self.last_beat_ms.store(now_millis(), Ordering::Relaxed);
+    }
+
+    pub fn since_last_beat(&self) -> Duration {
+        Duration::from_millis(
+            now_millis().saturating_sub(self.last_beat_ms.load(Ordering::Relaxed)),
+        )
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled.load(Ordering::Relaxed)
+    }
+
+    /// Updates the stall flag, returning true if the loop just transitioned into a stall.
+    fn check(&self, threshold: Duration) -> bool {
+        let stalled = self.since_last_beat() >= threshold;
+        let was_stalled = self.stalled.swap(stalled, Ordering::Relaxed);
+        stalled && !was_stalled
+    }
+}
+
+/// Spawns a task that warns when the event loop hasn't beaten within `threshold`.
+pub fn spawn_watchdog(heartbeat: Arc<Heartbeat>, threshold: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(threshold / 2);
+        loop {
+            interval.tick().await;
+            if heartbeat.check(threshold) {
+                warn!(
+                    stalled_for_ms = heartbeat.since_last_beat().as_millis() as u64,
+                    runtime = ?RuntimeSnapshot::capture(),
+                    "Gateway event loop appears to be stalled (blocking call on the runtime?)"
+                );
+            }
+        }
+    });
+}
+
+async fn health(State(heartbeat): State<Arc<Heartbeat>>) -> (StatusCode, Json<Value>) {
+    let since_last_beat_ms = heartbeat.since_last_beat().as_millis() as u64;
+
+    if heartbeat.is_stalled() {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "stalled", "since_last_heartbeat_ms": since_last_beat_ms })),
+        )
+    } else {
+        (
+            StatusCode::OK,
+            Json(json!({ "status": "ok", "since_last_heartbeat_ms": since_last_beat_ms })),
+        )
+    }
+}
+
+async fn metrics() -> String {
+    RuntimeSnapshot::capture().render_prometheus()
+}
+
+/// Serves the health and metrics endpoints on `bind` in a background task.
+pub async fn spawn_server(bind: &str, heartbeat: Arc<Heartbeat>) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to bind health endpoint to {}", bind))?;
+
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .with_state(heartbeat);
+
+    info!("Health endpoint listening on {}", bind);
+
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            warn!("Health endpoint stopped: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_fresh_is_not_stalled() {
+        let heartbeat = Heartbeat::new();
+        assert!(!heartbeat.check(Duration::from_secs(60)));
+        assert!(!heartbeat.is_stalled());
+    }
+
+    #[test]
+    fn test_heartbeat_stall_transitions_once() {
+        let heartbeat = Heartbeat::new();
+        assert!(heartbeat.check(Duration::ZERO));
+        assert!(heartbeat.is_stalled());
+        // Still stalled, but no new transition
+        assert!(!heartbeat.check(Duration::ZERO));
+        assert!(heartbeat.is_stalled());
+    }
+
+    #[test]
+    fn test_heartbeat_recovers_after_beat() {
+        let heartbeat = Heartbeat::new();
+        heartbeat.check(Duration::ZERO);
+        heartbeat.beat();
+        assert!(!heartbeat.check(Duration::from_secs(60)));
+        assert!(!heartbeat.is_stalled());
+    }
+
+    #[tokio::test]
+    async fn test_health_handler_reports_status() {
+        let heartbeat = Arc::new(Heartbeat::new());
+        let (status, _) = health(State(heartbeat.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+
+        heartbeat.check(Duration::ZERO);
+        let (status, Json(body)) = health(State(heartbeat)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "stalled");
+    }
+}
@@ -0,0 +1,759 @@
+/// Languages user-facing messages are available in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Sl,
+}
+
+impl Locale {
+    /// Parses a language code such as `en`, `en-US` or `sl`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code.split(['-', '_']).next()?.to_lowercase();
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "sl" => Some(Self::Sl),
+            _ => None,
+        }
+    }
+
+    fn messages(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::En => EN,
+            Self::Sl => SL,
+        }
+    }
+}
+
+const EN: &[(&str, &str)] = &[
+    ("error.unsupported_url", "Unsupported URL"),
+    ("error.network", "Network error - please try again"),
+    ("error.timeout", "Request timed out - please try again"),
+    (
+        "error.timeout_extraction",
+        "Looking up the media timed out after {secs}s - please try again",
+    ),
+    (
+        "error.timeout_download",
+        "Downloading the media timed out after {secs}s - please try again",
+    ),
+    (
+        "error.timeout_transcode",
+        "Converting the media timed out after {secs}s",
+    ),
+    (
+        "error.timeout_upload",
+        "Uploading the media timed out after {secs}s - please try again",
+    ),
+    ("error.download_failed", "Download failed"),
+    ("error.too_long", "Video is too long"),
+    ("error.low_disk_space", "The bot is low on disk space - please try again later"),
+    ("error.image_too_large", "Image is too large to process"),
+    (
+        "error.image_too_large_size",
+        "Image is too large to process ({width}x{height})",
+    ),
+    (
+        "error.flagged",
+        "The downloaded file was flagged as a potential threat and won't be posted",
+    ),
+    (
+        "error.too_long_limit",
+        "Video is too long ({duration} > {limit} limit)",
+    ),
+    (
+        "error.live_stream",
+        "Live streams can't be embedded - try again once the stream has ended",
+    ),
+    (
+        "error.session_required",
+        "Stories need a logged-in session, which the bot isn't set up with",
+    ),
+    ("error.reference", "Reference: `{id}`"),
+    ("embed.invalid_url", "Please provide a valid URL."),
+    ("embed.unsupported_url", "This URL is not supported."),
+    ("embed.downloading", "Downloading media..."),
+    ("embed.progress", "Downloading media... {percent}%"),
+    ("embed.credit", "via Grabby"),
+    ("embed.progress_eta", "(about {eta} left)"),
+    (
+        "embed.rate_limited",
+        "Rate limited by Discord, retrying the upload in {secs}s...",
+    ),
+    ("embed.downloading_many", "Downloading {count} links..."),
+    ("info.fetching", "Looking up the link..."),
+    ("info.author", "Author"),
+    ("info.duration", "Duration"),
+    ("info.live", "🔴 Live"),
+    ("info.likes", "Likes"),
+    ("info.views", "Views"),
+    ("info.uploaded", "Uploaded"),
+    ("info.site", "Site"),
+    ("info.formats", "Formats"),
+    (
+        "simulate.header",
+        "🧪 Simulated embed of <{url}>, nothing was uploaded (`{id}`)",
+    ),
+    (
+        "simulate.metadata_header",
+        "🧪 Simulated embed of <{url}>, metadata only (`{id}`)",
+    ),
+    ("simulate.attachment", "📎 `{file}` ({size})"),
+    (
+        "simulate.resized",
+        "📎 `{file}` ({size}, resized from {original})",
+    ),
+    ("simulate.offloaded", "🔗 `{file}` ({size}) would be offloaded"),
+    (
+        "simulate.skipped",
+        "⚠️ `{file}` ({size}) would be skipped as too large",
+    ),
+    (
+        "simulate.link_only",
+        "🔗 Nothing fits, the link would be posted instead",
+    ),
+    ("simulate.caption", "Caption:"),
+    ("simulate.spoiler", "🙈 Would be uploaded behind a spoiler"),
+    ("simulate.downloaders", "Downloaders: {order}"),
+    ("simulate.upload_limit", "Upload limit: {size}"),
+    (
+        "simulate.mirror",
+        "🧪 <{mirror}> would be posted instead of downloading <{url}>",
+    ),
+    ("embed.summary_ok", "✅ <{url}>"),
+    ("embed.summary_failed", "❌ <{url}>: {reason}"),
+    ("embed.no_channel", "Cannot determine channel for upload"),
+    ("embed.send_failed", "Failed to send media file"),
+    ("embed.no_files", "Media processed but no files to send"),
+    (
+        "embed.abandoned",
+        "Cancelled, the request expired before the media was ready",
+    ),
+    (
+        "embed.channel_denied",
+        "You need to be able to configure the server and post media in {channel} to embed there",
+    ),
+    (
+        "embed.invalid_pick",
+        "Invalid file selection, use numbers and ranges like `1-3,7` (1-{count})",
+    ),
+    ("auto.send_failed", "❌ Failed to send media: {error}"),
+    (
+        "auto.download_failed",
+        "Failed to download media: `{error}`",
+    ),
+    ("auto.failed_brief", "⚠️ Couldn't embed this link: {error}"),
+    (
+        "quota.exceeded",
+        "📉 This server used up its monthly download quota, it resets <t:{reset}:R>",
+    ),
+    ("dedup.already_embedded", "🔁 Already embedded here: {link}"),
+    ("media.author", "👤 Author: {author}"),
+    ("media.likes", "❤️ Likes: {likes}"),
+    (
+        "media.skipped_oversized",
+        "Skipped oversized files: {files}",
+    ),
+    ("media.truncated", "✂️ Video was cut to the first {secs}s"),
+    ("media.part", "📎 Part {part}/{parts}"),
+    ("media.mirrored_from", "📥 From {link}"),
+    (
+        "media.offloaded",
+        "📦 {file} is too large to upload: {url} (expires {expires})",
+    ),
+    (
+        "media.offloaded_permanent",
+        "📦 {file} is too large to upload: {url}",
+    ),
+    (
+        "media.truncated_chapters",
+        "✂️ Video was cut to the first {secs}s, pick a chapter with `/embed chapter:` instead: {chapters}",
+    ),
+    (
+        "admin.guild_only",
+        "This command can only be used in a server.",
+    ),
+    ("admin.auto_embed_on", "Auto-embed is now on in {channel}."),
+    ("admin.auto_embed_off", "Auto-embed is now off in {channel}."),
+    (
+        "admin.embed_command_on",
+        "The /embed command is now enabled in this server.",
+    ),
+    (
+        "admin.embed_command_off",
+        "The /embed command is now disabled in this server.",
+    ),
+    (
+        "admin.settings_not_saved",
+        "-# The change could not be saved and will be lost on restart.",
+    ),
+    (
+        "embed.disabled",
+        "The /embed command is disabled in this server.",
+    ),
+    ("admin.channel_settings", "Settings of {channel}: {settings}"),
+    ("delete.button", "🗑️ Delete"),
+    (
+        "delete.not_allowed",
+        "Only the member who shared this or a moderator can delete it.",
+    ),
+    ("retry.button", "Retry"),
+    ("retry.retrying", "🔁 Retrying…"),
+    (
+        "retry.expired",
+        "This download can no longer be retried, please try again with /embed.",
+    ),
+    (
+        "retry.not_requester",
+        "Only the member the download was for can retry it.",
+    ),
+    ("retry.cooldown", "Please wait {secs}s before retrying."),
+    (
+        "admin.channel_reset",
+        "{channel} now uses the server's settings.",
+    ),
+    (
+        "embed.domain_not_allowed",
+        "Links to this site are not embedded in this channel.",
+    ),
+    ("embed.user_blocked", "You are not allowed to embed links here."),
+    ("embed.url_blocked", "This link is blocked in this server."),
+    ("admin.blocked", "{entry} is now blocked."),
+    ("admin.unblocked", "{entry} is no longer blocked."),
+    ("admin.blocklist", "Blocked: {entries}"),
+    ("admin.blocklist_empty", "Nothing is blocked in this server."),
+    ("admin.history", "Recent changes to the server's settings:"),
+    ("admin.history_empty", "The server's settings have not been changed yet."),
+    (
+        "admin.invalid_pattern",
+        "`{pattern}` is not a valid regular expression.",
+    ),
+    (
+        "admin.job_webhook_set",
+        "Results of downloads are now posted to the webhook at {host}.",
+    ),
+    (
+        "admin.job_webhook_cleared",
+        "Results of downloads are no longer posted to a webhook.",
+    ),
+    (
+        "admin.invalid_webhook",
+        "The webhook must be an https:// URL.",
+    ),
+    (
+        "admin.unreachable_webhook",
+        "The webhook's host must resolve to a public address.",
+    ),
+    (
+        "admin.mirror_set",
+        "Auto-embeds in {channel} are now also posted to {archive}.",
+    ),
+    (
+        "admin.mirror_cleared",
+        "Auto-embeds in {channel} are no longer mirrored.",
+    ),
+    (
+        "admin.mirror_same_channel",
+        "A channel can't be mirrored into itself.",
+    ),
+    ("watch.checking", "Checking the feed..."),
+    (
+        "watch.invalid_url",
+        "Please provide an http:// or https:// link to a channel, playlist, RSS feed or Reddit user.",
+    ),
+    ("watch.feed_failed", "Could not read the feed at <{url}>."),
+    (
+        "watch.added",
+        "Watching <{url}> (#{id}), new posts will be embedded in {channel}.",
+    ),
+    ("watch.duplicate", "{channel} already watches this feed (#{id})."),
+    (
+        "watch.limit",
+        "This server already watches {max} feeds. Remove one with /watch remove first.",
+    ),
+    ("watch.removed", "Stopped watching <{url}>."),
+    ("watch.not_found", "This server has no watched feed #{id}."),
+    ("watch.list", "Watched feeds:"),
+    ("watch.list_empty", "This server watches no feeds."),
+    (
+        "media.nsfw_blocked",
+        "This media is marked as NSFW and is not embedded in this channel.",
+    ),
+    (
+        "admin.permission_denied",
+        "You need the Manage Server permission or a configured role to do this.",
+    ),
+    (
+        "admin.forget_confirm_guild",
+        "This permanently removes everything stored about this server. Continue?",
+    ),
+    (
+        "admin.forget_confirm_user",
+        "This permanently removes everything stored about {user} in this server. Continue?",
+    ),
+    ("admin.confirm", "Confirm"),
+    ("admin.cancel", "Cancel"),
+    ("admin.cancelled", "Cancelled, nothing was removed."),
+    ("admin.forget_done", "Removed {total} stored items:"),
+    (
+        "admin.forget_nothing",
+        "Nothing was stored, so nothing was removed.",
+    ),
+    (
+        "admin.forget_failed",
+        "Failed to remove stored data, please try again.",
+    ),
+    ("forget.scheduled_deletions", "Scheduled upload deletions"),
+    (
+        "admin.owner_only",
+        "Only bot owners can use this command.",
+    ),
+    (
+        "admin.reload_done",
+        "Reloaded the config with {servers} servers. Media, storage and health settings apply after a restart.",
+    ),
+    ("admin.reload_failed", "Failed to reload the config: {error}"),
+    ("admin.tool_missing", "- {tool}: not found"),
+    (
+        "admin.cache_purged",
+        "Cleared the Discord cache and {webhooks} cached webhooks, re-enabled {breakers} failing downloaders and dropped cached link metadata.",
+    ),
+    ("diag.running", "Running diagnostics..."),
+    ("diag.title", "Diagnostics"),
+    ("diag.tools", "Tools"),
+    ("diag.encoders", "ffmpeg encoders"),
+    ("diag.temp_dir", "Temp directory"),
+    ("diag.disk", "Free disk space"),
+    ("diag.network", "Discord API"),
+    ("diag.not_found", "not found"),
+    ("diag.missing", "missing: {list}"),
+    ("diag.no_ffmpeg", "ffmpeg can't be run"),
+    ("diag.unknown", "unknown"),
+    ("diag.reachable", "reachable (HTTP {status})"),
+    ("stats.uptime", "Uptime"),
+    ("stats.servers", "Servers"),
+    ("stats.configured_servers", "Configured servers"),
+    ("stats.active_downloads", "Active downloads"),
+    ("stats.queued_downloads", "Queued downloads"),
+    ("stats.scheduled_deletions", "Scheduled deletions"),
+    ("stats.alive_tasks", "Runtime tasks"),
+    ("forget.reposts", "Webhook repost records"),
+    ("forget.embed_history", "Embed history entries"),
+    ("forget.audit_log", "Settings changes"),
+    ("forget.watches", "Watched feeds"),
+    ("forget.requests", "Recently requested links"),
+    ("forget.metadata_cache", "Cached link metadata"),
+    ("forget.settings", "Saved server settings"),
+    ("forget.quota", "Quota usage"),
+    ("log.failure", "⚠️ {error} for `{domain}` (reference `{id}`)"),
+];
+
+const SL: &[(&str, &str)] = &[
+    ("error.unsupported_url", "Nepodprt URL"),
+    ("error.network", "Napaka omrežja - poskusite znova"),
+    ("error.timeout", "Zahteva je potekla - poskusite znova"),
+    (
+        "error.timeout_extraction",
+        "Iskanje medija je poteklo po {secs} s - poskusite znova",
+    ),
+    (
+        "error.timeout_download",
+        "Prenos medija je potekel po {secs} s - poskusite znova",
+    ),
+    (
+        "error.timeout_transcode",
+        "Pretvorba medija je potekla po {secs} s",
+    ),
+    (
+        "error.timeout_upload",
+        "Nalaganje medija je poteklo po {secs} s - poskusite znova",
+    ),
+    ("error.download_failed", "Prenos ni uspel"),
+    ("error.too_long", "Video je predolg"),
+    ("error.low_disk_space", "Botu zmanjkuje prostora na disku - poskusite znova kasneje"),
+    ("error.image_too_large", "Slika je prevelika za obdelavo"),
+    (
+        "error.image_too_large_size",
+        "Slika je prevelika za obdelavo ({width}x{height})",
+    ),
+    (
+        "error.flagged",
+        "Prenesena datoteka je bila označena kot morebitna grožnja in ne bo objavljena",
+    ),
+    (
+        "error.too_long_limit",
+        "Video je predolg ({duration} > omejitev {limit})",
+    ),
+    (
+        "error.live_stream",
+        "Prenosov v živo ni mogoče vdelati - poskusite znova, ko se prenos konča",
+    ),
+    (
+        "error.session_required",
+        "Zgodbe zahtevajo prijavljeno sejo, ki je bot nima nastavljene",
+    ),
+    ("error.reference", "Oznaka zahteve: `{id}`"),
+    ("embed.invalid_url", "Vnesite veljaven URL."),
+    ("embed.unsupported_url", "Ta URL ni podprt."),
+    ("embed.downloading", "Prenašam medij..."),
+    ("embed.progress", "Prenašam medij... {percent} %"),
+    ("embed.credit", "prek Grabbyja"),
+    ("embed.progress_eta", "(še približno {eta})"),
+    (
+        "embed.rate_limited",
+        "Discord omejuje hitrost, nalaganje ponovim čez {secs} s...",
+    ),
+    ("embed.downloading_many", "Prenašam {count} povezav..."),
+    ("info.fetching", "Preverjam povezavo..."),
+    ("info.author", "Avtor"),
+    ("info.duration", "Trajanje"),
+    ("info.live", "🔴 V živo"),
+    ("info.likes", "Všečki"),
+    ("info.views", "Ogledi"),
+    ("info.uploaded", "Objavljeno"),
+    ("info.site", "Stran"),
+    ("info.formats", "Formati"),
+    (
+        "simulate.header",
+        "🧪 Poskusna vdelava <{url}>, nič ni bilo naloženo (`{id}`)",
+    ),
+    (
+        "simulate.metadata_header",
+        "🧪 Poskusna vdelava <{url}>, samo metapodatki (`{id}`)",
+    ),
+    ("simulate.attachment", "📎 `{file}` ({size})"),
+    (
+        "simulate.resized",
+        "📎 `{file}` ({size}, pomanjšano z {original})",
+    ),
+    (
+        "simulate.offloaded",
+        "🔗 `{file}` ({size}) bi bila naložena drugam",
+    ),
+    (
+        "simulate.skipped",
+        "⚠️ `{file}` ({size}) bi bila izpuščena, ker je prevelika",
+    ),
+    (
+        "simulate.link_only",
+        "🔗 Nič ne ustreza omejitvi, objavljena bi bila povezava",
+    ),
+    ("simulate.caption", "Opis:"),
+    ("simulate.spoiler", "🙈 Naloženo bi bilo kot spoiler"),
+    ("simulate.downloaders", "Prenosniki: {order}"),
+    ("simulate.upload_limit", "Omejitev nalaganja: {size}"),
+    (
+        "simulate.mirror",
+        "🧪 Namesto prenosa <{url}> bi bila objavljena <{mirror}>",
+    ),
+    ("embed.summary_ok", "✅ <{url}>"),
+    ("embed.summary_failed", "❌ <{url}>: {reason}"),
+    ("embed.no_channel", "Kanala za nalaganje ni mogoče določiti"),
+    ("embed.send_failed", "Pošiljanje datoteke ni uspelo"),
+    (
+        "embed.no_files",
+        "Medij je obdelan, vendar ni datotek za pošiljanje",
+    ),
+    (
+        "embed.abandoned",
+        "Preklicano, zahteva je potekla, preden je bil medij pripravljen",
+    ),
+    (
+        "embed.channel_denied",
+        "Za objavo v {channel} morate lahko urejati strežnik in tam objavljati medije",
+    ),
+    (
+        "embed.invalid_pick",
+        "Neveljaven izbor datotek, uporabite številke in razpone, npr. `1-3,7` (1-{count})",
+    ),
+    (
+        "auto.send_failed",
+        "❌ Pošiljanje medija ni uspelo: {error}",
+    ),
+    ("auto.download_failed", "Prenos medija ni uspel: `{error}`"),
+    ("auto.failed_brief", "⚠️ Povezave ni bilo mogoče vdelati: {error}"),
+    (
+        "quota.exceeded",
+        "📉 Strežnik je porabil mesečno kvoto prenosov, ponastavi se <t:{reset}:R>",
+    ),
+    ("dedup.already_embedded", "🔁 Že objavljeno tukaj: {link}"),
+    ("media.author", "👤 Avtor: {author}"),
+    ("media.likes", "❤️ Všečki: {likes}"),
+    (
+        "media.skipped_oversized",
+        "Izpuščene prevelike datoteke: {files}",
+    ),
+    ("media.truncated", "✂️ Video je skrajšan na prvih {secs} s"),
+    ("media.part", "📎 Del {part}/{parts}"),
+    ("media.mirrored_from", "📥 Iz {link}"),
+    (
+        "media.offloaded",
+        "📦 {file} je prevelika za nalaganje: {url} (poteče {expires})",
+    ),
+    (
+        "media.offloaded_permanent",
+        "📦 {file} je prevelika za nalaganje: {url}",
+    ),
+    (
+        "media.truncated_chapters",
+        "✂️ Video je skrajšan na prvih {secs} s, poglavje lahko izberete z `/embed chapter:`: {chapters}",
+    ),
+    ("admin.guild_only", "Ta ukaz je na voljo samo na strežniku."),
+    (
+        "admin.auto_embed_on",
+        "Samodejno vdelovanje je vklopljeno v {channel}.",
+    ),
+    (
+        "admin.auto_embed_off",
+        "Samodejno vdelovanje je izklopljeno v {channel}.",
+    ),
+    (
+        "admin.embed_command_on",
+        "Ukaz /embed je zdaj omogočen na tem strežniku.",
+    ),
+    (
+        "admin.embed_command_off",
+        "Ukaz /embed je zdaj onemogočen na tem strežniku.",
+    ),
+    (
+        "admin.settings_not_saved",
+        "-# Spremembe ni bilo mogoče shraniti, ob ponovnem zagonu bo izgubljena.",
+    ),
+    (
+        "embed.disabled",
+        "Ukaz /embed je na tem strežniku onemogočen.",
+    ),
+    ("admin.channel_settings", "Nastavitve za {channel}: {settings}"),
+    ("delete.button", "🗑️ Izbriši"),
+    (
+        "delete.not_allowed",
+        "To lahko izbriše samo član, ki je to delil, ali moderator.",
+    ),
+    ("retry.button", "Poskusi znova"),
+    ("retry.retrying", "🔁 Ponoven poskus…"),
+    (
+        "retry.expired",
+        "Tega prenosa ni več mogoče ponoviti, poskusite znova z /embed.",
+    ),
+    (
+        "retry.not_requester",
+        "Prenos lahko ponovi samo član, za katerega je bil namenjen.",
+    ),
+    ("retry.cooldown", "Pred ponovnim poskusom počakajte {secs} s."),
+    (
+        "admin.channel_reset",
+        "{channel} zdaj uporablja nastavitve strežnika.",
+    ),
+    (
+        "embed.domain_not_allowed",
+        "Povezave na to stran se v tem kanalu ne vdelujejo.",
+    ),
+    ("embed.user_blocked", "Tukaj ne smete vdelovati povezav."),
+    ("embed.url_blocked", "Ta povezava je na tem strežniku blokirana."),
+    ("admin.blocked", "{entry} je zdaj blokiran."),
+    ("admin.unblocked", "{entry} ni več blokiran."),
+    ("admin.blocklist", "Blokirano: {entries}"),
+    ("admin.blocklist_empty", "Na tem strežniku ni nič blokirano."),
+    ("admin.history", "Nedavne spremembe nastavitev strežnika:"),
+    ("admin.history_empty", "Nastavitve strežnika še niso bile spremenjene."),
+    (
+        "admin.invalid_pattern",
+        "`{pattern}` ni veljaven regularni izraz.",
+    ),
+    (
+        "admin.job_webhook_set",
+        "Rezultati prenosov se zdaj pošiljajo na webhook na {host}.",
+    ),
+    (
+        "admin.job_webhook_cleared",
+        "Rezultati prenosov se ne pošiljajo več na webhook.",
+    ),
+    (
+        "admin.invalid_webhook",
+        "Webhook mora biti URL, ki se začne s https://.",
+    ),
+    (
+        "admin.unreachable_webhook",
+        "Gostitelj webhooka mora imeti javni naslov.",
+    ),
+    (
+        "admin.mirror_set",
+        "Samodejne vdelave v {channel} se zdaj objavljajo tudi v {archive}.",
+    ),
+    (
+        "admin.mirror_cleared",
+        "Samodejne vdelave v {channel} se ne zrcalijo več.",
+    ),
+    (
+        "admin.mirror_same_channel",
+        "Kanala ni mogoče zrcaliti vanj samega.",
+    ),
+    ("watch.checking", "Preverjam vir ..."),
+    (
+        "watch.invalid_url",
+        "Vnesite povezavo http:// ali https:// do kanala, seznama predvajanja, vira RSS ali uporabnika Reddita.",
+    ),
+    ("watch.feed_failed", "Vira na <{url}> ni bilo mogoče prebrati."),
+    (
+        "watch.added",
+        "Spremljam <{url}> (#{id}), nove objave bodo vdelane v {channel}.",
+    ),
+    ("watch.duplicate", "{channel} ta vir že spremlja (#{id})."),
+    (
+        "watch.limit",
+        "Ta strežnik že spremlja {max} virov. Najprej enega odstranite z /watch remove.",
+    ),
+    ("watch.removed", "Ne spremljam več <{url}>."),
+    ("watch.not_found", "Ta strežnik nima spremljanega vira #{id}."),
+    ("watch.list", "Spremljani viri:"),
+    ("watch.list_empty", "Ta strežnik ne spremlja nobenega vira."),
+    (
+        "media.nsfw_blocked",
+        "Ta vsebina je označena kot NSFW in se v tem kanalu ne vdeluje.",
+    ),
+    (
+        "admin.permission_denied",
+        "Za to potrebujete dovoljenje Upravljanje strežnika ali nastavljeno vlogo.",
+    ),
+    (
+        "admin.forget_confirm_guild",
+        "To trajno odstrani vse shranjene podatke o tem strežniku. Želite nadaljevati?",
+    ),
+    (
+        "admin.forget_confirm_user",
+        "To trajno odstrani vse shranjene podatke o {user} na tem strežniku. Želite nadaljevati?",
+    ),
+    ("admin.confirm", "Potrdi"),
+    ("admin.cancel", "Prekliči"),
+    ("admin.cancelled", "Preklicano, nič ni bilo odstranjeno."),
+    (
+        "admin.forget_done",
+        "Odstranjenih shranjenih elementov: {total}",
+    ),
+    (
+        "admin.forget_nothing",
+        "Ni shranjenih podatkov, zato ni bilo nič odstranjeno.",
+    ),
+    (
+        "admin.forget_failed",
+        "Odstranjevanje podatkov ni uspelo, poskusite znova.",
+    ),
+    ("forget.scheduled_deletions", "Načrtovani izbrisi objav"),
+    (
+        "admin.owner_only",
+        "Ta ukaz lahko uporabljajo samo lastniki bota.",
+    ),
+    (
+        "admin.reload_done",
+        "Nastavitve so ponovno naložene ({servers} strežnikov). Nastavitve medijev, shrambe in zdravja veljajo po ponovnem zagonu.",
+    ),
+    (
+        "admin.reload_failed",
+        "Ponovno nalaganje nastavitev ni uspelo: {error}",
+    ),
+    ("admin.tool_missing", "- {tool}: ni najden"),
+    (
+        "admin.cache_purged",
+        "Predpomnilnik Discorda in {webhooks} shranjenih spletnih kljuk sta izpraznjena, {breakers} onemogočenih prenosnikov je znova omogočenih, shranjeni metapodatki povezav so zavrženi.",
+    ),
+    ("diag.running", "Izvajam diagnostiko..."),
+    ("diag.title", "Diagnostika"),
+    ("diag.tools", "Orodja"),
+    ("diag.encoders", "Kodirniki ffmpeg"),
+    ("diag.temp_dir", "Začasna mapa"),
+    ("diag.disk", "Prost prostor na disku"),
+    ("diag.network", "API Discorda"),
+    ("diag.not_found", "ni najden"),
+    ("diag.missing", "manjkajo: {list}"),
+    ("diag.no_ffmpeg", "ffmpeg ni mogoče zagnati"),
+    ("diag.unknown", "neznano"),
+    ("diag.reachable", "dosegljiv (HTTP {status})"),
+    ("stats.uptime", "Čas delovanja"),
+    ("stats.servers", "Strežniki"),
+    ("stats.configured_servers", "Nastavljeni strežniki"),
+    ("stats.active_downloads", "Aktivni prenosi"),
+    ("stats.queued_downloads", "Prenosi v čakalni vrsti"),
+    ("stats.scheduled_deletions", "Načrtovana brisanja"),
+    ("stats.alive_tasks", "Opravila izvajalnika"),
+    ("forget.reposts", "Zapisi objav prek spletnih kljuk"),
+    ("forget.embed_history", "Zapisi zgodovine objav"),
+    ("forget.audit_log", "Spremembe nastavitev"),
+    ("forget.watches", "Spremljani viri"),
+    ("forget.requests", "Nedavno zahtevane povezave"),
+    ("forget.metadata_cache", "Predpomnjeni metapodatki povezav"),
+    ("forget.settings", "Shranjene nastavitve strežnika"),
+    ("forget.quota", "Poraba kvote"),
+    ("log.failure", "⚠️ {error} za `{domain}` (oznaka zahteve `{id}`)"),
+];
+
+/// Looks up the message for `key`, falling back to English and then to the key itself.
+pub fn t(locale: Locale, key: &'static str) -> &'static str {
+    lookup(locale.messages(), key)
+        .or_else(|| lookup(EN, key))
+        .unwrap_or(key)
+}
+
+/// Looks up the message for `key` and substitutes `{name}` placeholders with `args`.
+pub fn tf(locale: Locale, key: &'static str, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(t(locale, key).to_string(), |message, (name, value)| {
+            message.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+fn lookup(messages: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    messages
+        .iter()
+        .find(|(message_key, _)| *message_key == key)
+        .map(|(_, message)| *message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_from_code() {
+        assert_eq!(Locale::from_code("en"), Some(Locale::En));
+        assert_eq!(Locale::from_code("en-US"), Some(Locale::En));
+        assert_eq!(Locale::from_code("SL"), Some(Locale::Sl));
+        assert_eq!(Locale::from_code("sl_SI"), Some(Locale::Sl));
+        assert_eq!(Locale::from_code("de"), None);
+        assert_eq!(Locale::from_code(""), None);
+    }
+
+    #[test]
+    fn test_t_translates() {
+        assert_eq!(t(Locale::En, "error.unsupported_url"), "Unsupported URL");
+        assert_eq!(t(Locale::Sl, "error.unsupported_url"), "Nepodprt URL");
+    }
+
+    #[test]
+    fn test_t_unknown_key_falls_back_to_key() {
+        assert_eq!(t(Locale::Sl, "does.not.exist"), "does.not.exist");
+    }
+
+    #[test]
+    fn test_tf_substitutes_args() {
+        assert_eq!(
+            tf(Locale::En, "media.author", &[("author", "Rick Astley")]),
+            "👤 Author: Rick Astley"
+        );
+        assert_eq!(
+            tf(
+                Locale::Sl,
+                "auto.download_failed",
+                &[("error", "Nepodprt URL")]
+            ),
+            "Prenos medija ni uspel: `Nepodprt URL`"
+        );
+    }
+
+    #[test]
+    fn test_all_locales_have_same_keys() {
+        for (key, _) in EN {
+            assert!(lookup(SL, key).is_some(), "missing sl translation: {key}");
+        }
+        assert_eq!(EN.len(), SL.len());
+    }
+}
@@ -1,11 +1,26 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use config::secret::{SecretKey, SECRET_KEY_ENV};
+use config::LogRotation;
 use tracing::info;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::{fmt, prelude::*};
 
 mod bot;
+mod cluster;
 mod config;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod i18n;
 mod media;
+mod metrics;
+#[cfg(feature = "sentry")]
+mod reporting;
+mod storage;
+#[cfg(feature = "otel")]
+mod telemetry;
 mod utils;
 
 #[derive(Parser, Debug)]
@@ -14,6 +29,28 @@ struct Args {
     /// Path to the config file
     #[arg(short, long)]
     config: Option<String>,
+
+    /// Print a new key for encrypting config secrets and exit
+    #[arg(long)]
+    generate_secret_key: bool,
+
+    /// Encrypt a config value with the key from GRABBY_SECRET_KEY and exit
+    #[arg(long, value_name = "VALUE")]
+    encrypt_secret: Option<String>,
+
+    /// Print the loaded config with secrets redacted and exit
+    #[arg(long)]
+    print_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check tools, ffmpeg encoders, the temp directory, disk space and connectivity, exiting
+    /// with an error if something the bot needs is missing
+    Doctor,
 }
 
 fn get_config_path(args: &Args) -> Option<String> {
@@ -44,35 +81,148 @@ fn get_config_path(args: &Args) -> Option<String> {
     None
 }
 
+fn log_file_appender(
+    config: &crate::config::Config,
+    directory: &std::path::Path,
+) -> Result<RollingFileAppender> {
+    let rotation = match config.get_log_rotation() {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(config.get_log_file_prefix())
+        .filename_suffix("log");
+    if let Some(max_files) = config.get_log_max_files() {
+        builder = builder.max_log_files(max_files);
+    }
+
+    builder
+        .build(directory)
+        .with_context(|| format!("Failed to open log directory {}", directory.display()))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    let (log_format, log_level) = if let Some(config_path) = get_config_path(&args) {
-        let config_file = crate::config::Config::from_file(&config_path)?;
-        (
-            config_file.get_logging_format().to_string(),
-            config_file.get_log_level().to_string(),
-        )
-    } else {
-        ("json".to_string(), "info".to_string())
+    if args.generate_secret_key {
+        println!("{}", SecretKey::generate()?);
+        return Ok(());
+    }
+
+    if let Some(value) = &args.encrypt_secret {
+        let key = SecretKey::from_env()?
+            .with_context(|| format!("{} must be set to encrypt secrets", SECRET_KEY_ENV))?;
+        println!("{}", key.encrypt(value)?);
+        return Ok(());
+    }
+
+    if args.print_config {
+        let config_path = get_config_path(&args).context("No config file found")?;
+        let config = crate::config::Config::from_file(&config_path)?;
+        print!("{}", toml::to_string(&config.redacted())?);
+        return Ok(());
+    }
+
+    if let Some(Command::Doctor) = args.command {
+        if let Some(config_path) = get_config_path(&args) {
+            let config = crate::config::Config::from_file(&config_path)?;
+            media::configure_http_client(&config.get_http_settings())?;
+        }
+        let report = media::Diagnostics::collect().await;
+        println!(
+            "{}",
+            bot::owner::render_diagnostics(&report, i18n::Locale::default())
+        );
+        if report.status() == media::Status::Failed {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let startup_config = match get_config_path(&args) {
+        Some(config_path) => crate::config::Config::from_file(&config_path)?,
+        None => crate::config::Config::default(),
     };
+    let log_level = startup_config.get_log_level();
 
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
-        .parse_lossy(&log_level);
+        .parse_lossy(log_level);
 
-    if log_format == "json" {
-        tracing_subscriber::fmt()
-            .json()
-            .with_env_filter(env_filter)
-            .init();
+    let json = startup_config.get_logging_format() == "json";
+    let mut fmt_layers = vec![if json {
+        fmt::layer().json().boxed()
     } else {
-        tracing_subscriber::fmt().with_env_filter(env_filter).init();
+        fmt::layer().boxed()
+    }];
+
+    // Held until exit so buffered lines still reach the file
+    let mut _log_guard = None;
+    if let Some(directory) = startup_config.get_log_directory() {
+        let (writer, guard) =
+            tracing_appender::non_blocking(log_file_appender(&startup_config, directory)?);
+        _log_guard = Some(guard);
+        let layer = fmt::layer().with_writer(writer).with_ansi(false);
+        fmt_layers.push(if json {
+            layer.json().boxed()
+        } else {
+            layer.boxed()
+        });
     }
 
+    let registry = tracing_subscriber::registry().with(fmt_layers.with_filter(env_filter));
+
+    // tokio-console needs the runtime's own trace events, so it sits outside the log filter
+    #[cfg(feature = "console")]
+    let registry = registry.with(console_subscriber::spawn());
+
+    #[cfg(feature = "otel")]
+    let telemetry = telemetry::Telemetry::new(
+        startup_config.get_otlp_endpoint(),
+        startup_config.get_service_name(),
+    )?;
+    // The exporters' own HTTP clients stay out of the export, which would otherwise feed itself
+    #[cfg(feature = "otel")]
+    let registry = registry.with(telemetry.as_ref().map(|t| {
+        t.layer().with_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .parse_lossy(format!(
+                    "{log_level},hyper=off,reqwest=off,h2=off,opentelemetry=off"
+                )),
+        )
+    }));
+
+    registry.init();
+
+    #[cfg(feature = "sentry")]
+    let _error_reporting = reporting::ErrorReporting::new(
+        startup_config.get_sentry_dsn(),
+        startup_config.get_sentry_environment(),
+        startup_config.get_error_repeat_threshold(),
+    )?;
+
     info!("Starting Grabby...");
 
+    #[cfg(not(feature = "otel"))]
+    if startup_config.get_otlp_endpoint().is_some() {
+        tracing::warn!("telemetry.otlp_endpoint is set, but OTLP export needs the otel feature");
+    }
+
+    #[cfg(not(feature = "grpc"))]
+    if startup_config.get_grpc_bind().is_some() {
+        tracing::warn!("grpc.bind is set, but the gRPC API needs the grpc feature");
+    }
+
+    #[cfg(not(feature = "sentry"))]
+    if startup_config.get_sentry_dsn().is_some() {
+        tracing::warn!("error_reporting.sentry_dsn is set, but reporting needs the sentry feature");
+    }
+
     if let Some(config_path) = get_config_path(&args) {
         info!("Loading config from: {}", config_path);
         let config_file = crate::config::Config::from_file(&config_path)
@@ -90,5 +240,10 @@ async fn main() -> Result<()> {
         bot::run().await?;
     }
 
+    #[cfg(feature = "otel")]
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+
     Ok(())
 }
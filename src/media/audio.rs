@@ -0,0 +1,226 @@
+use super::{probe::MediaProbe, process};
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use tempfile::NamedTempFile;
+use tracing::info;
+
+/// EBU R128 loudness normalization: -16 LUFS integrated, -1.5 dBTP true peak.
+const LOUDNORM_FILTER: &str = "loudnorm=I=-16:TP=-1.5:LRA=11";
+
+/// Lowest bitrate audio is squeezed to when fitting a size limit.
+const MIN_AUDIO_BITRATE: u64 = 24_000;
+
+/// Format of transcoded audio tracks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// Opus in Ogg, best quality per byte
+    #[default]
+    Opus,
+    /// MP3, for players without Opus support
+    Mp3,
+}
+
+impl AudioFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "opus" => Some(Self::Opus),
+            "mp3" => Some(Self::Mp3),
+            _ => None,
+        }
+    }
+
+    /// Format an existing audio file is kept in when it's re-encoded.
+    pub fn for_filename(filename: &str) -> Self {
+        match Path::new(filename).extension().and_then(|e| e.to_str()) {
+            Some("ogg" | "opus") => Self::Opus,
+            _ => Self::Mp3,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Opus => "ogg",
+            Self::Mp3 => "mp3",
+        }
+    }
+
+    /// `filename` with the extension of this format.
+    pub fn filename(self, filename: &str) -> String {
+        let stem = Path::new(filename)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("audio");
+        format!("{stem}.{}", self.extension())
+    }
+
+    fn encoder(self) -> &'static str {
+        match self {
+            Self::Opus => "libopus",
+            Self::Mp3 => "libmp3lame",
+        }
+    }
+
+    fn default_bitrate(self) -> u64 {
+        match self {
+            Self::Opus => 128_000,
+            Self::Mp3 => 192_000,
+        }
+    }
+}
+
+/// Name of the audio file extracted from `filename`.
+pub fn audio_filename(filename: &str) -> String {
+    let stem = Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("audio");
+    format!("{stem}.mp3")
+}
+
+fn audio_filter_args(normalize: bool) -> Vec<&'static str> {
+    if normalize {
+        vec!["-af", LOUDNORM_FILTER]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Extracts the audio track of a video as MP3, optionally normalizing its loudness.
+pub fn extract_audio_file(data: &[u8], filename: &str, normalize: bool) -> Result<Vec<u8>> {
+    info!(
+        "Extracting audio from {} ({} bytes, normalize: {})",
+        filename,
+        data.len(),
+        normalize
+    );
+
+    let mut input_file = NamedTempFile::new()?;
+    input_file.write_all(data)?;
+
+    let output_file = NamedTempFile::with_suffix(".mp3")?;
+
+    let output = process::run(
+        Command::new("ffmpeg")
+            .arg("-i")
+            .arg(input_file.path())
+            .arg("-vn")
+            .args(audio_filter_args(normalize))
+            .arg("-c:a")
+            .arg("libmp3lame")
+            .arg("-q:a")
+            .arg("2")
+            .arg("-y")
+            .arg(output_file.path()),
+    )?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to extract audio: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(std::fs::read(output_file.path())?)
+}
+
+/// Bitrate that keeps `duration` seconds of audio within `max_bytes`, leaving room for the
+/// container.
+fn fitting_bitrate(format: AudioFormat, duration: f64, max_bytes: Option<u64>) -> u64 {
+    let Some(max_bytes) = max_bytes else {
+        return format.default_bitrate();
+    };
+
+    let budget = max_bytes * 8 * 95 / 100 / (duration.ceil() as u64).max(1);
+    budget.clamp(MIN_AUDIO_BITRATE, format.default_bitrate())
+}
+
+/// Transcodes the audio track of `data` to `format`, at a bitrate that fits `max_bytes` if set.
+pub fn transcode_audio(
+    data: &[u8],
+    format: AudioFormat,
+    max_bytes: Option<u64>,
+) -> Result<Vec<u8>> {
+    let mut input_file = NamedTempFile::new()?;
+    input_file.write_all(data)?;
+
+    let duration = MediaProbe::probe(input_file.path())?
+        .duration()
+        .context("Failed to get media duration")?;
+    let bitrate = fitting_bitrate(format, duration, max_bytes);
+    info!(
+        "Transcoding {:.0}s of audio ({} bytes) to {:?} at {} kbps",
+        duration,
+        data.len(),
+        format,
+        bitrate / 1000
+    );
+
+    let output_file = NamedTempFile::with_suffix(format!(".{}", format.extension()))?;
+
+    let output = process::run(
+        Command::new("ffmpeg")
+            .arg("-i")
+            .arg(input_file.path())
+            .arg("-map")
+            .arg("0:a:0")
+            .arg("-vn")
+            .arg("-c:a")
+            .arg(format.encoder())
+            .arg("-b:a")
+            .arg(bitrate.to_string())
+            .arg("-y")
+            .arg(output_file.path()),
+    )?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to transcode audio: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(std::fs::read(output_file.path())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_filename() {
+        assert_eq!(audio_filename("abc123.mp4"), "abc123.mp3");
+        assert_eq!(audio_filename("clip.final.webm"), "clip.final.mp3");
+        assert_eq!(audio_filename(""), "audio.mp3");
+    }
+
+    #[test]
+    fn test_audio_format() {
+        assert_eq!(AudioFormat::from_name("MP3"), Some(AudioFormat::Mp3));
+        assert_eq!(AudioFormat::from_name("aac"), None);
+        assert_eq!(AudioFormat::for_filename("track.ogg"), AudioFormat::Opus);
+        assert_eq!(AudioFormat::for_filename("track.m4a"), AudioFormat::Mp3);
+        assert_eq!(AudioFormat::Opus.filename("track.m4a"), "track.ogg");
+    }
+
+    #[test]
+    fn test_fitting_bitrate() {
+        assert_eq!(fitting_bitrate(AudioFormat::Opus, 200.0, None), 128_000);
+        // 10 MB over an hour leaves about 21 kbps, which is raised to the minimum
+        assert_eq!(
+            fitting_bitrate(AudioFormat::Mp3, 3600.0, Some(10_000_000)),
+            MIN_AUDIO_BITRATE
+        );
+        assert_eq!(
+            fitting_bitrate(AudioFormat::Mp3, 600.0, Some(10_000_000)),
+            126_666
+        );
+    }
+
+    #[test]
+    fn test_loudnorm_only_when_normalizing() {
+        assert_eq!(audio_filter_args(true), vec!["-af", LOUDNORM_FILTER]);
+        assert!(audio_filter_args(false).is_empty());
+    }
+}
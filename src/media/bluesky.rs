@@ -0,0 +1,382 @@
+use super::{
+    downloader::Downloader,
+    http,
+    image::process_image,
+    timeouts::Stage,
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+/// Public AppView serving posts without authentication.
+const APPVIEW_URL: &str = "https://public.api.bsky.app";
+const PLC_DIRECTORY_URL: &str = "https://plc.directory";
+
+/// Downloads images and videos embedded in Bluesky posts through the public XRPC API.
+pub struct BlueskyDownloader;
+
+/// Media embedded in a post.
+#[derive(Debug, PartialEq, Eq)]
+enum Embed {
+    /// Full-size image on the Bluesky CDN
+    Image(String),
+    /// Video blob, fetched from the author's PDS by its CID
+    Video(String),
+}
+
+impl BlueskyDownloader {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn get_json(&self, url: &str) -> Result<Value> {
+        let response = http::get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {url}"))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Bluesky API request failed: HTTP {}",
+                response.status()
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    async fn resolve_did(&self, actor: &str) -> Result<String> {
+        if actor.starts_with("did:") {
+            return Ok(actor.to_string());
+        }
+
+        let response = self
+            .get_json(&format!(
+                "{APPVIEW_URL}/xrpc/com.atproto.identity.resolveHandle?handle={actor}"
+            ))
+            .await?;
+        response["did"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Failed to resolve handle {actor}"))
+    }
+
+    async fn get_post(&self, at_uri: &str) -> Result<Value> {
+        let response = self
+            .get_json(&format!(
+                "{APPVIEW_URL}/xrpc/app.bsky.feed.getPosts?uris={}",
+                url::form_urlencoded::byte_serialize(at_uri.as_bytes()).collect::<String>()
+            ))
+            .await?;
+
+        response["posts"]
+            .get(0)
+            .cloned()
+            .ok_or_else(|| anyhow!("Post not found"))
+    }
+
+    /// Endpoint of the PDS hosting the account's blobs.
+    async fn resolve_pds(&self, did: &str) -> Result<String> {
+        let document_url = match did.strip_prefix("did:web:") {
+            Some(domain) => format!("https://{domain}/.well-known/did.json"),
+            None => format!("{PLC_DIRECTORY_URL}/{did}"),
+        };
+        let document = self.get_json(&document_url).await?;
+
+        document["service"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|service| service["id"].as_str() == Some("#atproto_pds"))
+            .and_then(|service| service["serviceEndpoint"].as_str())
+            .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+            .ok_or_else(|| anyhow!("No PDS found for {did}"))
+    }
+
+    async fn download_to_memory(&self, url: &str, filename: String) -> Result<MediaFile> {
+        debug!("Downloading to memory: {}", url);
+
+        let response = http::get(url)
+            .send()
+            .await
+            .context("Failed to fetch media URL")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to download media: HTTP {}",
+                response.status()
+            ));
+        }
+
+        let data = response
+            .bytes()
+            .await
+            .context("Failed to read media data")?
+            .to_vec();
+
+        Ok(MediaFile { filename, data })
+    }
+}
+
+/// Author (handle or DID) and record key of a `bsky.app/profile/<actor>/post/<rkey>` link.
+fn parse_post_url(url: &str) -> Option<(String, String)> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    if host != "bsky.app" && host != "www.bsky.app" {
+        return None;
+    }
+
+    let segments: Vec<&str> = parsed.path_segments()?.filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        ["profile", actor, "post", rkey] => Some((actor.to_string(), rkey.to_string())),
+        _ => None,
+    }
+}
+
+/// Maps a post view to metadata and the media embedded in it.
+fn parse_post(post: &Value, rkey: &str) -> Result<(MediaMetadata, Vec<Embed>)> {
+    let embed = &post["embed"];
+    // Quote posts with media keep it one level down
+    let media = match embed["$type"].as_str() {
+        Some("app.bsky.embed.recordWithMedia#view") => &embed["media"],
+        _ => embed,
+    };
+
+    let mut thumbnail = None;
+    let embeds = match media["$type"].as_str() {
+        Some("app.bsky.embed.images#view") => media["images"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|image| image["fullsize"].as_str())
+            .map(|url| Embed::Image(url.to_string()))
+            .collect(),
+        Some("app.bsky.embed.video#view") => {
+            thumbnail = media["thumbnail"].as_str().map(str::to_string);
+            media["cid"]
+                .as_str()
+                .map(|cid| vec![Embed::Video(cid.to_string())])
+                .unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+
+    if embeds.is_empty() {
+        return Err(anyhow!("Post has no images or video"));
+    }
+
+    let text = post["record"]["text"].as_str().unwrap_or_default().trim();
+    let format_ext = match embeds[0] {
+        Embed::Image(_) => "jpg",
+        Embed::Video(_) => "mp4",
+    };
+
+    Ok((
+        MediaMetadata {
+            title: if text.is_empty() {
+                "Unknown Media".to_string()
+            } else {
+                text.to_string()
+            },
+            id: rkey.to_string(),
+            thumbnail,
+            duration: None,
+            author: post["author"]["handle"].as_str().map(|h| format!("@{h}")),
+            likes: post["likeCount"].as_u64(),
+            description: None,
+            view_count: None,
+            uploaded_at: post["record"]["createdAt"]
+                .as_str()
+                .and_then(crate::utils::parse_date),
+            site: Some("Bluesky".to_string()),
+            format_ext: format_ext.to_string(),
+            chapters: Vec::new(),
+            nsfw: has_adult_label(post),
+            live: false,
+            formats: Vec::new(),
+        },
+        embeds,
+    ))
+}
+
+/// Whether the post carries one of Bluesky's adult content labels.
+fn has_adult_label(post: &Value) -> bool {
+    post["labels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|label| label["val"].as_str())
+        .any(|val| matches!(val, "porn" | "sexual" | "nudity" | "graphic-media"))
+}
+
+#[async_trait]
+impl Downloader for BlueskyDownloader {
+    fn name(&self) -> &'static str {
+        "bluesky"
+    }
+
+    fn supports_url(&self, url: &str) -> bool {
+        parse_post_url(url).is_some()
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let url = req.url.as_str();
+        let (actor, rkey) = parse_post_url(url).ok_or_else(|| anyhow!("Not a Bluesky post URL"))?;
+
+        let (did, post) = req
+            .timeouts
+            .run(Stage::Extraction, async {
+                let did = self.resolve_did(&actor).await?;
+                let at_uri = format!("at://{did}/app.bsky.feed.post/{rkey}");
+                info!("Resolving Bluesky post: {}", at_uri);
+                let post = self.get_post(&at_uri).await?;
+                Ok::<_, anyhow::Error>((did, post))
+            })
+            .await??;
+        let (metadata, embeds) = parse_post(&post, &rkey)?;
+
+        let files = req
+            .timeouts
+            .run(Stage::Download, async {
+                let mut pds = None;
+                let mut files = Vec::new();
+                for (index, embed) in embeds.iter().enumerate() {
+                    let (media_url, ext) = match embed {
+                        Embed::Image(url) => (url.clone(), "jpg"),
+                        Embed::Video(cid) => {
+                            if pds.is_none() {
+                                pds = Some(self.resolve_pds(&did).await?);
+                            }
+                            let pds = pds.as_deref().unwrap_or_default();
+                            (
+                                format!("{pds}/xrpc/com.atproto.sync.getBlob?did={did}&cid={cid}"),
+                                "mp4",
+                            )
+                        }
+                    };
+                    let filename = if index == 0 {
+                        format!("{}.{}", metadata.id, ext)
+                    } else {
+                        format!("{}_{}.{}", metadata.id, index + 1, ext)
+                    };
+
+                    match self.download_to_memory(&media_url, filename).await {
+                        Ok(file) => files.push(process_image(file).await),
+                        Err(e) => warn!("Failed to download {}: {}", media_url, e),
+                    }
+                }
+                Ok::<_, anyhow::Error>(files)
+            })
+            .await??;
+
+        if files.is_empty() {
+            return Err(anyhow!("Failed to download any media files"));
+        }
+
+        Ok(MediaInfo {
+            url: url.to_string(),
+            files,
+            metadata,
+            truncated_to: None,
+            timeouts: req.timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    async fn test_availability() -> bool {
+        // Only needs HTTP access to the Bluesky API
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_post_url() {
+        assert_eq!(
+            parse_post_url("https://bsky.app/profile/alice.bsky.social/post/3kabc123"),
+            Some(("alice.bsky.social".to_string(), "3kabc123".to_string()))
+        );
+        assert_eq!(
+            parse_post_url("https://bsky.app/profile/did:plc:abc/post/3kxyz/"),
+            Some(("did:plc:abc".to_string(), "3kxyz".to_string()))
+        );
+        assert_eq!(
+            parse_post_url("https://bsky.app/profile/alice.bsky.social"),
+            None
+        );
+        assert_eq!(parse_post_url("https://example.com/profile/a/post/b"), None);
+    }
+
+    #[test]
+    fn test_parse_post_with_images() {
+        let post = serde_json::json!({
+            "author": { "handle": "alice.bsky.social" },
+            "record": { "text": "Two pictures" },
+            "likeCount": 42,
+            "embed": {
+                "$type": "app.bsky.embed.images#view",
+                "images": [
+                    { "fullsize": "https://cdn.bsky.app/img/feed_fullsize/plain/did:plc:a/1@jpeg" },
+                    { "fullsize": "https://cdn.bsky.app/img/feed_fullsize/plain/did:plc:a/2@jpeg" }
+                ]
+            }
+        });
+
+        let (metadata, embeds) = parse_post(&post, "3kabc").unwrap();
+        assert_eq!(metadata.id, "3kabc");
+        assert_eq!(metadata.title, "Two pictures");
+        assert_eq!(metadata.author.as_deref(), Some("@alice.bsky.social"));
+        assert_eq!(metadata.likes, Some(42));
+        assert_eq!(metadata.format_ext, "jpg");
+        assert!(!metadata.nsfw);
+        assert_eq!(embeds.len(), 2);
+    }
+
+    #[test]
+    fn test_has_adult_label() {
+        let post = serde_json::json!({ "labels": [{ "val": "!no-unauthenticated" }] });
+        assert!(!has_adult_label(&post));
+
+        let post = serde_json::json!({ "labels": [{ "val": "porn" }] });
+        assert!(has_adult_label(&post));
+    }
+
+    #[test]
+    fn test_parse_quote_post_with_video() {
+        let post = serde_json::json!({
+            "author": { "handle": "bob.example" },
+            "record": { "text": "" },
+            "embed": {
+                "$type": "app.bsky.embed.recordWithMedia#view",
+                "media": {
+                    "$type": "app.bsky.embed.video#view",
+                    "cid": "bafkreivideo",
+                    "thumbnail": "https://video.bsky.app/thumb.jpg"
+                }
+            }
+        });
+
+        let (metadata, embeds) = parse_post(&post, "3k").unwrap();
+        assert_eq!(embeds, vec![Embed::Video("bafkreivideo".to_string())]);
+        assert_eq!(metadata.title, "Unknown Media");
+        assert_eq!(metadata.format_ext, "mp4");
+        assert_eq!(
+            metadata.thumbnail.as_deref(),
+            Some("https://video.bsky.app/thumb.jpg")
+        );
+    }
+
+    #[test]
+    fn test_parse_post_without_media() {
+        let post = serde_json::json!({
+            "record": { "text": "Just text" },
+            "embed": { "$type": "app.bsky.embed.external#view" }
+        });
+        assert!(parse_post(&post, "3k").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use super::http;
+use anyhow::{anyhow, Context, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+/// External downloaders that can be bootstrapped when missing from `PATH`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    YtDlp,
+    GalleryDl,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::YtDlp => "yt-dlp",
+            Self::GalleryDl => "gallery-dl",
+        }
+    }
+
+    /// Release the standalone binaries are downloaded from, unless overridden in config.
+    pub fn default_version(self) -> &'static str {
+        match self {
+            Self::YtDlp => "2025.06.30",
+            Self::GalleryDl => "1.30.0",
+        }
+    }
+
+    /// Name of the standalone release asset for an OS/arch pair, if one is published.
+    fn asset(self, os: &str, arch: &str) -> Option<&'static str> {
+        match (self, os, arch) {
+            (Self::YtDlp, "linux", "x86_64") => Some("yt-dlp_linux"),
+            (Self::YtDlp, "linux", "aarch64") => Some("yt-dlp_linux_aarch64"),
+            (Self::YtDlp, "macos", _) => Some("yt-dlp_macos"),
+            (Self::YtDlp, "windows", "x86_64") => Some("yt-dlp.exe"),
+            (Self::GalleryDl, "linux", "x86_64") => Some("gallery-dl.bin"),
+            (Self::GalleryDl, "windows", "x86_64") => Some("gallery-dl.exe"),
+            _ => None,
+        }
+    }
+
+    fn download_url(self, version: &str, asset: &str) -> String {
+        match self {
+            Self::YtDlp => {
+                format!("https://github.com/yt-dlp/yt-dlp/releases/download/{version}/{asset}")
+            }
+            Self::GalleryDl => {
+                format!("https://github.com/mikf/gallery-dl/releases/download/v{version}/{asset}")
+            }
+        }
+    }
+
+    /// Config key holding the expected SHA-256 of the build.
+    fn sha256_key(self) -> &'static str {
+        match self {
+            Self::YtDlp => "yt_dlp_sha256",
+            Self::GalleryDl => "gallery_dl_sha256",
+        }
+    }
+}
+
+/// How a single tool is pinned: release version and the expected SHA-256 of the build for
+/// this host's OS/arch. Tools without a digest are never downloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolPin {
+    pub tool: Tool,
+    pub version: String,
+    pub sha256: Option<String>,
+}
+
+static PROGRAMS: RwLock<Vec<(Tool, PathBuf)>> = RwLock::new(Vec::new());
+
+/// Returns the program to run for `tool`: the bootstrapped binary if there is one, else the name on `PATH`.
+pub fn program(tool: Tool) -> OsString {
+    PROGRAMS
+        .read()
+        .ok()
+        .and_then(|programs| {
+            programs
+                .iter()
+                .find(|(t, _)| *t == tool)
+                .map(|(_, path)| path.clone().into_os_string())
+        })
+        .unwrap_or_else(|| tool.name().into())
+}
+
+fn set_program(tool: Tool, path: PathBuf) {
+    if let Ok(mut programs) = PROGRAMS.write() {
+        programs.retain(|(t, _)| *t != tool);
+        programs.push((tool, path));
+    }
+}
+
+async fn is_on_path(tool: Tool) -> bool {
+    tokio::process::Command::new(tool.name())
+        .arg("--version")
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Makes every pinned tool that is missing from `PATH` available from `dir`,
+/// downloading and verifying it on first use.
+pub async fn ensure_tools(dir: &Path, pins: &[ToolPin]) {
+    for pin in pins {
+        if is_on_path(pin.tool).await {
+            continue;
+        }
+
+        match ensure_tool(dir, pin).await {
+            Ok(path) => {
+                info!(
+                    "Using bootstrapped {} at {}",
+                    pin.tool.name(),
+                    path.display()
+                );
+                set_program(pin.tool, path);
+            }
+            Err(e) => warn!("Failed to bootstrap {}: {:#}", pin.tool.name(), e),
+        }
+    }
+}
+
+/// Versions of the external programs media processing relies on, `None` for ones that don't run.
+pub async fn tool_versions() -> Vec<(&'static str, Option<String>)> {
+    let mut versions = Vec::new();
+    for tool in [Tool::YtDlp, Tool::GalleryDl] {
+        versions.push((
+            tool.name(),
+            program_version(program(tool), "--version").await,
+        ));
+    }
+    for name in ["ffmpeg", "ffprobe"] {
+        let version = program_version(name.into(), "-version").await;
+        versions.push((name, version.map(|v| ffmpeg_version(&v))));
+    }
+    versions
+}
+
+/// First line a program prints for its version flag.
+async fn program_version(program: OsString, flag: &str) -> Option<String> {
+    let output = tokio::process::Command::new(program)
+        .arg(flag)
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+/// Version from ffmpeg's "ffmpeg version 6.1.1 Copyright ..." banner.
+fn ffmpeg_version(banner: &str) -> String {
+    banner
+        .split_whitespace()
+        .skip_while(|word| *word != "version")
+        .nth(1)
+        .unwrap_or(banner)
+        .to_string()
+}
+
+async fn ensure_tool(dir: &Path, pin: &ToolPin) -> Result<PathBuf> {
+    let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+    let asset = pin
+        .tool
+        .asset(os, arch)
+        .with_context(|| format!("No standalone build for {os}/{arch}"))?;
+
+    let path = dir
+        .join(os)
+        .join(arch)
+        .join(&pin.version)
+        .join(pin.tool.name());
+
+    let expected = pin
+        .sha256
+        .as_deref()
+        .map(str::to_lowercase)
+        .with_context(|| {
+            format!(
+                "No pinned checksum for {asset}, set {} in the [bootstrap] config",
+                pin.tool.sha256_key()
+            )
+        })?;
+
+    // Reuse a previous download, but never trust it without checking it again
+    if let Ok(data) = tokio::fs::read(&path).await {
+        if sha256_hex(&data) == expected {
+            return Ok(path);
+        }
+        warn!(
+            "Bootstrapped {} at {} failed verification, downloading again",
+            pin.tool.name(),
+            path.display()
+        );
+    }
+
+    let url = pin.tool.download_url(&pin.version, asset);
+    info!("Downloading {} from {}", pin.tool.name(), url);
+    let data = http::get(&url)
+        .send()
+        .await?
+        .error_for_status()
+        .with_context(|| format!("Failed to download {url}"))?
+        .bytes()
+        .await?;
+
+    let actual = sha256_hex(&data);
+    if actual != expected {
+        return Err(anyhow!(
+            "Checksum mismatch for {asset}: expected {expected}, got {actual}"
+        ));
+    }
+
+    write_executable(&path, &data).await?;
+    Ok(path)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+async fn write_executable(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, data).await?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffmpeg_version() {
+        assert_eq!(
+            ffmpeg_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023"),
+            "6.1.1-3ubuntu5"
+        );
+        assert_eq!(
+            ffmpeg_version("ffprobe version n7.0 Copyright (c) 2007-2024"),
+            "n7.0"
+        );
+        assert_eq!(ffmpeg_version("unexpected"), "unexpected");
+    }
+
+    #[test]
+    fn test_asset_per_platform() {
+        assert_eq!(Tool::YtDlp.asset("linux", "x86_64"), Some("yt-dlp_linux"));
+        assert_eq!(
+            Tool::YtDlp.asset("linux", "aarch64"),
+            Some("yt-dlp_linux_aarch64")
+        );
+        assert_eq!(Tool::YtDlp.asset("macos", "aarch64"), Some("yt-dlp_macos"));
+        assert_eq!(
+            Tool::GalleryDl.asset("linux", "x86_64"),
+            Some("gallery-dl.bin")
+        );
+        assert_eq!(Tool::GalleryDl.asset("linux", "aarch64"), None);
+    }
+
+    #[test]
+    fn test_download_url() {
+        assert_eq!(
+            Tool::YtDlp.download_url("2025.06.30", "yt-dlp_linux"),
+            "https://github.com/yt-dlp/yt-dlp/releases/download/2025.06.30/yt-dlp_linux"
+        );
+        assert_eq!(
+            Tool::GalleryDl.download_url("1.30.0", "gallery-dl.bin"),
+            "https://github.com/mikf/gallery-dl/releases/download/v1.30.0/gallery-dl.bin"
+        );
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_program_defaults_to_path_lookup() {
+        assert_eq!(program(Tool::GalleryDl), "gallery-dl");
+    }
+
+    #[tokio::test]
+    async fn test_unpinned_tool_is_not_downloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let pin = ToolPin {
+            tool: Tool::YtDlp,
+            version: "test".to_string(),
+            sha256: None,
+        };
+        let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+        if Tool::YtDlp.asset(os, arch).is_none() {
+            return;
+        }
+
+        let err = ensure_tool(dir.path(), &pin).await.unwrap_err();
+        assert!(err.to_string().contains("yt_dlp_sha256"));
+        assert!(!dir.path().join(os).exists());
+    }
+
+    #[tokio::test]
+    async fn test_verified_download_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let pin = ToolPin {
+            tool: Tool::YtDlp,
+            version: "test".to_string(),
+            sha256: Some(sha256_hex(b"binary")),
+        };
+        let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+        if Tool::YtDlp.asset(os, arch).is_none() {
+            return;
+        }
+
+        let path = dir.path().join(os).join(arch).join("test").join("yt-dlp");
+        write_executable(&path, b"binary").await.unwrap();
+
+        assert_eq!(ensure_tool(dir.path(), &pin).await.unwrap(), path);
+    }
+}
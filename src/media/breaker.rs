@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Failures in a row after which a downloader is skipped for a domain.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long a downloader is skipped for a domain once it keeps failing there.
+const COOLDOWN: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Tracks failures per downloader and domain, so a downloader whose extractor for a site is
+/// broken is skipped there for a while instead of running into its timeouts on every link.
+///
+/// Once the cooldown has passed the next download is let through again; a failure skips the
+/// downloader for another cooldown, a success closes the circuit.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    circuits: Mutex<HashMap<(&'static str, String), Circuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `downloader` should be skipped for `domain` at `now`.
+    pub fn is_open(&self, downloader: &'static str, domain: &str, now: Instant) -> bool {
+        self.lock()
+            .get(&(downloader, domain.to_string()))
+            .and_then(|circuit| circuit.open_until)
+            .is_some_and(|until| now < until)
+    }
+
+    pub fn record_success(&self, downloader: &'static str, domain: &str) {
+        self.lock().remove(&(downloader, domain.to_string()));
+    }
+
+    pub fn record_failure(&self, downloader: &'static str, domain: &str, now: Instant) {
+        let mut circuits = self.lock();
+        let circuit = circuits
+            .entry((downloader, domain.to_string()))
+            .or_default();
+        circuit.consecutive_failures += 1;
+
+        if circuit.consecutive_failures >= FAILURE_THRESHOLD {
+            warn!(
+                monotonic_counter.circuit_breaker_trips = 1u64,
+                downloader,
+                domain,
+                "{} failed {} times in a row for {}, skipping it for {}s",
+                downloader,
+                circuit.consecutive_failures,
+                domain,
+                COOLDOWN.as_secs()
+            );
+            circuit.open_until = Some(now + COOLDOWN);
+        }
+    }
+
+    /// Closes every circuit, returning how many were open.
+    pub fn reset(&self) -> usize {
+        let mut circuits = self.lock();
+        let open = circuits
+            .values()
+            .filter(|circuit| circuit.open_until.is_some())
+            .count();
+        circuits.clear();
+        open
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(&'static str, String), Circuit>> {
+        self.circuits.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Domain a URL's circuits are tracked under, its host without a leading "www.".
+pub fn domain_of(url: &str) -> Option<String> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_lowercase();
+    Some(
+        host.strip_prefix("www.")
+            .map(str::to_string)
+            .unwrap_or(host),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new();
+        let now = Instant::now();
+
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            breaker.record_failure("yt-dlp", "example.com", now);
+        }
+        assert!(!breaker.is_open("yt-dlp", "example.com", now));
+
+        breaker.record_failure("yt-dlp", "example.com", now);
+        assert!(breaker.is_open("yt-dlp", "example.com", now));
+        // Other downloaders and domains are unaffected
+        assert!(!breaker.is_open("gallery-dl", "example.com", now));
+        assert!(!breaker.is_open("yt-dlp", "other.com", now));
+
+        assert!(!breaker.is_open("yt-dlp", "example.com", now + COOLDOWN));
+    }
+
+    #[test]
+    fn test_probe_after_cooldown() {
+        let breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 0..FAILURE_THRESHOLD {
+            breaker.record_failure("yt-dlp", "example.com", now);
+        }
+
+        // A failed probe opens the circuit again right away
+        let later = now + COOLDOWN;
+        breaker.record_failure("yt-dlp", "example.com", later);
+        assert!(breaker.is_open("yt-dlp", "example.com", later));
+
+        // A successful one closes it
+        breaker.record_success("yt-dlp", "example.com");
+        assert!(!breaker.is_open("yt-dlp", "example.com", later));
+        breaker.record_failure("yt-dlp", "example.com", later);
+        assert!(!breaker.is_open("yt-dlp", "example.com", later));
+    }
+
+    #[test]
+    fn test_successes_reset_failures() {
+        let breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 0..FAILURE_THRESHOLD * 2 {
+            breaker.record_failure("yt-dlp", "example.com", now);
+            breaker.record_success("yt-dlp", "example.com");
+        }
+        assert!(!breaker.is_open("yt-dlp", "example.com", now));
+    }
+
+    #[test]
+    fn test_reset() {
+        let breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 0..FAILURE_THRESHOLD {
+            breaker.record_failure("yt-dlp", "example.com", now);
+        }
+        breaker.record_failure("gallery-dl", "example.com", now);
+
+        assert_eq!(breaker.reset(), 1);
+        assert!(!breaker.is_open("yt-dlp", "example.com", now));
+    }
+
+    #[test]
+    fn test_domain_of() {
+        assert_eq!(
+            domain_of("https://www.YouTube.com/watch?v=1").as_deref(),
+            Some("youtube.com")
+        );
+        assert_eq!(
+            domain_of("https://m.youtube.com/watch?v=1").as_deref(),
+            Some("m.youtube.com")
+        );
+        assert_eq!(domain_of("not a url"), None);
+    }
+}
@@ -0,0 +1,243 @@
+use super::disk::free_space;
+use super::{http, tool_versions};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Encoders of ffmpeg the resize and audio pipelines use.
+const ENCODERS: &[&str] = &[
+    "libx264",
+    "libvpx-vp9",
+    "libaom-av1",
+    "aac",
+    "libopus",
+    "libmp3lame",
+];
+
+/// Free space below which the temp directory is reported as running low.
+const LOW_DISK_SPACE: u64 = 1_000_000_000;
+
+/// URL checked for outbound connectivity, answering without authentication.
+const CONNECTIVITY_URL: &str = "https://discord.com/api/v10/gateway";
+
+/// How long the connectivity check waits for a response.
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a single check of the setup turned out, ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl Status {
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Ok => "✅",
+            Self::Warning => "⚠️",
+            Self::Failed => "❌",
+        }
+    }
+}
+
+/// Report of everything the bot needs from the host it runs on.
+#[derive(Debug)]
+pub struct Diagnostics {
+    /// Versions of the external programs, `None` for ones that don't run
+    pub tools: Vec<(&'static str, Option<String>)>,
+    /// Whether ffmpeg has each of the encoders used, `None` when it can't be asked
+    pub encoders: Option<Vec<(&'static str, bool)>>,
+    pub temp_dir: PathBuf,
+    /// Why a file couldn't be written to the temp directory, if it couldn't
+    pub temp_dir_error: Option<String>,
+    /// Free bytes on the temp directory's file system, if known
+    pub free_space: Option<u64>,
+    /// HTTP status of the connectivity check, or why it failed
+    pub connectivity: Result<u16, String>,
+}
+
+impl Diagnostics {
+    /// Runs every check.
+    pub async fn collect() -> Self {
+        let temp_dir = std::env::temp_dir();
+        let temp_dir_error = {
+            let dir = temp_dir.clone();
+            tokio::task::spawn_blocking(move || check_writable(&dir))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()))
+                .err()
+        };
+
+        Self {
+            tools: tool_versions().await,
+            encoders: ffmpeg_encoders().await,
+            free_space: free_space(&temp_dir).await,
+            temp_dir,
+            temp_dir_error,
+            connectivity: check_connectivity().await,
+        }
+    }
+
+    pub fn tools_status(&self) -> Status {
+        let available = |name: &str| {
+            self.tools
+                .iter()
+                .any(|(tool, version)| *tool == name && version.is_some())
+        };
+        if !available("yt-dlp") && !available("gallery-dl") {
+            Status::Failed
+        } else if self.tools.iter().any(|(_, version)| version.is_none()) {
+            Status::Warning
+        } else {
+            Status::Ok
+        }
+    }
+
+    pub fn encoders_status(&self) -> Status {
+        match &self.encoders {
+            None => Status::Failed,
+            Some(encoders) if encoders.iter().all(|(_, available)| *available) => Status::Ok,
+            Some(_) => Status::Warning,
+        }
+    }
+
+    pub fn temp_dir_status(&self) -> Status {
+        match self.temp_dir_error {
+            None => Status::Ok,
+            Some(_) => Status::Failed,
+        }
+    }
+
+    pub fn disk_status(&self) -> Status {
+        match self.free_space {
+            Some(free) if free >= LOW_DISK_SPACE => Status::Ok,
+            _ => Status::Warning,
+        }
+    }
+
+    pub fn connectivity_status(&self) -> Status {
+        match self.connectivity {
+            Ok(_) => Status::Ok,
+            Err(_) => Status::Failed,
+        }
+    }
+
+    /// Worst outcome of all checks.
+    pub fn status(&self) -> Status {
+        [
+            self.tools_status(),
+            self.encoders_status(),
+            self.temp_dir_status(),
+            self.disk_status(),
+            self.connectivity_status(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(Status::Ok)
+    }
+}
+
+/// Asks ffmpeg which of the used encoders it was built with.
+async fn ffmpeg_encoders() -> Option<Vec<(&'static str, bool)>> {
+    let output = tokio::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-encoders"])
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    Some(parse_encoders(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Availability of each of [`ENCODERS`] in the listing of `ffmpeg -encoders`, whose lines look
+/// like " V....D libx264              libx264 H.264 / AVC".
+fn parse_encoders(listing: &str) -> Vec<(&'static str, bool)> {
+    let names: Vec<&str> = listing
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .collect();
+    ENCODERS
+        .iter()
+        .map(|encoder| (*encoder, names.contains(encoder)))
+        .collect()
+}
+
+fn check_writable(dir: &Path) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut file = tempfile::tempfile_in(dir).map_err(|e| e.to_string())?;
+    file.write_all(b"grabby").map_err(|e| e.to_string())
+}
+
+async fn check_connectivity() -> Result<u16, String> {
+    match tokio::time::timeout(CONNECTIVITY_TIMEOUT, http::get(CONNECTIVITY_URL).send()).await {
+        Ok(Ok(response)) => Ok(response.status().as_u16()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!(
+            "no response within {}s",
+            CONNECTIVITY_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics() -> Diagnostics {
+        Diagnostics {
+            tools: vec![
+                ("yt-dlp", Some("2025.01.01".to_string())),
+                ("gallery-dl", Some("1.29.0".to_string())),
+                ("ffmpeg", Some("7.1".to_string())),
+            ],
+            encoders: Some(ENCODERS.iter().map(|encoder| (*encoder, true)).collect()),
+            temp_dir: PathBuf::from("/tmp"),
+            temp_dir_error: None,
+            free_space: Some(50_000_000_000),
+            connectivity: Ok(200),
+        }
+    }
+
+    #[test]
+    fn test_parse_encoders() {
+        let listing = "Encoders:\n V..... = Video\n ------\n V....D libx264              libx264 H.264\n A....D aac                  AAC (Advanced Audio Coding)\n A....D libopus              libopus Opus\n";
+
+        let encoders = parse_encoders(listing);
+        assert!(encoders.contains(&("libx264", true)));
+        assert!(encoders.contains(&("aac", true)));
+        assert!(encoders.contains(&("libopus", true)));
+        assert!(encoders.contains(&("libvpx-vp9", false)));
+        assert!(encoders.contains(&("libmp3lame", false)));
+    }
+
+    #[test]
+    fn test_status_is_worst_check() {
+        assert_eq!(diagnostics().status(), Status::Ok);
+
+        let mut report = diagnostics();
+        report.free_space = Some(100_000_000);
+        assert_eq!(report.disk_status(), Status::Warning);
+        assert_eq!(report.status(), Status::Warning);
+
+        report.connectivity = Err("dns error".to_string());
+        assert_eq!(report.status(), Status::Failed);
+    }
+
+    #[test]
+    fn test_tools_status() {
+        let mut report = diagnostics();
+        report.tools[1].1 = None;
+        assert_eq!(report.tools_status(), Status::Warning);
+
+        report.tools[0].1 = None;
+        assert_eq!(report.tools_status(), Status::Failed);
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check_writable(dir.path()).is_ok());
+        assert!(check_writable(&dir.path().join("missing")).is_err());
+    }
+}
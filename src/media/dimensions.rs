@@ -0,0 +1,262 @@
+use super::probe::MediaProbe;
+use super::types::MediaFile;
+
+/// Largest images handed on for processing, so decompression bombs, small files that decode to
+/// gigabytes of pixels, are refused before anything decodes them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageLimits {
+    /// Most pixels an image may have, no limit when unset
+    pub max_pixels: Option<u64>,
+    /// Longest an image's width or height may be, no limit when unset
+    pub max_edge: Option<u32>,
+}
+
+impl ImageLimits {
+    /// Fails with [`ImageTooLarge`] if `file` is an image exceeding the limits. Dimensions are
+    /// read from the header of common formats and probed with ffprobe for others, files whose
+    /// dimensions can't be told are let through.
+    pub async fn check(&self, file: &MediaFile) -> Result<(), ImageTooLarge> {
+        if self.max_pixels.is_none() && self.max_edge.is_none() {
+            return Ok(());
+        }
+        let dimensions = match image_dimensions(&file.data) {
+            Some(dimensions) => Some(dimensions),
+            None if needs_probe(&file.filename) => MediaProbe::probe_data(&file.data)
+                .await
+                .ok()
+                .and_then(|probe| probe.dimensions()),
+            None => None,
+        };
+        match dimensions {
+            Some((width, height)) => self.check_dimensions(&file.filename, width, height),
+            None => Ok(()),
+        }
+    }
+
+    fn check_dimensions(
+        &self,
+        filename: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ImageTooLarge> {
+        let too_many_pixels = self
+            .max_pixels
+            .is_some_and(|max| u64::from(width) * u64::from(height) > max);
+        let too_long = self.max_edge.is_some_and(|max| width.max(height) > max);
+        if too_many_pixels || too_long {
+            return Err(ImageTooLarge {
+                filename: filename.to_string(),
+                width,
+                height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Whether `filename` is an image whose header isn't read here.
+fn needs_probe(filename: &str) -> bool {
+    let ext = filename.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+    matches!(
+        ext.as_deref(),
+        Some("tiff" | "tif" | "heic" | "heif" | "avif")
+    )
+}
+
+/// Refusal to process an image with absurd dimensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageTooLarge {
+    pub filename: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageTooLarge {
+    /// The image `error` is about, if it is one.
+    pub fn find(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<Self>())
+            .cloned()
+    }
+}
+
+impl std::fmt::Display for ImageTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Image {} is too large ({}x{})",
+            self.filename, self.width, self.height
+        )
+    }
+}
+
+impl std::error::Error for ImageTooLarge {}
+
+/// Width and height of a PNG, JPEG, GIF, WebP or BMP image, read from its header.
+pub fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    match data {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => {
+            Some((be_u32(data, 16)?, be_u32(data, 20)?))
+        }
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => {
+            Some((u32::from(le_u16(data, 6)?), u32::from(le_u16(data, 8)?)))
+        }
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => webp_dimensions(data),
+        [0xFF, 0xD8, ..] => jpeg_dimensions(data),
+        [b'B', b'M', ..] => bmp_dimensions(data),
+        _ => None,
+    }
+}
+
+/// Dimensions from the first chunk of a WebP file, which is lossy, lossless or extended.
+fn webp_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    match data.get(12..16)? {
+        // Frame tag and start code come before the 14-bit dimensions
+        b"VP8 " => Some((
+            u32::from(le_u16(data, 26)? & 0x3FFF),
+            u32::from(le_u16(data, 28)? & 0x3FFF),
+        )),
+        // A signature byte comes before the 14-bit dimensions, each stored less one
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(data.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        // Flags come before the 24-bit canvas dimensions, each stored less one
+        b"VP8X" => Some((le_u24(data, 24)? + 1, le_u24(data, 27)? + 1)),
+        _ => None,
+    }
+}
+
+/// Dimensions from the first start-of-frame segment of a JPEG file.
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    loop {
+        // Markers may be padded with any number of fill bytes
+        while *data.get(pos)? != 0xFF {
+            pos += 1;
+        }
+        while *data.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = *data.get(pos)?;
+        pos += 1;
+        match marker {
+            // Markers without a segment
+            0x01 | 0xD0..=0xD7 => continue,
+            // Image data starts without a frame having been described
+            0xD9 | 0xDA => return None,
+            // Start of frame, except the DHT, JPG and DAC segments sharing its range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = be_u16(data, pos + 3)?;
+                let width = be_u16(data, pos + 5)?;
+                return Some((u32::from(width), u32::from(height)));
+            }
+            _ => pos += usize::from(be_u16(data, pos)?),
+        }
+    }
+}
+
+/// Dimensions from the DIB header of a BMP file, whose height is negative for top-down images.
+fn bmp_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let header_size = u32::from_le_bytes(data.get(14..18)?.try_into().ok()?);
+    if header_size == 12 {
+        return Some((u32::from(le_u16(data, 18)?), u32::from(le_u16(data, 20)?)));
+    }
+    let width = i32::from_le_bytes(data.get(18..22)?.try_into().ok()?);
+    let height = i32::from_le_bytes(data.get(22..26)?.try_into().ok()?);
+    Some((width.unsigned_abs(), height.unsigned_abs()))
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        data.extend_from_slice(&13u32.to_be_bytes());
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        assert_eq!(image_dimensions(&png(640, 480)), Some((640, 480)));
+
+        let gif = [b"GIF89a".as_slice(), &[0x20, 0x03, 0x58, 0x02]].concat();
+        assert_eq!(image_dimensions(&gif), Some((800, 600)));
+
+        // APP0 segment before a baseline frame of 1920x1080
+        let jpeg = [
+            [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00].as_slice(),
+            &[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x04, 0x38, 0x07, 0x80, 0x03],
+        ]
+        .concat();
+        assert_eq!(image_dimensions(&jpeg), Some((1920, 1080)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(&[0x7F, 0x0C, 0x00, 0x37, 0x09, 0x00]);
+        assert_eq!(image_dimensions(&webp), Some((3200, 2360)));
+
+        let mut bmp = vec![b'B', b'M'];
+        bmp.extend_from_slice(&[0; 12]);
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&100i32.to_le_bytes());
+        bmp.extend_from_slice(&(-50i32).to_le_bytes());
+        assert_eq!(image_dimensions(&bmp), Some((100, 50)));
+
+        assert_eq!(image_dimensions(b"\x00\x00\x00\x18ftypmp42"), None);
+        assert_eq!(image_dimensions(&png(640, 480)[..18]), None);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_decompression_bombs() {
+        let limits = ImageLimits {
+            max_pixels: Some(100_000_000),
+            max_edge: Some(30_000),
+        };
+        let file = |filename: &str, data: Vec<u8>| MediaFile {
+            filename: filename.to_string(),
+            data,
+        };
+
+        assert!(limits.check(&file("ok.png", png(4000, 3000))).await.is_ok());
+        assert_eq!(
+            limits.check(&file("bomb.png", png(50_000, 50_000))).await,
+            Err(ImageTooLarge {
+                filename: "bomb.png".to_string(),
+                width: 50_000,
+                height: 50_000,
+            })
+        );
+        assert!(limits
+            .check(&file("strip.png", png(100, 40_000)))
+            .await
+            .is_err());
+        assert!(limits.check(&file("clip.mp4", vec![0; 32])).await.is_ok());
+        assert!(ImageLimits::default()
+            .check(&file("bomb.png", png(50_000, 50_000)))
+            .await
+            .is_ok());
+    }
+}
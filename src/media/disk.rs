@@ -0,0 +1,91 @@
+use crate::utils::format_size;
+use std::path::Path;
+
+/// Refusal to spool or transcode media while the temp directory's disk is almost full, instead
+/// of failing halfway through writing to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LowDiskSpace {
+    pub free: u64,
+    pub required: u64,
+}
+
+impl LowDiskSpace {
+    /// The shortage `error` is about, if it is one.
+    pub fn find(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<Self>())
+            .copied()
+    }
+}
+
+impl std::fmt::Display for LowDiskSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Not enough free disk space for temporary files ({} free, {} required)",
+            format_size(self.free),
+            format_size(self.required)
+        )
+    }
+}
+
+impl std::error::Error for LowDiskSpace {}
+
+/// Fails with [`LowDiskSpace`] unless the temp directory's disk has `min_free` bytes left after
+/// writing `needed` more. Passes when the free space can't be determined.
+pub async fn check_free_space(min_free: u64, needed: u64) -> Result<(), LowDiskSpace> {
+    let required = min_free.saturating_add(needed);
+    match free_space(&std::env::temp_dir()).await {
+        Some(free) if free < required => Err(LowDiskSpace { free, required }),
+        _ => Ok(()),
+    }
+}
+
+/// Free bytes on the file system of `path`, as `df` reports them.
+pub async fn free_space(path: &Path) -> Option<u64> {
+    let output = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Available bytes from POSIX `df -Pk` output, a header and a line of
+/// "filesystem 1024-blocks used available capacity mountpoint".
+fn parse_df(output: &str) -> Option<u64> {
+    let kilobytes: u64 = output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/sda1        102400000  51200000  48828125      52% /\n";
+        assert_eq!(parse_df(output), Some(50_000_000_000));
+        assert_eq!(parse_df("Filesystem 1024-blocks Used Available\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_free_space() {
+        assert!(check_free_space(0, 0).await.is_ok());
+
+        let shortage = check_free_space(u64::MAX, 0).await.unwrap_err();
+        assert_eq!(shortage.required, u64::MAX);
+        let error = anyhow::Error::new(shortage).context("Failed to download media");
+        assert_eq!(LowDiskSpace::find(&error), Some(shortage));
+    }
+}
@@ -1,5 +1,5 @@
-use super::types::MediaInfo;
-use anyhow::Result;
+use super::types::{DownloadRequest, MediaInfo, MediaMetadata};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 
 #[async_trait]
@@ -7,8 +7,41 @@ pub trait Downloader: Send + Sync {
     /// Human-readable name of the downloader
     fn name(&self) -> &'static str;
 
-    /// Download media from the given URL
-    async fn download(&self, url: &str) -> Result<MediaInfo>;
+    /// Download media as described by the request
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo>;
+
+    /// Whether requests for a single chapter are supported; others are skipped for them
+    fn supports_chapters(&self) -> bool {
+        false
+    }
+
+    /// Whether metadata can be extracted without downloading; others are skipped for `/info`
+    fn supports_metadata(&self) -> bool {
+        false
+    }
+
+    /// Extract the metadata of the requested media without downloading it
+    async fn metadata(&self, _req: &DownloadRequest) -> Result<MediaMetadata> {
+        Err(anyhow!(
+            "{} cannot extract metadata without downloading",
+            self.name()
+        ))
+    }
+
+    /// Whether this downloader can handle the URL at all; others are skipped without an attempt
+    fn supports_url(&self, _url: &str) -> bool {
+        true
+    }
+
+    /// Whether this downloader tries links of any site, as opposed to handling specific ones
+    fn is_general_purpose(&self) -> bool {
+        false
+    }
+
+    /// Drops metadata cached between jobs once the bot is idle, returning how many links had some
+    fn clear_cache(&self) -> usize {
+        0
+    }
 
     /// Test if this downloader is available on the system
     async fn test_availability() -> bool
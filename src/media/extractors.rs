@@ -0,0 +1,216 @@
+use super::bootstrap::{program, Tool};
+use super::process;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Hosts whose links yt-dlp handles under an extractor named differently.
+const EXTRACTOR_ALIASES: &[(&str, &str)] = &[
+    ("youtu.be", "youtube"),
+    ("x.com", "twitter"),
+    ("redd.it", "reddit"),
+    ("fb.watch", "facebook"),
+    ("instagr.am", "instagram"),
+];
+
+/// Sites the general-purpose downloaders have extractors for, matched by domain so links no
+/// extractor handles are turned away without starting a subprocess.
+#[derive(Debug, Default)]
+pub struct SupportedSites {
+    /// Extractor names of yt-dlp, matched against the labels of a host
+    extractors: HashSet<String>,
+    /// Domains of gallery-dl's example URLs, also matching their subdomains
+    domains: HashSet<String>,
+}
+
+impl SupportedSites {
+    /// Lists the extractors of yt-dlp and gallery-dl, failing only if neither could be listed.
+    pub async fn load() -> Result<Self> {
+        let yt_dlp = list_extractors(Tool::YtDlp).await;
+        let gallery_dl = list_extractors(Tool::GalleryDl).await;
+        if let (Err(e), Err(_)) = (&yt_dlp, &gallery_dl) {
+            return Err(anyhow!("Failed to list supported sites: {e}"));
+        }
+
+        let sites = Self {
+            extractors: yt_dlp
+                .map(|list| parse_yt_dlp_extractors(&list))
+                .unwrap_or_else(|e| {
+                    warn!("Failed to list yt-dlp extractors: {}", e);
+                    HashSet::new()
+                }),
+            domains: gallery_dl
+                .map(|list| parse_gallery_dl_extractors(&list))
+                .unwrap_or_else(|e| {
+                    warn!("Failed to list gallery-dl extractors: {}", e);
+                    HashSet::new()
+                }),
+        };
+        info!(
+            "Loaded {} yt-dlp extractors and {} gallery-dl domains",
+            sites.extractors.len(),
+            sites.domains.len()
+        );
+        Ok(sites)
+    }
+
+    /// Whether an extractor exists for the host of `url`.
+    pub fn matches(&self, url: &str) -> bool {
+        let Some(host) = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_lowercase))
+        else {
+            return false;
+        };
+
+        if self
+            .domains
+            .iter()
+            .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+        {
+            return true;
+        }
+
+        let alias = EXTRACTOR_ALIASES
+            .iter()
+            .find(|(domain, _)| host == *domain || host.ends_with(&format!(".{domain}")))
+            .map(|(_, extractor)| *extractor);
+        // The top-level domain never names a site
+        let mut labels = host.split('.').rev().skip(1);
+        alias.is_some_and(|extractor| self.extractors.contains(extractor))
+            || labels.any(|label| self.extractors.contains(label))
+    }
+}
+
+async fn list_extractors(tool: Tool) -> Result<String> {
+    let output = tokio::time::timeout(
+        Duration::from_secs(30),
+        process::output(Command::new(program(tool)).arg("--list-extractors")),
+    )
+    .await
+    .with_context(|| format!("Listing {} extractors timed out", tool.name()))?
+    .with_context(|| format!("Failed to run {}", tool.name()))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} --list-extractors failed: {}",
+            tool.name(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Site names of yt-dlp's `--list-extractors` output, such as "youtube" for "youtube:tab".
+fn parse_yt_dlp_extractors(list: &str) -> HashSet<String> {
+    list.lines()
+        .filter(|line| !line.contains("(CURRENTLY BROKEN)"))
+        .filter_map(|line| line.split([':', ' ']).next())
+        .map(|name| name.trim().to_lowercase())
+        // The generic extractor would match every site
+        .filter(|name| !name.is_empty() && name != "generic")
+        .collect()
+}
+
+/// Domains of the example URLs in gallery-dl's `--list-extractors` output.
+fn parse_gallery_dl_extractors(list: &str) -> HashSet<String> {
+    list.lines()
+        .filter_map(|line| line.strip_prefix("Example")?.trim_start().strip_prefix(':'))
+        .filter_map(|example| {
+            // Parsing lowercases the host, which hides placeholders
+            let (_, rest) = example.trim().split_once("://")?;
+            let host = rest.split(['/', '?', '#', ':']).next()?;
+            url::Url::parse(&format!("https://{host}")).ok()?;
+            // Placeholder subdomains such as "USER.tumblr.com" stand for any subdomain
+            let host = match host.split_once('.') {
+                Some((label, rest))
+                    if label.chars().all(|c| c.is_ascii_uppercase()) && rest.contains('.') =>
+                {
+                    rest
+                }
+                _ => host,
+            };
+            let host = host.to_lowercase();
+            Some(
+                host.strip_prefix("www.")
+                    .map(str::to_string)
+                    .unwrap_or(host),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YT_DLP_LIST: &str = "\
+generic
+Instagram
+instagram:story
+twitter:broadcast
+vimeo (CURRENTLY BROKEN)
+youtube
+youtube:tab
+";
+
+    const GALLERY_DL_LIST: &str = "\
+DanbooruPostExtractor
+Extractor for single danbooru posts
+Category: danbooru - Subcategory: post
+Example : https://danbooru.donmai.us/posts/12345
+
+TumblrUserExtractor
+Extractor for a Tumblr user's posts
+Category: tumblr - Subcategory: user
+Example : https://www.tumblr.com/BLOG
+
+TumblrPostExtractor
+Extractor for a single Tumblr post
+Category: tumblr - Subcategory: post
+Example : https://BLOG.tumblr.com/post/12345
+";
+
+    fn sites() -> SupportedSites {
+        SupportedSites {
+            extractors: parse_yt_dlp_extractors(YT_DLP_LIST),
+            domains: parse_gallery_dl_extractors(GALLERY_DL_LIST),
+        }
+    }
+
+    #[test]
+    fn test_parse_yt_dlp_extractors() {
+        let extractors = parse_yt_dlp_extractors(YT_DLP_LIST);
+        let mut names: Vec<_> = extractors.iter().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, vec!["instagram", "twitter", "youtube"]);
+    }
+
+    #[test]
+    fn test_parse_gallery_dl_extractors() {
+        let domains = parse_gallery_dl_extractors(GALLERY_DL_LIST);
+        let mut domains: Vec<_> = domains.iter().map(String::as_str).collect();
+        domains.sort();
+        assert_eq!(domains, vec!["danbooru.donmai.us", "tumblr.com"]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let sites = sites();
+        assert!(sites.matches("https://www.youtube.com/watch?v=1"));
+        assert!(sites.matches("https://m.youtube.com/watch?v=1"));
+        assert!(sites.matches("https://youtu.be/1"));
+        assert!(sites.matches("https://x.com/user/status/1"));
+        assert!(sites.matches("https://www.instagram.com/p/abc/"));
+        assert!(sites.matches("https://someone.tumblr.com/post/1"));
+        assert!(sites.matches("https://danbooru.donmai.us/posts/1"));
+
+        assert!(!sites.matches("https://vimeo.com/1"));
+        assert!(!sites.matches("https://example.com/video.mp4"));
+        // The top-level domain is not a site
+        assert!(!sites.matches("https://example.youtube/"));
+        assert!(!sites.matches("not a url"));
+    }
+}
@@ -0,0 +1,269 @@
+use super::{
+    bootstrap::{program, Tool},
+    http, process,
+    timeouts::{Stage, Timeouts},
+};
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use std::sync::LazyLock;
+use tokio::process::Command;
+use tracing::debug;
+
+/// Entries looked at per poll, newest first. Anything older was either seen already or posted
+/// while the bot was away for long enough that reposting it would flood the channel.
+const MAX_ENTRIES: usize = 20;
+
+static ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(item|entry)\b.*?</(?:item|entry)>").unwrap());
+static ATOM_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<link\b[^>]*?\bhref\s*=\s*["']([^"']+)["'][^>]*>"#).unwrap()
+});
+static RSS_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<link\s*>(.*?)</link>").unwrap());
+static ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(?:guid|id)\b[^>]*>(.*?)</(?:guid|id)>").unwrap());
+
+/// A post of a creator's feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEntry {
+    /// Stable id of the post, its link if the feed has none
+    pub id: String,
+    pub url: String,
+}
+
+/// How a creator's posts are listed, told apart by the link to the creator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Feed {
+    /// RSS or Atom feed
+    Syndication(String),
+    /// Posts of a Reddit user or subreddit, through Reddit's RSS feeds
+    Reddit(String),
+    /// Channel or playlist listed by yt-dlp
+    Playlist(String),
+}
+
+impl Feed {
+    pub fn detect(url: &str) -> Result<Self> {
+        let parsed = url::Url::parse(url).context("Invalid feed URL")?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(anyhow!("Unsupported feed URL: {}", url));
+        }
+        if let Some(feed) = reddit_feed(&parsed) {
+            return Ok(Self::Reddit(feed));
+        }
+        let path = parsed.path().trim_end_matches('/').to_ascii_lowercase();
+        let syndication = [".rss", ".xml", ".atom", "/feed", "/rss", "/atom"]
+            .iter()
+            .any(|suffix| path.ends_with(suffix));
+        Ok(if syndication {
+            Self::Syndication(url.to_string())
+        } else {
+            Self::Playlist(url.to_string())
+        })
+    }
+
+    /// Latest entries of the feed, newest first, failing once reading them takes longer than
+    /// an extraction may.
+    pub async fn entries(&self, timeouts: &Timeouts) -> Result<Vec<FeedEntry>> {
+        match self {
+            Self::Syndication(url) | Self::Reddit(url) => {
+                timeouts
+                    .defaults
+                    .run(Stage::Extraction, syndication_entries(url))
+                    .await?
+            }
+            Self::Playlist(url) => {
+                timeouts
+                    .for_downloader("yt-dlp")
+                    .run(Stage::Extraction, playlist_entries(url))
+                    .await?
+            }
+        }
+    }
+}
+
+/// RSS feed of the posts of a Reddit user or subreddit link.
+fn reddit_feed(url: &url::Url) -> Option<String> {
+    let host = url.host_str()?;
+    if host != "reddit.com" && !host.ends_with(".reddit.com") {
+        return None;
+    }
+    let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+    match (segments.next()?, segments.next()?) {
+        ("user" | "u", name) => Some(format!(
+            "https://www.reddit.com/user/{}/submitted/.rss",
+            name
+        )),
+        ("r", name) => Some(format!("https://www.reddit.com/r/{}/new/.rss", name)),
+        _ => None,
+    }
+}
+
+async fn syndication_entries(url: &str) -> Result<Vec<FeedEntry>> {
+    debug!("Fetching feed: {}", url);
+    let body = http::get(url)
+        .send()
+        .await
+        .context("Failed to fetch feed")?
+        .error_for_status()
+        .context("Failed to fetch feed")?
+        .text()
+        .await
+        .context("Failed to read feed")?;
+    Ok(parse_syndication(&body))
+}
+
+/// Entries of an RSS or Atom document. A full XML parser would be overkill for the two fields
+/// needed, and feeds in the wild are rarely valid XML anyway.
+fn parse_syndication(xml: &str) -> Vec<FeedEntry> {
+    ITEM.captures_iter(xml)
+        .filter_map(|item| {
+            let item = item.get(0)?.as_str();
+            let url = ATOM_LINK
+                .captures(item)
+                .or_else(|| RSS_LINK.captures(item))
+                .map(|link| unescape(&link[1]))
+                .filter(|url| url.starts_with("http"))?;
+            let id = ID
+                .captures(item)
+                .map(|id| unescape(&id[1]))
+                .filter(|id| !id.is_empty())
+                .unwrap_or_else(|| url.clone());
+            Some(FeedEntry { id, url })
+        })
+        .take(MAX_ENTRIES)
+        .collect()
+}
+
+/// Text of an XML element, without a CDATA wrapper and with the common entities resolved.
+fn unescape(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+        .unwrap_or(text);
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+async fn playlist_entries(url: &str) -> Result<Vec<FeedEntry>> {
+    debug!("Listing playlist with yt-dlp: {}", url);
+    let output = process::output(
+        Command::new(program(Tool::YtDlp))
+            .arg("--flat-playlist")
+            .arg("--playlist-end")
+            .arg(MAX_ENTRIES.to_string())
+            .arg("--print")
+            .arg("%(id)s %(webpage_url,url)s")
+            .arg("--no-warnings")
+            .args(http::user_agent_args())
+            .arg(url),
+    )
+    .await
+    .context("Failed to run yt-dlp")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Listing playlist failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(parse_playlist(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Entries of yt-dlp's `<id> <url>` lines, skipping the ones missing either.
+fn parse_playlist(output: &str) -> Vec<FeedEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (id, url) = line.trim().split_once(' ')?;
+            (id != "NA" && url.starts_with("http")).then(|| FeedEntry {
+                id: id.to_string(),
+                url: url.to_string(),
+            })
+        })
+        .take(MAX_ENTRIES)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Feed::detect("https://old.reddit.com/user/someone/").unwrap(),
+            Feed::Reddit("https://www.reddit.com/user/someone/submitted/.rss".to_string())
+        );
+        assert_eq!(
+            Feed::detect("https://www.reddit.com/r/videos").unwrap(),
+            Feed::Reddit("https://www.reddit.com/r/videos/new/.rss".to_string())
+        );
+        assert_eq!(
+            Feed::detect("https://example.com/blog/feed/").unwrap(),
+            Feed::Syndication("https://example.com/blog/feed/".to_string())
+        );
+        assert_eq!(
+            Feed::detect("https://www.youtube.com/@someone/videos").unwrap(),
+            Feed::Playlist("https://www.youtube.com/@someone/videos".to_string())
+        );
+        assert!(Feed::detect("ftp://example.com/feed.xml").is_err());
+        assert!(Feed::detect("not a url").is_err());
+    }
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<rss><channel><link>https://example.com</link>
+            <item><title>Second</title><link>https://example.com/2?a=1&amp;b=2</link>
+              <guid isPermaLink="false">post-2</guid></item>
+            <item><title>First</title><link><![CDATA[https://example.com/1]]></link></item>
+        </channel></rss>"#;
+        assert_eq!(
+            parse_syndication(xml),
+            vec![
+                FeedEntry {
+                    id: "post-2".to_string(),
+                    url: "https://example.com/2?a=1&b=2".to_string(),
+                },
+                FeedEntry {
+                    id: "https://example.com/1".to_string(),
+                    url: "https://example.com/1".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let xml = r#"<feed><entry>
+            <id>t3_abc</id>
+            <link href="https://www.reddit.com/r/videos/comments/abc/clip/" />
+        </entry></feed>"#;
+        assert_eq!(
+            parse_syndication(xml),
+            vec![FeedEntry {
+                id: "t3_abc".to_string(),
+                url: "https://www.reddit.com/r/videos/comments/abc/clip/".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_playlist() {
+        let output = "abc https://www.youtube.com/watch?v=abc\nNA https://x\ndef NA\n";
+        assert_eq!(
+            parse_playlist(output),
+            vec![FeedEntry {
+                id: "abc".to_string(),
+                url: "https://www.youtube.com/watch?v=abc".to_string(),
+            }]
+        );
+    }
+}
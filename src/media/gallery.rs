@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Result};
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Zip bookkeeping per entry on top of twice the file name (local and central headers).
+const ZIP_ENTRY_OVERHEAD: u64 = 128;
+
+/// Parses a 1-based selection like "1-3,7" into sorted, deduplicated 0-based indices.
+pub fn parse_selection(selection: &str, count: usize) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+
+    for part in selection
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse::<usize>()?, end.trim().parse::<usize>()?),
+            None => {
+                let index = part.parse::<usize>()?;
+                (index, index)
+            }
+        };
+
+        if start == 0 || start > end || end > count {
+            return Err(anyhow!("Selection {part} is outside of 1-{count}"));
+        }
+        indices.extend(start - 1..end);
+    }
+
+    if indices.is_empty() {
+        return Err(anyhow!("Empty selection"));
+    }
+
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+/// A zip archive of gallery files.
+pub struct Archive {
+    pub data: Vec<u8>,
+    /// Files left out to keep the archive within the size limit
+    pub skipped: Vec<String>,
+}
+
+/// Bundles files into a single archive of at most `max_bytes`, skipping files that don't fit.
+///
+/// Entries are stored uncompressed, as media formats are compressed already.
+pub fn zip_files<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    max_bytes: u64,
+) -> Result<Archive> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut size = 22;
+    let mut skipped = Vec::new();
+
+    for (name, data) in files {
+        let entry_size = data.len() as u64 + 2 * name.len() as u64 + ZIP_ENTRY_OVERHEAD;
+        if size + entry_size > max_bytes {
+            skipped.push(name.to_string());
+            continue;
+        }
+
+        writer.start_file(name, options)?;
+        writer.write_all(data)?;
+        size += entry_size;
+    }
+
+    Ok(Archive {
+        data: writer.finish()?.into_inner(),
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("1-3,7", 10).unwrap(), vec![0, 1, 2, 6]);
+        assert_eq!(parse_selection(" 2 , 2-3 ", 3).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_parse_selection_rejects_invalid() {
+        assert!(parse_selection("0", 5).is_err());
+        assert!(parse_selection("4-2", 5).is_err());
+        assert!(parse_selection("1-6", 5).is_err());
+        assert!(parse_selection("a", 5).is_err());
+        assert!(parse_selection(",", 5).is_err());
+    }
+
+    #[test]
+    fn test_zip_files_round_trip() {
+        let archive = zip_files([("a.jpg", &b"aaaa"[..]), ("b.png", &b"bb"[..])], 10_000).unwrap();
+        assert!(archive.skipped.is_empty());
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive.data)).unwrap();
+        assert_eq!(zip.len(), 2);
+        assert_eq!(zip.by_name("b.png").unwrap().size(), 2);
+    }
+
+    #[test]
+    fn test_zip_files_skips_what_does_not_fit() {
+        let big = vec![0; 1000];
+        let archive = zip_files(
+            [
+                ("1.jpg", &big[..]),
+                ("2.jpg", &big[..]),
+                ("3.jpg", &b"x"[..]),
+            ],
+            1500,
+        )
+        .unwrap();
+
+        assert_eq!(archive.skipped, vec!["2.jpg".to_string()]);
+        assert!(archive.data.len() <= 1500);
+        assert_eq!(
+            zip::ZipArchive::new(Cursor::new(archive.data))
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
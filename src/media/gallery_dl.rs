@@ -1,17 +1,34 @@
 use super::{
+    bootstrap::{program, Tool},
     downloader::Downloader,
-    types::{MediaFile, MediaInfo, MediaMetadata},
+    gallery_record::{UrlRecord, QUEUE, URL},
+    http,
+    image::process_image,
+    metadata_cache::MetadataCache,
+    process,
+    timeouts::{Stage, StageTimeouts},
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
 use serde_json::Value;
+use std::time::Instant;
 use tracing::{debug, info, warn};
 
-pub struct GalleryDlDownloader;
+/// Most files of one gallery fetched at the same time.
+const CONCURRENT_DOWNLOADS: usize = 4;
+
+pub struct GalleryDlDownloader {
+    cache: MetadataCache,
+}
 
 impl GalleryDlDownloader {
     pub fn new() -> Self {
-        Self
+        Self {
+            cache: MetadataCache::new(),
+        }
     }
 
     /// Parses gallery-dl JSON output and extracts metadata and URLs.
@@ -28,37 +45,14 @@ impl GalleryDlDownloader {
         let mut metadata = None;
 
         for item in array {
-            let Some(item_array) = item.as_array() else {
+            let Ok(UrlRecord(URL | QUEUE, url, post)) = UrlRecord::deserialize(item) else {
                 continue;
             };
 
-            if item_array.len() != 3 {
-                continue;
+            urls.push(url);
+            if metadata.is_none() {
+                metadata = Some(post.metadata(post.extension()));
             }
-
-            let (Some(url_val), Some(meta)) = (item_array.get(1), item_array.get(2)) else {
-                continue;
-            };
-
-            let Some(url_str) = url_val.as_str() else {
-                continue;
-            };
-
-            urls.push(url_str.to_string());
-
-            if metadata.is_some() {
-                continue;
-            }
-
-            metadata = Some(MediaMetadata {
-                title: extract_title(meta),
-                id: extract_id(meta),
-                thumbnail: None,
-                duration: None,
-                author: extract_author(meta),
-                likes: extract_likes(meta),
-                format_ext: extract_extension(meta),
-            });
         }
 
         let metadata = metadata.ok_or_else(|| anyhow::anyhow!("No media metadata found"))?;
@@ -80,22 +74,35 @@ impl GalleryDlDownloader {
         Ok((metadata, urls))
     }
 
-    async fn extract_metadata_and_urls(&self, url: &str) -> Result<(MediaMetadata, Vec<String>)> {
+    async fn extract_metadata_and_urls(
+        &self,
+        url: &str,
+        timeouts: &StageTimeouts,
+    ) -> Result<(MediaMetadata, Vec<String>)> {
+        if let Some(json_str) = self.cache.get(url, Instant::now()) {
+            debug!("Using cached gallery-dl metadata for: {}", url);
+            let json_array: Value =
+                serde_json::from_str(&json_str).context("Failed to parse media metadata")?;
+            return Self::parse_json(&json_array);
+        }
+
         debug!(
             "Extracting metadata with gallery-dl (resolving redirects) for: {}",
             url
         );
 
-        let output = tokio::time::timeout(
-            std::time::Duration::from_secs(30),
-            tokio::process::Command::new("gallery-dl")
-                .arg("--resolve-json")
-                .arg(url)
-                .output(),
-        )
-        .await
-        .context("Media metadata extraction timed out")?
-        .context("Failed to extract media metadata")?;
+        let output = timeouts
+            .run(
+                Stage::Extraction,
+                process::output(
+                    tokio::process::Command::new(program(Tool::GalleryDl))
+                        .arg("--resolve-json")
+                        .args(http::user_agent_args())
+                        .arg(url),
+                ),
+            )
+            .await?
+            .context("Failed to extract media metadata")?;
 
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
@@ -110,6 +117,8 @@ impl GalleryDlDownloader {
 
         let json_array: Value =
             serde_json::from_str(&json_str).context("Failed to parse media metadata")?;
+        self.cache
+            .insert(url, json_str.into_owned(), Instant::now());
 
         Self::parse_json(&json_array)
     }
@@ -122,13 +131,7 @@ impl GalleryDlDownloader {
     ) -> Result<MediaFile> {
         debug!("Downloading URL to memory: {}", url);
 
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(60))
-            .build()
-            .context("Failed to create HTTP client")?;
-
-        let response = client
-            .get(url)
+        let response = http::get(url)
             .send()
             .await
             .context("Failed to fetch media URL")?;
@@ -156,60 +159,36 @@ impl GalleryDlDownloader {
     }
 }
 
-fn extract_author(meta: &Value) -> Option<String> {
-    if let Some(author_obj) = meta["author"].as_object() {
-        author_obj
-            .get("nick")
-            .and_then(|v| v.as_str())
-            .or_else(|| author_obj.get("name").and_then(|v| v.as_str()))
-            .map(|s| s.to_string())
-    } else {
-        meta["author"]
-            .as_str()
-            .or(meta["uploader"].as_str())
-            .map(|s| s.to_string())
+#[async_trait]
+impl Downloader for GalleryDlDownloader {
+    fn name(&self) -> &'static str {
+        "gallery-dl"
     }
-}
-
-fn extract_id(meta: &Value) -> String {
-    meta["tweet_id"]
-        .as_str()
-        .or(meta["id"].as_str())
-        .or(meta["filename"].as_str())
-        .unwrap_or("unknown")
-        .to_string()
-}
 
-fn extract_title(meta: &Value) -> String {
-    meta["title"]
-        .as_str()
-        .or(meta["content"].as_str())
-        .or(meta["filename"].as_str())
-        .unwrap_or("Unknown Media")
-        .to_string()
-}
+    fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
 
-fn extract_likes(meta: &Value) -> Option<u64> {
-    meta["ups"]
-        .as_u64()
-        .or(meta["score"].as_u64())
-        .or(meta["favorite_count"].as_u64())
-}
+    fn is_general_purpose(&self) -> bool {
+        true
+    }
 
-fn extract_extension(meta: &Value) -> String {
-    meta["extension"].as_str().unwrap_or("jpg").to_string()
-}
+    fn supports_metadata(&self) -> bool {
+        true
+    }
 
-#[async_trait]
-impl Downloader for GalleryDlDownloader {
-    fn name(&self) -> &'static str {
-        "gallery-dl"
+    async fn metadata(&self, req: &DownloadRequest) -> Result<MediaMetadata> {
+        let (metadata, _) = self
+            .extract_metadata_and_urls(&req.url, &req.timeouts)
+            .await?;
+        Ok(metadata)
     }
 
-    async fn download(&self, url: &str) -> Result<MediaInfo> {
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let url = req.url.as_str();
         info!("Starting gallery-dl download for: {}", url);
         debug!("Extracting metadata and URLs...");
-        let (metadata, media_urls) = self.extract_metadata_and_urls(url).await?;
+        let (metadata, media_urls) = self.extract_metadata_and_urls(url, &req.timeouts).await?;
 
         info!(
             "Downloading {} media files with gallery-dl: {}",
@@ -217,16 +196,30 @@ impl Downloader for GalleryDlDownloader {
             metadata.id
         );
 
-        let mut files = Vec::new();
-        for (index, media_url) in media_urls.iter().enumerate() {
-            match self
-                .download_url_to_memory(media_url, index, &metadata)
-                .await
-            {
-                Ok(file) => files.push(file),
-                Err(e) => warn!("Failed to download {}: {}", media_url, e),
-            }
-        }
+        let files = req
+            .timeouts
+            .run(Stage::Download, async {
+                let metadata = &metadata;
+                // Buffered keeps the files in gallery order while fetching several at once
+                stream::iter(media_urls.into_iter().enumerate())
+                    .map(|(index, media_url)| async move {
+                        match self
+                            .download_url_to_memory(&media_url, index, metadata)
+                            .await
+                        {
+                            Ok(file) => Some(process_image(file).await),
+                            Err(e) => {
+                                warn!("Failed to download {}: {}", media_url, e);
+                                None
+                            }
+                        }
+                    })
+                    .buffered(CONCURRENT_DOWNLOADS)
+                    .filter_map(|file| async move { file })
+                    .collect::<Vec<_>>()
+                    .await
+            })
+            .await?;
 
         if files.is_empty() {
             return Err(anyhow::anyhow!("Failed to download any media files"));
@@ -236,11 +229,14 @@ impl Downloader for GalleryDlDownloader {
             url: url.to_string(),
             files,
             metadata,
+            truncated_to: None,
+            timeouts: req.timeouts,
+            memory: Default::default(),
         })
     }
 
     async fn test_availability() -> bool {
-        match tokio::process::Command::new("gallery-dl")
+        match tokio::process::Command::new(program(Tool::GalleryDl))
             .arg("--version")
             .output()
             .await
@@ -450,136 +446,62 @@ mod tests {
             .contains("No media metadata found"));
     }
 
-    #[test]
-    fn test_extract_author_twitter_nested() {
-        let meta = serde_json::json!({
-            "author": {"name": "username", "nick": "Display Name"}
-        });
-        let result = extract_author(&meta);
-        assert_eq!(result, Some("Display Name".to_string()));
-    }
-
-    #[test]
-    fn test_extract_author_twitter_nested_fallback() {
-        let meta = serde_json::json!({
-            "author": {"name": "username"}
-        });
-        let result = extract_author(&meta);
-        assert_eq!(result, Some("username".to_string()));
-    }
-
-    #[test]
-    fn test_extract_author_flat_string() {
-        let meta = serde_json::json!({
-            "author": "TestAuthor"
-        });
-        let result = extract_author(&meta);
-        assert_eq!(result, Some("TestAuthor".to_string()));
-    }
-
-    #[test]
-    fn test_extract_author_uploader_fallback() {
-        let meta = serde_json::json!({
-            "uploader": "TestUploader"
-        });
-        let result = extract_author(&meta);
-        assert_eq!(result, Some("TestUploader".to_string()));
-    }
-
-    #[test]
-    fn test_extract_author_none() {
-        let meta = serde_json::json!({});
-        let result = extract_author(&meta);
-        assert!(result.is_none());
-    }
-
-    #[test]
-    fn test_extract_id_tweet_id() {
-        let meta = serde_json::json!({"tweet_id": "12345"});
-        assert_eq!(extract_id(&meta), "12345");
-    }
-
-    #[test]
-    fn test_extract_id_generic_id() {
-        let meta = serde_json::json!({"id": "abc123"});
-        assert_eq!(extract_id(&meta), "abc123");
-    }
-
-    #[test]
-    fn test_extract_id_filename_fallback() {
-        let meta = serde_json::json!({"filename": "video"});
-        assert_eq!(extract_id(&meta), "video");
-    }
-
-    #[test]
-    fn test_extract_id_unknown_fallback() {
-        let meta = serde_json::json!({});
-        assert_eq!(extract_id(&meta), "unknown");
-    }
-
-    #[test]
-    fn test_extract_title_priority() {
-        let meta = serde_json::json!({
-            "title": "Video Title",
-            "content": "Tweet content"
-        });
-        assert_eq!(extract_title(&meta), "Video Title");
-    }
-
-    #[test]
-    fn test_extract_title_content_fallback() {
-        let meta = serde_json::json!({
-            "content": "Tweet content",
-            "filename": "file"
-        });
-        assert_eq!(extract_title(&meta), "Tweet content");
+    fn fixture(name: &str) -> Value {
+        let path = format!(
+            "{}/tests/fixtures/gallery_dl/{name}.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
     }
 
     #[test]
-    fn test_extract_title_filename_fallback() {
-        let meta = serde_json::json!({"filename": "file"});
-        assert_eq!(extract_title(&meta), "file");
-    }
+    fn test_parse_reddit_fixture() {
+        let (metadata, urls) = GalleryDlDownloader::parse_json(&fixture("reddit")).unwrap();
 
-    #[test]
-    fn test_extract_title_default() {
-        let meta = serde_json::json!({});
-        assert_eq!(extract_title(&meta), "Unknown Media");
+        assert_eq!(metadata.id, "1c9x7kq");
+        assert_eq!(metadata.title, "Found this little guy on my morning hike");
+        assert_eq!(metadata.author.as_deref(), Some("trailrunner_42"));
+        assert_eq!(metadata.likes, Some(4821));
+        assert_eq!(metadata.format_ext, "jpeg");
+        assert!(!metadata.nsfw);
+        assert_eq!(urls, vec!["https://i.redd.it/8f3k2j1v6mvc1.jpeg"]);
     }
 
     #[test]
-    fn test_extract_likes_ups() {
-        let meta = serde_json::json!({"ups": 100});
-        assert_eq!(extract_likes(&meta), Some(100));
-    }
+    fn test_parse_twitter_fixture() {
+        let (metadata, urls) = GalleryDlDownloader::parse_json(&fixture("twitter")).unwrap();
 
-    #[test]
-    fn test_extract_likes_score() {
-        let meta = serde_json::json!({"score": 500});
-        assert_eq!(extract_likes(&meta), Some(500));
+        assert_eq!(metadata.id, "1781234567890123456");
+        assert!(metadata.title.starts_with("Total solar eclipse"));
+        assert_eq!(metadata.author.as_deref(), Some("NASA"));
+        assert_eq!(metadata.likes, Some(52314));
+        assert_eq!(metadata.format_ext, "jpg");
+        assert_eq!(urls.len(), 2);
+        assert!(urls[1].ends_with(".mp4?tag=12"));
     }
 
     #[test]
-    fn test_extract_likes_favorite_count() {
-        let meta = serde_json::json!({"favorite_count": 1000});
-        assert_eq!(extract_likes(&meta), Some(1000));
-    }
+    fn test_parse_instagram_fixture() {
+        let (metadata, urls) = GalleryDlDownloader::parse_json(&fixture("instagram")).unwrap();
 
-    #[test]
-    fn test_extract_likes_none() {
-        let meta = serde_json::json!({});
-        assert!(extract_likes(&meta).is_none());
+        assert_eq!(metadata.id, "C6aBcDeFgHi");
+        assert!(metadata.title.starts_with("A snow leopard"));
+        assert_eq!(metadata.author.as_deref(), Some("National Geographic"));
+        assert_eq!(metadata.likes, Some(412876));
+        assert_eq!(metadata.format_ext, "jpg");
+        assert_eq!(urls.len(), 1);
     }
 
     #[test]
-    fn test_extract_extension_jpg() {
-        let meta = serde_json::json!({"extension": "jpg"});
-        assert_eq!(extract_extension(&meta), "jpg");
-    }
+    fn test_parse_pixiv_fixture() {
+        let (metadata, urls) = GalleryDlDownloader::parse_json(&fixture("pixiv")).unwrap();
 
-    #[test]
-    fn test_extract_extension_default() {
-        let meta = serde_json::json!({});
-        assert_eq!(extract_extension(&meta), "jpg");
+        assert_eq!(metadata.id, "117654321");
+        assert_eq!(metadata.title, "夕焼けの街");
+        assert_eq!(metadata.author.as_deref(), Some("Hanako"));
+        assert_eq!(metadata.likes, Some(8342));
+        assert_eq!(metadata.format_ext, "png");
+        assert!(!metadata.nsfw);
+        assert_eq!(urls.len(), 2);
     }
 }
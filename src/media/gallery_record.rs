@@ -0,0 +1,383 @@
+use super::types::MediaMetadata;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Record kinds of gallery-dl's JSON output that carry a link.
+pub const URL: u8 = 3;
+pub const QUEUE: u8 = 6;
+
+/// A record of gallery-dl's `--dump-json` and `--resolve-json` output carrying a link:
+/// `[URL, url, metadata]` for a file, or `[QUEUE, url, metadata]` for a link queued to another
+/// extractor. Directory records with the metadata of the post alone don't match.
+#[derive(Debug, Deserialize)]
+pub struct UrlRecord(pub u8, pub String, pub Post);
+
+/// Metadata gallery-dl reports for a post. Sites name and type their fields differently, so every
+/// field is optional and accepts both strings and numbers.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Post {
+    /// Twitter's tweet id, numeric in recent gallery-dl versions
+    #[serde(deserialize_with = "lenient_string")]
+    tweet_id: Option<String>,
+    /// Instagram's shortcode, the id in its post links
+    #[serde(deserialize_with = "lenient_string")]
+    post_shortcode: Option<String>,
+    /// Reddit's base-36 id or Pixiv's numeric id
+    #[serde(deserialize_with = "lenient_string")]
+    id: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    filename: Option<String>,
+
+    #[serde(deserialize_with = "lenient_string")]
+    title: Option<String>,
+    /// Twitter's tweet text
+    #[serde(deserialize_with = "lenient_string")]
+    content: Option<String>,
+    /// Instagram's caption
+    #[serde(deserialize_with = "lenient_string")]
+    description: Option<String>,
+    /// Reddit's post text
+    #[serde(deserialize_with = "lenient_string")]
+    selftext: Option<String>,
+    /// Extractor the post comes from, e.g. "twitter"
+    #[serde(deserialize_with = "lenient_string")]
+    category: Option<String>,
+    /// Publication time, e.g. "2024-01-02 03:04:05"
+    #[serde(deserialize_with = "lenient_string")]
+    date: Option<String>,
+
+    /// A plain name, or Twitter's `{name, nick}` profile
+    #[serde(deserialize_with = "lenient_name")]
+    author: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    uploader: Option<String>,
+    /// Instagram's display name
+    #[serde(deserialize_with = "lenient_string")]
+    fullname: Option<String>,
+    #[serde(deserialize_with = "lenient_string")]
+    username: Option<String>,
+    /// Pixiv's `{name, account}` profile
+    #[serde(deserialize_with = "lenient_name")]
+    user: Option<String>,
+
+    #[serde(deserialize_with = "lenient_count")]
+    ups: Option<u64>,
+    /// Reddit's score, sometimes a float
+    #[serde(deserialize_with = "lenient_count")]
+    score: Option<u64>,
+    #[serde(deserialize_with = "lenient_count")]
+    favorite_count: Option<u64>,
+    /// Instagram's like count
+    #[serde(deserialize_with = "lenient_count")]
+    likes: Option<u64>,
+    /// Pixiv's bookmark count
+    #[serde(deserialize_with = "lenient_count")]
+    total_bookmarks: Option<u64>,
+    #[serde(deserialize_with = "lenient_count")]
+    view_count: Option<u64>,
+    /// Twitter's and Pixiv's view counts
+    #[serde(deserialize_with = "lenient_count")]
+    views: Option<u64>,
+    #[serde(deserialize_with = "lenient_count")]
+    total_view: Option<u64>,
+
+    #[serde(deserialize_with = "lenient_bool")]
+    over_18: Option<bool>,
+    #[serde(deserialize_with = "lenient_bool")]
+    sensitive: Option<bool>,
+    #[serde(deserialize_with = "lenient_count")]
+    x_restrict: Option<u64>,
+
+    #[serde(deserialize_with = "lenient_string")]
+    extension: Option<String>,
+}
+
+impl Post {
+    pub fn id(&self) -> String {
+        self.tweet_id
+            .as_deref()
+            .or(self.post_shortcode.as_deref())
+            .or(self.id.as_deref())
+            .or(self.filename.as_deref())
+            .unwrap_or("unknown")
+            .to_string()
+    }
+
+    pub fn title(&self) -> String {
+        [
+            &self.title,
+            &self.content,
+            &self.description,
+            &self.filename,
+        ]
+        .into_iter()
+        .flatten()
+        .find(|text| !text.trim().is_empty())
+        .map(String::as_str)
+        .unwrap_or("Unknown Media")
+        .to_string()
+    }
+
+    pub fn author(&self) -> Option<String> {
+        self.author
+            .as_ref()
+            .or(self.uploader.as_ref())
+            .or(self.fullname.as_ref())
+            .or(self.username.as_ref())
+            .or(self.user.as_ref())
+            .cloned()
+    }
+
+    pub fn likes(&self) -> Option<u64> {
+        self.ups
+            .or(self.score)
+            .or(self.favorite_count)
+            .or(self.likes)
+            .or(self.total_bookmarks)
+    }
+
+    /// Caption or post text, unless it is already the title.
+    pub fn description(&self) -> Option<String> {
+        let title = self.title();
+        [&self.description, &self.content, &self.selftext]
+            .into_iter()
+            .flatten()
+            .map(|text| text.trim())
+            .find(|text| !text.is_empty() && *text != title.trim())
+            .map(str::to_string)
+    }
+
+    pub fn view_count(&self) -> Option<u64> {
+        self.view_count.or(self.views).or(self.total_view)
+    }
+
+    /// Reddit's `over_18`, Twitter's `sensitive` and Pixiv's `x_restrict` flags.
+    pub fn nsfw(&self) -> bool {
+        self.over_18.unwrap_or(false)
+            || self.sensitive.unwrap_or(false)
+            || self.x_restrict.is_some_and(|level| level > 0)
+    }
+
+    pub fn extension(&self) -> String {
+        self.extension.clone().unwrap_or_else(|| "jpg".to_string())
+    }
+
+    pub fn metadata(&self, format_ext: String) -> MediaMetadata {
+        MediaMetadata {
+            title: self.title(),
+            id: self.id(),
+            thumbnail: None,
+            duration: None,
+            author: self.author(),
+            likes: self.likes(),
+            description: self.description(),
+            view_count: self.view_count(),
+            uploaded_at: self.date.as_deref().and_then(crate::utils::parse_date),
+            site: self.category.clone(),
+            format_ext,
+            chapters: Vec::new(),
+            nsfw: self.nsfw(),
+            live: false,
+            formats: Vec::new(),
+        }
+    }
+}
+
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => Some(s),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// A plain name, or the display name of a profile object.
+fn lenient_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => Some(s),
+        Value::Object(profile) => profile
+            .get("nick")
+            .or(profile.get("name"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        _ => None,
+    })
+}
+
+/// A non-negative count, whether given as an integer, a float or a string.
+fn lenient_count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(n) => n
+            .as_u64()
+            .or_else(|| n.as_f64().filter(|f| *f >= 0.0).map(|f| f as u64)),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(b) => Some(b),
+        Value::Number(n) => n.as_u64().map(|n| n > 0),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn post(meta: Value) -> Post {
+        Post::deserialize(&meta).unwrap()
+    }
+
+    #[test]
+    fn test_author_twitter_nested() {
+        let meta = json!({"author": {"name": "username", "nick": "Display Name"}});
+        assert_eq!(post(meta).author(), Some("Display Name".to_string()));
+    }
+
+    #[test]
+    fn test_author_twitter_nested_fallback() {
+        let meta = json!({"author": {"name": "username"}});
+        assert_eq!(post(meta).author(), Some("username".to_string()));
+    }
+
+    #[test]
+    fn test_author_flat_string() {
+        let meta = json!({"author": "TestAuthor"});
+        assert_eq!(post(meta).author(), Some("TestAuthor".to_string()));
+    }
+
+    #[test]
+    fn test_author_uploader_fallback() {
+        let meta = json!({"uploader": "TestUploader"});
+        assert_eq!(post(meta).author(), Some("TestUploader".to_string()));
+    }
+
+    #[test]
+    fn test_author_none() {
+        assert!(post(json!({})).author().is_none());
+    }
+
+    #[test]
+    fn test_id_tweet_id() {
+        assert_eq!(post(json!({"tweet_id": "12345"})).id(), "12345");
+        assert_eq!(
+            post(json!({"tweet_id": 1790000000000000000u64, "id": 1})).id(),
+            "1790000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_id_generic_id() {
+        assert_eq!(post(json!({"id": "abc123"})).id(), "abc123");
+        assert_eq!(post(json!({"id": 117000000})).id(), "117000000");
+    }
+
+    #[test]
+    fn test_id_filename_fallback() {
+        assert_eq!(post(json!({"filename": "video"})).id(), "video");
+    }
+
+    #[test]
+    fn test_id_unknown_fallback() {
+        assert_eq!(post(json!({})).id(), "unknown");
+    }
+
+    #[test]
+    fn test_title_priority() {
+        let meta = json!({"title": "Video Title", "content": "Tweet content"});
+        assert_eq!(post(meta).title(), "Video Title");
+    }
+
+    #[test]
+    fn test_title_content_fallback() {
+        let meta = json!({"content": "Tweet content", "filename": "file"});
+        assert_eq!(post(meta).title(), "Tweet content");
+    }
+
+    #[test]
+    fn test_title_skips_empty_text() {
+        let meta = json!({"content": "", "filename": "file"});
+        assert_eq!(post(meta).title(), "file");
+    }
+
+    #[test]
+    fn test_title_default() {
+        assert_eq!(post(json!({})).title(), "Unknown Media");
+    }
+
+    #[test]
+    fn test_likes() {
+        assert_eq!(post(json!({"ups": 100})).likes(), Some(100));
+        assert_eq!(post(json!({"score": 500})).likes(), Some(500));
+        assert_eq!(post(json!({"score": 512.0})).likes(), Some(512));
+        assert_eq!(post(json!({"score": -3})).likes(), None);
+        assert_eq!(post(json!({"favorite_count": 1000})).likes(), Some(1000));
+        assert_eq!(post(json!({"likes": "42"})).likes(), Some(42));
+        assert!(post(json!({})).likes().is_none());
+    }
+
+    #[test]
+    fn test_description_and_details() {
+        let reddit = post(json!({
+            "title": "Look at this",
+            "selftext": "Found it in the attic",
+            "category": "reddit",
+            "date": "2024-02-29 01:02:03",
+        }));
+        assert_eq!(
+            reddit.description().as_deref(),
+            Some("Found it in the attic")
+        );
+        let metadata = reddit.metadata("jpg".to_string());
+        assert_eq!(metadata.site.as_deref(), Some("reddit"));
+        assert_eq!(metadata.uploaded_at, Some(1_709_168_523));
+
+        // Tweet text is already the title
+        assert_eq!(post(json!({"content": "Tweet"})).description(), None);
+        assert_eq!(post(json!({"view_count": 7})).view_count(), Some(7));
+        assert_eq!(post(json!({"total_view": "12"})).view_count(), Some(12));
+    }
+
+    #[test]
+    fn test_nsfw() {
+        assert!(post(json!({"over_18": true})).nsfw());
+        assert!(post(json!({"sensitive": true})).nsfw());
+        assert!(post(json!({"x_restrict": 1})).nsfw());
+        assert!(!post(json!({"over_18": false})).nsfw());
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(post(json!({"extension": "png"})).extension(), "png");
+        assert_eq!(post(json!({})).extension(), "jpg");
+    }
+
+    #[test]
+    fn test_mistyped_fields_are_ignored() {
+        let meta = json!({"id": ["a"], "author": 5, "score": "many", "over_18": "yes"});
+        let post = post(meta);
+        assert_eq!(post.id(), "unknown");
+        assert_eq!(post.author(), None);
+        assert_eq!(post.likes(), None);
+        assert!(!post.nsfw());
+        assert!(Post::deserialize(&json!("not an object")).is_err());
+    }
+
+    #[test]
+    fn test_url_records() {
+        let record = |json: Value| UrlRecord::deserialize(&json);
+
+        let file = record(json!([3, "https://example.com/a.jpg", {"id": "abc"}])).unwrap();
+        assert_eq!(file.0, URL);
+        assert_eq!(file.1, "https://example.com/a.jpg");
+        assert_eq!(file.2.id(), "abc");
+
+        assert!(record(json!([2, {"id": "abc"}])).is_err());
+        assert!(record(json!([-1, null])).is_err());
+    }
+}
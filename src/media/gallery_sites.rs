@@ -0,0 +1,499 @@
+use super::{
+    bootstrap::{program, Tool},
+    downloader::Downloader,
+    gallery_record::Post,
+    http,
+    image::process_image,
+    process,
+    timeouts::Stage,
+    types::{DownloadRequest, MediaFile, MediaInfo},
+};
+use crate::config::secret::{self, SecretKey};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+use tracing::{debug, info, warn};
+
+/// Per-site gallery-dl settings, keyed by the gallery-dl extractor name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GalleryDlSite {
+    /// gallery-dl extractor, e.g. "pixiv" or "danbooru"
+    pub extractor: String,
+    /// Domains whose links go straight to gallery-dl
+    pub domains: Vec<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub api_key: Option<String>,
+    pub user_id: Option<String>,
+    pub refresh_token: Option<String>,
+    /// Cookies file of a logged-in browser session, as Instagram stories need, either plain or
+    /// encrypted as a whole with the config secret key
+    pub cookies: Option<String>,
+    /// Convert Pixiv ugoira animations to MP4
+    pub ugoira_to_video: bool,
+}
+
+/// gallery-dl extractor of stories-type links, which expire after a day and need a logged-in
+/// session.
+fn story_extractor(url: &str) -> Option<&'static str> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let path = url.path();
+
+    match host {
+        "instagram.com" if path.starts_with("/stories/") || path.starts_with("/s/") => {
+            Some("instagram")
+        }
+        "story.snapchat.com" => Some("snapchat"),
+        "snapchat.com" if path.starts_with("/add/") || path.starts_with("/@") => Some("snapchat"),
+        _ => None,
+    }
+}
+
+/// Name of a site for users, e.g. "Instagram" for the "instagram" extractor.
+fn site_name(extractor: &str) -> String {
+    let mut chars = extractor.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+impl GalleryDlSite {
+    /// Domains routed to the extractor when none are configured.
+    pub fn default_domains(extractor: &str) -> &'static [&'static str] {
+        match extractor {
+            "pixiv" => &["pixiv.net"],
+            "danbooru" => &["danbooru.donmai.us"],
+            "gelbooru" => &["gelbooru.com"],
+            "e621" => &["e621.net"],
+            "yandere" => &["yande.re"],
+            "sankaku" => &["sankakucomplex.com"],
+            "instagram" => &["instagram.com"],
+            "snapchat" => &["snapchat.com"],
+            _ => &[],
+        }
+    }
+
+    /// Whether gallery-dl can log in, by cookies or by credentials.
+    fn has_session(&self) -> bool {
+        self.cookies.is_some() || (self.username.is_some() && self.password.is_some())
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        self.domains
+            .iter()
+            .any(|domain| host == domain || host.ends_with(&format!(".{domain}")))
+    }
+
+    /// Extractor options in gallery-dl's config format.
+    fn options(&self) -> Value {
+        let mut options = Map::new();
+        let fields = [
+            ("username", &self.username),
+            ("password", &self.password),
+            ("api-key", &self.api_key),
+            ("user-id", &self.user_id),
+            ("refresh-token", &self.refresh_token),
+            ("cookies", &self.cookies),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                options.insert(name.to_string(), json!(value));
+            }
+        }
+
+        if self.ugoira_to_video {
+            options.insert("ugoira".to_string(), json!(true));
+            options.insert(
+                "postprocessors".to_string(),
+                json!([{
+                    "name": "ugoira",
+                    "extension": "mp4",
+                    "ffmpeg-args": ["-c:v", "libx264", "-pix_fmt", "yuv420p"],
+                    "libx264-prevent-odd": true,
+                    "keep-files": false
+                }]),
+            );
+        }
+
+        Value::Object(options)
+    }
+}
+
+/// gallery-dl config file content for the given sites.
+fn gallery_dl_config(sites: &[GalleryDlSite]) -> Value {
+    let extractors: Map<String, Value> = sites
+        .iter()
+        .map(|site| (site.extractor.clone(), site.options()))
+        .collect();
+
+    json!({ "extractor": extractors })
+}
+
+/// Decrypts the cookies file at `path` into a temp file only this user can read, if it is
+/// encrypted. Missing files are left for gallery-dl to report.
+fn decrypt_cookies(path: &str, key: Option<&SecretKey>) -> Result<Option<NamedTempFile>> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    if !secret::is_encrypted(content.trim()) {
+        return Ok(None);
+    }
+
+    let cookies = secret::reveal(content.trim(), key)?;
+    let mut file = NamedTempFile::with_suffix(".txt")?;
+    file.write_all(cookies.as_bytes())?;
+    file.flush()?;
+    Ok(Some(file))
+}
+
+/// Downloads links of configured sites with gallery-dl itself, so credentials and
+/// postprocessors such as ugoira conversion apply.
+pub struct GalleryDlSiteDownloader {
+    sites: Vec<GalleryDlSite>,
+    /// Generated gallery-dl config, kept private to the process as it holds credentials
+    config_file: NamedTempFile,
+    /// Decrypted copies of encrypted cookies files, private to the process like the config
+    _cookies_files: Vec<NamedTempFile>,
+}
+
+impl GalleryDlSiteDownloader {
+    pub fn new(sites: Vec<GalleryDlSite>) -> Result<Self> {
+        let key = SecretKey::from_env()?;
+        let mut cookies_files = Vec::new();
+        let mut config_sites = sites.clone();
+        for site in &mut config_sites {
+            let Some(path) = site.cookies.as_mut() else {
+                continue;
+            };
+            if let Some(file) = decrypt_cookies(path, key.as_ref()).with_context(|| {
+                format!("Failed to decrypt cookies of gallery_dl.{}", site.extractor)
+            })? {
+                *path = file.path().to_string_lossy().into_owned();
+                cookies_files.push(file);
+            }
+        }
+
+        let mut config_file = NamedTempFile::with_suffix(".json")?;
+        serde_json::to_writer(&mut config_file, &gallery_dl_config(&config_sites))?;
+        config_file.flush()?;
+
+        Ok(Self {
+            sites,
+            config_file,
+            _cookies_files: cookies_files,
+        })
+    }
+
+    fn site_for(&self, url: &str) -> Option<&GalleryDlSite> {
+        let host = url::Url::parse(url).ok()?.host_str()?.to_lowercase();
+        self.sites.iter().find(|site| site.matches(&host))
+    }
+
+    /// The configured site to download a story with, failing with an explanation when there is
+    /// no session to do it with.
+    fn story_site(&self, extractor: &str) -> Result<&GalleryDlSite> {
+        self.sites
+            .iter()
+            .find(|site| site.extractor == extractor && site.has_session())
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} stories need a logged-in session, configure cookies for \
+                     gallery_dl.{extractor}",
+                    site_name(extractor)
+                )
+            })
+    }
+}
+
+/// Contents of a gallery-dl download directory.
+struct DownloadDir {
+    /// Metadata written for the first file
+    metadata: Option<Post>,
+    /// Media files as (lowercase extension, data), in file name order
+    files: Vec<(String, Vec<u8>)>,
+}
+
+fn read_download_dir(dir: &Path) -> Result<DownloadDir> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut metadata = None;
+    let mut files = Vec::new();
+    for path in paths {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string();
+
+        if name.ends_with(".json") {
+            if metadata.is_none() {
+                metadata = serde_json::from_slice(&std::fs::read(&path)?).ok();
+            }
+            continue;
+        }
+
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("bin")
+            .to_lowercase();
+        files.push((ext, std::fs::read(&path)?));
+    }
+
+    Ok(DownloadDir { metadata, files })
+}
+
+#[async_trait]
+impl Downloader for GalleryDlSiteDownloader {
+    fn name(&self) -> &'static str {
+        "gallery-dl (site)"
+    }
+
+    /// Stories are claimed even without a configured site, so a missing session is reported.
+    fn supports_url(&self, url: &str) -> bool {
+        self.site_for(url).is_some() || story_extractor(url).is_some()
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let url = req.url.as_str();
+        let story = story_extractor(url);
+        let site = match story {
+            Some(extractor) => self.story_site(extractor)?,
+            None => self
+                .site_for(url)
+                .ok_or_else(|| anyhow!("No gallery-dl site configured for this URL"))?,
+        };
+        info!("Downloading with gallery-dl ({}): {}", site.extractor, url);
+
+        let dir = tempfile::tempdir()?;
+        // gallery-dl looks the post up and fetches its files in the same run
+        let output = req
+            .timeouts
+            .extracting_while_downloading()
+            .run(
+                Stage::Download,
+                process::output(
+                    tokio::process::Command::new(program(Tool::GalleryDl))
+                        .arg("--config")
+                        .arg(self.config_file.path())
+                        .arg("--write-metadata")
+                        .args(http::user_agent_args())
+                        .arg("-D")
+                        .arg(dir.path())
+                        .arg(url),
+                ),
+            )
+            .await?
+            .context("Failed to run gallery-dl")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            // Sites redirect to their login page once the session cookies expire
+            if story.is_some() && error.to_lowercase().contains("login") {
+                return Err(anyhow!(
+                    "{} stories need a logged-in session, the configured one has expired: {}",
+                    site_name(&site.extractor),
+                    error
+                ));
+            }
+            return Err(anyhow!("Media download failed: {}", error));
+        }
+
+        let DownloadDir {
+            metadata: meta,
+            files: downloaded,
+        } = read_download_dir(dir.path())?;
+        if downloaded.is_empty() {
+            return Err(anyhow!("No media found for this URL"));
+        }
+        debug!("gallery-dl downloaded {} files", downloaded.len());
+
+        let metadata = meta.unwrap_or_default().metadata(downloaded[0].0.clone());
+
+        let mut files = Vec::new();
+        for (index, (ext, data)) in downloaded.into_iter().enumerate() {
+            let filename = if index == 0 {
+                format!("{}.{}", metadata.id, ext)
+            } else {
+                format!("{}_{}.{}", metadata.id, index + 1, ext)
+            };
+            files.push(process_image(MediaFile { filename, data }).await);
+        }
+
+        Ok(MediaInfo {
+            url: url.to_string(),
+            files,
+            metadata,
+            truncated_to: None,
+            timeouts: req.timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    async fn test_availability() -> bool {
+        let available = super::gallery_dl::GalleryDlDownloader::test_availability().await;
+        if !available {
+            warn!("❌ gallery-dl is required for configured gallery-dl sites");
+        }
+        available
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixiv() -> GalleryDlSite {
+        GalleryDlSite {
+            extractor: "pixiv".to_string(),
+            domains: vec!["pixiv.net".to_string()],
+            refresh_token: Some("token".to_string()),
+            ugoira_to_video: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gallery_dl_config() {
+        let danbooru = GalleryDlSite {
+            extractor: "danbooru".to_string(),
+            username: Some("user".to_string()),
+            api_key: Some("key".to_string()),
+            ..Default::default()
+        };
+        let config = gallery_dl_config(&[pixiv(), danbooru]);
+
+        assert_eq!(config["extractor"]["pixiv"]["refresh-token"], "token");
+        assert_eq!(config["extractor"]["pixiv"]["ugoira"], true);
+        assert_eq!(
+            config["extractor"]["pixiv"]["postprocessors"][0]["name"],
+            "ugoira"
+        );
+        assert_eq!(
+            config["extractor"]["danbooru"],
+            json!({ "username": "user", "api-key": "key" })
+        );
+    }
+
+    #[test]
+    fn test_site_routing() {
+        let downloader = GalleryDlSiteDownloader::new(vec![pixiv()]).unwrap();
+        assert!(downloader.supports_url("https://www.pixiv.net/en/artworks/123"));
+        assert!(downloader.supports_url("https://pixiv.net/artworks/123"));
+        assert!(!downloader.supports_url("https://notpixiv.net/artworks/123"));
+        assert!(!downloader.supports_url("https://danbooru.donmai.us/posts/1"));
+    }
+
+    #[test]
+    fn test_story_routing() {
+        let instagram = GalleryDlSite {
+            extractor: "instagram".to_string(),
+            domains: vec!["instagram.com".to_string()],
+            cookies: Some("/run/secrets/instagram-cookies.txt".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            gallery_dl_config(std::slice::from_ref(&instagram))["extractor"]["instagram"]
+                ["cookies"],
+            "/run/secrets/instagram-cookies.txt"
+        );
+
+        let downloader = GalleryDlSiteDownloader::new(vec![pixiv(), instagram]).unwrap();
+        assert!(downloader.supports_url("https://www.instagram.com/stories/someone/3300000000/"));
+        assert!(downloader.supports_url("https://www.snapchat.com/add/someone"));
+        assert!(downloader.supports_url("https://story.snapchat.com/p/abc"));
+        assert!(!downloader.supports_url("https://www.snapchat.com/spotlight/abc"));
+        assert!(downloader.story_site("instagram").is_ok());
+
+        let error = downloader.story_site("snapchat").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Snapchat stories need a logged-in session, configure cookies for gallery_dl.snapchat"
+        );
+
+        // Credentials alone don't make a session without a password
+        let downloader = GalleryDlSiteDownloader::new(vec![GalleryDlSite {
+            extractor: "instagram".to_string(),
+            username: Some("someone".to_string()),
+            ..Default::default()
+        }])
+        .unwrap();
+        assert!(downloader.story_site("instagram").is_err());
+    }
+
+    #[test]
+    fn test_decrypt_cookies() {
+        let key = SecretKey::from_base64(&SecretKey::generate().unwrap()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let plain = dir.path().join("plain.txt");
+        std::fs::write(&plain, "# Netscape HTTP Cookie File").unwrap();
+        assert!(decrypt_cookies(plain.to_str().unwrap(), Some(&key))
+            .unwrap()
+            .is_none());
+
+        let encrypted = dir.path().join("encrypted.txt");
+        let cookies = "# Netscape HTTP Cookie File\n.instagram.com\tTRUE\t/\tTRUE\t0\tsessionid\tx";
+        std::fs::write(&encrypted, format!("{}\n", key.encrypt(cookies).unwrap())).unwrap();
+        let path = encrypted.to_str().unwrap();
+        assert!(decrypt_cookies(path, None).is_err());
+
+        let file = decrypt_cookies(path, Some(&key)).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(file.path()).unwrap(), cookies);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = file.as_file().metadata().unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(decrypt_cookies("/nonexistent/cookies.txt", None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_story_read_download_dir_keeps_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("3300000001.jpg"), b"image").unwrap();
+        std::fs::write(dir.path().join("3300000001.jpg.json"), r#"{"id": 1}"#).unwrap();
+        std::fs::write(dir.path().join("3300000002.mp4"), b"video").unwrap();
+        std::fs::write(dir.path().join("3300000002.mp4.json"), r#"{"id": 2}"#).unwrap();
+
+        let downloaded = read_download_dir(dir.path()).unwrap();
+        let extensions: Vec<_> = downloaded
+            .files
+            .iter()
+            .map(|(ext, _)| ext.as_str())
+            .collect();
+        assert_eq!(extensions, vec!["jpg", "mp4"]);
+    }
+
+    #[test]
+    fn test_read_download_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("123_p0.png"), b"image").unwrap();
+        std::fs::write(dir.path().join("123_p0.png.json"), r#"{"id": 123}"#).unwrap();
+        std::fs::write(dir.path().join("123_ugoira.MP4"), b"video").unwrap();
+
+        let downloaded = read_download_dir(dir.path()).unwrap();
+        assert_eq!(downloaded.metadata.unwrap().id(), "123");
+        assert_eq!(
+            downloaded.files,
+            vec![
+                ("png".to_string(), b"image".to_vec()),
+                ("mp4".to_string(), b"video".to_vec())
+            ]
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use super::{
+    downloader::Downloader,
+    http,
+    section::DurationLimit,
+    timeouts::{Stage, StageTimeouts},
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
+    utils::{mux_to_mp4, remux_ts_to_mp4},
+    ytdlp::LIVE_STREAM_ERROR,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use reqwest::header::HeaderMap;
+use tracing::{debug, info};
+use url::Url;
+
+/// Most segments of a stream fetched at the same time.
+const CONCURRENT_SEGMENTS: usize = 8;
+
+/// An HLS playlist.
+#[derive(Debug, PartialEq)]
+enum Playlist {
+    /// Renditions of the stream at different bitrates, with the audio tracks some of them play
+    Master {
+        variants: Vec<Variant>,
+        audio: Vec<AudioRendition>,
+    },
+    /// Segments of a single rendition
+    Media(MediaPlaylist),
+}
+
+#[derive(Debug, PartialEq)]
+struct Variant {
+    bandwidth: u64,
+    url: Url,
+    /// Group of the audio renditions played along, when the variant carries no audio itself
+    audio_group: Option<String>,
+}
+
+/// Audio track of a master playlist, kept in a media playlist of its own.
+#[derive(Debug, PartialEq)]
+struct AudioRendition {
+    group: String,
+    url: Url,
+    /// Played when the viewer doesn't pick a track
+    default: bool,
+}
+
+/// The audio track played along with `variant`, the group's default one if it has several.
+fn audio_for<'a>(variant: &Variant, audio: &'a [AudioRendition]) -> Option<&'a AudioRendition> {
+    let group = variant.audio_group.as_deref()?;
+    let mut renditions = audio.iter().filter(|rendition| rendition.group == group);
+    let first = renditions.clone().next()?;
+    Some(
+        renditions
+            .find(|rendition| rendition.default)
+            .unwrap_or(first),
+    )
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct MediaPlaylist {
+    /// fMP4 initialization section preceding the segments
+    init: Option<Url>,
+    /// Segments with their length in seconds
+    segments: Vec<(Url, f64)>,
+    /// Not ended yet, so more segments are still to come
+    live: bool,
+}
+
+impl MediaPlaylist {
+    fn duration(&self) -> f64 {
+        self.segments.iter().map(|(_, secs)| secs).sum()
+    }
+
+    /// Drops the segments past `secs`.
+    fn truncate(&mut self, secs: u64) {
+        let mut elapsed = 0.0;
+        self.segments.retain(|(_, length)| {
+            let keep = elapsed < secs as f64;
+            elapsed += length;
+            keep
+        });
+    }
+}
+
+/// Value of `name` in an attribute list such as `BANDWIDTH=1280000,CODECS="avc1,mp4a"`.
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                let next = quoted[end + 1..].trim_start_matches(',');
+                (&quoted[..end], next)
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        if key.trim() == name {
+            return Some(value);
+        }
+        rest = next;
+    }
+    None
+}
+
+fn parse_playlist(base: &Url, text: &str) -> Result<Playlist> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if lines.next() != Some("#EXTM3U") {
+        return Err(anyhow!("Not an HLS playlist"));
+    }
+
+    let mut variants = Vec::new();
+    let mut audio = Vec::new();
+    let mut playlist = MediaPlaylist {
+        live: true,
+        ..Default::default()
+    };
+    let mut pending_variant = None;
+    let mut pending_length = 0.0;
+
+    for line in lines {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            pending_variant = Some((
+                attribute(attributes, "BANDWIDTH")
+                    .and_then(|bandwidth| bandwidth.parse().ok())
+                    .unwrap_or_default(),
+                attribute(attributes, "AUDIO").map(str::to_string),
+            ));
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MEDIA:") {
+            // Renditions without a URI are carried in the variants themselves
+            let uri = attribute(attributes, "URI");
+            let group = attribute(attributes, "GROUP-ID");
+            if let (Some("AUDIO"), Some(uri), Some(group)) =
+                (attribute(attributes, "TYPE"), uri, group)
+            {
+                audio.push(AudioRendition {
+                    group: group.to_string(),
+                    url: base.join(uri)?,
+                    default: attribute(attributes, "DEFAULT") == Some("YES"),
+                });
+            }
+        } else if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let length = info.split(',').next().unwrap_or_default();
+            pending_length = length.trim().parse().unwrap_or_default();
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            if attribute(attributes, "METHOD") != Some("NONE") {
+                return Err(anyhow!("Encrypted HLS streams are not supported"));
+            }
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+            if attribute(attributes, "BYTERANGE").is_some() {
+                return Err(anyhow!("HLS byte ranges are not supported"));
+            }
+            let uri = attribute(attributes, "URI").context("HLS map without a URI")?;
+            playlist.init = Some(base.join(uri)?);
+        } else if line.starts_with("#EXT-X-BYTERANGE") {
+            return Err(anyhow!("HLS byte ranges are not supported"));
+        } else if line == "#EXT-X-ENDLIST" {
+            playlist.live = false;
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some((bandwidth, audio_group)) = pending_variant.take() {
+            variants.push(Variant {
+                bandwidth,
+                url: base.join(line)?,
+                audio_group,
+            });
+        } else {
+            playlist
+                .segments
+                .push((base.join(line)?, std::mem::take(&mut pending_length)));
+        }
+    }
+
+    if !variants.is_empty() {
+        Ok(Playlist::Master { variants, audio })
+    } else if playlist.segments.is_empty() {
+        Err(anyhow!("HLS playlist has no segments"))
+    } else {
+        Ok(Playlist::Media(playlist))
+    }
+}
+
+async fn fetch(url: &Url, headers: &HeaderMap) -> Result<reqwest::Response> {
+    let response = http::get(url.as_str())
+        .headers(headers.clone())
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {url}"))?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to fetch HLS stream: HTTP {}",
+            response.status()
+        ));
+    }
+    Ok(response)
+}
+
+/// Segments of a stream's video, along with those of its audio when that is a separate track.
+#[derive(Debug)]
+struct Stream {
+    video: MediaPlaylist,
+    audio: Option<MediaPlaylist>,
+}
+
+impl Stream {
+    fn live(&self) -> bool {
+        self.video.live || self.audio.as_ref().is_some_and(|audio| audio.live)
+    }
+}
+
+async fn fetch_playlist(url: &Url, headers: &HeaderMap) -> Result<Playlist> {
+    let text = fetch(url, headers).await?.text().await?;
+    parse_playlist(url, &text)
+}
+
+/// The segments of the stream at `url`, of its highest bitrate rendition if it has several.
+async fn media_playlists(url: &str, headers: &HeaderMap) -> Result<Stream> {
+    let url = Url::parse(url)?;
+    let (variants, audio) = match fetch_playlist(&url, headers).await? {
+        Playlist::Media(video) => return Ok(Stream { video, audio: None }),
+        Playlist::Master { variants, audio } => (variants, audio),
+    };
+
+    let best = variants
+        .iter()
+        .max_by_key(|variant| variant.bandwidth)
+        .context("HLS playlist has no renditions")?;
+    debug!("Using HLS rendition {} ({} bps)", best.url, best.bandwidth);
+    // A master playlist only ever points to media playlists
+    let Playlist::Media(video) = fetch_playlist(&best.url, headers).await? else {
+        return Err(anyhow!(
+            "HLS master playlist points to another master playlist"
+        ));
+    };
+
+    let audio = match audio_for(best, &audio) {
+        Some(rendition) => {
+            debug!("Using HLS audio track {}", rendition.url);
+            match fetch_playlist(&rendition.url, headers).await? {
+                Playlist::Media(audio) => Some(audio),
+                Playlist::Master { .. } => {
+                    return Err(anyhow!("HLS audio track points to a master playlist"))
+                }
+            }
+        }
+        None => None,
+    };
+    Ok(Stream { video, audio })
+}
+
+/// Fetches the init section and segments of a media playlist, concatenated in order.
+async fn fetch_segments(playlist: &MediaPlaylist, headers: &HeaderMap) -> Result<Vec<u8>> {
+    let init = match &playlist.init {
+        Some(init) => fetch(init, headers).await?.bytes().await?.to_vec(),
+        None => Vec::new(),
+    };
+    // Buffered keeps the segments in order while fetching several at once
+    let urls: Vec<Url> = playlist
+        .segments
+        .iter()
+        .map(|(url, _)| url.clone())
+        .collect();
+    let segments: Vec<_> = stream::iter(urls)
+        .map(|segment| async move {
+            Ok::<_, anyhow::Error>(fetch(&segment, headers).await?.bytes().await?)
+        })
+        .buffered(CONCURRENT_SEGMENTS)
+        .try_collect()
+        .await?;
+
+    let mut data = init;
+    for segment in segments {
+        data.extend_from_slice(&segment);
+    }
+    Ok(data)
+}
+
+/// Downloads the HLS stream at `url` to an MP4, sending `headers` with every request. Streams
+/// longer than `duration_limit` are cut down, returning the seconds they were cut to. A separate
+/// audio track is muxed in.
+pub(super) async fn fetch_hls(
+    url: &str,
+    headers: &HeaderMap,
+    duration_limit: Option<DurationLimit>,
+    timeouts: &StageTimeouts,
+) -> Result<(Vec<u8>, Option<u64>)> {
+    let mut stream = timeouts
+        .run(Stage::Extraction, media_playlists(url, headers))
+        .await??;
+    if stream.live() {
+        return Err(anyhow!(LIVE_STREAM_ERROR));
+    }
+
+    let limit = duration_limit.filter(|limit| stream.video.duration() > limit.max_secs as f64);
+    if let Some(limit) = limit {
+        info!(
+            "Stream is longer than {}s, keeping the first {}s",
+            limit.max_secs, limit.clip_secs
+        );
+        stream.video.truncate(limit.clip_secs);
+        if let Some(audio) = &mut stream.audio {
+            audio.truncate(limit.clip_secs);
+        }
+    }
+
+    info!(
+        "Downloading {} HLS segments from {}{}",
+        stream.video.segments.len(),
+        url,
+        if stream.audio.is_some() {
+            " with a separate audio track"
+        } else {
+            ""
+        }
+    );
+    let data = timeouts
+        .run(Stage::Download, async {
+            match &stream.audio {
+                Some(audio) => {
+                    let (video, audio) = tokio::try_join!(
+                        fetch_segments(&stream.video, headers),
+                        fetch_segments(audio, headers)
+                    )?;
+                    mux_to_mp4(&video, &audio).await
+                }
+                None => remux_ts_to_mp4(&fetch_segments(&stream.video, headers).await?).await,
+            }
+        })
+        .await??;
+
+    Ok((data, limit.map(|limit| limit.clip_secs)))
+}
+
+/// Downloads direct links to HLS playlists. DASH manifests are left to yt-dlp.
+pub struct HlsDownloader {
+    duration_limit: Option<DurationLimit>,
+}
+
+impl HlsDownloader {
+    pub fn new(duration_limit: Option<DurationLimit>) -> Self {
+        Self { duration_limit }
+    }
+}
+
+#[async_trait]
+impl Downloader for HlsDownloader {
+    fn name(&self) -> &'static str {
+        "hls"
+    }
+
+    fn supports_url(&self, url: &str) -> bool {
+        Url::parse(url).is_ok_and(|url| url.path().to_lowercase().ends_with(".m3u8"))
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let (data, truncated_to) = fetch_hls(
+            &req.url,
+            &HeaderMap::new(),
+            req.duration_limit.or(self.duration_limit),
+            &req.timeouts,
+        )
+        .await?;
+
+        let name = Url::parse(&req.url)?
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|file| file.rsplit_once('.'))
+            .map(|(stem, _)| stem.to_string())
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or_else(|| "stream".to_string());
+
+        Ok(MediaInfo {
+            url: req.url.clone(),
+            files: vec![MediaFile {
+                filename: format!("{name}.mp4"),
+                data,
+            }],
+            metadata: MediaMetadata {
+                title: name.clone(),
+                id: name,
+                thumbnail: None,
+                duration: None,
+                author: None,
+                likes: None,
+                description: None,
+                view_count: None,
+                uploaded_at: None,
+                site: None,
+                format_ext: "mp4".to_string(),
+                chapters: Vec::new(),
+                nsfw: false,
+                live: false,
+                formats: Vec::new(),
+            },
+            truncated_to,
+            timeouts: req.timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    async fn test_availability() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://cdn.example.com/video/master.m3u8").unwrap()
+    }
+
+    #[test]
+    fn test_parse_master_playlist() {
+        let text = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,CODECS=\"avc1.4d401f,mp4a.40.2\"\n\
+            low/index.m3u8\n\
+            #EXT-X-STREAM-INF:CODECS=\"avc1.640028,mp4a.40.2\",BANDWIDTH=2400000\n\
+            https://other.example.com/high.m3u8\n";
+
+        assert_eq!(
+            parse_playlist(&base(), text).unwrap(),
+            Playlist::Master {
+                variants: vec![
+                    Variant {
+                        bandwidth: 800000,
+                        url: Url::parse("https://cdn.example.com/video/low/index.m3u8").unwrap(),
+                        audio_group: None,
+                    },
+                    Variant {
+                        bandwidth: 2400000,
+                        url: Url::parse("https://other.example.com/high.m3u8").unwrap(),
+                        audio_group: None,
+                    },
+                ],
+                audio: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_separate_audio_tracks() {
+        let text = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",NAME=\"English\",URI=\"audio/en.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",NAME=\"Main\",DEFAULT=YES,URI=\"audio/main.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"muxed\",NAME=\"Main\",DEFAULT=YES\n\
+            #EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"English\",URI=\"subs/en.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2400000,AUDIO=\"aac\",SUBTITLES=\"subs\"\n\
+            high.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,AUDIO=\"muxed\"\n\
+            low.m3u8\n";
+
+        let Playlist::Master { variants, audio } = parse_playlist(&base(), text).unwrap() else {
+            panic!("not a master playlist");
+        };
+        assert_eq!(audio.len(), 2);
+        assert_eq!(variants[0].audio_group.as_deref(), Some("aac"));
+        assert_eq!(
+            audio_for(&variants[0], &audio).map(|rendition| rendition.url.as_str()),
+            Some("https://cdn.example.com/video/audio/main.m3u8")
+        );
+        // Audio without a playlist of its own is in the variant
+        assert_eq!(audio_for(&variants[1], &audio), None);
+    }
+
+    #[test]
+    fn test_parse_media_playlist() {
+        let text = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXT-X-KEY:METHOD=NONE\n\
+            #EXTINF:6.0,\n\
+            seg0.m4s\n\
+            #EXTINF:4.5,\n\
+            seg1.m4s\n\
+            #EXT-X-ENDLIST\n";
+
+        let Playlist::Media(mut playlist) = parse_playlist(&base(), text).unwrap() else {
+            panic!("not a media playlist");
+        };
+        assert!(!playlist.live);
+        assert_eq!(
+            playlist.init.as_ref().map(Url::as_str),
+            Some("https://cdn.example.com/video/init.mp4")
+        );
+        assert_eq!(playlist.duration(), 10.5);
+
+        playlist.truncate(5);
+        assert_eq!(playlist.segments.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_rejects_unsupported_playlists() {
+        assert!(parse_playlist(&base(), "not a playlist").is_err());
+        assert!(parse_playlist(
+            &base(),
+            "#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n#EXTINF:6,\nseg0.ts\n"
+        )
+        .is_err());
+
+        let Playlist::Media(playlist) =
+            parse_playlist(&base(), "#EXTM3U\n#EXTINF:6,\nseg0.ts\n").unwrap()
+        else {
+            panic!("not a media playlist");
+        };
+        assert!(playlist.live);
+    }
+
+    #[test]
+    fn test_supports_only_playlist_links() {
+        let downloader = HlsDownloader::new(None);
+        assert!(downloader.supports_url("https://cdn.example.com/live/index.M3U8?token=1"));
+        assert!(!downloader.supports_url("https://example.com/watch?v=1"));
+        // DASH manifests are left to yt-dlp
+        assert!(!downloader.supports_url("https://cdn.example.com/video/manifest.mpd"));
+    }
+}
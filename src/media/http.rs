@@ -0,0 +1,222 @@
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    redirect, Client, Proxy, RequestBuilder,
+};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_USER_AGENT: &str = concat!("grabby/", env!("CARGO_PKG_VERSION"));
+
+/// How the HTTP client shared by all downloaders connects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpSettings {
+    /// User-Agent sent instead of "grabby/<version>", also passed on to yt-dlp and gallery-dl
+    pub user_agent: Option<String>,
+    /// Headers sent with every request
+    pub headers: HashMap<String, String>,
+    /// Headers sent to a domain and its subdomains, overriding the ones above
+    pub site_headers: HashMap<String, HashMap<String, String>>,
+    /// Proxy all requests go through, e.g. "http://127.0.0.1:3128"
+    pub proxy: Option<String>,
+    pub connect_timeout: Duration,
+    /// Longest wait for the next bytes of a response before a request fails
+    pub read_timeout: Duration,
+    /// Longest a whole request may take, unlimited when unset as media downloads are bounded by
+    /// their stage timeouts
+    pub timeout: Option<Duration>,
+    /// Redirects followed before a request fails
+    pub max_redirects: usize,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            headers: HashMap::new(),
+            site_headers: HashMap::new(),
+            proxy: None,
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            timeout: None,
+            max_redirects: 10,
+        }
+    }
+}
+
+impl HttpSettings {
+    fn build(&self) -> Result<Http> {
+        let mut builder = Client::builder()
+            .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .default_headers(header_map(&self.headers)?)
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .redirect(redirect::Policy::limited(self.max_redirects));
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("Invalid HTTP proxy {proxy}"))?);
+        }
+
+        let mut site_headers = self
+            .site_headers
+            .iter()
+            .map(|(domain, headers)| Ok((domain.to_lowercase(), header_map(headers)?)))
+            .collect::<Result<Vec<_>>>()?;
+        // Subdomains come last so their headers win over their parent domain's
+        site_headers.sort_by_key(|(domain, _)| domain.len());
+
+        Ok(Http {
+            client: builder.build().context("Failed to build HTTP client")?,
+            site_headers,
+            user_agent: self.user_agent.clone(),
+        })
+    }
+}
+
+fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            Ok((
+                HeaderName::try_from(name)
+                    .with_context(|| format!("Invalid header name {name}"))?,
+                HeaderValue::try_from(value)
+                    .with_context(|| format!("Invalid value of header {name}"))?,
+            ))
+        })
+        .collect()
+}
+
+struct Http {
+    client: Client,
+    site_headers: Vec<(String, HeaderMap)>,
+    user_agent: Option<String>,
+}
+
+impl Http {
+    fn headers_for(&self, url: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(host) = url::Url::parse(url)
+            .ok()
+            .and_then(|url| Some(url.host_str()?.to_lowercase()))
+        else {
+            return headers;
+        };
+
+        for (domain, overrides) in &self.site_headers {
+            if host == *domain || host.ends_with(&format!(".{domain}")) {
+                headers.extend(overrides.clone());
+            }
+        }
+        headers
+    }
+}
+
+static HTTP: OnceLock<Http> = OnceLock::new();
+
+fn shared() -> &'static Http {
+    // Building without a proxy or headers only fails where `Client::new` would panic as well
+    HTTP.get_or_init(|| {
+        HttpSettings::default()
+            .build()
+            .expect("Failed to build HTTP client")
+    })
+}
+
+/// Sets up the shared client with `settings`. Has no effect once the client is in use, so this
+/// belongs before any downloads.
+pub fn configure_http_client(settings: &HttpSettings) -> Result<()> {
+    let http = settings.build()?;
+    if HTTP.set(http).is_err() {
+        warn!("HTTP client is already in use, keeping its settings");
+    }
+    Ok(())
+}
+
+/// Starts a GET request on the client every HTTP fetch of the media module goes through, so
+/// connections and TLS sessions are reused, with the headers configured for the URL's site.
+pub fn get(url: &str) -> RequestBuilder {
+    let http = shared();
+    http.client.get(url).headers(http.headers_for(url))
+}
+
+/// User-Agent configured for the shared client, if any.
+pub fn user_agent() -> Option<&'static str> {
+    shared().user_agent.as_deref()
+}
+
+/// Arguments passing a configured User-Agent on to yt-dlp and gallery-dl.
+pub fn user_agent_args() -> Vec<String> {
+    user_agent_args_for(user_agent())
+}
+
+/// Arguments passing `user_agent` on, none leaving the tool's own in place.
+pub fn user_agent_args_for(user_agent: Option<&str>) -> Vec<String> {
+    match user_agent {
+        Some(user_agent) => vec!["--user-agent".to_string(), user_agent.to_string()],
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_rejects_invalid_settings() {
+        assert!(HttpSettings::default().build().is_ok());
+
+        let settings = HttpSettings {
+            proxy: Some("http://127.0.0.1:3128".to_string()),
+            ..HttpSettings::default()
+        };
+        assert!(settings.build().is_ok());
+
+        let settings = HttpSettings {
+            proxy: Some("not a proxy".to_string()),
+            ..HttpSettings::default()
+        };
+        assert!(settings.build().is_err());
+
+        let settings = HttpSettings {
+            headers: HashMap::from([("Bad Header".to_string(), "value".to_string())]),
+            ..HttpSettings::default()
+        };
+        assert!(settings.build().is_err());
+    }
+
+    #[test]
+    fn test_site_headers_cover_subdomains() {
+        let settings = HttpSettings {
+            site_headers: HashMap::from([
+                (
+                    "imgur.com".to_string(),
+                    HashMap::from([
+                        ("Referer".to_string(), "https://imgur.com/".to_string()),
+                        ("User-Agent".to_string(), "Mozilla/5.0".to_string()),
+                    ]),
+                ),
+                (
+                    "i.imgur.com".to_string(),
+                    HashMap::from([("User-Agent".to_string(), "curl/8.0".to_string())]),
+                ),
+            ]),
+            ..HttpSettings::default()
+        };
+        let http = settings.build().unwrap();
+
+        let headers = http.headers_for("https://i.imgur.com/abc.jpg");
+        assert_eq!(headers["referer"], "https://imgur.com/");
+        assert_eq!(headers["user-agent"], "curl/8.0");
+
+        let headers = http.headers_for("https://imgur.com/gallery/abc");
+        assert_eq!(headers["user-agent"], "Mozilla/5.0");
+
+        assert!(http.headers_for("https://notimgur.com/abc").is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tracks in-flight jobs and the time of the last activity, so idle
+/// resources can be released when nothing has run for a while.
+pub struct IdleTracker {
+    active_jobs: AtomicUsize,
+    last_activity: Mutex<Instant>,
+}
+
+impl IdleTracker {
+    pub fn new() -> Self {
+        Self {
+            active_jobs: AtomicUsize::new(0),
+            last_activity: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Marks the start of a job. The job ends when the returned guard is dropped.
+    pub fn begin_job(&self) -> JobGuard<'_> {
+        self.active_jobs.fetch_add(1, Ordering::SeqCst);
+        self.touch();
+        JobGuard { tracker: self }
+    }
+
+    pub fn active_jobs(&self) -> usize {
+        self.active_jobs.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if no job is running and none has finished within `timeout`.
+    pub fn is_idle_for(&self, timeout: Duration) -> bool {
+        self.active_jobs() == 0 && self.idle_duration() >= timeout
+    }
+
+    fn idle_duration(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .map(|last| last.elapsed())
+            .unwrap_or_default()
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last) = self.last_activity.lock() {
+            *last = Instant::now();
+        }
+    }
+}
+
+pub struct JobGuard<'a> {
+    tracker: &'a IdleTracker,
+}
+
+impl Drop for JobGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.active_jobs.fetch_sub(1, Ordering::SeqCst);
+        self.tracker.touch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_guard_tracks_active_jobs() {
+        let tracker = IdleTracker::new();
+        assert_eq!(tracker.active_jobs(), 0);
+
+        let first = tracker.begin_job();
+        let second = tracker.begin_job();
+        assert_eq!(tracker.active_jobs(), 2);
+
+        drop(first);
+        assert_eq!(tracker.active_jobs(), 1);
+        drop(second);
+        assert_eq!(tracker.active_jobs(), 0);
+    }
+
+    #[test]
+    fn test_not_idle_while_job_running() {
+        let tracker = IdleTracker::new();
+        let _job = tracker.begin_job();
+        assert!(!tracker.is_idle_for(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_idle_after_timeout() {
+        let tracker = IdleTracker::new();
+        drop(tracker.begin_job());
+        assert!(tracker.is_idle_for(Duration::ZERO));
+        assert!(!tracker.is_idle_for(Duration::from_secs(3600)));
+    }
+}
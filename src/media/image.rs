@@ -0,0 +1,338 @@
+use super::{process, sniff::correct_extension, types::MediaFile};
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use tempfile::NamedTempFile;
+use tracing::{debug, warn};
+
+/// Strips metadata from an image and converts it when Discord can't preview it inline.
+///
+/// Failures leave the file as it was, so a broken image never blocks an upload.
+pub async fn process_image(file: MediaFile) -> MediaFile {
+    let file = correct_extension(file);
+    let Some(ext) = extension(&file.filename) else {
+        return file;
+    };
+
+    if let Some(target) = conversion_target(&ext) {
+        let result = process::run_blocking({
+            let data = file.data.clone();
+            move || convert_image(&data, target)
+        })
+        .await;
+
+        return match result {
+            Ok(Ok(data)) => MediaFile {
+                filename: with_extension(&file.filename, target),
+                data,
+            },
+            Ok(Err(e)) => {
+                warn!("Failed to convert {}: {}", file.filename, e);
+                file
+            }
+            Err(e) => {
+                warn!("Conversion task failed for {}: {}", file.filename, e);
+                file
+            }
+        };
+    }
+
+    match strip_metadata(&file.data, &ext) {
+        Some(data) => {
+            debug!(
+                "Stripped {} bytes of metadata from {}",
+                file.data.len() - data.len(),
+                file.filename
+            );
+            MediaFile {
+                filename: file.filename,
+                data,
+            }
+        }
+        None => file,
+    }
+}
+
+fn extension(filename: &str) -> Option<String> {
+    Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+}
+
+fn with_extension(filename: &str, ext: &str) -> String {
+    Path::new(filename)
+        .with_extension(ext)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Format to convert images Discord doesn't preview to.
+fn conversion_target(ext: &str) -> Option<&'static str> {
+    match ext {
+        "heic" | "heif" => Some("jpg"),
+        // WebP keeps the transparency AVIF images may have
+        "avif" => Some("webp"),
+        "tif" | "tiff" | "bmp" => Some("png"),
+        _ => None,
+    }
+}
+
+/// Converts an image with ffmpeg, dropping all of its metadata.
+fn convert_image(data: &[u8], target: &str) -> Result<Vec<u8>> {
+    let mut input_file = NamedTempFile::new()?;
+    input_file.write_all(data)?;
+
+    let output_file = NamedTempFile::with_suffix(format!(".{target}"))?;
+
+    let output = process::run(
+        Command::new("ffmpeg")
+            .arg("-i")
+            .arg(input_file.path())
+            .arg("-map_metadata")
+            .arg("-1")
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-y")
+            .arg(output_file.path()),
+    )?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to convert image: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(std::fs::read(output_file.path())?)
+}
+
+/// Losslessly removes EXIF/XMP and similar metadata, or `None` if there is nothing to remove.
+fn strip_metadata(data: &[u8], ext: &str) -> Option<Vec<u8>> {
+    let stripped = match ext {
+        "jpg" | "jpeg" => strip_jpeg(data)?,
+        "png" => strip_png(data)?,
+        "webp" => strip_webp(data)?,
+        _ => return None,
+    };
+
+    (stripped.len() != data.len()).then_some(stripped)
+}
+
+/// Drops APP1 (EXIF, XMP) and APP13 (IPTC) segments, keeping the ICC profile in APP2.
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut output = vec![0xFF, 0xD8];
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            // Fill byte before a marker
+            0xFF => pos += 1,
+            // Entropy-coded data follows the start of scan, keep everything from there
+            0xDA | 0xD9 => {
+                output.extend_from_slice(&data[pos..]);
+                return Some(output);
+            }
+            // Markers without a length
+            0x01 | 0xD0..=0xD7 => {
+                output.extend_from_slice(&data[pos..pos + 2]);
+                pos += 2;
+            }
+            _ => {
+                let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]);
+                let end = pos + 2 + length as usize;
+                let segment = data.get(pos..end)?;
+                if marker != 0xE1 && marker != 0xED {
+                    output.extend_from_slice(segment);
+                }
+                pos = end;
+            }
+        }
+    }
+}
+
+/// Drops the EXIF, text and timestamp chunks of a PNG.
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    if !data.starts_with(SIGNATURE) {
+        return None;
+    }
+
+    let mut output = SIGNATURE.to_vec();
+    let mut pos = SIGNATURE.len();
+    while pos < data.len() {
+        let length = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let end = pos + 12 + length;
+        let chunk = data.get(pos..end)?;
+        if !matches!(
+            &chunk[4..8],
+            b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME"
+        ) {
+            output.extend_from_slice(chunk);
+        }
+        pos = end;
+    }
+
+    Some(output)
+}
+
+/// Drops the EXIF and XMP chunks of a WebP, clearing their flags in the VP8X header.
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return None;
+    }
+
+    let mut output = data[0..12].to_vec();
+    let mut pos = 12;
+    while pos < data.len() {
+        let fourcc = data.get(pos..pos + 4)?;
+        let size = u32::from_le_bytes(data.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        // Chunks are padded to an even size
+        let end = (pos + 8 + size + (size & 1)).min(data.len());
+        let chunk = data.get(pos..end)?;
+
+        match fourcc {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let flags_at = output.len() + 8;
+                output.extend_from_slice(chunk);
+                if let Some(flags) = output.get_mut(flags_at) {
+                    *flags &= !(0x08 | 0x04);
+                }
+            }
+            _ => output.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+
+    let riff_size = (output.len() - 8) as u32;
+    output[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_with_exif() -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        // APP0 (JFIF)
+        data.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x04, b'J', b'F']);
+        // APP1 (EXIF)
+        data.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x06, b'E', b'x', b'i', b'f']);
+        // Start of scan with entropy-coded data and end of image
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+        data
+    }
+
+    fn png_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = (payload.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(payload);
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        chunk
+    }
+
+    fn webp_chunk(fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = fourcc.to_vec();
+        chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_strip_jpeg_removes_exif() {
+        let stripped = strip_metadata(&jpeg_with_exif(), "jpg").unwrap();
+        assert_eq!(
+            stripped,
+            vec![
+                0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, b'J', b'F', 0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34,
+                0xFF, 0xD9
+            ]
+        );
+        assert_eq!(strip_metadata(&stripped, "jpg"), None);
+    }
+
+    #[test]
+    fn test_strip_jpeg_rejects_truncated_data() {
+        let data = jpeg_with_exif();
+        assert_eq!(strip_jpeg(&data[..8]), None);
+        assert_eq!(strip_jpeg(b"not a jpeg"), None);
+    }
+
+    #[test]
+    fn test_strip_png_removes_text_chunks() {
+        let signature = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        let ihdr = png_chunk(b"IHDR", &[1; 13]);
+        let iend = png_chunk(b"IEND", &[]);
+
+        let mut data = signature.to_vec();
+        data.extend(ihdr.iter());
+        data.extend(png_chunk(b"eXIf", &[2; 6]));
+        data.extend(png_chunk(b"tEXt", b"GPS"));
+        data.extend(iend.iter());
+
+        let mut expected = signature.to_vec();
+        expected.extend(ihdr);
+        expected.extend(iend);
+        assert_eq!(strip_metadata(&data, "png"), Some(expected));
+    }
+
+    #[test]
+    fn test_strip_webp_removes_exif_and_clears_flags() {
+        let body = [
+            webp_chunk(b"VP8X", &[0x0C, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            webp_chunk(b"VP8 ", &[3; 5]),
+            webp_chunk(b"EXIF", &[4; 7]),
+            webp_chunk(b"XMP ", &[5; 2]),
+        ]
+        .concat();
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        data.extend_from_slice(b"WEBP");
+        data.extend(body);
+
+        let stripped = strip_metadata(&data, "webp").unwrap();
+        let expected_body = [
+            webp_chunk(b"VP8X", &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            webp_chunk(b"VP8 ", &[3; 5]),
+        ]
+        .concat();
+        assert_eq!(&stripped[12..], expected_body.as_slice());
+        assert_eq!(
+            u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize,
+            stripped.len() - 8
+        );
+    }
+
+    #[test]
+    fn test_conversion_target() {
+        assert_eq!(conversion_target("heic"), Some("jpg"));
+        assert_eq!(conversion_target("avif"), Some("webp"));
+        assert_eq!(conversion_target("tiff"), Some("png"));
+        assert_eq!(conversion_target("jpg"), None);
+        assert_eq!(with_extension("abc_2.heic", "jpg"), "abc_2.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_process_image_leaves_other_files_alone() {
+        let file = MediaFile {
+            filename: "clip.mp4".to_string(),
+            data: vec![1, 2, 3],
+        };
+        let processed = process_image(file).await;
+        assert_eq!(processed.filename, "clip.mp4");
+        assert_eq!(processed.data, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,351 @@
+use super::{
+    downloader::Downloader,
+    http,
+    image::process_image,
+    timeouts::Stage,
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+/// Resolves fediverse statuses (Mastodon, and Pleroma/Akkoma with their Mastodon API) through
+/// the instance's public API.
+pub struct MastodonDownloader;
+
+impl MastodonDownloader {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn fetch_status(&self, api_url: &str) -> Result<Value> {
+        let response = http::get(api_url)
+            .header("accept", "application/json")
+            .send()
+            .await
+            .context("Failed to fetch status")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to fetch status: HTTP {}",
+                response.status()
+            ));
+        }
+
+        response.json().await.context("Not a fediverse status")
+    }
+
+    async fn download_attachment(&self, url: &str, filename: String) -> Result<MediaFile> {
+        debug!("Downloading attachment to memory: {}", url);
+
+        let response = http::get(url)
+            .send()
+            .await
+            .context("Failed to fetch attachment")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to download attachment: HTTP {}",
+                response.status()
+            ));
+        }
+
+        let data = response
+            .bytes()
+            .await
+            .context("Failed to read attachment data")?
+            .to_vec();
+
+        Ok(MediaFile { filename, data })
+    }
+}
+
+/// API URL of the status a post link points to, e.g.
+/// `https://mastodon.social/@user/123` or `https://example.com/notice/AbC`.
+fn status_api_url(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    let segments: Vec<&str> = parsed.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+    let id = match segments.as_slice() {
+        // Status ids are snowflakes, which keeps other sites' "@user/..." pages out
+        [user, id] if user.starts_with('@') && is_numeric(id) => *id,
+        ["users", _, "statuses", id] if is_numeric(id) => *id,
+        ["web", "statuses", id] if is_numeric(id) => *id,
+        // Pleroma and Akkoma use flake ids
+        ["notice", id] if id.chars().all(|c| c.is_ascii_alphanumeric()) => *id,
+        _ => return None,
+    };
+
+    let origin = match parsed.port() {
+        Some(port) => format!("{}://{host}:{port}", parsed.scheme()),
+        None => format!("{}://{host}", parsed.scheme()),
+    };
+    Some(format!("{origin}/api/v1/statuses/{id}"))
+}
+
+fn is_numeric(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Maps a status to metadata and the URLs of its media attachments.
+fn parse_status(status: &Value) -> Result<(MediaMetadata, Vec<String>)> {
+    // Boosts carry the boosted status
+    let status = match &status["reblog"] {
+        Value::Object(_) => &status["reblog"],
+        _ => status,
+    };
+
+    let id = status["id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Not a fediverse status"))?
+        .to_string();
+
+    let urls: Vec<String> = status["media_attachments"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|attachment| attachment["type"].as_str() != Some("unknown"))
+        .filter_map(|attachment| attachment["url"].as_str().map(str::to_string))
+        .collect();
+
+    if urls.is_empty() {
+        return Err(anyhow!("Status has no media attachments"));
+    }
+
+    let text = html_to_text(status["content"].as_str().unwrap_or_default());
+    let title = match status["spoiler_text"].as_str().filter(|s| !s.is_empty()) {
+        Some(spoiler) => spoiler.to_string(),
+        None if !text.is_empty() => text,
+        None => "Unknown Media".to_string(),
+    };
+
+    let account = &status["account"];
+    let author = account["display_name"]
+        .as_str()
+        .filter(|name| !name.is_empty())
+        .or(account["acct"].as_str())
+        .map(str::to_string);
+
+    let format_ext = extension(&urls[0]).unwrap_or_else(|| "jpg".to_string());
+
+    Ok((
+        MediaMetadata {
+            title,
+            id,
+            thumbnail: None,
+            duration: None,
+            author,
+            likes: status["favourites_count"].as_u64(),
+            description: None,
+            view_count: None,
+            uploaded_at: status["created_at"]
+                .as_str()
+                .and_then(crate::utils::parse_date),
+            site: Some("Mastodon".to_string()),
+            format_ext,
+            chapters: Vec::new(),
+            nsfw: status["sensitive"].as_bool().unwrap_or(false),
+            live: false,
+            formats: Vec::new(),
+        },
+        urls,
+    ))
+}
+
+/// Plain text of a status' HTML content, keeping line and paragraph breaks.
+fn html_to_text(html: &str) -> String {
+    let html = html
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("</p><p>", "\n\n");
+
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn extension(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    let name = parsed.path_segments()?.next_back()?;
+    let (_, ext) = name.rsplit_once('.')?;
+    Some(ext.to_lowercase())
+}
+
+#[async_trait]
+impl Downloader for MastodonDownloader {
+    fn name(&self) -> &'static str {
+        "mastodon"
+    }
+
+    fn supports_url(&self, url: &str) -> bool {
+        status_api_url(url).is_some()
+    }
+
+    fn supports_metadata(&self) -> bool {
+        true
+    }
+
+    async fn metadata(&self, req: &DownloadRequest) -> Result<MediaMetadata> {
+        let api_url =
+            status_api_url(&req.url).ok_or_else(|| anyhow!("Not a fediverse status URL"))?;
+        let status = req
+            .timeouts
+            .run(Stage::Extraction, self.fetch_status(&api_url))
+            .await??;
+        let (metadata, _) = parse_status(&status)?;
+        Ok(metadata)
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let url = req.url.as_str();
+        let api_url = status_api_url(url).ok_or_else(|| anyhow!("Not a fediverse status URL"))?;
+        info!("Resolving fediverse status: {}", api_url);
+
+        let status = req
+            .timeouts
+            .run(Stage::Extraction, self.fetch_status(&api_url))
+            .await??;
+        let (metadata, media_urls) = parse_status(&status)?;
+
+        let files = req
+            .timeouts
+            .run(Stage::Download, async {
+                let mut files = Vec::new();
+                for (index, media_url) in media_urls.iter().enumerate() {
+                    let ext = extension(media_url).unwrap_or_else(|| metadata.format_ext.clone());
+                    let filename = if index == 0 {
+                        format!("{}.{}", metadata.id, ext)
+                    } else {
+                        format!("{}_{}.{}", metadata.id, index + 1, ext)
+                    };
+
+                    match self.download_attachment(media_url, filename).await {
+                        Ok(file) => files.push(process_image(file).await),
+                        Err(e) => warn!("Failed to download {}: {}", media_url, e),
+                    }
+                }
+                files
+            })
+            .await?;
+
+        if files.is_empty() {
+            return Err(anyhow!("Failed to download any attachments"));
+        }
+
+        Ok(MediaInfo {
+            url: url.to_string(),
+            files,
+            metadata,
+            truncated_to: None,
+            timeouts: req.timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    async fn test_availability() -> bool {
+        // Only needs HTTP access to the instance
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_api_url() {
+        assert_eq!(
+            status_api_url("https://mastodon.social/@Gargron/109123456789012345").as_deref(),
+            Some("https://mastodon.social/api/v1/statuses/109123456789012345")
+        );
+        assert_eq!(
+            status_api_url("https://fosstodon.org/@user@mastodon.social/1091234").as_deref(),
+            Some("https://fosstodon.org/api/v1/statuses/1091234")
+        );
+        assert_eq!(
+            status_api_url("https://example.social/users/bob/statuses/42").as_deref(),
+            Some("https://example.social/api/v1/statuses/42")
+        );
+        assert_eq!(
+            status_api_url("https://pleroma.example/notice/AbC123").as_deref(),
+            Some("https://pleroma.example/api/v1/statuses/AbC123")
+        );
+    }
+
+    #[test]
+    fn test_status_api_url_ignores_other_links() {
+        assert_eq!(status_api_url("https://youtube.com/@channel/videos"), None);
+        assert_eq!(status_api_url("https://x.com/user/status/123"), None);
+        assert_eq!(status_api_url("not-a-url"), None);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let status = serde_json::json!({
+            "id": "109123",
+            "content": "<p>Look at this &amp; that<br>second line</p><p>next</p>",
+            "spoiler_text": "",
+            "favourites_count": 12,
+            "account": { "acct": "alice@example.social", "display_name": "" },
+            "media_attachments": [
+                { "type": "video", "url": "https://files.example/original/abc.MP4" },
+                { "type": "unknown", "url": "https://files.example/original/broken" },
+                { "type": "image", "url": "https://files.example/original/def.png" }
+            ]
+        });
+
+        let (metadata, urls) = parse_status(&status).unwrap();
+        assert_eq!(metadata.id, "109123");
+        assert_eq!(metadata.title, "Look at this & that\nsecond line\n\nnext");
+        assert_eq!(metadata.author.as_deref(), Some("alice@example.social"));
+        assert_eq!(metadata.likes, Some(12));
+        assert_eq!(metadata.format_ext, "mp4");
+        assert_eq!(urls.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_status_uses_boosted_status() {
+        let status = serde_json::json!({
+            "id": "2",
+            "content": "",
+            "media_attachments": [],
+            "reblog": {
+                "id": "1",
+                "content": "",
+                "spoiler_text": "CW: cats",
+                "account": { "acct": "bob", "display_name": "Bob" },
+                "media_attachments": [{ "type": "gifv", "url": "https://files.example/a.mp4" }]
+            }
+        });
+
+        let (metadata, _) = parse_status(&status).unwrap();
+        assert_eq!(metadata.id, "1");
+        assert_eq!(metadata.title, "CW: cats");
+        assert_eq!(metadata.author.as_deref(), Some("Bob"));
+    }
+
+    #[test]
+    fn test_parse_status_without_media() {
+        let status = serde_json::json!({ "id": "1", "media_attachments": [] });
+        assert!(parse_status(&status).is_err());
+        assert!(parse_status(&serde_json::json!({ "error": "Not found" })).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use std::pin::pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::Notify;
+
+/// Bytes of downloaded media held in memory across all downloads, with new downloads held back
+/// while they exceed the budget.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    released: Notify,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: u64) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            used: Mutex::new(0),
+            released: Notify::new(),
+        })
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Bytes held by downloaded media that hasn't been dropped yet.
+    pub fn used(&self) -> u64 {
+        *self.used.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until the media held is back under the budget.
+    pub async fn admit(&self) {
+        loop {
+            // Registered before checking, so a release in between isn't missed
+            let mut released = pin!(self.released.notified());
+            released.as_mut().enable();
+            if self.used() < self.limit {
+                return;
+            }
+            released.await;
+        }
+    }
+
+    /// Counts `bytes` against the budget until the returned lease is dropped.
+    pub fn hold(self: &Arc<Self>, bytes: u64) -> MemoryLease {
+        *self.used.lock().unwrap_or_else(PoisonError::into_inner) += bytes;
+        MemoryLease {
+            budget: Some(Arc::clone(self)),
+            bytes,
+        }
+    }
+}
+
+/// Share of the memory budget held by a download's files, given back when dropped.
+#[derive(Debug, Default)]
+pub struct MemoryLease {
+    /// `None` when no budget is configured
+    budget: Option<Arc<MemoryBudget>>,
+    bytes: u64,
+}
+
+impl Drop for MemoryLease {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            let mut used = budget.used.lock().unwrap_or_else(PoisonError::into_inner);
+            *used = used.saturating_sub(self.bytes);
+            drop(used);
+            budget.released.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_lease_counts_until_dropped() {
+        let budget = MemoryBudget::new(100);
+        let lease = budget.hold(60);
+        let other = budget.hold(30);
+        assert_eq!(budget.used(), 90);
+
+        drop(lease);
+        assert_eq!(budget.used(), 30);
+        drop(other);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_admit_waits_for_release() {
+        let budget = MemoryBudget::new(100);
+        budget.admit().await;
+
+        let lease = budget.hold(150);
+        let waiting = tokio::spawn({
+            let budget = Arc::clone(&budget);
+            async move { budget.admit().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(lease);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
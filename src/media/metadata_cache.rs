@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How long extracted metadata is reused, short enough for the media URLs in it to still work.
+const TTL: Duration = Duration::from_secs(300);
+/// Most links whose metadata is kept at once.
+const MAX_ENTRIES: usize = 256;
+
+/// Metadata JSON a downloader extracted recently, keyed by link, so `/info` followed by `/embed`
+/// or a retry doesn't extract it again.
+#[derive(Debug, Default)]
+pub struct MetadataCache {
+    entries: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, url: &str, now: Instant) -> Option<String> {
+        self.lock()
+            .get(url)
+            .filter(|(stored_at, _)| now.duration_since(*stored_at) < TTL)
+            .map(|(_, json)| json.clone())
+    }
+
+    pub fn insert(&self, url: &str, json: String, now: Instant) {
+        let mut entries = self.lock();
+        entries.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < TTL);
+        if entries.len() >= MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(url, _)| url.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(url.to_string(), (now, json));
+    }
+
+    /// Drops the metadata of a link, e.g. once a download with it failed.
+    pub fn remove(&self, url: &str) {
+        self.lock().remove(url);
+    }
+
+    /// Drops all metadata, returning how many links had some.
+    pub fn clear(&self) -> usize {
+        let mut entries = self.lock();
+        let count = entries.len();
+        *entries = HashMap::new();
+        count
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Instant, String)>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_expire() {
+        let cache = MetadataCache::new();
+        let now = Instant::now();
+        cache.insert("https://example.com/a", "{}".to_string(), now);
+
+        assert_eq!(
+            cache.get("https://example.com/a", now + TTL / 2).as_deref(),
+            Some("{}")
+        );
+        assert_eq!(cache.get("https://example.com/a", now + TTL), None);
+        assert_eq!(cache.get("https://example.com/b", now), None);
+
+        cache.remove("https://example.com/a");
+        assert_eq!(cache.get("https://example.com/a", now), None);
+
+        cache.insert("https://example.com/b", "{}".to_string(), now);
+        assert_eq!(cache.clear(), 1);
+        assert_eq!(cache.get("https://example.com/b", now), None);
+    }
+
+    #[test]
+    fn test_oldest_entry_is_evicted() {
+        let cache = MetadataCache::new();
+        let now = Instant::now();
+        for index in 0..=MAX_ENTRIES {
+            cache.insert(
+                &format!("https://example.com/{index}"),
+                index.to_string(),
+                now + Duration::from_millis(index as u64),
+            );
+        }
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(cache.get("https://example.com/0", later), None);
+        assert!(cache.get("https://example.com/1", later).is_some());
+        assert!(cache
+            .get(&format!("https://example.com/{MAX_ENTRIES}"), later)
+            .is_some());
+    }
+}
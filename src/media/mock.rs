@@ -0,0 +1,141 @@
+use super::{
+    downloader::Downloader,
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// What the mock answers for a link.
+#[derive(Debug, Clone)]
+enum Canned {
+    Files(Vec<(String, Vec<u8>)>),
+    Error(String),
+}
+
+/// Downloader returning canned media for known links, so bot logic can be tested without yt-dlp,
+/// gallery-dl or network access.
+#[derive(Debug, Default)]
+pub struct MockDownloader {
+    canned: HashMap<String, Canned>,
+    nsfw: bool,
+    duration: Option<u64>,
+    requests: Arc<Mutex<Vec<DownloadRequest>>>,
+}
+
+impl MockDownloader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers `url` with the given `(filename, data)` files.
+    pub fn with_media(mut self, url: &str, files: &[(&str, &[u8])]) -> Self {
+        let files = files
+            .iter()
+            .map(|(name, data)| (name.to_string(), data.to_vec()))
+            .collect();
+        self.canned.insert(url.to_string(), Canned::Files(files));
+        self
+    }
+
+    /// Fails downloads of `url` with `error`.
+    pub fn with_error(mut self, url: &str, error: &str) -> Self {
+        self.canned
+            .insert(url.to_string(), Canned::Error(error.to_string()));
+        self
+    }
+
+    /// Marks all media as adult content.
+    pub fn nsfw(mut self) -> Self {
+        self.nsfw = true;
+        self
+    }
+
+    /// Reports all media as lasting `secs` seconds.
+    pub fn with_duration(mut self, secs: u64) -> Self {
+        self.duration = Some(secs);
+        self
+    }
+
+    /// Requests the mock received, shared so they can be checked once it was handed over.
+    pub fn requests(&self) -> Arc<Mutex<Vec<DownloadRequest>>> {
+        Arc::clone(&self.requests)
+    }
+
+    fn canned(&self, req: &DownloadRequest) -> Result<Vec<(String, Vec<u8>)>> {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(DownloadRequest {
+                progress: None,
+                ..req.clone()
+            });
+
+        match self.canned.get(&req.url) {
+            Some(Canned::Files(files)) => Ok(files.clone()),
+            Some(Canned::Error(error)) => Err(anyhow!("{error}")),
+            None => Err(anyhow!("No media found for this URL")),
+        }
+    }
+
+    fn metadata_for(&self, files: &[(String, Vec<u8>)]) -> MediaMetadata {
+        MediaMetadata {
+            title: "Mock media".to_string(),
+            id: "mock".to_string(),
+            thumbnail: None,
+            duration: self.duration,
+            author: Some("Mock author".to_string()),
+            likes: Some(42),
+            description: None,
+            view_count: None,
+            uploaded_at: None,
+            site: Some("Mock".to_string()),
+            format_ext: files
+                .first()
+                .and_then(|(name, _)| name.rsplit_once('.'))
+                .map(|(_, ext)| ext.to_string())
+                .unwrap_or_else(|| "mp4".to_string()),
+            chapters: Vec::new(),
+            nsfw: self.nsfw,
+            live: false,
+            formats: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Downloader for MockDownloader {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn supports_metadata(&self) -> bool {
+        true
+    }
+
+    async fn metadata(&self, req: &DownloadRequest) -> Result<MediaMetadata> {
+        let files = self.canned(req)?;
+        Ok(self.metadata_for(&files))
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let files = self.canned(req)?;
+        let metadata = self.metadata_for(&files);
+        Ok(MediaInfo {
+            url: req.url.clone(),
+            files: files
+                .into_iter()
+                .map(|(filename, data)| MediaFile { filename, data })
+                .collect(),
+            metadata,
+            truncated_to: None,
+            timeouts: req.timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    async fn test_availability() -> bool {
+        true
+    }
+}
@@ -1,18 +1,84 @@
+mod audio;
+mod bluesky;
+mod bootstrap;
+mod breaker;
+mod diagnostics;
+mod dimensions;
+mod disk;
 mod downloader;
+mod extractors;
+mod feed;
+mod gallery;
 mod gallery_dl;
+mod gallery_record;
+mod gallery_sites;
+mod hls;
+mod http;
+mod idle;
+mod image;
+mod mastodon;
+mod memory;
+mod metadata_cache;
+#[cfg(test)]
+mod mock;
+mod music;
+mod probe;
+mod process;
+mod progress;
+mod queue;
 mod resize;
+mod scan;
+mod section;
+mod sniff;
+mod subtitles;
+mod timeouts;
 mod types;
 mod utils;
 mod ytdlp;
 
+pub use audio::{audio_filename, extract_audio_file, transcode_audio, AudioFormat};
+pub use bootstrap::{ensure_tools, tool_versions, Tool, ToolPin};
+pub use diagnostics::{Diagnostics, Status};
+pub use dimensions::{ImageLimits, ImageTooLarge};
+pub use disk::{check_free_space, LowDiskSpace};
 pub use downloader::Downloader;
-pub use resize::{resize_image_file, resize_media_file};
-pub use types::MediaInfo;
+pub use feed::{Feed, FeedEntry};
+pub use gallery::{parse_selection, zip_files};
+pub use gallery_sites::GalleryDlSite;
+pub use http::{configure_http_client, HttpSettings};
+#[cfg(test)]
+pub use mock::MockDownloader;
+pub use process::{kill_orphans, run_blocking};
+pub use progress::{Progress, ProgressReporter};
+pub use queue::{JobClass, QueuePolicy};
+pub use resize::{
+    resize_image_file_with_profile, resize_media_file_with_profile, transcoded_filename,
+    OutputContainer, ResizeProfile, VideoCodec,
+};
+pub use scan::{FileScanner, Flagged, Scanner};
+pub use section::{DurationLimit, TooLong};
+pub use subtitles::{burn_subtitles, fetch_subtitles};
+pub use timeouts::{Stage, StageTimeout, StageTimeouts, Timeouts};
+pub use types::{DownloadRequest, MediaInfo, MediaMetadata};
 pub use utils::remux_ts_to_mp4;
 
 use anyhow::Result;
+use bluesky::BlueskyDownloader;
+use breaker::{domain_of, CircuitBreaker};
+use extractors::SupportedSites;
 use gallery_dl::GalleryDlDownloader;
-use tracing::{info, warn};
+use gallery_sites::GalleryDlSiteDownloader;
+use hls::HlsDownloader;
+use idle::IdleTracker;
+use mastodon::MastodonDownloader;
+use memory::MemoryBudget;
+use music::MusicDownloader;
+use queue::DownloadQueue;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, info_span, warn, Instrument};
+use types::MediaFile;
 use ytdlp::YtDlpDownloader;
 
 const URL_TRANSFORMS: &[(&str, &str)] = &[
@@ -25,10 +91,18 @@ const URL_TRANSFORMS: &[(&str, &str)] = &[
 ];
 
 pub fn get_transformed_url(url: &str) -> Option<String> {
+    transform_url(url, URL_TRANSFORMS.iter().copied())
+}
+
+/// Moves `url` to the mirror of the first `(domain, mirror)` pair its host falls under.
+fn transform_url<'a>(
+    url: &str,
+    transforms: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Option<String> {
     let parsed = url::Url::parse(url).ok()?;
     let host = parsed.host_str()?.to_lowercase();
-    for (pattern, replacement) in URL_TRANSFORMS {
-        if host == *pattern || host.ends_with(&format!(".{pattern}")) {
+    for (pattern, replacement) in transforms {
+        if host == pattern || host.ends_with(&format!(".{pattern}")) {
             let mut new_url = parsed.clone();
             new_url.set_host(Some(replacement)).ok()?;
             return Some(new_url.to_string());
@@ -37,58 +111,521 @@ pub fn get_transformed_url(url: &str) -> Option<String> {
     None
 }
 
+/// Error of a link no downloader handled, keeping the timeout among the causes so it can be
+/// reported.
+fn failure<T>(summary: &str, errors: &[String], timed_out: Option<StageTimeout>) -> Result<T> {
+    let message = format!("{summary}: {}", errors.join(". "));
+    Err(match timed_out {
+        Some(timeout) => anyhow::Error::new(timeout).context(message),
+        None => anyhow::anyhow!(message),
+    })
+}
+
+/// Message key of the class a download error falls into.
+pub fn error_class(error: &anyhow::Error) -> &'static str {
+    if StageTimeout::find(error).is_some() {
+        return "error.timeout";
+    }
+    if TooLong::find(error).is_some() {
+        return "error.too_long";
+    }
+    if LowDiskSpace::find(error).is_some() {
+        return "error.low_disk_space";
+    }
+    if ImageTooLarge::find(error).is_some() {
+        return "error.image_too_large";
+    }
+    if Flagged::find(error).is_some() {
+        return "error.flagged";
+    }
+
+    let error_str = error.to_string().to_lowercase();
+    if error_str.contains("live streams can't be downloaded") {
+        "error.live_stream"
+    } else if error_str.contains("need a logged-in session") {
+        "error.session_required"
+    } else if error_str.contains("unsupported url") || error_str.contains("no extractor found") {
+        "error.unsupported_url"
+    } else if error_str.contains("network error") || error_str.contains("connection") {
+        "error.network"
+    } else if error_str.contains("timeout") {
+        "error.timeout"
+    } else {
+        "error.download_failed"
+    }
+}
+
 pub struct MediaDownloader {
     downloaders: Vec<Box<dyn Downloader>>,
+    idle: IdleTracker,
+    /// Downloaders that keep failing for a site, skipped there until their cooldown passes
+    breaker: CircuitBreaker,
+    /// Configured mirrors, taking precedence over the built-in ones
+    url_rewrites: Vec<(String, String)>,
+    timeouts: Timeouts,
+    /// Limits downloads running at once, if configured
+    queue: Option<DownloadQueue>,
+    /// Holds back downloads while downloaded media takes up too much memory, if configured
+    memory: Option<Arc<MemoryBudget>>,
+    /// Largest images handed out, bigger ones are dropped before anything decodes them
+    image_limits: ImageLimits,
+    /// Scans downloaded files before they are handed out, if configured
+    scanner: Option<FileScanner>,
+    /// Sites the general-purpose downloaders have extractors for, once listed
+    sites: RwLock<Option<SupportedSites>>,
+    /// Whether the external tools have been checked since startup or the last idle release
+    warm: Mutex<Warmth>,
+}
+
+/// Progress of checking the external tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Warmth {
+    Cold,
+    WarmingUp,
+    Warm,
 }
 
 impl MediaDownloader {
-    pub fn new() -> Result<Self> {
+    pub fn new(
+        duration_limit: Option<DurationLimit>,
+        live_capture_secs: Option<u64>,
+        sites: Vec<GalleryDlSite>,
+        audio_format: AudioFormat,
+    ) -> Result<Self> {
         info!(
             "Media downloader initialized - using in-memory downloads with yt-dlp and gallery-dl"
         );
 
-        // Create downloader instances in priority order (gallery-dl first, then yt-dlp)
-        let downloaders: Vec<Box<dyn Downloader>> = vec![
-            // gallery-dl is tried first as it also has yt-dlp integration
+        // Create downloader instances in priority order (configured gallery-dl sites, native site
+        // downloaders, gallery-dl, yt-dlp)
+        let mut downloaders: Vec<Box<dyn Downloader>> = vec![
+            // Only handles fediverse status links, whose attachments yt-dlp sometimes misses
+            Box::new(MastodonDownloader::new()),
+            // Only handles bsky.app posts, as generic tools lag behind Bluesky changes
+            Box::new(BlueskyDownloader::new()),
+            // Only handles direct links to HLS playlists
+            Box::new(HlsDownloader::new(duration_limit)),
+            // Only handles audio platforms, whose tracks are sent as audio with cover art
+            Box::new(MusicDownloader::new(audio_format)),
+            // gallery-dl is tried first by default as it also has yt-dlp integration
             Box::new(GalleryDlDownloader::new()),
-            Box::new(YtDlpDownloader::new()),
+            Box::new(YtDlpDownloader::new(duration_limit, live_capture_secs)),
         ];
+        // Configured sites go straight to gallery-dl with their credentials and postprocessors,
+        // as do stories, which fail with an explanation when no session is configured
+        downloaders.insert(0, Box::new(GalleryDlSiteDownloader::new(sites)?));
+
+        Ok(Self {
+            downloaders,
+            idle: IdleTracker::new(),
+            breaker: CircuitBreaker::new(),
+            url_rewrites: Vec::new(),
+            timeouts: Timeouts::default(),
+            queue: None,
+            memory: None,
+            image_limits: ImageLimits::default(),
+            scanner: None,
+            sites: RwLock::new(None),
+            warm: Mutex::new(Warmth::Cold),
+        })
+    }
+
+    /// Media downloader trying only the given downloaders, with the external tools treated as
+    /// checked.
+    #[cfg(test)]
+    pub fn with_downloaders(downloaders: Vec<Box<dyn Downloader>>) -> Self {
+        Self {
+            downloaders,
+            idle: IdleTracker::new(),
+            breaker: CircuitBreaker::new(),
+            url_rewrites: Vec::new(),
+            timeouts: Timeouts::default(),
+            queue: None,
+            memory: None,
+            image_limits: ImageLimits::default(),
+            scanner: None,
+            sites: RwLock::new(None),
+            warm: Mutex::new(Warmth::Warm),
+        }
+    }
+
+    /// Adds mirrors links are rewritten to, keyed by the domain whose links they take.
+    pub fn with_url_rewrites(mut self, rewrites: HashMap<String, String>) -> Self {
+        self.url_rewrites = rewrites
+            .into_iter()
+            .map(|(domain, mirror)| (domain.to_lowercase(), mirror))
+            .collect();
+        // Longer domains first, so subdomains win over their parents
+        self.url_rewrites
+            .sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        self
+    }
+
+    /// Sets the time each stage may take, overridden per downloader.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Runs at most `max_running` downloads at once, if given, starting waiting ones according
+    /// to `policy`.
+    pub fn with_queue(mut self, max_running: Option<usize>, policy: QueuePolicy) -> Self {
+        self.queue = max_running.map(|max_running| DownloadQueue::new(max_running, policy));
+        self
+    }
+
+    /// Holds back new downloads while the files of ones being handled take up more than
+    /// `max_bytes`, if given.
+    pub fn with_memory_budget(mut self, max_bytes: Option<u64>) -> Self {
+        self.memory = max_bytes.map(MemoryBudget::new);
+        self
+    }
+
+    /// Drops downloaded images exceeding `limits`.
+    pub fn with_image_limits(mut self, limits: ImageLimits) -> Self {
+        self.image_limits = limits;
+        self
+    }
+
+    /// Scans downloaded files with `scanner`, if given, dropping the ones it flags.
+    pub fn with_scanner(mut self, scanner: Option<FileScanner>) -> Self {
+        self.scanner = scanner;
+        self
+    }
+
+    /// The request as handled by `downloader`, with its timeouts.
+    fn request_for(&self, req: &DownloadRequest, downloader: &dyn Downloader) -> DownloadRequest {
+        DownloadRequest {
+            timeouts: self.timeouts.for_downloader(downloader.name()),
+            ..req.clone()
+        }
+    }
 
-        Ok(Self { downloaders })
+    /// Downloaders able to handle the request, with those named in its downloader order tried
+    /// in that order after the ones that aren't.
+    ///
+    /// Unnamed downloaders only handle specific sites, so they keep precedence.
+    fn candidates(&self, req: &DownloadRequest) -> Vec<&dyn Downloader> {
+        let mut candidates: Vec<&dyn Downloader> = self
+            .downloaders
+            .iter()
+            .map(|d| d.as_ref())
+            .filter(|d| d.supports_url(&req.url))
+            .filter(|d| req.chapter.is_none() || d.supports_chapters())
+            .collect();
+        candidates.sort_by_key(|d| {
+            req.downloader_order
+                .iter()
+                .position(|name| name == d.name())
+        });
+        candidates
     }
 
-    pub async fn download(&self, url: &str) -> Result<MediaInfo> {
-        info!("Starting download for URL: {}", url);
+    pub async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        if let Some(max_secs) = req.max_duration_secs {
+            self.check_duration(req, max_secs).await?;
+        }
+
+        if let Some(memory) = &self.memory {
+            if memory.used() >= memory.limit() {
+                info!(
+                    "Waiting for downloaded media to be released before downloading {}",
+                    req.url
+                );
+            }
+            memory.admit().await;
+        }
+
+        let _slot = match &self.queue {
+            Some(queue) => Some(queue.enter(req.class, req.priority).await),
+            None => None,
+        };
+        let _job = self.idle.begin_job();
+        self.warm_up().await;
+
+        match &req.chapter {
+            Some(chapter) => info!(
+                "Starting chapter \"{}\" download for URL: {}",
+                chapter, req.url
+            ),
+            None => info!("Starting download for URL: {}", req.url),
+        }
 
         let mut errors = Vec::new();
+        let mut timed_out = None;
+        let domain = domain_of(&req.url).unwrap_or_default();
+
+        for downloader in self.candidates(req) {
+            if self
+                .breaker
+                .is_open(downloader.name(), &domain, Instant::now())
+            {
+                info!(
+                    "Skipping {} for {}, it keeps failing there",
+                    downloader.name(),
+                    domain
+                );
+                errors.push(format!(
+                    "{} is temporarily disabled for {}",
+                    downloader.name(),
+                    domain
+                ));
+                continue;
+            }
 
-        for downloader in &self.downloaders {
-            match downloader.download(url).await {
-                Ok(media_info) => {
-                    info!("Successfully downloaded with {}", downloader.name());
+            let span = info_span!("downloader", name = downloader.name());
+            let started = Instant::now();
+            let req = self.request_for(req, downloader);
+            let download = downloader.download(&req).instrument(span);
+            #[cfg(feature = "sentry")]
+            let download = crate::reporting::bind(download, downloader.name(), &domain);
+            match download.await {
+                Ok(mut media_info) => {
+                    media_info.files = media_info
+                        .files
+                        .into_iter()
+                        .map(sniff::correct_extension)
+                        .collect();
+                    info!(
+                        histogram.download_duration_seconds = started.elapsed().as_secs_f64(),
+                        downloader = downloader.name(),
+                        "Successfully downloaded with {}",
+                        downloader.name()
+                    );
+                    self.breaker.record_success(downloader.name(), &domain);
+                    media_info.files = self.drop_oversized_images(media_info.files).await?;
+                    if let Some(scanner) = &self.scanner {
+                        media_info.files = scanner.filter(media_info.files).await?;
+                    }
+                    if media_info.metadata.duration.is_none() {
+                        media_info.metadata.duration =
+                            probe::media_duration(&media_info.files).await;
+                    }
+                    if let Some(memory) = &self.memory {
+                        media_info.memory = memory.hold(media_info.total_bytes());
+                    }
                     return Ok(media_info);
                 }
                 Err(e) => {
-                    warn!("{} failed: {}", downloader.name(), e);
+                    warn!(
+                        monotonic_counter.download_failures = 1u64,
+                        downloader = downloader.name(),
+                        "{} failed: {}",
+                        downloader.name(),
+                        e
+                    );
+                    self.breaker
+                        .record_failure(downloader.name(), &domain, Instant::now());
+                    #[cfg(feature = "sentry")]
+                    crate::reporting::record_failure(downloader.name(), &domain, &e);
+                    timed_out = timed_out.or(StageTimeout::find(&e));
+                    errors.push(format!("{e}"));
+                }
+            }
+        }
+
+        failure("Media download failed", &errors, timed_out)
+    }
+
+    /// The files that aren't images exceeding the image limits. Fails with [`ImageTooLarge`]
+    /// if all of them are.
+    async fn drop_oversized_images(&self, files: Vec<MediaFile>) -> Result<Vec<MediaFile>> {
+        let mut kept = Vec::with_capacity(files.len());
+        let mut refused = None;
+        for file in files {
+            match self.image_limits.check(&file).await {
+                Ok(()) => kept.push(file),
+                Err(too_large) => {
+                    warn!("Dropping {}", too_large);
+                    refused = refused.or(Some(too_large));
+                }
+            }
+        }
+        match refused {
+            Some(too_large) if kept.is_empty() => Err(too_large.into()),
+            _ => Ok(kept),
+        }
+    }
+
+    /// Fails with [`TooLong`] if the metadata shows a video longer than `max_secs`. Media whose
+    /// metadata can't be extracted is let through, the download has the final say.
+    async fn check_duration(&self, req: &DownloadRequest, max_secs: u64) -> Result<()> {
+        let metadata = match self.metadata(req).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                debug!("Not checking the duration of {}: {}", req.url, e);
+                return Ok(());
+            }
+        };
+        match metadata.duration {
+            Some(duration_secs) if duration_secs > max_secs && !metadata.live => {
+                info!(
+                    "Refusing {}, it is {}s long with a cap of {}s",
+                    req.url, duration_secs, max_secs
+                );
+                Err(TooLong {
+                    duration_secs,
+                    max_secs,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Extracts the metadata of the requested media without downloading it, with the first
+    /// downloader able to.
+    pub async fn metadata(&self, req: &DownloadRequest) -> Result<MediaMetadata> {
+        let _job = self.idle.begin_job();
+        self.warm_up().await;
+        info!("Extracting metadata for URL: {}", req.url);
+
+        let mut errors = Vec::new();
+        let mut timed_out = None;
+        let domain = domain_of(&req.url).unwrap_or_default();
+
+        for downloader in self.candidates(req) {
+            if !downloader.supports_metadata()
+                || self
+                    .breaker
+                    .is_open(downloader.name(), &domain, Instant::now())
+            {
+                continue;
+            }
+
+            let span = info_span!("downloader", name = downloader.name());
+            let req = self.request_for(req, downloader);
+            match downloader.metadata(&req).instrument(span).await {
+                Ok(metadata) => {
+                    self.breaker.record_success(downloader.name(), &domain);
+                    return Ok(metadata);
+                }
+                Err(e) => {
+                    warn!("{} metadata extraction failed: {}", downloader.name(), e);
+                    self.breaker
+                        .record_failure(downloader.name(), &domain, Instant::now());
+                    #[cfg(feature = "sentry")]
+                    crate::reporting::record_failure(downloader.name(), &domain, &e);
+                    timed_out = timed_out.or(StageTimeout::find(&e));
                     errors.push(format!("{e}"));
                 }
             }
         }
 
-        Err(anyhow::anyhow!(
-            "Media download failed: {}",
-            errors.join(". ")
-        ))
+        if errors.is_empty() {
+            errors.push("No downloader can extract metadata for this URL".to_string());
+        }
+        failure("Media metadata extraction failed", &errors, timed_out)
     }
 
+    /// Embed-friendly mirror of the link, if one is configured or built in for its domain.
     pub fn get_transformed_url(&self, url: &str) -> Option<String> {
-        get_transformed_url(url)
+        let configured = self
+            .url_rewrites
+            .iter()
+            .map(|(domain, mirror)| (domain.as_str(), mirror.as_str()));
+        transform_url(url, configured).or_else(|| get_transformed_url(url))
     }
 
-    pub fn is_supported_url(&self, _url: &str) -> bool {
-        // For /embed command, assume all URLs are supported
-        // The individual downloaders will handle validation and error reporting
-        true
+    /// Whether any downloader is expected to handle the URL, judged by its domain without
+    /// starting a subprocess. Every URL passes until the supported sites have been listed.
+    pub fn is_supported_url(&self, url: &str) -> bool {
+        let sites = self.sites.read().unwrap_or_else(PoisonError::into_inner);
+        let Some(sites) = sites.as_ref() else {
+            return true;
+        };
+
+        sites.matches(url)
+            || self
+                .downloaders
+                .iter()
+                .any(|d| !d.is_general_purpose() && d.supports_url(url))
+    }
+
+    /// Lists the sites yt-dlp and gallery-dl support, for [`Self::is_supported_url`].
+    pub async fn load_supported_sites(&self) {
+        match SupportedSites::load().await {
+            Ok(sites) => {
+                *self.sites.write().unwrap_or_else(PoisonError::into_inner) = Some(sites);
+            }
+            Err(e) => warn!("{}, accepting links of any site", e),
+        }
+    }
+
+    /// Checks the external tools unless that already happened since the last idle release.
+    /// Downloads starting while another one checks them don't wait for the check.
+    pub async fn warm_up(&self) {
+        {
+            let mut warm = self.lock_warmth();
+            if *warm != Warmth::Cold {
+                return;
+            }
+            *warm = Warmth::WarmingUp;
+        }
+
+        if let Err(e) = self.test_setup().await {
+            warn!("Media downloader test failed: {}", e);
+        }
+        *self.lock_warmth() = Warmth::Warm;
+    }
+
+    fn lock_warmth(&self) -> std::sync::MutexGuard<'_, Warmth> {
+        self.warm.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Number of downloads currently running.
+    pub fn active_downloads(&self) -> usize {
+        self.idle.active_jobs()
+    }
+
+    /// Number of downloads waiting for the download queue.
+    pub fn queued_downloads(&self) -> usize {
+        self.queue.as_ref().map_or(0, DownloadQueue::waiting)
+    }
+
+    /// Drops the metadata downloaders cached between jobs, returning how many links had some.
+    pub fn clear_metadata_cache(&self) -> usize {
+        self.downloaders.iter().map(|d| d.clear_cache()).sum()
+    }
+
+    /// Drops metadata cached between jobs and has the next download check the external tools
+    /// again, as they may have been updated meanwhile.
+    pub async fn release_idle_resources(&self) {
+        {
+            let mut warm = self.lock_warmth();
+            if *warm != Warmth::Warm {
+                return;
+            }
+            *warm = Warmth::Cold;
+        }
+
+        let cleared = self.clear_metadata_cache();
+        info!(
+            "Media downloader idle, dropped cached metadata of {} links",
+            cleared
+        );
+    }
+
+    /// Lets downloaders skipped for failing on a site try it again, returning how many were.
+    pub fn reset_circuit_breakers(&self) -> usize {
+        self.breaker.reset()
+    }
+
+    /// Spawns a background task releasing idle resources once no job ran for `idle_timeout`.
+    pub fn spawn_idle_monitor(self: &Arc<Self>, idle_timeout: Duration) {
+        let downloader = Arc::clone(self);
+        let check_interval =
+            (idle_timeout / 4).clamp(Duration::from_secs(1), Duration::from_secs(60));
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(check_interval);
+            loop {
+                interval.tick().await;
+                if downloader.idle.is_idle_for(idle_timeout) {
+                    downloader.release_idle_resources().await;
+                }
+            }
+        });
     }
 
     pub async fn test_setup(&self) -> Result<()> {
@@ -97,12 +634,38 @@ impl MediaDownloader {
         let ytdlp_available = YtDlpDownloader::test_availability().await;
         let gallery_dl_available = GalleryDlDownloader::test_availability().await;
 
+        let report = Diagnostics::collect().await;
+        match &report.encoders {
+            Some(encoders) => {
+                for (encoder, _) in encoders.iter().filter(|(_, available)| !available) {
+                    warn!("ffmpeg has no {} encoder", encoder);
+                }
+            }
+            None => warn!("ffmpeg can't be run to list its encoders"),
+        }
+        if let Some(error) = &report.temp_dir_error {
+            warn!(
+                "Temp directory {} is not writable: {}",
+                report.temp_dir.display(),
+                error
+            );
+        }
+        if report.disk_status() != Status::Ok {
+            warn!(
+                "Temp directory {} is low on disk space",
+                report.temp_dir.display()
+            );
+        }
+        if let Err(e) = &report.connectivity {
+            warn!("Discord API is unreachable: {}", e);
+        }
+
         if ytdlp_available || gallery_dl_available {
             info!("✅ At least one media downloader is available");
             Ok(())
         } else {
             Err(anyhow::anyhow!(
-                "No media downloaders are available. Please install yt-dlp and/or gallery-dl."
+                "No media downloaders are available. Please install yt-dlp and/or gallery-dl, or enable [bootstrap] in the config."
             ))
         }
     }
@@ -114,21 +677,124 @@ mod tests {
 
     #[test]
     fn test_media_downloader_new() {
-        let downloader = MediaDownloader::new();
+        let downloader = MediaDownloader::new(None, None, Vec::new(), AudioFormat::default());
         assert!(downloader.is_ok());
         let dl = downloader.unwrap();
-        assert_eq!(dl.downloaders.len(), 2);
+        assert_eq!(dl.downloaders.len(), 7);
+    }
+
+    #[test]
+    fn test_candidates_follow_order() {
+        let downloader =
+            MediaDownloader::new(None, None, Vec::new(), AudioFormat::default()).unwrap();
+        let names = |url: &str, order: &[&str]| {
+            let req = DownloadRequest {
+                downloader_order: order.iter().map(|s| s.to_string()).collect(),
+                ..DownloadRequest::new(url)
+            };
+            downloader
+                .candidates(&req)
+                .iter()
+                .map(|d| d.name())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names("https://example.com/a", &["gallery-dl", "yt-dlp"]),
+            vec!["gallery-dl", "yt-dlp"]
+        );
+        assert_eq!(
+            names("https://example.com/a", &["yt-dlp", "gallery-dl"]),
+            vec!["yt-dlp", "gallery-dl"]
+        );
+        // Site-specific downloaders stay ahead of the ordered ones
+        assert_eq!(
+            names(
+                "https://bsky.app/profile/a/post/b",
+                &["yt-dlp", "gallery-dl"]
+            ),
+            vec!["bluesky", "yt-dlp", "gallery-dl"]
+        );
+
+        let chapter = DownloadRequest {
+            chapter: Some("Intro".to_string()),
+            ..DownloadRequest::new("https://example.com/a")
+        };
+        let names: Vec<_> = downloader
+            .candidates(&chapter)
+            .iter()
+            .map(|d| d.name())
+            .collect();
+        assert_eq!(names, vec!["yt-dlp"]);
+    }
+
+    #[test]
+    fn test_download_request_ids() {
+        let first = DownloadRequest::new("https://example.com/a");
+        let second = DownloadRequest::new("https://example.com/a");
+        assert_eq!(first.id.len(), 8);
+        assert!(first.id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first.id, second.id);
+    }
+
+    #[tokio::test]
+    async fn test_release_idle_resources_resets_warm_state() {
+        let downloader =
+            MediaDownloader::new(None, None, Vec::new(), AudioFormat::default()).unwrap();
+        *downloader.lock_warmth() = Warmth::Warm;
+
+        downloader.release_idle_resources().await;
+        assert_eq!(*downloader.lock_warmth(), Warmth::Cold);
+
+        // A check still running isn't undone
+        *downloader.lock_warmth() = Warmth::WarmingUp;
+        downloader.release_idle_resources().await;
+        assert_eq!(*downloader.lock_warmth(), Warmth::WarmingUp);
+    }
+
+    #[tokio::test]
+    async fn test_downloaded_media_holds_memory_budget() {
+        let mock = MockDownloader::new().with_media(
+            "https://example.com/v",
+            &[("a.mp4", b"12345"), ("b.jpg", b"678")],
+        );
+        let downloader =
+            MediaDownloader::with_downloaders(vec![Box::new(mock)]).with_memory_budget(Some(100));
+
+        let media_info = downloader
+            .download(&DownloadRequest::new("https://example.com/v"))
+            .await
+            .unwrap();
+        let memory = downloader.memory.as_ref().unwrap();
+        assert_eq!(memory.used(), 8);
+
+        drop(media_info);
+        assert_eq!(memory.used(), 0);
     }
 
     #[test]
     fn test_is_supported_url() {
-        let downloader = MediaDownloader::new().unwrap();
+        let downloader =
+            MediaDownloader::new(None, None, Vec::new(), AudioFormat::default()).unwrap();
         assert!(downloader.is_supported_url("https://example.com/video.mp4"));
         assert!(downloader.is_supported_url("https://x.com/user/status/123"));
         assert!(downloader.is_supported_url("https://youtube.com/watch?v=123"));
         assert!(downloader.is_supported_url(""));
     }
 
+    #[test]
+    fn test_is_supported_url_with_listed_sites() {
+        let downloader =
+            MediaDownloader::new(None, None, Vec::new(), AudioFormat::default()).unwrap();
+        *downloader.sites.write().unwrap() = Some(SupportedSites::default());
+
+        assert!(!downloader.is_supported_url("https://example.com/video.mp4"));
+        assert!(!downloader.is_supported_url(""));
+        // Site-specific downloaders still take their links
+        assert!(downloader.is_supported_url("https://bsky.app/profile/a/post/b"));
+        assert!(downloader.is_supported_url("https://soundcloud.com/artist/track"));
+    }
+
     #[test]
     fn test_transform_reddit_basic() {
         assert_eq!(
@@ -234,6 +900,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_configured_url_rewrites() {
+        let downloader = MediaDownloader::new(None, None, Vec::new(), AudioFormat::default())
+            .unwrap()
+            .with_url_rewrites(HashMap::from([
+                ("TikTok.com".to_string(), "vxtiktok.com".to_string()),
+                ("vm.tiktok.com".to_string(), "vm.vxtiktok.com".to_string()),
+                ("bsky.app".to_string(), "fxbsky.app".to_string()),
+            ]));
+
+        assert_eq!(
+            downloader
+                .get_transformed_url("https://www.tiktok.com/@user/video/1")
+                .as_deref(),
+            Some("https://vxtiktok.com/@user/video/1")
+        );
+        assert_eq!(
+            downloader
+                .get_transformed_url("https://vm.tiktok.com/ZMhAbCdEf/")
+                .as_deref(),
+            Some("https://vm.vxtiktok.com/ZMhAbCdEf/")
+        );
+        assert_eq!(
+            downloader
+                .get_transformed_url("https://bsky.app/profile/a/post/b")
+                .as_deref(),
+            Some("https://fxbsky.app/profile/a/post/b")
+        );
+        // Built-in mirrors still apply to other domains
+        assert_eq!(
+            downloader
+                .get_transformed_url("https://x.com/user/status/1")
+                .as_deref(),
+            Some("https://fxtwitter.com/user/status/1")
+        );
+    }
+
     #[test]
     fn test_transform_invalid_url() {
         assert_eq!(get_transformed_url("not-a-url"), None);
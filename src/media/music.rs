@@ -0,0 +1,306 @@
+use super::{
+    audio::{transcode_audio, AudioFormat},
+    bootstrap::{program, Tool},
+    downloader::Downloader,
+    http,
+    image::process_image,
+    metadata_cache::MetadataCache,
+    process,
+    timeouts::{Stage, StageTimeouts},
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
+    ytdlp::{
+        extract_description, extract_formats, extract_live, extract_site, extract_upload_time,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::time::Instant;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+/// Audio platforms whose links are downloaded as audio tracks.
+const AUDIO_DOMAINS: &[&str] = &["soundcloud.com", "bandcamp.com"];
+
+/// Downloads the best audio of SoundCloud and Bandcamp tracks with yt-dlp and transcodes it,
+/// attaching the cover art.
+pub struct MusicDownloader {
+    format: AudioFormat,
+    cache: MetadataCache,
+}
+
+impl MusicDownloader {
+    pub fn new(format: AudioFormat) -> Self {
+        Self {
+            format,
+            cache: MetadataCache::new(),
+        }
+    }
+
+    async fn extract_metadata(&self, url: &str, timeouts: &StageTimeouts) -> Result<MediaMetadata> {
+        if let Some(json_str) = self.cache.get(url, Instant::now()) {
+            debug!("Using cached track metadata for: {}", url);
+            let json: Value =
+                serde_json::from_str(&json_str).context("Failed to parse media metadata")?;
+            return Ok(parse_track(&json));
+        }
+
+        debug!("Extracting track metadata with yt-dlp for: {}", url);
+
+        let output = timeouts
+            .run(
+                Stage::Extraction,
+                process::output(
+                    Command::new(program(Tool::YtDlp))
+                        .arg("--dump-json")
+                        .arg("--no-download")
+                        .arg("--no-warnings")
+                        .args(http::user_agent_args())
+                        // Album and set links resolve to their first track
+                        .arg("--playlist-items")
+                        .arg("1")
+                        .arg(url),
+                ),
+            )
+            .await?
+            .context("Failed to extract media metadata")?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Media metadata extraction failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let json_str = String::from_utf8_lossy(&output.stdout);
+        let json_str = json_str.lines().next().unwrap_or_default();
+        let json: Value =
+            serde_json::from_str(json_str).context("Failed to parse media metadata")?;
+        self.cache.insert(url, json_str.to_string(), Instant::now());
+
+        Ok(parse_track(&json))
+    }
+
+    async fn download_audio(&self, url: &str, timeouts: &StageTimeouts) -> Result<Vec<u8>> {
+        let output = timeouts
+            .run(
+                Stage::Download,
+                process::output(
+                    Command::new(program(Tool::YtDlp))
+                        .arg("--output")
+                        .arg("-")
+                        .arg("--format")
+                        .arg("bestaudio/best")
+                        .arg("--playlist-items")
+                        .arg("1")
+                        .arg("--no-warnings")
+                        .args(http::user_agent_args())
+                        .arg("--quiet")
+                        .arg(url),
+                ),
+            )
+            .await?
+            .context("Failed to download media")?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Media download failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(output.stdout)
+    }
+
+    async fn download_cover(&self, url: &str, id: &str) -> Result<MediaFile> {
+        let response = http::get(url)
+            .send()
+            .await
+            .context("Failed to fetch cover art")?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to download cover art: HTTP {}",
+                response.status()
+            ));
+        }
+
+        let ext = url::Url::parse(url)
+            .ok()
+            .and_then(|u| {
+                u.path_segments()?
+                    .next_back()?
+                    .rsplit_once('.')
+                    .map(|(_, ext)| ext.to_lowercase())
+            })
+            .unwrap_or_else(|| "jpg".to_string());
+
+        Ok(MediaFile {
+            filename: format!("{id}_cover.{ext}"),
+            data: response.bytes().await?.to_vec(),
+        })
+    }
+}
+
+fn is_audio_platform(url: &str) -> bool {
+    let Some(host) = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+    else {
+        return false;
+    };
+
+    AUDIO_DOMAINS
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+}
+
+/// Maps yt-dlp track info to metadata, with the artist as author and the album in the title.
+fn parse_track(json: &Value) -> MediaMetadata {
+    let track = json["track"]
+        .as_str()
+        .or(json["title"].as_str())
+        .unwrap_or("Unknown Title");
+    let title = match json["album"].as_str().filter(|album| *album != track) {
+        Some(album) => format!("{track} — {album}"),
+        None => track.to_string(),
+    };
+
+    MediaMetadata {
+        title,
+        id: json["id"]
+            .as_str()
+            .map(str::to_string)
+            .or_else(|| json["id"].as_u64().map(|id| id.to_string()))
+            .unwrap_or_else(|| "track".to_string()),
+        thumbnail: json["thumbnail"].as_str().map(str::to_string),
+        duration: json["duration"].as_f64().map(|d| d as u64),
+        author: json["artist"]
+            .as_str()
+            .or(json["creator"].as_str())
+            .or(json["uploader"].as_str())
+            .map(str::to_string),
+        likes: json["like_count"].as_u64(),
+        description: extract_description(json),
+        view_count: json["view_count"].as_u64(),
+        uploaded_at: extract_upload_time(json),
+        site: extract_site(json),
+        format_ext: "audio".to_string(),
+        chapters: Vec::new(),
+        nsfw: false,
+        live: extract_live(json),
+        formats: extract_formats(json),
+    }
+}
+
+#[async_trait]
+impl Downloader for MusicDownloader {
+    fn name(&self) -> &'static str {
+        "yt-dlp (audio)"
+    }
+
+    fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    fn supports_url(&self, url: &str) -> bool {
+        is_audio_platform(url)
+    }
+
+    fn supports_metadata(&self) -> bool {
+        true
+    }
+
+    async fn metadata(&self, req: &DownloadRequest) -> Result<MediaMetadata> {
+        self.extract_metadata(&req.url, &req.timeouts).await
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let url = req.url.as_str();
+        let mut metadata = self.extract_metadata(url, &req.timeouts).await?;
+        info!("Downloading audio track with yt-dlp: {}", metadata.id);
+
+        let audio = self.download_audio(url, &req.timeouts).await?;
+        let format = self.format;
+        let data = process::run_blocking(move || transcode_audio(&audio, format, None)).await??;
+        metadata.format_ext = format.extension().to_string();
+
+        let mut files = vec![MediaFile {
+            filename: format!("{}.{}", metadata.id, format.extension()),
+            data,
+        }];
+
+        if let Some(thumbnail) = &metadata.thumbnail {
+            match self.download_cover(thumbnail, &metadata.id).await {
+                Ok(cover) => files.push(process_image(cover).await),
+                Err(e) => warn!("Failed to download cover art: {}", e),
+            }
+        }
+
+        Ok(MediaInfo {
+            url: url.to_string(),
+            files,
+            metadata,
+            truncated_to: None,
+            timeouts: req.timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    async fn test_availability() -> bool {
+        super::ytdlp::YtDlpDownloader::test_availability().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_audio_platform() {
+        assert!(is_audio_platform("https://soundcloud.com/artist/track"));
+        assert!(is_audio_platform("https://m.soundcloud.com/artist/track"));
+        assert!(is_audio_platform("https://artist.bandcamp.com/track/song"));
+        assert!(!is_audio_platform("https://youtube.com/watch?v=123"));
+        assert!(!is_audio_platform("not-a-url"));
+    }
+
+    #[test]
+    fn test_parse_track() {
+        let json = serde_json::json!({
+            "id": "12345",
+            "title": "Artist - Song",
+            "track": "Song",
+            "artist": "Artist",
+            "uploader": "label",
+            "album": "Album",
+            "duration": 215.4,
+            "thumbnail": "https://f4.bcbits.com/img/a123_10.jpg"
+        });
+
+        let metadata = parse_track(&json);
+        assert_eq!(metadata.id, "12345");
+        assert_eq!(metadata.title, "Song — Album");
+        assert_eq!(metadata.author.as_deref(), Some("Artist"));
+        assert_eq!(metadata.duration, Some(215));
+        assert_eq!(
+            metadata.thumbnail.as_deref(),
+            Some("https://f4.bcbits.com/img/a123_10.jpg")
+        );
+    }
+
+    #[test]
+    fn test_parse_track_falls_back_to_title_and_uploader() {
+        let json = serde_json::json!({
+            "id": 987,
+            "title": "Mix",
+            "uploader": "dj",
+            "like_count": 7
+        });
+
+        let metadata = parse_track(&json);
+        assert_eq!(metadata.id, "987");
+        assert_eq!(metadata.title, "Mix");
+        assert_eq!(metadata.author.as_deref(), Some("dj"));
+        assert_eq!(metadata.likes, Some(7));
+    }
+}
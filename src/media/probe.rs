@@ -0,0 +1,248 @@
+use super::process;
+use super::types::MediaFile;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Output;
+use tempfile::NamedTempFile;
+use tracing::debug;
+
+/// What a stream of a media file carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+/// A stream of a media file, as ffprobe reports it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeStream {
+    pub kind: StreamKind,
+    /// Short codec name, e.g. "h264" or "opus"
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Seconds the stream lasts, if its container records it
+    pub duration: Option<f64>,
+    /// Cover art attached to an audio file rather than video
+    pub attached_pic: bool,
+}
+
+/// Streams and length of a media file, as ffprobe reports them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaProbe {
+    /// Container names, e.g. "mov,mp4,m4a,3gp,3g2,mj2"
+    pub format_name: Option<String>,
+    duration: Option<f64>,
+    pub streams: Vec<ProbeStream>,
+}
+
+/// Arguments printing a file's format and streams as JSON, before the file's path.
+const ARGS: &[&str] = &[
+    "-v",
+    "error",
+    "-print_format",
+    "json",
+    "-show_format",
+    "-show_streams",
+];
+
+impl MediaProbe {
+    /// Probes the file at `path` with ffprobe, blocking until it exits.
+    pub fn probe(path: &Path) -> Result<Self> {
+        let output = process::run(std::process::Command::new("ffprobe").args(ARGS).arg(path))
+            .context("Failed to run ffprobe")?;
+        Self::from_output(output)
+    }
+
+    /// Probes `data` through a temp file, as containers like MP4 may keep their index at the end.
+    pub async fn probe_data(data: &[u8]) -> Result<Self> {
+        let file = NamedTempFile::new()?;
+        tokio::fs::write(file.path(), data).await?;
+        let output = process::output(
+            tokio::process::Command::new("ffprobe")
+                .args(ARGS)
+                .arg(file.path()),
+        )
+        .await
+        .context("Failed to run ffprobe")?;
+        Self::from_output(output)
+    }
+
+    fn from_output(output: Output) -> Result<Self> {
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to probe media: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parses ffprobe's `-show_format -show_streams` JSON output.
+    pub fn parse(json: &str) -> Result<Self> {
+        let output: ProbeOutput =
+            serde_json::from_str(json).context("Failed to parse ffprobe output")?;
+        let format = output.format.unwrap_or_default();
+        Ok(Self {
+            format_name: format.format_name,
+            duration: parse_secs(format.duration.as_deref()),
+            streams: output.streams.into_iter().map(ProbeStream::from).collect(),
+        })
+    }
+
+    /// Seconds the media lasts, from its container or else its longest stream.
+    pub fn duration(&self) -> Option<f64> {
+        self.duration.or_else(|| {
+            self.streams
+                .iter()
+                .filter_map(|stream| stream.duration)
+                .reduce(f64::max)
+        })
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.streams
+            .iter()
+            .any(|stream| stream.kind == StreamKind::Audio)
+    }
+
+    /// The first video stream that isn't cover art.
+    pub fn video(&self) -> Option<&ProbeStream> {
+        self.streams
+            .iter()
+            .find(|stream| stream.kind == StreamKind::Video && !stream.attached_pic)
+    }
+
+    /// Width and height of the video, or of an image, which ffprobe reports as a video stream.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        let video = self.video()?;
+        Some((video.width?, video.height?))
+    }
+}
+
+/// Whole seconds the first video or audio file of a download lasts, for downloaders whose
+/// metadata leaves it out.
+pub async fn media_duration(files: &[MediaFile]) -> Option<u64> {
+    let file = files
+        .iter()
+        .find(|file| file.is_video() || file.is_audio())?;
+    match MediaProbe::probe_data(&file.data).await {
+        Ok(probe) => probe.duration().map(|secs| secs.round() as u64),
+        Err(e) => {
+            debug!("Not probing the duration of {}: {}", file.filename, e);
+            None
+        }
+    }
+}
+
+/// ffprobe numbers like durations are strings, "N/A" when unknown.
+fn parse_secs(secs: Option<&str>) -> Option<f64> {
+    secs?
+        .parse()
+        .ok()
+        .filter(|secs: &f64| secs.is_finite() && *secs >= 0.0)
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<RawStream>,
+    format: Option<RawFormat>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    duration: Option<String>,
+    #[serde(default)]
+    disposition: RawDisposition,
+}
+
+#[derive(Deserialize, Default)]
+struct RawDisposition {
+    #[serde(default)]
+    attached_pic: u8,
+}
+
+impl From<RawStream> for ProbeStream {
+    fn from(raw: RawStream) -> Self {
+        Self {
+            kind: match raw.codec_type.as_deref() {
+                Some("video") => StreamKind::Video,
+                Some("audio") => StreamKind::Audio,
+                Some("subtitle") => StreamKind::Subtitle,
+                _ => StreamKind::Other,
+            },
+            codec: raw.codec_name,
+            width: raw.width,
+            height: raw.height,
+            duration: parse_secs(raw.duration.as_deref()),
+            attached_pic: raw.disposition.attached_pic != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_video() {
+        let probe = MediaProbe::parse(
+            r#"{
+                "streams": [
+                    {"codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+                     "duration": "12.480000", "disposition": {"attached_pic": 0}},
+                    {"codec_type": "audio", "codec_name": "aac", "duration": "12.512000"}
+                ],
+                "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "12.512000"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(probe.duration(), Some(12.512));
+        assert!(probe.has_audio());
+        assert_eq!(probe.dimensions(), Some((1920, 1080)));
+        assert_eq!(probe.video().unwrap().codec.as_deref(), Some("h264"));
+    }
+
+    #[test]
+    fn test_parse_audio_with_cover_art() {
+        let probe = MediaProbe::parse(
+            r#"{
+                "streams": [
+                    {"codec_type": "audio", "codec_name": "mp3", "duration": "N/A"},
+                    {"codec_type": "video", "codec_name": "mjpeg", "width": 500, "height": 500,
+                     "disposition": {"attached_pic": 1}}
+                ],
+                "format": {"format_name": "mp3", "duration": "N/A"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(probe.duration(), None);
+        assert!(probe.has_audio());
+        assert_eq!(probe.video(), None);
+
+        // Streams count when the container doesn't record a length
+        let probe = MediaProbe::parse(
+            r#"{"streams": [{"codec_type": "video", "duration": "3.0"},
+                            {"codec_type": "audio", "duration": "4.5"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(probe.duration(), Some(4.5));
+        assert!(MediaProbe::parse("not json").is_err());
+    }
+}
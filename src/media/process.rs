@@ -0,0 +1,391 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io;
+use std::process::{Output, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tracing::{debug, info, warn};
+
+/// Variable marking the processes the bot starts, and the ones they start, with the bot's PID.
+const SUPERVISOR_ENV: &str = "GRABBY_SUPERVISOR";
+
+/// Time given to a killed process group's members to exit before their zombies are reaped.
+#[cfg(target_os = "linux")]
+const REAP_DELAY: Duration = Duration::from_secs(1);
+
+/// Starts commands in a process group of their own, marked as started by this process, so they
+/// can be killed along with everything they start.
+pub trait Supervised {
+    fn supervised(&mut self) -> &mut Self;
+}
+
+impl Supervised for Command {
+    fn supervised(&mut self) -> &mut Self {
+        #[cfg(unix)]
+        self.process_group(0);
+        self.env(SUPERVISOR_ENV, std::process::id().to_string())
+            .kill_on_drop(true)
+    }
+}
+
+impl Supervised for std::process::Command {
+    fn supervised(&mut self) -> &mut Self {
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(self, 0);
+        self.env(SUPERVISOR_ENV, std::process::id().to_string())
+    }
+}
+
+/// Kills the process group of a child when dropped before the child exited, e.g. once a timeout
+/// gives up on it. Dropping the child alone would leave the ffmpeg processes yt-dlp and
+/// gallery-dl start running.
+pub struct ProcessGroupGuard {
+    pgid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    /// Guards the group of a child started with [`Supervised::supervised`].
+    pub fn new(child: &Child) -> Self {
+        Self { pgid: child.id() }
+    }
+
+    /// Leaves the group alone, as the child exited.
+    pub fn disarm(mut self) {
+        self.pgid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.pgid.take() {
+            debug!("Killing process group {} of an abandoned child", pgid);
+            kill_group(pgid);
+        }
+    }
+}
+
+/// Runs the command like [`Command::output`], killing it and everything it started if the
+/// returned future is dropped before it exits.
+pub async fn output(command: &mut Command) -> io::Result<Output> {
+    let child = command
+        .supervised()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let group = ProcessGroupGuard::new(&child);
+    let output = child.wait_with_output().await;
+    group.disarm();
+    output
+}
+
+thread_local! {
+    /// Scope of the blocking work running on this thread, if it runs through [`run_blocking`]
+    static SCOPE: RefCell<Option<Arc<BlockingScope>>> = const { RefCell::new(None) };
+}
+
+/// Process groups started by blocking work, killed together once the work is given up on.
+#[derive(Default)]
+struct BlockingScope {
+    state: Mutex<ScopeState>,
+}
+
+#[derive(Default)]
+struct ScopeState {
+    groups: HashSet<u32>,
+    abandoned: bool,
+}
+
+impl BlockingScope {
+    fn lock(&self) -> MutexGuard<'_, ScopeState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Kills the groups still running and refuses to start further ones.
+    fn abandon(&self) {
+        let mut state = self.lock();
+        state.abandoned = true;
+        for pgid in state.groups.drain() {
+            debug!("Killing process group {} of abandoned blocking work", pgid);
+            kill_group(pgid);
+        }
+    }
+}
+
+/// Abandons a scope when dropped before its work finished.
+struct ScopeGuard(Option<Arc<BlockingScope>>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        if let Some(scope) = self.0.take() {
+            scope.abandon();
+        }
+    }
+}
+
+/// Runs blocking work on its own thread, killing the commands it runs with [`run`], and
+/// everything they started, if the returned future is dropped before the work is done, e.g. once
+/// a timeout gives up on it. Dropping the thread's handle alone would leave ffmpeg running.
+pub async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, tokio::task::JoinError> {
+    let scope = Arc::new(BlockingScope::default());
+    let mut guard = ScopeGuard(Some(Arc::clone(&scope)));
+    let result = tokio::task::spawn_blocking(move || {
+        // Left even if the work panics, as the thread goes on to run other blocking work
+        struct Leave;
+        impl Drop for Leave {
+            fn drop(&mut self) {
+                SCOPE.with(|current| current.borrow_mut().take());
+            }
+        }
+        SCOPE.with(|current| *current.borrow_mut() = Some(scope));
+        let _leave = Leave;
+        work()
+    })
+    .await;
+    guard.0 = None;
+    result
+}
+
+/// Runs the command like [`std::process::Command::output`], in a process group of its own that
+/// is killed if the [`run_blocking`] work running it is given up on.
+pub fn run(command: &mut std::process::Command) -> io::Result<Output> {
+    command
+        .supervised()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let scope = SCOPE.with(|current| current.borrow().clone());
+    let child = match &scope {
+        // Spawned under the lock, so the work can't be given up on before its group is known
+        Some(scope) => {
+            let mut state = scope.lock();
+            if state.abandoned {
+                return Err(io::Error::other("Blocking work was abandoned"));
+            }
+            let child = command.spawn()?;
+            state.groups.insert(child.id());
+            child
+        }
+        None => command.spawn()?,
+    };
+    let pgid = child.id();
+
+    let output = child.wait_with_output();
+    if let Some(scope) = &scope {
+        scope.lock().groups.remove(&pgid);
+    }
+    output
+}
+
+#[cfg(unix)]
+fn kill_group(pgid: u32) {
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(-(pgid as libc::pid_t), libc::SIGKILL) } != 0 {
+        debug!(
+            "Process group {} was already gone: {}",
+            pgid,
+            io::Error::last_os_error()
+        );
+    }
+    // Members whose parent exited are handed to PID 1, which the bot is in containers without an
+    // init process
+    #[cfg(target_os = "linux")]
+    if std::process::id() == 1 {
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                tokio::time::sleep(REAP_DELAY).await;
+                let reaped = reap_zombies(pgid);
+                if reaped > 0 {
+                    debug!("Reaped {} exited processes of group {}", reaped, pgid);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pgid: u32) {}
+
+/// Waits for the exited members of a killed process group that were handed to this process,
+/// leaving the group's leader to tokio, which started it. Returns how many there were.
+#[cfg(target_os = "linux")]
+fn reap_zombies(pgid: u32) -> usize {
+    let own = std::process::id();
+    let mut reaped = 0;
+    for pid in proc_pids() {
+        let Ok(stat) = std::fs::read_to_string(format!("/proc/{pid}/stat")) else {
+            continue;
+        };
+        if pid == pgid || parse_stat(&stat) != Some(('Z', own, pgid)) {
+            continue;
+        }
+        // SAFETY: waitpid is given no status pointer and doesn't block
+        if unsafe { libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), libc::WNOHANG) } > 0 {
+            reaped += 1;
+        }
+    }
+    reaped
+}
+
+/// Kills processes a previous run of the bot started and left behind, e.g. after it was killed
+/// mid-download. Returns how many there were.
+#[cfg(target_os = "linux")]
+pub fn kill_orphans() -> usize {
+    let own = std::process::id();
+    let mut killed = 0;
+    for pid in proc_pids() {
+        let Ok(environ) = std::fs::read(format!("/proc/{pid}/environ")) else {
+            continue;
+        };
+        let Some(supervisor) = supervisor_of(&environ) else {
+            continue;
+        };
+        // Processes of other running instances are theirs to handle
+        if supervisor == own || std::path::Path::new(&format!("/proc/{supervisor}")).exists() {
+            continue;
+        }
+
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) } == 0 {
+            info!(
+                "Killed process {} left over from a previous run (PID {})",
+                pid, supervisor
+            );
+            killed += 1;
+        } else {
+            warn!(
+                "Failed to kill process {} left over from a previous run: {}",
+                pid,
+                io::Error::last_os_error()
+            );
+        }
+    }
+    killed
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn kill_orphans() -> usize {
+    0
+}
+
+#[cfg(target_os = "linux")]
+fn proc_pids() -> Vec<u32> {
+    std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// PID of the bot that started a process, from the process's environment as listed in
+/// `/proc/<pid>/environ`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn supervisor_of(environ: &[u8]) -> Option<u32> {
+    environ
+        .split(|byte| *byte == 0)
+        .find_map(|var| {
+            var.strip_prefix(SUPERVISOR_ENV.as_bytes())?
+                .strip_prefix(b"=")
+        })
+        .and_then(|pid| std::str::from_utf8(pid).ok()?.parse().ok())
+}
+
+/// State, parent PID and process group from `/proc/<pid>/stat`, whose second field, the command
+/// name, is in parentheses and may contain spaces.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(stat: &str) -> Option<(char, u32, u32)> {
+    let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    let pgrp = fields.next()?.parse().ok()?;
+    Some((state, ppid, pgrp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supervisor_of() {
+        let environ = b"PATH=/usr/bin\0GRABBY_SUPERVISOR=4242\0HOME=/root\0";
+        assert_eq!(supervisor_of(environ), Some(4242));
+        assert_eq!(
+            supervisor_of(b"PATH=/usr/bin\0GRABBY_SUPERVISOR_X=1\0"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "1234 (ffmpeg (x) y) Z 1 1200 1200 0 -1 4194560 110 0 0 0";
+        assert_eq!(parse_stat(stat), Some(('Z', 1, 1200)));
+        assert_eq!(parse_stat("1234 (ffmpeg"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dropped_output_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("survived");
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("(sleep 1; touch {}) & wait", marker.display()));
+
+        let result = tokio::time::timeout(Duration::from_millis(200), output(&mut command)).await;
+        assert!(result.is_err());
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_abandoned_blocking_work_kills_its_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("survived");
+        let after = dir.path().join("continued");
+        let work = {
+            let (marker, after) = (marker.clone(), after.clone());
+            move || {
+                let script = format!("(sleep 1; touch {}) & wait", marker.display());
+                let first = run(std::process::Command::new("sh").arg("-c").arg(script));
+                // Commands started once the work was given up on are refused
+                let second = run(std::process::Command::new("touch").arg(&after));
+                (first, second)
+            }
+        };
+
+        let result = tokio::time::timeout(Duration::from_millis(200), run_blocking(work)).await;
+        assert!(result.is_err());
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+        assert!(!after.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_captures_stdout() {
+        let output = run(std::process::Command::new("echo").arg("hello")).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_captures_stdout() {
+        let output = output(Command::new("sh").arg("-c").arg("echo hi"))
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hi\n");
+    }
+}
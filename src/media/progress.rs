@@ -0,0 +1,262 @@
+use super::process::{ProcessGroupGuard, Supervised};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fmt;
+use std::process::{Output, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Marks the lines of yt-dlp's progress template among its other output.
+const PROGRESS_PREFIX: &str = "grabby-progress ";
+
+/// yt-dlp arguments printing download progress as one JSON line per update.
+pub const YT_DLP_PROGRESS_ARGS: [&str; 4] = [
+    "--progress",
+    "--newline",
+    "--progress-template",
+    "download:grabby-progress %(progress)j",
+];
+
+/// How far a download has come.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Progress {
+    /// Share of the download done, from 0 to 100
+    pub percent: f64,
+    pub eta: Option<Duration>,
+    /// Bytes per second
+    pub speed: Option<f64>,
+    /// Set while an upload of the media waits out a rate limit for this long
+    pub rate_limited: Option<Duration>,
+}
+
+impl Progress {
+    /// Parses a line of yt-dlp output, either of the JSON progress template or the default
+    /// `[download]  42.0% of 10.00MiB at 1.00MiB/s ETA 00:05` format.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        match line.strip_prefix(PROGRESS_PREFIX) {
+            Some(json) => Self::from_json(&serde_json::from_str(json).ok()?),
+            None => Self::from_text(line.strip_prefix("[download]")?),
+        }
+    }
+
+    fn from_json(progress: &Value) -> Option<Self> {
+        let downloaded = progress["downloaded_bytes"].as_f64()?;
+        let total = progress["total_bytes"]
+            .as_f64()
+            .or(progress["total_bytes_estimate"].as_f64())
+            .filter(|total| *total > 0.0)?;
+
+        Some(Self {
+            percent: (downloaded / total * 100.0).clamp(0.0, 100.0),
+            eta: progress["eta"]
+                .as_f64()
+                .map(|secs| Duration::from_secs_f64(secs.max(0.0))),
+            speed: progress["speed"].as_f64(),
+            rate_limited: None,
+        })
+    }
+
+    fn from_text(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let percent = words.next()?.strip_suffix('%')?.parse::<f64>().ok()?;
+        let eta = words
+            .skip_while(|word| *word != "ETA")
+            .nth(1)
+            .and_then(parse_clock);
+
+        Some(Self {
+            percent: percent.clamp(0.0, 100.0),
+            eta,
+            speed: None,
+            rate_limited: None,
+        })
+    }
+}
+
+/// Parses "05", "01:05" or "1:01:05" into a duration.
+fn parse_clock(value: &str) -> Option<Duration> {
+    value
+        .split(':')
+        .try_fold(0u64, |secs, part| {
+            Some(secs * 60 + part.parse::<u64>().ok()?)
+        })
+        .map(Duration::from_secs)
+}
+
+/// Publishes the latest progress of a download to whoever displays it.
+#[derive(Clone)]
+pub struct ProgressReporter(Arc<watch::Sender<Option<Progress>>>);
+
+impl ProgressReporter {
+    /// Creates a reporter and the receiver its updates go to. The receiver sees the sender
+    /// closed once every clone of the reporter is dropped.
+    pub fn channel() -> (Self, watch::Receiver<Option<Progress>>) {
+        let (sender, receiver) = watch::channel(None);
+        (Self(Arc::new(sender)), receiver)
+    }
+
+    pub fn report(&self, progress: Progress) {
+        self.0.send_replace(Some(progress));
+    }
+
+    /// Reports that the upload of the downloaded media is retried in `retry_after`.
+    pub fn rate_limited(&self, retry_after: Duration) {
+        self.report(Progress {
+            percent: 100.0,
+            rate_limited: Some(retry_after),
+            ..Default::default()
+        });
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressReporter")
+    }
+}
+
+impl PartialEq for ProgressReporter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ProgressReporter {}
+
+/// Runs yt-dlp writing the media to stdout, forwarding the progress lines of its stderr to
+/// `reporter` and leaving the rest as the output's stderr.
+pub async fn output_with_progress(
+    command: &mut Command,
+    reporter: Option<&ProgressReporter>,
+) -> Result<Output> {
+    let mut child = command
+        .args(YT_DLP_PROGRESS_ARGS)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .supervised()
+        .spawn()
+        .context("Failed to start yt-dlp")?;
+    let group = ProcessGroupGuard::new(&child);
+
+    let mut stdout = child.stdout.take().context("yt-dlp stdout is not piped")?;
+    let stderr = child.stderr.take().context("yt-dlp stderr is not piped")?;
+
+    let read_stdout = async {
+        let mut data = Vec::new();
+        stdout.read_to_end(&mut data).await.map(|_| data)
+    };
+    let read_stderr = async {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut errors = Vec::new();
+        let mut last = None;
+        while let Some(line) = lines.next_line().await? {
+            match Progress::parse_line(&line) {
+                Some(progress) => {
+                    if let Some(reporter) = reporter {
+                        reporter.report(progress);
+                    }
+                    last = Some(progress);
+                }
+                None => errors.push(line),
+            }
+        }
+        Ok::<_, std::io::Error>((errors.join("\n"), last))
+    };
+
+    let (stdout, stderr) = tokio::try_join!(read_stdout, read_stderr)?;
+    let status = child.wait().await?;
+    group.disarm();
+
+    let (stderr, last) = stderr;
+    if let Some(speed) = last.and_then(|progress| progress.speed) {
+        info!(
+            histogram.download_speed_bytes_per_second = speed,
+            "yt-dlp downloaded at {:.0} bytes/s", speed
+        );
+    }
+
+    Ok(Output {
+        status,
+        stdout,
+        stderr: stderr.into_bytes(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template_line() {
+        let line = r#"grabby-progress {"status": "downloading", "downloaded_bytes": 2500000, "total_bytes": 10000000, "eta": 5, "speed": 1048576.0}"#;
+        let progress = Progress::parse_line(line).unwrap();
+        assert_eq!(progress.percent, 25.0);
+        assert_eq!(progress.eta, Some(Duration::from_secs(5)));
+        assert_eq!(progress.speed, Some(1048576.0));
+    }
+
+    #[test]
+    fn test_parse_template_line_with_estimate() {
+        let line = r#"grabby-progress {"downloaded_bytes": 500, "total_bytes": null, "total_bytes_estimate": 1000.0, "eta": null}"#;
+        let progress = Progress::parse_line(line).unwrap();
+        assert_eq!(progress.percent, 50.0);
+        assert_eq!(progress.eta, None);
+
+        // Without any total there is nothing to report
+        let line = r#"grabby-progress {"downloaded_bytes": 500, "total_bytes": null}"#;
+        assert_eq!(Progress::parse_line(line), None);
+    }
+
+    #[test]
+    fn test_parse_default_line() {
+        let progress =
+            Progress::parse_line("[download]  42.3% of ~ 10.00MiB at  1.00MiB/s ETA 01:05")
+                .unwrap();
+        assert_eq!(progress.percent, 42.3);
+        assert_eq!(progress.eta, Some(Duration::from_secs(65)));
+
+        let progress = Progress::parse_line("[download] 100% of 10.00MiB in 00:00:09").unwrap();
+        assert_eq!(progress.percent, 100.0);
+        assert_eq!(progress.eta, None);
+    }
+
+    #[test]
+    fn test_parse_other_lines() {
+        assert_eq!(Progress::parse_line("ERROR: Unsupported URL"), None);
+        assert_eq!(
+            Progress::parse_line("[download] Destination: video.mp4"),
+            None
+        );
+        assert_eq!(Progress::parse_line("grabby-progress not json"), None);
+    }
+
+    #[test]
+    fn test_parse_clock() {
+        assert_eq!(parse_clock("05"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_clock("1:01:05"), Some(Duration::from_secs(3665)));
+        assert_eq!(parse_clock("Unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reporter_closes_with_last_clone() {
+        let (reporter, mut receiver) = ProgressReporter::channel();
+        let clone = reporter.clone();
+        assert_eq!(reporter, clone);
+
+        clone.report(Progress {
+            percent: 10.0,
+            ..Default::default()
+        });
+        drop(clone);
+        receiver.changed().await.unwrap();
+        assert_eq!(receiver.borrow_and_update().unwrap().percent, 10.0);
+
+        drop(reporter);
+        assert!(receiver.changed().await.is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::oneshot;
+
+/// What a download was started by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JobClass {
+    /// Links picked up from messages, e.g. in auto-embed channels
+    #[default]
+    Background,
+    /// A command someone is waiting on, e.g. `/embed`
+    Command,
+}
+
+/// Order of downloads waiting for a free slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Commands start before background downloads
+    #[default]
+    CommandsFirst,
+    /// Downloads start in the order they came in
+    Fifo,
+}
+
+impl QueuePolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "commands_first" => Some(Self::CommandsFirst),
+            "fifo" => Some(Self::Fifo),
+            _ => None,
+        }
+    }
+}
+
+struct Waiter {
+    /// Tier priority, then whether the policy lets the download skip ahead of its class
+    rank: (u8, bool),
+    /// Arrival order, earlier first among equal ranks
+    seq: u64,
+    ready: oneshot::Sender<()>,
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank
+            .cmp(&other.rank)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    waiting: BinaryHeap<Waiter>,
+    next_seq: u64,
+}
+
+/// Limits how many downloads run at once, starting waiting ones by priority as slots free up.
+pub struct DownloadQueue {
+    max_running: usize,
+    policy: QueuePolicy,
+    state: Mutex<State>,
+}
+
+impl DownloadQueue {
+    pub fn new(max_running: usize, policy: QueuePolicy) -> Self {
+        Self {
+            max_running: max_running.max(1),
+            policy,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Waits for a free slot, which is taken until the returned guard is dropped. Downloads of
+    /// a higher `priority` go first, then commands if the policy prefers them.
+    pub async fn enter(&self, class: JobClass, priority: u8) -> QueueSlot<'_> {
+        let ready = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            // Waiting downloads are only left behind while all slots are taken
+            if state.running < self.max_running && state.waiting.is_empty() {
+                state.running += 1;
+                return QueueSlot { queue: self };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let skips_ahead =
+                self.policy == QueuePolicy::CommandsFirst && class == JobClass::Command;
+            state.waiting.push(Waiter {
+                rank: (priority, skips_ahead),
+                seq,
+                ready: sender,
+            });
+            receiver
+        };
+
+        let mut pending = Pending {
+            queue: self,
+            ready: Some(ready),
+        };
+        if let Some(ready) = pending.ready.as_mut() {
+            // The sender is only dropped along with the queue, which outlives this borrow
+            let _ = ready.await;
+        }
+        pending.ready = None;
+        QueueSlot { queue: self }
+    }
+
+    /// Downloads waiting for a slot, including ones whose caller has given up.
+    pub fn waiting(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .waiting
+            .len()
+    }
+
+    /// Hands a freed slot to the next download still waiting for one.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        while let Some(waiter) = state.waiting.pop() {
+            if waiter.ready.send(()).is_ok() {
+                return;
+            }
+        }
+        state.running -= 1;
+    }
+}
+
+/// A download waiting for its slot, giving the slot back if it stops waiting after getting one.
+struct Pending<'a> {
+    queue: &'a DownloadQueue,
+    ready: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if let Some(mut ready) = self.ready.take() {
+            ready.close();
+            if ready.try_recv().is_ok() {
+                self.queue.release();
+            }
+        }
+    }
+}
+
+/// A taken slot of the queue, freed on drop.
+pub struct QueueSlot<'a> {
+    queue: &'a DownloadQueue,
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+    use std::time::Duration;
+
+    async fn start_order(policy: QueuePolicy) -> Vec<&'static str> {
+        let queue = DownloadQueue::new(1, policy);
+        let order = Mutex::new(Vec::new());
+        let running = queue.enter(JobClass::Background, 0).await;
+
+        let wait = |name, class, priority| {
+            let queue = &queue;
+            let order = &order;
+            async move {
+                let _slot = queue.enter(class, priority).await;
+                order.lock().unwrap().push(name);
+            }
+        };
+        tokio::join!(
+            wait("background", JobClass::Background, 0),
+            wait("command", JobClass::Command, 0),
+            wait("later background", JobClass::Background, 0),
+            wait("premium", JobClass::Background, 1),
+            async move {
+                tokio::task::yield_now().await;
+                drop(running);
+            }
+        );
+        order.into_inner().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_waiting_downloads_start_by_priority() {
+        assert_eq!(
+            start_order(QueuePolicy::CommandsFirst).await,
+            vec!["premium", "command", "background", "later background"]
+        );
+        assert_eq!(
+            start_order(QueuePolicy::Fifo).await,
+            vec!["premium", "background", "command", "later background"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waits_free_their_slot() {
+        let queue = DownloadQueue::new(1, QueuePolicy::default());
+        let running = queue.enter(JobClass::Background, 0).await;
+
+        let gave_up =
+            tokio::time::timeout(Duration::from_millis(10), queue.enter(JobClass::Command, 0))
+                .await;
+        assert!(gave_up.is_err());
+        assert_eq!(queue.waiting(), 1);
+
+        drop(running);
+        assert_eq!(queue.waiting(), 0);
+        assert!(queue.enter(JobClass::Command, 0).now_or_never().is_some());
+    }
+}
@@ -1,41 +1,351 @@
+use super::{probe::MediaProbe, process};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::io::Write;
 use std::process::Command;
 use tempfile::NamedTempFile;
-use tracing::{debug, info};
-
-fn get_video_duration(input_path: &std::path::Path) -> Result<f64> {
-    let output = Command::new("ffprobe")
-        .arg("-v")
-        .arg("error")
-        .arg("-show_entries")
-        .arg("format=duration")
-        .arg("-of")
-        .arg("default=noprint_wrappers=1:nokey=1")
+use tracing::{debug, info, warn};
+
+/// Output preset of the resize pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeProfile {
+    /// Only shrink files that exceed the destination's upload limit
+    #[default]
+    Standard,
+    /// Always compress aggressively to a sticker-sized file (≤512 KB, ≤320px)
+    Tiny,
+}
+
+impl ResizeProfile {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "standard" => Some(Self::Standard),
+            "tiny" => Some(Self::Tiny),
+            _ => None,
+        }
+    }
+
+    /// Size cap in bytes imposed by the profile on top of the upload limit.
+    pub fn max_bytes(self) -> Option<u64> {
+        match self {
+            Self::Standard => None,
+            Self::Tiny => Some(512_000),
+        }
+    }
+
+    /// Size files are fitted to, the upload limit `max_size_bytes` or the profile's tighter cap.
+    pub fn size_limit(self, max_size_bytes: u64) -> u64 {
+        self.max_bytes()
+            .map_or(max_size_bytes, |cap| cap.min(max_size_bytes))
+    }
+
+    /// Whether files must be processed even when they already fit the size limit.
+    pub fn always_process(self) -> bool {
+        self == Self::Tiny
+    }
+
+    /// Maximum length of the longest edge of videos, whatever their orientation.
+    fn video_max_edge(self) -> u32 {
+        match self {
+            Self::Standard => 720,
+            Self::Tiny => 320,
+        }
+    }
+
+    /// Bounding box and quality of the first image encode.
+    fn image_params(self) -> ImageParams {
+        match self {
+            Self::Standard => ImageParams {
+                max_width: 1280,
+                max_height: 720,
+                quality: 85,
+            },
+            Self::Tiny => ImageParams {
+                max_width: 320,
+                max_height: 320,
+                quality: 60,
+            },
+        }
+    }
+}
+
+/// Output of a single image encode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageParams {
+    max_width: u32,
+    max_height: u32,
+    quality: u32,
+}
+
+impl ImageParams {
+    /// Parameters for a retry after an encode came out too large, with a quarter less of each
+    /// edge and lower quality.
+    fn shrink(self) -> Self {
+        Self {
+            max_width: (self.max_width * 3 / 4).max(16),
+            max_height: (self.max_height * 3 / 4).max(16),
+            quality: self.quality.saturating_sub(15).max(20),
+        }
+    }
+}
+
+/// ffmpeg arguments for encoding an image at `quality` (0-100) into `ext`. The JPEG encoder
+/// ignores `-quality` and takes a `-q:v` scale from 2 (best) to 31 instead, PNG is lossless.
+fn image_quality_args(ext: &str, quality: u32) -> Vec<String> {
+    match ext {
+        "jpg" => {
+            let qscale = 2 + (100 - quality.min(100)) * 29 / 100;
+            vec!["-q:v".to_string(), qscale.to_string()]
+        }
+        "webp" => vec!["-quality".to_string(), quality.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// Scales the longest edge down to `max_edge`, keeping the aspect ratio. Both edges come out
+/// even, as 4:2:0 encoders require, rounding an odd longest edge down.
+fn video_scale_filter(max_edge: u32) -> String {
+    let max_edge = max_edge / 2 * 2;
+    format!(
+        "scale=w='if(gte(iw,ih),min({max_edge},trunc(iw/2)*2),-2)':h='if(gte(iw,ih),-2,min({max_edge},trunc(ih/2)*2))'"
+    )
+}
+
+/// Video codec of transcoded output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VideoCodec {
+    /// H.264/AAC in MP4, playable everywhere
+    #[default]
+    H264,
+    /// VP9/Opus in WebM
+    Vp9,
+    /// AV1/Opus in WebM, smallest output but slowest to encode
+    Av1,
+}
+
+impl VideoCodec {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "h264" => Some(Self::H264),
+            "vp9" => Some(Self::Vp9),
+            "av1" => Some(Self::Av1),
+            _ => None,
+        }
+    }
+
+    /// File extension of the container the codec is written to.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::H264 => "mp4",
+            Self::Vp9 | Self::Av1 => "webm",
+        }
+    }
+
+    /// Constant rate factor of the first encode attempt.
+    fn initial_crf(self, profile: ResizeProfile) -> u8 {
+        match (self, profile) {
+            (Self::H264, ResizeProfile::Standard) => 23,
+            (Self::H264, ResizeProfile::Tiny) => 30,
+            (Self::Vp9, ResizeProfile::Standard) => 33,
+            (Self::Vp9, ResizeProfile::Tiny) => 40,
+            (Self::Av1, ResizeProfile::Standard) => 35,
+            (Self::Av1, ResizeProfile::Tiny) => 42,
+        }
+    }
+
+    fn max_crf(self) -> u8 {
+        match self {
+            Self::H264 => 51,
+            Self::Vp9 | Self::Av1 => 63,
+        }
+    }
+
+    fn video_args(self) -> &'static [&'static str] {
+        match self {
+            Self::H264 => &["-c:v", "libx264", "-preset", "slow"],
+            Self::Vp9 => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-deadline",
+                "good",
+                "-cpu-used",
+                "2",
+                "-row-mt",
+                "1",
+            ],
+            Self::Av1 => &["-c:v", "libaom-av1", "-cpu-used", "6", "-row-mt", "1"],
+        }
+    }
+
+    fn audio_encoder(self) -> &'static str {
+        match self {
+            Self::H264 => "aac",
+            Self::Vp9 | Self::Av1 => "libopus",
+        }
+    }
+}
+
+/// Container videos are delivered in, set per server or channel.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputContainer {
+    /// Merged into MP4, preferring H.264 so Discord plays it inline
+    #[default]
+    Mp4,
+    /// Merged into WebM from VP9 or AV1 streams when the source has them
+    Webm,
+    /// Whatever the source serves, without merging into a fixed container
+    Original,
+}
+
+impl OutputContainer {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mp4" => Some(Self::Mp4),
+            "webm" => Some(Self::Webm),
+            "original" | "passthrough" => Some(Self::Original),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+            Self::Original => "original",
+        }
+    }
+
+    /// Codec for resizing `filename` so it stays in this container, `preferred` if it fits.
+    pub fn codec(self, filename: &str, preferred: VideoCodec) -> VideoCodec {
+        let webm = |preferred: VideoCodec| {
+            if preferred.extension() == "webm" {
+                preferred
+            } else {
+                VideoCodec::Vp9
+            }
+        };
+        match self {
+            Self::Mp4 => VideoCodec::H264,
+            Self::Webm => webm(preferred),
+            Self::Original => {
+                match std::path::Path::new(filename)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(str::to_lowercase)
+                    .as_deref()
+                {
+                    Some("webm") => webm(preferred),
+                    Some("mp4") => VideoCodec::H264,
+                    _ => preferred,
+                }
+            }
+        }
+    }
+}
+
+/// Name of a video after transcoding it with `codec`.
+pub fn transcoded_filename(filename: &str, codec: VideoCodec) -> String {
+    let stem = std::path::Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("video");
+    format!("{}.{}", stem, codec.extension())
+}
+
+/// Encodes attempted before giving up on reaching the size target.
+const MAX_ENCODE_ATTEMPTS: u32 = 3;
+
+/// Rate control of a single video encode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EncodeParams {
+    crf: u8,
+    /// Peak video bitrate in bits per second
+    video_bitrate: u64,
+    /// Audio bitrate in bits per second, `None` when the input has no audio
+    audio_bitrate: Option<u64>,
+}
+
+impl EncodeParams {
+    /// Parameters for a retry after an encode came out at `actual_size` bytes.
+    fn shrink(self, actual_size: u64, max_size_bytes: u64, codec: VideoCodec) -> Self {
+        // Aim a little below the limit, as the new size scales only roughly with bitrate
+        let scale = |bitrate: u64| (bitrate * max_size_bytes / actual_size.max(1)) * 9 / 10;
+
+        Self {
+            crf: (self.crf + 4).min(codec.max_crf()),
+            video_bitrate: scale(self.video_bitrate).max(1000),
+            audio_bitrate: self.audio_bitrate.map(|b| scale(b).max(1000)),
+        }
+    }
+}
+
+fn encode_video(
+    input_path: &std::path::Path,
+    output_path: &std::path::Path,
+    scale_filter: &str,
+    codec: VideoCodec,
+    params: &EncodeParams,
+) -> Result<()> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-i")
         .arg(input_path)
-        .output()?;
+        .arg("-vf")
+        .arg(scale_filter)
+        .args(codec.video_args())
+        .arg("-crf")
+        .arg(params.crf.to_string());
+
+    // libx264 caps CRF with a VBV buffer, libvpx/libaom take the cap as constrained quality target
+    match codec {
+        VideoCodec::H264 => command
+            .arg("-maxrate")
+            .arg(format!("{}k", params.video_bitrate / 1000))
+            .arg("-bufsize")
+            .arg(format!("{}k", params.video_bitrate * 2 / 1000))
+            .arg("-movflags")
+            .arg("+faststart"),
+        VideoCodec::Vp9 | VideoCodec::Av1 => command
+            .arg("-b:v")
+            .arg(format!("{}k", params.video_bitrate / 1000)),
+    };
+
+    match params.audio_bitrate {
+        Some(audio_bitrate) => command
+            .arg("-c:a")
+            .arg(codec.audio_encoder())
+            .arg("-b:a")
+            .arg(format!("{}k", audio_bitrate / 1000)),
+        None => command.arg("-an"),
+    };
+
+    let output = process::run(command.arg("-y").arg(output_path))?;
 
     if !output.status.success() {
         anyhow::bail!(
-            "Failed to get video duration: {}",
+            "Failed to resize video: {}",
             String::from_utf8_lossy(&output.stderr)
         );
     }
 
-    let duration_str = String::from_utf8_lossy(&output.stdout);
-    let duration: f64 = duration_str
-        .trim()
-        .parse()
-        .context("Failed to parse video duration")?;
-
-    Ok(duration)
+    Ok(())
 }
 
-pub fn resize_media_file(data: &[u8], filename: &str, max_size_mb: u64) -> Result<Vec<u8>> {
+/// Resizes a video to fit within `max_size_bytes` and the bounds of `profile`, encoding with `codec`.
+///
+/// Transcoded output is named with [`transcoded_filename`].
+pub fn resize_media_file_with_profile(
+    data: &[u8],
+    filename: &str,
+    max_size_bytes: u64,
+    profile: ResizeProfile,
+    codec: VideoCodec,
+) -> Result<Vec<u8>> {
     let current_size = data.len() as u64;
-    let max_size_bytes = max_size_mb * 1_000_000;
+    let max_size_bytes = profile.size_limit(max_size_bytes);
 
-    if current_size <= max_size_bytes {
+    if current_size <= max_size_bytes && !profile.always_process() {
         debug!(
             "File {} ({} bytes) is within size limit",
             filename, current_size
@@ -44,101 +354,71 @@ pub fn resize_media_file(data: &[u8], filename: &str, max_size_mb: u64) -> Resul
     }
 
     info!(
-        "Resizing {} ({} bytes, {:.2} MB) to fit within {} MB limit",
+        "Resizing {} ({} bytes, {:.2} MB) to fit within {} bytes ({:?} profile, {:?})",
         filename,
         current_size,
         current_size as f64 / 1_000_000.0,
-        max_size_mb
+        max_size_bytes,
+        profile,
+        codec
     );
 
     let mut input_file = NamedTempFile::new()?;
     input_file.write_all(data)?;
     let input_path = input_file.path();
 
-    let output_ext = if filename.ends_with(".mp4") {
-        "mp4"
-    } else if filename.ends_with(".webm") {
-        "webm"
-    } else if filename.ends_with(".mov") {
-        "mov"
-    } else {
-        "mp4"
-    };
-
-    let output_file = NamedTempFile::with_suffix(format!(".{}", output_ext))?;
+    let output_file = NamedTempFile::with_suffix(format!(".{}", codec.extension()))?;
     let output_path = output_file.path();
 
-    let duration = get_video_duration(input_path)?;
-    let target_size_bytes = max_size_mb * 1_000_000;
-    let target_bitrate = (target_size_bytes * 8) / duration as u64;
-
-    let video_bitrate = target_bitrate * 9 / 10;
-    let audio_bitrate = target_bitrate / 10;
+    let probe = MediaProbe::probe(input_path)?;
+    let duration = probe.duration().context("Failed to get media duration")?;
+    let target_bitrate = (max_size_bytes * 8) / (duration as u64).max(1);
+    let scale_filter = video_scale_filter(profile.video_max_edge());
+
+    // Audio-less inputs get the whole budget for video
+    let mut params = if probe.has_audio() {
+        EncodeParams {
+            crf: codec.initial_crf(profile),
+            video_bitrate: target_bitrate * 9 / 10,
+            audio_bitrate: Some(target_bitrate / 10),
+        }
+    } else {
+        EncodeParams {
+            crf: codec.initial_crf(profile),
+            video_bitrate: target_bitrate,
+            audio_bitrate: None,
+        }
+    };
 
     info!(
-        "Video duration: {:.2}s, target bitrate: {} kbps (video: {} kbps, audio: {} kbps)",
+        "Video duration: {:.2}s, target bitrate: {} kbps (video: {} kbps, audio: {:?} kbps)",
         duration,
         target_bitrate / 1000,
-        video_bitrate / 1000,
-        audio_bitrate / 1000
+        params.video_bitrate / 1000,
+        params.audio_bitrate.map(|b| b / 1000)
     );
 
-    let pass1_output = NamedTempFile::with_suffix(format!(".{}.log", output_ext))?;
-
-    let pass1 = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_path)
-        .arg("-vf")
-        .arg("scale='min(720\\,iw*2/2):min(480\\,ih*2/2):force_original_aspect_ratio=decrease'")
-        .arg("-c:v")
-        .arg("libx264")
-        .arg("-preset")
-        .arg("slow")
-        .arg("-b:v")
-        .arg(format!("{}k", video_bitrate / 1000))
-        .arg("-pass")
-        .arg("1")
-        .arg("-f")
-        .arg("null")
-        .arg("-y")
-        .arg(pass1_output.path())
-        .output()?;
-
-    if !pass1.status.success() {
-        anyhow::bail!(
-            "Failed to encode video pass 1: {}",
-            String::from_utf8_lossy(&pass1.stderr)
-        );
-    }
+    // One capped CRF pass usually lands under the limit; only re-encode when it doesn't
+    for attempt in 1..=MAX_ENCODE_ATTEMPTS {
+        encode_video(input_path, output_path, &scale_filter, codec, &params)?;
+
+        let encoded_size = std::fs::metadata(output_path)?.len();
+        if encoded_size <= max_size_bytes {
+            break;
+        }
+        if attempt == MAX_ENCODE_ATTEMPTS {
+            warn!(
+                "Giving up on fitting {} within {} bytes after {} attempts, it came out at {} bytes",
+                filename, max_size_bytes, attempt, encoded_size
+            );
+            break;
+        }
 
-    let output = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_path)
-        .arg("-vf")
-        .arg("scale='min(720\\,iw*2/2):min(480\\,ih*2/2):force_original_aspect_ratio=decrease'")
-        .arg("-c:v")
-        .arg("libx264")
-        .arg("-preset")
-        .arg("slow")
-        .arg("-b:v")
-        .arg(format!("{}k", video_bitrate / 1000))
-        .arg("-pass")
-        .arg("2")
-        .arg("-c:a")
-        .arg("aac")
-        .arg("-b:a")
-        .arg(format!("{}k", audio_bitrate / 1000))
-        .arg("-movflags")
-        .arg("+faststart")
-        .arg("-y")
-        .arg(output_path)
-        .output()?;
-
-    if !output.status.success() {
-        anyhow::bail!(
-            "Failed to resize video: {}",
-            String::from_utf8_lossy(&output.stderr)
+        debug!(
+            "Attempt {} produced {} bytes with {:?}, re-encoding",
+            attempt, encoded_size, params
         );
+        params = params.shrink(encoded_size, max_size_bytes, codec);
     }
 
     let resized_data = std::fs::read(output_path)?;
@@ -154,11 +434,51 @@ pub fn resize_media_file(data: &[u8], filename: &str, max_size_mb: u64) -> Resul
     Ok(resized_data)
 }
 
-pub fn resize_image_file(data: &[u8], filename: &str, max_size_mb: u64) -> Result<Vec<u8>> {
+fn encode_image(
+    input_path: &std::path::Path,
+    output_path: &std::path::Path,
+    output_ext: &str,
+    params: &ImageParams,
+) -> Result<()> {
+    let ImageParams {
+        max_width,
+        max_height,
+        quality,
+    } = params;
+    let output = process::run(
+        Command::new("ffmpeg")
+        .arg("-i")
+        .arg(input_path)
+        .arg("-vf")
+        .arg(format!(
+            "scale=iw*min(1\\,min({max_width}/iw\\,{max_height}/ih)):ih*min(1\\,min({max_width}/iw\\,{max_height}/ih))"
+        ))
+        .args(image_quality_args(output_ext, *quality))
+        .arg("-y")
+        .arg(output_path),
+    )?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to resize image: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Resizes an image to fit within `max_size_bytes` and the bounds of `profile`.
+pub fn resize_image_file_with_profile(
+    data: &[u8],
+    filename: &str,
+    max_size_bytes: u64,
+    profile: ResizeProfile,
+) -> Result<Vec<u8>> {
     let current_size = data.len() as u64;
-    let max_size_bytes = max_size_mb * 1_000_000;
+    let max_size_bytes = profile.size_limit(max_size_bytes);
 
-    if current_size <= max_size_bytes {
+    if current_size <= max_size_bytes && !profile.always_process() {
         debug!(
             "File {} ({} bytes) is within size limit",
             filename, current_size
@@ -167,11 +487,12 @@ pub fn resize_image_file(data: &[u8], filename: &str, max_size_mb: u64) -> Resul
     }
 
     info!(
-        "Resizing {} ({} bytes, {:.2} MB) to fit within {} MB limit",
+        "Resizing {} ({} bytes, {:.2} MB) to fit within {} bytes ({:?} profile)",
         filename,
         current_size,
         current_size as f64 / 1_000_000.0,
-        max_size_mb
+        max_size_bytes,
+        profile
     );
 
     let mut input_file = NamedTempFile::new()?;
@@ -191,22 +512,28 @@ pub fn resize_image_file(data: &[u8], filename: &str, max_size_mb: u64) -> Resul
     let output_file = NamedTempFile::with_suffix(format!(".{}", output_ext))?;
     let output_path = output_file.path();
 
-    let output = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_path)
-        .arg("-vf")
-        .arg("scale=iw*min(1\\,min(1280/iw\\,720/ih)):ih*min(1\\,min(1280/iw\\,720/ih))")
-        .arg("-quality")
-        .arg("85")
-        .arg("-y")
-        .arg(output_path)
-        .output()?;
+    // Output that doesn't fit is encoded again, smaller and at lower quality
+    let mut params = profile.image_params();
+    for attempt in 1..=MAX_ENCODE_ATTEMPTS {
+        encode_image(input_path, output_path, output_ext, &params)?;
+
+        let encoded_size = std::fs::metadata(output_path)?.len();
+        if encoded_size <= max_size_bytes {
+            break;
+        }
+        if attempt == MAX_ENCODE_ATTEMPTS {
+            warn!(
+                "Giving up on fitting {} within {} bytes after {} attempts, it came out at {} bytes",
+                filename, max_size_bytes, attempt, encoded_size
+            );
+            break;
+        }
 
-    if !output.status.success() {
-        anyhow::bail!(
-            "Failed to resize image: {}",
-            String::from_utf8_lossy(&output.stderr)
+        debug!(
+            "Attempt {} produced {} bytes with {:?}, re-encoding",
+            attempt, encoded_size, params
         );
+        params = params.shrink();
     }
 
     let resized_data = std::fs::read(output_path)?;
@@ -233,7 +560,8 @@ mod tests {
     #[test]
     fn test_resize_image_file_within_limit() {
         let data = create_small_test_data();
-        let result = resize_image_file(&data, "test.jpg", 10);
+        let result =
+            resize_image_file_with_profile(&data, "test.jpg", 10_000_000, ResizeProfile::Standard);
 
         assert!(result.is_ok());
         let resized = result.unwrap();
@@ -244,17 +572,144 @@ mod tests {
     #[test]
     fn test_resize_image_file_exactly_at_limit() {
         let data = vec![0; 10_000_000];
-        let result = resize_image_file(&data, "test.jpg", 10);
+        let result =
+            resize_image_file_with_profile(&data, "test.jpg", 10_000_000, ResizeProfile::Standard);
 
         assert!(result.is_ok());
         let resized = result.unwrap();
         assert_eq!(resized.len(), data.len());
     }
 
+    #[test]
+    fn test_encode_params_shrink() {
+        let params = EncodeParams {
+            crf: 23,
+            video_bitrate: 900_000,
+            audio_bitrate: Some(100_000),
+        };
+
+        let shrunk = params.shrink(20_000_000, 10_000_000, VideoCodec::H264);
+        assert_eq!(shrunk.crf, 27);
+        assert_eq!(shrunk.video_bitrate, 405_000);
+        assert_eq!(shrunk.audio_bitrate, Some(45_000));
+
+        let capped =
+            EncodeParams { crf: 50, ..params }.shrink(20_000_000, 10_000_000, VideoCodec::H264);
+        assert_eq!(capped.crf, 51);
+
+        let silent = EncodeParams {
+            audio_bitrate: None,
+            ..params
+        };
+        assert_eq!(
+            silent
+                .shrink(20_000_000, 10_000_000, VideoCodec::H264)
+                .audio_bitrate,
+            None
+        );
+    }
+
+    #[test]
+    fn test_image_params_shrink() {
+        let params = ResizeProfile::Tiny.image_params().shrink();
+        assert_eq!(
+            params,
+            ImageParams {
+                max_width: 240,
+                max_height: 240,
+                quality: 45,
+            }
+        );
+
+        let floor = ImageParams {
+            max_width: 20,
+            max_height: 20,
+            quality: 25,
+        };
+        assert_eq!(
+            floor.shrink(),
+            ImageParams {
+                max_width: 16,
+                max_height: 16,
+                quality: 20,
+            }
+        );
+    }
+
+    #[test]
+    fn test_image_quality_args() {
+        assert_eq!(image_quality_args("jpg", 100), ["-q:v", "2"]);
+        assert_eq!(image_quality_args("jpg", 85), ["-q:v", "6"]);
+        assert_eq!(image_quality_args("jpg", 20), ["-q:v", "25"]);
+        assert_eq!(image_quality_args("jpg", 0), ["-q:v", "31"]);
+        assert_eq!(image_quality_args("webp", 85), ["-quality", "85"]);
+        assert!(image_quality_args("png", 85).is_empty());
+    }
+
+    #[test]
+    fn test_video_codec_from_name() {
+        assert_eq!(VideoCodec::from_name("H264"), Some(VideoCodec::H264));
+        assert_eq!(VideoCodec::from_name("vp9"), Some(VideoCodec::Vp9));
+        assert_eq!(VideoCodec::from_name("av1"), Some(VideoCodec::Av1));
+        assert_eq!(VideoCodec::from_name("hevc"), None);
+    }
+
+    #[test]
+    fn test_transcoded_filename() {
+        assert_eq!(transcoded_filename("abc.mov", VideoCodec::H264), "abc.mp4");
+        assert_eq!(transcoded_filename("abc.mp4", VideoCodec::Vp9), "abc.webm");
+        assert_eq!(transcoded_filename("abc.mp4", VideoCodec::Av1), "abc.webm");
+    }
+
+    #[test]
+    fn test_output_container_codec() {
+        use OutputContainer::*;
+        assert_eq!(OutputContainer::from_name("passthrough"), Some(Original));
+        assert_eq!(OutputContainer::from_name("mkv"), None);
+
+        assert_eq!(Mp4.codec("a.webm", VideoCodec::Vp9), VideoCodec::H264);
+        assert_eq!(Webm.codec("a.mp4", VideoCodec::H264), VideoCodec::Vp9);
+        assert_eq!(Webm.codec("a.mp4", VideoCodec::Av1), VideoCodec::Av1);
+        // The source's container is kept, other ones get the preferred codec
+        assert_eq!(Original.codec("a.WEBM", VideoCodec::H264), VideoCodec::Vp9);
+        assert_eq!(Original.codec("a.mp4", VideoCodec::Vp9), VideoCodec::H264);
+        assert_eq!(Original.codec("a.mkv", VideoCodec::Av1), VideoCodec::Av1);
+    }
+
+    #[test]
+    fn test_shrink_respects_codec_crf_range() {
+        let params = EncodeParams {
+            crf: 60,
+            video_bitrate: 900_000,
+            audio_bitrate: None,
+        };
+        assert_eq!(params.shrink(2, 1, VideoCodec::Vp9).crf, 63);
+        assert_eq!(params.shrink(2, 1, VideoCodec::H264).crf, 51);
+    }
+
+    #[test]
+    fn test_video_scale_filter_caps_longest_edge() {
+        assert_eq!(
+            video_scale_filter(720),
+            "scale=w='if(gte(iw,ih),min(720,trunc(iw/2)*2),-2)':h='if(gte(iw,ih),-2,min(720,trunc(ih/2)*2))'"
+        );
+        // Odd sources and limits still come out with even edges
+        assert_eq!(
+            video_scale_filter(321),
+            "scale=w='if(gte(iw,ih),min(320,trunc(iw/2)*2),-2)':h='if(gte(iw,ih),-2,min(320,trunc(ih/2)*2))'"
+        );
+    }
+
     #[test]
     fn test_resize_media_file_within_limit() {
         let data = create_small_test_data();
-        let result = resize_media_file(&data, "test.mp4", 10);
+        let result = resize_media_file_with_profile(
+            &data,
+            "test.mp4",
+            10_000_000,
+            ResizeProfile::Standard,
+            VideoCodec::H264,
+        );
 
         assert!(result.is_ok());
         let resized = result.unwrap();
@@ -265,18 +720,57 @@ mod tests {
     #[test]
     fn test_resize_media_file_exactly_at_limit() {
         let data = vec![0; 10_000_000];
-        let result = resize_media_file(&data, "test.mp4", 10);
+        let result = resize_media_file_with_profile(
+            &data,
+            "test.mp4",
+            10_000_000,
+            ResizeProfile::Standard,
+            VideoCodec::H264,
+        );
 
         assert!(result.is_ok());
         let resized = result.unwrap();
         assert_eq!(resized.len(), data.len());
     }
 
+    #[test]
+    fn test_resize_profile_from_name() {
+        assert_eq!(
+            ResizeProfile::from_name("standard"),
+            Some(ResizeProfile::Standard)
+        );
+        assert_eq!(ResizeProfile::from_name("TINY"), Some(ResizeProfile::Tiny));
+        assert_eq!(ResizeProfile::from_name("huge"), None);
+    }
+
+    #[test]
+    fn test_resize_profile_tiny_limits() {
+        assert_eq!(ResizeProfile::Tiny.max_bytes(), Some(512_000));
+        assert!(ResizeProfile::Tiny.always_process());
+        assert_eq!(ResizeProfile::Standard.max_bytes(), None);
+        assert!(!ResizeProfile::Standard.always_process());
+        assert_eq!(ResizeProfile::Tiny.size_limit(10_000_000), 512_000);
+        assert_eq!(ResizeProfile::Tiny.size_limit(100_000), 100_000);
+        assert_eq!(ResizeProfile::Standard.size_limit(10_000_000), 10_000_000);
+    }
+
+    #[test]
+    #[ignore = "Requires ffmpeg installed"]
+    fn test_resize_image_file_tiny_profile() {
+        let data = vec![0; 100_000];
+        let result =
+            resize_image_file_with_profile(&data, "test.jpg", 10_000_000, ResizeProfile::Tiny);
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().len() <= 512_000);
+    }
+
     #[test]
     #[ignore = "Requires ffmpeg installed"]
     fn test_resize_image_file_exceeds_limit() {
         let data = vec![0; 30_000_000];
-        let result = resize_image_file(&data, "test.jpg", 10);
+        let result =
+            resize_image_file_with_profile(&data, "test.jpg", 10_000_000, ResizeProfile::Standard);
 
         assert!(result.is_ok());
         let resized = result.unwrap();
@@ -287,7 +781,13 @@ mod tests {
     #[ignore = "Requires ffmpeg installed"]
     fn test_resize_media_file_exceeds_limit() {
         let data = vec![0; 30_000_000];
-        let result = resize_media_file(&data, "test.mp4", 10);
+        let result = resize_media_file_with_profile(
+            &data,
+            "test.mp4",
+            10_000_000,
+            ResizeProfile::Standard,
+            VideoCodec::H264,
+        );
 
         assert!(result.is_ok());
         let resized = result.unwrap();
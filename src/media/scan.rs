@@ -0,0 +1,268 @@
+use super::process::{ProcessGroupGuard, Supervised};
+use super::types::MediaFile;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, warn};
+
+/// Bytes sent to clamd per INSTREAM chunk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Exit code of a scan command for a flagged file, as ClamAV's scanners use it.
+const FLAGGED_EXIT_CODE: i32 = 1;
+
+/// Where downloaded files are scanned before they are uploaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scanner {
+    /// clamd listening on a Unix socket, which files are streamed to
+    Clamd(PathBuf),
+    /// Program files are piped to on stdin, exiting with 0 for clean files and 1 for flagged ones
+    Command(Vec<String>),
+}
+
+/// Outcome of scanning a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    /// Flagged by the scanner, with what it found
+    Flagged(String),
+}
+
+/// Refusal to upload media whose every file the scanner flagged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flagged {
+    pub filename: String,
+    pub signature: String,
+}
+
+impl Flagged {
+    /// The flagged file `error` is about, if it is one.
+    pub fn find(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<Self>())
+            .cloned()
+    }
+}
+
+impl std::fmt::Display for Flagged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was flagged by the file scanner ({})",
+            self.filename, self.signature
+        )
+    }
+}
+
+impl std::error::Error for Flagged {}
+
+/// Scans downloaded files, so links to arbitrary domains can't get malware reposted.
+#[derive(Debug, Clone)]
+pub struct FileScanner {
+    scanner: Scanner,
+    timeout: Duration,
+}
+
+impl FileScanner {
+    pub fn new(scanner: Scanner, timeout: Duration) -> Self {
+        Self { scanner, timeout }
+    }
+
+    /// The files the scanner passed. Fails with [`Flagged`] if it flagged all of them, and when
+    /// a file can't be scanned, so an unreachable scanner lets nothing through.
+    pub async fn filter(&self, files: Vec<MediaFile>) -> Result<Vec<MediaFile>> {
+        let mut passed = Vec::with_capacity(files.len());
+        let mut flagged = None;
+        for file in files {
+            match self
+                .scan(&file.data)
+                .await
+                .with_context(|| format!("Failed to scan {} for threats", file.filename))?
+            {
+                Verdict::Clean => passed.push(file),
+                Verdict::Flagged(signature) => {
+                    warn!(
+                        monotonic_counter.flagged_files = 1u64,
+                        "Dropping {}, the file scanner flagged it: {}", file.filename, signature
+                    );
+                    flagged = flagged.or(Some(Flagged {
+                        filename: file.filename,
+                        signature,
+                    }));
+                }
+            }
+        }
+        match flagged {
+            Some(flagged) if passed.is_empty() => Err(flagged.into()),
+            _ => Ok(passed),
+        }
+    }
+
+    /// Scans `data`, failing if the scanner can't be reached, errors or takes too long.
+    pub async fn scan(&self, data: &[u8]) -> Result<Verdict> {
+        let scan = async {
+            match &self.scanner {
+                Scanner::Clamd(socket) => clamd(socket, data).await,
+                Scanner::Command(command) => run_command(command, data).await,
+            }
+        };
+        tokio::time::timeout(self.timeout, scan)
+            .await
+            .with_context(|| format!("Scan timed out after {}s", self.timeout.as_secs()))?
+    }
+}
+
+/// Streams `data` to clamd with the INSTREAM command.
+#[cfg(unix)]
+async fn clamd(socket: &std::path::Path, data: &[u8]) -> Result<Verdict> {
+    let mut stream = tokio::net::UnixStream::connect(socket)
+        .await
+        .with_context(|| format!("Failed to connect to clamd at {}", socket.display()))?;
+
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in data.chunks(CHUNK_SIZE) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    parse_clamd_reply(&String::from_utf8_lossy(&reply))
+}
+
+#[cfg(not(unix))]
+async fn clamd(_socket: &std::path::Path, _data: &[u8]) -> Result<Verdict> {
+    bail!("clamd sockets are only supported on Unix")
+}
+
+/// Verdict from clamd's reply to a stream, e.g. "stream: OK" or
+/// "stream: Eicar-Test-Signature FOUND".
+fn parse_clamd_reply(reply: &str) -> Result<Verdict> {
+    let reply = reply.trim_end_matches(['\0', '\n']);
+    let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+    if result == "OK" {
+        Ok(Verdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(Verdict::Flagged(signature.to_string()))
+    } else {
+        bail!("clamd failed to scan: {}", result)
+    }
+}
+
+/// Pipes `data` to the scan command, taking the first line it prints as what it found.
+async fn run_command(command: &[String], data: &[u8]) -> Result<Verdict> {
+    let (program, args) = command.split_first().context("Scan command is empty")?;
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .supervised()
+        .spawn()
+        .with_context(|| format!("Failed to spawn {program}"))?;
+    let group = ProcessGroupGuard::new(&child);
+
+    let mut stdin = child.stdin.take().context("Failed to get scanner stdin")?;
+    let write = async move {
+        // Scanners may stop reading once they found something
+        if let Err(e) = stdin.write_all(data).await {
+            debug!("Scanner stopped reading its input: {}", e);
+        }
+    };
+    let (_, output) = tokio::join!(write, child.wait_with_output());
+    let output = output?;
+    group.disarm();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match output.status.code() {
+        Some(0) => Ok(Verdict::Clean),
+        Some(FLAGGED_EXIT_CODE) => Ok(Verdict::Flagged(
+            stdout
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or("flagged")
+                .to_string(),
+        )),
+        _ => bail!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: &str, data: &[u8]) -> MediaFile {
+        MediaFile {
+            filename: filename.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    /// Scanner flagging files containing "EICAR".
+    fn grep_scanner() -> FileScanner {
+        let script = "if grep -q EICAR; then echo Test-Signature; exit 1; fi";
+        FileScanner::new(
+            Scanner::Command(vec!["sh".into(), "-c".into(), script.into()]),
+            Duration::from_secs(10),
+        )
+    }
+
+    #[test]
+    fn test_parse_clamd_reply() {
+        assert_eq!(parse_clamd_reply("stream: OK\0").unwrap(), Verdict::Clean);
+        assert_eq!(
+            parse_clamd_reply("stream: Eicar-Test-Signature FOUND\0").unwrap(),
+            Verdict::Flagged("Eicar-Test-Signature".to_string())
+        );
+        assert!(parse_clamd_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drops_flagged_files() {
+        let scanner = grep_scanner();
+        let files = vec![file("a.jpg", b"fine"), file("b.exe", b"EICAR")];
+
+        let passed = scanner.filter(files).await.unwrap();
+        assert_eq!(passed.len(), 1);
+        assert_eq!(passed[0].filename, "a.jpg");
+
+        let error = scanner
+            .filter(vec![file("b.exe", b"EICAR")])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            Flagged::find(&error),
+            Some(Flagged {
+                filename: "b.exe".to_string(),
+                signature: "Test-Signature".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_failures_let_nothing_through() {
+        let failing = FileScanner::new(
+            Scanner::Command(vec!["sh".into(), "-c".into(), "exit 2".into()]),
+            Duration::from_secs(10),
+        );
+        assert!(failing.filter(vec![file("a.jpg", b"fine")]).await.is_err());
+
+        let unreachable = FileScanner::new(
+            Scanner::Clamd(PathBuf::from("/nonexistent/clamd.sock")),
+            Duration::from_secs(10),
+        );
+        assert!(unreachable.scan(b"fine").await.is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use serde_json::Value;
+
+/// Videos longer than `max_secs` are cut down to their first `clip_secs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurationLimit {
+    pub max_secs: u64,
+    pub clip_secs: u64,
+}
+
+/// A video refused for being longer than the duration cap, found before downloading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLong {
+    pub duration_secs: u64,
+    pub max_secs: u64,
+}
+
+impl TooLong {
+    /// The cap that refused the video `error` is about, if one did.
+    pub fn find(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<Self>())
+            .copied()
+    }
+}
+
+impl std::fmt::Display for TooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Video is too long ({}s > {}s limit)",
+            self.duration_secs, self.max_secs
+        )
+    }
+}
+
+impl std::error::Error for TooLong {}
+
+/// A chapter from the video's metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Chapter {
+    /// Parses the `chapters` array of yt-dlp JSON output.
+    pub fn parse_all(json: &Value) -> Vec<Self> {
+        json["chapters"]
+            .as_array()
+            .map(|chapters| {
+                chapters
+                    .iter()
+                    .filter_map(|chapter| {
+                        Some(Self {
+                            title: chapter["title"].as_str()?.to_string(),
+                            start: chapter["start_time"].as_f64()?,
+                            end: chapter["end_time"].as_f64()?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Time range of a video to download, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Section {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Section {
+    pub fn head(secs: u64) -> Self {
+        Self {
+            start: 0.0,
+            end: secs as f64,
+        }
+    }
+
+    /// The last `secs` of a live stream, which yt-dlp needs to download from its start.
+    pub fn live_tail(secs: u64) -> Self {
+        Self {
+            start: -(secs as f64),
+            end: f64::INFINITY,
+        }
+    }
+
+    pub fn is_live_tail(&self) -> bool {
+        self.end.is_infinite()
+    }
+
+    /// The chapter whose title matches `query`, preferring exact matches over partial ones.
+    pub fn chapter(chapters: &[Chapter], query: &str) -> Option<Self> {
+        let query = query.trim().to_lowercase();
+        chapters
+            .iter()
+            .find(|chapter| chapter.title.to_lowercase() == query)
+            .or_else(|| {
+                chapters
+                    .iter()
+                    .find(|chapter| chapter.title.to_lowercase().contains(&query))
+            })
+            .map(|chapter| Self {
+                start: chapter.start,
+                end: chapter.end,
+            })
+    }
+
+    /// Value of yt-dlp's `--download-sections` for this range.
+    pub fn yt_dlp_arg(&self) -> String {
+        format!("*{}-{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Vec<Chapter> {
+        Chapter::parse_all(&serde_json::json!({
+            "chapters": [
+                {"title": "Intro", "start_time": 0.0, "end_time": 12.5},
+                {"title": "Main Topic", "start_time": 12.5, "end_time": 300.0},
+                {"title": "Intro to Q&A", "start_time": 300.0, "end_time": 420.0},
+                {"start_time": 420.0, "end_time": 480.0}
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_live_tail_arg() {
+        let section = Section::live_tail(30);
+        assert!(section.is_live_tail());
+        assert!(!Section::head(30).is_live_tail());
+        assert_eq!(section.yt_dlp_arg(), "*-30-inf");
+    }
+
+    #[test]
+    fn test_parse_chapters_skips_incomplete() {
+        let chapters = chapters();
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[1].title, "Main Topic");
+        assert!(Chapter::parse_all(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_chapter_section_prefers_exact_match() {
+        let chapters = chapters();
+        assert_eq!(
+            Section::chapter(&chapters, "intro"),
+            Some(Section {
+                start: 0.0,
+                end: 12.5
+            })
+        );
+        assert_eq!(
+            Section::chapter(&chapters, "q&a"),
+            Some(Section {
+                start: 300.0,
+                end: 420.0
+            })
+        );
+        assert_eq!(Section::chapter(&chapters, "outro"), None);
+    }
+
+    #[test]
+    fn test_yt_dlp_arg() {
+        assert_eq!(Section::head(60).yt_dlp_arg(), "*0-60");
+        assert_eq!(
+            Section {
+                start: 12.5,
+                end: 300.0
+            }
+            .yt_dlp_arg(),
+            "*12.5-300"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use super::types::MediaFile;
+use std::path::Path;
+use tracing::debug;
+
+/// Extensions naming the same format, of which the first is used for corrections.
+const ALIASES: &[&[&str]] = &[
+    &["jpg", "jpeg", "jfif"],
+    &["tiff", "tif"],
+    // Discord plays all ISO media files alike, their brands are too loosely used to tell apart
+    &["mp4", "m4v", "m4a", "mov"],
+    &["ogg", "oga", "opus"],
+    &["mkv", "mka"],
+];
+
+/// Format of `data` told by its magic bytes, as a file extension.
+fn sniff(data: &[u8]) -> Option<&'static str> {
+    let head = &data[..data.len().min(64)];
+    let contains = |needle: &[u8]| head.windows(needle.len()).any(|window| window == needle);
+
+    match head {
+        [0xFF, 0xD8, 0xFF, ..] => Some("jpg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("png"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("webp"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("wav"),
+        [b'B', b'M', ..] if head.len() >= 14 => Some("bmp"),
+        [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => Some("tiff"),
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => match brand.get(..4)? {
+            b"avif" | b"avis" => Some("avif"),
+            b"heic" | b"heix" | b"heim" | b"heis" | b"mif1" | b"msf1" => Some("heic"),
+            b"qt  " => Some("mov"),
+            b"M4A " => Some("m4a"),
+            _ => Some("mp4"),
+        },
+        [0x1A, 0x45, 0xDF, 0xA3, ..] if contains(b"webm") => Some("webm"),
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => Some("mkv"),
+        [b'O', b'g', b'g', b'S', ..] if contains(b"OpusHead") => Some("opus"),
+        [b'O', b'g', b'g', b'S', ..] => Some("ogg"),
+        [b'f', b'L', b'a', b'C', ..] => Some("flac"),
+        [b'I', b'D', b'3', ..] | [0xFF, 0xFB | 0xF3 | 0xF2, ..] => Some("mp3"),
+        _ => None,
+    }
+}
+
+fn canonical(ext: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|aliases| aliases.contains(&ext))
+        .map_or(ext, |aliases| aliases[0])
+}
+
+/// Renames a file whose extension doesn't match its contents, e.g. a WebP image served as
+/// `.jpg` or an MP4 saved as `.bin`, so Discord previews it inline.
+///
+/// Files of unknown formats keep their name.
+pub fn correct_extension(file: MediaFile) -> MediaFile {
+    let Some(actual) = sniff(&file.data) else {
+        return file;
+    };
+
+    let path = Path::new(&file.filename);
+    let current = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    if canonical(&current) == canonical(actual) {
+        return file;
+    }
+
+    let filename = path.with_extension(actual).to_string_lossy().into_owned();
+    debug!(
+        "Renaming {} to {} to match its contents",
+        file.filename, filename
+    );
+    MediaFile {
+        filename,
+        data: file.data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: &str, data: &[u8]) -> MediaFile {
+        MediaFile {
+            filename: filename.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\xFF\xD8\xFF\xE0\x00\x10JFIF"), Some("jpg"));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1A\n\x00\x00"), Some("png"));
+        assert_eq!(sniff(b"GIF89a\x01\x00"), Some("gif"));
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some("webp"));
+        assert_eq!(sniff(b"\x00\x00\x00\x20ftypisom\x00\x00"), Some("mp4"));
+        assert_eq!(sniff(b"\x00\x00\x00\x1CftypM4A \x00\x00"), Some("m4a"));
+        assert_eq!(sniff(b"\x00\x00\x00\x1Cftypavif\x00\x00"), Some("avif"));
+        assert_eq!(sniff(b"\x1A\x45\xDF\xA3\x9F\x42\x82\x84webm"), Some("webm"));
+        assert_eq!(sniff(b"OggS\x00\x02\x00\x00OpusHead"), Some("opus"));
+        assert_eq!(sniff(b"ID3\x04\x00"), Some("mp3"));
+        assert_eq!(sniff(b"plain text"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_correct_extension() {
+        let webp = b"RIFF\x24\x00\x00\x00WEBPVP8 ";
+        assert_eq!(correct_extension(file("a.jpg", webp)).filename, "a.webp");
+
+        let mp4 = b"\x00\x00\x00\x20ftypisom\x00\x00";
+        assert_eq!(correct_extension(file("a.bin", mp4)).filename, "a.mp4");
+        assert_eq!(correct_extension(file("a", mp4)).filename, "a.mp4");
+        // Aliases of the right format are kept
+        assert_eq!(correct_extension(file("a.m4v", mp4)).filename, "a.m4v");
+        assert_eq!(
+            correct_extension(file("a.JPEG", b"\xFF\xD8\xFF\xE0")).filename,
+            "a.JPEG"
+        );
+        // Unknown contents are left alone
+        assert_eq!(correct_extension(file("a.txt", b"hello")).filename, "a.txt");
+    }
+}
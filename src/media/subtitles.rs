@@ -0,0 +1,121 @@
+use super::{
+    bootstrap::{program, Tool},
+    http, process,
+};
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use tempfile::NamedTempFile;
+use tracing::{debug, info};
+
+/// Downloads manual or automatic subtitles for `url` in `lang` as SRT, if the site has any.
+pub async fn fetch_subtitles(url: &str, lang: &str) -> Result<Option<String>> {
+    let dir = tempfile::tempdir()?;
+    debug!("Fetching {} subtitles with yt-dlp for: {}", lang, url);
+
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        process::output(
+            tokio::process::Command::new(program(Tool::YtDlp))
+                .arg("--skip-download")
+                .args(http::user_agent_args())
+                .arg("--write-subs")
+                .arg("--write-auto-subs")
+                .arg("--sub-langs")
+                .arg(lang)
+                .arg("--convert-subs")
+                .arg("srt")
+                .arg("--no-warnings")
+                .arg("--output")
+                .arg(dir.path().join("subtitles"))
+                .arg(url),
+        ),
+    )
+    .await
+    .context("Subtitle download timed out")?
+    .context("Failed to download subtitles")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("Subtitle download failed: {}", error));
+    }
+
+    let mut entries = tokio::fs::read_dir(dir.path()).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.path().extension().is_some_and(|ext| ext == "srt") {
+            return Ok(Some(tokio::fs::read_to_string(entry.path()).await?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Quotes a path for use inside an ffmpeg filter argument.
+fn subtitles_filter(path: &Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace(':', "\\:");
+    format!("subtitles='{escaped}'")
+}
+
+/// Burns SRT `subtitles` into a video, returning H.264/MP4 output.
+pub fn burn_subtitles(data: &[u8], filename: &str, subtitles: &str) -> Result<Vec<u8>> {
+    info!("Burning subtitles into {} ({} bytes)", filename, data.len());
+
+    let mut input_file = NamedTempFile::new()?;
+    input_file.write_all(data)?;
+
+    let mut subtitles_file = NamedTempFile::with_suffix(".srt")?;
+    subtitles_file.write_all(subtitles.as_bytes())?;
+
+    let output_file = NamedTempFile::with_suffix(".mp4")?;
+
+    let output = process::run(
+        Command::new("ffmpeg")
+            .arg("-i")
+            .arg(input_file.path())
+            .arg("-vf")
+            .arg(subtitles_filter(subtitles_file.path()))
+            .arg("-c:v")
+            .arg("libx264")
+            .arg("-preset")
+            .arg("veryfast")
+            .arg("-crf")
+            .arg("20")
+            .arg("-c:a")
+            .arg("copy")
+            .arg("-movflags")
+            .arg("+faststart")
+            .arg("-y")
+            .arg(output_file.path()),
+    )?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to burn subtitles: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(std::fs::read(output_file.path())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtitles_filter_escapes_path() {
+        assert_eq!(
+            subtitles_filter(Path::new("/tmp/subs.srt")),
+            "subtitles='/tmp/subs.srt'"
+        );
+        assert_eq!(
+            subtitles_filter(Path::new("C:\\tmp\\it's.srt")),
+            "subtitles='C\\:\\\\tmp\\\\it\\'s.srt'"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+/// Step of handling a link that is limited in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Looking up the media and its metadata
+    Extraction,
+    /// Fetching the media files
+    Download,
+    /// Re-encoding files to fit the upload limit or to extract their audio
+    Transcode,
+    /// Sending the files to Discord
+    Upload,
+}
+
+impl Stage {
+    /// Message key describing a timeout of this stage.
+    pub fn timeout_key(self) -> &'static str {
+        match self {
+            Self::Extraction => "error.timeout_extraction",
+            Self::Download => "error.timeout_download",
+            Self::Transcode => "error.timeout_transcode",
+            Self::Upload => "error.timeout_upload",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Extraction => "Media metadata extraction",
+            Self::Download => "Media download",
+            Self::Transcode => "Transcoding",
+            Self::Upload => "Upload",
+        })
+    }
+}
+
+/// A stage that took longer than allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTimeout {
+    pub stage: Stage,
+    pub after: Duration,
+}
+
+impl StageTimeout {
+    /// The timeout that caused `error`, if one did.
+    pub fn find(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<Self>())
+            .copied()
+    }
+}
+
+impl fmt::Display for StageTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} timed out after {}s",
+            self.stage,
+            self.after.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for StageTimeout {}
+
+/// Time allowed for each stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTimeouts {
+    pub extraction: Duration,
+    pub download: Duration,
+    pub transcode: Duration,
+    pub upload: Duration,
+}
+
+impl Default for StageTimeouts {
+    fn default() -> Self {
+        Self {
+            extraction: Duration::from_secs(30),
+            download: Duration::from_secs(120),
+            transcode: Duration::from_secs(600),
+            upload: Duration::from_secs(120),
+        }
+    }
+}
+
+impl StageTimeouts {
+    pub fn get(&self, stage: Stage) -> Duration {
+        match stage {
+            Stage::Extraction => self.extraction,
+            Stage::Download => self.download,
+            Stage::Transcode => self.transcode,
+            Stage::Upload => self.upload,
+        }
+    }
+
+    /// The same timeouts, with the download also given the extraction's time, for tools that look
+    /// the media up and fetch it in a single run.
+    pub fn extracting_while_downloading(self) -> Self {
+        Self {
+            download: self.download + self.extraction,
+            ..self
+        }
+    }
+
+    /// Runs `future` as `stage`, failing with a [`StageTimeout`] once its time is up.
+    pub async fn run<F: Future>(&self, stage: Stage, future: F) -> Result<F::Output, StageTimeout> {
+        let after = self.get(stage);
+        tokio::time::timeout(after, future)
+            .await
+            .map_err(|_| StageTimeout { stage, after })
+    }
+}
+
+/// Stage timeouts of all downloaders, some of them overriding the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    pub defaults: StageTimeouts,
+    /// Keyed by downloader name, e.g. "yt-dlp"
+    pub downloaders: HashMap<String, StageTimeouts>,
+}
+
+impl Timeouts {
+    pub fn for_downloader(&self, name: &str) -> StageTimeouts {
+        self.downloaders.get(name).copied().unwrap_or(self.defaults)
+    }
+
+    /// Longest upload any downloader's media may take.
+    pub fn longest_upload(&self) -> Duration {
+        self.downloaders
+            .values()
+            .map(|timeouts| timeouts.upload)
+            .fold(self.defaults.upload, Duration::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_reports_stage() {
+        let timeouts = StageTimeouts {
+            download: Duration::from_millis(10),
+            ..Default::default()
+        };
+
+        let result = timeouts
+            .run(Stage::Download, tokio::time::sleep(Duration::from_secs(5)))
+            .await;
+        let timeout = result.unwrap_err();
+        assert_eq!(timeout.stage, Stage::Download);
+        assert_eq!(timeout.to_string(), "Media download timed out after 0s");
+
+        let error = anyhow::Error::new(timeout).context("Media download failed");
+        assert_eq!(StageTimeout::find(&error), Some(timeout));
+        assert_eq!(StageTimeout::find(&anyhow::anyhow!("other")), None);
+
+        assert_eq!(timeouts.run(Stage::Extraction, async { 1 }).await, Ok(1));
+    }
+
+    #[test]
+    fn test_downloader_overrides() {
+        let slow = StageTimeouts {
+            upload: Duration::from_secs(900),
+            ..Default::default()
+        };
+        let timeouts = Timeouts {
+            defaults: StageTimeouts::default(),
+            downloaders: HashMap::from([("yt-dlp".to_string(), slow)]),
+        };
+
+        assert_eq!(timeouts.for_downloader("yt-dlp"), slow);
+        assert_eq!(
+            timeouts.for_downloader("gallery-dl"),
+            StageTimeouts::default()
+        );
+        assert_eq!(timeouts.longest_upload(), Duration::from_secs(900));
+    }
+}
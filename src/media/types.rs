@@ -1,3 +1,13 @@
+use super::{
+    memory::MemoryLease,
+    progress::ProgressReporter,
+    queue::JobClass,
+    resize::OutputContainer,
+    section::{Chapter, DurationLimit},
+    timeouts::StageTimeouts,
+};
+use ring::rand::{SecureRandom, SystemRandom};
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct MediaMetadata {
@@ -7,7 +17,21 @@ pub struct MediaMetadata {
     pub duration: Option<u64>,
     pub author: Option<String>,
     pub likes: Option<u64>,
+    /// Caption or description, as the source writes it
+    pub description: Option<String>,
+    pub view_count: Option<u64>,
+    /// Unix seconds the media was published at
+    pub uploaded_at: Option<u64>,
+    /// Name of the site or extractor the media comes from, e.g. "Youtube" or "twitter"
+    pub site: Option<String>,
     pub format_ext: String,
+    pub chapters: Vec<Chapter>,
+    /// Marked as adult or sensitive by the source
+    pub nsfw: bool,
+    /// Streaming live right now, so there is no end to download up to
+    pub live: bool,
+    /// Formats the source offers, e.g. "1080p mp4", best first; empty when unknown
+    pub formats: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -16,9 +40,93 @@ pub struct MediaFile {
     pub data: Vec<u8>,
 }
 
+impl MediaFile {
+    pub fn is_video(&self) -> bool {
+        self.filename.ends_with(".mp4")
+            || self.filename.ends_with(".webm")
+            || self.filename.ends_with(".mov")
+    }
+
+    pub fn is_audio(&self) -> bool {
+        [".mp3", ".ogg", ".opus", ".m4a", ".flac", ".wav"]
+            .iter()
+            .any(|ext| self.filename.ends_with(ext))
+    }
+}
+
+/// What to download, and how.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadRequest {
+    /// Short id correlating the request's log lines with error reports
+    pub id: String,
+    pub url: String,
+    /// Only download the chapter whose title matches
+    pub chapter: Option<String>,
+    /// Only the audio track is needed
+    pub audio_only: bool,
+    /// Container videos are merged into, for downloaders that merge streams
+    pub container: OutputContainer,
+    /// Upload limit of the destination in bytes, a format that fits it is downloaded untouched
+    /// rather than a bigger one that has to be re-encoded
+    pub max_filesize: Option<u64>,
+    /// Tallest video resolution wanted, e.g. 720 for a quality picked with `/embed`
+    pub max_height: Option<u32>,
+    /// Names of general-purpose downloaders in the order they are tried
+    pub downloader_order: Vec<String>,
+    /// Receives download progress, for downloaders that report it
+    pub progress: Option<ProgressReporter>,
+    /// Time each stage may take, set for the downloader handling the request
+    pub timeouts: StageTimeouts,
+    /// Cuts down long videos in place of the downloader's limit, e.g. for the requester's tier
+    pub duration_limit: Option<DurationLimit>,
+    /// Videos longer than this many seconds are refused from their metadata, before downloading
+    pub max_duration_secs: Option<u64>,
+    /// What started the download, ordering it in the download queue
+    pub class: JobClass,
+    /// Priority of the requester's tier, downloads with a higher one leave the queue first
+    pub priority: u8,
+}
+
+impl DownloadRequest {
+    /// Request for a plain URL with default options.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            id: request_id(),
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Random 8 character hex id, falling back to the current time if no randomness is available.
+fn request_id() -> String {
+    let mut bytes = [0u8; 4];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        bytes = nanos.to_be_bytes();
+    }
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
 #[derive(Debug)]
 pub struct MediaInfo {
     pub url: String,
     pub files: Vec<MediaFile>,
     pub metadata: MediaMetadata,
+    /// Seconds the video was cut down to for exceeding the duration limit
+    pub truncated_to: Option<u64>,
+    /// Time the remaining stages of handling the media may take
+    pub timeouts: StageTimeouts,
+    /// Share of the memory budget the files hold while the media is handled
+    pub memory: MemoryLease,
+}
+
+impl MediaInfo {
+    /// Combined size of the downloaded files.
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.data.len() as u64).sum()
+    }
 }
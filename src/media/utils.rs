@@ -1,4 +1,6 @@
+use super::process::{self, ProcessGroupGuard, Supervised};
 use anyhow::{Context, Result};
+use tempfile::NamedTempFile;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::info;
 
@@ -26,8 +28,10 @@ pub async fn remux_ts_to_mp4(ts_data: &[u8]) -> Result<Vec<u8>> {
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
+        .supervised()
         .spawn()
         .context("Failed to spawn ffmpeg")?;
+    let group = ProcessGroupGuard::new(&ffmpeg);
 
     info!("ffmpeg spawned successfully");
 
@@ -82,6 +86,7 @@ pub async fn remux_ts_to_mp4(ts_data: &[u8]) -> Result<Vec<u8>> {
     error.context("Failed to read ffmpeg stderr")?;
 
     let status = ffmpeg.wait().await.context("Failed to wait for ffmpeg")?;
+    group.disarm();
 
     write_task.await.context("Failed to join write task")?;
 
@@ -98,6 +103,44 @@ pub async fn remux_ts_to_mp4(ts_data: &[u8]) -> Result<Vec<u8>> {
     Ok(buffer)
 }
 
+/// Muxes separately downloaded video and audio streams, e.g. MPEG-TS or fMP4, into an MP4.
+pub async fn mux_to_mp4(video: &[u8], audio: &[u8]) -> Result<Vec<u8>> {
+    let video_file = NamedTempFile::new()?;
+    tokio::fs::write(video_file.path(), video).await?;
+    let audio_file = NamedTempFile::new()?;
+    tokio::fs::write(audio_file.path(), audio).await?;
+    let output_file = NamedTempFile::with_suffix(".mp4")?;
+
+    let output = process::output(
+        tokio::process::Command::new("ffmpeg")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-i")
+            .arg(video_file.path())
+            .arg("-i")
+            .arg(audio_file.path())
+            .args(["-map", "0:v", "-map", "1:a", "-c", "copy"])
+            .args(["-bsf:a", "aac_adtstoasc", "-movflags", "+faststart"])
+            .arg("-y")
+            .arg(output_file.path()),
+    )
+    .await
+    .context("Failed to run ffmpeg")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to mux audio: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let data = tokio::fs::read(output_file.path()).await?;
+    info!(
+        "Muxed video and audio to MP4, output size: {} bytes",
+        data.len()
+    );
+    Ok(data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,19 +1,55 @@
 use super::{
+    bootstrap::{program, Tool},
     downloader::Downloader,
+    hls::fetch_hls,
+    http,
+    metadata_cache::MetadataCache,
+    process,
+    progress::output_with_progress,
     remux_ts_to_mp4,
-    types::{MediaFile, MediaInfo, MediaMetadata},
+    resize::OutputContainer,
+    section::{Chapter, DurationLimit, Section},
+    timeouts::{Stage, StageTimeouts},
+    types::{DownloadRequest, MediaFile, MediaInfo, MediaMetadata},
 };
+use crate::utils::parse_date;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde_json::Value;
+use std::time::Instant;
 use tokio::process::Command;
 use tracing::{debug, info, warn};
 
-pub struct YtDlpDownloader;
+/// Prefers H.264 so Discord can play the result inline.
+const FORMAT: &str = "bestvideo[vcodec=h264]+bestaudio/best[vcodec=h264]/bestvideo[vcodec=avc1]+bestaudio/best[vcodec=avc1]/best";
+
+/// Prefers streams that can be merged into WebM without re-encoding.
+const WEBM_FORMAT: &str = "bestvideo[ext=webm]+bestaudio[ext=webm]/best[ext=webm]/best";
+
+/// Takes the best streams whatever their codec, as they are left in their own container.
+const ORIGINAL_FORMAT: &str = "bestvideo+bestaudio/best";
+
+/// Skips the video stream when only the audio is wanted.
+const AUDIO_FORMAT: &str = "bestaudio[ext=m4a]/bestaudio/best";
+
+/// Error of links to streams that are live, telling users to come back once they end.
+pub(super) const LIVE_STREAM_ERROR: &str = "Live streams can't be downloaded until they end";
+
+pub struct YtDlpDownloader {
+    duration_limit: Option<DurationLimit>,
+    /// Seconds captured from the end of live streams, which are refused when unset
+    live_capture_secs: Option<u64>,
+    cache: MetadataCache,
+}
 
 impl YtDlpDownloader {
-    pub fn new() -> Self {
-        Self
+    pub fn new(duration_limit: Option<DurationLimit>, live_capture_secs: Option<u64>) -> Self {
+        Self {
+            duration_limit,
+            live_capture_secs,
+            cache: MetadataCache::new(),
+        }
     }
 
     /// Parses yt-dlp JSON output and extracts metadata.
@@ -25,27 +61,42 @@ impl YtDlpDownloader {
             duration: extract_duration(json_value),
             author: extract_author(json_value),
             likes: extract_likes(json_value),
+            description: extract_description(json_value),
+            view_count: json_value["view_count"].as_u64(),
+            uploaded_at: extract_upload_time(json_value),
+            site: extract_site(json_value),
             format_ext: extract_extension(json_value),
+            chapters: Chapter::parse_all(json_value),
+            nsfw: extract_nsfw(json_value),
+            live: extract_live(json_value),
+            formats: extract_formats(json_value),
         })
     }
 
-    async fn extract_metadata(&self, url: &str) -> Result<MediaMetadata> {
+    async fn extract_metadata(&self, url: &str, timeouts: &StageTimeouts) -> Result<MediaMetadata> {
+        if let Some(json_str) = self.cache.get(url, Instant::now()) {
+            debug!("Using cached yt-dlp metadata for: {}", url);
+            let json: Value =
+                serde_json::from_str(&json_str).context("Failed to parse media metadata")?;
+            return Self::parse_json(&json);
+        }
+
         debug!("Extracting metadata with yt-dlp for: {}", url);
 
-        let output = tokio::time::timeout(
-            std::time::Duration::from_secs(30),
-            tokio::process::Command::new("yt-dlp")
-                .arg("--dump-json")
-                .arg("--no-download")
-                .arg("--no-warnings")
-                .arg("--user-agent")
-                .arg("\"foobar\"")
-                .arg(url)
-                .output(),
-        )
-        .await
-        .context("Media metadata extraction timed out")?
-        .context("Failed to extract media metadata")?;
+        let output = timeouts
+            .run(
+                Stage::Extraction,
+                process::output(
+                    tokio::process::Command::new(program(Tool::YtDlp))
+                        .arg("--dump-json")
+                        .arg("--no-download")
+                        .arg("--no-warnings")
+                        .args(http::user_agent_args())
+                        .arg(url),
+                ),
+            )
+            .await?
+            .context("Failed to extract media metadata")?;
 
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
@@ -60,65 +111,433 @@ impl YtDlpDownloader {
             serde_json::from_str(&json_str).context("Failed to parse media metadata")?;
 
         debug!("yt-dlp JSON output: {}", json_str);
+        self.cache
+            .insert(url, json_str.into_owned(), Instant::now());
 
         Self::parse_json(&json)
     }
 
-    async fn download_to_memory(
+    /// yt-dlp writing media to stdout, with the link or info file still to be added.
+    ///
+    /// `format` is a format picked by probing, yt-dlp selects one by itself without it.
+    /// `user_agent` is sent instead of yt-dlp's own, usually [`http::user_agent`].
+    fn download_command(
+        req: &DownloadRequest,
+        format: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Command {
+        let mut command = Command::new(program(Tool::YtDlp));
+        command
+            .args(http::user_agent_args_for(user_agent))
+            .arg("--output")
+            .arg("-")
+            .arg("--format")
+            .arg(match format {
+                Some(format) => format.to_string(),
+                None if req.audio_only => AUDIO_FORMAT.to_string(),
+                None => video_format(req.container, req.max_filesize),
+            })
+            .args(merge_args(req.container))
+            .arg("--no-warnings")
+            .arg("--quiet");
+        if let Some(height) = req.max_height.filter(|_| !req.audio_only) {
+            // Prefers the tallest resolution up to the height, and the smallest above it if
+            // there is nothing smaller
+            command.arg("--format-sort").arg(format!("res:{height}"));
+        }
+        command
+    }
+
+    /// Probes the formats on offer for the best one that fits the request's size limit, leaving
+    /// the choice to yt-dlp if their sizes are unknown.
+    ///
+    /// The probed metadata is cached, so the download doesn't extract it again.
+    async fn fitting_format(&self, req: &DownloadRequest) -> Option<String> {
+        let max_filesize = req.max_filesize.filter(|_| !req.audio_only)?;
+        if let Err(e) = self.extract_metadata(&req.url, &req.timeouts).await {
+            debug!("Failed to probe formats of {}: {}", req.url, e);
+            return None;
+        }
+        let json_str = self.cache.get(&req.url, Instant::now())?;
+        let format = pick_format(
+            &serde_json::from_str::<Value>(&json_str).ok()?,
+            req.container,
+            max_filesize,
+            req.max_height,
+        );
+        match &format {
+            Some(format) => info!("Format {} of {} fits the upload limit", format, req.url),
+            None => debug!("No format of {} is known to fit the upload limit", req.url),
+        }
+        format
+    }
+
+    /// Fetches the HLS stream yt-dlp found for the media by itself, for sites whose streams
+    /// yt-dlp's own downloader is slow or blocked on. Fails with `error` if there is none.
+    async fn download_manifest(
         &self,
         url: &str,
+        duration_limit: Option<DurationLimit>,
+        timeouts: &StageTimeouts,
+        error: anyhow::Error,
+    ) -> Result<MediaInfo> {
+        let Ok(metadata) = self.extract_metadata(url, timeouts).await else {
+            return Err(error);
+        };
+        let manifest = self
+            .cache
+            .get(url, Instant::now())
+            .and_then(|json_str| extract_manifest(&serde_json::from_str(&json_str).ok()?));
+        let Some((manifest, headers)) = manifest.filter(|_| !metadata.live) else {
+            return Err(error);
+        };
+
+        warn!(
+            "yt-dlp failed to download {}, fetching its HLS stream directly: {}",
+            url, error
+        );
+        let (data, truncated_to) = fetch_hls(&manifest, &headers, duration_limit, timeouts).await?;
+
+        Ok(MediaInfo {
+            url: url.to_string(),
+            files: vec![MediaFile {
+                filename: format!("{}.mp4", metadata.id),
+                data,
+            }],
+            metadata,
+            truncated_to,
+            timeouts: *timeouts,
+            memory: Default::default(),
+        })
+    }
+
+    /// Downloads the media and extracts its metadata in a single yt-dlp run, which writes the
+    /// metadata to a file while the media goes to stdout.
+    ///
+    /// Metadata extracted recently, e.g. by `/info`, is handed back to yt-dlp instead of extracting
+    /// it again. Returns `None` if the video is live or over the duration limit, as yt-dlp then
+    /// skips it.
+    async fn download_with_metadata(
+        &self,
+        req: &DownloadRequest,
+        format: Option<&str>,
+        duration_limit: Option<DurationLimit>,
+    ) -> Result<Option<(MediaMetadata, Vec<MediaFile>)>> {
+        let (url, audio_only, timeouts) = (req.url.as_str(), req.audio_only, &req.timeouts);
+        info!(
+            "Downloading media with yt-dlp: {} (audio only: {})",
+            url, audio_only
+        );
+
+        let dir = tempfile::tempdir()?;
+        let info_path = dir.path().join("info.json");
+        let cached = self.cache.get(url, Instant::now());
+        let mut command = Self::download_command(req, format, http::user_agent());
+        match &cached {
+            Some(json_str) => {
+                debug!("Using cached yt-dlp metadata for: {}", url);
+                tokio::fs::write(&info_path, json_str).await?;
+                command.arg("--load-info-json").arg(&info_path);
+            }
+            None => {
+                command
+                    .arg("--print-to-file")
+                    .arg("video:%()j")
+                    .arg(&info_path)
+                    .arg(url);
+            }
+        }
+        // Live streams never finish downloading, they are handled once their metadata is known
+        let mut filter = "!is_live".to_string();
+        if let Some(limit) = duration_limit {
+            filter.push_str(&format!(" & duration <=? {}", limit.max_secs));
+        }
+        command.arg("--match-filter").arg(filter);
+
+        let output = timeouts
+            .extracting_while_downloading()
+            .run(
+                Stage::Download,
+                output_with_progress(&mut command, req.progress.as_ref()),
+            )
+            .await?
+            .context("Failed to download media")?;
+
+        let used_cache = cached.is_some();
+        let json_str = match cached {
+            Some(json_str) => json_str,
+            // The metadata is written before downloading, so a retry can use it even if this failed
+            None => tokio::fs::read_to_string(&info_path)
+                .await
+                .ok()
+                .and_then(|json_str| json_str.lines().next().map(str::to_string))
+                .inspect(|json_str| self.cache.insert(url, json_str.clone(), Instant::now()))
+                .unwrap_or_default(),
+        };
+
+        if !output.status.success() {
+            // The media URLs in the cached metadata may have expired
+            if used_cache {
+                self.cache.remove(url);
+            }
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Media download failed: {}", error));
+        }
+        if output.stdout.is_empty() {
+            return Ok(None);
+        }
+
+        let json: Value =
+            serde_json::from_str(&json_str).context("yt-dlp wrote no media metadata")?;
+        debug!("yt-dlp JSON output: {}", json_str);
+
+        let metadata = Self::parse_json(&json)?;
+        let files = media_files(output.stdout, &metadata, audio_only).await?;
+        Ok(Some((metadata, files)))
+    }
+
+    async fn download_to_memory(
+        &self,
+        req: &DownloadRequest,
         metadata: &MediaMetadata,
+        format: Option<&str>,
     ) -> Result<Vec<MediaFile>> {
-        info!("Downloading media with yt-dlp: {}", metadata.id);
-
-        let output = tokio::time::timeout(
-            std::time::Duration::from_secs(120),
-            Command::new("yt-dlp")
-                .arg("--output")
-                .arg("-")
-                .arg("--format")
-                .arg("bestvideo[vcodec=h264]+bestaudio/best[vcodec=h264]/bestvideo[vcodec=avc1]+bestaudio/best[vcodec=avc1]/best")
-                .arg("--merge-output-format")
-                .arg("mp4")
-                .arg("--no-warnings")
-                .arg("--quiet")
-                .arg("--user-agent")
-                .arg("\"foobar\"")
-                .arg(url)
-                .output(),
-        )
-        .await
-        .context("Media download timed out")?
-        .context("Failed to download media")?;
+        info!(
+            "Downloading media with yt-dlp: {} (audio only: {})",
+            metadata.id, req.audio_only
+        );
+
+        let output = req
+            .timeouts
+            .run(
+                Stage::Download,
+                output_with_progress(
+                    Self::download_command(req, format, http::user_agent()).arg(&req.url),
+                    req.progress.as_ref(),
+                ),
+            )
+            .await?
+            .context("Failed to download media")?;
 
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(anyhow::anyhow!("Media download failed: {}", error));
         }
 
-        let filename = format!("{}.{}", metadata.id, metadata.format_ext);
+        media_files(output.stdout, metadata, req.audio_only).await
+    }
 
+    /// Downloads only `section` of the video, going through a temporary file as
+    /// yt-dlp cuts sections with ffmpeg.
+    async fn download_section_to_memory(
+        &self,
+        url: &str,
+        metadata: &MediaMetadata,
+        section: Section,
+        container: OutputContainer,
+        timeouts: &StageTimeouts,
+    ) -> Result<Vec<MediaFile>> {
         info!(
-            "yt-dlp output size: {} bytes, first 4 bytes: {:02x} {:02x} {:02x} {:02x}",
-            output.stdout.len(),
-            output.stdout.first().unwrap_or(&0),
-            output.stdout.get(1).unwrap_or(&0),
-            output.stdout.get(2).unwrap_or(&0),
-            output.stdout.get(3).unwrap_or(&0)
+            "Downloading section {} with yt-dlp: {}",
+            section.yt_dlp_arg(),
+            metadata.id
         );
 
-        let data = if output.stdout.len() > 2 && output.stdout.starts_with(&[0x47, 0x40]) {
-            info!("Detected MPEG-TS output, remuxing to MP4 with ffmpeg");
-            remux_ts_to_mp4(&output.stdout).await?
-        } else {
-            info!("Output appears to be MP4 or other format, no remuxing needed");
-            output.stdout
+        let dir = tempfile::tempdir()?;
+        let mut command = Command::new(program(Tool::YtDlp));
+        command
+            .args(http::user_agent_args())
+            .arg("--output")
+            .arg(dir.path().join("section.%(ext)s"))
+            .arg("--format")
+            .arg(video_format(container, None))
+            .args(merge_args(container))
+            .arg("--download-sections")
+            .arg(section.yt_dlp_arg())
+            .arg("--no-warnings")
+            .arg("--quiet");
+        if section.is_live_tail() {
+            command.arg("--live-from-start");
+        }
+        let output = timeouts
+            .run(Stage::Download, process::output(command.arg(url)))
+            .await?
+            .context("Failed to download media")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Media download failed: {}", error));
+        }
+
+        let mut entries = tokio::fs::read_dir(dir.path()).await?;
+        let path = entries
+            .next_entry()
+            .await?
+            .context("yt-dlp produced no output")?
+            .path();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("mp4");
+
+        Ok(vec![MediaFile {
+            filename: format!("{}.{}", metadata.id, ext),
+            data: tokio::fs::read(&path).await?,
+        }])
+    }
+}
+
+/// yt-dlp format selection of videos delivered in `container`.
+///
+/// With a size limit, a single-file format known to fit it comes first, so it is downloaded as is
+/// instead of a bigger one that would have to be re-encoded.
+fn video_format(container: OutputContainer, max_filesize: Option<u64>) -> String {
+    let format = match container {
+        OutputContainer::Mp4 => FORMAT,
+        OutputContainer::Webm => WEBM_FORMAT,
+        OutputContainer::Original => ORIGINAL_FORMAT,
+    };
+    let Some(max) = max_filesize else {
+        return format.to_string();
+    };
+    let ext = match container {
+        OutputContainer::Original => String::new(),
+        container => format!("[ext={}]", container.name()),
+    };
+    format!("best{ext}[filesize<={max}]/best{ext}[filesize_approx<={max}]/{format}")
+}
+
+/// Format id of the highest resolution up to `max_height` whose size is known to fit in
+/// `max_filesize`, merging a video-only stream with the largest audio stream that still fits where
+/// that beats the single-file formats. Only streams that can go into `container` as they are
+/// count.
+fn pick_format(
+    json: &Value,
+    container: OutputContainer,
+    max_filesize: u64,
+    max_height: Option<u32>,
+) -> Option<String> {
+    let mut single = Vec::new();
+    let mut video = Vec::new();
+    let mut audio = Vec::new();
+    for format in json["formats"].as_array().into_iter().flatten() {
+        let Some(id) = format["format_id"].as_str() else {
+            continue;
+        };
+        let Some(size) = ["filesize", "filesize_approx"]
+            .iter()
+            .find_map(|key| format[key].as_f64())
+        else {
+            continue;
+        };
+        let ext = format["ext"].as_str().unwrap_or_default();
+        let fits_container = match container {
+            OutputContainer::Mp4 => ext == "mp4" || ext == "m4a",
+            OutputContainer::Webm => ext == "webm",
+            OutputContainer::Original => true,
         };
+        if !fits_container {
+            continue;
+        }
+        let vcodec = format["vcodec"].as_str().unwrap_or("none");
+        let has_audio = format["acodec"]
+            .as_str()
+            .is_some_and(|codec| codec != "none");
+        let height = format["height"].as_u64().unwrap_or_default();
+        if max_height.is_some_and(|max| height > u64::from(max)) {
+            continue;
+        }
+        // Discord plays H.264 inline, so it wins at the same resolution when MP4 is wanted
+        let h264 = container == OutputContainer::Mp4
+            && (vcodec.starts_with("avc1") || vcodec.starts_with("h264"));
+        let stream = (height, h264, size as u64, id);
+        match (vcodec != "none", has_audio) {
+            (true, true) => single.push(stream),
+            (true, false) => video.push(stream),
+            (false, true) => audio.push(stream),
+            (false, false) => {}
+        }
+    }
+
+    let merged = video.iter().filter_map(|&(height, h264, size, id)| {
+        let (.., audio_size, audio_id) = audio
+            .iter()
+            .filter(|(.., audio_size, _)| size + audio_size <= max_filesize)
+            .max_by_key(|(.., audio_size, _)| *audio_size)?;
+        Some((height, h264, size + audio_size, format!("{id}+{audio_id}")))
+    });
+    single
+        .iter()
+        .filter(|(.., size, _)| *size <= max_filesize)
+        .map(|&(height, h264, size, id)| (height, h264, size, id.to_string()))
+        .chain(merged)
+        .max_by_key(|&(height, h264, size, _)| (height, h264, size))
+        .map(|(.., id)| id)
+}
 
-        Ok(vec![MediaFile { filename, data }])
+/// Container yt-dlp merges separate video and audio streams into, its own choice for
+/// passthrough.
+fn merge_args(container: OutputContainer) -> Vec<&'static str> {
+    match container {
+        OutputContainer::Original => Vec::new(),
+        container => vec!["--merge-output-format", container.name()],
     }
 }
 
+/// HLS playlist yt-dlp picked for the media, with the headers it would send, if it picked a
+/// single HLS stream.
+fn extract_manifest(json: &Value) -> Option<(String, HeaderMap)> {
+    if !json["protocol"].as_str()?.starts_with("m3u8") {
+        return None;
+    }
+    let url = json["url"].as_str()?.to_string();
+    let headers = json["http_headers"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| {
+            Some((
+                HeaderName::try_from(name.as_str()).ok()?,
+                HeaderValue::try_from(value.as_str()?).ok()?,
+            ))
+        })
+        .collect();
+    Some((url, headers))
+}
+
+/// Names yt-dlp's output after the media, remuxing MPEG-TS streams to MP4.
+async fn media_files(
+    stdout: Vec<u8>,
+    metadata: &MediaMetadata,
+    audio_only: bool,
+) -> Result<Vec<MediaFile>> {
+    let ext = if audio_only {
+        "m4a"
+    } else {
+        &metadata.format_ext
+    };
+    let filename = format!("{}.{}", metadata.id, ext);
+
+    info!(
+        "yt-dlp output size: {} bytes, first 4 bytes: {:02x} {:02x} {:02x} {:02x}",
+        stdout.len(),
+        stdout.first().unwrap_or(&0),
+        stdout.get(1).unwrap_or(&0),
+        stdout.get(2).unwrap_or(&0),
+        stdout.get(3).unwrap_or(&0)
+    );
+
+    let data = if !audio_only && stdout.len() > 2 && stdout.starts_with(&[0x47, 0x40]) {
+        info!("Detected MPEG-TS output, remuxing to MP4 with ffmpeg");
+        remux_ts_to_mp4(&stdout).await?
+    } else {
+        info!("Output appears to be MP4 or other format, no remuxing needed");
+        stdout
+    };
+
+    Ok(vec![MediaFile { filename, data }])
+}
+
 fn extract_title(json: &Value) -> String {
     json["title"]
         .as_str()
@@ -130,46 +549,247 @@ fn extract_id(json: &Value) -> String {
     json["id"].as_str().unwrap_or("video").to_string()
 }
 
+/// The main thumbnail, or the last of the listed ones as yt-dlp orders them by preference.
 fn extract_thumbnail(json: &Value) -> Option<String> {
-    json["thumbnail"].as_str().map(|s| s.to_string())
+    json["thumbnail"]
+        .as_str()
+        .or_else(|| {
+            json["thumbnails"]
+                .as_array()?
+                .iter()
+                .rev()
+                .find_map(|thumbnail| thumbnail["url"].as_str())
+        })
+        .map(|s| s.to_string())
 }
 
 fn extract_duration(json: &Value) -> Option<u64> {
     json["duration"].as_f64().map(|d| d as u64)
 }
 
+/// Extractors without an uploader, such as TikTok's and Twitch's, name the channel or creator.
 fn extract_author(json: &Value) -> Option<String> {
-    json["uploader"].as_str().map(|s| s.to_string())
+    ["uploader", "channel", "creator", "uploader_id"]
+        .into_iter()
+        .find_map(|field| json[field].as_str().filter(|name| !name.is_empty()))
+        .map(|s| s.to_string())
 }
 
+/// Some extractors report counts as floats.
 fn extract_likes(json: &Value) -> Option<u64> {
-    json["like_count"].as_u64()
+    json["like_count"].as_u64().or_else(|| {
+        json["like_count"]
+            .as_f64()
+            .filter(|likes| *likes >= 0.0)
+            .map(|likes| likes as u64)
+    })
+}
+
+pub(super) fn extract_description(json: &Value) -> Option<String> {
+    json["description"]
+        .as_str()
+        .map(str::trim)
+        .filter(|description| !description.is_empty())
+        .map(str::to_string)
+}
+
+/// Exact `timestamp` if the site has one, the start of the `upload_date` day otherwise.
+pub(super) fn extract_upload_time(json: &Value) -> Option<u64> {
+    json["timestamp"]
+        .as_f64()
+        .filter(|timestamp| *timestamp >= 0.0)
+        .map(|timestamp| timestamp as u64)
+        .or_else(|| json["upload_date"].as_str().and_then(parse_date))
+}
+
+pub(super) fn extract_site(json: &Value) -> Option<String> {
+    json["extractor_key"]
+        .as_str()
+        .or(json["extractor"].as_str())
+        .map(str::to_string)
 }
 
 fn extract_extension(json: &Value) -> String {
     json["ext"].as_str().unwrap_or("mp4").to_string()
 }
 
+/// Age-restricted videos carry an age limit of 18.
+fn extract_nsfw(json: &Value) -> bool {
+    json["age_limit"].as_u64().is_some_and(|age| age >= 18)
+}
+
+/// Older extractors only set `is_live`, newer ones also report `live_status`.
+pub(super) fn extract_live(json: &Value) -> bool {
+    json["is_live"].as_bool().unwrap_or(false) || json["live_status"] == "is_live"
+}
+
+/// Distinct video resolutions and audio-only formats on offer, highest resolution first.
+pub(super) fn extract_formats(json: &Value) -> Vec<String> {
+    let mut video = Vec::new();
+    let mut audio = Vec::new();
+    for format in json["formats"].as_array().into_iter().flatten() {
+        let ext = format["ext"].as_str().unwrap_or("unknown");
+        let has_video = format["vcodec"]
+            .as_str()
+            .is_some_and(|codec| codec != "none");
+        let has_audio = format["acodec"]
+            .as_str()
+            .is_some_and(|codec| codec != "none");
+        // Storyboards and other formats without streams are left out
+        match format["height"].as_u64() {
+            Some(height) if has_video => video.push((height, ext)),
+            _ if has_audio && !has_video => audio.push(ext),
+            _ => {}
+        }
+    }
+
+    video.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+    video.dedup();
+    audio.sort();
+    audio.dedup();
+
+    video
+        .into_iter()
+        .map(|(height, ext)| format!("{height}p {ext}"))
+        .chain(audio.into_iter().map(|ext| format!("audio {ext}")))
+        .collect()
+}
+
 #[async_trait]
 impl Downloader for YtDlpDownloader {
     fn name(&self) -> &'static str {
         "yt-dlp"
     }
 
-    async fn download(&self, url: &str) -> Result<MediaInfo> {
-        let metadata = self.extract_metadata(url).await?;
-        let files = self.download_to_memory(url, &metadata).await?;
+    fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    fn is_general_purpose(&self) -> bool {
+        true
+    }
+
+    fn supports_chapters(&self) -> bool {
+        true
+    }
+
+    fn supports_metadata(&self) -> bool {
+        true
+    }
+
+    async fn metadata(&self, req: &DownloadRequest) -> Result<MediaMetadata> {
+        self.extract_metadata(&req.url, &req.timeouts).await
+    }
+
+    async fn download(&self, req: &DownloadRequest) -> Result<MediaInfo> {
+        let url = req.url.as_str();
+        let duration_limit = req.duration_limit.or(self.duration_limit);
+
+        // Chapters need the metadata before downloading, anything else is done in one run
+        if req.chapter.is_none() {
+            let format = self.fitting_format(req).await;
+            let downloaded = match self
+                .download_with_metadata(req, format.as_deref(), duration_limit)
+                .await
+            {
+                Err(e) if !req.audio_only => {
+                    return self
+                        .download_manifest(url, duration_limit, &req.timeouts, e)
+                        .await
+                }
+                result => result?,
+            };
+            if let Some((metadata, files)) = downloaded {
+                return Ok(MediaInfo {
+                    url: url.to_string(),
+                    files,
+                    metadata,
+                    truncated_to: None,
+                    timeouts: req.timeouts,
+                    memory: Default::default(),
+                });
+            }
+            info!("Video is live or over the duration limit, extracting metadata to cut it down");
+        }
+
+        let metadata = self.extract_metadata(url, &req.timeouts).await?;
+
+        if metadata.live {
+            let secs = self
+                .live_capture_secs
+                .ok_or_else(|| anyhow::anyhow!(LIVE_STREAM_ERROR))?;
+            info!("Video is live, capturing its last {}s", secs);
+            let files = self
+                .download_section_to_memory(
+                    url,
+                    &metadata,
+                    Section::live_tail(secs),
+                    req.container,
+                    &req.timeouts,
+                )
+                .await?;
+
+            return Ok(MediaInfo {
+                url: url.to_string(),
+                files,
+                metadata,
+                truncated_to: None,
+                timeouts: req.timeouts,
+                memory: Default::default(),
+            });
+        }
+
+        if let Some(chapter) = &req.chapter {
+            let section = Section::chapter(&metadata.chapters, chapter)
+                .with_context(|| format!("No chapter matching \"{chapter}\""))?;
+            let files = self
+                .download_section_to_memory(url, &metadata, section, req.container, &req.timeouts)
+                .await?;
+
+            return Ok(MediaInfo {
+                url: url.to_string(),
+                files,
+                metadata,
+                truncated_to: None,
+                timeouts: req.timeouts,
+                memory: Default::default(),
+            });
+        }
+
+        // Long videos are cut down instead of being refused
+        let limit =
+            duration_limit.filter(|limit| metadata.duration.is_some_and(|d| d > limit.max_secs));
+        let files = match limit {
+            Some(limit) => {
+                info!(
+                    "Video is longer than {}s, keeping the first {}s",
+                    limit.max_secs, limit.clip_secs
+                );
+                self.download_section_to_memory(
+                    url,
+                    &metadata,
+                    Section::head(limit.clip_secs),
+                    req.container,
+                    &req.timeouts,
+                )
+                .await?
+            }
+            None => self.download_to_memory(req, &metadata, None).await?,
+        };
 
         Ok(MediaInfo {
             url: url.to_string(),
             files,
             metadata,
+            truncated_to: limit.map(|limit| limit.clip_secs),
+            timeouts: req.timeouts,
+            memory: Default::default(),
         })
     }
 
     async fn test_availability() -> bool {
         // Test yt-dlp
-        let yt_dlp_available = match tokio::process::Command::new("yt-dlp")
+        let yt_dlp_available = match tokio::process::Command::new(program(Tool::YtDlp))
             .arg("--version")
             .output()
             .await
@@ -239,6 +859,10 @@ mod tests {
             "thumbnail": "https://example.com/thumb.jpg",
             "duration": 212.0,
             "like_count": 15000000,
+            "view_count": 1500000000,
+            "description": "The official video ",
+            "upload_date": "20091025",
+            "extractor_key": "Youtube",
             "ext": "mp4"
         })
     }
@@ -260,9 +884,20 @@ mod tests {
         );
         assert_eq!(metadata.duration, Some(212));
         assert_eq!(metadata.likes, Some(15000000));
+        assert_eq!(metadata.view_count, Some(1500000000));
+        assert_eq!(metadata.description.as_deref(), Some("The official video"));
+        assert_eq!(metadata.uploaded_at, Some(1_256_428_800));
+        assert_eq!(metadata.site.as_deref(), Some("Youtube"));
         assert_eq!(metadata.format_ext, "mp4");
     }
 
+    #[test]
+    fn test_extract_upload_time_prefers_timestamp() {
+        let json = serde_json::json!({"timestamp": 1_256_450_000, "upload_date": "20091025"});
+        assert_eq!(extract_upload_time(&json), Some(1_256_450_000));
+        assert_eq!(extract_upload_time(&serde_json::json!({})), None);
+    }
+
     #[test]
     fn test_parse_minimal_json() {
         let json = serde_json::json!({
@@ -306,6 +941,22 @@ mod tests {
         assert_eq!(extract_id(&json), "video");
     }
 
+    #[test]
+    fn test_extract_manifest() {
+        let json = serde_json::json!({
+            "protocol": "m3u8_native",
+            "url": "https://cdn.example.com/index.m3u8",
+            "http_headers": {"Referer": "https://example.com/", "Bad Header": "x"}
+        });
+        let (url, headers) = extract_manifest(&json).unwrap();
+        assert_eq!(url, "https://cdn.example.com/index.m3u8");
+        assert_eq!(headers["referer"], "https://example.com/");
+        assert_eq!(headers.len(), 1);
+
+        let json = serde_json::json!({"protocol": "https", "url": "https://cdn.example.com/v.mp4"});
+        assert!(extract_manifest(&json).is_none());
+    }
+
     #[test]
     fn test_extract_thumbnail() {
         let json = serde_json::json!({"thumbnail": "https://example.com/thumb.jpg"});
@@ -363,9 +1014,444 @@ mod tests {
         assert_eq!(extract_extension(&json), "mp4");
     }
 
+    #[test]
+    fn test_extract_nsfw() {
+        assert!(extract_nsfw(&serde_json::json!({"age_limit": 18})));
+        assert!(!extract_nsfw(&serde_json::json!({"age_limit": 0})));
+        assert!(!extract_nsfw(&serde_json::json!({})));
+    }
+
+    #[test]
+    fn test_extract_live() {
+        assert!(extract_live(&serde_json::json!({"is_live": true})));
+        assert!(extract_live(&serde_json::json!({"live_status": "is_live"})));
+        assert!(!extract_live(
+            &serde_json::json!({"is_live": false, "live_status": "was_live"})
+        ));
+        assert!(!extract_live(&serde_json::json!({})));
+    }
+
+    #[test]
+    fn test_download_command_container() {
+        let args = |audio_only, container| -> Vec<String> {
+            let req = DownloadRequest {
+                audio_only,
+                container,
+                ..DownloadRequest::new("https://example.com/v")
+            };
+            YtDlpDownloader::download_command(&req, None, None)
+                .as_std()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+        let mp4 = args(false, OutputContainer::Mp4);
+        assert!(mp4
+            .windows(2)
+            .any(|w| w == ["--merge-output-format", "mp4"]));
+        assert!(mp4.windows(2).any(|w| w == ["--format", FORMAT]));
+
+        let webm = args(false, OutputContainer::Webm);
+        assert!(webm
+            .windows(2)
+            .any(|w| w == ["--merge-output-format", "webm"]));
+        assert!(webm.windows(2).any(|w| w == ["--format", WEBM_FORMAT]));
+
+        // Passthrough leaves the streams in their own container
+        let original = args(false, OutputContainer::Original);
+        assert!(!original.iter().any(|arg| arg == "--merge-output-format"));
+        assert!(original
+            .windows(2)
+            .any(|w| w == ["--format", ORIGINAL_FORMAT]));
+
+        let audio = args(true, OutputContainer::Webm);
+        assert!(audio.windows(2).any(|w| w == ["--format", AUDIO_FORMAT]));
+    }
+
+    #[test]
+    fn test_download_command_max_height() {
+        let req = DownloadRequest {
+            max_height: Some(720),
+            ..DownloadRequest::new("https://example.com/v")
+        };
+        let args: Vec<String> = YtDlpDownloader::download_command(&req, None, None)
+            .as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert!(args.windows(2).any(|w| w == ["--format-sort", "res:720"]));
+    }
+
+    #[test]
+    fn test_download_command_user_agent() {
+        let args = |user_agent| -> Vec<String> {
+            let req = DownloadRequest::new("https://example.com/v");
+            YtDlpDownloader::download_command(&req, None, user_agent)
+                .as_std()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let configured = args(Some("Mozilla/5.0 (grabby)"));
+        let positions: Vec<usize> = configured
+            .iter()
+            .enumerate()
+            .filter(|(_, arg)| *arg == "--user-agent")
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(configured[positions[0] + 1], "Mozilla/5.0 (grabby)");
+
+        // yt-dlp keeps its own without a configured one
+        assert!(!args(None).iter().any(|arg| arg == "--user-agent"));
+    }
+
+    #[test]
+    fn test_pick_format() {
+        let json = serde_json::json!({
+            "formats": [
+                { "format_id": "140", "ext": "m4a", "vcodec": "none", "acodec": "mp4a.40.2", "filesize": 1_000_000 },
+                { "format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus", "filesize": 900_000 },
+                { "format_id": "18", "ext": "mp4", "vcodec": "avc1.42001E", "acodec": "mp4a.40.2", "height": 360, "filesize": 3_000_000 },
+                { "format_id": "135", "ext": "mp4", "vcodec": "avc1.4d401f", "acodec": "none", "height": 480, "filesize": 5_000_000 },
+                { "format_id": "397", "ext": "mp4", "vcodec": "av01.0.04M.08", "acodec": "none", "height": 480, "filesize_approx": 4_000_000.0 },
+                { "format_id": "137", "ext": "mp4", "vcodec": "avc1.640028", "acodec": "none", "height": 1080, "filesize": 20_000_000 },
+                { "format_id": "248", "ext": "webm", "vcodec": "vp9", "acodec": "none", "height": 1080, "filesize": 15_000_000 },
+                { "format_id": "sb0", "ext": "mhtml", "vcodec": "none", "acodec": "none" },
+                { "format_id": "22", "ext": "mp4", "vcodec": "avc1.64001F", "acodec": "mp4a.40.2", "height": 720 }
+            ]
+        });
+        let pick = |container, max| pick_format(&json, container, max, None);
+
+        // Small servers get 1080p when it fits
+        assert_eq!(
+            pick(OutputContainer::Mp4, 25_000_000).as_deref(),
+            Some("137+140")
+        );
+        // H.264 is preferred at the same resolution, formats of unknown size are skipped
+        assert_eq!(
+            pick(OutputContainer::Mp4, 10_000_000).as_deref(),
+            Some("135+140")
+        );
+        assert_eq!(pick(OutputContainer::Mp4, 4_000_000).as_deref(), Some("18"));
+        assert_eq!(pick(OutputContainer::Mp4, 1_000_000), None);
+        assert_eq!(
+            pick(OutputContainer::Webm, 16_000_000).as_deref(),
+            Some("248+251")
+        );
+        assert_eq!(pick(OutputContainer::Webm, 10_000_000), None);
+        // Passthrough mixes streams of any container
+        assert_eq!(
+            pick(OutputContainer::Original, 16_000_000).as_deref(),
+            Some("248+140")
+        );
+        // A picked quality caps the resolution even when more would fit
+        assert_eq!(
+            pick_format(&json, OutputContainer::Mp4, 25_000_000, Some(480)).as_deref(),
+            Some("135+140")
+        );
+    }
+
+    #[test]
+    fn test_video_format_prefers_fitting_file() {
+        assert_eq!(video_format(OutputContainer::Mp4, None), FORMAT);
+        assert_eq!(
+            video_format(OutputContainer::Mp4, Some(10_000_000)),
+            format!(
+                "best[ext=mp4][filesize<=10000000]/best[ext=mp4][filesize_approx<=10000000]/{FORMAT}"
+            )
+        );
+        assert_eq!(
+            video_format(OutputContainer::Original, Some(8)),
+            format!("best[filesize<=8]/best[filesize_approx<=8]/{ORIGINAL_FORMAT}")
+        );
+    }
+
+    #[test]
+    fn test_extract_formats() {
+        let json = serde_json::json!({
+            "formats": [
+                { "ext": "mhtml", "vcodec": "none", "acodec": "none", "format_note": "storyboard" },
+                { "ext": "m4a", "vcodec": "none", "acodec": "mp4a.40.2" },
+                { "ext": "webm", "vcodec": "none", "acodec": "opus" },
+                { "ext": "mp4", "vcodec": "avc1.4d401e", "acodec": "none", "height": 480 },
+                { "ext": "mp4", "vcodec": "avc1.640028", "acodec": "none", "height": 1080 },
+                { "ext": "webm", "vcodec": "vp9", "acodec": "none", "height": 1080 },
+                { "ext": "mp4", "vcodec": "avc1.640028", "acodec": "mp4a.40.2", "height": 1080 }
+            ]
+        });
+
+        assert_eq!(
+            extract_formats(&json),
+            vec![
+                "1080p mp4",
+                "1080p webm",
+                "480p mp4",
+                "audio m4a",
+                "audio webm"
+            ]
+        );
+        assert!(extract_formats(&serde_json::json!({})).is_empty());
+    }
+
     #[test]
     fn test_extract_extension_default() {
         let json = serde_json::json!({});
         assert_eq!(extract_extension(&json), "mp4");
     }
+
+    /// Expected mapping of a recorded `--dump-json` output in tests/fixtures/yt_dlp.
+    struct Golden {
+        fixture: &'static str,
+        id: &'static str,
+        title: &'static str,
+        author: Option<&'static str>,
+        duration: Option<u64>,
+        likes: Option<u64>,
+        format_ext: &'static str,
+        thumbnail: bool,
+        nsfw: bool,
+        live: bool,
+        chapters: usize,
+        formats: &'static [&'static str],
+    }
+
+    const GOLDEN: &[Golden] = &[
+        Golden {
+            fixture: "youtube",
+            id: "jNQXAC9IVRw",
+            title: "Me at the zoo",
+            author: Some("jawed"),
+            duration: Some(19),
+            likes: Some(17000000),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["360p mp4", "144p mp4", "audio m4a", "audio webm"],
+        },
+        Golden {
+            fixture: "youtube_chapters",
+            id: "aqz-KE-bpKQ",
+            title: "Big Buck Bunny 60fps 4K - Official Blender Foundation Short Film",
+            author: Some("Blender"),
+            duration: Some(635),
+            likes: Some(91000),
+            format_ext: "webm",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 2,
+            formats: &["2160p webm", "1080p mp4", "audio m4a"],
+        },
+        Golden {
+            fixture: "youtube_live",
+            id: "jfKfPfyJRdk",
+            title: "lofi hip hop radio 📚 beats to relax/study to",
+            author: Some("Lofi Girl"),
+            duration: None,
+            likes: Some(1900000),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: true,
+            chapters: 0,
+            formats: &["1080p mp4", "144p mp4"],
+        },
+        Golden {
+            fixture: "youtube_age_restricted",
+            id: "Tq6NzWZ5Y7s",
+            title: "Graphic surgery footage (viewer discretion)",
+            author: Some("Medical Channel"),
+            duration: Some(421),
+            likes: Some(2311),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: true,
+            live: false,
+            chapters: 0,
+            formats: &["360p mp4"],
+        },
+        Golden {
+            fixture: "vimeo",
+            id: "76979871",
+            title: "The New Vimeo Player (You Know, For Videos)",
+            author: Some("Vimeo"),
+            duration: Some(62),
+            likes: None,
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1080p mp4", "720p mp4", "audio m4a"],
+        },
+        Golden {
+            fixture: "tiktok",
+            id: "7106594312292453675",
+            title: "#duet with @user we were all that kid",
+            author: Some("Scout"),
+            duration: Some(9),
+            likes: Some(1392),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1024p mp4"],
+        },
+        Golden {
+            fixture: "twitter",
+            id: "1781234567890123456",
+            title: "NASA - Total solar eclipse as seen from the International Space Station",
+            author: Some("NASA"),
+            duration: Some(45),
+            likes: Some(52314),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["720p mp4", "360p mp4", "audio mp4"],
+        },
+        Golden {
+            fixture: "instagram",
+            id: "C6aBcDeFgHi",
+            title: "Video by natgeo",
+            author: Some("National Geographic"),
+            duration: Some(30),
+            likes: Some(412876),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1920p mp4", "audio m4a"],
+        },
+        Golden {
+            fixture: "reddit",
+            id: "8f3k2j1v6mvc1",
+            title: "Cat learns to open the fridge",
+            author: Some("trailrunner_42"),
+            duration: Some(24),
+            likes: Some(4821),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1080p mp4", "480p mp4", "audio m4a"],
+        },
+        Golden {
+            fixture: "reddit_nsfw",
+            id: "q1w2e3r4t5y6",
+            title: "[NSFW] Horror movie practical effects breakdown",
+            author: Some("fxnerd"),
+            duration: Some(58),
+            likes: Some(312),
+            format_ext: "mp4",
+            thumbnail: false,
+            nsfw: true,
+            live: false,
+            chapters: 0,
+            formats: &["720p mp4"],
+        },
+        Golden {
+            fixture: "twitch_clip",
+            id: "2458765432",
+            title: "no way that worked",
+            author: Some("bigstreamer"),
+            duration: Some(28),
+            likes: None,
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1080p mp4", "480p mp4"],
+        },
+        Golden {
+            fixture: "soundcloud",
+            id: "1234567890",
+            title: "Midnight Drive",
+            author: Some("Synth Artist"),
+            duration: Some(214),
+            likes: Some(5212),
+            format_ext: "opus",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["audio mp3", "audio opus"],
+        },
+        Golden {
+            fixture: "dailymotion",
+            id: "x8abc12",
+            title: "Highlights: Paris vs Lyon",
+            author: Some("Ligue 1"),
+            duration: Some(154),
+            likes: Some(87),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1080p mp4", "240p mp4"],
+        },
+        Golden {
+            fixture: "bilibili",
+            id: "BV1GJ411x7h7",
+            title: "【官方 MV】Never Gonna Give You Up - Rick Astley",
+            author: Some("索尼音乐中国"),
+            duration: Some(213),
+            likes: Some(1521000),
+            format_ext: "mp4",
+            thumbnail: true,
+            nsfw: false,
+            live: false,
+            chapters: 0,
+            formats: &["1080p mp4", "360p mp4", "audio m4a"],
+        },
+    ];
+
+    fn fixture_dir() -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/yt_dlp")
+    }
+
+    #[test]
+    fn test_golden_fixtures() {
+        for golden in GOLDEN {
+            let path = fixture_dir().join(format!("{}.json", golden.fixture));
+            let json: Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let metadata = YtDlpDownloader::parse_json(&json).unwrap();
+
+            let fixture = golden.fixture;
+            assert_eq!(metadata.id, golden.id, "{fixture}");
+            assert_eq!(metadata.title, golden.title, "{fixture}");
+            assert_eq!(metadata.author.as_deref(), golden.author, "{fixture}");
+            assert_eq!(metadata.duration, golden.duration, "{fixture}");
+            assert_eq!(metadata.likes, golden.likes, "{fixture}");
+            assert_eq!(metadata.format_ext, golden.format_ext, "{fixture}");
+            assert_eq!(metadata.thumbnail.is_some(), golden.thumbnail, "{fixture}");
+            assert_eq!(metadata.nsfw, golden.nsfw, "{fixture}");
+            assert_eq!(metadata.live, golden.live, "{fixture}");
+            assert_eq!(metadata.chapters.len(), golden.chapters, "{fixture}");
+            assert_eq!(metadata.formats, golden.formats, "{fixture}");
+        }
+    }
+
+    #[test]
+    fn test_every_fixture_has_a_golden() {
+        for entry in std::fs::read_dir(fixture_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            assert!(
+                GOLDEN.iter().any(|golden| golden.fixture == name),
+                "No expected metadata for fixture {name}"
+            );
+        }
+    }
 }
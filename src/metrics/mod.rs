@@ -0,0 +1,159 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Point-in-time view of the Tokio runtime, used to diagnose blocking and task leaks.
+///
+/// Poll statistics are only collected by Tokio when built with `--cfg tokio_unstable`.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeSnapshot {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+    pub total_busy: Duration,
+    pub total_parks: u64,
+    #[cfg(tokio_unstable)]
+    pub spawned_tasks: u64,
+    #[cfg(tokio_unstable)]
+    pub total_polls: u64,
+    #[cfg(tokio_unstable)]
+    pub mean_poll_time: Duration,
+}
+
+impl RuntimeSnapshot {
+    /// Captures metrics of the runtime the caller is running on.
+    pub fn capture() -> Self {
+        let metrics = tokio::runtime::Handle::current().metrics();
+        let workers = metrics.num_workers();
+
+        let mut snapshot = Self {
+            workers,
+            alive_tasks: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+            ..Default::default()
+        };
+
+        for worker in 0..workers {
+            snapshot.total_busy += metrics.worker_total_busy_duration(worker);
+            snapshot.total_parks += metrics.worker_park_count(worker);
+        }
+
+        #[cfg(tokio_unstable)]
+        {
+            snapshot.spawned_tasks = metrics.spawned_tasks_count();
+            let mut weighted_poll_nanos = 0u128;
+            for worker in 0..workers {
+                let polls = metrics.worker_poll_count(worker);
+                snapshot.total_polls += polls;
+                weighted_poll_nanos +=
+                    metrics.worker_mean_poll_time(worker).as_nanos() * polls as u128;
+            }
+            if snapshot.total_polls > 0 {
+                snapshot.mean_poll_time = Duration::from_nanos(
+                    (weighted_poll_nanos / snapshot.total_polls as u128) as u64,
+                );
+            }
+        }
+
+        snapshot
+    }
+
+    /// Renders the snapshot in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            let _ = writeln!(out, "{name} {value}");
+        };
+
+        metric(
+            "grabby_runtime_workers",
+            "gauge",
+            "Number of runtime worker threads",
+            self.workers.to_string(),
+        );
+        metric(
+            "grabby_runtime_alive_tasks",
+            "gauge",
+            "Number of tasks currently alive",
+            self.alive_tasks.to_string(),
+        );
+        metric(
+            "grabby_runtime_global_queue_depth",
+            "gauge",
+            "Number of tasks waiting in the global queue",
+            self.global_queue_depth.to_string(),
+        );
+        metric(
+            "grabby_runtime_busy_seconds_total",
+            "counter",
+            "Total time worker threads spent busy",
+            format!("{:.3}", self.total_busy.as_secs_f64()),
+        );
+        metric(
+            "grabby_runtime_parks_total",
+            "counter",
+            "Total number of times worker threads parked",
+            self.total_parks.to_string(),
+        );
+
+        #[cfg(tokio_unstable)]
+        {
+            metric(
+                "grabby_runtime_spawned_tasks_total",
+                "counter",
+                "Total number of tasks spawned",
+                self.spawned_tasks.to_string(),
+            );
+            metric(
+                "grabby_runtime_polls_total",
+                "counter",
+                "Total number of task polls",
+                self.total_polls.to_string(),
+            );
+            metric(
+                "grabby_runtime_mean_poll_seconds",
+                "gauge",
+                "Mean task poll duration across workers",
+                format!("{:.6}", self.mean_poll_time.as_secs_f64()),
+            );
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_capture_counts_current_runtime() {
+        let snapshot = RuntimeSnapshot::capture();
+        assert_eq!(snapshot.workers, 1);
+    }
+
+    #[test]
+    // The update is only needed for the fields present under tokio_unstable
+    #[allow(clippy::needless_update)]
+    fn test_render_prometheus() {
+        let snapshot = RuntimeSnapshot {
+            workers: 4,
+            alive_tasks: 12,
+            global_queue_depth: 1,
+            total_busy: Duration::from_millis(1500),
+            total_parks: 42,
+            ..Default::default()
+        };
+
+        let rendered = snapshot.render_prometheus();
+        assert!(
+            rendered.contains("# TYPE grabby_runtime_workers gauge\ngrabby_runtime_workers 4\n")
+        );
+        assert!(rendered.contains("grabby_runtime_alive_tasks 12\n"));
+        assert!(rendered.contains("grabby_runtime_global_queue_depth 1\n"));
+        assert!(rendered.contains("grabby_runtime_busy_seconds_total 1.500\n"));
+        assert!(rendered.contains("grabby_runtime_parks_total 42\n"));
+    }
+}
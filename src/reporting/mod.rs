@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use sentry::integrations::anyhow::capture_anyhow;
+use sentry::{ClientInitGuard, ClientOptions, Hub, SentryFutureExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Standard variable Sentry reads its DSN from.
+pub const SENTRY_DSN_ENV: &str = "SENTRY_DSN";
+
+/// Period within which failures count as repeated.
+const REPEAT_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Failures counted since reporting was set up, shared by every download.
+static REPEATS: OnceLock<Repeats> = OnceLock::new();
+
+/// Sends panics and download failures that keep happening to Sentry, until dropped.
+pub struct ErrorReporting {
+    _guard: ClientInitGuard,
+}
+
+impl ErrorReporting {
+    /// Sets up reporting to `dsn`, or to the DSN in the standard `SENTRY_DSN` variable.
+    /// Returns `None` when neither is set.
+    pub fn new(
+        dsn: Option<&str>,
+        environment: Option<&str>,
+        repeat_threshold: u32,
+    ) -> Result<Option<Self>> {
+        if dsn.is_none() && std::env::var_os(SENTRY_DSN_ENV).is_none() {
+            return Ok(None);
+        }
+
+        let guard = sentry::init(ClientOptions {
+            dsn: dsn
+                .map(str::parse)
+                .transpose()
+                .context("Invalid error_reporting.sentry_dsn")?,
+            environment: environment.map(|env| env.to_string().into()),
+            release: sentry::release_name!(),
+            ..Default::default()
+        });
+        let _ = REPEATS.set(Repeats::new(repeat_threshold));
+
+        Ok(Some(Self { _guard: guard }))
+    }
+}
+
+/// Runs a download with the downloader and domain attached to anything it reports, panics
+/// included.
+pub fn bind<F: Future>(
+    future: F,
+    downloader: &'static str,
+    domain: &str,
+) -> impl Future<Output = F::Output> {
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    hub.configure_scope(|scope| {
+        scope.set_tag("downloader", downloader);
+        scope.set_tag("domain", domain);
+    });
+    future.bind_hub(hub)
+}
+
+/// Counts a failed download, reporting it once its class keeps failing for the downloader and
+/// domain.
+pub fn record_failure(downloader: &'static str, domain: &str, error: &anyhow::Error) {
+    let Some(repeats) = REPEATS.get() else {
+        return;
+    };
+    let class = crate::media::error_class(error);
+    if !repeats.record(downloader, domain, class, Instant::now()) {
+        return;
+    }
+
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("downloader", downloader);
+            scope.set_tag("domain", domain);
+            scope.set_tag("class", class);
+            scope.set_fingerprint(Some(&["repeated-failure", downloader, domain, class]));
+        },
+        || capture_anyhow(error),
+    );
+}
+
+#[derive(Debug)]
+struct Repeat {
+    count: u32,
+    since: Instant,
+}
+
+/// Failures per downloader, domain and error class within the repeat window.
+#[derive(Debug)]
+struct Repeats {
+    threshold: u32,
+    counts: Mutex<HashMap<(&'static str, String, &'static str), Repeat>>,
+}
+
+impl Repeats {
+    fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns true for the failure reaching the threshold, once per window.
+    fn record(
+        &self,
+        downloader: &'static str,
+        domain: &str,
+        class: &'static str,
+        now: Instant,
+    ) -> bool {
+        let mut counts = self.counts.lock().unwrap_or_else(PoisonError::into_inner);
+        counts.retain(|_, repeat| now.duration_since(repeat.since) < REPEAT_WINDOW);
+
+        let repeat = counts
+            .entry((downloader, domain.to_string(), class))
+            .or_insert(Repeat {
+                count: 0,
+                since: now,
+            });
+        repeat.count += 1;
+        repeat.count == self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_once_threshold_is_reached() {
+        let repeats = Repeats::new(3);
+        let now = Instant::now();
+
+        assert!(!repeats.record("yt-dlp", "example.com", "error.network", now));
+        assert!(!repeats.record("yt-dlp", "example.com", "error.network", now));
+        assert!(repeats.record("yt-dlp", "example.com", "error.network", now));
+        assert!(!repeats.record("yt-dlp", "example.com", "error.network", now));
+    }
+
+    #[test]
+    fn test_counts_classes_separately() {
+        let repeats = Repeats::new(2);
+        let now = Instant::now();
+
+        assert!(!repeats.record("yt-dlp", "example.com", "error.network", now));
+        assert!(!repeats.record("yt-dlp", "example.com", "error.timeout", now));
+        assert!(!repeats.record("gallery-dl", "example.com", "error.network", now));
+        assert!(!repeats.record("yt-dlp", "example.org", "error.network", now));
+        assert!(repeats.record("yt-dlp", "example.com", "error.network", now));
+    }
+
+    #[test]
+    fn test_window_starts_over() {
+        let repeats = Repeats::new(2);
+        let now = Instant::now();
+
+        assert!(!repeats.record("yt-dlp", "example.com", "error.network", now));
+        let later = now + REPEAT_WINDOW;
+        assert!(!repeats.record("yt-dlp", "example.com", "error.network", later));
+        assert!(repeats.record("yt-dlp", "example.com", "error.network", later));
+    }
+}
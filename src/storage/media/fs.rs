@@ -0,0 +1,94 @@
+use super::{expires_at, expiry_of, object_name, MediaStore, StoredMedia};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Writes media to a local directory that is served over HTTP by something else.
+pub struct FilesystemStore {
+    dir: PathBuf,
+    base_url: String,
+}
+
+impl FilesystemStore {
+    pub fn new<P: Into<PathBuf>>(dir: P, base_url: String) -> Self {
+        Self {
+            dir: dir.into(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl MediaStore for FilesystemStore {
+    fn name(&self) -> &'static str {
+        "filesystem"
+    }
+
+    async fn upload(&self, filename: &str, data: Vec<u8>, ttl: Duration) -> Result<StoredMedia> {
+        let expires_at = expires_at(ttl);
+        let name = object_name(filename, expires_at)?;
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create directory: {}", self.dir.display()))?;
+        let path = self.dir.join(&name);
+        tokio::fs::write(&path, data)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(StoredMedia {
+            url: format!("{}/{}", self.base_url, name),
+            expires_at: Some(expires_at),
+        })
+    }
+
+    async fn purge_expired(&self, now: u64) -> Result<usize> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let expired = name
+                .to_str()
+                .and_then(expiry_of)
+                .is_some_and(|expires_at| expires_at <= now);
+            if expired {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upload_and_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FilesystemStore::new(dir.path(), "https://media.example.com/".to_string());
+
+        let stored = store
+            .upload("clip.mp4", b"video".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(stored.url.starts_with("https://media.example.com/"));
+        assert!(stored.url.ends_with("-clip.mp4"));
+
+        // Files that don't look like uploads are left alone
+        std::fs::write(dir.path().join("index.html"), "").unwrap();
+
+        let expires_at = stored.expires_at.unwrap();
+        assert_eq!(store.purge_expired(expires_at - 1).await.unwrap(), 0);
+        assert_eq!(store.purge_expired(expires_at).await.unwrap(), 1);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,254 @@
+use super::{hex, MediaStore, StoredMedia};
+use crate::bot::expiry::unix_now;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::time::Duration;
+
+/// Retention periods litterbox accepts, in hours.
+const LITTERBOX_HOURS: [u64; 4] = [1, 12, 24, 72];
+
+/// Public file hosts that take anonymous uploads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileHost {
+    /// catbox.moe, keeps files permanently
+    Catbox,
+    /// litterbox.catbox.moe, keeps files for up to 72 hours
+    Litterbox,
+    /// 0x0.st, keeps files for the requested time within a size-dependent limit
+    ZeroX0,
+}
+
+impl FileHost {
+    pub fn from_backend(backend: &str) -> Option<Self> {
+        match backend {
+            "catbox" => Some(Self::Catbox),
+            "litterbox" => Some(Self::Litterbox),
+            "0x0" => Some(Self::ZeroX0),
+            _ => None,
+        }
+    }
+
+    fn endpoint(self) -> &'static str {
+        match self {
+            Self::Catbox => "https://catbox.moe/user/api.php",
+            Self::Litterbox => "https://litterbox.catbox.moe/resources/internals/api.php",
+            Self::ZeroX0 => "https://0x0.st",
+        }
+    }
+
+    /// Largest upload the host accepts.
+    fn max_bytes(self) -> u64 {
+        match self {
+            Self::Catbox => 200 * 1024 * 1024,
+            Self::Litterbox => 1024 * 1024 * 1024,
+            Self::ZeroX0 => 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// Uploads media to a public file host.
+pub struct FileHostStore {
+    client: reqwest::Client,
+    host: FileHost,
+}
+
+impl FileHostStore {
+    pub fn new(host: FileHost) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            // 0x0.st rejects requests without a descriptive user agent
+            .user_agent(concat!("grabby/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(300))
+            .build()?;
+
+        Ok(Self { client, host })
+    }
+
+    /// Form fields for an upload kept for `ttl`, and when it will expire.
+    fn form_fields(&self, ttl: Duration) -> (Vec<(&'static str, String)>, Option<u64>) {
+        let hours = ttl.as_secs().div_ceil(3600).max(1);
+
+        match self.host {
+            FileHost::Catbox => (vec![("reqtype", "fileupload".to_string())], None),
+            FileHost::Litterbox => {
+                let hours = litterbox_hours(hours);
+                (
+                    vec![
+                        ("reqtype", "fileupload".to_string()),
+                        ("time", format!("{hours}h")),
+                    ],
+                    Some(unix_now() + hours * 3600),
+                )
+            }
+            FileHost::ZeroX0 => (
+                vec![("expires", hours.to_string())],
+                Some(unix_now() + hours * 3600),
+            ),
+        }
+    }
+
+    fn file_field(&self) -> &'static str {
+        match self.host {
+            FileHost::ZeroX0 => "file",
+            FileHost::Catbox | FileHost::Litterbox => "fileToUpload",
+        }
+    }
+}
+
+#[async_trait]
+impl MediaStore for FileHostStore {
+    fn name(&self) -> &'static str {
+        match self.host {
+            FileHost::Catbox => "catbox",
+            FileHost::Litterbox => "litterbox",
+            FileHost::ZeroX0 => "0x0",
+        }
+    }
+
+    async fn upload(&self, filename: &str, data: Vec<u8>, ttl: Duration) -> Result<StoredMedia> {
+        if data.len() as u64 > self.host.max_bytes() {
+            bail!(
+                "{} bytes exceed the {} byte limit of {}",
+                data.len(),
+                self.host.max_bytes(),
+                self.name()
+            );
+        }
+
+        let (fields, mut expires_at) = self.form_fields(ttl);
+        let boundary = boundary()?;
+        let body = multipart_body(&boundary, &fields, self.file_field(), filename, &data);
+
+        let response = self
+            .client
+            .post(self.host.endpoint())
+            .header(
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.name()))?;
+
+        let status = response.status();
+        // 0x0.st reports the actual expiry, which its size-based retention may have shortened
+        if let Some(expires_ms) = response
+            .headers()
+            .get("x-expires")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+        {
+            expires_at = Some(expires_ms / 1000);
+        }
+        let text = response.text().await?;
+        let url = text.trim();
+
+        if !status.is_success() {
+            bail!("{} upload failed ({}): {}", self.name(), status, url);
+        }
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(anyhow!("{} returned no link: {}", self.name(), url));
+        }
+
+        Ok(StoredMedia {
+            url: url.to_string(),
+            expires_at,
+        })
+    }
+}
+
+/// Shortest litterbox retention that covers `hours`, or the longest one.
+fn litterbox_hours(hours: u64) -> u64 {
+    LITTERBOX_HOURS
+        .into_iter()
+        .find(|&option| option >= hours)
+        .unwrap_or(LITTERBOX_HOURS[LITTERBOX_HOURS.len() - 1])
+}
+
+fn boundary() -> Result<String> {
+    let mut nonce = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("Failed to generate multipart boundary"))?;
+
+    Ok(format!("grabby-{}", hex(&nonce)))
+}
+
+/// Encodes text fields and a single file as `multipart/form-data`.
+fn multipart_body(
+    boundary: &str,
+    fields: &[(&str, String)],
+    file_field: &str,
+    filename: &str,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 512);
+
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+
+    let filename = filename.replace(['"', '\r', '\n'], "_");
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{file_field}\"; filename=\"{filename}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_litterbox_hours() {
+        assert_eq!(litterbox_hours(1), 1);
+        assert_eq!(litterbox_hours(2), 12);
+        assert_eq!(litterbox_hours(24), 24);
+        assert_eq!(litterbox_hours(1000), 72);
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let body = multipart_body(
+            "b",
+            &[("reqtype", "fileupload".to_string())],
+            "fileToUpload",
+            "clip\".mp4",
+            b"data",
+        );
+
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--b\r\nContent-Disposition: form-data; name=\"reqtype\"\r\n\r\nfileupload\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"fileToUpload\"; filename=\"clip_.mp4\"\r\nContent-Type: application/octet-stream\r\n\r\n\
+             data\r\n--b--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_form_fields_follow_ttl() {
+        let store = FileHostStore::new(FileHost::Litterbox).unwrap();
+        let (fields, expires_at) = store.form_fields(Duration::from_secs(5 * 3600));
+        assert_eq!(fields[1], ("time", "12h".to_string()));
+        assert!(expires_at.unwrap() >= unix_now() + 12 * 3600 - 1);
+
+        let store = FileHostStore::new(FileHost::ZeroX0).unwrap();
+        let (fields, _) = store.form_fields(Duration::from_secs(90));
+        assert_eq!(fields, vec![("expires", "1".to_string())]);
+
+        let store = FileHostStore::new(FileHost::Catbox).unwrap();
+        assert_eq!(store.form_fields(Duration::from_secs(60)).1, None);
+    }
+}
@@ -0,0 +1,144 @@
+mod fs;
+mod hosts;
+mod s3;
+
+pub use fs::FilesystemStore;
+pub use hosts::{FileHost, FileHostStore};
+pub use s3::S3Store;
+
+use crate::bot::expiry::unix_now;
+use crate::config::OffloadConfig;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where an offloaded file can be fetched from, and until when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredMedia {
+    pub url: String,
+    /// Unix timestamp after which the link stops working, `None` if it never does
+    pub expires_at: Option<u64>,
+}
+
+/// Backend holding media that is too large to upload to Discord.
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    /// Human-readable name of the backend
+    fn name(&self) -> &'static str;
+
+    /// Stores `data` and returns a link that stays valid for `ttl`
+    async fn upload(&self, filename: &str, data: Vec<u8>, ttl: Duration) -> Result<StoredMedia>;
+
+    /// Removes files whose link has expired, returning how many were removed.
+    /// Backends that expire files on their own keep the default.
+    async fn purge_expired(&self, _now: u64) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+/// Builds the backend selected in the `[offload]` config.
+pub fn open(config: &OffloadConfig) -> Result<Arc<dyn MediaStore>> {
+    let required = |value: &Option<String>, field: &str| {
+        value.clone().with_context(|| {
+            format!(
+                "offload.{field} is required for the {} backend",
+                config.backend
+            )
+        })
+    };
+
+    let store: Arc<dyn MediaStore> = match config.backend.as_str() {
+        "filesystem" => Arc::new(FilesystemStore::new(
+            required(&config.dir, "dir")?,
+            required(&config.base_url, "base_url")?,
+        )),
+        "s3" => Arc::new(S3Store::new(
+            required(&config.endpoint, "endpoint")?,
+            required(&config.bucket, "bucket")?,
+            config
+                .region
+                .clone()
+                .unwrap_or_else(|| "us-east-1".to_string()),
+            required(&config.access_key_id, "access_key_id")?,
+            required(&config.secret_access_key, "secret_access_key")?,
+            config.base_url.clone(),
+        )?),
+        other => match FileHost::from_backend(other) {
+            Some(host) => Arc::new(FileHostStore::new(host)?),
+            None => return Err(anyhow!("Unknown offload backend: {other}")),
+        },
+    };
+
+    Ok(store)
+}
+
+/// Object name for an upload: unguessable, and safe in URLs and file systems.
+fn object_name(filename: &str, expires_at: u64) -> Result<String> {
+    let mut nonce = [0u8; 8];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("Failed to generate object name"))?;
+
+    let safe_name: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    Ok(format!("{expires_at}-{}-{safe_name}", hex(&nonce)))
+}
+
+/// Expiry encoded in an object name by [`object_name`].
+fn expiry_of(object_name: &str) -> Option<u64> {
+    object_name.split_once('-')?.0.parse().ok()
+}
+
+fn expires_at(ttl: Duration) -> u64 {
+    unix_now() + ttl.as_secs()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_name_is_safe_and_carries_expiry() {
+        let name = object_name("my clip (1).mp4", 1700000000).unwrap();
+        assert!(name.starts_with("1700000000-"));
+        assert!(name.ends_with("-my_clip__1_.mp4"));
+        assert_eq!(expiry_of(&name), Some(1700000000));
+        assert_ne!(name, object_name("my clip (1).mp4", 1700000000).unwrap());
+    }
+
+    #[test]
+    fn test_open_requires_backend_settings() {
+        let config = OffloadConfig {
+            backend: "filesystem".to_string(),
+            ..Default::default()
+        };
+        assert!(open(&config).is_err());
+
+        let config = OffloadConfig {
+            backend: "ftp".to_string(),
+            ..Default::default()
+        };
+        assert!(open(&config).is_err());
+
+        let config = OffloadConfig {
+            backend: "litterbox".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(open(&config).unwrap().name(), "litterbox");
+    }
+}
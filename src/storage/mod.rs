@@ -0,0 +1,204 @@
+pub mod media;
+#[cfg(feature = "redis")]
+pub mod redis;
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+/// Where state that instances can share, such as embed history and quota usage, is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// JSON files in the data directory
+    #[default]
+    Local,
+    /// The Redis of the `[cluster]` section
+    Redis,
+}
+
+impl StorageBackend {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "local" => Some(Self::Local),
+            "redis" => Some(Self::Redis),
+            _ => None,
+        }
+    }
+}
+
+/// Directory holding the bot's persisted state, one JSON file per store.
+///
+/// Stores that support it keep their state in Redis instead when one is shared.
+#[derive(Clone)]
+pub struct Storage {
+    dir: PathBuf,
+    #[cfg(feature = "redis")]
+    shared: Option<redis::RedisStore>,
+}
+
+impl Storage {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            #[cfg(feature = "redis")]
+            shared: None,
+        }
+    }
+
+    /// Storage in the configured data directory, connected to Redis if it is the backend.
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        let storage = Self::new(config.get_data_dir());
+        if config.get_storage_backend() == StorageBackend::Local {
+            return Ok(storage);
+        }
+        let url = config
+            .get_redis_url()
+            .context("storage.backend = \"redis\" needs cluster.redis_url")?;
+
+        #[cfg(feature = "redis")]
+        {
+            let shared = redis::RedisStore::connect(url, config.get_cluster_key_prefix()).await?;
+            Ok(Self {
+                shared: Some(shared),
+                ..storage
+            })
+        }
+
+        #[cfg(not(feature = "redis"))]
+        {
+            let _ = (url, storage);
+            Err(anyhow::anyhow!(
+                "storage.backend = \"redis\" needs a build with the redis feature"
+            ))
+        }
+    }
+
+    /// Redis shared with other instances, if it is the backend.
+    #[cfg(feature = "redis")]
+    pub fn shared(&self) -> Option<&redis::RedisStore> {
+        self.shared.as_ref()
+    }
+
+    /// Opens the store `name`, starting empty if it was never written.
+    pub async fn open<T>(&self, name: &str) -> Result<JsonStore<T>>
+    where
+        T: Serialize + DeserializeOwned + Default,
+    {
+        JsonStore::open(self.dir.join(format!("{name}.json"))).await
+    }
+}
+
+/// A value kept in memory and written back to a JSON file on every update.
+pub struct JsonStore<T> {
+    path: PathBuf,
+    data: Mutex<T>,
+}
+
+impl<T> JsonStore<T>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    pub async fn open<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+
+        let data = match tokio::fs::read(&path).await {
+            Ok(content) => serde_json::from_slice(&content)
+                .with_context(|| format!("Failed to parse store: {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => T::default(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read store: {}", path.display()))
+            }
+        };
+
+        Ok(Self {
+            path,
+            data: Mutex::new(data),
+        })
+    }
+
+    pub async fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&*self.data.lock().await)
+    }
+
+    /// Applies `f` and persists the result before releasing the lock.
+    pub async fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        let mut data = self.data.lock().await;
+        let result = f(&mut data);
+        self.persist(&data).await?;
+        Ok(result)
+    }
+
+    async fn persist(&self, data: &T) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_vec_pretty(data)?;
+
+        // Write to a temporary file first, so a crash never leaves a truncated store
+        let tmp_path = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, content)
+            .await
+            .with_context(|| format!("Failed to write store: {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, &self.path)
+            .await
+            .with_context(|| format!("Failed to replace store: {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_missing_store_starts_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        let store = storage.open::<Vec<u64>>("missing").await.unwrap();
+        assert!(store.read(|data| data.is_empty()).await);
+    }
+
+    #[tokio::test]
+    async fn test_update_persists_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("nested"));
+
+        let store = storage.open::<Vec<u64>>("numbers").await.unwrap();
+        store.update(|data| data.extend([1, 2, 3])).await.unwrap();
+
+        let reopened = storage.open::<Vec<u64>>("numbers").await.unwrap();
+        assert_eq!(reopened.read(|data| data.clone()).await, vec![1, 2, 3]);
+        assert!(!dir.path().join("nested/numbers.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_redis_backend_needs_redis_url() {
+        let config: Config = toml::from_str(
+            r#"
+            servers = []
+
+            [storage]
+            backend = "redis"
+            "#,
+        )
+        .unwrap();
+        assert!(Storage::from_config(&config).await.is_err());
+        assert!(Storage::from_config(&Config::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_store_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.json"), "not json").unwrap();
+
+        let storage = Storage::new(dir.path());
+        assert!(storage.open::<Vec<u64>>("broken").await.is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+/// Connection to a Redis shared by instances, with the keys of each store under a common
+/// prefix.
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self> {
+        let client = redis::Client::open(url).context("Invalid cluster.redis_url")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(Self {
+            connection,
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// Full key of `name`, e.g. "grabby:quota_usage:2026-10:1".
+    pub fn key(&self, name: &str) -> String {
+        format!("{}:{}", self.prefix, name)
+    }
+
+    /// A handle to the shared connection, which reconnects on its own.
+    pub fn connection(&self) -> ConnectionManager {
+        self.connection.clone()
+    }
+
+    /// Keys starting with the full key of `name`, scanned in batches so Redis isn't blocked.
+    pub async fn keys_with_prefix(&self, name: &str) -> Result<Vec<String>> {
+        let mut connection = self.connection();
+        let mut keys = Vec::new();
+        let mut iter: redis::AsyncIter<String> = connection
+            .scan_match(format!("{}*", self.key(name)))
+            .await
+            .context("Failed to scan keys in Redis")?;
+        while let Some(key) = iter.next_item().await {
+            keys.push(key.context("Failed to scan keys in Redis")?);
+        }
+        Ok(keys)
+    }
+}
+
+/// Storage backed by the Redis at `REDIS_URL` (default: local), under a prefix of its own so
+/// tests don't see each other's keys.
+#[cfg(test)]
+pub async fn test_storage() -> super::Storage {
+    let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1".to_string());
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let prefix = format!("grabby-test-{nanos}");
+    let mut storage = super::Storage::new(std::env::temp_dir());
+    storage.shared = Some(RedisStore::connect(&url, &prefix).await.unwrap());
+    storage
+}
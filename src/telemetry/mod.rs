@@ -0,0 +1,73 @@
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider, Resource};
+use tracing::{warn, Subscriber};
+use tracing_opentelemetry::MetricsLayer;
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+/// Standard variable the OTLP exporters read their collector URL from.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Exports spans and `histogram.`/`counter.` event fields to an OTLP/HTTP collector.
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Sets up export to `endpoint`, or to the collector configured through the standard
+    /// `OTEL_EXPORTER_OTLP_*` variables. Returns `None` when neither is set.
+    pub fn new(endpoint: Option<&str>, service_name: &str) -> Result<Option<Self>> {
+        if endpoint.is_none() && std::env::var_os(OTLP_ENDPOINT_ENV).is_none() {
+            return Ok(None);
+        }
+
+        let mut span_exporter = SpanExporter::builder().with_http();
+        let mut metric_exporter = MetricExporter::builder().with_http();
+        if let Some(endpoint) = endpoint {
+            // Explicit endpoints are used as-is, so they need the signal paths
+            let endpoint = endpoint.trim_end_matches('/');
+            span_exporter = span_exporter.with_endpoint(format!("{endpoint}/v1/traces"));
+            metric_exporter = metric_exporter.with_endpoint(format!("{endpoint}/v1/metrics"));
+        }
+
+        let resource = Resource::builder()
+            .with_service_name(service_name.to_string())
+            .build();
+
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter.build()?)
+            .with_resource(resource.clone())
+            .build();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter.build()?)
+            .with_resource(resource)
+            .build();
+
+        Ok(Some(Self {
+            tracer_provider,
+            meter_provider,
+        }))
+    }
+
+    /// Layer forwarding spans as traces and metric fields of events as metrics.
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer()
+            .with_tracer(self.tracer_provider.tracer("grabby"))
+            .and_then(MetricsLayer::new(self.meter_provider.clone()))
+    }
+
+    /// Flushes pending spans and metrics.
+    pub fn shutdown(self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            warn!("Failed to flush traces: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            warn!("Failed to flush metrics: {}", e);
+        }
+    }
+}
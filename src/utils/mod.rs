@@ -1,16 +1,137 @@
-pub fn format_number(num: u64) -> String {
-    let num_str = num.to_string();
-    let chars: Vec<char> = num_str.chars().collect();
-    let mut result = String::new();
-
-    for (i, c) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i).is_multiple_of(3) {
-            result.push(',');
+use std::time::Duration;
+
+/// Formats seconds as "m:ss", or "h:mm:ss" from an hour on.
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// Shortens a count to at most one decimal and a suffix, e.g. "999", "1.2K" or "3.4M".
+pub fn abbreviate_count(num: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "K")];
+
+    for (size, suffix) in UNITS {
+        // Rounded to tenths first, so 999,950 becomes "1M" rather than "1000K"
+        let tenths = (num as u128 * 10 + size as u128 / 2) / size as u128;
+        if num >= size || (tenths >= 10 && size > 1_000) {
+            let tenths = tenths as u64;
+            return if tenths.is_multiple_of(10) || tenths >= 1000 {
+                format!("{}{suffix}", tenths / 10)
+            } else {
+                format!("{}.{}{suffix}", tenths / 10, tenths % 10)
+            };
+        }
+    }
+    num.to_string()
+}
+
+/// Formats a byte count in decimal units with one decimal, e.g. "512 B", "1.2 MB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000_000_000, "GB"), (1_000_000, "MB"), (1_000, "KB")];
+
+    UNITS.iter().find(|(size, _)| bytes >= *size).map_or_else(
+        || format!("{bytes} B"),
+        |(size, unit)| format!("{:.1} {unit}", bytes as f64 / *size as f64),
+    )
+}
+
+/// Formats a duration as days, hours and minutes, e.g. "2d 3h 5m".
+pub fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// How long ago Unix second `then` was at `now`, in its largest whole unit, e.g. "3 days ago".
+pub fn format_relative(then: u64, now: u64) -> String {
+    const UNITS: [(u64, &str); 6] = [
+        (365 * 86400, "year"),
+        (30 * 86400, "month"),
+        (7 * 86400, "week"),
+        (86400, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+    ];
+
+    let elapsed = now.saturating_sub(then);
+    UNITS.iter().find(|(size, _)| elapsed >= *size).map_or_else(
+        || "just now".to_string(),
+        |(size, unit)| {
+            let count = elapsed / size;
+            let plural = if count == 1 { "" } else { "s" };
+            format!("{count} {unit}{plural} ago")
+        },
+    )
+}
+
+/// Unix seconds of a date as sources report it: "20240102" (yt-dlp), "2024-01-02 03:04:05"
+/// (gallery-dl) or ISO 8601 like "2024-01-02T03:04:05.000Z". Dates without an offset are UTC.
+pub fn parse_date(date: &str) -> Option<u64> {
+    let date = date.trim();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = date.get(range)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+
+    if date.len() == 8 {
+        let days = days_from_civil(number(0..4)?, number(4..6)?, number(6..8)?)?;
+        return u64::try_from(days * 86400).ok();
+    }
+
+    if date.get(4..5)? != "-" || date.get(7..8)? != "-" {
+        return None;
+    }
+    let days = days_from_civil(number(0..4)?, number(5..7)?, number(8..10)?)?;
+    let mut secs = days * 86400;
+    let time = date.get(10..).unwrap_or_default();
+    if let Some(time) = time.strip_prefix(['T', ' ']) {
+        let field = |range: std::ops::Range<usize>| -> Option<i64> {
+            let digits = time.get(range)?;
+            digits.parse().ok()
+        };
+        secs += field(0..2)? * 3600 + field(3..5)? * 60 + field(6..8).unwrap_or(0);
+        // Fractions of a second are skipped up to the offset
+        let offset = time
+            .get(5..)?
+            .trim_start_matches(|c: char| c.is_ascii_digit() || c == ':' || c == '.');
+        if let Some(sign) = offset.chars().next().filter(|c| *c == '+' || *c == '-') {
+            let hours: i64 = offset.get(1..3)?.parse().ok()?;
+            let minutes: i64 = match offset.get(3..).map(|m| m.trim_start_matches(':')) {
+                Some(minutes) if !minutes.is_empty() => minutes.parse().ok()?,
+                _ => 0,
+            };
+            let offset = hours * 3600 + minutes * 60;
+            secs -= if sign == '+' { offset } else { -offset };
         }
-        result.push(*c);
     }
+    u64::try_from(secs).ok()
+}
 
-    result
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
 }
 
 #[cfg(test)]
@@ -18,13 +139,70 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_format_number() {
-        assert_eq!(format_number(0), "0");
-        assert_eq!(format_number(100), "100");
-        assert_eq!(format_number(1000), "1,000");
-        assert_eq!(format_number(10000), "10,000");
-        assert_eq!(format_number(100000), "100,000");
-        assert_eq!(format_number(1000000), "1,000,000");
-        assert_eq!(format_number(1234567890), "1,234,567,890");
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(65), "1:05");
+        assert_eq!(format_duration(3599), "59:59");
+        assert_eq!(format_duration(3665), "1:01:05");
+    }
+
+    #[test]
+    fn test_abbreviate_count() {
+        assert_eq!(abbreviate_count(0), "0");
+        assert_eq!(abbreviate_count(999), "999");
+        assert_eq!(abbreviate_count(1000), "1K");
+        assert_eq!(abbreviate_count(1234), "1.2K");
+        assert_eq!(abbreviate_count(15_000), "15K");
+        assert_eq!(abbreviate_count(999_950), "1M");
+        assert_eq!(abbreviate_count(3_400_000), "3.4M");
+        assert_eq!(abbreviate_count(123_456_789), "123M");
+        assert_eq!(abbreviate_count(1_500_000_000), "1.5B");
+        assert_eq!(abbreviate_count(u64::MAX), "18446744073B");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1_500), "1.5 KB");
+        assert_eq!(format_size(25_000_000), "25.0 MB");
+        assert_eq!(format_size(3_210_000_000), "3.2 GB");
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(59)), "0m");
+        assert_eq!(format_uptime(Duration::from_secs(3 * 3600 + 120)), "3h 2m");
+        assert_eq!(
+            format_uptime(Duration::from_secs(2 * 86400 + 3600 + 300)),
+            "2d 1h 5m"
+        );
+    }
+
+    #[test]
+    fn test_format_relative() {
+        let now = 1_000_000_000;
+        assert_eq!(format_relative(now - 30, now), "just now");
+        assert_eq!(format_relative(now + 30, now), "just now");
+        assert_eq!(format_relative(now - 60, now), "1 minute ago");
+        assert_eq!(format_relative(now - 2 * 3600, now), "2 hours ago");
+        assert_eq!(format_relative(now - 3 * 86400, now), "3 days ago");
+        assert_eq!(format_relative(now - 15 * 86400, now), "2 weeks ago");
+        assert_eq!(format_relative(now - 90 * 86400, now), "3 months ago");
+        assert_eq!(format_relative(now - 800 * 86400, now), "2 years ago");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("19700101"), Some(0));
+        assert_eq!(parse_date("20240229"), Some(1_709_164_800));
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800));
+        assert_eq!(parse_date("2024-02-29 01:02:03"), Some(1_709_168_523));
+        assert_eq!(parse_date("2024-02-29T01:02:03.000Z"), Some(1_709_168_523));
+        assert_eq!(parse_date("2024-02-29T03:02:03+02:00"), Some(1_709_168_523));
+        assert_eq!(parse_date("2024-02-28T23:02:03-0200"), Some(1_709_168_523));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("yesterday"), None);
+        assert_eq!(parse_date(""), None);
     }
 }